@@ -0,0 +1,72 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use std::collections::BTreeMap;
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{Expr, LitStr, Token};
+
+struct Keyword {
+    text: LitStr,
+    value: Expr,
+}
+
+impl Parse for Keyword {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let text = input.parse()?;
+        input.parse::<Token![=>]>()?;
+        let value = input.parse()?;
+        Ok(Keyword { text, value })
+    }
+}
+
+struct Keywords(Punctuated<Keyword, Token![,]>);
+
+impl Parse for Keywords {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        Punctuated::parse_terminated(input).map(Keywords)
+    }
+}
+
+pub fn expand(input: TokenStream) -> syn::Result<TokenStream> {
+    let Keywords(keywords) = syn::parse2(input)?;
+
+    let mut seen = Vec::new();
+    for keyword in &keywords {
+        let text = keyword.text.value();
+        if seen.contains(&text) {
+            return Err(syn::Error::new_spanned(
+                &keyword.text,
+                format!("duplicate keyword `{}`", text),
+            ));
+        }
+        seen.push(text);
+    }
+
+    let mut buckets: BTreeMap<usize, Vec<&Keyword>> = BTreeMap::new();
+    for keyword in &keywords {
+        buckets
+            .entry(keyword.text.value().len())
+            .or_default()
+            .push(keyword);
+    }
+    let arms = buckets.iter().map(|(len, keywords)| {
+        let texts = keywords.iter().map(|keyword| &keyword.text);
+        let values = keywords.iter().map(|keyword| &keyword.value);
+        quote! {
+            #len => match text {
+                #(#texts => ::core::option::Option::Some(#values),)*
+                _ => ::core::option::Option::None,
+            }
+        }
+    });
+    let words = keywords.iter().map(|keyword| &keyword.text);
+
+    Ok(quote! {
+        ::grammarsmith::keywords::Keywords::new(&[#(#words),*], |text: &str| {
+            match text.len() {
+                #(#arms,)*
+                _ => ::core::option::Option::None,
+            }
+        })
+    })
+}
@@ -0,0 +1,44 @@
+//! Derive macros for [grammarsmith](https://docs.rs/grammarsmith).
+//!
+//! Use them through grammarsmith's `derive` feature rather than depending on
+//! this crate directly.
+
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput};
+
+mod keywords;
+mod token;
+
+/// Derives `Token`, `KindMeta` and, optionally, `EndOfFile` for a token enum.
+///
+/// The derive generates a payload-free kind enum with one variant per token
+/// variant, named after the token enum with a `Kind` suffix unless set with
+/// `#[token(kind = Name)]`. The kind enum derives `Debug`, `Clone`, `Copy`,
+/// `PartialEq`, `Eq` and `Hash`, and implements `KindMeta`.
+///
+/// Kinds are described by their variant name in error messages, unless given
+/// a display name with `#[token(name = "...")]`, such as "`+`" or "an
+/// identifier".
+///
+/// Marking a unit variant with `#[token(eof)]` implements `EndOfFile` with
+/// that variant.
+///
+/// See the grammarsmith documentation for an example.
+#[proc_macro_derive(Token, attributes(token))]
+pub fn derive_token(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    token::derive(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Generates a `Keywords` table from `"text" => value` pairs, bucketing the
+/// keywords by length.
+///
+/// See the grammarsmith documentation for an example.
+#[proc_macro]
+pub fn keywords(input: TokenStream) -> TokenStream {
+    keywords::expand(input.into())
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
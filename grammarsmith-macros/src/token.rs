@@ -0,0 +1,170 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{Data, DeriveInput, Fields, Ident, LitStr, Variant};
+
+struct TokenVariant {
+    ident: Ident,
+    fields: Fields,
+    eof: bool,
+    name: Option<LitStr>,
+}
+
+pub fn derive(input: DeriveInput) -> syn::Result<TokenStream> {
+    let Data::Enum(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "`Token` can only be derived for enums",
+        ));
+    };
+    if data.variants.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "`Token` cannot be derived for empty enums",
+        ));
+    }
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "`Token` cannot be derived for generic enums",
+        ));
+    }
+
+    let mut kind = format_ident!("{}Kind", input.ident);
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("token"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("kind") {
+                kind = meta.value()?.parse()?;
+                Ok(())
+            } else {
+                Err(meta.error("expected `kind = Name`"))
+            }
+        })?;
+    }
+
+    let variants = data
+        .variants
+        .iter()
+        .map(token_variant)
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    let vis = &input.vis;
+    let name = &input.ident;
+    let idents: Vec<_> = variants.iter().map(|variant| &variant.ident).collect();
+    let names: Vec<_> = idents.iter().map(|ident| ident.to_string()).collect();
+    let display_names: Vec<_> = variants
+        .iter()
+        .zip(&names)
+        .map(|(variant, name)| match &variant.name {
+            Some(display_name) => display_name.value(),
+            None => name.clone(),
+        })
+        .collect();
+    let patterns = variants.iter().map(|variant| {
+        let ident = &variant.ident;
+        match variant.fields {
+            Fields::Named(_) => quote!(#name::#ident { .. }),
+            Fields::Unnamed(_) => quote!(#name::#ident(..)),
+            Fields::Unit => quote!(#name::#ident),
+        }
+    });
+
+    let mut eof = variants.iter().filter(|variant| variant.eof);
+    let eof_impl = match (eof.next(), eof.next()) {
+        (None, _) => quote!(),
+        (Some(_), Some(second)) => {
+            return Err(syn::Error::new_spanned(
+                &second.ident,
+                "only one variant can be marked `#[token(eof)]`",
+            ))
+        }
+        (Some(variant), None) => {
+            if !matches!(variant.fields, Fields::Unit) {
+                return Err(syn::Error::new_spanned(
+                    &variant.ident,
+                    "the `#[token(eof)]` variant must not have fields",
+                ));
+            }
+            let ident = &variant.ident;
+            quote! {
+                impl ::grammarsmith::parser::EndOfFile for #name {
+                    fn eof() -> Self {
+                        #name::#ident
+                    }
+
+                    fn eof_kind() -> #kind {
+                        #kind::#ident
+                    }
+                }
+            }
+        }
+    };
+
+    let kind_doc = format!("The kinds of [`{}`] tokens.", name);
+    Ok(quote! {
+        #[doc = #kind_doc]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        #vis enum #kind {
+            #(#idents,)*
+        }
+
+        impl ::grammarsmith::parser::Token for #name {
+            type Kind = #kind;
+
+            fn to_kind(&self) -> #kind {
+                match self {
+                    #(#patterns => #kind::#idents,)*
+                }
+            }
+        }
+
+        impl ::grammarsmith::parser::KindMeta for #kind {
+            const ALL: &'static [#kind] = &[#(#kind::#idents,)*];
+
+            fn display_name(&self) -> &'static str {
+                match self {
+                    #(#kind::#idents => #display_names,)*
+                }
+            }
+
+            fn name(&self) -> &'static str {
+                match self {
+                    #(#kind::#idents => #names,)*
+                }
+            }
+        }
+
+        #eof_impl
+    })
+}
+
+fn token_variant(variant: &Variant) -> syn::Result<TokenVariant> {
+    let mut eof = false;
+    let mut name = None;
+    for attr in variant
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("token"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("eof") {
+                eof = true;
+                Ok(())
+            } else if meta.path.is_ident("name") {
+                name = Some(meta.value()?.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `eof` or `name = \"...\"`"))
+            }
+        })?;
+    }
+    Ok(TokenVariant {
+        ident: variant.ident.clone(),
+        fields: variant.fields.clone(),
+        eof,
+        name,
+    })
+}
@@ -0,0 +1,181 @@
+//! Interoperability with `annotate-snippets`.
+//!
+//! Diagnostics can be converted into annotate-snippets reports with
+//! [`Diagnostic::to_annotate_snippets`], so the renderer extracted from
+//! rustc can be used instead of the built-in one.
+//!
+//! # Examples
+//! ```
+//! use annotate_snippets::Renderer;
+//! use grammarsmith::diagnostics::{Diagnostic, Severity};
+//! use grammarsmith::position::Span;
+//! use grammarsmith::source::SourceMap;
+//!
+//! let mut map = SourceMap::new();
+//! let file = map.add("main.lang", "let x = ;\n");
+//! let diagnostic = Diagnostic::builder(Severity::Error, "expected expression")
+//!     .primary(Span::new(8, 9).unwrap(), "found `;`")
+//!     .build();
+//!
+//! let report = diagnostic.to_annotate_snippets(file, &map);
+//! let rendered = Renderer::plain().render(&report);
+//! assert!(rendered.contains("main.lang:1:9"));
+//! ```
+
+use crate::diagnostics::{Diagnostic, Label, LabelStyle, Severity, Suggestion};
+use crate::source::{FileId, SourceMap};
+use ::annotate_snippets::{Annotation, AnnotationKind, Group, Level, Patch, Snippet};
+
+impl From<Severity> for Level<'static> {
+    fn from(severity: Severity) -> Self {
+        match severity {
+            Severity::Help => Level::HELP,
+            Severity::Note => Level::NOTE,
+            Severity::Warning => Level::WARNING,
+            Severity::Error => Level::ERROR,
+        }
+    }
+}
+
+impl From<LabelStyle> for AnnotationKind {
+    fn from(style: LabelStyle) -> Self {
+        match style {
+            LabelStyle::Primary => AnnotationKind::Primary,
+            LabelStyle::Secondary => AnnotationKind::Context,
+        }
+    }
+}
+
+impl Label {
+    /// Converts the label into an annotation on the snippet of its file.
+    pub fn to_annotate_snippets(&self) -> Annotation<'_> {
+        let annotation = AnnotationKind::from(self.style).span(self.span.start()..self.span.end());
+        if self.message.is_empty() {
+            annotation
+        } else {
+            annotation.label(self.message.as_str())
+        }
+    }
+}
+
+impl Suggestion {
+    /// Converts the suggestion into a help group showing its edits in
+    /// `file`, or `None` if `map` has no such file.
+    pub fn to_annotate_snippets<'a>(
+        &'a self,
+        file: FileId,
+        map: &'a SourceMap,
+    ) -> Option<Group<'a>> {
+        let source = map.get(file)?;
+        let snippet = Snippet::source(source.source())
+            .path(source.name())
+            .patches(self.edits.iter().map(|edit| {
+                Patch::new(edit.range.start()..edit.range.end(), edit.new_text.as_str())
+            }));
+        Some(
+            Level::HELP
+                .secondary_title(self.message.as_str())
+                .element(snippet),
+        )
+    }
+}
+
+impl Diagnostic {
+    /// Converts the diagnostic, reported for `file`, into an
+    /// annotate-snippets report to render with
+    /// [`Renderer::render`](::annotate_snippets::Renderer::render).
+    ///
+    /// The first group holds the message, code, labels and notes, with a
+    /// snippet per file the labels point into, in order of first use.
+    /// Each suggestion follows in a group of its own. Labels and
+    /// suggestions in files missing from `map` are left out.
+    pub fn to_annotate_snippets<'a>(&'a self, file: FileId, map: &'a SourceMap) -> Vec<Group<'a>> {
+        let mut title = Level::from(self.severity).primary_title(self.message.as_str());
+        if let Some(code) = &self.code {
+            title = title.id(code.as_str());
+        }
+
+        let mut files: Vec<FileId> = Vec::new();
+        for label in &self.labels {
+            let file = label.file.unwrap_or(file);
+            if !files.contains(&file) {
+                files.push(file);
+            }
+        }
+        let snippets = files.into_iter().filter_map(|id| {
+            let source = map.get(id)?;
+            let annotations = self
+                .labels
+                .iter()
+                .filter(|label| label.file.unwrap_or(file) == id)
+                .map(Label::to_annotate_snippets);
+            Some(
+                Snippet::source(source.source())
+                    .path(source.name())
+                    .annotations(annotations),
+            )
+        });
+        let notes = self
+            .notes
+            .iter()
+            .map(|note| Level::NOTE.message(note.as_str()));
+
+        let mut report = vec![Group::with_title(title).elements(snippets).elements(notes)];
+        report.extend(
+            self.suggestions
+                .iter()
+                .filter_map(|suggestion| suggestion.to_annotate_snippets(file, map)),
+        );
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::Applicability;
+    use crate::position::{Span, TextEdit};
+    use crate::source::FileSpan;
+    use ::annotate_snippets::Renderer;
+
+    #[test]
+    fn test_labels_across_files() {
+        let mut map = SourceMap::new();
+        let main = map.add("main.lang", "use lib;\nf();\n");
+        let lib = map.add("lib.lang", "fn g() {}\n");
+        let diagnostic = Diagnostic::error("unknown function `f`")
+            .with_code("E0425")
+            .with_label(Span::new(9, 10).unwrap(), "not found")
+            .with_secondary_label_in(
+                FileSpan::new(lib, Span::new(3, 4).unwrap()),
+                "similar function",
+            )
+            .with_note("functions must be declared");
+
+        let rendered = Renderer::plain().render(&diagnostic.to_annotate_snippets(main, &map));
+        assert!(rendered.starts_with("error[E0425]: unknown function `f`"));
+        assert!(rendered.contains("main.lang:2:1"));
+        assert!(rendered.contains("^ not found"));
+        assert!(rendered.contains("lib.lang:1:4"));
+        assert!(rendered.contains("- similar function"));
+        assert!(rendered.contains("= note: functions must be declared"));
+    }
+
+    #[test]
+    fn test_suggestions() {
+        let mut map = SourceMap::new();
+        let main = map.add("main.lang", "let x = 1\n");
+        let diagnostic = Diagnostic::error("missing `;`").with_suggestion(Suggestion::new(
+            "add a semicolon",
+            vec![TextEdit::insert(Span::point(9).start, ";")],
+            Applicability::MachineApplicable,
+        ));
+
+        let report = diagnostic.to_annotate_snippets(main, &map);
+        assert_eq!(report.len(), 2);
+        let rendered = Renderer::plain().render(&report);
+        assert!(rendered.contains("help: add a semicolon"));
+        assert!(rendered.contains("let x = 1;"));
+        assert_eq!(diagnostic.to_annotate_snippets(FileId(7), &map).len(), 1);
+    }
+}
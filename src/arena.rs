@@ -0,0 +1,203 @@
+//! Arena allocation of AST nodes, with `bumpalo`.
+//!
+//! Allocating every AST node in its own `Box` costs a heap allocation per
+//! node. An [`Arena`] instead hands out references into large chunks of
+//! memory, all freed at once when the arena is dropped. Nodes then refer to
+//! each other with `&'ast` references, where `'ast` is the lifetime of the
+//! arena, independent of the lifetime of the tokens a [`Parser`] borrows.
+//!
+//! Like `bumpalo`, the arena does not run destructors: nodes should hold
+//! their children as arena references and slices, and their text as arena
+//! strings or [`Symbol`]s, rather than `Vec`s and `String`s, which would
+//! leak.
+//!
+//! [`Parser`]: crate::parser::Parser
+//! [`Symbol`]: crate::intern::Symbol
+//!
+//! # Examples
+//! ```
+//! use grammarsmith::arena::Arena;
+//! use grammarsmith::parser::{EndOfFile, Parser, Token};
+//! use grammarsmith::position::{Span, WithSpan};
+//!
+//! #[derive(Debug, Clone, Copy, PartialEq)]
+//! enum Tok {
+//!     Num(u32),
+//!     Plus,
+//!     Eof,
+//! }
+//!
+//! #[derive(Debug, Clone, Copy, PartialEq)]
+//! enum Kind {
+//!     Num,
+//!     Plus,
+//!     Eof,
+//! }
+//!
+//! impl Token for Tok {
+//!     type Kind = Kind;
+//!
+//!     fn to_kind(&self) -> Kind {
+//!         match self {
+//!             Tok::Num(_) => Kind::Num,
+//!             Tok::Plus => Kind::Plus,
+//!             Tok::Eof => Kind::Eof,
+//!         }
+//!     }
+//! }
+//!
+//! impl EndOfFile for Tok {
+//!     fn eof() -> Tok {
+//!         Tok::Eof
+//!     }
+//!
+//!     fn eof_kind() -> Kind {
+//!         Kind::Eof
+//!     }
+//! }
+//!
+//! #[derive(Debug)]
+//! enum Expr<'ast> {
+//!     Num(u32),
+//!     Sum(&'ast [&'ast WithSpan<Expr<'ast>>]),
+//! }
+//!
+//! fn sum<'ast>(parser: &mut Parser<'_, Tok>, arena: &'ast Arena) -> &'ast WithSpan<Expr<'ast>> {
+//!     let mut terms = Vec::new();
+//!     loop {
+//!         let token = parser.advance();
+//!         let Tok::Num(n) = token.value else { break };
+//!         terms.push(&*arena.alloc_spanned(Expr::Num(n), token.span));
+//!         if !parser.is(Kind::Plus) {
+//!             break;
+//!         }
+//!     }
+//!     let span = terms[0].span.union(&terms[terms.len() - 1].span);
+//!     arena.alloc_spanned(Expr::Sum(arena.alloc_slice(terms)), span)
+//! }
+//!
+//! let tokens = [
+//!     WithSpan::new(Tok::Num(1), Span::new(0, 1).unwrap()),
+//!     WithSpan::new(Tok::Plus, Span::new(2, 3).unwrap()),
+//!     WithSpan::new(Tok::Num(2), Span::new(4, 5).unwrap()),
+//! ];
+//! let eof = WithSpan::new(Tok::Eof, Span::point(5));
+//! let arena = Arena::new();
+//! let tree = {
+//!     // The parser goes away; the tree lives as long as the arena.
+//!     let mut parser = Parser::new(&tokens, &eof);
+//!     sum(&mut parser, &arena)
+//! };
+//! assert_eq!(tree.span, Span::new(0, 5).unwrap());
+//! assert!(matches!(tree.value, Expr::Sum(terms) if terms.len() == 2));
+//! ```
+
+use crate::position::{GetSpan, WithSpan};
+use bumpalo::Bump;
+use std::fmt;
+
+/// A bump allocator for AST nodes.
+#[derive(Default)]
+pub struct Arena {
+    bump: Bump,
+}
+
+impl Arena {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an arena with room for `bytes` bytes before it needs to
+    /// allocate, such as a multiple of the source length.
+    pub fn with_capacity(bytes: usize) -> Self {
+        Arena {
+            bump: Bump::with_capacity(bytes),
+        }
+    }
+
+    /// Moves `value` into the arena.
+    pub fn alloc<T>(&self, value: T) -> &mut T {
+        self.bump.alloc(value)
+    }
+
+    /// Moves `value` into the arena together with its span.
+    pub fn alloc_spanned<T>(&self, value: T, span: impl GetSpan) -> &mut WithSpan<T> {
+        self.bump.alloc(WithSpan::new(value, span.get_span()))
+    }
+
+    /// Moves `values` into the arena as a slice, such as the children of a
+    /// node collected into a `Vec` while parsing.
+    pub fn alloc_slice<T, I>(&self, values: I) -> &mut [T]
+    where
+        I: IntoIterator<Item = T>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        self.bump.alloc_slice_fill_iter(values)
+    }
+
+    /// Copies `text` into the arena.
+    pub fn alloc_str(&self, text: &str) -> &mut str {
+        self.bump.alloc_str(text)
+    }
+
+    /// Returns the number of bytes allocated by the arena, including unused
+    /// room in its chunks.
+    pub fn allocated_bytes(&self) -> usize {
+        self.bump.allocated_bytes()
+    }
+
+    /// Frees every allocation, keeping the largest chunk of memory for
+    /// reuse, such as between the files of a project.
+    pub fn reset(&mut self) {
+        self.bump.reset();
+    }
+
+    /// Returns the underlying `bumpalo` allocator, for its collections.
+    pub fn bump(&self) -> &Bump {
+        &self.bump
+    }
+}
+
+impl From<Bump> for Arena {
+    fn from(bump: Bump) -> Self {
+        Arena { bump }
+    }
+}
+
+impl fmt::Debug for Arena {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Arena")
+            .field("allocated_bytes", &self.allocated_bytes())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::position::Span;
+
+    #[test]
+    fn test_alloc() {
+        let arena = Arena::with_capacity(1024);
+        let name = arena.alloc_str("x");
+        let node = arena.alloc_spanned(&*name, Span::new(0, 1).unwrap());
+        let list = arena.alloc_slice(vec![&*node, &*node]);
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[1].value, "x");
+        assert_eq!(list[1].span, Span::new(0, 1).unwrap());
+    }
+
+    #[test]
+    fn test_reset() {
+        let mut arena = Arena::new();
+        for i in 0..1000u64 {
+            arena.alloc(i);
+        }
+        let allocated = arena.allocated_bytes();
+        assert!(allocated >= 8000);
+        arena.reset();
+        assert!(arena.allocated_bytes() <= allocated);
+        assert_eq!(*arena.alloc(7u8), 7);
+    }
+}
@@ -0,0 +1,183 @@
+//! Interoperability with `ariadne`.
+//!
+//! [`FileSpan`] implements ariadne's [`Span`](::ariadne::Span) trait,
+//! [`SourceMapCache`] provides ariadne's [`Cache`] over a [`SourceMap`], and
+//! diagnostics can be turned into reports with [`Diagnostic::to_ariadne`].
+//!
+//! # Examples
+//! ```
+//! use grammarsmith::ariadne::SourceMapCache;
+//! use grammarsmith::diagnostics::{Diagnostic, Severity};
+//! use grammarsmith::position::Span;
+//! use grammarsmith::source::SourceMap;
+//!
+//! let mut map = SourceMap::new();
+//! let file = map.add("main.lang", "let x = ;\n");
+//! let diagnostic = Diagnostic::builder(Severity::Error, "expected expression")
+//!     .primary(Span::new(8, 9).unwrap(), "found `;`")
+//!     .build();
+//!
+//! let mut out = Vec::new();
+//! diagnostic
+//!     .to_ariadne(file)
+//!     .write(SourceMapCache::new(&map), &mut out)
+//!     .unwrap();
+//! assert!(String::from_utf8(out).unwrap().contains("main.lang:1:9"));
+//! ```
+
+use crate::diagnostics::{Diagnostic, Label, Severity};
+use crate::position::Span;
+use crate::source::{FileId, FileSpan, SourceMap};
+use ::ariadne::{Cache, Color, Config, IndexType, Report, ReportKind, Source};
+use std::collections::HashMap;
+use std::fmt;
+
+impl ::ariadne::Span for FileSpan {
+    type SourceId = FileId;
+
+    fn source(&self) -> &FileId {
+        &self.file
+    }
+
+    fn start(&self) -> usize {
+        self.span.start()
+    }
+
+    fn end(&self) -> usize {
+        self.span.end()
+    }
+}
+
+/// An ariadne [`Cache`] that reads files from a [`SourceMap`].
+///
+/// Ariadne indexes the lines of every source it renders, so the indexed
+/// sources are kept around for reuse across reports.
+#[derive(Debug)]
+pub struct SourceMapCache<'a> {
+    map: &'a SourceMap,
+    sources: HashMap<FileId, Source<&'a str>>,
+}
+
+impl<'a> SourceMapCache<'a> {
+    pub fn new(map: &'a SourceMap) -> Self {
+        SourceMapCache {
+            map,
+            sources: HashMap::new(),
+        }
+    }
+}
+
+impl<'a> Cache<FileId> for SourceMapCache<'a> {
+    type Storage = &'a str;
+
+    fn fetch(&mut self, id: &FileId) -> Result<&Source<&'a str>, impl fmt::Debug> {
+        let Some(file) = self.map.get(*id) else {
+            return Err(format!("file {} is not in the source map", id.0));
+        };
+        Ok(self
+            .sources
+            .entry(*id)
+            .or_insert_with(|| Source::from(file.source())))
+    }
+
+    fn display<'b>(&self, id: &'b FileId) -> Option<impl fmt::Display + 'b> {
+        self.map.get(*id).map(|file| file.name().to_string())
+    }
+}
+
+impl From<Severity> for ReportKind<'static> {
+    fn from(severity: Severity) -> Self {
+        match severity {
+            Severity::Help => ReportKind::Advice,
+            Severity::Note => ReportKind::Custom("Note", Color::Cyan),
+            Severity::Warning => ReportKind::Warning,
+            Severity::Error => ReportKind::Error,
+        }
+    }
+}
+
+impl Label {
+    /// Converts the label into an ariadne label, where `file` is the file the
+    /// diagnostic is reported for.
+    pub fn to_ariadne(&self, file: FileId) -> ::ariadne::Label<FileSpan> {
+        ::ariadne::Label::new(self.file_span(file)).with_message(&self.message)
+    }
+}
+
+impl Diagnostic {
+    /// Converts the diagnostic into an ariadne report for `file`, using
+    /// ariadne's default configuration.
+    pub fn to_ariadne(&self, file: FileId) -> Report<'static, FileSpan> {
+        self.to_ariadne_with_config(file, Config::default())
+    }
+
+    /// Converts the diagnostic into an ariadne report using `config`.
+    ///
+    /// The index type of `config` is always set to bytes, since that is how
+    /// spans are measured.
+    pub fn to_ariadne_with_config(
+        &self,
+        file: FileId,
+        config: Config,
+    ) -> Report<'static, FileSpan> {
+        let span = self
+            .primary_label()
+            .map_or(FileSpan::new(file, Span::empty()), |label| {
+                label.file_span(file)
+            });
+        let mut report = Report::build(self.severity.into(), span)
+            .with_config(config.with_index_type(IndexType::Byte))
+            .with_message(&self.message)
+            .with_labels(self.labels.iter().map(|label| label.to_ariadne(file)));
+        if let Some(code) = &self.code {
+            report = report.with_code(code);
+        }
+        for note in &self.notes {
+            report.add_note(note);
+        }
+        report.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(map: &SourceMap, report: &Report<'_, FileSpan>) -> String {
+        let mut out = Vec::new();
+        report.write(SourceMapCache::new(map), &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_to_ariadne() {
+        let mut map = SourceMap::new();
+        let file = map.add("main.lang", "let é = 1 +;\n");
+        let diagnostic = Diagnostic::builder(Severity::Warning, "dangling operator")
+            .code("W01")
+            .primary(Span::new(11, 12).unwrap(), "operator here")
+            .secondary(Span::new(4, 6).unwrap(), "in this binding")
+            .note("remove the `+`")
+            .build();
+
+        let report = diagnostic.to_ariadne_with_config(file, Config::default().with_color(false));
+        let rendered = render(&map, &report);
+        assert!(rendered.starts_with("[W01] Warning: dangling operator"));
+        // Columns are counted in characters, so the `é` counts once.
+        assert!(rendered.contains("main.lang:1:11"));
+        assert!(rendered.contains("operator here"));
+        assert!(rendered.contains("in this binding"));
+        assert!(rendered.contains("Note: remove the `+`"));
+    }
+
+    #[test]
+    fn test_cache() {
+        let mut map = SourceMap::new();
+        let file = map.add("a.lang", "ab\ncd");
+        let mut cache = SourceMapCache::new(&map);
+        assert_eq!(cache.fetch(&file).unwrap().lines().count(), 2);
+        assert!(cache.fetch(&FileId(1)).is_err());
+        assert_eq!(cache.display(&file).unwrap().to_string(), "a.lang");
+        assert!(cache.display(&FileId(1)).is_none());
+    }
+}
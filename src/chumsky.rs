@@ -0,0 +1,121 @@
+//! Interoperability with `chumsky` 0.9.
+//!
+//! [`Span`] implements chumsky's [`Span`](chumsky::Span) trait, and
+//! [`token_stream`] exposes the tokens of a grammarsmith lexer as a chumsky
+//! [`Stream`], so chumsky parsers can run on them and report errors with
+//! grammarsmith spans. This makes it possible to prototype parts of a grammar
+//! with chumsky next to a handwritten parser.
+//!
+//! # Examples
+//! ```
+//! use chumsky::prelude::*;
+//! use grammarsmith::chumsky::token_stream;
+//! use grammarsmith::position::{Span, WithSpan};
+//!
+//! #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+//! enum Token {
+//!     Number(u32),
+//!     Plus,
+//! }
+//!
+//! let tokens = vec![
+//!     WithSpan::new(Token::Number(1), Span::new(0, 1).unwrap()),
+//!     WithSpan::new(Token::Plus, Span::new(2, 3).unwrap()),
+//! ];
+//!
+//! let number = select! { Token::Number(n) => n };
+//! let sum = number
+//!     .then_ignore(just(Token::Plus))
+//!     .then(number)
+//!     .map(|(a, b)| a + b);
+//!
+//! let errors = sum.parse(token_stream(&tokens)).unwrap_err();
+//! let error: &Simple<Token, Span> = &errors[0];
+//! // The missing operand is reported at the end of the input.
+//! assert_eq!(error.span(), Span::new(3, 3).unwrap());
+//! ```
+
+use crate::position::{Span, WithSpan};
+use chumsky::Stream;
+use std::ops::Range;
+
+impl chumsky::Span for Span {
+    type Context = ();
+    type Offset = usize;
+
+    /// # Panics
+    /// Panics if the range is reversed or out of bounds of [`BytePos`](crate::position::BytePos).
+    fn new((): (), range: Range<usize>) -> Self {
+        Span::new(range.start, range.end).expect("invalid span range")
+    }
+
+    fn context(&self) {}
+
+    fn start(&self) -> usize {
+        Span::start(self)
+    }
+
+    fn end(&self) -> usize {
+        Span::end(self)
+    }
+}
+
+/// Returns a chumsky stream over `tokens`.
+///
+/// The end of input is reported as an empty span after the last token.
+pub fn token_stream<'a, T: Clone>(
+    tokens: &'a [WithSpan<T>],
+) -> Stream<'a, T, Span, impl Iterator<Item = (T, Span)> + 'a> {
+    let end = tokens.last().map_or(0, |token| token.span.end());
+    token_stream_with_eoi(tokens, Span::new(end, end).unwrap())
+}
+
+/// Returns a chumsky stream over `tokens`, reporting the end of input at `eoi`.
+pub fn token_stream_with_eoi<'a, T: Clone>(
+    tokens: &'a [WithSpan<T>],
+    eoi: Span,
+) -> Stream<'a, T, Span, impl Iterator<Item = (T, Span)> + 'a> {
+    Stream::from_iter(
+        eoi,
+        tokens.iter().map(|token| (token.value.clone(), token.span)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chumsky::prelude::*;
+
+    fn tokens() -> Vec<WithSpan<char>> {
+        vec![
+            WithSpan::new('a', Span::new(0, 1).unwrap()),
+            WithSpan::new('b', Span::new(4, 6).unwrap()),
+        ]
+    }
+
+    #[test]
+    fn test_spans() {
+        let parser = just::<_, _, Simple<char, Span>>('a')
+            .map_with_span(|_, span| span)
+            .then(just('b').map_with_span(|_, span| span));
+        let (a, b) = parser.parse(token_stream(&tokens())).unwrap();
+        assert_eq!(a, Span::new(0, 1).unwrap());
+        assert_eq!(b, Span::new(4, 6).unwrap());
+    }
+
+    #[test]
+    fn test_error_span() {
+        let parser = just::<_, _, Simple<char, Span>>('a').then(just('c'));
+        let errors = parser.parse(token_stream(&tokens())).unwrap_err();
+        assert_eq!(errors[0].span(), Span::new(4, 6).unwrap());
+
+        let eoi = Span::new(9, 9).unwrap();
+        let parser = just::<_, _, Simple<char, Span>>('a')
+            .then(just('b'))
+            .then(just('c'));
+        let errors = parser
+            .parse(token_stream_with_eoi(&tokens(), eoi))
+            .unwrap_err();
+        assert_eq!(errors[0].span(), eoi);
+    }
+}
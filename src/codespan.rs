@@ -0,0 +1,187 @@
+//! Interoperability with `codespan_reporting`.
+//!
+//! [`SourceMap`] implements codespan's [`Files`] trait, and diagnostics can be
+//! converted into codespan diagnostics with [`Diagnostic::to_codespan`], so
+//! codespan's terminal renderer can be used instead of the built-in one.
+//!
+//! # Examples
+//! ```
+//! use codespan_reporting::term::{self, Config};
+//! use grammarsmith::diagnostics::Diagnostic;
+//! use grammarsmith::diagnostics::Severity;
+//! use grammarsmith::position::Span;
+//! use grammarsmith::source::SourceMap;
+//!
+//! let mut map = SourceMap::new();
+//! let file = map.add("main.lang", "let x = ;\n");
+//! let diagnostic = Diagnostic::builder(Severity::Error, "expected expression")
+//!     .primary(Span::new(8, 9).unwrap(), "found `;`")
+//!     .build();
+//!
+//! let rendered =
+//!     term::emit_into_string(&Config::default(), &map, &diagnostic.to_codespan(file)).unwrap();
+//! assert!(rendered.contains("main.lang:1:9"));
+//! ```
+
+use crate::diagnostics::{Diagnostic, Label, LabelStyle, Severity};
+use crate::position::BytePos;
+use crate::source::{FileId, FileSpan, SourceFile, SourceMap};
+use codespan_reporting::diagnostic as codespan;
+use codespan_reporting::files::{Error, Files};
+use std::ops::Range;
+
+impl SourceMap {
+    fn codespan_file(&self, id: FileId) -> Result<&SourceFile, Error> {
+        self.get(id).ok_or(Error::FileMissing)
+    }
+}
+
+impl<'a> Files<'a> for SourceMap {
+    type FileId = FileId;
+    type Name = &'a str;
+    type Source = &'a str;
+
+    fn name(&'a self, id: FileId) -> Result<&'a str, Error> {
+        Ok(self.codespan_file(id)?.name())
+    }
+
+    fn source(&'a self, id: FileId) -> Result<&'a str, Error> {
+        Ok(self.codespan_file(id)?.source())
+    }
+
+    fn line_index(&'a self, id: FileId, byte_index: usize) -> Result<usize, Error> {
+        let file = self.codespan_file(id)?;
+        let max = file.source().len();
+        if byte_index > max {
+            return Err(Error::IndexTooLarge {
+                given: byte_index,
+                max,
+            });
+        }
+        Ok(file.line_offsets().line(BytePos::from_usize(byte_index)) - 1)
+    }
+
+    fn line_range(&'a self, id: FileId, line_index: usize) -> Result<Range<usize>, Error> {
+        let offsets = self.codespan_file(id)?.line_offsets();
+        offsets
+            .line_span(line_index + 1)
+            .map(|span| span.start()..span.end())
+            .ok_or(Error::LineTooLarge {
+                given: line_index,
+                max: offsets.line_count() - 1,
+            })
+    }
+}
+
+impl From<Severity> for codespan::Severity {
+    fn from(severity: Severity) -> Self {
+        match severity {
+            Severity::Help => codespan::Severity::Help,
+            Severity::Note => codespan::Severity::Note,
+            Severity::Warning => codespan::Severity::Warning,
+            Severity::Error => codespan::Severity::Error,
+        }
+    }
+}
+
+impl From<LabelStyle> for codespan::LabelStyle {
+    fn from(style: LabelStyle) -> Self {
+        match style {
+            LabelStyle::Primary => codespan::LabelStyle::Primary,
+            LabelStyle::Secondary => codespan::LabelStyle::Secondary,
+        }
+    }
+}
+
+impl From<FileSpan> for codespan::Label<FileId> {
+    /// Creates a primary label without a message.
+    fn from(span: FileSpan) -> Self {
+        codespan::Label::primary(span.file, span.span.start()..span.span.end())
+    }
+}
+
+impl Label {
+    /// Converts the label into a codespan label, where `file` is the file
+    /// the diagnostic is reported for.
+    pub fn to_codespan(&self, file: FileId) -> codespan::Label<FileId> {
+        let file = self.file.unwrap_or(file);
+        codespan::Label::new(self.style.into(), file, self.span.start()..self.span.end())
+            .with_message(&self.message)
+    }
+}
+
+impl Diagnostic {
+    /// Converts the diagnostic into a codespan diagnostic reported for
+    /// `file`.
+    pub fn to_codespan(&self, file: FileId) -> codespan::Diagnostic<FileId> {
+        let mut diagnostic =
+            codespan::Diagnostic::new(self.severity.into()).with_message(&self.message);
+        if let Some(code) = &self.code {
+            diagnostic = diagnostic.with_code(code);
+        }
+        diagnostic
+            .with_labels_iter(self.labels.iter().map(|label| label.to_codespan(file)))
+            .with_notes(self.notes.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::position::Span;
+    use codespan_reporting::term::{self, Config};
+
+    #[test]
+    fn test_files() {
+        let mut map = SourceMap::new();
+        let id = map.add("a.lang", "ab\ncd\n");
+        assert_eq!(map.name(id).unwrap(), "a.lang");
+        assert_eq!(map.line_index(id, 0).unwrap(), 0);
+        assert_eq!(map.line_index(id, 4).unwrap(), 1);
+        assert_eq!(map.line_index(id, 6).unwrap(), 2);
+        assert_eq!(map.line_range(id, 1).unwrap(), 3..6);
+        assert_eq!(map.line_range(id, 2).unwrap(), 6..6);
+        assert_eq!(map.location(id, 4).unwrap().column_number, 2);
+        assert!(matches!(
+            map.line_range(id, 3),
+            Err(Error::LineTooLarge { given: 3, max: 2 })
+        ));
+        assert!(matches!(
+            map.line_index(id, 7),
+            Err(Error::IndexTooLarge { given: 7, max: 6 })
+        ));
+        assert!(matches!(map.source(FileId(1)), Err(Error::FileMissing)));
+    }
+
+    #[test]
+    fn test_to_codespan() {
+        let mut map = SourceMap::new();
+        let file = map.add("main.lang", "let x = 1 +;\n");
+        let diagnostic = Diagnostic::builder(Severity::Warning, "dangling operator")
+            .code("W01")
+            .primary(Span::new(10, 11).unwrap(), "operator here")
+            .secondary(Span::new(4, 5).unwrap(), "in this binding")
+            .note("remove the `+`")
+            .build();
+
+        let converted = diagnostic.to_codespan(file);
+        assert_eq!(converted.severity, codespan::Severity::Warning);
+        assert_eq!(converted.code.as_deref(), Some("W01"));
+        assert_eq!(converted.labels.len(), 2);
+        assert_eq!(converted.labels[1].range, 4..5);
+        assert_eq!(converted.labels[1].style, codespan::LabelStyle::Secondary);
+
+        let rendered = term::emit_into_string(&Config::default(), &map, &converted).unwrap();
+        assert!(rendered.starts_with("warning[W01]: dangling operator"));
+        assert!(rendered.contains("main.lang:1:11"));
+        assert!(rendered.contains("= remove the `+`"));
+    }
+
+    #[test]
+    fn test_file_span_label() {
+        let span = FileSpan::new(FileId(3), Span::new(1, 4).unwrap());
+        let label = codespan::Label::from(span);
+        assert_eq!(label.file_id, FileId(3));
+        assert_eq!(label.range, 1..4);
+    }
+}
@@ -0,0 +1,63 @@
+//! Diagnostics.
+//!
+//! This module provides a structured [`Diagnostic`] type shared by error
+//! reporting, recovery and rendering.
+//!
+//! # Examples
+//! ```
+//! use grammarsmith::diagnostics::*;
+//! use grammarsmith::position::Span;
+//!
+//! let diagnostic = Diagnostic::builder(Severity::Error, "mismatched types")
+//!     .code("E0308")
+//!     .primary(Span::new(8, 13).unwrap(), "expected `i32`, found `&str`")
+//!     .secondary(Span::new(4, 7).unwrap(), "expected due to this")
+//!     .note("strings cannot be used as numbers")
+//!     .build();
+//!
+//! assert_eq!(diagnostic.primary_span(), Span::new(8, 13));
+//! ```
+
+pub mod code;
+pub mod color;
+pub mod config;
+pub mod dedup;
+pub mod diagnostic;
+pub mod expected;
+pub mod html;
+#[cfg(feature = "serde")]
+pub mod json;
+pub mod localize;
+#[cfg(feature = "serde")]
+pub mod sarif;
+pub mod short;
+pub mod similar;
+pub mod sink;
+pub mod snapshot;
+pub mod sort;
+pub mod speculative;
+pub mod suggestion;
+pub mod summary;
+pub mod terminal;
+
+pub use code::*;
+pub use color::*;
+pub use config::*;
+pub use dedup::*;
+pub use diagnostic::*;
+pub use expected::*;
+pub use html::*;
+#[cfg(feature = "serde")]
+pub use json::*;
+pub use localize::*;
+#[cfg(feature = "serde")]
+pub use sarif::*;
+pub use short::*;
+pub use similar::*;
+pub use sink::*;
+pub use snapshot::*;
+pub use sort::*;
+pub use speculative::*;
+pub use suggestion::*;
+pub use summary::*;
+pub use terminal::*;
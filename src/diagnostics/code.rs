@@ -0,0 +1,257 @@
+use crate::diagnostics::{Diagnostic, DiagnosticBuilder, Severity};
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::fmt;
+
+/// A short, stable identifier for a kind of diagnostic, such as `E0042`.
+///
+/// Codes stay the same when messages are reworded, so tools can filter on
+/// them and users can look up a longer explanation in a [`CodeRegistry`].
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
+pub struct Code(String);
+
+impl Code {
+    pub fn new(code: impl Into<String>) -> Self {
+        Code(code.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Code {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<&str> for Code {
+    fn from(code: &str) -> Self {
+        Code::new(code)
+    }
+}
+
+impl From<String> for Code {
+    fn from(code: String) -> Self {
+        Code(code)
+    }
+}
+
+impl AsRef<str> for Code {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for Code {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq<str> for Code {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<&str> for Code {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
+/// Everything known about a registered [`Code`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodeInfo {
+    pub code: Code,
+    /// A human readable identifier, such as `unexpected-token`, that can be
+    /// used instead of the code in configuration files.
+    pub name: String,
+    /// The severity used for diagnostics with this code unless configured
+    /// otherwise.
+    pub default_severity: Severity,
+    /// A longer explanation, as shown by `--explain`.
+    pub explanation: String,
+}
+
+impl CodeInfo {
+    pub fn new(
+        code: impl Into<Code>,
+        name: impl Into<String>,
+        default_severity: Severity,
+        explanation: impl Into<String>,
+    ) -> Self {
+        CodeInfo {
+            code: code.into(),
+            name: name.into(),
+            default_severity,
+            explanation: explanation.into(),
+        }
+    }
+}
+
+/// Error returned when registering a code or name that is already taken.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegistryError {
+    DuplicateCode(Code),
+    DuplicateName(String),
+}
+
+impl fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegistryError::DuplicateCode(code) => {
+                write!(f, "diagnostic code `{}` is already registered", code)
+            }
+            RegistryError::DuplicateName(name) => {
+                write!(f, "diagnostic name `{}` is already registered", name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RegistryError {}
+
+/// The set of diagnostic codes a tool can emit.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::diagnostics::*;
+///
+/// let mut registry = CodeRegistry::new();
+/// registry
+///     .register(CodeInfo::new(
+///         "E0042",
+///         "unexpected-token",
+///         Severity::Error,
+///         "The parser found a token it did not expect here.",
+///     ))
+///     .unwrap();
+///
+/// assert_eq!(registry.by_name("unexpected-token").unwrap().code, "E0042");
+/// let diagnostic = registry.diagnostic("E0042", "unexpected `;`").unwrap().build();
+/// assert_eq!(diagnostic.severity, Severity::Error);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CodeRegistry {
+    codes: Vec<CodeInfo>,
+    by_code: HashMap<Code, usize>,
+    by_name: HashMap<String, usize>,
+}
+
+impl CodeRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a code, failing if its code or name is already registered.
+    pub fn register(&mut self, info: CodeInfo) -> Result<(), RegistryError> {
+        if self.by_code.contains_key(&info.code) {
+            return Err(RegistryError::DuplicateCode(info.code));
+        }
+        if self.by_name.contains_key(&info.name) {
+            return Err(RegistryError::DuplicateName(info.name));
+        }
+        let index = self.codes.len();
+        self.by_code.insert(info.code.clone(), index);
+        self.by_name.insert(info.name.clone(), index);
+        self.codes.push(info);
+        Ok(())
+    }
+
+    /// Looks up a code.
+    pub fn get(&self, code: &str) -> Option<&CodeInfo> {
+        self.by_code.get(code).map(|&index| &self.codes[index])
+    }
+
+    /// Looks up a code by its human readable name.
+    pub fn by_name(&self, name: &str) -> Option<&CodeInfo> {
+        self.by_name.get(name).map(|&index| &self.codes[index])
+    }
+
+    /// Looks up a code by either its code or its name.
+    pub fn resolve(&self, code_or_name: &str) -> Option<&CodeInfo> {
+        self.get(code_or_name)
+            .or_else(|| self.by_name(code_or_name))
+    }
+
+    /// Returns the explanation of a code.
+    pub fn explain(&self, code: &str) -> Option<&str> {
+        self.get(code).map(|info| info.explanation.as_str())
+    }
+
+    /// Starts a diagnostic with a registered code and its default severity.
+    ///
+    /// Returns `None` if the code is not registered.
+    pub fn diagnostic(&self, code: &str, message: impl Into<String>) -> Option<DiagnosticBuilder> {
+        let info = self.get(code)?;
+        Some(Diagnostic::builder(info.default_severity, message).code(info.code.clone()))
+    }
+
+    /// Returns the number of registered codes.
+    pub fn len(&self) -> usize {
+        self.codes.len()
+    }
+
+    /// Returns true if no codes are registered.
+    pub fn is_empty(&self) -> bool {
+        self.codes.is_empty()
+    }
+
+    /// Iterates over the registered codes in registration order.
+    pub fn iter(&self) -> impl Iterator<Item = &CodeInfo> {
+        self.codes.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry() -> CodeRegistry {
+        let mut registry = CodeRegistry::new();
+        registry
+            .register(CodeInfo::new("E1", "bad-token", Severity::Error, "bad"))
+            .unwrap();
+        registry
+            .register(CodeInfo::new("W1", "unused", Severity::Warning, "unused"))
+            .unwrap();
+        registry
+    }
+
+    #[test]
+    fn test_lookup() {
+        let registry = registry();
+        assert_eq!(registry.len(), 2);
+        assert_eq!(registry.explain("W1"), Some("unused"));
+        assert_eq!(registry.resolve("bad-token").unwrap().code, "E1");
+        assert_eq!(registry.resolve("E1").unwrap().name, "bad-token");
+        assert_eq!(registry.get("E2"), None);
+        assert!(registry.diagnostic("E2", "missing").is_none());
+
+        let diagnostic = registry.diagnostic("W1", "unused `x`").unwrap().build();
+        assert_eq!(diagnostic.severity, Severity::Warning);
+        assert_eq!(diagnostic.code, Some(Code::new("W1")));
+    }
+
+    #[test]
+    fn test_duplicates() {
+        let mut registry = registry();
+        assert_eq!(
+            registry.register(CodeInfo::new("E1", "other", Severity::Error, "")),
+            Err(RegistryError::DuplicateCode(Code::new("E1")))
+        );
+        assert_eq!(
+            registry.register(CodeInfo::new("E2", "unused", Severity::Error, "")),
+            Err(RegistryError::DuplicateName("unused".to_string()))
+        );
+        assert_eq!(registry.len(), 2);
+    }
+}
@@ -0,0 +1,126 @@
+use super::Severity;
+use std::ffi::OsString;
+use std::io::IsTerminal;
+
+/// When to color rendered diagnostics.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub enum ColorChoice {
+    /// Always use colors.
+    Always,
+    /// Use colors when writing to a terminal, unless the `NO_COLOR`
+    /// environment variable is set or `TERM` is `dumb`.
+    #[default]
+    Auto,
+    /// Never use colors.
+    Never,
+}
+
+impl ColorChoice {
+    /// Decides whether output written to `stream` should be colored.
+    ///
+    /// # Examples
+    /// ```
+    /// use grammarsmith::diagnostics::ColorChoice;
+    ///
+    /// assert!(ColorChoice::Always.use_color(&std::io::stderr()));
+    /// assert!(!ColorChoice::Never.use_color(&std::io::stderr()));
+    /// ```
+    pub fn use_color(self, stream: &impl IsTerminal) -> bool {
+        self.resolve(
+            stream.is_terminal(),
+            std::env::var_os("NO_COLOR"),
+            std::env::var_os("TERM"),
+        )
+    }
+
+    fn resolve(
+        self,
+        is_terminal: bool,
+        no_color: Option<OsString>,
+        term: Option<OsString>,
+    ) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            // See https://no-color.org: only a non-empty value disables color.
+            ColorChoice::Auto => {
+                is_terminal
+                    && no_color.is_none_or(|value| value.is_empty())
+                    && term.is_none_or(|term| term != "dumb")
+            }
+        }
+    }
+}
+
+/// The ANSI escape sequences used to style each part of a rendered
+/// diagnostic.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::diagnostics::*;
+///
+/// let palette = Palette {
+///     error: "\x1b[1;35m",
+///     ..Palette::default()
+/// };
+/// let renderer = TerminalRenderer::new().with_color(true).with_palette(palette);
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Palette {
+    pub error: &'static str,
+    pub warning: &'static str,
+    pub note: &'static str,
+    pub help: &'static str,
+    /// Secondary labels.
+    pub secondary: &'static str,
+    /// Line numbers, separators and location arrows.
+    pub gutter: &'static str,
+    /// The message and the `note:` and `help:` headings.
+    pub emphasis: &'static str,
+    /// Ends each styled piece of text.
+    pub reset: &'static str,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette {
+            error: "\x1b[1;31m",
+            warning: "\x1b[1;33m",
+            note: "\x1b[1;32m",
+            help: "\x1b[1;36m",
+            secondary: "\x1b[1;34m",
+            gutter: "\x1b[1;34m",
+            emphasis: "\x1b[1m",
+            reset: "\x1b[0m",
+        }
+    }
+}
+
+impl Palette {
+    /// Returns the style of a severity, used for headers and primary labels.
+    pub fn severity(&self, severity: Severity) -> &'static str {
+        match severity {
+            Severity::Error => self.error,
+            Severity::Warning => self.warning,
+            Severity::Note => self.note,
+            Severity::Help => self.help,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_auto() {
+        let auto = ColorChoice::Auto;
+        assert!(auto.resolve(true, None, Some("xterm".into())));
+        assert!(auto.resolve(true, Some("".into()), None));
+        assert!(!auto.resolve(false, None, None));
+        assert!(!auto.resolve(true, Some("1".into()), None));
+        assert!(!auto.resolve(true, None, Some("dumb".into())));
+        assert!(ColorChoice::Always.resolve(false, Some("1".into()), None));
+        assert!(!ColorChoice::Never.resolve(true, None, None));
+    }
+}
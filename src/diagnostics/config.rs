@@ -0,0 +1,217 @@
+use super::{Code, CodeRegistry, Diagnostic, DiagnosticSink, Severity};
+use std::collections::HashMap;
+use std::fmt;
+
+/// What to do with diagnostics of a configured code, in the style of lint
+/// levels.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Level {
+    /// Drop the diagnostic.
+    Allow,
+    /// Report the diagnostic as a warning.
+    Warn,
+    /// Report the diagnostic as an error.
+    Deny,
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Level::Allow => "allow",
+            Level::Warn => "warn",
+            Level::Deny => "deny",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Error returned when configuring a code or name a registry does not know.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownCode(pub String);
+
+impl fmt::Display for UnknownCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown diagnostic code `{}`", self.0)
+    }
+}
+
+impl std::error::Error for UnknownCode {}
+
+/// Overrides the severity of diagnostics by code at runtime.
+///
+/// Levels can be set for a single code, or for a category: every code
+/// starting with a prefix. A level for a code wins over a category level,
+/// and a longer category wins over a shorter one. Diagnostics without a code,
+/// or with a code that is not configured, are left alone.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::diagnostics::*;
+///
+/// let mut config = SeverityConfig::new();
+/// config.set_category("W", Level::Deny);
+/// config.set("W0001", Level::Allow);
+///
+/// let mut sink = config.sink(Vec::new());
+/// sink.emit(Diagnostic::builder(Severity::Warning, "unused").code("W0001").build());
+/// sink.emit(Diagnostic::builder(Severity::Warning, "shadowed").code("W0002").build());
+///
+/// let diagnostics = sink.into_inner();
+/// assert_eq!(diagnostics.len(), 1);
+/// assert_eq!(diagnostics[0].severity, Severity::Error);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SeverityConfig {
+    codes: HashMap<Code, Level>,
+    categories: Vec<(String, Level)>,
+}
+
+impl SeverityConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the level of a single code.
+    pub fn set(&mut self, code: impl Into<Code>, level: Level) -> &mut Self {
+        self.codes.insert(code.into(), level);
+        self
+    }
+
+    /// Sets the level of every code starting with `prefix`.
+    pub fn set_category(&mut self, prefix: impl Into<String>, level: Level) -> &mut Self {
+        let prefix = prefix.into();
+        self.categories.retain(|(existing, _)| *existing != prefix);
+        self.categories.push((prefix, level));
+        self.categories
+            .sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.len()));
+        self
+    }
+
+    /// Sets the level of a code given by either its code or its name in
+    /// `registry`, as a user would write it in a command-line flag.
+    pub fn set_in(
+        &mut self,
+        registry: &CodeRegistry,
+        code_or_name: &str,
+        level: Level,
+    ) -> Result<&mut Self, UnknownCode> {
+        let info = registry
+            .resolve(code_or_name)
+            .ok_or_else(|| UnknownCode(code_or_name.to_string()))?;
+        Ok(self.set(info.code.clone(), level))
+    }
+
+    /// Returns the configured level for a code, if any.
+    pub fn level(&self, code: &Code) -> Option<Level> {
+        self.codes.get(code).copied().or_else(|| {
+            self.categories
+                .iter()
+                .find(|(prefix, _)| code.as_str().starts_with(prefix.as_str()))
+                .map(|&(_, level)| level)
+        })
+    }
+
+    /// Applies the configuration to a diagnostic, returning `None` if it is
+    /// allowed.
+    pub fn apply(&self, mut diagnostic: Diagnostic) -> Option<Diagnostic> {
+        let Some(level) = diagnostic.code.as_ref().and_then(|code| self.level(code)) else {
+            return Some(diagnostic);
+        };
+        diagnostic.severity = match level {
+            Level::Allow => return None,
+            Level::Warn => Severity::Warning,
+            Level::Deny => Severity::Error,
+        };
+        Some(diagnostic)
+    }
+
+    /// Wraps `inner` in a sink that applies this configuration to every
+    /// diagnostic before passing it on.
+    pub fn sink<D: DiagnosticSink>(&self, inner: D) -> ConfiguredSink<'_, D> {
+        ConfiguredSink {
+            config: self,
+            inner,
+        }
+    }
+}
+
+/// A sink applying a [`SeverityConfig`], created by
+/// [`SeverityConfig::sink`].
+#[derive(Debug)]
+pub struct ConfiguredSink<'c, D> {
+    config: &'c SeverityConfig,
+    inner: D,
+}
+
+impl<D> ConfiguredSink<'_, D> {
+    /// Returns the wrapped sink.
+    pub fn into_inner(self) -> D {
+        self.inner
+    }
+
+    pub fn inner(&self) -> &D {
+        &self.inner
+    }
+}
+
+impl<D: DiagnosticSink> DiagnosticSink for ConfiguredSink<'_, D> {
+    fn emit(&mut self, diagnostic: Diagnostic) {
+        if let Some(diagnostic) = self.config.apply(diagnostic) {
+            self.inner.emit(diagnostic);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::CodeInfo;
+
+    fn coded(code: &str) -> Diagnostic {
+        Diagnostic::builder(Severity::Warning, "message")
+            .code(code)
+            .build()
+    }
+
+    #[test]
+    fn test_precedence() {
+        let mut config = SeverityConfig::new();
+        config
+            .set_category("P", Level::Allow)
+            .set_category("P1", Level::Deny)
+            .set("P10", Level::Warn);
+
+        assert_eq!(config.level(&Code::new("P10")), Some(Level::Warn));
+        assert_eq!(config.level(&Code::new("P11")), Some(Level::Deny));
+        assert_eq!(config.level(&Code::new("P2")), Some(Level::Allow));
+        assert_eq!(config.level(&Code::new("Q1")), None);
+
+        assert_eq!(config.apply(coded("P2")), None);
+        assert_eq!(
+            config.apply(coded("P11")).unwrap().severity,
+            Severity::Error
+        );
+        assert_eq!(
+            config.apply(coded("Q1")).unwrap().severity,
+            Severity::Warning
+        );
+        let uncoded = Diagnostic::new(Severity::Note, "no code");
+        assert_eq!(config.apply(uncoded.clone()), Some(uncoded));
+    }
+
+    #[test]
+    fn test_set_in_registry() {
+        let mut registry = CodeRegistry::new();
+        registry
+            .register(CodeInfo::new("W1", "unused", Severity::Warning, ""))
+            .unwrap();
+
+        let mut config = SeverityConfig::new();
+        config.set_in(&registry, "unused", Level::Deny).unwrap();
+        assert_eq!(config.level(&Code::new("W1")), Some(Level::Deny));
+        assert_eq!(
+            config.set_in(&registry, "missing", Level::Allow),
+            Err(UnknownCode("missing".to_string()))
+        );
+    }
+}
@@ -0,0 +1,176 @@
+use super::{Code, Diagnostic, DiagnosticSink, Severity};
+use crate::position::{LineOffsets, Span};
+use std::collections::{HashMap, HashSet};
+
+/// A sink that filters out cascading and repeated diagnostics before passing
+/// the rest on.
+///
+/// Parsers that recover from errors tend to report the same problem several
+/// times: the lexer and the parser both complain about a bad token, or every
+/// rule on the way out of a broken construct reports something inside it.
+/// A `DedupSink` drops:
+///
+/// - diagnostics with the same code and primary span as an earlier one,
+/// - errors whose primary span lies inside the primary span of an earlier
+///   error,
+/// - and, if [`with_line_limit`](DedupSink::with_line_limit) is used, errors
+///   on a line that already has the maximum number of errors.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::diagnostics::*;
+/// use grammarsmith::position::Span;
+///
+/// let error = |start, end, message| {
+///     Diagnostic::builder(Severity::Error, message)
+///         .primary(Span::new(start, end).unwrap(), "")
+///         .build()
+/// };
+///
+/// let mut sink = DedupSink::new(Vec::new());
+/// sink.emit(error(0, 10, "invalid block"));
+/// sink.emit(error(2, 3, "unexpected token"));
+/// sink.emit(error(12, 13, "missing `;`"));
+/// assert_eq!(sink.into_inner().len(), 2);
+/// ```
+#[derive(Debug)]
+pub struct DedupSink<'a, D> {
+    inner: D,
+    seen: HashSet<(Option<Code>, Option<Span>)>,
+    error_spans: Vec<Span>,
+    line_limit: Option<(usize, &'a LineOffsets)>,
+    errors_per_line: HashMap<usize, usize>,
+    dropped: usize,
+}
+
+impl<'a, D: DiagnosticSink> DedupSink<'a, D> {
+    pub fn new(inner: D) -> Self {
+        DedupSink {
+            inner,
+            seen: HashSet::new(),
+            error_spans: Vec::new(),
+            line_limit: None,
+            errors_per_line: HashMap::new(),
+            dropped: 0,
+        }
+    }
+
+    /// Passes on at most `limit` errors per line, using `offsets` to find the
+    /// line of each error's primary span.
+    pub fn with_line_limit(mut self, limit: usize, offsets: &'a LineOffsets) -> Self {
+        self.line_limit = Some((limit, offsets));
+        self
+    }
+
+    /// Returns the number of diagnostics that were dropped.
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    /// Returns the wrapped sink.
+    pub fn into_inner(self) -> D {
+        self.inner
+    }
+
+    fn keep(&mut self, diagnostic: &Diagnostic) -> bool {
+        let primary = diagnostic.primary_span();
+        if !self.seen.insert((diagnostic.code.clone(), primary)) {
+            return false;
+        }
+        if diagnostic.severity != Severity::Error {
+            return true;
+        }
+        let Some(span) = primary else {
+            return true;
+        };
+
+        let cascading = self
+            .error_spans
+            .iter()
+            .any(|earlier| earlier.start <= span.start && span.end <= earlier.end);
+        if cascading {
+            return false;
+        }
+        if let Some((limit, offsets)) = self.line_limit {
+            let count = self
+                .errors_per_line
+                .entry(offsets.line(span.start))
+                .or_default();
+            if *count >= limit {
+                return false;
+            }
+            *count += 1;
+        }
+        self.error_spans.push(span);
+        true
+    }
+}
+
+impl<D: DiagnosticSink> DiagnosticSink for DedupSink<'_, D> {
+    fn emit(&mut self, diagnostic: Diagnostic) {
+        if self.keep(&diagnostic) {
+            self.inner.emit(diagnostic);
+        } else {
+            self.dropped += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diagnostic(severity: Severity, code: &str, start: usize, end: usize) -> Diagnostic {
+        Diagnostic::builder(severity, "message")
+            .code(code)
+            .primary(Span::new(start, end).unwrap(), "")
+            .build()
+    }
+
+    #[test]
+    fn test_duplicates() {
+        let mut sink = DedupSink::new(Vec::new());
+        sink.emit(diagnostic(Severity::Warning, "W1", 0, 1));
+        sink.emit(diagnostic(Severity::Warning, "W1", 0, 1));
+        sink.emit(diagnostic(Severity::Warning, "W2", 0, 1));
+        sink.emit(Diagnostic::new(Severity::Note, "no span"));
+        sink.emit(Diagnostic::new(Severity::Note, "no span either"));
+        assert_eq!(sink.dropped(), 2);
+        assert_eq!(sink.into_inner().len(), 3);
+    }
+
+    #[test]
+    fn test_cascading_errors() {
+        let mut sink = DedupSink::new(Vec::new());
+        sink.emit(diagnostic(Severity::Error, "E1", 4, 8));
+        sink.emit(diagnostic(Severity::Error, "E2", 4, 8));
+        sink.emit(diagnostic(Severity::Error, "E2", 5, 6));
+        // Warnings inside an error and errors overlapping it are kept.
+        sink.emit(diagnostic(Severity::Warning, "W1", 5, 6));
+        sink.emit(diagnostic(Severity::Error, "E3", 6, 10));
+
+        let codes: Vec<_> = sink
+            .into_inner()
+            .into_iter()
+            .map(|d| d.code.unwrap().to_string())
+            .collect();
+        assert_eq!(codes, ["E1", "W1", "E3"]);
+    }
+
+    #[test]
+    fn test_line_limit() {
+        let offsets = LineOffsets::new("a b c\nd e");
+        let mut sink = DedupSink::new(Vec::new()).with_line_limit(2, &offsets);
+        for (code, start) in [("E1", 0), ("E2", 2), ("E3", 4), ("E4", 6), ("E5", 8)] {
+            sink.emit(diagnostic(Severity::Error, code, start, start + 1));
+        }
+        sink.emit(diagnostic(Severity::Warning, "W1", 4, 5));
+
+        let codes: Vec<_> = sink
+            .into_inner()
+            .into_iter()
+            .map(|d| d.code.unwrap().to_string())
+            .collect();
+        assert_eq!(codes, ["E1", "E2", "E4", "E5", "W1"]);
+    }
+}
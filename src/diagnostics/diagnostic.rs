@@ -0,0 +1,505 @@
+use crate::diagnostics::{Code, DiagnosticSink, Suggestion, Template};
+use crate::position::{GetSpan, Span};
+use crate::source::{FileId, FileSpan};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// How severe a diagnostic is.
+///
+/// Severities are ordered from least to most severe.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Severity {
+    Help,
+    Note,
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Severity::Help => "help",
+            Severity::Note => "note",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Whether a label marks the main location of a diagnostic or supporting
+/// context.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum LabelStyle {
+    Primary,
+    Secondary,
+}
+
+/// Extra information about the code a diagnostic points at, which editors
+/// use to render it differently.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum DiagnosticTag {
+    /// The code is unused or unreachable, and is usually faded out.
+    Unnecessary,
+    /// The code is deprecated, and is usually struck through.
+    Deprecated,
+}
+
+/// A message attached to a span of the source.
+///
+/// A label points into the file the diagnostic is reported for, unless
+/// `file` says otherwise. Labels in other files can point at related code,
+/// such as a conflicting declaration in an imported module.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Label {
+    pub style: LabelStyle,
+    pub span: Span,
+    pub message: String,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub file: Option<FileId>,
+}
+
+impl Label {
+    pub fn new(style: LabelStyle, span: Span, message: impl Into<String>) -> Self {
+        Label {
+            style,
+            span,
+            message: message.into(),
+            file: None,
+        }
+    }
+
+    /// Creates a label pointing into a specific file.
+    pub fn in_file(style: LabelStyle, span: FileSpan, message: impl Into<String>) -> Self {
+        Label {
+            file: Some(span.file),
+            ..Label::new(style, span.span, message)
+        }
+    }
+
+    /// Returns the file and span of the label, where `file` is the file the
+    /// diagnostic is reported for.
+    pub fn file_span(&self, file: FileId) -> FileSpan {
+        FileSpan::new(self.file.unwrap_or(file), self.span)
+    }
+
+    pub fn primary(span: Span, message: impl Into<String>) -> Self {
+        Label::new(LabelStyle::Primary, span, message)
+    }
+
+    pub fn secondary(span: Span, message: impl Into<String>) -> Self {
+        Label::new(LabelStyle::Secondary, span, message)
+    }
+}
+
+impl GetSpan for Label {
+    fn get_span(&self) -> Span {
+        self.span
+    }
+}
+
+/// A structured message about the source, such as an error or a warning.
+///
+/// Diagnostics can be assembled with a [`DiagnosticBuilder`], or in one
+/// expression with the `with_*` methods:
+///
+/// ```
+/// use grammarsmith::diagnostics::*;
+/// use grammarsmith::position::{Span, WithSpan};
+///
+/// let token = WithSpan::new("}", Span::new(10, 11).unwrap());
+/// let mut sink = Vec::new();
+/// Diagnostic::error("unexpected `}`")
+///     .with_code("E001")
+///     .with_label(&token, "here")
+///     .with_note("blocks must be balanced")
+///     .emit(&mut sink);
+///
+/// assert_eq!(sink[0].primary_span(), Span::new(10, 11));
+/// ```
+///
+/// # Serialization
+/// With the `serde` feature, a diagnostic is serialized field by field,
+/// with byte offset spans, and with its optional and empty fields left out:
+///
+/// ```json
+/// {
+///   "severity": "error",
+///   "message": "unexpected `}`",
+///   "code": "E001",
+///   "labels": [
+///     { "style": "primary", "span": { "start": 10, "end": 11 }, "message": "here", "file": 0 }
+///   ],
+///   "notes": ["blocks must be balanced"],
+///   "suggestions": [
+///     {
+///       "message": "remove it",
+///       "edits": [{ "range": { "start": 10, "end": 11 }, "new_text": "" }],
+///       "applicability": "maybe-incorrect"
+///     }
+///   ],
+///   "tags": ["unnecessary"],
+///   "template": { "key": "unexpected", "args": [["token", "}"]] }
+/// }
+/// ```
+///
+/// This is the lossless form, for caching and passing diagnostics between
+/// processes; [`json`](crate::diagnostics::json) has a format with lines and
+/// columns for tools that do not have the source.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub code: Option<Code>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub labels: Vec<Label>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub notes: Vec<String>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub suggestions: Vec<Suggestion>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub tags: Vec<DiagnosticTag>,
+    /// The key and arguments to translate the message with.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub template: Option<Template>,
+}
+
+impl Diagnostic {
+    /// Creates a diagnostic without labels, notes, suggestions or code.
+    pub fn new(severity: Severity, message: impl Into<String>) -> Self {
+        Diagnostic {
+            severity,
+            message: message.into(),
+            code: None,
+            labels: Vec::new(),
+            notes: Vec::new(),
+            suggestions: Vec::new(),
+            tags: Vec::new(),
+            template: None,
+        }
+    }
+
+    /// Creates an error without labels.
+    pub fn error(message: impl Into<String>) -> Self {
+        Diagnostic::new(Severity::Error, message)
+    }
+
+    /// Creates a warning without labels.
+    pub fn warning(message: impl Into<String>) -> Self {
+        Diagnostic::new(Severity::Warning, message)
+    }
+
+    /// Creates a note without labels.
+    pub fn note(message: impl Into<String>) -> Self {
+        Diagnostic::new(Severity::Note, message)
+    }
+
+    /// Creates a help message without labels.
+    pub fn help(message: impl Into<String>) -> Self {
+        Diagnostic::new(Severity::Help, message)
+    }
+
+    /// Starts building a diagnostic.
+    pub fn builder(severity: Severity, message: impl Into<String>) -> DiagnosticBuilder {
+        DiagnosticBuilder {
+            diagnostic: Diagnostic::new(severity, message),
+        }
+    }
+
+    /// Returns the first primary label.
+    pub fn primary_label(&self) -> Option<&Label> {
+        self.labels
+            .iter()
+            .find(|label| label.style == LabelStyle::Primary)
+    }
+
+    /// Returns the span of the first primary label.
+    pub fn primary_span(&self) -> Option<Span> {
+        self.primary_label().map(|label| label.span)
+    }
+
+    /// Iterates over the secondary labels.
+    pub fn secondary_labels(&self) -> impl Iterator<Item = &Label> {
+        self.labels
+            .iter()
+            .filter(|label| label.style == LabelStyle::Secondary)
+    }
+
+    /// Returns true if this is an error.
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    /// Sets the error code.
+    pub fn with_code(mut self, code: impl Into<Code>) -> Self {
+        self.code = Some(code.into());
+        self
+    }
+
+    /// Adds a primary label at the span of `span`, which may be a [`Span`]
+    /// or anything spanned such as a token.
+    ///
+    /// The label refers to the file the diagnostic is reported for; use
+    /// [`Diagnostic::with_label_in`] for other files.
+    pub fn with_label(mut self, span: impl GetSpan, message: impl Into<String>) -> Self {
+        self.labels.push(Label::primary(span.get_span(), message));
+        self
+    }
+
+    /// Adds a secondary label at the span of `span`.
+    pub fn with_secondary_label(mut self, span: impl GetSpan, message: impl Into<String>) -> Self {
+        self.labels.push(Label::secondary(span.get_span(), message));
+        self
+    }
+
+    /// Adds a primary label in a specific file.
+    pub fn with_label_in(mut self, span: FileSpan, message: impl Into<String>) -> Self {
+        self.labels
+            .push(Label::in_file(LabelStyle::Primary, span, message));
+        self
+    }
+
+    /// Adds a secondary label in a specific file.
+    pub fn with_secondary_label_in(mut self, span: FileSpan, message: impl Into<String>) -> Self {
+        self.labels
+            .push(Label::in_file(LabelStyle::Secondary, span, message));
+        self
+    }
+
+    /// Adds a note shown after the labels.
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    /// Adds a suggested fix.
+    pub fn with_suggestion(mut self, suggestion: Suggestion) -> Self {
+        self.suggestions.push(suggestion);
+        self
+    }
+
+    /// Adds a tag.
+    pub fn with_tag(mut self, tag: DiagnosticTag) -> Self {
+        self.tags.push(tag);
+        self
+    }
+
+    /// Sets the template to translate the message with.
+    pub fn with_template(mut self, template: Template) -> Self {
+        self.template = Some(template);
+        self
+    }
+
+    /// Reports the diagnostic to `sink`.
+    pub fn emit<D: DiagnosticSink + ?Sized>(self, sink: &mut D) {
+        sink.emit(self);
+    }
+}
+
+/// Builds a [`Diagnostic`] step by step.
+#[derive(Debug, Clone)]
+pub struct DiagnosticBuilder {
+    diagnostic: Diagnostic,
+}
+
+impl DiagnosticBuilder {
+    /// Sets the error code.
+    pub fn code(mut self, code: impl Into<Code>) -> Self {
+        self.diagnostic.code = Some(code.into());
+        self
+    }
+
+    /// Adds a primary label at the span of `span`.
+    pub fn primary(mut self, span: impl GetSpan, message: impl Into<String>) -> Self {
+        self.diagnostic
+            .labels
+            .push(Label::primary(span.get_span(), message));
+        self
+    }
+
+    /// Adds a secondary label at the span of `span`.
+    pub fn secondary(mut self, span: impl GetSpan, message: impl Into<String>) -> Self {
+        self.diagnostic
+            .labels
+            .push(Label::secondary(span.get_span(), message));
+        self
+    }
+
+    /// Adds a primary label in a specific file.
+    pub fn primary_in(mut self, span: FileSpan, message: impl Into<String>) -> Self {
+        self.diagnostic
+            .labels
+            .push(Label::in_file(LabelStyle::Primary, span, message));
+        self
+    }
+
+    /// Adds a secondary label in a specific file.
+    pub fn secondary_in(mut self, span: FileSpan, message: impl Into<String>) -> Self {
+        self.diagnostic
+            .labels
+            .push(Label::in_file(LabelStyle::Secondary, span, message));
+        self
+    }
+
+    /// Adds a note shown after the labels.
+    pub fn note(mut self, note: impl Into<String>) -> Self {
+        self.diagnostic.notes.push(note.into());
+        self
+    }
+
+    /// Adds a suggested fix.
+    pub fn suggestion(mut self, suggestion: Suggestion) -> Self {
+        self.diagnostic.suggestions.push(suggestion);
+        self
+    }
+
+    /// Adds a tag.
+    pub fn tag(mut self, tag: DiagnosticTag) -> Self {
+        self.diagnostic.tags.push(tag);
+        self
+    }
+
+    /// Sets the template to translate the message with.
+    pub fn template(mut self, template: Template) -> Self {
+        self.diagnostic.template = Some(template);
+        self
+    }
+
+    /// Finishes the diagnostic.
+    pub fn build(self) -> Diagnostic {
+        self.diagnostic
+    }
+
+    /// Finishes the diagnostic and reports it to `sink`.
+    pub fn emit<D: DiagnosticSink + ?Sized>(self, sink: &mut D) {
+        sink.emit(self.diagnostic);
+    }
+}
+
+impl From<DiagnosticBuilder> for Diagnostic {
+    fn from(builder: DiagnosticBuilder) -> Self {
+        builder.build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::position::WithSpan;
+
+    #[test]
+    fn test_builder() {
+        let diagnostic = Diagnostic::builder(Severity::Warning, "unused variable")
+            .secondary(Span::new(0, 3).unwrap(), "declared here")
+            .primary(Span::new(4, 5).unwrap(), "never read")
+            .note("prefix it with an underscore to silence this")
+            .build();
+
+        assert_eq!(diagnostic.severity, Severity::Warning);
+        assert_eq!(diagnostic.code, None);
+        assert_eq!(diagnostic.primary_label().unwrap().message, "never read");
+        assert_eq!(diagnostic.secondary_labels().count(), 1);
+        assert_eq!(diagnostic.notes.len(), 1);
+        assert!(!diagnostic.is_error());
+    }
+
+    #[test]
+    fn test_fluent() {
+        let token = WithSpan::new('x', Span::new(4, 5).unwrap());
+        let mut sink = Vec::new();
+        Diagnostic::warning("unused variable")
+            .with_secondary_label(Span::new(0, 3).unwrap(), "declared here")
+            .with_label(&token, "never read")
+            .with_note("prefix it with an underscore to silence this")
+            .with_tag(DiagnosticTag::Unnecessary)
+            .emit(&mut sink);
+
+        let expected = Diagnostic::builder(Severity::Warning, "unused variable")
+            .secondary(Span::new(0, 3).unwrap(), "declared here")
+            .primary(token, "never read")
+            .note("prefix it with an underscore to silence this")
+            .tag(DiagnosticTag::Unnecessary)
+            .build();
+        assert_eq!(sink, [expected]);
+    }
+
+    #[test]
+    fn test_severity_order() {
+        assert!(Severity::Error > Severity::Warning);
+        assert!(Severity::Warning > Severity::Note);
+        assert!(Severity::Note > Severity::Help);
+        assert_eq!(Severity::Error.to_string(), "error");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_schema() {
+        use crate::diagnostics::Applicability;
+        use crate::position::TextEdit;
+
+        let json = serde_json::json!({
+            "severity": "error",
+            "message": "unexpected `}`",
+            "code": "E001",
+            "labels": [
+                { "style": "primary", "span": { "start": 10, "end": 11 }, "message": "here", "file": 0 }
+            ],
+            "notes": ["blocks must be balanced"],
+            "suggestions": [{
+                "message": "remove it",
+                "edits": [{ "range": { "start": 10, "end": 11 }, "new_text": "" }],
+                "applicability": "maybe-incorrect"
+            }],
+            "tags": ["unnecessary"],
+            "template": { "key": "unexpected", "args": [["token", "}"]] }
+        });
+        let span = Span::new(10, 11).unwrap();
+        let diagnostic = Diagnostic::error("unexpected `}`")
+            .with_code("E001")
+            .with_label_in(FileSpan::new(FileId(0), span), "here")
+            .with_note("blocks must be balanced")
+            .with_suggestion(Suggestion::new(
+                "remove it",
+                vec![TextEdit::delete(span)],
+                Applicability::MaybeIncorrect,
+            ))
+            .with_tag(DiagnosticTag::Unnecessary)
+            .with_template(Template::new("unexpected").with_arg("token", "}"));
+        assert_eq!(serde_json::to_value(&diagnostic).unwrap(), json);
+        assert_eq!(
+            serde_json::from_value::<Diagnostic>(json).unwrap(),
+            diagnostic
+        );
+    }
+}
@@ -0,0 +1,64 @@
+/// The largest number of names [`expected_list`] spells out in full.
+pub const MAX_EXPECTED_NAMES: usize = 5;
+
+/// Joins the names of expected tokens into a phrase for an error message.
+///
+/// Names are deduplicated, keeping the first occurrence. One name is
+/// returned as is, two are joined with "or", and up to
+/// [`MAX_EXPECTED_NAMES`] are listed with a final "or". Larger sets are
+/// summarized by their size and first few names, so a rule that accepts
+/// every keyword does not produce a wall of text.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::diagnostics::expected_list;
+///
+/// assert_eq!(expected_list(["`)`"]), "`)`");
+/// assert_eq!(expected_list(["`,`", "`)`"]), "`,` or `)`");
+/// assert_eq!(
+///     expected_list(["`,`", "`)`", "an identifier"]),
+///     "`,`, `)`, or an identifier"
+/// );
+/// assert_eq!(
+///     expected_list(["`a`", "`b`", "`c`", "`d`", "`e`", "`f`"]),
+///     "one of 6 tokens, including `a`, `b`, `c`"
+/// );
+/// ```
+pub fn expected_list<'a>(names: impl IntoIterator<Item = &'a str>) -> String {
+    let mut unique: Vec<&str> = Vec::new();
+    for name in names {
+        if !unique.contains(&name) {
+            unique.push(name);
+        }
+    }
+    match unique.as_slice() {
+        [] => "nothing".to_string(),
+        [name] => name.to_string(),
+        [first, second] => format!("{} or {}", first, second),
+        [init @ .., last] if unique.len() <= MAX_EXPECTED_NAMES => {
+            format!("{}, or {}", init.join(", "), last)
+        }
+        _ => format!(
+            "one of {} tokens, including {}",
+            unique.len(),
+            unique[..3].join(", ")
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_duplicates() {
+        assert_eq!(expected_list(["`;`", "`;`", "`}`"]), "`;` or `}`");
+        assert_eq!(expected_list([]), "nothing");
+    }
+
+    #[test]
+    fn test_limit() {
+        let names = ["a", "b", "c", "d", "e"];
+        assert_eq!(expected_list(names), "a, b, c, d, or e");
+    }
+}
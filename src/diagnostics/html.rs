@@ -0,0 +1,213 @@
+use super::terminal::{Markup, Role};
+use super::{Diagnostic, Severity, TerminalRenderer};
+use crate::position::ColumnMode;
+use crate::source::{FileId, SourceFile, SourceLookup};
+
+/// A stylesheet for the classes used by [`HtmlRenderer`], with colors
+/// matching the terminal renderer's defaults.
+pub const DIAGNOSTIC_CSS: &str = "\
+pre.gs-diagnostic { font-family: monospace; }
+.gs-error { color: #d0312d; font-weight: bold; }
+.gs-warning { color: #b8860b; font-weight: bold; }
+.gs-note { color: #2e8b57; font-weight: bold; }
+.gs-help { color: #008b8b; font-weight: bold; }
+.gs-secondary { color: #1e5aa8; font-weight: bold; }
+.gs-gutter { color: #1e5aa8; font-weight: bold; }
+.gs-emphasis { font-weight: bold; }
+";
+
+/// Renders diagnostics as HTML, for documentation generators and web
+/// playgrounds.
+///
+/// The layout is the same as [`TerminalRenderer`]'s, inside a
+/// `<pre class="gs-diagnostic">` element. Each styled part is wrapped in a
+/// `<span>` whose class is `gs-` followed by the severity for headers and
+/// primary labels, or by `secondary`, `gutter` or `emphasis`; see
+/// [`DIAGNOSTIC_CSS`]. All source text and messages are escaped.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::diagnostics::*;
+/// use grammarsmith::position::Span;
+/// use grammarsmith::source::SourceFile;
+///
+/// let file = SourceFile::new("main", "a < b");
+/// let diagnostic = Diagnostic::error("bad <").with_label(Span::new(2, 3).unwrap(), "");
+///
+/// let html = HtmlRenderer::new().render(&diagnostic, &file);
+/// assert!(html.starts_with(
+///     "<pre class=\"gs-diagnostic\"><span class=\"gs-error\">error</span>\
+///      <span class=\"gs-emphasis\">: bad &lt;</span>\n"
+/// ));
+/// assert!(html.contains("a &lt; b"));
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HtmlRenderer {
+    layout: TerminalRenderer,
+}
+
+impl HtmlRenderer {
+    /// Creates a renderer that reports columns in characters and expands
+    /// tabs to stops every four columns.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the unit columns are reported in.
+    pub fn with_column_mode(mut self, column_mode: ColumnMode) -> Self {
+        self.layout = self.layout.with_column_mode(column_mode);
+        self
+    }
+
+    /// Sets the distance between tab stops used to expand tabs in source
+    /// excerpts.
+    ///
+    /// # Panics
+    /// Panics if `tab_width` is zero.
+    pub fn with_tab_width(mut self, tab_width: usize) -> Self {
+        self.layout = self.layout.with_tab_width(tab_width);
+        self
+    }
+
+    /// Renders a diagnostic whose spans refer to `file` as a `<pre>`
+    /// element.
+    ///
+    /// Labels pointing into other files are left out; use
+    /// [`HtmlRenderer::render_in`] to include them.
+    pub fn render(&self, diagnostic: &Diagnostic, file: &SourceFile) -> String {
+        let mut out = String::from("<pre class=\"gs-diagnostic\">");
+        self.layout
+            .write_with(&Html, &mut out, diagnostic, file, &|_| None)
+            .unwrap();
+        out.push_str("</pre>");
+        out
+    }
+
+    /// Renders a diagnostic reported for the file `file` of `map` as a
+    /// `<pre>` element, showing excerpts of every file its labels point
+    /// into.
+    ///
+    /// # Panics
+    /// Panics if `file` is not in `map`.
+    pub fn render_in(
+        &self,
+        diagnostic: &Diagnostic,
+        file: FileId,
+        map: &(impl SourceLookup + ?Sized),
+    ) -> String {
+        let main = map.file(file).expect("file is not in the source map");
+        let mut out = String::from("<pre class=\"gs-diagnostic\">");
+        self.layout
+            .write_with(&Html, &mut out, diagnostic, main, &|id| map.file(id))
+            .unwrap();
+        out.push_str("</pre>");
+        out
+    }
+
+    /// Renders diagnostics whose spans refer to `file` as a complete HTML
+    /// document, with [`DIAGNOSTIC_CSS`] embedded.
+    pub fn render_page(&self, diagnostics: &[Diagnostic], file: &SourceFile) -> String {
+        let mut out = format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n\
+             <style>\n{}</style>\n</head>\n<body>\n",
+            escape_html(file.name()),
+            DIAGNOSTIC_CSS
+        );
+        for diagnostic in diagnostics {
+            out.push_str(&self.render(diagnostic, file));
+            out.push('\n');
+        }
+        out.push_str("</body>\n</html>\n");
+        out
+    }
+}
+
+/// Escapes `text` for use in HTML text and attribute values.
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            ch => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+struct Html;
+
+impl Markup for Html {
+    fn paint(&self, role: Role, text: &str) -> String {
+        let class = match role {
+            Role::Plain => return escape_html(text),
+            Role::Severity(Severity::Error) => "error",
+            Role::Severity(Severity::Warning) => "warning",
+            Role::Severity(Severity::Note) => "note",
+            Role::Severity(Severity::Help) => "help",
+            Role::Secondary => "secondary",
+            Role::Gutter => "gutter",
+            Role::Emphasis => "emphasis",
+        };
+        if text.is_empty() {
+            return String::new();
+        }
+        format!("<span class=\"gs-{}\">{}</span>", class, escape_html(text))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::position::Span;
+
+    #[test]
+    fn test_same_layout_as_terminal() {
+        let file = SourceFile::new("<input>", "x = \"&\";");
+        let diagnostic = Diagnostic::warning("odd string")
+            .with_label(Span::new(4, 7).unwrap(), "contains `&`")
+            .with_secondary_label(Span::new(0, 1).unwrap(), "")
+            .with_note("use <amp>");
+
+        let html = HtmlRenderer::new().render(&diagnostic, &file);
+        let text = html
+            .strip_prefix("<pre class=\"gs-diagnostic\">")
+            .and_then(|html| html.strip_suffix("</pre>"))
+            .unwrap();
+        let mut stripped = String::new();
+        let mut in_tag = false;
+        for ch in text.chars() {
+            match ch {
+                '<' => in_tag = true,
+                '>' => in_tag = false,
+                ch if !in_tag => stripped.push(ch),
+                _ => {}
+            }
+        }
+        let unescaped = stripped
+            .replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&quot;", "\"")
+            .replace("&amp;", "&");
+        assert_eq!(
+            unescaped,
+            TerminalRenderer::new().render(&diagnostic, &file)
+        );
+        assert!(html.contains("<span class=\"gs-secondary\">-</span>"));
+        assert!(html.contains(
+            "<span class=\"gs-warning\">^^^</span> <span class=\"gs-warning\">contains `&amp;`</span>"
+        ));
+    }
+
+    #[test]
+    fn test_page() {
+        let file = SourceFile::new("a<b>", "");
+        let page = HtmlRenderer::new().render_page(&[Diagnostic::note("hi")], &file);
+        assert!(page.contains("<title>a&lt;b&gt;</title>"));
+        assert!(page.contains(DIAGNOSTIC_CSS));
+        assert!(page.contains("<span class=\"gs-note\">note</span>"));
+    }
+}
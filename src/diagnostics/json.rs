@@ -0,0 +1,341 @@
+//! A stable JSON representation of diagnostics.
+//!
+//! The types in this module are a flattened view of a [`Diagnostic`] that
+//! resolves spans to lines and columns, so consumers do not need the source
+//! text. The format is versioned by [`JSON_FORMAT_VERSION`]; fields are only
+//! ever added in a new version, never renamed or removed.
+//!
+//! A diagnostic serializes as:
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "severity": "error",
+//!   "message": "expected expression",
+//!   "code": "E0042",
+//!   "file": "main.lang",
+//!   "span": {
+//!     "start": 8, "end": 9,
+//!     "start_line": 1, "start_column": 9, "end_line": 1, "end_column": 10
+//!   },
+//!   "labels": [{
+//!     "style": "primary", "message": "found `;`", "file": "main.lang", "span": { ... }
+//!   }],
+//!   "notes": [],
+//!   "tags": ["unnecessary"],
+//!   "suggestions": [{
+//!     "message": "insert a value",
+//!     "applicability": "has-placeholders",
+//!     "edits": [{ "span": { ... }, "new_text": "<value>" }]
+//!   }]
+//! }
+//! ```
+//!
+//! `span` is the span of the primary label, or `null` if there is none or it
+//! is in another file. Every label names the file it points into.
+//! Byte offsets are zero-based and lines and columns are one-based; columns
+//! are counted in the [`ColumnMode`] passed to [`JsonDiagnostic::new`], and
+//! `end_column` is exclusive.
+//!
+//! # Examples
+//! ```
+//! use grammarsmith::diagnostics::*;
+//! use grammarsmith::position::{ColumnMode, Span};
+//! use grammarsmith::source::SourceFile;
+//!
+//! let file = SourceFile::new("main.lang", "let x = ;");
+//! let diagnostic = Diagnostic::builder(Severity::Error, "expected expression")
+//!     .primary(Span::new(8, 9).unwrap(), "found `;`")
+//!     .build();
+//!
+//! let json = JsonDiagnostic::new(&diagnostic, &file, ColumnMode::Char);
+//! let value = serde_json::to_value(&json).unwrap();
+//! assert_eq!(value["severity"], "error");
+//! assert_eq!(value["span"]["start_column"], 9);
+//! ```
+
+use super::{Applicability, Diagnostic, DiagnosticTag, Label, LabelStyle, Severity, Suggestion};
+use crate::position::TextEdit;
+use crate::position::{ColumnMode, Span};
+use crate::source::{FileId, SourceFile, SourceMap};
+use serde::{Deserialize, Serialize};
+
+/// The version of the JSON format produced by this crate.
+pub const JSON_FORMAT_VERSION: u32 = 1;
+
+/// A span with both byte offsets and line/column positions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct JsonSpan {
+    pub start: usize,
+    pub end: usize,
+    pub start_line: usize,
+    pub start_column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+}
+
+impl JsonSpan {
+    pub fn new(span: Span, file: &SourceFile, mode: ColumnMode) -> Self {
+        let (start_line, start_column) = file.line_column(span.start, mode);
+        let (end_line, end_column) = file.line_column(span.end, mode);
+        JsonSpan {
+            start: span.start(),
+            end: span.end(),
+            start_line,
+            start_column,
+            end_line,
+            end_column,
+        }
+    }
+}
+
+/// A label in the JSON format.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct JsonLabel {
+    pub style: LabelStyle,
+    pub message: String,
+    pub file: String,
+    pub span: JsonSpan,
+}
+
+impl JsonLabel {
+    /// Converts a label whose span refers to `file`.
+    pub fn new(label: &Label, file: &SourceFile, mode: ColumnMode) -> Self {
+        JsonLabel {
+            style: label.style,
+            message: label.message.clone(),
+            file: file.location(label.span.start, mode).0.to_string(),
+            span: JsonSpan::new(label.span, file, mode),
+        }
+    }
+}
+
+/// A text edit in the JSON format.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct JsonEdit {
+    pub span: JsonSpan,
+    pub new_text: String,
+}
+
+impl JsonEdit {
+    pub fn new(edit: &TextEdit, file: &SourceFile, mode: ColumnMode) -> Self {
+        JsonEdit {
+            span: JsonSpan::new(edit.range, file, mode),
+            new_text: edit.new_text.clone(),
+        }
+    }
+}
+
+/// A suggested fix in the JSON format.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct JsonSuggestion {
+    pub message: String,
+    pub applicability: Applicability,
+    pub edits: Vec<JsonEdit>,
+}
+
+impl JsonSuggestion {
+    pub fn new(suggestion: &Suggestion, file: &SourceFile, mode: ColumnMode) -> Self {
+        JsonSuggestion {
+            message: suggestion.message.clone(),
+            applicability: suggestion.applicability,
+            edits: suggestion
+                .edits
+                .iter()
+                .map(|edit| JsonEdit::new(edit, file, mode))
+                .collect(),
+        }
+    }
+}
+
+/// A diagnostic in the JSON format.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct JsonDiagnostic {
+    pub version: u32,
+    pub severity: Severity,
+    pub message: String,
+    pub code: Option<String>,
+    pub file: String,
+    pub span: Option<JsonSpan>,
+    pub labels: Vec<JsonLabel>,
+    pub notes: Vec<String>,
+    pub tags: Vec<DiagnosticTag>,
+    pub suggestions: Vec<JsonSuggestion>,
+}
+
+impl JsonDiagnostic {
+    /// Converts a diagnostic whose spans refer to `file`.
+    ///
+    /// Labels pointing into other files are left out; use
+    /// [`JsonDiagnostic::in_map`] to include them.
+    pub fn new(diagnostic: &Diagnostic, file: &SourceFile, mode: ColumnMode) -> Self {
+        Self::convert(diagnostic, file, |_| None, mode)
+    }
+
+    /// Converts a diagnostic reported for the file `file` of `map`.
+    ///
+    /// Returns `None` if `file` is not in `map`. Labels pointing into files
+    /// that are not in `map` are left out.
+    pub fn in_map(
+        diagnostic: &Diagnostic,
+        file: FileId,
+        map: &SourceMap,
+        mode: ColumnMode,
+    ) -> Option<Self> {
+        let main = map.get(file)?;
+        Some(Self::convert(diagnostic, main, |id| map.get(id), mode))
+    }
+
+    fn convert<'a>(
+        diagnostic: &Diagnostic,
+        file: &'a SourceFile,
+        other: impl Fn(FileId) -> Option<&'a SourceFile>,
+        mode: ColumnMode,
+    ) -> Self {
+        JsonDiagnostic {
+            version: JSON_FORMAT_VERSION,
+            severity: diagnostic.severity,
+            message: diagnostic.message.clone(),
+            code: diagnostic.code.as_ref().map(|code| code.to_string()),
+            file: match diagnostic.primary_label() {
+                Some(label) if label.file.is_none() => file.location(label.span.start, mode).0,
+                _ => file.name(),
+            }
+            .to_string(),
+            span: diagnostic
+                .primary_label()
+                .filter(|label| label.file.is_none())
+                .map(|label| JsonSpan::new(label.span, file, mode)),
+            labels: diagnostic
+                .labels
+                .iter()
+                .filter_map(|label| {
+                    let file = match label.file {
+                        None => file,
+                        Some(id) => other(id)?,
+                    };
+                    Some(JsonLabel::new(label, file, mode))
+                })
+                .collect(),
+            notes: diagnostic.notes.clone(),
+            tags: diagnostic.tags.clone(),
+            suggestions: diagnostic
+                .suggestions
+                .iter()
+                .map(|suggestion| JsonSuggestion::new(suggestion, file, mode))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::FileSpan;
+
+    #[test]
+    fn test_format() {
+        let file = SourceFile::new("main.lang", "let x\n  = é;");
+        let diagnostic = Diagnostic::builder(Severity::Warning, "odd value")
+            .code("W7")
+            .primary(Span::new(10, 12).unwrap(), "this")
+            .secondary(Span::new(4, 5).unwrap(), "bound here")
+            .note("consider ASCII")
+            .suggestion(Suggestion::replace(
+                "use an ASCII letter",
+                Span::new(10, 12).unwrap(),
+                "e",
+                Applicability::MaybeIncorrect,
+            ))
+            .build();
+
+        let json = JsonDiagnostic::new(&diagnostic, &file, ColumnMode::Char);
+        assert_eq!(
+            serde_json::to_value(&json).unwrap(),
+            serde_json::json!({
+                "version": 1,
+                "severity": "warning",
+                "message": "odd value",
+                "code": "W7",
+                "file": "main.lang",
+                "span": {
+                    "start": 10, "end": 12,
+                    "start_line": 2, "start_column": 5, "end_line": 2, "end_column": 6
+                },
+                "labels": [
+                    {
+                        "style": "primary",
+                        "message": "this",
+                        "file": "main.lang",
+                        "span": {
+                            "start": 10, "end": 12,
+                            "start_line": 2, "start_column": 5, "end_line": 2, "end_column": 6
+                        }
+                    },
+                    {
+                        "style": "secondary",
+                        "message": "bound here",
+                        "file": "main.lang",
+                        "span": {
+                            "start": 4, "end": 5,
+                            "start_line": 1, "start_column": 5, "end_line": 1, "end_column": 6
+                        }
+                    }
+                ],
+                "notes": ["consider ASCII"],
+                "tags": [],
+                "suggestions": [{
+                    "message": "use an ASCII letter",
+                    "applicability": "maybe-incorrect",
+                    "edits": [{
+                        "span": {
+                            "start": 10, "end": 12,
+                            "start_line": 2, "start_column": 5, "end_line": 2, "end_column": 6
+                        },
+                        "new_text": "e"
+                    }]
+                }]
+            })
+        );
+
+        let text = serde_json::to_string(&json).unwrap();
+        assert_eq!(serde_json::from_str::<JsonDiagnostic>(&text).unwrap(), json);
+    }
+
+    #[test]
+    fn test_without_labels() {
+        let file = SourceFile::new("a", "");
+        let json = JsonDiagnostic::new(
+            &Diagnostic::new(Severity::Note, "hi"),
+            &file,
+            ColumnMode::Byte,
+        );
+        let value = serde_json::to_value(&json).unwrap();
+        assert!(value["span"].is_null());
+        assert!(value["code"].is_null());
+    }
+
+    #[test]
+    fn test_other_files() {
+        let mut map = SourceMap::new();
+        let main = map.add("main.lang", "use lib;\nfn f() {}");
+        let lib = map.add("lib.lang", "fn f() {}");
+        let diagnostic = Diagnostic::builder(Severity::Error, "`f` is defined twice")
+            .primary(Span::new(9, 18).unwrap(), "redefined here")
+            .secondary_in(
+                FileSpan::new(lib, Span::new(0, 9).unwrap()),
+                "first defined here",
+            )
+            .build();
+
+        let json = JsonDiagnostic::in_map(&diagnostic, main, &map, ColumnMode::Char).unwrap();
+        assert_eq!(json.labels.len(), 2);
+        assert_eq!(json.labels[1].file, "lib.lang");
+        assert_eq!(json.labels[1].span.start_line, 1);
+        assert_eq!(json.span.unwrap().start_line, 2);
+
+        let json = JsonDiagnostic::new(&diagnostic, map.get(main).unwrap(), ColumnMode::Char);
+        assert_eq!(json.labels.len(), 1);
+        assert!(JsonDiagnostic::in_map(&diagnostic, FileId(2), &map, ColumnMode::Char).is_none());
+    }
+}
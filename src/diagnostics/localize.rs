@@ -0,0 +1,193 @@
+use super::{Diagnostic, DiagnosticSink};
+use std::collections::HashMap;
+
+/// Identifies the message of a diagnostic by a key and named arguments, so
+/// it can be translated by a [`MessageCatalog`].
+///
+/// The diagnostic's `message` is kept as the text to show when no catalog
+/// is used or the catalog has no entry for the key.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Template {
+    pub key: String,
+    pub args: Vec<(String, String)>,
+}
+
+impl Template {
+    /// Creates a template without arguments.
+    pub fn new(key: impl Into<String>) -> Self {
+        Template {
+            key: key.into(),
+            args: Vec::new(),
+        }
+    }
+
+    /// Adds an argument.
+    pub fn with_arg(mut self, name: impl Into<String>, value: impl ToString) -> Self {
+        self.args.push((name.into(), value.to_string()));
+        self
+    }
+
+    /// Returns the value of an argument.
+    pub fn arg(&self, name: &str) -> Option<&str> {
+        self.args
+            .iter()
+            .find(|(arg, _)| arg == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Fills in `pattern` with the arguments of the template.
+    ///
+    /// Each `{name}` in the pattern is replaced by the value of the argument
+    /// `name`; placeholders without an argument are kept as they are. `{{`
+    /// and `}}` stand for literal braces.
+    ///
+    /// # Examples
+    /// ```
+    /// use grammarsmith::diagnostics::Template;
+    ///
+    /// let template = Template::new("expected-found")
+    ///     .with_arg("expected", "`;`")
+    ///     .with_arg("found", "`}`");
+    /// assert_eq!(
+    ///     template.format("{expected} attendu, {found} trouvé {{}}"),
+    ///     "`;` attendu, `}` trouvé {}"
+    /// );
+    /// ```
+    pub fn format(&self, pattern: &str) -> String {
+        let mut out = String::with_capacity(pattern.len());
+        let mut rest = pattern;
+        while let Some(i) = rest.find(['{', '}']) {
+            out.push_str(&rest[..i]);
+            rest = &rest[i..];
+            if rest.starts_with("{{") || rest.starts_with("}}") {
+                out.push_str(&rest[..1]);
+                rest = &rest[2..];
+                continue;
+            }
+            let placeholder = rest
+                .strip_prefix('{')
+                .and_then(|inner| inner.find('}').map(|end| &inner[..end]));
+            match placeholder.and_then(|name| self.arg(name).map(|value| (name, value))) {
+                Some((name, value)) => {
+                    out.push_str(value);
+                    rest = &rest[name.len() + 2..];
+                }
+                None => {
+                    out.push_str(&rest[..1]);
+                    rest = &rest[1..];
+                }
+            }
+        }
+        out.push_str(rest);
+        out
+    }
+}
+
+/// Resolves message keys to patterns in one language.
+///
+/// Patterns use the placeholder syntax of [`Template::format`].
+pub trait MessageCatalog {
+    /// Returns the pattern for `key`, or `None` if it is not translated.
+    fn pattern(&self, key: &str) -> Option<&str>;
+}
+
+impl MessageCatalog for HashMap<String, String> {
+    fn pattern(&self, key: &str) -> Option<&str> {
+        self.get(key).map(String::as_str)
+    }
+}
+
+impl MessageCatalog for HashMap<&str, &str> {
+    fn pattern(&self, key: &str) -> Option<&str> {
+        self.get(key).copied()
+    }
+}
+
+impl Diagnostic {
+    /// Replaces the message with its translation in `catalog`, if the
+    /// diagnostic has a [`Template`] the catalog knows.
+    ///
+    /// # Examples
+    /// ```
+    /// use grammarsmith::diagnostics::*;
+    /// use std::collections::HashMap;
+    ///
+    /// let catalog = HashMap::from([("unknown-variable", "variable inconnue `{name}`")]);
+    /// let mut diagnostic = Diagnostic::error("unknown variable `x`")
+    ///     .with_template(Template::new("unknown-variable").with_arg("name", "x"));
+    /// diagnostic.localize(&catalog);
+    /// assert_eq!(diagnostic.message, "variable inconnue `x`");
+    /// ```
+    pub fn localize(&mut self, catalog: &(impl MessageCatalog + ?Sized)) {
+        if let Some(template) = &self.template {
+            if let Some(pattern) = catalog.pattern(&template.key) {
+                self.message = template.format(pattern);
+            }
+        }
+    }
+}
+
+/// A sink that translates the message of every diagnostic with a
+/// [`MessageCatalog`] before passing it on, typically to a renderer.
+#[derive(Debug)]
+pub struct LocalizedSink<'c, C: ?Sized, D> {
+    catalog: &'c C,
+    inner: D,
+}
+
+impl<'c, C: MessageCatalog + ?Sized, D: DiagnosticSink> LocalizedSink<'c, C, D> {
+    pub fn new(catalog: &'c C, inner: D) -> Self {
+        LocalizedSink { catalog, inner }
+    }
+
+    /// Returns the wrapped sink.
+    pub fn into_inner(self) -> D {
+        self.inner
+    }
+}
+
+impl<C: MessageCatalog + ?Sized, D: DiagnosticSink> DiagnosticSink for LocalizedSink<'_, C, D> {
+    fn emit(&mut self, mut diagnostic: Diagnostic) {
+        diagnostic.localize(self.catalog);
+        self.inner.emit(diagnostic);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format() {
+        let template = Template::new("key").with_arg("a", 1).with_arg("b", "{a}");
+        assert_eq!(template.format("{a}-{b}"), "1-{a}");
+        assert_eq!(template.format("{missing} {a"), "{missing} {a");
+        assert_eq!(template.format("}} {{a}} }"), "} {a} }");
+        assert_eq!(template.format(""), "");
+    }
+
+    #[test]
+    fn test_sink() {
+        let catalog: HashMap<String, String> =
+            HashMap::from([("eof".to_string(), "fin de fichier inattendue".to_string())]);
+        let mut sink = LocalizedSink::new(&catalog, Vec::new());
+        Diagnostic::error("unexpected end of file")
+            .with_template(Template::new("eof"))
+            .emit(&mut sink);
+        Diagnostic::error("no template").emit(&mut sink);
+        Diagnostic::error("unknown key")
+            .with_template(Template::new("other"))
+            .emit(&mut sink);
+
+        let messages: Vec<_> = sink
+            .into_inner()
+            .into_iter()
+            .map(|diagnostic| diagnostic.message)
+            .collect();
+        assert_eq!(
+            messages,
+            ["fin de fichier inattendue", "no template", "unknown key"]
+        );
+    }
+}
@@ -0,0 +1,319 @@
+//! Export of diagnostics as a [SARIF 2.1.0] log.
+//!
+//! SARIF is the format code-scanning services such as GitHub code scanning
+//! accept. [`to_sarif`] covers the subset needed to report results: the tool
+//! with one rule per diagnostic code, and one result per diagnostic with its
+//! primary label as the location and the other labels as related locations.
+//! Notes are kept in the `notes` entry of the result's property bag.
+//!
+//! Columns are reported in UTF-16 code units, SARIF's default column kind.
+//!
+//! [SARIF 2.1.0]: https://docs.oasis-open.org/sarif/sarif/v2.1.0/sarif-v2.1.0.html
+//!
+//! # Examples
+//! ```
+//! use grammarsmith::diagnostics::*;
+//! use grammarsmith::position::Span;
+//! use grammarsmith::source::SourceMap;
+//!
+//! let mut map = SourceMap::new();
+//! let file = map.add("src/main.lang", "let x = ;");
+//! let diagnostic = Diagnostic::builder(Severity::Error, "expected expression")
+//!     .code("E0042")
+//!     .primary(Span::new(8, 9).unwrap(), "found `;`")
+//!     .build();
+//!
+//! let log = to_sarif("langc", [(file, &diagnostic)], &map);
+//! let value = serde_json::to_value(&log).unwrap();
+//! assert_eq!(value["runs"][0]["results"][0]["ruleId"], "E0042");
+//! assert_eq!(value["runs"][0]["results"][0]["level"], "error");
+//! ```
+
+use super::{Diagnostic, Label, Severity};
+use crate::position::ColumnMode;
+use crate::source::{FileId, SourceMap};
+use serde::{Deserialize, Serialize};
+
+/// The SARIF version written by [`to_sarif`].
+pub const SARIF_VERSION: &str = "2.1.0";
+
+/// The JSON schema of the SARIF version written by [`to_sarif`].
+pub const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// The top-level SARIF object.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SarifLog {
+    #[serde(rename = "$schema")]
+    pub schema: String,
+    pub version: String,
+    pub runs: Vec<SarifRun>,
+}
+
+/// A single invocation of an analysis tool.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SarifRun {
+    pub tool: SarifTool,
+    pub column_kind: String,
+    pub results: Vec<SarifResult>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SarifTool {
+    pub driver: SarifDriver,
+}
+
+/// The analysis tool and the rules it can report.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SarifDriver {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<SarifRule>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SarifRule {
+    pub id: String,
+}
+
+/// One reported problem.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SarifResult {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rule_id: Option<String>,
+    pub level: String,
+    pub message: SarifMessage,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub locations: Vec<SarifLocation>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub related_locations: Vec<SarifLocation>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub properties: Option<SarifProperties>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SarifMessage {
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SarifProperties {
+    pub notes: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SarifLocation {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<usize>,
+    pub physical_location: SarifPhysicalLocation,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<SarifMessage>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SarifPhysicalLocation {
+    pub artifact_location: SarifArtifactLocation,
+    pub region: SarifRegion,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SarifArtifactLocation {
+    pub uri: String,
+}
+
+/// A region of a file. Lines and columns are one-based and `end_column` is
+/// exclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SarifRegion {
+    pub start_line: usize,
+    pub start_column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+}
+
+/// Returns the SARIF level of a severity.
+pub fn sarif_level(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Note | Severity::Help => "note",
+    }
+}
+
+/// Builds a SARIF log with a single run of the tool `tool_name`.
+///
+/// Each diagnostic is paired with the file it is reported for. Labels that
+/// point into files not in `map` are left out.
+pub fn to_sarif<'d>(
+    tool_name: &str,
+    diagnostics: impl IntoIterator<Item = (FileId, &'d Diagnostic)>,
+    map: &SourceMap,
+) -> SarifLog {
+    let mut rules: Vec<SarifRule> = Vec::new();
+    let mut results = Vec::new();
+    for (file, diagnostic) in diagnostics {
+        let rule_id = diagnostic.code.as_ref().map(|code| code.to_string());
+        if let Some(id) = &rule_id {
+            if !rules.iter().any(|rule| &rule.id == id) {
+                rules.push(SarifRule { id: id.clone() });
+            }
+        }
+
+        let location = |label: &Label, id: Option<usize>| {
+            let file = map.get(label.file.unwrap_or(file))?;
+            let (start_line, start_column) = file.line_column(label.span.start, ColumnMode::Utf16);
+            let (end_line, end_column) = file.line_column(label.span.end, ColumnMode::Utf16);
+            Some(SarifLocation {
+                id,
+                physical_location: SarifPhysicalLocation {
+                    artifact_location: SarifArtifactLocation {
+                        uri: file
+                            .location(label.span.start, ColumnMode::Utf16)
+                            .0
+                            .to_string(),
+                    },
+                    region: SarifRegion {
+                        start_line,
+                        start_column,
+                        end_line,
+                        end_column,
+                    },
+                },
+                message: (id.is_some() && !label.message.is_empty()).then(|| SarifMessage {
+                    text: label.message.clone(),
+                }),
+            })
+        };
+
+        let primary = diagnostic.primary_label();
+        results.push(SarifResult {
+            rule_id,
+            level: sarif_level(diagnostic.severity).to_string(),
+            message: SarifMessage {
+                text: diagnostic.message.clone(),
+            },
+            locations: primary
+                .and_then(|label| location(label, None))
+                .into_iter()
+                .collect(),
+            related_locations: diagnostic
+                .labels
+                .iter()
+                .filter(|label| !primary.is_some_and(|primary| std::ptr::eq(primary, *label)))
+                .enumerate()
+                .filter_map(|(i, label)| location(label, Some(i + 1)))
+                .collect(),
+            properties: (!diagnostic.notes.is_empty()).then(|| SarifProperties {
+                notes: diagnostic.notes.clone(),
+            }),
+        });
+    }
+
+    SarifLog {
+        schema: SARIF_SCHEMA.to_string(),
+        version: SARIF_VERSION.to_string(),
+        runs: vec![SarifRun {
+            tool: SarifTool {
+                driver: SarifDriver {
+                    name: tool_name.to_string(),
+                    rules,
+                },
+            },
+            column_kind: "utf16CodeUnits".to_string(),
+            results,
+        }],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::position::Span;
+
+    #[test]
+    fn test_to_sarif() {
+        let mut map = SourceMap::new();
+        let a = map.add("a.lang", "x = 🦀 + y\n");
+        let b = map.add("b.lang", "oops");
+        let first = Diagnostic::builder(Severity::Warning, "odd sum")
+            .code("W1")
+            .primary(Span::new(9, 10).unwrap(), "")
+            .secondary(Span::new(4, 8).unwrap(), "a crab")
+            .note("crabs are not numbers")
+            .build();
+        let second = Diagnostic::builder(Severity::Help, "rename")
+            .code("W1")
+            .primary(Span::new(0, 4).unwrap(), "")
+            .build();
+
+        let log = to_sarif("test", [(a, &first), (b, &second)], &map);
+        assert_eq!(
+            serde_json::to_value(&log).unwrap(),
+            serde_json::json!({
+                "$schema": SARIF_SCHEMA,
+                "version": "2.1.0",
+                "runs": [{
+                    "tool": { "driver": { "name": "test", "rules": [{ "id": "W1" }] } },
+                    "columnKind": "utf16CodeUnits",
+                    "results": [
+                        {
+                            "ruleId": "W1",
+                            "level": "warning",
+                            "message": { "text": "odd sum" },
+                            "locations": [{
+                                "physicalLocation": {
+                                    "artifactLocation": { "uri": "a.lang" },
+                                    "region": {
+                                        "startLine": 1, "startColumn": 8,
+                                        "endLine": 1, "endColumn": 9
+                                    }
+                                }
+                            }],
+                            "relatedLocations": [{
+                                "id": 1,
+                                "physicalLocation": {
+                                    "artifactLocation": { "uri": "a.lang" },
+                                    "region": {
+                                        "startLine": 1, "startColumn": 5,
+                                        "endLine": 1, "endColumn": 7
+                                    }
+                                },
+                                "message": { "text": "a crab" }
+                            }],
+                            "properties": { "notes": ["crabs are not numbers"] }
+                        },
+                        {
+                            "ruleId": "W1",
+                            "level": "note",
+                            "message": { "text": "rename" },
+                            "locations": [{
+                                "physicalLocation": {
+                                    "artifactLocation": { "uri": "b.lang" },
+                                    "region": {
+                                        "startLine": 1, "startColumn": 1,
+                                        "endLine": 1, "endColumn": 5
+                                    }
+                                }
+                            }]
+                        }
+                    ]
+                }]
+            })
+        );
+    }
+
+    #[test]
+    fn test_missing_file() {
+        let map = SourceMap::new();
+        let diagnostic = Diagnostic::builder(Severity::Error, "lost")
+            .primary(Span::new(0, 1).unwrap(), "")
+            .build();
+        let log = to_sarif("test", [(FileId(0), &diagnostic)], &map);
+        assert!(log.runs[0].results[0].locations.is_empty());
+        assert!(log.runs[0].tool.driver.rules.is_empty());
+    }
+}
@@ -0,0 +1,171 @@
+use super::Diagnostic;
+use crate::position::{ColumnMode, Span};
+use crate::source::{FileId, SourceFile, SourceLookup};
+use std::io;
+
+/// Renders diagnostics on a single line in the classic compiler format,
+/// `file:line:col: severity[code]: message`, without source excerpts.
+///
+/// The location is that of the primary label. Diagnostics without labels
+/// are reported against the file alone, as `file: severity: message`. This is
+/// the format editors, `grep` and CI problem matchers expect.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::diagnostics::*;
+/// use grammarsmith::position::Span;
+/// use grammarsmith::source::SourceFile;
+///
+/// let file = SourceFile::new("main.calc", "1 +\n* 2");
+/// let diagnostic = Diagnostic::builder(Severity::Error, "expected an expression")
+///     .code("E001")
+///     .primary(Span::new(4, 5).unwrap(), "found `*`")
+///     .build();
+///
+/// assert_eq!(
+///     ShortRenderer::new().render(&diagnostic, &file),
+///     "main.calc:2:1: error[E001]: expected an expression\n"
+/// );
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct ShortRenderer {
+    column_mode: ColumnMode,
+}
+
+impl Default for ShortRenderer {
+    fn default() -> Self {
+        ShortRenderer {
+            column_mode: ColumnMode::Char,
+        }
+    }
+}
+
+impl ShortRenderer {
+    /// Creates a renderer that reports columns in characters.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the unit columns are reported in.
+    pub fn with_column_mode(mut self, column_mode: ColumnMode) -> Self {
+        self.column_mode = column_mode;
+        self
+    }
+
+    /// Renders a diagnostic whose spans refer to `file`.
+    ///
+    /// If the primary label points into another file, the diagnostic is
+    /// reported against `file` alone; use [`ShortRenderer::render_in`] to
+    /// report it at the label.
+    pub fn render(&self, diagnostic: &Diagnostic, file: &SourceFile) -> String {
+        let label = diagnostic
+            .primary_label()
+            .filter(|label| label.file.is_none());
+        self.line(diagnostic, file, label.map(|label| label.span))
+    }
+
+    /// Renders a diagnostic reported for the file `file` of `map`.
+    ///
+    /// # Panics
+    /// Panics if `file` is not in `map`.
+    pub fn render_in(
+        &self,
+        diagnostic: &Diagnostic,
+        file: FileId,
+        map: &(impl SourceLookup + ?Sized),
+    ) -> String {
+        let main = map.file(file).expect("file is not in the source map");
+        match diagnostic.primary_label() {
+            Some(label) => match map.file(label.file.unwrap_or(file)) {
+                Some(source) => self.line(diagnostic, source, Some(label.span)),
+                None => self.line(diagnostic, main, None),
+            },
+            None => self.line(diagnostic, main, None),
+        }
+    }
+
+    /// Renders a diagnostic whose spans refer to `file` into `writer`.
+    pub fn emit(
+        &self,
+        writer: &mut impl io::Write,
+        diagnostic: &Diagnostic,
+        file: &SourceFile,
+    ) -> io::Result<()> {
+        writer.write_all(self.render(diagnostic, file).as_bytes())
+    }
+
+    fn line(&self, diagnostic: &Diagnostic, file: &SourceFile, span: Option<Span>) -> String {
+        let location = match span {
+            Some(span) => {
+                let (name, line, column) = file.location(span.start, self.column_mode);
+                format!("{}:{}:{}", name, line, column)
+            }
+            None => file.name().to_string(),
+        };
+        match &diagnostic.code {
+            Some(code) => format!(
+                "{}: {}[{}]: {}\n",
+                location, diagnostic.severity, code, diagnostic.message
+            ),
+            None => format!(
+                "{}: {}: {}\n",
+                location, diagnostic.severity, diagnostic.message
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::Severity;
+    use crate::source::{FileSpan, SourceMap};
+
+    #[test]
+    fn test_without_labels() {
+        let file = SourceFile::new("a.lang", "");
+        let diagnostic = Diagnostic::new(Severity::Warning, "empty file");
+        assert_eq!(
+            ShortRenderer::new().render(&diagnostic, &file),
+            "a.lang: warning: empty file\n"
+        );
+    }
+
+    #[test]
+    fn test_columns() {
+        let file = SourceFile::new("a.lang", "🦀 x");
+        let diagnostic = Diagnostic::builder(Severity::Note, "here")
+            .primary(Span::new(5, 6).unwrap(), "")
+            .build();
+        let renderer = ShortRenderer::new();
+        assert_eq!(
+            renderer.render(&diagnostic, &file),
+            "a.lang:1:3: note: here\n"
+        );
+        assert_eq!(
+            renderer
+                .with_column_mode(ColumnMode::Byte)
+                .render(&diagnostic, &file),
+            "a.lang:1:6: note: here\n"
+        );
+    }
+
+    #[test]
+    fn test_render_in() {
+        let mut map = SourceMap::new();
+        let main = map.add("main.lang", "use lib;");
+        let lib = map.add("lib.lang", "\nbad");
+        let diagnostic = Diagnostic::builder(Severity::Error, "broken import")
+            .primary_in(FileSpan::new(lib, Span::new(1, 4).unwrap()), "")
+            .build();
+        let renderer = ShortRenderer::new();
+        assert_eq!(
+            renderer.render_in(&diagnostic, main, &map),
+            "lib.lang:2:1: error: broken import\n"
+        );
+        assert_eq!(
+            renderer.render(&diagnostic, map.get(main).unwrap()),
+            "main.lang: error: broken import\n"
+        );
+    }
+}
@@ -0,0 +1,113 @@
+use super::{Applicability, Suggestion};
+use crate::position::Span;
+
+/// Returns the Levenshtein distance between `a` and `b` in characters: the
+/// number of insertions, deletions and substitutions that turn one into the
+/// other.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::diagnostics::edit_distance;
+///
+/// assert_eq!(edit_distance("retrun", "return"), 2);
+/// assert_eq!(edit_distance("", "abc"), 3);
+/// ```
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Returns the candidate most similar to `found`, for "did you mean"
+/// suggestions on misspelled keywords, fields or directives.
+///
+/// Similarity is the [`edit_distance`] divided by the length of the longer
+/// string, from 0 for equal strings to 1 for completely different ones.
+/// Candidates further than `threshold` are ignored; 0.34 is a reasonable
+/// default, allowing one typo in three characters. Among equally similar
+/// candidates, the first wins.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::diagnostics::suggest;
+///
+/// let keywords = ["let", "return", "while"];
+/// assert_eq!(suggest(keywords, "retrun", 0.34), Some("return"));
+/// assert_eq!(suggest(keywords, "xyz", 0.34), None);
+/// ```
+pub fn suggest<'a>(
+    candidates: impl IntoIterator<Item = &'a str>,
+    found: &str,
+    threshold: f64,
+) -> Option<&'a str> {
+    let found_len = found.chars().count();
+    let mut best: Option<(f64, &'a str)> = None;
+    for candidate in candidates {
+        let len = found_len.max(candidate.chars().count());
+        let distance = if len == 0 {
+            0.0
+        } else {
+            edit_distance(found, candidate) as f64 / len as f64
+        };
+        if distance <= threshold && best.is_none_or(|(best, _)| distance < best) {
+            best = Some((distance, candidate));
+        }
+    }
+    best.map(|(_, candidate)| candidate)
+}
+
+impl Suggestion {
+    /// Creates a "did you mean" suggestion replacing the misspelled text in
+    /// `span` with `candidate`, typically one returned by [`suggest`].
+    ///
+    /// # Examples
+    /// ```
+    /// use grammarsmith::diagnostics::*;
+    /// use grammarsmith::position::Span;
+    ///
+    /// let fix = Suggestion::did_you_mean(Span::new(0, 6).unwrap(), "return");
+    /// assert_eq!(fix.message, "did you mean `return`?");
+    /// assert_eq!(fix.applicability, Applicability::MaybeIncorrect);
+    /// ```
+    pub fn did_you_mean(span: Span, candidate: &str) -> Self {
+        Suggestion::replace(
+            format!("did you mean `{}`?", candidate),
+            span,
+            candidate,
+            Applicability::MaybeIncorrect,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("same", "same"), 0);
+        assert_eq!(edit_distance("héllo", "hello"), 1);
+        assert_eq!(edit_distance("abc", ""), 3);
+    }
+
+    #[test]
+    fn test_suggest() {
+        let fields = ["width", "height", "weight"];
+        assert_eq!(suggest(fields, "heigth", 0.34), Some("height"));
+        assert_eq!(suggest(fields, "wieght", 0.34), Some("weight"));
+        assert_eq!(suggest(fields, "width", 0.0), Some("width"));
+        assert_eq!(suggest(fields, "depth", 0.34), None);
+        assert_eq!(suggest([], "x", 1.0), None);
+        assert_eq!(suggest([""], "", 0.0), Some(""));
+    }
+}
@@ -0,0 +1,322 @@
+use super::{Diagnostic, Severity, TerminalRenderer};
+use crate::source::SourceFile;
+use std::io;
+use std::sync::mpsc;
+
+/// Receives diagnostics as they are produced.
+///
+/// Scanners and parsers report problems to a sink instead of deciding what
+/// happens to them, so the same grammar code can stream diagnostics to a
+/// language server or collect them for a batch compiler.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::diagnostics::*;
+///
+/// fn check(sink: &mut impl DiagnosticSink) {
+///     sink.emit(Diagnostic::new(Severity::Warning, "unused import"));
+/// }
+///
+/// let mut collected = Vec::new();
+/// check(&mut collected);
+/// assert_eq!(collected.len(), 1);
+///
+/// let mut counter = CountingSink::new();
+/// check(&mut counter);
+/// assert_eq!(counter.warnings(), 1);
+/// ```
+pub trait DiagnosticSink {
+    /// Reports a diagnostic.
+    fn emit(&mut self, diagnostic: Diagnostic);
+
+    /// Reports the diagnostic built by `make`, for sinks that may drop it
+    /// to skip building it, such as a [`SpeculativeSink`](super::SpeculativeSink)
+    /// inside a checkpoint that is rewound. Defaults to building it and
+    /// passing it to [`emit`](DiagnosticSink::emit).
+    ///
+    /// Such sinks keep `make` after the call, until they know whether the
+    /// diagnostic survives, so it can only borrow `'static` data, such as
+    /// constant token kinds and display names.
+    fn emit_lazy(&mut self, make: Box<dyn FnOnce() -> Diagnostic>) {
+        self.emit(make())
+    }
+}
+
+impl<D: DiagnosticSink + ?Sized> DiagnosticSink for &mut D {
+    fn emit(&mut self, diagnostic: Diagnostic) {
+        (**self).emit(diagnostic)
+    }
+
+    fn emit_lazy(&mut self, make: Box<dyn FnOnce() -> Diagnostic>) {
+        (**self).emit_lazy(make)
+    }
+}
+
+impl<D: DiagnosticSink + ?Sized> DiagnosticSink for Box<D> {
+    fn emit(&mut self, diagnostic: Diagnostic) {
+        (**self).emit(diagnostic)
+    }
+
+    fn emit_lazy(&mut self, make: Box<dyn FnOnce() -> Diagnostic>) {
+        (**self).emit_lazy(make)
+    }
+}
+
+/// Collects diagnostics in the order they are emitted.
+impl DiagnosticSink for Vec<Diagnostic> {
+    fn emit(&mut self, diagnostic: Diagnostic) {
+        self.push(diagnostic)
+    }
+}
+
+/// Sends diagnostics to another thread, such as a UI thread showing them as
+/// they arrive. Diagnostics are dropped once the receiver is gone.
+impl DiagnosticSink for mpsc::Sender<Diagnostic> {
+    fn emit(&mut self, diagnostic: Diagnostic) {
+        let _ = self.send(diagnostic);
+    }
+}
+
+/// Sends diagnostics to another thread, blocking while the channel is
+/// full. Diagnostics are dropped once the receiver is gone.
+impl DiagnosticSink for mpsc::SyncSender<Diagnostic> {
+    fn emit(&mut self, diagnostic: Diagnostic) {
+        let _ = self.send(diagnostic);
+    }
+}
+
+/// Discards every diagnostic.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct IgnoreSink;
+
+impl DiagnosticSink for IgnoreSink {
+    fn emit(&mut self, _diagnostic: Diagnostic) {}
+}
+
+/// Counts diagnostics by severity and discards them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CountingSink {
+    counts: [usize; 4],
+}
+
+impl CountingSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of diagnostics emitted with the given severity.
+    pub fn count(&self, severity: Severity) -> usize {
+        self.counts[severity as usize]
+    }
+
+    /// Returns the number of errors.
+    pub fn errors(&self) -> usize {
+        self.count(Severity::Error)
+    }
+
+    /// Returns the number of warnings.
+    pub fn warnings(&self) -> usize {
+        self.count(Severity::Warning)
+    }
+
+    /// Returns the number of diagnostics of any severity.
+    pub fn total(&self) -> usize {
+        self.counts.iter().sum()
+    }
+
+    /// Returns true if an error was emitted.
+    pub fn has_errors(&self) -> bool {
+        self.errors() > 0
+    }
+}
+
+impl DiagnosticSink for CountingSink {
+    fn emit(&mut self, diagnostic: Diagnostic) {
+        self.counts[diagnostic.severity as usize] += 1;
+    }
+}
+
+/// Renders each diagnostic with a [`TerminalRenderer`] as soon as it is
+/// emitted.
+///
+/// Diagnostics are rendered against a single file. The first write error is
+/// kept and returned by [`RenderSink::finish`]; later diagnostics are dropped.
+#[derive(Debug)]
+pub struct RenderSink<'a, W: io::Write> {
+    writer: W,
+    file: &'a SourceFile,
+    renderer: TerminalRenderer,
+    error: Option<io::Error>,
+}
+
+impl<'a, W: io::Write> RenderSink<'a, W> {
+    pub fn new(writer: W, file: &'a SourceFile) -> Self {
+        RenderSink {
+            writer,
+            file,
+            renderer: TerminalRenderer::new(),
+            error: None,
+        }
+    }
+
+    /// Sets the renderer used for each diagnostic.
+    pub fn with_renderer(mut self, renderer: TerminalRenderer) -> Self {
+        self.renderer = renderer;
+        self
+    }
+
+    /// Returns the writer, or the first error that occurred while writing.
+    pub fn finish(self) -> io::Result<W> {
+        match self.error {
+            Some(error) => Err(error),
+            None => Ok(self.writer),
+        }
+    }
+}
+
+impl<W: io::Write> DiagnosticSink for RenderSink<'_, W> {
+    fn emit(&mut self, diagnostic: Diagnostic) {
+        if self.error.is_none() {
+            if let Err(error) = self.renderer.emit(&mut self.writer, &diagnostic, self.file) {
+                self.error = Some(error);
+            }
+        }
+    }
+}
+
+/// How far a scanner or parser has got through its input.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Progress {
+    /// The number of bytes of input processed.
+    pub bytes: usize,
+    /// The number of tokens processed.
+    pub tokens: usize,
+}
+
+/// Calls a function with each diagnostic the moment it is emitted, together
+/// with the progress of the parse so far.
+///
+/// The driver of a long parse reports its progress with
+/// [`set_progress`](CallbackSink::set_progress), so a UI can show errors
+/// incrementally along with how much of the input has been checked.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::diagnostics::*;
+///
+/// let mut seen = Vec::new();
+/// let mut sink = CallbackSink::new(|diagnostic: Diagnostic, progress: Progress| {
+///     seen.push(format!("{} after {} bytes", diagnostic.message, progress.bytes));
+/// });
+/// sink.set_progress(Progress { bytes: 120, tokens: 30 });
+/// Diagnostic::error("unclosed string").emit(&mut sink);
+///
+/// assert_eq!(seen, ["unclosed string after 120 bytes"]);
+/// ```
+#[derive(Debug)]
+pub struct CallbackSink<F> {
+    callback: F,
+    progress: Progress,
+}
+
+impl<F: FnMut(Diagnostic, Progress)> CallbackSink<F> {
+    pub fn new(callback: F) -> Self {
+        CallbackSink {
+            callback,
+            progress: Progress::default(),
+        }
+    }
+
+    /// Records how far the parse has got, passed along with the following
+    /// diagnostics.
+    pub fn set_progress(&mut self, progress: Progress) {
+        self.progress = progress;
+    }
+
+    /// Returns the last recorded progress.
+    pub fn progress(&self) -> Progress {
+        self.progress
+    }
+
+    /// Returns the callback.
+    pub fn into_inner(self) -> F {
+        self.callback
+    }
+}
+
+impl<F: FnMut(Diagnostic, Progress)> DiagnosticSink for CallbackSink<F> {
+    fn emit(&mut self, diagnostic: Diagnostic) {
+        (self.callback)(diagnostic, self.progress);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::position::Span;
+
+    fn emit_all(sink: &mut dyn DiagnosticSink) {
+        sink.emit(Diagnostic::new(Severity::Error, "first"));
+        sink.emit(Diagnostic::new(Severity::Note, "second"));
+        sink.emit(Diagnostic::new(Severity::Error, "third"));
+    }
+
+    #[test]
+    fn test_counting_sink() {
+        let mut sink = CountingSink::new();
+        emit_all(&mut sink);
+        assert_eq!(sink.errors(), 2);
+        assert_eq!(sink.warnings(), 0);
+        assert_eq!(sink.count(Severity::Note), 1);
+        assert_eq!(sink.total(), 3);
+        assert!(sink.has_errors());
+    }
+
+    #[test]
+    fn test_vec_sink() {
+        let mut sink = Vec::new();
+        emit_all(&mut sink);
+        emit_all(&mut IgnoreSink);
+        let messages: Vec<_> = sink.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(messages, ["first", "second", "third"]);
+    }
+
+    #[test]
+    fn test_render_sink() {
+        let file = SourceFile::new("a", "x");
+        let mut sink = RenderSink::new(Vec::new(), &file);
+        sink.emit(
+            Diagnostic::builder(Severity::Error, "bad")
+                .primary(Span::new(0, 1).unwrap(), "here")
+                .build(),
+        );
+        let out = String::from_utf8(sink.finish().unwrap()).unwrap();
+        assert!(out.starts_with("error: bad\n --> a:1:1\n"));
+    }
+
+    #[test]
+    fn test_channel_sink() {
+        let (sender, receiver) = mpsc::channel();
+        let handle = std::thread::spawn(move || {
+            let mut sink = sender;
+            emit_all(&mut sink);
+        });
+        handle.join().unwrap();
+        let messages: Vec<_> = receiver.iter().map(|d| d.message).collect();
+        assert_eq!(messages, ["first", "second", "third"]);
+    }
+
+    #[test]
+    fn test_callback_sink() {
+        let mut tokens = Vec::new();
+        let mut sink = CallbackSink::new(|_, progress: Progress| tokens.push(progress.tokens));
+        emit_all(&mut sink);
+        sink.set_progress(Progress {
+            bytes: 10,
+            tokens: 4,
+        });
+        sink.emit(Diagnostic::new(Severity::Help, "last"));
+        assert_eq!(sink.progress().bytes, 10);
+        assert_eq!(tokens, [0, 0, 0, 4]);
+    }
+}
@@ -0,0 +1,142 @@
+//! Deterministic plain-text rendering of diagnostics for snapshot tests.
+//!
+//! The output is that of [`TerminalRenderer`] without colors, with columns
+//! in characters and tabs expanded to four spaces, so it does not depend on
+//! the terminal or on renderer defaults changing. File names have their
+//! backslashes turned into slashes, so snapshots recorded on Windows match
+//! the ones recorded elsewhere, and diagnostics are sorted into source order
+//! with [`sort_diagnostics`].
+//!
+//! # Examples
+//! ```
+//! use grammarsmith::diagnostics::*;
+//! use grammarsmith::position::Span;
+//! use grammarsmith::source::SourceFile;
+//!
+//! let file = SourceFile::new("tests\\input.lang", "let = 1;");
+//! let diagnostics =
+//!     [Diagnostic::error("expected a name").with_label(Span::new(4, 5).unwrap(), "")];
+//!
+//! assert_eq!(
+//!     snapshot(&diagnostics, &file),
+//!     "error: expected a name\n \
+//!      --> tests/input.lang:1:5\n  \
+//!      |\n\
+//!      1 | let = 1;\n  \
+//!      |     ^\n"
+//! );
+//! ```
+
+use super::{sort_diagnostics, Diagnostic, TerminalRenderer};
+use crate::position::ColumnMode;
+use crate::source::{FileId, SourceFile, SourceMap};
+use std::fmt;
+
+fn renderer() -> TerminalRenderer {
+    TerminalRenderer::new()
+        .with_color(false)
+        .with_column_mode(ColumnMode::Char)
+        .with_tab_width(4)
+}
+
+fn normalize(file: &SourceFile) -> SourceFile {
+    let normalized = SourceFile::new(file.name().replace('\\', "/"), file.source());
+    match file.remapper() {
+        Some(remapper) => normalized.with_remapper(remapper.clone()),
+        None => normalized,
+    }
+}
+
+/// Renders diagnostics whose spans refer to `file` for a snapshot, separated
+/// by empty lines.
+pub fn snapshot(diagnostics: &[Diagnostic], file: &SourceFile) -> String {
+    let mut map = SourceMap::new();
+    let id = map.add_file(file.clone());
+    let paired: Vec<_> = diagnostics
+        .iter()
+        .map(|diagnostic| (id, diagnostic.clone()))
+        .collect();
+    snapshot_in(&paired, &map)
+}
+
+/// Renders diagnostics, each paired with the file of `map` it is reported
+/// for, for a snapshot, separated by empty lines.
+pub fn snapshot_in(diagnostics: &[(FileId, Diagnostic)], map: &SourceMap) -> String {
+    let mut normalized = SourceMap::new();
+    for (_, file) in map.iter() {
+        normalized.add_file(normalize(file));
+    }
+    let mut sorted = diagnostics.to_vec();
+    sort_diagnostics(&mut sorted, &normalized);
+
+    let renderer = renderer();
+    sorted
+        .iter()
+        .map(|(file, diagnostic)| renderer.render_in(diagnostic, *file, &normalized))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Renders the result of a parse for a snapshot: the pretty-printed `Debug`
+/// form of the tree, followed by the diagnostics, if any.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::diagnostics::*;
+/// use grammarsmith::source::SourceFile;
+///
+/// let file = SourceFile::new("input", "");
+/// let tree = vec!["statement"];
+/// assert_eq!(
+///     snapshot_parse(&tree, &[Diagnostic::warning("empty")], &file),
+///     "[\n    \"statement\",\n]\n\n--- diagnostics ---\nwarning: empty\n"
+/// );
+/// ```
+pub fn snapshot_parse(
+    tree: &impl fmt::Debug,
+    diagnostics: &[Diagnostic],
+    file: &SourceFile,
+) -> String {
+    let mut out = format!("{:#?}\n", tree);
+    if !diagnostics.is_empty() {
+        out.push_str("\n--- diagnostics ---\n");
+        out.push_str(&snapshot(diagnostics, file));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::position::Span;
+
+    #[test]
+    fn test_order_and_separation() {
+        let file = SourceFile::new("a.lang", "x y");
+        let diagnostics = [
+            Diagnostic::warning("second").with_label(Span::new(2, 3).unwrap(), ""),
+            Diagnostic::error("first").with_label(Span::new(0, 1).unwrap(), "here"),
+        ];
+        assert_eq!(
+            snapshot(&diagnostics, &file),
+            "error: first\n \
+             --> a.lang:1:1\n  \
+             |\n\
+             1 | x y\n  \
+             | ^ here\n\
+             \n\
+             warning: second\n \
+             --> a.lang:1:3\n  \
+             |\n\
+             1 | x y\n  \
+             |   ^\n"
+        );
+    }
+
+    #[test]
+    fn test_no_diagnostics() {
+        let file = SourceFile::new("a.lang", "");
+        assert_eq!(snapshot(&[], &file), "");
+        assert_eq!(snapshot_parse(&(), &[], &file), "()\n");
+    }
+}
@@ -0,0 +1,146 @@
+use super::Diagnostic;
+use crate::source::{FileId, SourceMap};
+use std::cmp::Reverse;
+
+/// Returns the file and span a diagnostic is located at: its primary label,
+/// or the file it is reported for if it has none.
+fn location(file: FileId, diagnostic: &Diagnostic) -> (FileId, Option<(usize, usize)>) {
+    match diagnostic.primary_label() {
+        Some(label) => (
+            label.file.unwrap_or(file),
+            Some((label.span.start(), label.span.end())),
+        ),
+        None => (file, None),
+    }
+}
+
+/// Sorts diagnostics into source order.
+///
+/// Each diagnostic is paired with the file it is reported for, and is
+/// located at its primary label, which may point into another file.
+/// Diagnostics are ordered by the name of that file in `map`, then by the
+/// position of the primary label, with diagnostics without one first, and
+/// finally by severity, most severe first. The sort is stable, so
+/// diagnostics that tie keep the order they were emitted in.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::diagnostics::*;
+/// use grammarsmith::position::Span;
+/// use grammarsmith::source::SourceMap;
+///
+/// let mut map = SourceMap::new();
+/// let b = map.add("b.lang", "x y");
+/// let a = map.add("a.lang", "x y");
+/// let at = |start, message| {
+///     Diagnostic::error(message).with_label(Span::new(start, start + 1).unwrap(), "")
+/// };
+///
+/// let mut diagnostics = vec![(b, at(0, "b")), (a, at(2, "a2")), (a, at(0, "a0"))];
+/// sort_diagnostics(&mut diagnostics, &map);
+///
+/// let messages: Vec<_> = diagnostics.iter().map(|(_, d)| d.message.as_str()).collect();
+/// assert_eq!(messages, ["a0", "a2", "b"]);
+/// ```
+pub fn sort_diagnostics(diagnostics: &mut [(FileId, Diagnostic)], map: &SourceMap) {
+    diagnostics.sort_by_cached_key(|(file, diagnostic)| {
+        let (file, span) = location(*file, diagnostic);
+        let name = map.get(file).map(|source| source.name().to_string());
+        (name, file, span, Reverse(diagnostic.severity))
+    });
+}
+
+/// Sorts diagnostics with [`sort_diagnostics`] and groups them by the file
+/// they are located in, in the same order.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::diagnostics::*;
+/// use grammarsmith::source::SourceMap;
+///
+/// let mut map = SourceMap::new();
+/// let a = map.add("a.lang", "");
+/// let b = map.add("b.lang", "");
+///
+/// let groups = group_by_file(
+///     vec![
+///         (b, Diagnostic::error("one")),
+///         (a, Diagnostic::warning("two")),
+///         (b, Diagnostic::error("three")),
+///     ],
+///     &map,
+/// );
+/// assert_eq!(groups.len(), 2);
+/// assert_eq!(groups[0].0, a);
+/// assert_eq!(groups[1].1.len(), 2);
+/// ```
+pub fn group_by_file(
+    mut diagnostics: Vec<(FileId, Diagnostic)>,
+    map: &SourceMap,
+) -> Vec<(FileId, Vec<Diagnostic>)> {
+    sort_diagnostics(&mut diagnostics, map);
+    let mut groups: Vec<(FileId, Vec<Diagnostic>)> = Vec::new();
+    for (file, diagnostic) in diagnostics {
+        let (file, _) = location(file, &diagnostic);
+        match groups.last_mut() {
+            Some((last, group)) if *last == file => group.push(diagnostic),
+            _ => groups.push((file, vec![diagnostic])),
+        }
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::Severity;
+    use crate::position::Span;
+    use crate::source::FileSpan;
+
+    fn messages(diagnostics: &[(FileId, Diagnostic)]) -> Vec<&str> {
+        diagnostics
+            .iter()
+            .map(|(_, diagnostic)| diagnostic.message.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_position_then_severity() {
+        let map = SourceMap::new();
+        let file = FileId(0);
+        let span = Span::new(3, 4).unwrap();
+        let mut diagnostics = vec![
+            (file, Diagnostic::warning("warning").with_label(span, "")),
+            (
+                file,
+                Diagnostic::error("later").with_label(Span::new(5, 6).unwrap(), ""),
+            ),
+            (file, Diagnostic::error("error").with_label(span, "")),
+            (file, Diagnostic::note("file")),
+            (file, Diagnostic::error("error again").with_label(span, "")),
+        ];
+        sort_diagnostics(&mut diagnostics, &map);
+        assert_eq!(
+            messages(&diagnostics),
+            ["file", "error", "error again", "warning", "later"]
+        );
+        assert_eq!(diagnostics[4].1.severity, Severity::Error);
+    }
+
+    #[test]
+    fn test_labels_in_other_files() {
+        let mut map = SourceMap::new();
+        let main = map.add("main.lang", "");
+        let lib = map.add("lib.lang", "");
+        let imported = Diagnostic::error("imported")
+            .with_label_in(FileSpan::new(lib, Span::new(0, 1).unwrap()), "");
+
+        let groups = group_by_file(
+            vec![(main, Diagnostic::error("main")), (main, imported)],
+            &map,
+        );
+        assert_eq!(groups[0].0, lib);
+        assert_eq!(groups[0].1[0].message, "imported");
+        assert_eq!(groups[1].0, main);
+    }
+}
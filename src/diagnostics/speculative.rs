@@ -0,0 +1,180 @@
+use super::{Diagnostic, DiagnosticSink};
+use std::fmt;
+
+/// A sink for speculative parsing that formats diagnostics only once they
+/// are known to survive.
+///
+/// Diagnostics are emitted as closures with
+/// [`defer`](SpeculativeSink::defer). Outside of any checkpoint they
+/// are built and passed on right away. Inside one they are held back:
+/// [`rewind`](SpeculativeSink::rewind) drops those emitted since the last
+/// checkpoint without ever calling them, and
+/// [`unwind`](SpeculativeSink::unwind) keeps them. Calling these alongside
+/// [`Parser::checkpoint`](crate::parser::Parser::checkpoint),
+/// [`Parser::rewind`](crate::parser::Parser::rewind) and
+/// [`Parser::unwind`](crate::parser::Parser::unwind) means a grammar that
+/// tries several alternatives never pays for formatting the errors of the
+/// ones it abandons. The [`Parser`](crate::parser::Parser) methods that
+/// write their own messages, such as
+/// [`expect_one_of`](crate::parser::Parser::expect_one_of), emit them
+/// lazily through [`DiagnosticSink::emit_lazy`].
+///
+/// # Examples
+/// ```
+/// use grammarsmith::diagnostics::*;
+///
+/// let mut sink = SpeculativeSink::new(Vec::new());
+/// sink.checkpoint();
+/// sink.defer(|| Diagnostic::error(format!("expected {}", "a type")));
+/// sink.rewind();
+///
+/// sink.checkpoint();
+/// sink.defer(|| Diagnostic::error(format!("expected {}", "an expression")));
+/// sink.unwind();
+///
+/// let diagnostics = sink.into_inner();
+/// assert_eq!(diagnostics.len(), 1);
+/// assert_eq!(diagnostics[0].message, "expected an expression");
+/// ```
+pub struct SpeculativeSink<'a, D> {
+    inner: D,
+    pending: Vec<Box<dyn FnOnce() -> Diagnostic + 'a>>,
+    checkpoints: Vec<usize>,
+}
+
+impl<'a, D: DiagnosticSink> SpeculativeSink<'a, D> {
+    pub fn new(inner: D) -> Self {
+        SpeculativeSink {
+            inner,
+            pending: Vec::new(),
+            checkpoints: Vec::new(),
+        }
+    }
+
+    /// Emits the diagnostic built by `make`, calling it only if the
+    /// diagnostic is not rewound. Unlike with
+    /// [`DiagnosticSink::emit_lazy`], `make` can borrow data that outlives
+    /// the sink.
+    pub fn defer(&mut self, make: impl FnOnce() -> Diagnostic + 'a) {
+        if self.checkpoints.is_empty() {
+            self.inner.emit(make());
+        } else {
+            self.pending.push(Box::new(make));
+        }
+    }
+
+    /// Starts holding back diagnostics until the matching
+    /// [`rewind`](SpeculativeSink::rewind) or
+    /// [`unwind`](SpeculativeSink::unwind).
+    pub fn checkpoint(&mut self) {
+        self.checkpoints.push(self.pending.len());
+    }
+
+    /// Drops the diagnostics emitted since the last checkpoint, and the
+    /// checkpoint itself.
+    ///
+    /// If there are no checkpoints, this method does nothing.
+    pub fn rewind(&mut self) {
+        if let Some(len) = self.checkpoints.pop() {
+            self.pending.truncate(len);
+        }
+    }
+
+    /// Drops the last checkpoint, keeping the diagnostics emitted since. If
+    /// it was the outermost checkpoint, they are built and passed on.
+    ///
+    /// If there are no checkpoints, this method does nothing.
+    pub fn unwind(&mut self) {
+        if self.checkpoints.pop().is_some() && self.checkpoints.is_empty() {
+            self.flush();
+        }
+    }
+
+    /// Returns the number of diagnostics held back by checkpoints.
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Returns the wrapped sink, after passing on the diagnostics still held
+    /// back as if every checkpoint was unwound.
+    pub fn into_inner(mut self) -> D {
+        self.flush();
+        self.inner
+    }
+
+    fn flush(&mut self) {
+        for make in self.pending.drain(..) {
+            self.inner.emit(make());
+        }
+    }
+}
+
+impl<D: DiagnosticSink> DiagnosticSink for SpeculativeSink<'_, D> {
+    fn emit(&mut self, diagnostic: Diagnostic) {
+        self.defer(move || diagnostic);
+    }
+
+    fn emit_lazy(&mut self, make: Box<dyn FnOnce() -> Diagnostic>) {
+        self.defer(make);
+    }
+}
+
+impl<D: fmt::Debug> fmt::Debug for SpeculativeSink<'_, D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SpeculativeSink")
+            .field("inner", &self.inner)
+            .field("pending", &self.pending.len())
+            .field("checkpoints", &self.checkpoints)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[test]
+    fn test_rewound_messages_are_not_formatted() {
+        let calls = Cell::new(0);
+        let make = |message: &'static str| {
+            let calls = &calls;
+            move || {
+                calls.set(calls.get() + 1);
+                Diagnostic::error(message)
+            }
+        };
+
+        let mut sink = SpeculativeSink::new(Vec::new());
+        sink.defer(make("before"));
+        sink.checkpoint();
+        sink.defer(make("kept"));
+        sink.checkpoint();
+        sink.defer(make("rewound"));
+        sink.rewind();
+        assert_eq!(sink.pending(), 1);
+        sink.unwind();
+        sink.emit(Diagnostic::error("after"));
+
+        let messages: Vec<_> = sink
+            .into_inner()
+            .into_iter()
+            .map(|diagnostic| diagnostic.message)
+            .collect();
+        assert_eq!(messages, ["before", "kept", "after"]);
+        assert_eq!(calls.get(), 2);
+    }
+
+    #[test]
+    fn test_nested_unwind_keeps_holding() {
+        let mut sink = SpeculativeSink::new(Vec::new());
+        sink.checkpoint();
+        sink.checkpoint();
+        sink.emit(Diagnostic::error("inner"));
+        sink.unwind();
+        assert_eq!(sink.pending(), 1);
+        sink.rewind();
+        assert_eq!(sink.pending(), 0);
+        assert!(sink.into_inner().is_empty());
+    }
+}
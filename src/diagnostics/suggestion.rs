@@ -0,0 +1,206 @@
+use super::Diagnostic;
+use crate::position::{apply_edits, sort_edits, Span, TextEdit};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// How confident a [`Suggestion`] is that its edits are correct.
+///
+/// Mirrors rustc's applicability levels. Only
+/// [`MachineApplicable`](Applicability::MachineApplicable) suggestions are
+/// applied by [`apply_suggestions`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum Applicability {
+    /// The edits are definitely what the user intended and can be applied
+    /// without review.
+    MachineApplicable,
+    /// The edits may be what the user intended, but should be reviewed.
+    MaybeIncorrect,
+    /// The edits contain placeholders such as `<type>` that have to be
+    /// filled in.
+    HasPlaceholders,
+    /// Nothing is known about the edits.
+    Unspecified,
+}
+
+impl fmt::Display for Applicability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Applicability::MachineApplicable => "machine-applicable",
+            Applicability::MaybeIncorrect => "maybe-incorrect",
+            Applicability::HasPlaceholders => "has-placeholders",
+            Applicability::Unspecified => "unspecified",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A proposed fix for a diagnostic, made of one or more edits to the source.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::diagnostics::*;
+/// use grammarsmith::position::*;
+///
+/// let fix = Suggestion::replace(
+///     "use `==` to compare",
+///     Span::new(5, 6).unwrap(),
+///     "==",
+///     Applicability::MachineApplicable,
+/// );
+/// assert_eq!(fix.edits, [TextEdit::new(Span::new(5, 6).unwrap(), "==")]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Suggestion {
+    pub message: String,
+    pub edits: Vec<TextEdit>,
+    pub applicability: Applicability,
+}
+
+impl Suggestion {
+    pub fn new(
+        message: impl Into<String>,
+        edits: Vec<TextEdit>,
+        applicability: Applicability,
+    ) -> Self {
+        Suggestion {
+            message: message.into(),
+            edits,
+            applicability,
+        }
+    }
+
+    /// Creates a suggestion replacing the text in `span` with `new_text`.
+    pub fn replace(
+        message: impl Into<String>,
+        span: Span,
+        new_text: impl Into<String>,
+        applicability: Applicability,
+    ) -> Self {
+        Suggestion::new(message, vec![TextEdit::new(span, new_text)], applicability)
+    }
+}
+
+/// Applies every machine-applicable suggestion of `diagnostics` to `source`.
+///
+/// Suggestions are considered in order. A suggestion is skipped as a whole
+/// if one of its edits overlaps an edit of a suggestion that was already
+/// accepted, or does not fit `source`, so the result never contains half of
+/// a fix. Running the tool again after fixing usually picks up the skipped
+/// suggestions.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::diagnostics::*;
+/// use grammarsmith::position::*;
+///
+/// let source = "let x = 1\nlet y = 2\n";
+/// let missing_semicolon = |at: usize| {
+///     Diagnostic::builder(Severity::Error, "missing `;`")
+///         .suggestion(Suggestion::new(
+///             "add `;`",
+///             vec![TextEdit::insert(BytePos::from_usize(at), ";")],
+///             Applicability::MachineApplicable,
+///         ))
+///         .build()
+/// };
+///
+/// let fixed = apply_suggestions(source, &[missing_semicolon(9), missing_semicolon(19)]);
+/// assert_eq!(fixed, "let x = 1;\nlet y = 2;\n");
+/// ```
+pub fn apply_suggestions(source: &str, diagnostics: &[Diagnostic]) -> String {
+    let mut accepted: Vec<TextEdit> = Vec::new();
+    let suggestions = diagnostics
+        .iter()
+        .flat_map(|diagnostic| &diagnostic.suggestions)
+        .filter(|suggestion| suggestion.applicability == Applicability::MachineApplicable);
+
+    for suggestion in suggestions {
+        let fits = suggestion.edits.iter().all(|edit| {
+            edit.range.end() <= source.len()
+                && source.is_char_boundary(edit.range.start())
+                && source.is_char_boundary(edit.range.end())
+        });
+        let candidate_len = accepted.len();
+        accepted.extend(suggestion.edits.iter().cloned());
+        if !fits || sort_edits(&accepted).is_err() {
+            accepted.truncate(candidate_len);
+        }
+    }
+
+    apply_edits(source, &accepted).expect("accepted edits are valid and disjoint")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::Severity;
+    use crate::position::BytePos;
+
+    fn fix(edits: Vec<TextEdit>, applicability: Applicability) -> Diagnostic {
+        Diagnostic::builder(Severity::Warning, "fixable")
+            .suggestion(Suggestion::new("fix", edits, applicability))
+            .build()
+    }
+
+    #[test]
+    fn test_overlapping_suggestion_is_skipped() {
+        let source = "a + b";
+        let diagnostics = [
+            fix(
+                vec![TextEdit::new(Span::new(0, 1).unwrap(), "x")],
+                Applicability::MachineApplicable,
+            ),
+            // Overlaps the first fix, so neither of its edits is applied.
+            fix(
+                vec![
+                    TextEdit::insert(BytePos(5), ";"),
+                    TextEdit::new(Span::new(0, 3).unwrap(), ""),
+                ],
+                Applicability::MachineApplicable,
+            ),
+            fix(
+                vec![TextEdit::new(Span::new(4, 5).unwrap(), "y")],
+                Applicability::MachineApplicable,
+            ),
+        ];
+        assert_eq!(apply_suggestions(source, &diagnostics), "x + y");
+    }
+
+    #[test]
+    fn test_only_machine_applicable() {
+        let diagnostics = [
+            fix(
+                vec![TextEdit::insert(BytePos(0), "a")],
+                Applicability::MaybeIncorrect,
+            ),
+            fix(
+                vec![TextEdit::insert(BytePos(0), "b")],
+                Applicability::HasPlaceholders,
+            ),
+            fix(
+                vec![TextEdit::insert(BytePos(0), "c")],
+                Applicability::Unspecified,
+            ),
+        ];
+        assert_eq!(apply_suggestions("x", &diagnostics), "x");
+    }
+
+    #[test]
+    fn test_invalid_suggestion_is_skipped() {
+        let diagnostics = [
+            fix(
+                vec![TextEdit::new(Span::new(0, 9).unwrap(), "")],
+                Applicability::MachineApplicable,
+            ),
+            fix(
+                vec![TextEdit::new(Span::new(0, 1).unwrap(), "")],
+                Applicability::MachineApplicable,
+            ),
+        ];
+        assert_eq!(apply_suggestions("é", &diagnostics), "é");
+    }
+}
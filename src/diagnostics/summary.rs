@@ -0,0 +1,164 @@
+use super::{Diagnostic, DiagnosticSink, Severity};
+use std::fmt;
+use std::process::ExitCode;
+
+/// Counts diagnostics by severity over a run of a tool, to decide its exit
+/// status and print a closing summary.
+///
+/// With [`promote_warnings`](DiagnosticSummary::promote_warnings), warnings
+/// are turned into errors as they pass through
+/// [`sink`](DiagnosticSummary::sink), like `-D warnings`.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::diagnostics::*;
+///
+/// let mut summary = DiagnosticSummary::new();
+/// let mut sink = summary.sink(Vec::new());
+/// Diagnostic::error("first").emit(&mut sink);
+/// Diagnostic::warning("second").emit(&mut sink);
+/// Diagnostic::error("third").emit(&mut sink);
+///
+/// assert!(summary.has_errors());
+/// assert_eq!(summary.to_string(), "2 errors, 1 warning emitted");
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DiagnosticSummary {
+    counts: [usize; 4],
+    promote_warnings: bool,
+}
+
+impl DiagnosticSummary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether warnings are reported as errors.
+    pub fn promote_warnings(mut self, promote: bool) -> Self {
+        self.promote_warnings = promote;
+        self
+    }
+
+    /// Counts a diagnostic, returning it with warnings promoted if enabled.
+    pub fn record(&mut self, mut diagnostic: Diagnostic) -> Diagnostic {
+        if self.promote_warnings && diagnostic.severity == Severity::Warning {
+            diagnostic.severity = Severity::Error;
+        }
+        self.counts[diagnostic.severity as usize] += 1;
+        diagnostic
+    }
+
+    /// Wraps `inner` in a sink that records every diagnostic in this
+    /// summary before passing it on.
+    pub fn sink<D: DiagnosticSink>(&mut self, inner: D) -> SummarySink<'_, D> {
+        SummarySink {
+            summary: self,
+            inner,
+        }
+    }
+
+    /// Returns the number of diagnostics recorded with the given severity.
+    pub fn count(&self, severity: Severity) -> usize {
+        self.counts[severity as usize]
+    }
+
+    /// Returns the number of errors, including promoted warnings.
+    pub fn errors(&self) -> usize {
+        self.count(Severity::Error)
+    }
+
+    /// Returns the number of warnings that were not promoted.
+    pub fn warnings(&self) -> usize {
+        self.count(Severity::Warning)
+    }
+
+    /// Returns true if an error was recorded.
+    pub fn has_errors(&self) -> bool {
+        self.errors() > 0
+    }
+
+    /// Returns the exit code of the run: 1 if there were errors, 0 otherwise.
+    pub fn exit_code(&self) -> u8 {
+        u8::from(self.has_errors())
+    }
+
+    /// Returns the process exit status of the run, to be returned from
+    /// `main`.
+    pub fn status(&self) -> ExitCode {
+        ExitCode::from(self.exit_code())
+    }
+}
+
+/// Formats the closing line of a run, such as "3 errors, 2 warnings
+/// emitted". Notes and help messages are not mentioned.
+impl fmt::Display for DiagnosticSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let plural = |count: usize, noun: &str| {
+            format!("{} {}{}", count, noun, if count == 1 { "" } else { "s" })
+        };
+        let parts: Vec<String> = [(self.errors(), "error"), (self.warnings(), "warning")]
+            .into_iter()
+            .filter(|&(count, _)| count > 0)
+            .map(|(count, noun)| plural(count, noun))
+            .collect();
+        if parts.is_empty() {
+            f.write_str("no errors or warnings emitted")
+        } else {
+            write!(f, "{} emitted", parts.join(", "))
+        }
+    }
+}
+
+/// A sink recording diagnostics in a [`DiagnosticSummary`], created by
+/// [`DiagnosticSummary::sink`].
+#[derive(Debug)]
+pub struct SummarySink<'s, D> {
+    summary: &'s mut DiagnosticSummary,
+    inner: D,
+}
+
+impl<D> SummarySink<'_, D> {
+    /// Returns the wrapped sink.
+    pub fn into_inner(self) -> D {
+        self.inner
+    }
+}
+
+impl<D: DiagnosticSink> DiagnosticSink for SummarySink<'_, D> {
+    fn emit(&mut self, diagnostic: Diagnostic) {
+        let diagnostic = self.summary.record(diagnostic);
+        self.inner.emit(diagnostic);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_promote_warnings() {
+        let mut summary = DiagnosticSummary::new().promote_warnings(true);
+        let mut sink = summary.sink(Vec::new());
+        Diagnostic::warning("unused").emit(&mut sink);
+        Diagnostic::note("fyi").emit(&mut sink);
+        let diagnostics = sink.into_inner();
+
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert_eq!(summary.errors(), 1);
+        assert_eq!(summary.warnings(), 0);
+        assert_eq!(summary.count(Severity::Note), 1);
+        assert_eq!(summary.exit_code(), 1);
+        assert_eq!(summary.to_string(), "1 error emitted");
+    }
+
+    #[test]
+    fn test_footer() {
+        let mut summary = DiagnosticSummary::new();
+        assert_eq!(summary.to_string(), "no errors or warnings emitted");
+        assert_eq!(summary.exit_code(), 0);
+        summary.record(Diagnostic::warning("a"));
+        summary.record(Diagnostic::warning("b"));
+        assert_eq!(summary.to_string(), "2 warnings emitted");
+        assert_eq!(summary.exit_code(), 0);
+    }
+}
@@ -0,0 +1,782 @@
+use super::{ColorChoice, Diagnostic, Label, LabelStyle, Palette, Severity};
+use crate::position::lineoffset::trim_line_terminator;
+use crate::position::ColumnMode;
+use crate::source::{FileId, SourceFile, SourceLookup};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::io::{self, IsTerminal};
+
+/// Renders diagnostics for a terminal in the style of rustc: a header, the
+/// location of the primary label, source excerpts with underlined labels,
+/// and notes.
+///
+/// Labels on the same line are drawn beneath a single copy of it, primary
+/// labels with `^` and secondary ones with `-`. When several of them have
+/// messages, all but the rightmost hang below their underlines, and lines
+/// skipped between labels are marked with `...`.
+///
+/// Tabs in excerpts are expanded to spaces so underlines line up with them
+/// whatever the terminal's tab width. With the `unicode-width` feature,
+/// underlines also account for characters wider or narrower than one
+/// column, such as East Asian wide characters, emoji and combining marks.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::diagnostics::*;
+/// use grammarsmith::position::Span;
+/// use grammarsmith::source::SourceFile;
+///
+/// let file = SourceFile::new("main.calc", "1 + * 2");
+/// let diagnostic = Diagnostic::builder(Severity::Error, "expected an expression")
+///     .code("E001")
+///     .primary(Span::new(4, 5).unwrap(), "found `*`")
+///     .note("operators need an operand on both sides")
+///     .build();
+///
+/// assert_eq!(
+///     TerminalRenderer::new().render(&diagnostic, &file),
+///     "error[E001]: expected an expression\n \
+///      --> main.calc:1:5\n  \
+///      |\n\
+///      1 | 1 + * 2\n  \
+///      |     ^ found `*`\n  \
+///      |\n  \
+///      = note: operators need an operand on both sides\n"
+/// );
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct TerminalRenderer {
+    color: bool,
+    palette: Palette,
+    column_mode: ColumnMode,
+    tab_width: usize,
+}
+
+impl Default for TerminalRenderer {
+    fn default() -> Self {
+        TerminalRenderer {
+            color: false,
+            palette: Palette::default(),
+            column_mode: ColumnMode::Char,
+            tab_width: 4,
+        }
+    }
+}
+
+impl TerminalRenderer {
+    /// Creates a renderer without colors that reports columns in characters
+    /// and expands tabs to stops every four columns.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enables or disables ANSI colors.
+    pub fn with_color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Enables ANSI colors according to `choice` for output written to
+    /// `stream`. See [`ColorChoice::use_color`].
+    ///
+    /// # Examples
+    /// ```
+    /// use grammarsmith::diagnostics::*;
+    ///
+    /// let renderer = TerminalRenderer::new().with_color_choice(ColorChoice::Auto, &std::io::stderr());
+    /// ```
+    pub fn with_color_choice(self, choice: ColorChoice, stream: &impl IsTerminal) -> Self {
+        self.with_color(choice.use_color(stream))
+    }
+
+    /// Sets the styles used when colors are enabled.
+    pub fn with_palette(mut self, palette: Palette) -> Self {
+        self.palette = palette;
+        self
+    }
+
+    /// Sets the unit columns are reported in.
+    pub fn with_column_mode(mut self, column_mode: ColumnMode) -> Self {
+        self.column_mode = column_mode;
+        self
+    }
+
+    /// Sets the distance between tab stops used to expand tabs in source
+    /// excerpts.
+    ///
+    /// # Panics
+    /// Panics if `tab_width` is zero.
+    pub fn with_tab_width(mut self, tab_width: usize) -> Self {
+        assert!(tab_width > 0, "tab width must be positive");
+        self.tab_width = tab_width;
+        self
+    }
+
+    /// Renders a diagnostic whose spans refer to `file`.
+    ///
+    /// Labels pointing into other files are left out; use
+    /// [`TerminalRenderer::render_in`] to include them.
+    pub fn render(&self, diagnostic: &Diagnostic, file: &SourceFile) -> String {
+        let mut out = String::new();
+        self.write_with(&self.ansi(), &mut out, diagnostic, file, &|_| None)
+            .unwrap();
+        out
+    }
+
+    /// Renders a diagnostic reported for the file `file` of `map`, showing
+    /// excerpts of every file its labels point into.
+    ///
+    /// Labels pointing into files that are not in `map` are left out.
+    ///
+    /// # Panics
+    /// Panics if `file` is not in `map`.
+    pub fn render_in(
+        &self,
+        diagnostic: &Diagnostic,
+        file: FileId,
+        map: &(impl SourceLookup + ?Sized),
+    ) -> String {
+        let main = map.file(file).expect("file is not in the source map");
+        let mut out = String::new();
+        self.write_with(&self.ansi(), &mut out, diagnostic, main, &|id| map.file(id))
+            .unwrap();
+        out
+    }
+
+    /// Renders a diagnostic whose spans refer to `file` into `writer`.
+    pub fn emit(
+        &self,
+        writer: &mut impl io::Write,
+        diagnostic: &Diagnostic,
+        file: &SourceFile,
+    ) -> io::Result<()> {
+        writer.write_all(self.render(diagnostic, file).as_bytes())
+    }
+
+    fn ansi(&self) -> Ansi<'_> {
+        Ansi {
+            color: self.color,
+            palette: &self.palette,
+        }
+    }
+
+    /// Lays out a diagnostic, marking up each piece of text with `markup`.
+    pub(crate) fn write_with<'a>(
+        &self,
+        markup: &dyn Markup,
+        out: &mut String,
+        diagnostic: &Diagnostic,
+        file: &'a SourceFile,
+        other: &dyn Fn(FileId) -> Option<&'a SourceFile>,
+    ) -> std::fmt::Result {
+        let severity_role = Role::Severity(diagnostic.severity);
+        let header = match &diagnostic.code {
+            Some(code) => format!("{}[{}]", diagnostic.severity, code),
+            None => diagnostic.severity.to_string(),
+        };
+        writeln!(
+            out,
+            "{}{}",
+            markup.paint(severity_role, &header),
+            markup.paint(Role::Emphasis, &format!(": {}", diagnostic.message))
+        )?;
+
+        // Group the labels by file, keeping the file the diagnostic is
+        // reported for first and the others in order of appearance.
+        let mut groups: Vec<(Option<FileId>, &SourceFile, Vec<&Label>)> = Vec::new();
+        for label in &diagnostic.labels {
+            if let Some(group) = groups.iter_mut().find(|group| group.0 == label.file) {
+                group.2.push(label);
+                continue;
+            }
+            let source = match label.file {
+                None => file,
+                Some(id) => match other(id) {
+                    Some(source) => source,
+                    None => continue,
+                },
+            };
+            groups.push((label.file, source, vec![label]));
+        }
+        groups.sort_by_key(|group| group.0.is_some());
+        for (_, _, labels) in &mut groups {
+            labels.sort_by_key(|label| (label.span.start, label.span.end));
+        }
+
+        let gutter = groups
+            .iter()
+            .flat_map(|(_, source, labels)| {
+                labels.iter().map(|label| {
+                    source
+                        .line_offsets()
+                        .lines_of(label.span)
+                        .last()
+                        .map_or(1, |(line, _)| line)
+                })
+            })
+            .max()
+            .unwrap_or(1)
+            .to_string()
+            .len();
+        let empty_gutter = markup.paint(Role::Gutter, &format!("{:gutter$} |", ""));
+
+        for (i, (_, source, labels)) in groups.iter().enumerate() {
+            let span = labels
+                .iter()
+                .find(|label| label.style == LabelStyle::Primary)
+                .unwrap_or(&labels[0])
+                .span;
+            let (name, line, column) = source.location(span.start, self.column_mode);
+            if i > 0 {
+                writeln!(out, "{}", empty_gutter)?;
+            }
+            writeln!(
+                out,
+                "{:gutter$}{} {}",
+                "",
+                markup.paint(Role::Gutter, if i == 0 { "-->" } else { ":::" }),
+                markup.paint(Role::Plain, &format!("{}:{}:{}", name, line, column))
+            )?;
+            writeln!(out, "{}", empty_gutter)?;
+            self.write_labels(markup, out, source, labels, severity_role, gutter)?;
+        }
+
+        if !diagnostic.notes.is_empty() || !diagnostic.suggestions.is_empty() {
+            writeln!(out, "{}", empty_gutter)?;
+        }
+        for note in &diagnostic.notes {
+            writeln!(
+                out,
+                "{:gutter$} {} {} {}",
+                "",
+                markup.paint(Role::Gutter, "="),
+                markup.paint(Role::Emphasis, "note:"),
+                markup.paint(Role::Plain, note)
+            )?;
+        }
+        for suggestion in &diagnostic.suggestions {
+            writeln!(
+                out,
+                "{:gutter$} {} {} {}",
+                "",
+                markup.paint(Role::Gutter, "="),
+                markup.paint(Role::Emphasis, "help:"),
+                markup.paint(Role::Plain, &suggestion.message)
+            )?;
+        }
+        Ok(())
+    }
+
+    fn write_labels(
+        &self,
+        markup: &dyn Markup,
+        out: &mut String,
+        source: &SourceFile,
+        labels: &[&Label],
+        severity_role: Role,
+        gutter: usize,
+    ) -> std::fmt::Result {
+        let offsets = source.line_offsets();
+        let empty_gutter = markup.paint(Role::Gutter, &format!("{:gutter$} |", ""));
+
+        // Split every label into the parts on each line it covers, so that
+        // labels sharing a line are drawn beneath a single copy of it.
+        let mut lines: BTreeMap<usize, Vec<Segment>> = BTreeMap::new();
+        for label in labels {
+            let (marker, role) = match label.style {
+                LabelStyle::Primary => ('^', severity_role),
+                LabelStyle::Secondary => ('-', Role::Secondary),
+            };
+            let portions: Vec<_> = offsets.lines_of(label.span).collect();
+            for (i, &(line, portion)) in portions.iter().enumerate() {
+                let line_span = offsets.line_span(line).unwrap();
+                let line_text = source.slice(line_span);
+                let start = self.advance(0, &line_text[..portion.start() - line_span.start()]);
+                let end = self.advance(start, trim_line_terminator(source.slice(portion)));
+                lines.entry(line).or_default().push(Segment {
+                    start,
+                    end: end.max(start + 1),
+                    primary: label.style == LabelStyle::Primary,
+                    marker,
+                    role,
+                    message: (i + 1 == portions.len() && !label.message.is_empty())
+                        .then_some(label.message.as_str()),
+                });
+            }
+        }
+
+        let mut previous = None;
+        for (line, mut segments) in lines {
+            if previous.is_some_and(|previous| line > previous + 1) {
+                writeln!(out, "{}", markup.paint(Role::Gutter, "..."))?;
+            }
+            previous = Some(line);
+
+            let line_text = trim_line_terminator(source.slice(offsets.line_span(line).unwrap()));
+            writeln!(
+                out,
+                "{} {}",
+                markup.paint(Role::Gutter, &format!("{:>gutter$} |", line)),
+                markup.paint(Role::Plain, &self.expand_tabs(line_text))
+            )?;
+            segments.sort_by_key(|segment| (segment.start, segment.end));
+            for row in segment_rows(&segments) {
+                writeln!(out, "{} {}", empty_gutter, paint_row(markup, &row))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the display column reached by writing `text` from `column`.
+    fn advance(&self, column: usize, text: &str) -> usize {
+        text.chars().fold(column, |column, ch| match ch {
+            '\t' => (column / self.tab_width + 1) * self.tab_width,
+            ch => column + char_width(ch),
+        })
+    }
+
+    fn expand_tabs(&self, text: &str) -> String {
+        let mut expanded = String::with_capacity(text.len());
+        let mut column = 0;
+        for ch in text.chars() {
+            let next = self.advance(column, ch.encode_utf8(&mut [0; 4]));
+            if ch == '\t' {
+                expanded.push_str(&" ".repeat(next - column));
+            } else {
+                expanded.push(ch);
+            }
+            column = next;
+        }
+        expanded
+    }
+}
+
+/// The part a piece of text plays in a rendered diagnostic, which decides
+/// how it is styled.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Role {
+    /// Source code, file names and messages.
+    Plain,
+    /// Headers and primary labels.
+    Severity(Severity),
+    /// Secondary labels.
+    Secondary,
+    /// Line numbers, separators and location arrows.
+    Gutter,
+    /// The message and the `note:` and `help:` headings.
+    Emphasis,
+}
+
+/// Turns laid out text into output, such as ANSI-colored or HTML text.
+pub(crate) trait Markup {
+    fn paint(&self, role: Role, text: &str) -> String;
+}
+
+/// Styles text with the ANSI escape sequences of a [`Palette`].
+struct Ansi<'a> {
+    color: bool,
+    palette: &'a Palette,
+}
+
+impl Markup for Ansi<'_> {
+    fn paint(&self, role: Role, text: &str) -> String {
+        let style = match role {
+            Role::Plain => return text.to_string(),
+            Role::Severity(severity) => self.palette.severity(severity),
+            Role::Secondary => self.palette.secondary,
+            Role::Gutter => self.palette.gutter,
+            Role::Emphasis => self.palette.emphasis,
+        };
+        if self.color && !text.is_empty() {
+            format!("{}{}{}", style, text, self.palette.reset)
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+fn paint_row(markup: &dyn Markup, row: &[(char, Role)]) -> String {
+    let mut out = String::new();
+    let mut rest = row;
+    while let Some(&(_, role)) = rest.first() {
+        let run = rest.iter().take_while(|cell| cell.1 == role).count();
+        let text: String = rest[..run].iter().map(|cell| cell.0).collect();
+        out.push_str(&markup.paint(role, &text));
+        rest = &rest[run..];
+    }
+    out
+}
+
+/// The number of columns `ch` takes up in a terminal.
+#[cfg(feature = "unicode-width")]
+fn char_width(ch: char) -> usize {
+    unicode_width::UnicodeWidthChar::width(ch).unwrap_or(0)
+}
+
+/// The number of columns `ch` takes up in a terminal.
+#[cfg(not(feature = "unicode-width"))]
+fn char_width(_: char) -> usize {
+    1
+}
+
+/// The part of a label on one source line, in display columns.
+struct Segment<'a> {
+    start: usize,
+    end: usize,
+    primary: bool,
+    marker: char,
+    role: Role,
+    message: Option<&'a str>,
+}
+
+/// Lays out the underlines and messages of the labels on one line, sorted
+/// by position, as rows of styled characters.
+///
+/// All underlines share the first row, with primary labels drawn over
+/// secondary ones where they overlap. The message of the rightmost label
+/// follows the underlines if no other underline extends past it. The other
+/// messages hang below their underlines, connected by `|`, rightmost first,
+/// so no message ever crosses a connector.
+fn segment_rows(segments: &[Segment]) -> Vec<Vec<(char, Role)>> {
+    let put = |row: &mut Vec<(char, Role)>, column: usize, text: &str, role: Role| {
+        for (i, ch) in text.chars().enumerate() {
+            if row.len() <= column + i {
+                row.resize(column + i + 1, (' ', Role::Plain));
+            }
+            row[column + i] = (ch, role);
+        }
+    };
+
+    let mut underlines = Vec::new();
+    for primary in [false, true] {
+        for segment in segments.iter().filter(|segment| segment.primary == primary) {
+            let underline = segment
+                .marker
+                .to_string()
+                .repeat(segment.end - segment.start);
+            put(&mut underlines, segment.start, &underline, segment.role);
+        }
+    }
+
+    let (last, others) = segments
+        .split_last()
+        .expect("a line has at least one label");
+    let inline = last.message.is_some() && others.iter().all(|other| other.end <= last.end);
+    if inline {
+        put(
+            &mut underlines,
+            last.end + 1,
+            last.message.unwrap(),
+            last.role,
+        );
+    }
+    let hanging: Vec<_> = segments[..segments.len() - usize::from(inline)]
+        .iter()
+        .filter(|segment| segment.message.is_some())
+        .collect();
+
+    let mut rows = vec![underlines];
+    if hanging.is_empty() {
+        return rows;
+    }
+    let mut connectors = Vec::new();
+    for segment in &hanging {
+        put(&mut connectors, segment.start, "|", segment.role);
+    }
+    rows.push(connectors);
+    for (i, segment) in hanging.iter().enumerate().rev() {
+        let mut row = Vec::new();
+        for above in &hanging[..i] {
+            put(&mut row, above.start, "|", above.role);
+        }
+        put(
+            &mut row,
+            segment.start,
+            segment.message.unwrap(),
+            segment.role,
+        );
+        rows.push(row);
+    }
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::{Applicability, Severity, Suggestion};
+    use crate::position::{BytePos, PositionRemapper, Span};
+    use crate::source::{FileSpan, SourceMap};
+
+    fn span(start: usize, end: usize) -> Span {
+        Span::new(start, end).unwrap()
+    }
+
+    #[test]
+    fn test_secondary_labels_and_multiline() {
+        let file = SourceFile::new("test", "let x: i32 = {\n  \"five\"\n};\n");
+        let diagnostic = Diagnostic::builder(Severity::Error, "mismatched types")
+            .secondary(span(7, 10), "expected due to this")
+            .primary(span(13, 25), "expected `i32`")
+            .build();
+
+        assert_eq!(
+            TerminalRenderer::new().render(&diagnostic, &file),
+            "error: mismatched types\n \
+             --> test:1:14\n  \
+             |\n\
+             1 | let x: i32 = {\n  \
+             |        ---   ^\n  \
+             |        |\n  \
+             |        expected due to this\n\
+             2 |   \"five\"\n  \
+             | ^^^^^^^^\n\
+             3 | };\n  \
+             | ^ expected `i32`\n"
+        );
+    }
+
+    #[test]
+    fn test_labels_on_one_line() {
+        let file = SourceFile::new("test", "let x: i32 = \"five\";");
+        let diagnostic = Diagnostic::builder(Severity::Error, "mismatched types")
+            .primary(span(13, 19), "expected `i32`, found `&str`")
+            .secondary(span(7, 10), "expected due to this")
+            .secondary(span(4, 5), "")
+            .build();
+
+        assert_eq!(
+            TerminalRenderer::new().render(&diagnostic, &file),
+            "error: mismatched types\n \
+             --> test:1:14\n  \
+             |\n\
+             1 | let x: i32 = \"five\";\n  \
+             |     -  ---   ^^^^^^ expected `i32`, found `&str`\n  \
+             |        |\n  \
+             |        expected due to this\n"
+        );
+    }
+
+    #[test]
+    fn test_overlapping_labels() {
+        let file = SourceFile::new("test", "f(a, b)");
+        let diagnostic = Diagnostic::builder(Severity::Error, "wrong argument")
+            .secondary(span(0, 7), "in this call")
+            .primary(span(2, 3), "this argument")
+            .secondary(span(5, 6), "and this one")
+            .build();
+
+        // Primary underlines win where labels overlap, and each message
+        // hangs below its own underline.
+        assert_eq!(
+            TerminalRenderer::new().render(&diagnostic, &file),
+            "error: wrong argument\n \
+             --> test:1:3\n  \
+             |\n\
+             1 | f(a, b)\n  \
+             | --^----\n  \
+             | | |  |\n  \
+             | | |  and this one\n  \
+             | | this argument\n  \
+             | in this call\n"
+        );
+    }
+
+    #[test]
+    fn test_distant_lines() {
+        let file = SourceFile::new("test", "a\nb\nc\nd\n");
+        let diagnostic = Diagnostic::builder(Severity::Warning, "unused")
+            .primary(span(0, 1), "declared here")
+            .secondary(span(2, 3), "")
+            .secondary(span(6, 7), "last use")
+            .build();
+
+        assert_eq!(
+            TerminalRenderer::new().render(&diagnostic, &file),
+            "warning: unused\n \
+             --> test:1:1\n  \
+             |\n\
+             1 | a\n  \
+             | ^ declared here\n\
+             2 | b\n  \
+             | -\n\
+             ...\n\
+             4 | d\n  \
+             | - last use\n"
+        );
+    }
+
+    #[test]
+    fn test_tabs() {
+        let file = SourceFile::new("test", "\tif\tx");
+        let diagnostic = Diagnostic::builder(Severity::Error, "bad")
+            .primary(span(3, 5), "here")
+            .build();
+
+        assert_eq!(
+            TerminalRenderer::new().render(&diagnostic, &file),
+            "error: bad\n \
+             --> test:1:4\n  \
+             |\n\
+             1 |     if  x\n  \
+             |       ^^^ here\n"
+        );
+        assert_eq!(
+            TerminalRenderer::new()
+                .with_tab_width(8)
+                .render(&diagnostic, &file),
+            "error: bad\n \
+             --> test:1:4\n  \
+             |\n\
+             1 |         if      x\n  \
+             |           ^^^^^^^ here\n"
+        );
+    }
+
+    #[cfg(feature = "unicode-width")]
+    #[test]
+    fn test_wide_characters() {
+        let file = SourceFile::new("test", "名前 = 🦀;");
+        let diagnostic = Diagnostic::builder(Severity::Error, "not a value")
+            .secondary(span(0, 6), "name")
+            .primary(span(9, 13), "")
+            .build();
+
+        assert_eq!(
+            TerminalRenderer::new().render(&diagnostic, &file),
+            "error: not a value\n \
+             --> test:1:6\n  \
+             |\n\
+             1 | 名前 = 🦀;\n  \
+             | ----   ^^\n  \
+             | |\n  \
+             | name\n"
+        );
+    }
+
+    #[test]
+    fn test_no_labels() {
+        let file = SourceFile::new("test", "");
+        let diagnostic = Diagnostic::new(Severity::Warning, "empty input");
+        assert_eq!(
+            TerminalRenderer::new().render(&diagnostic, &file),
+            "warning: empty input\n"
+        );
+    }
+
+    #[test]
+    fn test_other_files() {
+        let mut map = SourceMap::new();
+        let main = map.add("main.lang", "use lib;\nfn f() {}\n");
+        let lib = map.add("lib.lang", "fn f() {}\n");
+        let diagnostic = Diagnostic::builder(Severity::Error, "`f` is defined twice")
+            .secondary_in(FileSpan::new(lib, span(0, 6)), "first defined here")
+            .primary(span(9, 15), "redefined here")
+            .build();
+
+        let renderer = TerminalRenderer::new();
+        assert_eq!(
+            renderer.render_in(&diagnostic, main, &map),
+            "error: `f` is defined twice\n \
+             --> main.lang:2:1\n  \
+             |\n\
+             2 | fn f() {}\n  \
+             | ^^^^^^ redefined here\n  \
+             |\n \
+             ::: lib.lang:1:1\n  \
+             |\n\
+             1 | fn f() {}\n  \
+             | ------ first defined here\n"
+        );
+        assert_eq!(
+            renderer.render(&diagnostic, map.get(main).unwrap()),
+            "error: `f` is defined twice\n \
+             --> main.lang:2:1\n  \
+             |\n\
+             2 | fn f() {}\n  \
+             | ^^^^^^ redefined here\n"
+        );
+    }
+
+    #[test]
+    fn test_remapped() {
+        let mut remapper = PositionRemapper::new();
+        remapper.add(BytePos(18), "main.c", 10);
+        let file = SourceFile::new("gen.c", "#line 10 \"main.c\"\nint x\n").with_remapper(remapper);
+        let diagnostic = Diagnostic::builder(Severity::Error, "missing `;`")
+            .primary(span(22, 23), "")
+            .build();
+        // The header points into the original file; the snippet shows the
+        // text that was parsed.
+        assert_eq!(
+            TerminalRenderer::new().render(&diagnostic, &file),
+            "error: missing `;`\n \
+             --> main.c:10:5\n  \
+             |\n\
+             2 | int x\n  \
+             |     ^\n"
+        );
+    }
+
+    #[test]
+    fn test_suggestions() {
+        let file = SourceFile::new("test", "x = 1");
+        let diagnostic = Diagnostic::builder(Severity::Error, "missing `;`")
+            .primary(span(5, 5), "")
+            .suggestion(Suggestion::replace(
+                "add `;`",
+                span(5, 5),
+                ";",
+                Applicability::MachineApplicable,
+            ))
+            .build();
+        assert_eq!(
+            TerminalRenderer::new().render(&diagnostic, &file),
+            "error: missing `;`\n \
+             --> test:1:6\n  \
+             |\n\
+             1 | x = 1\n  \
+             |      ^\n  \
+             |\n  \
+             = help: add `;`\n"
+        );
+    }
+
+    #[test]
+    fn test_color() {
+        let file = SourceFile::new("test", "x");
+        let diagnostic = Diagnostic::builder(Severity::Error, "bad")
+            .primary(span(0, 1), "")
+            .build();
+        let rendered = TerminalRenderer::new()
+            .with_color(true)
+            .render(&diagnostic, &file);
+        assert!(rendered.starts_with("\x1b[1;31merror\x1b[0m\x1b[1m: bad\x1b[0m\n"));
+        assert!(rendered.contains("\x1b[1;31m^\x1b[0m"));
+    }
+
+    #[test]
+    fn test_palette() {
+        let file = SourceFile::new("test", "x");
+        let diagnostic = Diagnostic::builder(Severity::Error, "bad")
+            .primary(span(0, 1), "")
+            .build();
+        let palette = Palette {
+            error: "<e>",
+            emphasis: "<b>",
+            reset: "</>",
+            ..Palette::default()
+        };
+        let rendered = TerminalRenderer::new()
+            .with_color(true)
+            .with_palette(palette)
+            .render(&diagnostic, &file);
+        assert!(rendered.starts_with("<e>error</><b>: bad</>\n"));
+        assert!(rendered.contains("<e>^</>"));
+
+        let plain = TerminalRenderer::new()
+            .with_color_choice(ColorChoice::Never, &std::io::stdout())
+            .with_palette(palette)
+            .render(&diagnostic, &file);
+        assert!(!plain.contains('<'));
+    }
+}
@@ -0,0 +1,536 @@
+//! Documents kept up to date through edits, such as the open files of a
+//! language server.
+//!
+//! A [`DocumentState`] owns the source of a document with its line offsets,
+//! tokens and, given a parser, its events, lossless [`SyntaxTree`] and
+//! diagnostics. [`DocumentState::apply_edit`] lexes again only the tokens
+//! around an edit, and parses again only the smallest node around them that
+//! has a reparser, returning what changed.
+//!
+//! # Examples
+//! ```
+//! use grammarsmith::document::DocumentState;
+//! use grammarsmith::event::Event;
+//! use grammarsmith::parser::ParseOutput;
+//! use grammarsmith::position::{Span, TextEdit, WithSpan};
+//!
+//! /// Lexes runs of letters and single other characters.
+//! fn lex(source: &str, start: usize) -> WithSpan<char> {
+//!     let rest = &source[start..];
+//!     let len = match rest.find(|ch: char| !ch.is_alphabetic()) {
+//!         Some(0) => rest.chars().next().unwrap().len_utf8(),
+//!         Some(len) => len,
+//!         None => rest.len(),
+//!     };
+//!     let kind = if rest.starts_with(char::is_alphabetic) { 'w' } else { ' ' };
+//!     WithSpan::new(kind, Span::new(start, start + len).unwrap())
+//! }
+//!
+//! /// Parses a list of words.
+//! fn parse(_: &str, tokens: &[WithSpan<char>]) -> ParseOutput<Vec<Event<char>>> {
+//!     let mut events = vec![Event::StartNode('l')];
+//!     for token in tokens {
+//!         events.push(Event::Token { kind: token.value, span: token.span });
+//!     }
+//!     events.push(Event::FinishNode);
+//!     ParseOutput::new(events, Vec::new())
+//! }
+//!
+//! let mut document = DocumentState::new("one two", lex).with_parser(|kind| *kind == ' ', parse);
+//! let update = document
+//!     .apply_edit(&TextEdit::new(Span::new(4, 7).unwrap(), "three\nfour"))
+//!     .unwrap();
+//! assert_eq!(document.source(), "one three\nfour");
+//! assert_eq!(update.relexed, Span::new(3, 14).unwrap());
+//! assert_eq!(document.tokens().len(), 5);
+//! assert_eq!(document.line_offsets().line_count(), 2);
+//! assert_eq!(document.tree().unwrap().root().unwrap().text(), "one three\nfour");
+//! ```
+
+use crate::diagnostics::Diagnostic;
+use crate::event::Event;
+use crate::parser::ParseOutput;
+use crate::position::{EditError, LineOffsets, Span, TextEdit, WithSpan};
+use crate::tree::{diff_trees, SyntaxTree, TreeChange};
+use std::fmt;
+use std::hash::Hash;
+
+/// Lexes the token starting at an offset of a source.
+type Lex<T> = dyn Fn(&str, usize) -> WithSpan<T>;
+
+/// Parses tokens of a source into events.
+type Parse<T> = dyn Fn(&str, &[WithSpan<T>]) -> ParseOutput<Vec<Event<T>>>;
+
+/// The state of a document being edited: its source, line offsets, tokens,
+/// and optionally its syntax tree and diagnostics.
+///
+/// The lexer returns the token starting at an offset, which must not be
+/// empty; tokens cover the whole source, so unknown text should be lexed
+/// into error tokens. Lexing again after an edit stops at the first token
+/// that ends where an old token started after the edit, so the lexer
+/// should not depend on the text before the offset.
+///
+/// The parser, added with [`with_parser`](DocumentState::with_parser),
+/// returns the events of the whole document, with a token event for each
+/// token, trivia included, in order. Reparsers, added with
+/// [`with_reparser`](DocumentState::with_reparser), parse the tokens of a
+/// single node of their kind, such as a block, from the same events.
+pub struct DocumentState<T> {
+    source: String,
+    lines: LineOffsets,
+    tokens: Vec<WithSpan<T>>,
+    lex: Box<Lex<T>>,
+    syntax: Option<Syntax<T>>,
+    reparsers: Vec<(T, Box<Parse<T>>)>,
+}
+
+struct Syntax<T> {
+    parse: Box<Parse<T>>,
+    is_trivia: Box<dyn Fn(&T) -> bool>,
+    events: Vec<Event<T>>,
+    tree: SyntaxTree<T>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// What changed in a [`DocumentState`] with an edit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocumentUpdate<T> {
+    /// The text of the new source that was lexed again.
+    pub relexed: Span,
+    /// The text of the new source that was parsed again, which is all of it
+    /// unless a node could be reparsed, or `None` without a parser.
+    pub reparsed: Option<Span>,
+    /// The changes to the syntax tree, as by [`diff_trees`].
+    pub changes: Vec<TreeChange<T>>,
+    /// The diagnostics of the document after the edit.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl<T: Clone + Eq + Hash + 'static> DocumentState<T> {
+    /// Creates the state of a document, lexing all of `source` with `lex`.
+    ///
+    /// # Panics
+    /// Panics if `lex` returns an empty token or one not starting at the
+    /// offset it was given.
+    pub fn new(
+        source: impl Into<String>,
+        lex: impl Fn(&str, usize) -> WithSpan<T> + 'static,
+    ) -> Self {
+        let source = source.into();
+        let mut document = DocumentState {
+            lines: LineOffsets::new(source.as_str()),
+            tokens: Vec::new(),
+            source,
+            lex: Box::new(lex),
+            syntax: None,
+            reparsers: Vec::new(),
+        };
+        let (tokens, _) = document.lex_from(0, |_| None);
+        document.tokens = tokens;
+        document
+    }
+
+    /// Parses the document with `parse`, and again after each edit, keeping
+    /// a syntax tree with the tokens of the kinds for which `is_trivia`
+    /// holds as trivia.
+    pub fn with_parser(
+        mut self,
+        is_trivia: impl Fn(&T) -> bool + 'static,
+        parse: impl Fn(&str, &[WithSpan<T>]) -> ParseOutput<Vec<Event<T>>> + 'static,
+    ) -> Self {
+        let output = parse(&self.source, &self.tokens);
+        self.syntax = Some(Syntax {
+            tree: SyntaxTree::new(self.source.as_str(), &output.value, &is_trivia),
+            parse: Box::new(parse),
+            is_trivia: Box::new(is_trivia),
+            events: output.value,
+            diagnostics: output.diagnostics,
+        });
+        self
+    }
+
+    /// Parses nodes of `kind` alone with `parse` after edits inside them,
+    /// instead of the whole document.
+    ///
+    /// `parse` is given the tokens of the node and returns its events, a
+    /// single node of `kind`; the whole document is parsed again if it
+    /// returns anything else.
+    pub fn with_reparser(
+        mut self,
+        kind: T,
+        parse: impl Fn(&str, &[WithSpan<T>]) -> ParseOutput<Vec<Event<T>>> + 'static,
+    ) -> Self {
+        self.reparsers.push((kind, Box::new(parse)));
+        self
+    }
+
+    /// Applies `edit` to the document, lexing and parsing again the parts
+    /// of it the edit may have changed.
+    ///
+    /// Diagnostics outside a reparsed node are kept, moved with the edit,
+    /// and those inside it replaced.
+    pub fn apply_edit(&mut self, edit: &TextEdit) -> Result<DocumentUpdate<T>, EditError> {
+        let range = edit.range;
+        if range.end() > self.source.len() {
+            return Err(EditError::OutOfBounds(range));
+        }
+        if !self.source.is_char_boundary(range.start())
+            || !self.source.is_char_boundary(range.end())
+        {
+            return Err(EditError::NotCharBoundary(range));
+        }
+        self.source
+            .replace_range(range.start()..range.end(), &edit.new_text);
+        self.lines.apply_edit(edit);
+
+        // Lex again from the token touching the start of the edit, until
+        // the tokens are back in step with the old ones after it.
+        let first = self
+            .tokens
+            .partition_point(|token| token.span.end() < range.start());
+        let start = self
+            .tokens
+            .get(first)
+            .map_or(range.start(), |token| token.span.start().min(range.start()));
+        let old_tokens = std::mem::take(&mut self.tokens);
+        let edit_end = range.start() + edit.new_text.len();
+        let (relexed, resync) = self.lex_from(start, |pos| {
+            if pos < edit_end {
+                return None;
+            }
+            let old = pos + range.len() - edit.new_text.len();
+            old_tokens
+                .binary_search_by_key(&old, |token| token.span.start())
+                .ok()
+                .filter(|&i| old_tokens[i].span.start() >= range.end())
+        });
+        let resync = resync.unwrap_or(old_tokens.len());
+        let relexed_span = Span::new_unchecked(
+            start,
+            relexed.last().map_or(start, |token| token.span.end()),
+        );
+        let relexed_len = relexed.len();
+        self.tokens = old_tokens[..first].to_vec();
+        self.tokens.extend(relexed);
+        self.tokens
+            .extend(old_tokens[resync..].iter().map(|token| WithSpan {
+                value: token.value.clone(),
+                span: shift(token.span, edit),
+            }));
+
+        let reparsed = self.reparse(edit, &old_tokens, first..resync, relexed_len);
+        let Some(syntax) = &mut self.syntax else {
+            return Ok(DocumentUpdate {
+                relexed: relexed_span,
+                reparsed: None,
+                changes: Vec::new(),
+                diagnostics: Vec::new(),
+            });
+        };
+        let reparsed = reparsed.unwrap_or_else(|| {
+            let output = (syntax.parse)(&self.source, &self.tokens);
+            syntax.events = output.value;
+            syntax.diagnostics = output.diagnostics;
+            Span::new_unchecked(0, self.source.len())
+        });
+        let tree = SyntaxTree::new(self.source.as_str(), &syntax.events, &syntax.is_trivia);
+        let changes = diff_trees(&syntax.tree, &tree);
+        syntax.tree = tree;
+        Ok(DocumentUpdate {
+            relexed: relexed_span,
+            reparsed: Some(reparsed),
+            changes,
+            diagnostics: syntax.diagnostics.clone(),
+        })
+    }
+
+    /// Lexes the source from `start` to its end, or until `resync` returns
+    /// the index of an old token to continue with at the end of a token.
+    fn lex_from(
+        &self,
+        start: usize,
+        resync: impl Fn(usize) -> Option<usize>,
+    ) -> (Vec<WithSpan<T>>, Option<usize>) {
+        let mut tokens = Vec::new();
+        let mut pos = start;
+        while pos < self.source.len() {
+            if let Some(old) = resync(pos) {
+                return (tokens, Some(old));
+            }
+            let token = (self.lex)(&self.source, pos);
+            assert!(
+                token.span.start() == pos && token.span.end() > pos,
+                "lexer returned an empty token or one not starting at {}",
+                pos
+            );
+            pos = token.span.end();
+            tokens.push(token);
+        }
+        (tokens, None)
+    }
+
+    /// Parses again the smallest node with a reparser around the tokens
+    /// `relexed` of `old_tokens`, which were replaced by `relexed_len` tokens,
+    /// and returns its new span, or `None` if there is no such node or its
+    /// reparser did not return a single node of its kind.
+    fn reparse(
+        &mut self,
+        edit: &TextEdit,
+        old_tokens: &[WithSpan<T>],
+        relexed: std::ops::Range<usize>,
+        relexed_len: usize,
+    ) -> Option<Span> {
+        let syntax = self.syntax.as_mut()?;
+        let (source, tokens) = (&self.source, &self.tokens);
+        // The events and old tokens of the nodes that are started, by the
+        // index of their start event and their first token.
+        let mut stack = Vec::new();
+        let mut token = 0;
+        let mut found = None;
+        for (i, event) in syntax.events.iter().enumerate() {
+            match event {
+                Event::StartNode(_) => stack.push((i, token)),
+                Event::Token { .. } => token += 1,
+                Event::FinishNode => {
+                    let (start, first) = stack.pop()?;
+                    let Event::StartNode(kind) = &syntax.events[start] else {
+                        unreachable!()
+                    };
+                    let surrounds = first < relexed.start && token > relexed.end;
+                    let reparser = self.reparsers.iter().find(|(k, _)| k == kind);
+                    if let (true, Some((_, reparser))) = (surrounds, reparser) {
+                        found = Some((start..i + 1, first..token, kind.clone(), reparser));
+                        break;
+                    }
+                }
+            }
+        }
+        let (events, old, kind, reparser) = found?;
+        let new = old.start..old.end - relexed.len() + relexed_len;
+        let output = reparser(source, &tokens[new.clone()]);
+        if !is_node(&output.value, &kind, new.len()) {
+            return None;
+        }
+
+        let old_span = old_tokens[old.start]
+            .span
+            .union(&old_tokens[old.end - 1].span);
+        let mut diagnostics: Vec<Diagnostic> = std::mem::take(&mut syntax.diagnostics)
+            .into_iter()
+            .filter(|diagnostic| {
+                diagnostic.primary_span().is_none_or(|span| {
+                    span.start() < old_span.start() || span.end() > old_span.end()
+                })
+            })
+            .filter_map(|diagnostic| map_diagnostic(diagnostic, edit))
+            .collect();
+        diagnostics.extend(output.diagnostics);
+        diagnostics.sort_by_key(|diagnostic| diagnostic.primary_span().map(|span| span.start));
+        syntax.diagnostics = diagnostics;
+        // The tokens after the node moved with the edit.
+        let after = events.start + output.value.len();
+        syntax.events.splice(events, output.value);
+        for event in &mut syntax.events[after..] {
+            if let Event::Token { span, .. } = event {
+                *span = shift(*span, edit);
+            }
+        }
+        Some(tokens[new.start].span.union(&tokens[new.end - 1].span))
+    }
+}
+
+impl<T> DocumentState<T> {
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn line_offsets(&self) -> &LineOffsets {
+        &self.lines
+    }
+
+    /// Returns the tokens of the document, trivia included.
+    pub fn tokens(&self) -> &[WithSpan<T>] {
+        &self.tokens
+    }
+
+    /// Returns the events of the last parse, if the document has a parser.
+    pub fn events(&self) -> Option<&[Event<T>]> {
+        self.syntax.as_ref().map(|syntax| syntax.events.as_slice())
+    }
+
+    /// Returns the syntax tree, if the document has a parser.
+    pub fn tree(&self) -> Option<&SyntaxTree<T>> {
+        self.syntax.as_ref().map(|syntax| &syntax.tree)
+    }
+
+    /// Returns the diagnostics of the parser, if any.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        self.syntax
+            .as_ref()
+            .map_or(&[], |syntax| syntax.diagnostics.as_slice())
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for DocumentState<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DocumentState")
+            .field("source", &self.source)
+            .field("tokens", &self.tokens)
+            .field("events", &self.events())
+            .field("diagnostics", &self.diagnostics())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Returns whether `events` are a single node of `kind` with `tokens`
+/// tokens.
+fn is_node<T: Eq>(events: &[Event<T>], kind: &T, tokens: usize) -> bool {
+    let mut depth = 0usize;
+    let mut count = 0;
+    for (i, event) in events.iter().enumerate() {
+        match event {
+            Event::StartNode(k) if i == 0 && k != kind => return false,
+            Event::StartNode(_) => depth += 1,
+            Event::Token { .. } if depth == 0 => return false,
+            Event::Token { .. } => count += 1,
+            Event::FinishNode => match depth.checked_sub(1) {
+                Some(0) if i + 1 != events.len() => return false,
+                Some(d) => depth = d,
+                None => return false,
+            },
+        }
+    }
+    !events.is_empty() && depth == 0 && count == tokens
+}
+
+/// Moves a span after an edit by the change in length of the edit.
+fn shift(span: Span, edit: &TextEdit) -> Span {
+    let delta = edit.delta();
+    Span::new_unchecked(
+        span.start().wrapping_add_signed(delta),
+        span.end().wrapping_add_signed(delta),
+    )
+}
+
+/// Maps the spans of a diagnostic through `edit`, or returns `None` if one
+/// crosses a boundary of the edit.
+fn map_diagnostic(mut diagnostic: Diagnostic, edit: &TextEdit) -> Option<Diagnostic> {
+    let edits = std::slice::from_ref(edit);
+    for label in &mut diagnostic.labels {
+        label.span = label.span.map_through_edits(edits)?;
+    }
+    for suggestion in &mut diagnostic.suggestions {
+        for suggested in &mut suggestion.edits {
+            suggested.range = suggested.range.map_through_edits(edits)?;
+        }
+    }
+    Some(diagnostic)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lexes `(`, `)`, and runs of spaces, digits, and other characters.
+    fn lex(source: &str, start: usize) -> WithSpan<char> {
+        let rest = &source[start..];
+        let first = rest.chars().next().unwrap();
+        let class = |ch: char| match ch {
+            '(' | ')' => ch,
+            ' ' | '\n' => ' ',
+            '0'..='9' => '0',
+            _ => '?',
+        };
+        let len = match class(first) {
+            '(' | ')' => 1,
+            kind => rest.find(|ch| class(ch) != kind).unwrap_or(rest.len()),
+        };
+        WithSpan::new(class(first), Span::new(start, start + len).unwrap())
+    }
+
+    /// Parses lists of numbers in parentheses, reporting other characters.
+    fn parse(_: &str, tokens: &[WithSpan<char>]) -> ParseOutput<Vec<Event<char>>> {
+        let mut events = vec![Event::StartNode('r')];
+        let mut diagnostics = Vec::new();
+        for token in tokens {
+            match token.value {
+                '(' => events.push(Event::StartNode('l')),
+                '?' => diagnostics.push(Diagnostic::error("unexpected").with_label(token.span, "")),
+                _ => {}
+            }
+            events.push(Event::Token {
+                kind: token.value,
+                span: token.span,
+            });
+            if token.value == ')' {
+                events.push(Event::FinishNode);
+            }
+        }
+        events.push(Event::FinishNode);
+        ParseOutput::new(events, diagnostics)
+    }
+
+    /// Parses a single list.
+    fn parse_list(source: &str, tokens: &[WithSpan<char>]) -> ParseOutput<Vec<Event<char>>> {
+        let mut output = parse(source, tokens);
+        output.value.remove(0);
+        output.value.pop();
+        output
+    }
+
+    fn document() -> DocumentState<char> {
+        DocumentState::new("(1 2) x (3)", lex)
+            .with_parser(|kind| *kind == ' ', parse)
+            .with_reparser('l', parse_list)
+    }
+
+    fn span(start: usize, end: usize) -> Span {
+        Span::new(start, end).unwrap()
+    }
+
+    #[test]
+    fn test_reparse_node() {
+        let mut document = document();
+        assert_eq!(document.diagnostics().len(), 1);
+        let update = document
+            .apply_edit(&TextEdit::new(span(3, 4), "45 y"))
+            .unwrap();
+        assert_eq!(document.source(), "(1 45 y) x (3)");
+        assert_eq!(update.relexed, span(2, 7));
+        assert_eq!(update.reparsed, Some(span(0, 8)));
+        assert_eq!(
+            update.changes,
+            [
+                TreeChange::Updated {
+                    kind: '0',
+                    old: span(3, 4),
+                    new: span(3, 5),
+                },
+                TreeChange::Inserted {
+                    kind: '?',
+                    new: span(6, 7),
+                },
+            ]
+        );
+        let primary: Vec<Span> = update
+            .diagnostics
+            .iter()
+            .filter_map(Diagnostic::primary_span)
+            .collect();
+        assert_eq!(primary, [span(6, 7), span(9, 10)]);
+        assert_eq!(document.tree().unwrap().verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_full_parse() {
+        let mut document = document();
+        let update = document.apply_edit(&TextEdit::delete(span(4, 5))).unwrap();
+        assert_eq!(document.source(), "(1 2 x (3)");
+        assert_eq!(update.reparsed, Some(span(0, 10)));
+        assert_eq!(document.line_offsets(), &LineOffsets::new("(1 2 x (3)"));
+        assert_eq!(
+            document.apply_edit(&TextEdit::insert(crate::position::BytePos(11), "")),
+            Err(EditError::OutOfBounds(Span::point(11)))
+        );
+    }
+}
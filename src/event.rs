@@ -0,0 +1,172 @@
+//! Building syntax trees from a flat stream of parser events.
+//!
+//! Instead of constructing tree nodes directly, a parser can record
+//! [`Event`]s: the start of a node, a token, and the end of a node. The
+//! events are later replayed into a [`TreeSink`], which builds whatever tree
+//! representation the caller wants, such as a rowan green tree with the
+//! `rowan` feature.
+//!
+//! # Examples
+//! ```
+//! use grammarsmith::event::*;
+//! use grammarsmith::position::Span;
+//!
+//! let source = "1+2";
+//! let mut events = Events::new();
+//! events.start_node('+');
+//! events.token('n', Span::new(0, 1).unwrap());
+//! events.token('o', Span::new(1, 2).unwrap());
+//! events.token('n', Span::new(2, 3).unwrap());
+//! events.finish_node();
+//!
+//! let mut outline = Vec::new();
+//! events.replay(source, &mut outline);
+//! assert_eq!(
+//!     outline,
+//!     ["('+'", "'n' `1`", "'o' `+`", "'n' `2`", ")"]
+//! );
+//! ```
+
+use crate::position::Span;
+
+/// A step in building a syntax tree.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Event<K> {
+    /// Starts a node of the given kind; the following events up to the
+    /// matching [`FinishNode`](Event::FinishNode) are its children.
+    StartNode(K),
+    /// A token of the given kind covering `span` of the source.
+    Token { kind: K, span: Span },
+    /// Finishes the most recently started node.
+    FinishNode,
+}
+
+/// Receives the events of a parse to build a tree.
+pub trait TreeSink<K> {
+    fn start_node(&mut self, kind: K);
+    fn token(&mut self, kind: K, text: &str);
+    fn finish_node(&mut self);
+}
+
+/// Writes an outline of the tree, one entry per event, mainly for tests.
+impl<K: std::fmt::Debug> TreeSink<K> for Vec<String> {
+    fn start_node(&mut self, kind: K) {
+        self.push(format!("({:?}", kind));
+    }
+
+    fn token(&mut self, kind: K, text: &str) {
+        self.push(format!("{:?} `{}`", kind, text));
+    }
+
+    fn finish_node(&mut self) {
+        self.push(")".to_string());
+    }
+}
+
+/// A recorded sequence of [`Event`]s.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Events<K> {
+    events: Vec<Event<K>>,
+    open: usize,
+}
+
+impl<K> Default for Events<K> {
+    fn default() -> Self {
+        Events {
+            events: Vec::new(),
+            open: 0,
+        }
+    }
+}
+
+impl<K> Events<K> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts a node of the given kind.
+    pub fn start_node(&mut self, kind: K) {
+        self.open += 1;
+        self.events.push(Event::StartNode(kind));
+    }
+
+    /// Records a token.
+    pub fn token(&mut self, kind: K, span: Span) {
+        self.events.push(Event::Token { kind, span });
+    }
+
+    /// Finishes the most recently started node.
+    ///
+    /// # Panics
+    /// Panics if there is no open node.
+    pub fn finish_node(&mut self) {
+        assert!(self.open > 0, "no node to finish");
+        self.open -= 1;
+        self.events.push(Event::FinishNode);
+    }
+
+    /// Returns the recorded events.
+    pub fn as_slice(&self) -> &[Event<K>] {
+        &self.events
+    }
+
+    /// Returns the number of nodes started but not finished.
+    pub fn open_nodes(&self) -> usize {
+        self.open
+    }
+
+    /// Returns the recorded events.
+    pub fn into_vec(self) -> Vec<Event<K>> {
+        self.events
+    }
+}
+
+impl<K: Clone> Events<K> {
+    /// Replays the events into `sink`, taking token texts from `source`.
+    pub fn replay(&self, source: &str, sink: &mut impl TreeSink<K>) {
+        replay(&self.events, source, sink);
+    }
+}
+
+/// Replays `events` into `sink`, taking token texts from `source`.
+///
+/// # Panics
+/// Panics if a token span is out of bounds of `source`.
+pub fn replay<K: Clone>(events: &[Event<K>], source: &str, sink: &mut (impl TreeSink<K> + ?Sized)) {
+    for event in events {
+        match event {
+            Event::StartNode(kind) => sink.start_node(kind.clone()),
+            Event::Token { kind, span } => {
+                sink.token(kind.clone(), &source[span.start()..span.end()])
+            }
+            Event::FinishNode => sink.finish_node(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nesting() {
+        let mut events = Events::new();
+        events.start_node('a');
+        events.start_node('b');
+        assert_eq!(events.open_nodes(), 2);
+        events.finish_node();
+        events.token('x', Span::new(0, 1).unwrap());
+        events.finish_node();
+        assert_eq!(events.open_nodes(), 0);
+
+        let mut outline = Vec::new();
+        replay(events.as_slice(), "x", &mut outline);
+        assert_eq!(outline, ["('a'", "('b'", ")", "'x' `x`", ")"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "no node to finish")]
+    fn test_unbalanced() {
+        Events::<u8>::new().finish_node();
+    }
+}
@@ -0,0 +1,148 @@
+//! Fuzzing and property-testing support.
+//!
+//! This module implements [`arbitrary::Arbitrary`] for the position types and
+//! provides [`proptest`] strategies that only generate valid spans: ordered,
+//! in bounds for a given source length, and optionally on character
+//! boundaries.
+//!
+//! For fuzzing parsers, [`arbitrary_token_stream`] generates plausible token
+//! streams for tokens implementing [`ArbitraryToken`], and [`Mutation`]s
+//! make random changes to source texts.
+//!
+//! # Examples
+//! ```
+//! use grammarsmith::fuzzing::span_in;
+//! use proptest::prelude::*;
+//!
+//! proptest!(|(span in span_in(10))| {
+//!     prop_assert!(span.start <= span.end);
+//!     prop_assert!(span.end() <= 10);
+//! });
+//! ```
+
+pub mod mutation;
+pub mod tokens;
+
+pub use mutation::*;
+pub use tokens::*;
+
+use crate::position::*;
+use arbitrary::{Arbitrary, Unstructured};
+use proptest::prelude::*;
+
+impl<'a> Arbitrary<'a> for BytePos {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(BytePos(RawPos::arbitrary(u)?))
+    }
+
+    fn size_hint(depth: usize) -> (usize, Option<usize>) {
+        RawPos::size_hint(depth)
+    }
+}
+
+impl<'a> Arbitrary<'a> for Span {
+    /// Generates an ordered span, i.e. `start <= end`.
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        let a = BytePos::arbitrary(u)?;
+        let b = BytePos::arbitrary(u)?;
+        Ok(Span {
+            start: a.min(b),
+            end: a.max(b),
+        })
+    }
+
+    fn size_hint(depth: usize) -> (usize, Option<usize>) {
+        let (lo, hi) = BytePos::size_hint(depth);
+        (2 * lo, hi.map(|hi| 2 * hi))
+    }
+}
+
+impl<'a, T: Arbitrary<'a>> Arbitrary<'a> for WithSpan<T> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(WithSpan::new(T::arbitrary(u)?, Span::arbitrary(u)?))
+    }
+}
+
+/// Generates an ordered span within a source of `len` bytes.
+pub fn arbitrary_span_in(u: &mut Unstructured<'_>, len: usize) -> arbitrary::Result<Span> {
+    let a = u.int_in_range(0..=len)?;
+    let b = u.int_in_range(0..=len)?;
+    Ok(Span::new_unchecked(a.min(b), a.max(b)))
+}
+
+/// A strategy for positions in a source of `len` bytes, including the end.
+pub fn byte_pos_in(len: usize) -> impl Strategy<Value = BytePos> {
+    (0..=len).prop_map(BytePos::from_usize)
+}
+
+/// A strategy for ordered spans within a source of `len` bytes.
+pub fn span_in(len: usize) -> impl Strategy<Value = Span> {
+    (0..=len, 0..=len).prop_map(|(a, b)| Span::new_unchecked(a.min(b), a.max(b)))
+}
+
+/// A strategy for ordered spans within `source` that start and end on
+/// character boundaries, so they can always be used to slice it.
+pub fn span_in_source(source: &str) -> impl Strategy<Value = Span> {
+    let boundaries: Vec<usize> = source
+        .char_indices()
+        .map(|(i, _)| i)
+        .chain(std::iter::once(source.len()))
+        .collect();
+    let count = boundaries.len();
+    (0..count, 0..count)
+        .prop_map(move |(a, b)| Span::new_unchecked(boundaries[a.min(b)], boundaries[a.max(b)]))
+}
+
+/// A strategy for values paired with ordered spans within a source of `len`
+/// bytes.
+pub fn with_span_in<S: Strategy>(
+    value: S,
+    len: usize,
+) -> impl Strategy<Value = WithSpan<S::Value>> {
+    (value, span_in(len)).prop_map(|(value, span)| WithSpan::new(value, span))
+}
+
+/// A strategy for up to `max_count` sorted, non-overlapping spans within a
+/// source of `len` bytes, like the spans of a token stream.
+pub fn sorted_spans_in(len: usize, max_count: usize) -> impl Strategy<Value = Vec<Span>> {
+    proptest::collection::vec(0..=len, 0..=2 * max_count).prop_map(|mut points| {
+        points.sort_unstable();
+        points
+            .chunks_exact(2)
+            .map(|pair| Span::new_unchecked(pair[0], pair[1]))
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arbitrary_span_is_ordered() {
+        let data: Vec<u8> = (0..=255).rev().collect();
+        let mut u = Unstructured::new(&data);
+        while !u.is_empty() {
+            let span = Span::arbitrary(&mut u).unwrap();
+            assert!(span.start <= span.end);
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn test_span_in_source_slices(span in span_in_source("a€🦀b")) {
+            prop_assert!("a€🦀b".get(span.start()..span.end()).is_some());
+        }
+
+        #[test]
+        fn test_sorted_spans_do_not_overlap(spans in sorted_spans_in(50, 10)) {
+            prop_assert!(spans.len() <= 10);
+            for pair in spans.windows(2) {
+                prop_assert!(pair[0].end <= pair[1].start);
+            }
+            for span in spans {
+                prop_assert!(span.start <= span.end && span.end() <= 50);
+            }
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use crate::position::Span;
+use arbitrary::{Arbitrary, Unstructured};
+
+/// A random change to a source text, for fuzzing lexers and parsers with
+/// inputs close to valid ones.
+///
+/// Mutations are generated for a particular source and only cut it on
+/// character boundaries.
+///
+/// # Examples
+/// ```
+/// use arbitrary::Unstructured;
+/// use grammarsmith::fuzzing::Mutation;
+/// use grammarsmith::position::Span;
+///
+/// let source = "let x = 1;";
+/// let duplicate = Mutation::Duplicate { span: Span::new(4, 6).unwrap(), at: 0 };
+/// assert_eq!(duplicate.apply(source), "x let x = 1;");
+///
+/// let mutation = Mutation::arbitrary_for(source, &mut Unstructured::new(&[3, 1, 4, 1, 5])).unwrap();
+/// let mutated = mutation.apply(source);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Mutation {
+    /// Replaces the text in `span` with `text`.
+    Splice { span: Span, text: String },
+    /// Copies the text in `span` to the offset `at`.
+    Duplicate { span: Span, at: usize },
+    /// Cuts the source off at `len` bytes.
+    Truncate { len: usize },
+}
+
+impl Mutation {
+    /// Generates a mutation of `source`.
+    ///
+    /// Spliced text is either a piece of the source itself or arbitrary
+    /// text.
+    pub fn arbitrary_for(source: &str, u: &mut Unstructured<'_>) -> arbitrary::Result<Self> {
+        Ok(match u.int_in_range(0..=2)? {
+            0 => {
+                let span = arbitrary_span(source, u)?;
+                let text = if u.arbitrary()? {
+                    let piece = arbitrary_span(source, u)?;
+                    source[piece.start()..piece.end()].to_string()
+                } else {
+                    <&str>::arbitrary(u)?.to_string()
+                };
+                Mutation::Splice { span, text }
+            }
+            1 => Mutation::Duplicate {
+                span: arbitrary_span(source, u)?,
+                at: arbitrary_boundary(source, u)?,
+            },
+            _ => Mutation::Truncate {
+                len: arbitrary_boundary(source, u)?,
+            },
+        })
+    }
+
+    /// Returns `source` with the mutation applied.
+    ///
+    /// # Panics
+    /// Panics if the mutation does not fit `source`, for example if it was
+    /// generated for another source.
+    pub fn apply(&self, source: &str) -> String {
+        match self {
+            Mutation::Splice { span, text } => {
+                let mut out = source.to_string();
+                out.replace_range(span.start()..span.end(), text);
+                out
+            }
+            Mutation::Duplicate { span, at } => {
+                let mut out = source.to_string();
+                out.insert_str(*at, &source[span.start()..span.end()]);
+                out
+            }
+            Mutation::Truncate { len } => source[..*len].to_string(),
+        }
+    }
+}
+
+/// Returns a random offset in `source` on a character boundary.
+fn arbitrary_boundary(source: &str, u: &mut Unstructured<'_>) -> arbitrary::Result<usize> {
+    let mut pos = u.int_in_range(0..=source.len())?;
+    while !source.is_char_boundary(pos) {
+        pos -= 1;
+    }
+    Ok(pos)
+}
+
+fn arbitrary_span(source: &str, u: &mut Unstructured<'_>) -> arbitrary::Result<Span> {
+    let a = arbitrary_boundary(source, u)?;
+    let b = arbitrary_boundary(source, u)?;
+    Ok(Span::new_unchecked(a.min(b), a.max(b)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mutations_apply() {
+        let source = "a€🦀b";
+        for seed in 0..=255u8 {
+            let data: Vec<u8> = (0..32u8).map(|i| i.wrapping_mul(31) ^ seed).collect();
+            let mutation = Mutation::arbitrary_for(source, &mut Unstructured::new(&data)).unwrap();
+            let mutated = mutation.apply(source);
+            if let Mutation::Truncate { len } = mutation {
+                assert_eq!(mutated.len(), len);
+            }
+        }
+    }
+
+    #[test]
+    fn test_splice() {
+        let splice = Mutation::Splice {
+            span: Span::new(1, 4).unwrap(),
+            text: "x".to_string(),
+        };
+        assert_eq!(splice.apply("a€b"), "axb");
+    }
+}
@@ -0,0 +1,228 @@
+use crate::parser::{Bracket, KindMeta, Token};
+use crate::position::{Span, WithSpan};
+use arbitrary::{Arbitrary, Unstructured};
+
+/// Tokens that can be generated from their kind, for fuzzing parsers with
+/// random token streams.
+///
+/// # Examples
+/// ```
+/// use arbitrary::{Arbitrary, Unstructured};
+/// use grammarsmith::fuzzing::{ArbitraryToken, TokenStream};
+/// use grammarsmith::parser::{Bracket, KindMeta, Token};
+///
+/// #[derive(Debug, Clone, Copy, PartialEq)]
+/// enum Kind {
+///     Number,
+///     LParen,
+///     RParen,
+/// }
+///
+/// impl KindMeta for Kind {
+///     const ALL: &'static [Kind] = &[Kind::Number, Kind::LParen, Kind::RParen];
+///
+///     fn display_name(&self) -> &'static str {
+///         "token"
+///     }
+/// }
+///
+/// #[derive(Debug, Clone, PartialEq)]
+/// enum Tok {
+///     Number(u8),
+///     LParen,
+///     RParen,
+/// }
+///
+/// impl Token for Tok {
+///     type Kind = Kind;
+///
+///     fn to_kind(&self) -> Kind {
+///         match self {
+///             Tok::Number(_) => Kind::Number,
+///             Tok::LParen => Kind::LParen,
+///             Tok::RParen => Kind::RParen,
+///         }
+///     }
+/// }
+///
+/// impl ArbitraryToken for Tok {
+///     fn from_kind(kind: &Kind, u: &mut Unstructured<'_>) -> arbitrary::Result<Tok> {
+///         Ok(match kind {
+///             Kind::Number => Tok::Number(u.arbitrary()?),
+///             Kind::LParen => Tok::LParen,
+///             Kind::RParen => Tok::RParen,
+///         })
+///     }
+///
+///     fn bracket(kind: &Kind) -> Option<Bracket> {
+///         match kind {
+///             Kind::LParen => Some(Bracket::Open),
+///             Kind::RParen => Some(Bracket::Close),
+///             Kind::Number => None,
+///         }
+///     }
+/// }
+///
+/// let data = [7u8; 64];
+/// let TokenStream(tokens) = TokenStream::<Tok>::arbitrary(&mut Unstructured::new(&data)).unwrap();
+/// let depth = tokens.iter().try_fold(0i32, |depth, token| {
+///     let depth = match token.value {
+///         Tok::LParen => depth + 1,
+///         Tok::RParen => depth - 1,
+///         _ => depth,
+///     };
+///     (depth >= 0).then_some(depth)
+/// });
+/// assert_eq!(depth, Some(0));
+/// ```
+pub trait ArbitraryToken: Token + Sized
+where
+    Self::Kind: KindMeta,
+{
+    /// Generates a token of the given kind, with an arbitrary payload.
+    fn from_kind(kind: &Self::Kind, u: &mut Unstructured<'_>) -> arbitrary::Result<Self>;
+
+    /// Tells whether tokens of a kind open or close a delimited region, so
+    /// generated streams keep them balanced. Defaults to no brackets.
+    fn bracket(kind: &Self::Kind) -> Option<Bracket> {
+        let _ = kind;
+        None
+    }
+}
+
+/// Generates a random stream of up to `max_len` tokens of kinds from
+/// [`KindMeta::ALL`].
+///
+/// The tokens have sorted, non-overlapping spans separated by random gaps,
+/// like the output of a lexer. Brackets are kept balanced: a closing bracket
+/// is only generated inside an open region, and open regions are closed at
+/// the end, which may exceed `max_len`.
+pub fn arbitrary_token_stream<T>(
+    u: &mut Unstructured<'_>,
+    max_len: usize,
+) -> arbitrary::Result<Vec<WithSpan<T>>>
+where
+    T: ArbitraryToken,
+    T::Kind: KindMeta,
+{
+    let kinds = T::Kind::ALL;
+    let closers: Vec<_> = kinds
+        .iter()
+        .filter(|kind| T::bracket(kind) == Some(Bracket::Close))
+        .collect();
+    let mut tokens = Vec::new();
+    let mut depth = 0usize;
+    let mut pos = 0;
+    let mut push = |kind: &T::Kind, u: &mut Unstructured<'_>| -> arbitrary::Result<()> {
+        let start = pos + u.int_in_range(0..=2)?;
+        let end = start + u.int_in_range(1..=4)?;
+        tokens.push(WithSpan::new(
+            T::from_kind(kind, u)?,
+            Span::new_unchecked(start, end),
+        ));
+        pos = end;
+        Ok(())
+    };
+
+    if kinds.is_empty() {
+        return Ok(Vec::new());
+    }
+    let len = u.int_in_range(0..=max_len)?;
+    for _ in 0..len {
+        let kind = u.choose(kinds)?;
+        match T::bracket(kind) {
+            Some(Bracket::Open) => depth += 1,
+            Some(Bracket::Close) if depth == 0 => continue,
+            Some(Bracket::Close) => depth -= 1,
+            None => {}
+        }
+        push(kind, u)?;
+    }
+    if !closers.is_empty() {
+        for _ in 0..depth {
+            let kind = *u.choose(&closers)?;
+            push(kind, u)?;
+        }
+    }
+    Ok(tokens)
+}
+
+/// A random token stream, generated by [`arbitrary_token_stream`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenStream<T>(pub Vec<WithSpan<T>>);
+
+impl<'a, T> Arbitrary<'a> for TokenStream<T>
+where
+    T: ArbitraryToken,
+    T::Kind: KindMeta,
+{
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        let max_len = u.arbitrary_len::<u8>()?;
+        arbitrary_token_stream(u, max_len).map(TokenStream)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum Kind {
+        Open,
+        Close,
+        Word,
+    }
+
+    impl KindMeta for Kind {
+        const ALL: &'static [Kind] = &[Kind::Open, Kind::Close, Kind::Word];
+
+        fn display_name(&self) -> &'static str {
+            "kind"
+        }
+    }
+
+    impl Token for Kind {
+        type Kind = Kind;
+
+        fn to_kind(&self) -> Kind {
+            *self
+        }
+    }
+
+    impl ArbitraryToken for Kind {
+        fn from_kind(kind: &Kind, _: &mut Unstructured<'_>) -> arbitrary::Result<Kind> {
+            Ok(*kind)
+        }
+
+        fn bracket(kind: &Kind) -> Option<Bracket> {
+            match kind {
+                Kind::Open => Some(Bracket::Open),
+                Kind::Close => Some(Bracket::Close),
+                Kind::Word => None,
+            }
+        }
+    }
+
+    #[test]
+    fn test_streams_are_balanced_and_sorted() {
+        for seed in 0..64u8 {
+            let data: Vec<u8> = (0..200)
+                .map(|i: u8| i.wrapping_mul(seed).wrapping_add(seed))
+                .collect();
+            let tokens = arbitrary_token_stream::<Kind>(&mut Unstructured::new(&data), 20).unwrap();
+            let mut depth = 0i32;
+            for token in &tokens {
+                match token.value {
+                    Kind::Open => depth += 1,
+                    Kind::Close => depth -= 1,
+                    Kind::Word => {}
+                }
+                assert!(depth >= 0);
+            }
+            assert_eq!(depth, 0);
+            for pair in tokens.windows(2) {
+                assert!(pair[0].span.end <= pair[1].span.start);
+            }
+        }
+    }
+}
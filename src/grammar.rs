@@ -0,0 +1,97 @@
+//! Runtime grammar descriptions.
+//!
+//! This module provides a [`Grammar`] made of named [`Rule`]s whose bodies
+//! are [`Expr`]essions over token kinds and other rules, so a grammar can be
+//! described once and used in many ways.
+//!
+//! - Grammars can be read from an EBNF notation with [`parse_ebnf`], which
+//!   can define the token kinds too, as [`TokenDef`]s for a [`Lexer`], and
+//!   can be composed from others with [`Grammar::import`] and
+//!   [`Grammar::resolve_imports`].
+//! - They can be analyzed with [`Grammar::analyze`], checked for LL(1)
+//!   conflicts with [`Grammar::check_ll1`] and left recursion with
+//!   [`Grammar::check_left_recursion`], and validated for likely mistakes
+//!   with [`Grammar::validate`]. Alternatives starting the same way can be
+//!   found with [`Grammar::common_prefixes`] and factored with
+//!   [`Grammar::left_factor`]. Operator rules can declare their
+//!   precedence, as [`PrecedenceLevel`]s, and be rewritten into a rule per
+//!   level with [`Grammar::resolve_precedence`].
+//! - They can be exported to other tools, such as tree-sitter with
+//!   [`TreeSitterExporter`], documented with railroad diagrams with
+//!   [`RailroadExporter`], or written in other notations with
+//!   [`Grammar::to_bnf`], [`Grammar::to_w3c_ebnf`] and
+//!   [`Grammar::to_antlr`].
+//! - [`RustGenerator`] turns a grammar into a recursive-descent parser over
+//!   [`Parser`](crate::parser::Parser), which recovers from errors with the
+//!   [`SyncSets`] derived from the FOLLOW sets, or into an [`LlTable`] that
+//!   can be built ahead of time and parsed with at runtime, or an
+//!   [`LalrTable`] for grammars that fit LR parsing better, and
+//!   [`SentenceGenerator`] goes the other way, into random sentences.
+//!   Ambiguous grammars can be parsed as they are written with
+//!   [`GeneralizedParser`] or [`EarleyParser`], into a [`ParseForest`] of
+//!   every parse, to try a grammar out before settling on a parser, and
+//!   [`ScannerlessParser`] parses text with rules over characters, with no
+//!   tokenizer in between. [`StreamParser`] parses input with an
+//!   [`LalrTable`] as it arrives in chunks.
+//!
+//! # Examples
+//! ```
+//! use grammarsmith::grammar::*;
+//!
+//! let grammar = Grammar::new()
+//!     .with_rule("sum", Expr::seq([
+//!         Expr::rule("term"),
+//!         Expr::repeat(Expr::seq([Expr::token("Plus"), Expr::rule("term")])),
+//!     ]))
+//!     .with_rule("term", Expr::token("Number"));
+//!
+//! assert_eq!(grammar.start().unwrap().name, "sum");
+//! assert_eq!(grammar.to_string(), "sum = term (Plus term)* ;\nterm = Number ;\n");
+//! ```
+
+pub mod analysis;
+pub mod codegen;
+pub mod conflicts;
+pub mod description;
+pub mod earley;
+pub mod ebnf;
+pub mod factoring;
+pub mod forest;
+pub mod generalized;
+pub mod generate;
+pub mod import;
+pub mod lalr;
+pub mod lexer;
+pub mod lints;
+pub mod notation;
+pub mod precedence;
+pub mod railroad;
+pub mod recursion;
+pub mod scannerless;
+pub mod stream;
+pub mod sync;
+pub mod table;
+pub mod treesitter;
+
+pub use analysis::*;
+pub use codegen::*;
+pub use conflicts::*;
+pub use description::*;
+pub use earley::*;
+pub use ebnf::*;
+pub use factoring::*;
+pub use forest::*;
+pub use generalized::*;
+pub use generate::*;
+pub use import::*;
+pub use lalr::*;
+pub use lexer::*;
+pub use lints::*;
+pub use precedence::*;
+pub use railroad::*;
+pub use recursion::*;
+pub use scannerless::*;
+pub use stream::*;
+pub use sync::*;
+pub use table::*;
+pub use treesitter::*;
@@ -0,0 +1,342 @@
+use super::{Expr, Grammar};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+/// A token that can come next in the input: a token kind, or the end of the
+/// input.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Lookahead {
+    Token(String),
+    End,
+}
+
+impl Lookahead {
+    pub fn token(kind: impl Into<String>) -> Self {
+        Lookahead::Token(kind.into())
+    }
+}
+
+impl fmt::Display for Lookahead {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Lookahead::Token(kind) => write!(f, "{}", kind),
+            Lookahead::End => write!(f, "end of input"),
+        }
+    }
+}
+
+/// The nullable, FIRST and FOLLOW sets of the rules of a [`Grammar`].
+///
+/// - A rule is nullable if it can match no tokens at all.
+/// - The FIRST set of a rule holds the token kinds its matches can start
+///   with. It never holds [`Lookahead::End`].
+/// - The FOLLOW set of a rule holds the tokens that can come right after a
+///   match of it, in the input matched by the start rule, which is followed
+///   by [`Lookahead::End`].
+///
+/// The sets are computed once, by iterating to a fixpoint. References to
+/// undefined rules are treated as matching nothing, so they are not
+/// nullable and their FIRST sets are empty.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::grammar::*;
+///
+/// let grammar = parse_ebnf(r#"
+///     list  = "[" items? "]" ;
+///     items = Number ("," Number)* ;
+/// "#).value;
+///
+/// let analysis = grammar.analyze();
+/// assert!(!analysis.is_nullable("items"));
+/// assert!(analysis.first("list").contains(&Lookahead::token("\"[\"")));
+/// assert_eq!(
+///     analysis.follow("items").iter().collect::<Vec<_>>(),
+///     [&Lookahead::token("\"]\"")]
+/// );
+/// assert_eq!(
+///     analysis.follow("list").iter().collect::<Vec<_>>(),
+///     [&Lookahead::End]
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Analysis {
+    nullable: BTreeSet<String>,
+    first: BTreeMap<String, BTreeSet<Lookahead>>,
+    follow: BTreeMap<String, BTreeSet<Lookahead>>,
+}
+
+impl Analysis {
+    pub fn new(grammar: &Grammar) -> Self {
+        let mut analysis = Analysis::default();
+        for rule in grammar.rules() {
+            analysis.first.insert(rule.name.clone(), BTreeSet::new());
+            analysis.follow.insert(rule.name.clone(), BTreeSet::new());
+        }
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for rule in grammar.rules() {
+                if !analysis.nullable.contains(&rule.name) && analysis.is_nullable_expr(&rule.expr)
+                {
+                    analysis.nullable.insert(rule.name.clone());
+                    changed = true;
+                }
+                let first = analysis.first_of(&rule.expr);
+                let set = analysis.first.get_mut(&rule.name).unwrap();
+                if !first.is_subset(set) {
+                    set.extend(first);
+                    changed = true;
+                }
+            }
+        }
+
+        if let Some(start) = grammar.start() {
+            analysis
+                .follow
+                .get_mut(&start.name)
+                .unwrap()
+                .insert(Lookahead::End);
+        }
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for rule in grammar.rules() {
+                let after = analysis.follow[&rule.name].clone();
+                changed |= analysis.add_follow(&rule.expr, &after);
+            }
+        }
+        analysis
+    }
+
+    /// Returns whether the rule `name` can match no tokens.
+    pub fn is_nullable(&self, name: &str) -> bool {
+        self.nullable.contains(name)
+    }
+
+    /// Returns the FIRST set of the rule `name`, empty for undefined rules.
+    pub fn first(&self, name: &str) -> &BTreeSet<Lookahead> {
+        self.first.get(name).unwrap_or(&EMPTY)
+    }
+
+    /// Returns the FOLLOW set of the rule `name`, empty for undefined
+    /// rules and rules no other rule refers to.
+    pub fn follow(&self, name: &str) -> &BTreeSet<Lookahead> {
+        self.follow.get(name).unwrap_or(&EMPTY)
+    }
+
+    /// Returns the nullable rules, sorted by name.
+    pub fn nullable_rules(&self) -> &BTreeSet<String> {
+        &self.nullable
+    }
+
+    /// Returns the FIRST sets of all rules, by name.
+    pub fn first_sets(&self) -> &BTreeMap<String, BTreeSet<Lookahead>> {
+        &self.first
+    }
+
+    /// Returns the FOLLOW sets of all rules, by name.
+    pub fn follow_sets(&self) -> &BTreeMap<String, BTreeSet<Lookahead>> {
+        &self.follow
+    }
+
+    /// Returns whether `expr` can match no tokens.
+    pub fn is_nullable_expr(&self, expr: &Expr) -> bool {
+        match expr {
+            Expr::Token(_) => false,
+            Expr::Rule(name) => self.nullable.contains(name),
+            Expr::Seq(exprs) => exprs.iter().all(|expr| self.is_nullable_expr(expr)),
+            Expr::Choice(alternatives) => alternatives
+                .iter()
+                .any(|alternative| self.is_nullable_expr(alternative)),
+            Expr::Optional(_) | Expr::Repeat(_) => true,
+            Expr::Repeat1(expr) => self.is_nullable_expr(expr),
+        }
+    }
+
+    /// Returns the token kinds the matches of `expr` can start with.
+    pub fn first_of(&self, expr: &Expr) -> BTreeSet<Lookahead> {
+        let mut first = BTreeSet::new();
+        self.add_first(expr, &mut first);
+        first
+    }
+
+    /// Returns the tokens that can come first when `expr` is followed by
+    /// tokens from `after`: the FIRST set of `expr`, plus `after` if `expr`
+    /// is nullable.
+    ///
+    /// This is the lookahead an LL(1) parser uses to choose `expr`, such as
+    /// an alternative of a rule with the FOLLOW set of the rule as `after`.
+    pub fn lookahead(&self, expr: &Expr, after: &BTreeSet<Lookahead>) -> BTreeSet<Lookahead> {
+        let mut lookahead = self.first_of(expr);
+        if self.is_nullable_expr(expr) {
+            lookahead.extend(after.iter().cloned());
+        }
+        lookahead
+    }
+
+    fn add_first(&self, expr: &Expr, first: &mut BTreeSet<Lookahead>) {
+        match expr {
+            Expr::Token(kind) => {
+                first.insert(Lookahead::token(kind));
+            }
+            Expr::Rule(name) => first.extend(self.first(name).iter().cloned()),
+            Expr::Seq(exprs) => {
+                for expr in exprs {
+                    self.add_first(expr, first);
+                    if !self.is_nullable_expr(expr) {
+                        break;
+                    }
+                }
+            }
+            Expr::Choice(alternatives) => {
+                for alternative in alternatives {
+                    self.add_first(alternative, first);
+                }
+            }
+            Expr::Optional(expr) | Expr::Repeat(expr) | Expr::Repeat1(expr) => {
+                self.add_first(expr, first)
+            }
+        }
+    }
+
+    /// Adds `after`, the tokens that can follow `expr`, to the FOLLOW sets
+    /// of the rules `expr` ends with, and so on inside it. Returns whether
+    /// any set grew.
+    fn add_follow(&mut self, expr: &Expr, after: &BTreeSet<Lookahead>) -> bool {
+        match expr {
+            Expr::Token(_) => false,
+            Expr::Rule(name) => match self.follow.get_mut(name) {
+                Some(follow) if !after.is_subset(follow) => {
+                    follow.extend(after.iter().cloned());
+                    true
+                }
+                _ => false,
+            },
+            Expr::Seq(exprs) => {
+                let mut changed = false;
+                let mut after = after.clone();
+                for expr in exprs.iter().rev() {
+                    changed |= self.add_follow(expr, &after);
+                    after = self.lookahead(expr, &after);
+                }
+                changed
+            }
+            Expr::Choice(alternatives) => {
+                let mut changed = false;
+                for alternative in alternatives {
+                    changed |= self.add_follow(alternative, after);
+                }
+                changed
+            }
+            Expr::Optional(expr) => self.add_follow(expr, after),
+            Expr::Repeat(inner) | Expr::Repeat1(inner) => {
+                let mut after = after.clone();
+                after.extend(self.first_of(inner));
+                self.add_follow(inner, &after)
+            }
+        }
+    }
+}
+
+static EMPTY: BTreeSet<Lookahead> = BTreeSet::new();
+
+impl Grammar {
+    /// Computes the nullable, FIRST and FOLLOW sets of the rules.
+    pub fn analyze(&self) -> Analysis {
+        Analysis::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(kinds: &[&str]) -> BTreeSet<Lookahead> {
+        kinds
+            .iter()
+            .map(|kind| match *kind {
+                "$" => Lookahead::End,
+                kind => Lookahead::token(kind),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_expression_grammar() {
+        // The classic grammar from the dragon book.
+        let grammar = Grammar::new()
+            .with_rule("e", Expr::seq([Expr::rule("t"), Expr::rule("e2")]))
+            .with_rule(
+                "e2",
+                Expr::optional(Expr::seq([
+                    Expr::token("Plus"),
+                    Expr::rule("t"),
+                    Expr::rule("e2"),
+                ])),
+            )
+            .with_rule("t", Expr::seq([Expr::rule("f"), Expr::rule("t2")]))
+            .with_rule(
+                "t2",
+                Expr::optional(Expr::seq([
+                    Expr::token("Star"),
+                    Expr::rule("f"),
+                    Expr::rule("t2"),
+                ])),
+            )
+            .with_rule(
+                "f",
+                Expr::choice([
+                    Expr::seq([
+                        Expr::token("LParen"),
+                        Expr::rule("e"),
+                        Expr::token("RParen"),
+                    ]),
+                    Expr::token("Id"),
+                ]),
+            );
+        let analysis = grammar.analyze();
+
+        assert_eq!(
+            analysis.nullable_rules().iter().collect::<Vec<_>>(),
+            ["e2", "t2"]
+        );
+        for rule in ["e", "t", "f"] {
+            assert_eq!(analysis.first(rule), &set(&["Id", "LParen"]), "{}", rule);
+        }
+        assert_eq!(analysis.first("e2"), &set(&["Plus"]));
+        assert_eq!(analysis.first("t2"), &set(&["Star"]));
+        assert_eq!(analysis.follow("e"), &set(&["RParen", "$"]));
+        assert_eq!(analysis.follow("e2"), &set(&["RParen", "$"]));
+        assert_eq!(analysis.follow("t"), &set(&["Plus", "RParen", "$"]));
+        assert_eq!(analysis.follow("t2"), &set(&["Plus", "RParen", "$"]));
+        assert_eq!(analysis.follow("f"), &set(&["Plus", "Star", "RParen", "$"]));
+    }
+
+    #[test]
+    fn test_repetitions_and_undefined_rules() {
+        let grammar = Grammar::new()
+            .with_rule(
+                "block",
+                Expr::seq([
+                    Expr::repeat(Expr::rule("stmt")),
+                    Expr::repeat1(Expr::rule("missing")),
+                ]),
+            )
+            .with_rule(
+                "stmt",
+                Expr::seq([Expr::optional(Expr::token("Let")), Expr::token("Id")]),
+            );
+        let analysis = grammar.analyze();
+
+        assert!(!analysis.is_nullable("block"));
+        assert_eq!(analysis.first("block"), &set(&["Id", "Let"]));
+        assert_eq!(analysis.follow("stmt"), &set(&["Id", "Let"]));
+        assert_eq!(analysis.first("missing"), &set(&[]));
+        assert_eq!(analysis.follow("missing"), &set(&[]));
+    }
+}
@@ -0,0 +1,652 @@
+use super::{Analysis, Expr, Grammar, LeftRecursion, Lookahead, Rule, SyncSets};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::{self, Write};
+
+/// An error generating a parser from a grammar.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CodegenError {
+    /// The grammar has no rules.
+    Empty,
+    /// A rule refers to a rule the grammar does not define.
+    UndefinedRule { rule: String, name: String },
+    /// A rule name is not a Rust identifier.
+    InvalidRuleName { rule: String },
+    /// A token kind is not a Rust identifier, and has no path given with
+    /// [`RustGenerator::with_kind`].
+    MissingKind { token: String },
+    /// The grammar is left-recursive, so the parser would loop forever.
+    LeftRecursive(LeftRecursion),
+}
+
+impl fmt::Display for CodegenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CodegenError::Empty => write!(f, "the grammar has no rules"),
+            CodegenError::UndefinedRule { rule, name } => {
+                write!(f, "rule `{}` refers to undefined rule `{}`", rule, name)
+            }
+            CodegenError::InvalidRuleName { rule } => {
+                write!(f, "rule name `{}` is not a Rust identifier", rule)
+            }
+            CodegenError::MissingKind { token } => {
+                write!(f, "no kind is defined for token `{}`", token)
+            }
+            CodegenError::LeftRecursive(cycle) => {
+                write!(f, "the grammar is left-recursive: {}", cycle)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CodegenError {}
+
+/// Generates a recursive-descent parser for a [`Grammar`] as Rust source,
+/// such as from a build script.
+///
+/// Each rule `name` becomes a function `parse_name` over a
+/// [`Parser`](crate::parser::Parser) of the token type, which is `Token` by
+/// default, with kinds of the kind type, `Kind` by default. Both must be in
+/// scope where the code is included. Token kinds are written as
+/// `Kind::Name`, unless given another path with
+/// [`with_kind`](RustGenerator::with_kind), as literals such as `"+"` must.
+///
+/// The functions choose between alternatives with one token of lookahead,
+/// from the FIRST sets of the grammar, preferring earlier alternatives on
+/// conflicts. Missing tokens are reported with
+/// [`expect_one_of`](crate::parser::Parser::expect_one_of); a rule in error
+/// skips to a token of its sync set and returns `None`, and the rule using
+/// it carries on. A rule with a custom [`ErrorMessage`](super::ErrorMessage)
+/// reports it instead where it cannot go on itself: on a missing token of
+/// its own, or a subrule that cannot start. The sync sets are the
+/// [`SyncSets`] of the grammar, with the tokens given with
+/// [`with_sync_token`](RustGenerator::with_sync_token), and are generated
+/// as constants, such as `SYNC_LIST` for the rule `list`. Each rule runs in
+/// [`Parser::rule`](crate::parser::Parser::rule), so the generated parser
+/// works with [`Coverage`](crate::parser::Coverage).
+///
+/// The values of the rules are built by an `Actions` trait, which is
+/// generated too. It gets the values of tokens from `token`, and the value
+/// of each rule from its children, the values of its tokens and subrules in
+/// order, and its span, computed with a
+/// [`Marker`](crate::parser::Marker). By default rules go to `node`, and
+/// each rule `name` has a hook `on_name` to override.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::grammar::*;
+///
+/// let grammar = parse_ebnf("list = \"[\" [Number {\",\" Number}] \"]\" ;").value;
+/// let code = RustGenerator::new(&grammar)
+///     .with_kind("\"[\"", "Kind::LBracket")
+///     .with_kind("\"]\"", "Kind::RBracket")
+///     .with_kind("\",\"", "Kind::Comma")
+///     .generate()
+///     .unwrap();
+/// assert!(code.contains("pub fn parse_list<'a, A, D>("));
+/// assert!(code.contains("if p.check_one_of(&[Kind::Number]) {"));
+/// assert!(code.contains("while p.check_one_of(&[Kind::Comma]) {"));
+///
+/// // In a build script, the code would be written to a file, to be
+/// // included with `include!(concat!(env!("OUT_DIR"), "/list.rs"))`:
+/// // std::fs::write(out_dir.join("list.rs"), code)?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct RustGenerator<'g> {
+    grammar: &'g Grammar,
+    token_type: String,
+    kind_type: String,
+    kinds: HashMap<String, String>,
+    sync_tokens: Vec<String>,
+}
+
+impl<'g> RustGenerator<'g> {
+    pub fn new(grammar: &'g Grammar) -> Self {
+        RustGenerator {
+            grammar,
+            token_type: "Token".to_string(),
+            kind_type: "Kind".to_string(),
+            kinds: HashMap::new(),
+            sync_tokens: Vec::new(),
+        }
+    }
+
+    /// Sets the path of the token type.
+    pub fn with_token_type(mut self, path: impl Into<String>) -> Self {
+        self.token_type = path.into();
+        self
+    }
+
+    /// Sets the path of the token kind type, used for the kinds without a
+    /// path of their own.
+    pub fn with_kind_type(mut self, path: impl Into<String>) -> Self {
+        self.kind_type = path.into();
+        self
+    }
+
+    /// Sets the path of the kind of `token`, such as `Kind::Plus` for `"+"`.
+    pub fn with_kind(mut self, token: impl Into<String>, path: impl Into<String>) -> Self {
+        self.kinds.insert(token.into(), path.into());
+        self
+    }
+
+    /// Adds the token kind `token` to the sync set of every rule, such as a
+    /// statement terminator.
+    pub fn with_sync_token(mut self, token: impl Into<String>) -> Self {
+        self.sync_tokens.push(token.into());
+        self
+    }
+
+    /// Generates the parser.
+    pub fn generate(&self) -> Result<String, CodegenError> {
+        if self.grammar.rules().is_empty() {
+            return Err(CodegenError::Empty);
+        }
+        for rule in self.grammar.rules() {
+            if !is_identifier(&rule.name) {
+                return Err(CodegenError::InvalidRuleName {
+                    rule: rule.name.clone(),
+                });
+            }
+            let mut undefined = None;
+            rule.expr.walk(&mut |expr| match expr {
+                Expr::Rule(name) if self.grammar.get(name).is_none() => {
+                    undefined.get_or_insert(name);
+                }
+                _ => {}
+            });
+            if let Some(name) = undefined {
+                return Err(CodegenError::UndefinedRule {
+                    rule: rule.name.clone(),
+                    name: name.clone(),
+                });
+            }
+        }
+        let mut kinds = HashMap::new();
+        let mut tokens = self.grammar.tokens();
+        tokens.extend(self.sync_tokens.iter().map(String::as_str));
+        for token in tokens {
+            let path = match self.kinds.get(token) {
+                Some(path) => path.clone(),
+                None if is_identifier(token) => format!("{}::{}", self.kind_type, token),
+                None => {
+                    return Err(CodegenError::MissingKind {
+                        token: token.to_string(),
+                    })
+                }
+            };
+            kinds.insert(token.to_string(), path);
+        }
+        let analysis = self.grammar.analyze();
+        if let Some(cycle) = analysis.left_recursion(self.grammar).into_iter().next() {
+            return Err(CodegenError::LeftRecursive(cycle));
+        }
+
+        let mut out = Code {
+            out: String::new(),
+            indent: 0,
+        };
+        out.line("// Generated by grammarsmith from a grammar description. Do not edit.");
+        out.line("");
+        self.actions(&mut out);
+        let sync = self
+            .sync_tokens
+            .iter()
+            .fold(analysis.sync_sets(self.grammar), |sync, token| {
+                sync.with_token(token.as_str())
+            });
+        let emitter = Emitter {
+            analysis: &analysis,
+            kinds: &kinds,
+            kind_type: &self.kind_type,
+            custom_error: false,
+        };
+        for rule in self.grammar.rules() {
+            out.line("");
+            emitter.sync_set(&mut out, &rule.name, &sync);
+            out.line("");
+            emitter.rule(&mut out, rule, &self.token_type);
+        }
+        Ok(out.out)
+    }
+
+    fn actions(&self, out: &mut Code) {
+        out.line("/// Builds the values of the rules of the grammar.");
+        out.line("///");
+        out.line("/// Rules are built by `node` unless their `on_*` hook is overridden.");
+        out.line("pub trait Actions<'a> {");
+        out.indent += 1;
+        out.line("type Node;");
+        out.line("");
+        out.line("/// Builds the value of a consumed token.");
+        out.line(&format!(
+            "fn token(&mut self, token: &'a grammarsmith::position::WithSpan<{}>) -> Self::Node;",
+            self.token_type
+        ));
+        out.line("");
+        out.line("/// Builds the value of a rule from the values of its tokens and subrules.");
+        out.line("fn node(");
+        out.indent += 1;
+        out.line("&mut self,");
+        out.line("rule: &'static str,");
+        out.line("span: grammarsmith::position::Span,");
+        out.line("children: Vec<Self::Node>,");
+        out.indent -= 1;
+        out.line(") -> Self::Node;");
+        for rule in self.grammar.rules() {
+            out.line("");
+            out.line(&format!(
+                "/// Builds the value of the rule `{}`.",
+                rule.name
+            ));
+            out.line(&format!("fn on_{}(", rule.name));
+            out.indent += 1;
+            out.line("&mut self,");
+            out.line("span: grammarsmith::position::Span,");
+            out.line("children: Vec<Self::Node>,");
+            out.indent -= 1;
+            out.line(") -> Self::Node {");
+            out.indent += 1;
+            out.line(&format!("self.node(\"{}\", span, children)", rule.name));
+            out.indent -= 1;
+            out.line("}");
+        }
+        out.indent -= 1;
+        out.line("}");
+    }
+}
+
+/// Indented lines of generated code.
+struct Code {
+    out: String,
+    indent: usize,
+}
+
+impl Code {
+    fn line(&mut self, line: &str) {
+        if !line.is_empty() {
+            let _ = write!(self.out, "{:width$}", "", width = self.indent * 4);
+        }
+        self.out.push_str(line);
+        self.out.push('\n');
+    }
+
+    fn open(&mut self, line: &str) {
+        self.line(line);
+        self.indent += 1;
+    }
+
+    fn close(&mut self, line: &str) {
+        self.indent -= 1;
+        self.line(line);
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Emitter<'a> {
+    analysis: &'a Analysis,
+    kinds: &'a HashMap<String, String>,
+    kind_type: &'a str,
+    /// Whether the current rule has a custom error message, so its errors
+    /// are collected to be rewritten.
+    custom_error: bool,
+}
+
+impl Emitter<'_> {
+    fn sync_set(&self, out: &mut Code, name: &str, sync: &SyncSets) {
+        let tokens: Option<BTreeSet<Lookahead>> =
+            sync.get(name).map(|set| set.iter().cloned().collect());
+        out.line(&format!(
+            "/// The tokens `parse_{}` skips to after an error.",
+            name
+        ));
+        out.line(&format!(
+            "pub const {}: &[{}] = &[{}];",
+            sync_name(name),
+            self.kind_type,
+            self.kinds_of(&tokens.unwrap_or_default())
+        ));
+    }
+
+    fn rule(&self, out: &mut Code, rule: &Rule, token_type: &str) {
+        let (name, expr) = (rule.name.as_str(), &rule.expr);
+        out.line(&format!("/// Parses the rule `{}`.", name));
+        out.line(&format!("pub fn parse_{}<'a, A, D>(", name));
+        out.indent += 1;
+        out.line(&format!(
+            "p: &mut grammarsmith::parser::Parser<'a, {}>,",
+            token_type
+        ));
+        out.line("actions: &mut A,");
+        out.line("sink: &mut D,");
+        out.indent -= 1;
+        out.line(") -> Option<A::Node>");
+        out.line("where");
+        out.indent += 1;
+        out.line("A: Actions<'a> + ?Sized,");
+        out.line("D: grammarsmith::diagnostics::DiagnosticSink + ?Sized,");
+        out.indent -= 1;
+        out.open("{");
+        out.line("let marker = p.mark();");
+        if !has_atoms(expr) {
+            out.line("let children = Vec::new();");
+            out.line(&format!("p.rule(\"{}\", |_| ());", name));
+        } else {
+            out.line("let mut children = Vec::new();");
+            if rule.error.is_some() {
+                out.line(
+                    "let mut errors: Vec<grammarsmith::diagnostics::Diagnostic> = Vec::new();",
+                );
+            }
+            let emitter = Emitter {
+                custom_error: rule.error.is_some(),
+                ..*self
+            };
+            out.open(&format!("let parsed = p.rule(\"{}\", |p| {{", name));
+            emitter.expr(out, expr);
+            out.line("Some(())");
+            out.close("});");
+            if let Some(error) = &rule.error {
+                out.open("for mut error in errors {");
+                out.line(&format!("error.message = {:?}.to_string();", error.message));
+                if let Some(hint) = &error.hint {
+                    out.line(&format!("error.notes.push({:?}.to_string());", hint));
+                }
+                out.line("sink.emit(error);");
+                out.close("}");
+            }
+            out.open("if parsed.is_none() {");
+            out.line(&format!("p.drop_until({});", sync_name(name)));
+            out.line("return None;");
+            out.close("}");
+        }
+        out.line(&format!(
+            "Some(actions.on_{}(p.span_since(marker), children))",
+            name
+        ));
+        out.close("}");
+    }
+
+    fn expr(&self, out: &mut Code, expr: &Expr) {
+        match expr {
+            Expr::Token(kind) => out.line(&format!(
+                "children.push(actions.token(p.expect_one_of(&[{}], {})?));",
+                self.kinds[kind],
+                self.sink()
+            )),
+            Expr::Rule(name) => {
+                // A rule with a custom error message reports the subrules
+                // that cannot start itself.
+                if self.custom_error && !self.analysis.is_nullable(name) {
+                    out.open(&format!("if !p.check_one_of(&[{}]) {{", self.first(expr)));
+                    out.line(&format!(
+                        "let _ = p.expect_one_of(&[{}], {});",
+                        self.first(expr),
+                        self.sink()
+                    ));
+                    out.line("return None;");
+                    out.close("}");
+                }
+                self.call(out, name);
+            }
+            Expr::Seq(exprs) => {
+                for expr in exprs {
+                    self.expr(out, expr);
+                }
+            }
+            Expr::Choice(alternatives) => self.choice(out, alternatives),
+            Expr::Optional(inner) => {
+                out.open(&format!("if p.check_one_of(&[{}]) {{", self.first(inner)));
+                self.checked_expr(out, inner);
+                out.close("}");
+            }
+            Expr::Repeat(inner) => {
+                out.open(&format!(
+                    "while p.check_one_of(&[{}]) {{",
+                    self.first(inner)
+                ));
+                self.repetition(out, inner, true);
+                out.close("}");
+            }
+            Expr::Repeat1(inner) => {
+                out.open("loop {");
+                self.repetition(out, inner, false);
+                out.open(&format!("if !p.check_one_of(&[{}]) {{", self.first(inner)));
+                out.line("break;");
+                out.close("}");
+                out.close("}");
+            }
+        }
+    }
+
+    /// Writes `expr` after its first token has been checked, so a subrule
+    /// it starts with can start.
+    fn checked_expr(&self, out: &mut Code, expr: &Expr) {
+        match expr {
+            Expr::Rule(name) => self.call(out, name),
+            Expr::Seq(exprs) => match exprs.split_first() {
+                Some((Expr::Rule(name), rest)) => {
+                    self.call(out, name);
+                    for expr in rest {
+                        self.expr(out, expr);
+                    }
+                }
+                _ => self.expr(out, expr),
+            },
+            _ => self.expr(out, expr),
+        }
+    }
+
+    fn call(&self, out: &mut Code, name: &str) {
+        out.open(&format!(
+            "if let Some(node) = parse_{}(p, actions, sink) {{",
+            name
+        ));
+        out.line("children.push(node);");
+        out.close("}");
+    }
+
+    /// Writes an iteration of a repetition, which stops the loop if it
+    /// consumed no token, as it would loop forever after an error.
+    fn repetition(&self, out: &mut Code, inner: &Expr, checked: bool) {
+        out.line("let before = p.mark();");
+        if checked {
+            self.checked_expr(out, inner);
+        } else {
+            self.expr(out, inner);
+        }
+        out.open("if p.mark() == before {");
+        out.line("break;");
+        out.close("}");
+    }
+
+    fn choice(&self, out: &mut Code, alternatives: &[Expr]) {
+        // The first alternative that can match nothing is taken when no
+        // other can start with the current token.
+        let mut fallback = None;
+        let mut branches = Vec::new();
+        for alternative in alternatives {
+            if self.analysis.is_nullable_expr(alternative) {
+                fallback.get_or_insert(alternative);
+            } else {
+                branches.push(alternative);
+            }
+        }
+        let Some((first, rest)) = branches.split_first() else {
+            if let Some(alternative) = fallback {
+                self.expr(out, alternative);
+            }
+            return;
+        };
+        out.open(&format!("if p.check_one_of(&[{}]) {{", self.first(first)));
+        self.checked_expr(out, first);
+        for alternative in rest {
+            out.indent -= 1;
+            out.open(&format!(
+                "}} else if p.check_one_of(&[{}]) {{",
+                self.first(alternative)
+            ));
+            self.checked_expr(out, alternative);
+        }
+        match fallback {
+            Some(alternative) if has_atoms(alternative) => {
+                out.indent -= 1;
+                out.open("} else {");
+                self.expr(out, alternative);
+            }
+            Some(_) => {}
+            None => {
+                let mut first = BTreeSet::new();
+                for alternative in &branches {
+                    first.extend(self.analysis.first_of(alternative));
+                }
+                out.indent -= 1;
+                out.open("} else {");
+                out.line(&format!(
+                    "let _ = p.expect_one_of(&[{}], {});",
+                    self.kinds_of(&first),
+                    self.sink()
+                ));
+                out.line("return None;");
+            }
+        }
+        out.close("}");
+    }
+
+    /// Returns where the errors of the current rule go.
+    fn sink(&self) -> &'static str {
+        if self.custom_error {
+            "&mut errors"
+        } else {
+            "sink"
+        }
+    }
+
+    fn first(&self, expr: &Expr) -> String {
+        self.kinds_of(&self.analysis.first_of(expr))
+    }
+
+    /// Writes the kinds of a set of tokens, leaving out the end of input.
+    fn kinds_of(&self, tokens: &BTreeSet<Lookahead>) -> String {
+        let kinds: Vec<&str> = tokens
+            .iter()
+            .filter_map(|token| match token {
+                Lookahead::Token(kind) => Some(self.kinds[kind].as_str()),
+                Lookahead::End => None,
+            })
+            .collect();
+        kinds.join(", ")
+    }
+}
+
+/// Returns whether `expr` matches any token or rule, and so whether the
+/// code parsing it uses the parser.
+fn has_atoms(expr: &Expr) -> bool {
+    let mut found = false;
+    expr.walk(&mut |expr| found |= matches!(expr, Expr::Token(_) | Expr::Rule(_)));
+    found
+}
+
+fn sync_name(rule: &str) -> String {
+    format!("SYNC_{}", rule.to_uppercase())
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::parse_ebnf;
+
+    #[test]
+    fn test_errors() {
+        let generate = |source: &str| RustGenerator::new(&parse_ebnf(source).value).generate();
+        assert_eq!(generate(""), Err(CodegenError::Empty));
+        assert_eq!(
+            generate("a = b ;"),
+            Err(CodegenError::UndefinedRule {
+                rule: "a".to_string(),
+                name: "b".to_string()
+            })
+        );
+        assert_eq!(
+            generate("a = \"+\" ;").unwrap_err().to_string(),
+            "no kind is defined for token `\"+\"`"
+        );
+        assert_eq!(
+            generate("a = a X | Y ;").unwrap_err().to_string(),
+            "the grammar is left-recursive: a -> a"
+        );
+        let grammar = Grammar::new().with_rule("a b", Expr::token("X"));
+        assert_eq!(
+            RustGenerator::new(&grammar).generate(),
+            Err(CodegenError::InvalidRuleName {
+                rule: "a b".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_choice() {
+        let grammar = parse_ebnf("a = X | Y Z | () ; b = X | Y ; c = () ;").value;
+        let code = RustGenerator::new(&grammar)
+            .with_kind_type("tokens::Kind")
+            .generate()
+            .unwrap();
+        assert!(code.contains(
+            "        if p.check_one_of(&[tokens::Kind::X]) {
+            children.push(actions.token(p.expect_one_of(&[tokens::Kind::X], sink)?));
+        } else if p.check_one_of(&[tokens::Kind::Y]) {
+            children.push(actions.token(p.expect_one_of(&[tokens::Kind::Y], sink)?));
+            children.push(actions.token(p.expect_one_of(&[tokens::Kind::Z], sink)?));
+        }
+        Some(())
+"
+        ));
+        assert!(code.contains(
+            "        } else {
+            let _ = p.expect_one_of(&[tokens::Kind::X, tokens::Kind::Y], sink);
+            return None;
+        }
+"
+        ));
+        assert!(code.contains("    p.rule(\"c\", |_| ());\n"));
+    }
+
+    #[test]
+    fn test_custom_error() {
+        let grammar =
+            parse_ebnf(r#"if = If cond on_error("expected a condition") ; cond = X ;"#).value;
+        let code = RustGenerator::new(&grammar).generate().unwrap();
+        assert!(code.contains(
+            "        children.push(actions.token(p.expect_one_of(&[Kind::If], &mut errors)?));
+        if !p.check_one_of(&[Kind::X]) {
+            let _ = p.expect_one_of(&[Kind::X], &mut errors);
+            return None;
+        }
+        if let Some(node) = parse_cond(p, actions, sink) {
+"
+        ));
+        assert!(code.contains("        error.message = \"expected a condition\".to_string();\n"));
+        assert!(code.contains(
+            "        children.push(actions.token(p.expect_one_of(&[Kind::X], sink)?));\n"
+        ));
+    }
+
+    #[test]
+    fn test_sync_sets() {
+        let grammar = parse_ebnf("block = LBrace stmt* RBrace ; stmt = Id Number ;").value;
+        let code = RustGenerator::new(&grammar)
+            .with_sync_token("Semicolon")
+            .generate()
+            .unwrap();
+        assert!(code.contains(
+            "pub const SYNC_STMT: &[Kind] = &[Kind::Id, Kind::RBrace, Kind::Semicolon];\n"
+        ));
+        assert!(code.contains("pub const SYNC_BLOCK: &[Kind] = &[Kind::Semicolon];\n"));
+        assert!(code.contains("        p.drop_until(SYNC_STMT);\n"));
+    }
+}
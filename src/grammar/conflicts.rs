@@ -0,0 +1,304 @@
+use super::{Analysis, Expr, Grammar, Lookahead};
+use crate::diagnostics::{expected_list, Diagnostic};
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// The kind of an LL(1) [`Conflict`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ConflictKind {
+    /// Two alternatives can start with the same token.
+    FirstFirst,
+    /// A part that can match nothing can be followed by a token that can
+    /// also start it, or start another alternative.
+    FirstFollow,
+}
+
+impl fmt::Display for ConflictKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConflictKind::FirstFirst => write!(f, "FIRST/FIRST"),
+            ConflictKind::FirstFollow => write!(f, "FIRST/FOLLOW"),
+        }
+    }
+}
+
+/// A choice that a parser looking one token ahead cannot make.
+///
+/// Expressions are identified by their index in the order [`Expr::walk`]
+/// visits the body of the rule, so their spans can be found with
+/// [`Rule::expr_span`](super::Rule::expr_span).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Conflict {
+    pub kind: ConflictKind,
+    /// The rule the conflict is in.
+    pub rule: String,
+    /// The choice, optional part or repetition where the parser cannot
+    /// decide.
+    pub expr: usize,
+    /// For choices, the two alternatives in conflict, the first being the
+    /// one that can match nothing in FIRST/FOLLOW conflicts. For optional
+    /// parts and repetitions, the repeated expression alone.
+    pub alternatives: Vec<usize>,
+    /// The tokens on which the parser cannot decide.
+    pub tokens: BTreeSet<Lookahead>,
+}
+
+impl Conflict {
+    /// Converts the conflict into an error pointing at the alternatives in
+    /// conflict, if `grammar` was read from a source.
+    pub fn to_diagnostic(&self, grammar: &Grammar) -> Diagnostic {
+        let names: Vec<String> = self
+            .tokens
+            .iter()
+            .map(|token| match token {
+                Lookahead::Token(_) => format!("`{}`", token),
+                Lookahead::End => token.to_string(),
+            })
+            .collect();
+        let tokens = expected_list(names.iter().map(String::as_str));
+        let mut diagnostic =
+            Diagnostic::error(format!("{} conflict in rule `{}`", self.kind, self.rule));
+        let rule = grammar.get(&self.rule);
+        let span = |index: usize| rule.and_then(|rule| rule.expr_spans.get(index).copied());
+        match (self.kind, self.alternatives.as_slice()) {
+            (ConflictKind::FirstFirst, &[first, second]) => {
+                if let Some(span) = span(second) {
+                    diagnostic = diagnostic
+                        .with_label(span, format!("this alternative can start with {}", tokens));
+                }
+                if let Some(span) = span(first) {
+                    diagnostic = diagnostic.with_secondary_label(span, "and so can this one");
+                }
+                diagnostic.with_note(
+                    "a parser looking one token ahead cannot choose between the alternatives",
+                )
+            }
+            (_, &[empty, other]) => {
+                if let Some(span) = span(other) {
+                    diagnostic = diagnostic
+                        .with_label(span, format!("this alternative can start with {}", tokens));
+                }
+                if let Some(span) = span(empty) {
+                    diagnostic = diagnostic.with_secondary_label(
+                        span,
+                        format!(
+                            "this alternative can match nothing and be followed by {}",
+                            tokens
+                        ),
+                    );
+                }
+                diagnostic.with_note(
+                    "a parser looking one token ahead cannot choose between the alternatives",
+                )
+            }
+            _ => {
+                if let Some(span) = span(self.expr) {
+                    diagnostic = diagnostic.with_label(
+                        span,
+                        format!("{} can both start this part and follow it", tokens),
+                    );
+                }
+                diagnostic.with_note(
+                    "a parser looking one token ahead cannot tell whether to match this part",
+                )
+            }
+        }
+    }
+}
+
+impl Analysis {
+    /// Finds the LL(1) conflicts of `grammar`, the grammar this analysis
+    /// was computed for, in the order of its rules.
+    ///
+    /// # Examples
+    /// ```
+    /// use grammarsmith::grammar::*;
+    ///
+    /// let source = "stmt = Id \"=\" Number | Id \"(\" \")\" ;";
+    /// let grammar = parse_ebnf(source).value;
+    /// let conflicts = grammar.analyze().conflicts(&grammar);
+    /// assert_eq!(conflicts.len(), 1);
+    /// assert_eq!(conflicts[0].kind, ConflictKind::FirstFirst);
+    ///
+    /// let diagnostic = conflicts[0].to_diagnostic(&grammar);
+    /// assert_eq!(diagnostic.message, "FIRST/FIRST conflict in rule `stmt`");
+    /// let span = diagnostic.primary_span().unwrap();
+    /// assert_eq!(&source[span.start()..span.end()], "Id \"(\" \")\"");
+    /// ```
+    pub fn conflicts(&self, grammar: &Grammar) -> Vec<Conflict> {
+        let mut finder = ConflictFinder {
+            analysis: self,
+            rule: "",
+            conflicts: Vec::new(),
+        };
+        for rule in grammar.rules() {
+            finder.rule = &rule.name;
+            finder.visit(&rule.expr, 0, self.follow(&rule.name));
+        }
+        finder.conflicts
+    }
+}
+
+impl Grammar {
+    /// Reports the LL(1) conflicts of the grammar as errors.
+    pub fn check_ll1(&self) -> Vec<Diagnostic> {
+        self.analyze()
+            .conflicts(self)
+            .iter()
+            .map(|conflict| conflict.to_diagnostic(self))
+            .collect()
+    }
+}
+
+struct ConflictFinder<'a> {
+    analysis: &'a Analysis,
+    rule: &'a str,
+    conflicts: Vec<Conflict>,
+}
+
+impl ConflictFinder<'_> {
+    /// Looks for conflicts in `expr`, the `index`th expression of the rule,
+    /// followed by tokens from `after`.
+    fn visit(&mut self, expr: &Expr, index: usize, after: &BTreeSet<Lookahead>) {
+        let analysis = self.analysis;
+        match expr {
+            Expr::Token(_) | Expr::Rule(_) => {}
+            Expr::Seq(exprs) => {
+                let mut afters = Vec::with_capacity(exprs.len());
+                let mut next = after.clone();
+                for expr in exprs.iter().rev() {
+                    let lookahead = analysis.lookahead(expr, &next);
+                    afters.push(next);
+                    next = lookahead;
+                }
+                let mut child = index + 1;
+                for (expr, after) in exprs.iter().zip(afters.iter().rev()) {
+                    self.visit(expr, child, after);
+                    child += size(expr);
+                }
+            }
+            Expr::Choice(alternatives) => {
+                let mut seen: Vec<(usize, bool, BTreeSet<Lookahead>, BTreeSet<Lookahead>)> =
+                    Vec::new();
+                let mut child = index + 1;
+                for alternative in alternatives {
+                    let nullable = analysis.is_nullable_expr(alternative);
+                    let first = analysis.first_of(alternative);
+                    let lookahead = analysis.lookahead(alternative, after);
+                    for (previous, previous_nullable, previous_first, previous_lookahead) in &seen {
+                        let tokens: BTreeSet<_> = previous_lookahead
+                            .intersection(&lookahead)
+                            .cloned()
+                            .collect();
+                        if tokens.is_empty() {
+                            continue;
+                        }
+                        let (kind, alternatives) = if !previous_first.is_disjoint(&first) {
+                            (ConflictKind::FirstFirst, vec![*previous, child])
+                        } else if *previous_nullable || !nullable {
+                            (ConflictKind::FirstFollow, vec![*previous, child])
+                        } else {
+                            (ConflictKind::FirstFollow, vec![child, *previous])
+                        };
+                        self.conflicts.push(Conflict {
+                            kind,
+                            rule: self.rule.to_string(),
+                            expr: index,
+                            alternatives,
+                            tokens,
+                        });
+                    }
+                    self.visit(alternative, child, after);
+                    seen.push((child, nullable, first, lookahead));
+                    child += size(alternative);
+                }
+            }
+            Expr::Optional(inner) | Expr::Repeat(inner) | Expr::Repeat1(inner) => {
+                let first = analysis.first_of(inner);
+                let tokens: BTreeSet<_> = first.intersection(after).cloned().collect();
+                if !tokens.is_empty() {
+                    self.conflicts.push(Conflict {
+                        kind: ConflictKind::FirstFollow,
+                        rule: self.rule.to_string(),
+                        expr: index,
+                        alternatives: vec![index + 1],
+                        tokens,
+                    });
+                }
+                if matches!(expr, Expr::Optional(_)) {
+                    self.visit(inner, index + 1, after);
+                } else {
+                    let mut after = after.clone();
+                    after.extend(first);
+                    self.visit(inner, index + 1, &after);
+                }
+            }
+        }
+    }
+}
+
+/// Returns the number of expressions [`Expr::walk`] visits in `expr`.
+fn size(expr: &Expr) -> usize {
+    let mut size = 0;
+    expr.walk(&mut |_| size += 1);
+    size
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::parse_ebnf;
+
+    fn conflicts(source: &str) -> Vec<Conflict> {
+        let grammar = parse_ebnf(source).value;
+        grammar.analyze().conflicts(&grammar)
+    }
+
+    #[test]
+    fn test_ll1_grammar_has_no_conflicts() {
+        let source = "
+            e  = t e2 ;
+            e2 = [Plus t e2] ;
+            t  = f t2 ;
+            t2 = [Star f t2] ;
+            f  = LParen e RParen | Id ;
+        ";
+        assert_eq!(conflicts(source), []);
+    }
+
+    #[test]
+    fn test_first_follow_conflicts() {
+        // The dangling else: `Else` can both start the optional part and
+        // follow the statement.
+        let source = "
+            stmt = If Cond stmt [Else stmt] | Other ;
+            list = item* Id ;
+            item = Id | () ;
+        ";
+        let grammar = parse_ebnf(source).value;
+        let found = grammar.analyze().conflicts(&grammar);
+        assert_eq!(found.len(), 3);
+        assert_eq!(found[0].kind, ConflictKind::FirstFollow);
+        assert_eq!(found[0].rule, "stmt");
+        assert_eq!(found[0].tokens, BTreeSet::from([Lookahead::token("Else")]));
+
+        assert_eq!(found[1].rule, "list");
+        assert_eq!(found[1].alternatives, [2]);
+        assert_eq!(found[1].tokens, BTreeSet::from([Lookahead::token("Id")]));
+        assert_eq!(found[2].rule, "item");
+        assert_eq!(found[2].alternatives, [2, 1]);
+
+        let diagnostic = found[0].to_diagnostic(&grammar);
+        let span = diagnostic.primary_span().unwrap();
+        assert_eq!(&source[span.start()..span.end()], "[Else stmt]");
+        assert_eq!(
+            diagnostic.labels[0].message,
+            "`Else` can both start this part and follow it"
+        );
+        let diagnostic = found[2].to_diagnostic(&grammar);
+        assert_eq!(
+            diagnostic.labels[1].message,
+            "this alternative can match nothing and be followed by `Id`"
+        );
+    }
+}
@@ -0,0 +1,481 @@
+use super::{Assoc, Import, PrecedenceLevel, Terminal};
+use crate::position::Span;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// The body of a grammar rule.
+///
+/// Terminals are token kinds, referred to by name; nonterminals are other
+/// rules of the grammar. By convention token kind names start with an
+/// uppercase letter and rule names with a lowercase one, which is how the
+/// [`Display`](fmt::Display) notation tells them apart.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Expr {
+    /// A token of the named kind.
+    Token(String),
+    /// A reference to the named rule.
+    Rule(String),
+    /// The expressions in order; the empty sequence matches nothing.
+    Seq(Vec<Expr>),
+    /// One of the alternatives, preferring earlier ones.
+    Choice(Vec<Expr>),
+    /// The expression or nothing.
+    Optional(Box<Expr>),
+    /// The expression zero or more times.
+    Repeat(Box<Expr>),
+    /// The expression one or more times.
+    Repeat1(Box<Expr>),
+}
+
+impl Expr {
+    pub fn token(kind: impl Into<String>) -> Self {
+        Expr::Token(kind.into())
+    }
+
+    pub fn rule(name: impl Into<String>) -> Self {
+        Expr::Rule(name.into())
+    }
+
+    pub fn seq(exprs: impl IntoIterator<Item = Expr>) -> Self {
+        Expr::Seq(exprs.into_iter().collect())
+    }
+
+    pub fn choice(alternatives: impl IntoIterator<Item = Expr>) -> Self {
+        Expr::Choice(alternatives.into_iter().collect())
+    }
+
+    pub fn optional(expr: Expr) -> Self {
+        Expr::Optional(Box::new(expr))
+    }
+
+    pub fn repeat(expr: Expr) -> Self {
+        Expr::Repeat(Box::new(expr))
+    }
+
+    pub fn repeat1(expr: Expr) -> Self {
+        Expr::Repeat1(Box::new(expr))
+    }
+
+    /// Returns the expression matching nothing.
+    pub fn empty() -> Self {
+        Expr::Seq(Vec::new())
+    }
+
+    /// Calls `f` on this expression and all expressions nested in it,
+    /// parents before children.
+    pub fn walk<'a>(&'a self, f: &mut impl FnMut(&'a Expr)) {
+        f(self);
+        match self {
+            Expr::Token(_) | Expr::Rule(_) => {}
+            Expr::Seq(exprs) | Expr::Choice(exprs) => {
+                for expr in exprs {
+                    expr.walk(f);
+                }
+            }
+            Expr::Optional(expr) | Expr::Repeat(expr) | Expr::Repeat1(expr) => expr.walk(f),
+        }
+    }
+
+    pub(super) fn precedence(&self) -> u8 {
+        match self {
+            Expr::Seq(exprs) | Expr::Choice(exprs) if exprs.len() == 1 => exprs[0].precedence(),
+            Expr::Choice(alternatives) if alternatives.len() > 1 => 0,
+            Expr::Seq(exprs) if exprs.len() > 1 => 1,
+            Expr::Optional(_) | Expr::Repeat(_) | Expr::Repeat1(_) => 2,
+            _ => 3,
+        }
+    }
+
+    fn fmt_at(&self, f: &mut fmt::Formatter<'_>, precedence: u8) -> fmt::Result {
+        if self.precedence() < precedence {
+            write!(f, "(")?;
+            self.fmt_at(f, 0)?;
+            return write!(f, ")");
+        }
+        match self {
+            Expr::Token(name) | Expr::Rule(name) => write!(f, "{}", name),
+            Expr::Seq(exprs) if exprs.is_empty() => write!(f, "()"),
+            Expr::Seq(exprs) => {
+                for (i, expr) in exprs.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    expr.fmt_at(f, 2)?;
+                }
+                Ok(())
+            }
+            Expr::Choice(alternatives) if alternatives.is_empty() => write!(f, "()"),
+            Expr::Choice(alternatives) => {
+                for (i, alternative) in alternatives.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " | ")?;
+                    }
+                    alternative.fmt_at(f, 1)?;
+                }
+                Ok(())
+            }
+            Expr::Optional(expr) => {
+                expr.fmt_at(f, 3)?;
+                write!(f, "?")
+            }
+            Expr::Repeat(expr) => {
+                expr.fmt_at(f, 3)?;
+                write!(f, "*")
+            }
+            Expr::Repeat1(expr) => {
+                expr.fmt_at(f, 3)?;
+                write!(f, "+")
+            }
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_at(f, 0)
+    }
+}
+
+/// A custom error message of a [`Rule`], reported in place of the generic
+/// "expected ..." message where the rule cannot go on, written
+/// `on_error("message", "hint")` in the EBNF notation.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ErrorMessage {
+    pub message: String,
+    /// A note on how to fix the error, if any.
+    pub hint: Option<String>,
+}
+
+impl ErrorMessage {
+    pub fn new(message: impl Into<String>) -> Self {
+        ErrorMessage {
+            message: message.into(),
+            hint: None,
+        }
+    }
+
+    pub fn with_hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+}
+
+impl fmt::Display for ErrorMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "on_error({}", quoted(&self.message))?;
+        if let Some(hint) = &self.hint {
+            write!(f, ", {}", quoted(hint))?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// Quotes `text` as a literal of the EBNF notation, with single quotes if
+/// it holds double ones.
+fn quoted(text: &str) -> String {
+    if text.contains('"') {
+        format!("'{}'", text)
+    } else {
+        format!("\"{}\"", text)
+    }
+}
+
+/// A named rule of a [`Grammar`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Rule {
+    pub name: String,
+    pub expr: Expr,
+    /// Where the rule is defined, if the grammar was read from a source.
+    pub span: Option<Span>,
+    /// The spans of the expressions of the rule, in the order
+    /// [`Expr::walk`] visits them, if the grammar was read from a source.
+    pub expr_spans: Vec<Span>,
+    /// The message to report where the rule cannot go on, if any.
+    pub error: Option<ErrorMessage>,
+}
+
+impl Rule {
+    pub fn new(name: impl Into<String>, expr: Expr) -> Self {
+        Rule {
+            name: name.into(),
+            expr,
+            span: None,
+            expr_spans: Vec::new(),
+            error: None,
+        }
+    }
+
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    /// Sets the spans of the expressions, in the order [`Expr::walk`]
+    /// visits them.
+    pub fn with_expr_spans(mut self, spans: Vec<Span>) -> Self {
+        self.expr_spans = spans;
+        self
+    }
+
+    pub fn with_error(mut self, error: ErrorMessage) -> Self {
+        self.error = Some(error);
+        self
+    }
+
+    /// Returns the span of the `index`th expression visited by
+    /// [`Expr::walk`], the whole body being the first, or else the span of
+    /// the rule.
+    pub fn expr_span(&self, index: usize) -> Option<Span> {
+        self.expr_spans.get(index).copied().or(self.span)
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} = {}", self.name, self.expr)?;
+        if let Some(error) = &self.error {
+            write!(f, " {}", error)?;
+        }
+        write!(f, " ;")
+    }
+}
+
+/// The definition of a token kind of a [`Grammar`], for
+/// [`Lexer`](super::Lexer).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TokenDef {
+    pub name: String,
+    pub terminal: Terminal,
+    /// Breaks ties between definitions matching text of the same length,
+    /// higher first. Defaults to 0.
+    pub priority: i32,
+    /// Whether matches are skipped rather than made tokens, as for
+    /// whitespace and comments.
+    pub skip: bool,
+    /// Where the definition is, if the grammar was read from a source.
+    pub span: Option<Span>,
+}
+
+impl TokenDef {
+    pub fn new(name: impl Into<String>, terminal: Terminal) -> Self {
+        TokenDef {
+            name: name.into(),
+            terminal,
+            priority: 0,
+            skip: false,
+            span: None,
+        }
+    }
+
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Makes matches skipped rather than tokens.
+    pub fn skipped(mut self) -> Self {
+        self.skip = true;
+        self
+    }
+
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = Some(span);
+        self
+    }
+}
+
+impl fmt::Display for TokenDef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let keyword = if self.skip { "skip" } else { "token" };
+        write!(f, "{} {} = ", keyword, self.name)?;
+        match &self.terminal {
+            Terminal::Literal(text) => write!(f, "{}", quoted(text))?,
+            Terminal::Pattern(regex) => write!(f, "/{}/", regex)?,
+        }
+        if self.priority != 0 {
+            write!(f, " priority {}", self.priority)?;
+        }
+        write!(f, " ;")
+    }
+}
+
+/// A grammar: an ordered list of rules, the first of which is the start
+/// rule, the definitions and operator precedence of its token kinds, if it
+/// has any, and the other grammars it imports rules from.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Grammar {
+    rules: Vec<Rule>,
+    token_defs: Vec<TokenDef>,
+    precedence: Vec<PrecedenceLevel>,
+    imports: Vec<Import>,
+}
+
+impl Grammar {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a rule, replacing any rule of the same name in place.
+    pub fn add(&mut self, rule: Rule) {
+        match self.rules.iter_mut().find(|r| r.name == rule.name) {
+            Some(existing) => *existing = rule,
+            None => self.rules.push(rule),
+        }
+    }
+
+    /// Adds the rule `name = expr`, replacing any rule of the same name.
+    pub fn with_rule(mut self, name: impl Into<String>, expr: Expr) -> Self {
+        self.add(Rule::new(name, expr));
+        self
+    }
+
+    /// Returns the rule with the given name.
+    pub fn get(&self, name: &str) -> Option<&Rule> {
+        self.rules.iter().find(|rule| rule.name == name)
+    }
+
+    /// Returns the start rule, the first one added.
+    pub fn start(&self) -> Option<&Rule> {
+        self.rules.first()
+    }
+
+    /// Returns the rules in the order they were added.
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    /// Adds a token definition, replacing any definition of the same name in
+    /// place.
+    pub fn add_token_def(&mut self, def: TokenDef) {
+        match self.token_defs.iter_mut().find(|d| d.name == def.name) {
+            Some(existing) => *existing = def,
+            None => self.token_defs.push(def),
+        }
+    }
+
+    /// Adds the definition of the token kind `name`, replacing any
+    /// definition of the same name.
+    pub fn with_token_def(mut self, name: impl Into<String>, terminal: Terminal) -> Self {
+        self.add_token_def(TokenDef::new(name, terminal));
+        self
+    }
+
+    /// Returns the definition of the token kind with the given name.
+    pub fn token_def(&self, name: &str) -> Option<&TokenDef> {
+        self.token_defs.iter().find(|def| def.name == name)
+    }
+
+    /// Returns the token definitions in the order they were added.
+    pub fn token_defs(&self) -> &[TokenDef] {
+        &self.token_defs
+    }
+
+    /// Adds a precedence level, binding tighter than the levels added
+    /// before it. See [`Grammar::resolve_precedence`].
+    pub fn add_precedence(&mut self, level: PrecedenceLevel) {
+        self.precedence.push(level);
+    }
+
+    /// Adds a precedence level of the operators `tokens`, binding tighter
+    /// than the levels added before it.
+    pub fn with_precedence(
+        mut self,
+        assoc: Assoc,
+        tokens: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.add_precedence(PrecedenceLevel::new(assoc, tokens));
+        self
+    }
+
+    /// Returns the precedence levels, loosest first.
+    pub fn precedence(&self) -> &[PrecedenceLevel] {
+        &self.precedence
+    }
+
+    /// Adds an import, to be resolved with [`Grammar::resolve_imports`].
+    pub fn add_import(&mut self, import: Import) {
+        self.imports.push(import);
+    }
+
+    /// Adds an import of the grammar at `path`, with its rules named
+    /// `namespace.rule`, or as they are for `None`.
+    pub fn with_import(mut self, path: impl Into<String>, namespace: Option<&str>) -> Self {
+        let mut import = Import::new(path);
+        if let Some(namespace) = namespace {
+            import = import.with_namespace(namespace);
+        }
+        self.add_import(import);
+        self
+    }
+
+    /// Returns the imports in the order they were added.
+    pub fn imports(&self) -> &[Import] {
+        &self.imports
+    }
+
+    /// Returns the names of the token kinds used by the grammar, in order of
+    /// first use.
+    pub fn tokens(&self) -> Vec<&str> {
+        let mut tokens = Vec::new();
+        for rule in &self.rules {
+            rule.expr.walk(&mut |expr| {
+                if let Expr::Token(name) = expr {
+                    if !tokens.contains(&name.as_str()) {
+                        tokens.push(name.as_str());
+                    }
+                }
+            });
+        }
+        tokens
+    }
+}
+
+impl fmt::Display for Grammar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for import in &self.imports {
+            writeln!(f, "{}", import)?;
+        }
+        for rule in &self.rules {
+            writeln!(f, "{}", rule)?;
+        }
+        for level in &self.precedence {
+            writeln!(f, "{}", level)?;
+        }
+        for def in &self.token_defs {
+            writeln!(f, "{}", def)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_parenthesizes() {
+        let expr = Expr::seq([
+            Expr::choice([
+                Expr::token("A"),
+                Expr::seq([Expr::token("B"), Expr::rule("c")]),
+            ]),
+            Expr::optional(Expr::choice([Expr::token("D"), Expr::token("E")])),
+            Expr::repeat1(Expr::token("F")),
+            Expr::empty(),
+        ]);
+        assert_eq!(expr.to_string(), "(A | B c) (D | E)? F+ ()");
+    }
+
+    #[test]
+    fn test_add_replaces() {
+        let mut grammar = Grammar::new()
+            .with_rule("a", Expr::token("X"))
+            .with_rule("b", Expr::repeat(Expr::token("Y")));
+        grammar.add(Rule::new("a", Expr::token("Z")));
+        assert_eq!(grammar.rules().len(), 2);
+        assert_eq!(grammar.start().unwrap().expr, Expr::token("Z"));
+        assert_eq!(grammar.tokens(), ["Z", "Y"]);
+    }
+}
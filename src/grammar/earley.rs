@@ -0,0 +1,433 @@
+use super::forest::{ForestBuilder, NodeId, Packed, ParseForest};
+use super::table::{Bnf, Symbol};
+use super::Grammar;
+use crate::diagnostics::{expected_list, Diagnostic};
+use crate::parser::ParseOutput;
+use crate::position::{Span, WithSpan};
+use crate::tree::ParseTree;
+use std::collections::{BTreeSet, HashSet};
+
+/// An Earley item: a production, how many of its symbols are matched, and
+/// the position its match starts at.
+type Item = (usize, usize, usize);
+
+/// An Earley parser interpreting any grammar, for trying a grammar out
+/// before settling on an [`LlTable`](super::LlTable), an
+/// [`LalrTable`](super::LalrTable), generated code or a handwritten parser.
+///
+/// It takes the grammar as it is written, with left recursion, ambiguities
+/// and rules that match nothing, and parses into a [`ParseForest`] of every
+/// parse, or into the first of its trees with
+/// [`parse_tree`](EarleyParser::parse_tree). A grammar where a rule can
+/// derive itself and nothing else has inputs with infinitely many trees,
+/// and the forest leaves out those that go round such a cycle.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::grammar::*;
+/// use grammarsmith::position::{Span, WithSpan};
+/// use grammarsmith::tree::ParseTree;
+///
+/// let grammar = parse_ebnf(r#"
+///     list = list "," item | item ;
+///     item = Id? ;
+/// "#).value;
+/// let parser = EarleyParser::new(&grammar);
+///
+/// // a,,b
+/// let kinds = ["Id", "\",\"", "\",\"", "Id"];
+/// let tokens: Vec<_> = kinds
+///     .iter()
+///     .enumerate()
+///     .map(|(i, kind)| WithSpan::new(parser.token(kind).unwrap(), Span::new_unchecked(i, i + 1)))
+///     .collect();
+/// let tree = parser.parse_tree(&tokens, Span::point(4)).value.unwrap();
+/// let ParseTree::Node { kind, children, .. } = tree else { unreachable!() };
+/// assert_eq!(kind, "list");
+/// assert_eq!(children.len(), 3);
+/// ```
+#[derive(Debug, Clone)]
+pub struct EarleyParser {
+    bnf: Bnf,
+    /// The indices of the productions of each rule.
+    by_rule: Vec<Vec<usize>>,
+    nullable: Vec<bool>,
+}
+
+impl EarleyParser {
+    pub fn new(grammar: &Grammar) -> Self {
+        let bnf = Bnf::new(grammar);
+        let mut by_rule = vec![Vec::new(); bnf.rules.len()];
+        for (index, production) in bnf.productions.iter().enumerate() {
+            by_rule[production.rule].push(index);
+        }
+        let analysis = bnf.plain.analyze();
+        let nullable = bnf
+            .rules
+            .iter()
+            .map(|rule| analysis.is_nullable(rule))
+            .collect();
+        EarleyParser {
+            bnf,
+            by_rule,
+            nullable,
+        }
+    }
+
+    /// Returns the names of the rules, as in [`Grammar::to_bnf`], the start
+    /// rule first.
+    pub fn rules(&self) -> &[String] {
+        &self.bnf.rules
+    }
+
+    /// Returns the names of the token kinds.
+    pub fn tokens(&self) -> &[String] {
+        &self.bnf.tokens
+    }
+
+    /// Returns the index of the token kind with the given name.
+    pub fn token(&self, name: &str) -> Option<usize> {
+        self.bnf.tokens.iter().position(|token| token == name)
+    }
+
+    /// Parses token kinds, by index, from the start rule, where `end` is the
+    /// span of the end of the input.
+    ///
+    /// # Returns
+    /// The forest of every parse of the whole input, or `None` with an error
+    /// at the first token no parse gets past, or at the start of the input
+    /// if the grammar has no rules.
+    pub fn parse(&self, tokens: &[WithSpan<usize>], end: Span) -> ParseOutput<Option<ParseForest>> {
+        if self.by_rule.is_empty() {
+            let span = tokens.first().map_or(end, |token| token.span);
+            let error = Diagnostic::error("the grammar has no rules").with_label(span, "");
+            return ParseOutput::new(None, vec![error]);
+        }
+        let chart = match self.recognize(tokens) {
+            Ok(chart) => chart,
+            Err((position, set)) => {
+                let error = self.error(position, &set, tokens, end);
+                return ParseOutput::new(None, vec![error]);
+            }
+        };
+        let mut extractor = Extractor {
+            parser: self,
+            tokens,
+            chart: &chart,
+            builder: ForestBuilder::default(),
+            built: HashSet::new(),
+        };
+        let root = extractor.node(0, 0, tokens.len());
+        let mut builder = extractor.builder;
+        builder.break_cycles(root);
+        let spans = tokens.iter().map(|token| token.span).collect();
+        let bnf = &self.bnf;
+        let forest = builder.finish(root, &bnf.rules, &bnf.origins, &bnf.tokens, spans, end);
+        ParseOutput::new(Some(forest), Vec::new())
+    }
+
+    /// Parses token kinds like [`parse`](EarleyParser::parse), returning
+    /// the first tree of the forest.
+    pub fn parse_tree(
+        &self,
+        tokens: &[WithSpan<usize>],
+        end: Span,
+    ) -> ParseOutput<Option<ParseTree<String>>> {
+        let output = self.parse(tokens, end);
+        let tree = output
+            .value
+            .and_then(|forest| forest.trees(1).into_iter().next());
+        ParseOutput::new(tree, output.diagnostics)
+    }
+
+    /// Returns the item sets of the input, one more than there are tokens,
+    /// or the position where parsing fails with its set.
+    fn recognize(
+        &self,
+        tokens: &[WithSpan<usize>],
+    ) -> Result<Vec<HashSet<Item>>, (usize, Vec<Item>)> {
+        let mut sets: Vec<Vec<Item>> = vec![Vec::new(); tokens.len() + 1];
+        let mut seen: Vec<HashSet<Item>> = vec![HashSet::new(); tokens.len() + 1];
+        fn add(sets: &mut [Vec<Item>], seen: &mut [HashSet<Item>], position: usize, item: Item) {
+            if seen[position].insert(item) {
+                sets[position].push(item);
+            }
+        }
+        for &production in self.by_rule.first().into_iter().flatten() {
+            add(&mut sets, &mut seen, 0, (production, 0, 0));
+        }
+        for position in 0..=tokens.len() {
+            let mut next = 0;
+            while let Some(&(production, dot, origin)) = sets[position].get(next) {
+                next += 1;
+                let production_rule = self.bnf.productions[production].rule;
+                match self.bnf.productions[production].symbols.get(dot) {
+                    None => {
+                        for index in 0..sets[origin].len() {
+                            let (waiting, at, from) = sets[origin][index];
+                            let symbols = &self.bnf.productions[waiting].symbols;
+                            if symbols.get(at) == Some(&Symbol::Rule(production_rule)) {
+                                add(&mut sets, &mut seen, position, (waiting, at + 1, from));
+                            }
+                        }
+                    }
+                    Some(&Symbol::Rule(rule)) => {
+                        for &predicted in &self.by_rule[rule] {
+                            add(&mut sets, &mut seen, position, (predicted, 0, position));
+                        }
+                        // A rule that can match nothing is completed as soon
+                        // as it is predicted.
+                        if self.nullable[rule] {
+                            add(
+                                &mut sets,
+                                &mut seen,
+                                position,
+                                (production, dot + 1, origin),
+                            );
+                        }
+                    }
+                    Some(&Symbol::Token(kind)) => {
+                        if tokens.get(position).map(|token| token.value) == Some(kind) {
+                            add(
+                                &mut sets,
+                                &mut seen,
+                                position + 1,
+                                (production, dot + 1, origin),
+                            );
+                        }
+                    }
+                }
+            }
+            let accepted = position == tokens.len() && self.completes(&seen, 0, 0, position);
+            let stuck = position < tokens.len() && sets[position + 1].is_empty();
+            if stuck || (position == tokens.len() && !accepted) {
+                return Err((position, std::mem::take(&mut sets[position])));
+            }
+        }
+        Ok(seen)
+    }
+
+    /// Returns whether `rule` matches from `start` to `end`.
+    fn completes(&self, chart: &[HashSet<Item>], rule: usize, start: usize, end: usize) -> bool {
+        self.by_rule[rule].iter().any(|&production| {
+            let len = self.bnf.productions[production].symbols.len();
+            chart[end].contains(&(production, len, start))
+        })
+    }
+
+    /// Returns the error for the position where parsing fails: what the
+    /// items of its set expect next, and the token there.
+    fn error(
+        &self,
+        position: usize,
+        set: &[Item],
+        tokens: &[WithSpan<usize>],
+        end: Span,
+    ) -> Diagnostic {
+        let mut expected: BTreeSet<Option<usize>> = BTreeSet::new();
+        for &(production, dot, origin) in set {
+            let production = &self.bnf.productions[production];
+            match production.symbols.get(dot) {
+                Some(&Symbol::Token(kind)) => {
+                    expected.insert(Some(kind));
+                }
+                None if production.rule == 0 && origin == 0 => {
+                    expected.insert(None);
+                }
+                _ => {}
+            }
+        }
+        let name = |token: Option<usize>| match token {
+            Some(token) => self
+                .bnf
+                .tokens
+                .get(token)
+                .map_or("an unknown token", String::as_str),
+            None => "end of input",
+        };
+        let (found, span) = match tokens.get(position) {
+            Some(token) => (Some(token.value), token.span),
+            None => (None, end),
+        };
+        Diagnostic::error(format!(
+            "expected {}, found {}",
+            expected_list(expected.iter().map(|&token| name(token))),
+            name(found)
+        ))
+        .with_label(span, "")
+    }
+}
+
+/// Builds the forest of a parse from its item sets.
+struct Extractor<'a> {
+    parser: &'a EarleyParser,
+    tokens: &'a [WithSpan<usize>],
+    chart: &'a [HashSet<Item>],
+    builder: ForestBuilder,
+    /// The matches whose node has its alternatives.
+    built: HashSet<(usize, usize, usize)>,
+}
+
+impl Extractor<'_> {
+    /// Returns the node of the match of `rule` from `start` to `end`, which
+    /// the chart has.
+    fn node(&mut self, rule: usize, start: usize, end: usize) -> NodeId {
+        let id = self.builder.rule(rule, start, end);
+        if !self.built.insert((rule, start, end)) {
+            return id;
+        }
+        for &production in &self.parser.by_rule[rule] {
+            let len = self.parser.bnf.productions[production].symbols.len();
+            if !self.chart[end].contains(&(production, len, start)) {
+                continue;
+            }
+            for children in self.splits(production, len, start, end) {
+                self.builder.pack(
+                    id,
+                    Packed {
+                        production,
+                        children,
+                    },
+                );
+            }
+        }
+        id
+    }
+
+    /// Returns each way the first `count` symbols of a production match
+    /// from `start` to `end`, as a node per symbol.
+    fn splits(
+        &mut self,
+        production: usize,
+        count: usize,
+        start: usize,
+        end: usize,
+    ) -> Vec<Vec<NodeId>> {
+        if count == 0 {
+            return if start == end {
+                vec![Vec::new()]
+            } else {
+                Vec::new()
+            };
+        }
+        let before = (production, count - 1, start);
+        let mut splits = Vec::new();
+        match self.parser.bnf.productions[production].symbols[count - 1] {
+            Symbol::Token(kind) => {
+                let matches = end > start
+                    && self.tokens[end - 1].value == kind
+                    && self.chart[end - 1].contains(&before);
+                if matches {
+                    let token = self.builder.token(kind, end - 1);
+                    for mut children in self.splits(production, count - 1, start, end - 1) {
+                        children.push(token);
+                        splits.push(children);
+                    }
+                }
+            }
+            Symbol::Rule(rule) => {
+                for middle in start..=end {
+                    let matches = self.chart[middle].contains(&before)
+                        && self.parser.completes(self.chart, rule, middle, end);
+                    if !matches {
+                        continue;
+                    }
+                    let child = self.node(rule, middle, end);
+                    for mut children in self.splits(production, count - 1, start, middle) {
+                        children.push(child);
+                        splits.push(children);
+                    }
+                }
+            }
+        }
+        splits
+    }
+}
+
+impl Grammar {
+    /// Returns an [`EarleyParser`] for the grammar.
+    pub fn earley_parser(&self) -> EarleyParser {
+        EarleyParser::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::parse_ebnf;
+
+    fn tokens(parser: &EarleyParser, kinds: &[&str]) -> Vec<WithSpan<usize>> {
+        kinds
+            .iter()
+            .enumerate()
+            .map(|(i, kind)| {
+                WithSpan::new(parser.token(kind).unwrap(), Span::new_unchecked(i, i + 1))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_any_grammar() {
+        let grammar = parse_ebnf(
+            r#"
+            expr = expr "+" expr | expr expr | term ;
+            term = N | "(" expr ")" | ;
+            "#,
+        )
+        .value;
+        let parser = grammar.earley_parser();
+        let forest = parser
+            .parse(
+                &tokens(&parser, &["N", "\"+\"", "N", "\"+\"", "N"]),
+                Span::point(5),
+            )
+            .value
+            .unwrap();
+        assert!(forest.is_ambiguous());
+        assert_eq!(forest.trees(usize::MAX).len() as u64, forest.count_trees());
+        assert_eq!(forest.ambiguities()[0].span, Span::new_unchecked(0, 5));
+
+        let forest = parser.parse(&[], Span::point(0)).value.unwrap();
+        assert_eq!(forest.count_trees(), 1);
+
+        // Cyclic: `a` derives `b`, which derives `a`.
+        let grammar = parse_ebnf(r#"a = b | X ; b = a | Y ;"#).value;
+        let parser = grammar.earley_parser();
+        let forest = parser
+            .parse(&tokens(&parser, &["X"]), Span::point(1))
+            .value
+            .unwrap();
+        let ParseTree::Node { kind, .. } = &forest.trees(1)[0] else {
+            panic!("the root is a node");
+        };
+        assert_eq!(kind, "a");
+        assert_eq!(forest.count_trees(), 1);
+    }
+
+    #[test]
+    fn test_errors() {
+        let grammar = parse_ebnf(r#"list = "[" (N ("," N)*)? "]" ;"#).value;
+        let parser = grammar.earley_parser();
+        let parse = |kinds: &[&str]| {
+            parser
+                .parse_tree(&tokens(&parser, kinds), Span::point(kinds.len()))
+                .diagnostics[0]
+                .message
+                .clone()
+        };
+        assert_eq!(
+            parse(&["\"[\"", "N", "N"]),
+            "expected \"]\" or \",\", found N"
+        );
+        assert_eq!(
+            parse(&["\"[\"", "\"]\"", "\"]\""]),
+            "expected end of input, found \"]\""
+        );
+        assert_eq!(parse(&["\"[\""]), "expected \"]\" or N, found end of input");
+
+        let parser = Grammar::new().earley_parser();
+        let output = parser.parse(&[], Span::point(0));
+        assert!(output.value.is_none());
+        assert_eq!(output.diagnostics[0].message, "the grammar has no rules");
+    }
+}
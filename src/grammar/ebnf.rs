@@ -0,0 +1,854 @@
+use super::{
+    Assoc, ErrorMessage, Expr, Grammar, Import, PrecedenceLevel, Rule, Terminal, TokenDef,
+};
+use crate::diagnostics::Diagnostic;
+use crate::parser::{EndOfFile, KindMeta, ParseOutput, Parser, Token};
+use crate::position::{Span, WithSpan};
+use crate::scanner::Scanner;
+
+/// The tokens of the EBNF notation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Tok {
+    Name,
+    Literal,
+    Regex,
+    Integer,
+    Directive,
+    Define,
+    Semicolon,
+    Bar,
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    LBrace,
+    RBrace,
+    Question,
+    Star,
+    Plus,
+    Comma,
+    Eof,
+}
+
+impl Token for Tok {
+    type Kind = Tok;
+
+    fn to_kind(&self) -> Tok {
+        *self
+    }
+}
+
+impl EndOfFile for Tok {
+    fn eof() -> Tok {
+        Tok::Eof
+    }
+
+    fn eof_kind() -> Tok {
+        Tok::Eof
+    }
+}
+
+impl KindMeta for Tok {
+    const ALL: &'static [Tok] = &[
+        Tok::Name,
+        Tok::Literal,
+        Tok::Regex,
+        Tok::Integer,
+        Tok::Directive,
+        Tok::Define,
+        Tok::Semicolon,
+        Tok::Bar,
+        Tok::LParen,
+        Tok::RParen,
+        Tok::LBracket,
+        Tok::RBracket,
+        Tok::LBrace,
+        Tok::RBrace,
+        Tok::Question,
+        Tok::Star,
+        Tok::Plus,
+        Tok::Comma,
+        Tok::Eof,
+    ];
+
+    fn display_name(&self) -> &'static str {
+        match self {
+            Tok::Name => "a name",
+            Tok::Literal => "a literal",
+            Tok::Regex => "a pattern",
+            Tok::Integer => "an integer",
+            Tok::Directive => "a declaration",
+            Tok::Define => "`=`",
+            Tok::Semicolon => "`;`",
+            Tok::Bar => "`|`",
+            Tok::LParen => "`(`",
+            Tok::RParen => "`)`",
+            Tok::LBracket => "`[`",
+            Tok::RBracket => "`]`",
+            Tok::LBrace => "`{`",
+            Tok::RBrace => "`}`",
+            Tok::Question => "`?`",
+            Tok::Star => "`*`",
+            Tok::Plus => "`+`",
+            Tok::Comma => "`,`",
+            Tok::Eof => "end of input",
+        }
+    }
+}
+
+fn lex(source: &str, diagnostics: &mut Vec<Diagnostic>) -> Vec<WithSpan<Tok>> {
+    let mut scanner = Scanner::new(source);
+    let mut tokens = Vec::new();
+    loop {
+        scanner.consume_while(char::is_whitespace);
+        scanner.shift();
+        let Some(c) = scanner.next() else {
+            return tokens;
+        };
+        let kind = match c {
+            '(' if scanner.next_match('*') => {
+                skip_block_comment(&mut scanner, diagnostics);
+                continue;
+            }
+            '/' if scanner.next_match('/') => {
+                scanner.consume_while(|c| c != '\n');
+                continue;
+            }
+            '#' => {
+                scanner.consume_while(|c| c != '\n');
+                continue;
+            }
+            '/' => {
+                while let Some(&next) = scanner.peek() {
+                    if next == '/' || next == '\n' {
+                        break;
+                    }
+                    scanner.next();
+                    if next == '\\' {
+                        scanner.consume_if(|c| c != '\n');
+                    }
+                }
+                if !scanner.next_match('/') {
+                    scanner.error("unterminated pattern", diagnostics);
+                    continue;
+                }
+                Tok::Regex
+            }
+            c if c.is_ascii_digit() || c == '-' && scanner.if_next(|c| c.is_ascii_digit()) => {
+                scanner.consume_while(|c| c.is_ascii_digit());
+                Tok::Integer
+            }
+            '%' if scanner.if_next(char::is_alphabetic) => {
+                scanner.consume_while(|c| c.is_alphanumeric() || c == '_');
+                Tok::Directive
+            }
+            '=' => Tok::Define,
+            ':' if scanner.next_match(':') && scanner.next_match('=') => Tok::Define,
+            ';' => Tok::Semicolon,
+            '|' => Tok::Bar,
+            '(' => Tok::LParen,
+            ')' => Tok::RParen,
+            '[' => Tok::LBracket,
+            ']' => Tok::RBracket,
+            '{' => Tok::LBrace,
+            '}' => Tok::RBrace,
+            '?' => Tok::Question,
+            '*' => Tok::Star,
+            '+' => Tok::Plus,
+            ',' => Tok::Comma,
+            '"' | '\'' => {
+                scanner.consume_while(|next| next != c && next != '\n');
+                if !scanner.next_match(c) {
+                    scanner.error("unterminated literal", diagnostics);
+                    continue;
+                }
+                Tok::Literal
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                // Names of imported rules are qualified, as `expr.atom`.
+                loop {
+                    scanner.consume_while(|c| c.is_alphanumeric() || c == '_');
+                    if !scanner.if_next(|c| c == '.') {
+                        break;
+                    }
+                    scanner.next();
+                }
+                Tok::Name
+            }
+            c => {
+                scanner.error(format!("unexpected character `{}`", c), diagnostics);
+                continue;
+            }
+        };
+        tokens.push(scanner.with_span(kind));
+    }
+}
+
+fn skip_block_comment(scanner: &mut Scanner<'_>, diagnostics: &mut Vec<Diagnostic>) {
+    while let Some(c) = scanner.next() {
+        if c == '*' && scanner.next_match(')') {
+            return;
+        }
+    }
+    scanner.error("unterminated comment", diagnostics);
+}
+
+fn redefinition(what: &str, name: &str, span: Span, previous: Option<Span>) -> Diagnostic {
+    let diagnostic = Diagnostic::error(format!("{} `{}` is defined more than once", what, name))
+        .with_label(span, "redefined here");
+    match previous {
+        Some(previous) => diagnostic.with_secondary_label(previous, "first defined here"),
+        None => diagnostic,
+    }
+}
+
+/// An expression with the spans of it and its subexpressions in
+/// [`Expr::walk`] order.
+struct Spanned {
+    expr: Expr,
+    spans: Vec<Span>,
+}
+
+impl Spanned {
+    fn span(&self) -> Span {
+        self.spans[0]
+    }
+
+    /// Wraps `children` in a node made by `make`, unless there is a single
+    /// child, which stands for itself.
+    fn node(children: Vec<Spanned>, at: Span, make: fn(Vec<Expr>) -> Expr) -> Spanned {
+        if children.len() == 1 {
+            return children.into_iter().next().unwrap();
+        }
+        let span = match (children.first(), children.last()) {
+            (Some(first), Some(last)) => first.span().union(&last.span()),
+            _ => at,
+        };
+        let mut spans = vec![span];
+        let mut exprs = Vec::new();
+        for child in children {
+            spans.extend(child.spans);
+            exprs.push(child.expr);
+        }
+        Spanned {
+            expr: make(exprs),
+            spans,
+        }
+    }
+
+    fn wrap(self, span: Span, make: fn(Expr) -> Expr) -> Spanned {
+        let mut spans = vec![span];
+        spans.extend(self.spans);
+        Spanned {
+            expr: make(self.expr),
+            spans,
+        }
+    }
+}
+
+struct EbnfParser<'a> {
+    source: &'a str,
+    parser: Parser<'a, Tok>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> EbnfParser<'a> {
+    fn text(&self, token: &WithSpan<Tok>) -> &'a str {
+        &self.source[token.span.start()..token.span.end()]
+    }
+
+    fn grammar(&mut self) -> Grammar {
+        let mut grammar = Grammar::new();
+        while !self.parser.is_at_end() {
+            if self.parser.check(Tok::Directive) {
+                match self.precedence() {
+                    Some(level) => grammar.add_precedence(level),
+                    None => {
+                        self.parser.drop_until(&[Tok::Semicolon]);
+                        self.parser.advance();
+                    }
+                }
+                continue;
+            }
+            if self.import_keyword() {
+                match self.import() {
+                    Some(import) => grammar.add_import(import),
+                    None => {
+                        self.parser.drop_until(&[Tok::Semicolon]);
+                        self.parser.advance();
+                    }
+                }
+                continue;
+            }
+            if let Some(skip) = self.token_keyword() {
+                match self.token_def(skip) {
+                    Some(def) => {
+                        if let Some(previous) = grammar.token_def(&def.name) {
+                            self.diagnostics.push(redefinition(
+                                "token",
+                                &def.name,
+                                def.span.unwrap(),
+                                previous.span,
+                            ));
+                        }
+                        grammar.add_token_def(def);
+                    }
+                    None => {
+                        self.parser.drop_until(&[Tok::Semicolon]);
+                        self.parser.advance();
+                    }
+                }
+                continue;
+            }
+            match self.rule() {
+                Some(rule) => {
+                    if let Some(previous) = grammar.get(&rule.name) {
+                        self.diagnostics.push(redefinition(
+                            "rule",
+                            &rule.name,
+                            rule.span.unwrap(),
+                            previous.span,
+                        ));
+                    }
+                    grammar.add(rule);
+                }
+                None => {
+                    self.parser.drop_until(&[Tok::Semicolon]);
+                    self.parser.advance();
+                }
+            }
+        }
+        grammar
+    }
+
+    /// Returns whether a token definition starts here, and if so whether
+    /// it is a skip rule: `token` and `skip` only start one when followed by
+    /// a name, and are rule names otherwise.
+    fn token_keyword(&mut self) -> Option<bool> {
+        if !self.parser.check(Tok::Name) {
+            return None;
+        }
+        let skip = match self.text(self.parser.peek_token()) {
+            "token" => false,
+            "skip" => true,
+            _ => return None,
+        };
+        self.parser.checkpoint();
+        self.parser.advance();
+        let is_def = self.parser.check(Tok::Name);
+        self.parser.rewind();
+        is_def.then_some(skip)
+    }
+
+    /// Returns whether an import starts here: `import` is a rule name
+    /// unless followed by a literal.
+    fn import_keyword(&mut self) -> bool {
+        if !self.parser.check(Tok::Name) || self.text(self.parser.peek_token()) != "import" {
+            return false;
+        }
+        self.parser.checkpoint();
+        self.parser.advance();
+        let is_import = self.parser.check(Tok::Literal);
+        self.parser.rewind();
+        is_import
+    }
+
+    fn import(&mut self) -> Option<Import> {
+        let keyword = self.parser.advance();
+        let path = self.parser.advance();
+        let mut import = Import::new(self.literal_text(path));
+        if self.parser.check(Tok::Name) && self.text(self.parser.peek_token()) == "as" {
+            self.parser.advance();
+            let namespace = self
+                .parser
+                .expect_one_of(&[Tok::Name], &mut self.diagnostics)?;
+            import = import.with_namespace(self.text(namespace));
+        }
+        let end = self
+            .parser
+            .expect_one_of(&[Tok::Semicolon], &mut self.diagnostics)?;
+        Some(import.with_span(keyword.span.union(&end.span)))
+    }
+
+    fn token_def(&mut self, skip: bool) -> Option<TokenDef> {
+        self.parser.advance();
+        let name = self
+            .parser
+            .expect_one_of(&[Tok::Name], &mut self.diagnostics)?;
+        let text = self.text(name);
+        if !text.starts_with(|c: char| c.is_uppercase()) {
+            self.diagnostics.push(
+                Diagnostic::error(format!(
+                    "token `{}` does not start with an uppercase letter",
+                    text
+                ))
+                .with_label(name.span, "")
+                .with_note("rules refer to names starting with a lowercase letter as rules"),
+            );
+        }
+        self.parser
+            .expect_one_of(&[Tok::Define], &mut self.diagnostics)?;
+        let pattern = self
+            .parser
+            .expect_one_of(&[Tok::Literal, Tok::Regex], &mut self.diagnostics)?;
+        let body = self.text(pattern);
+        let body = &body[1..body.len() - 1];
+        let terminal = match pattern.value {
+            Tok::Literal => Terminal::literal(body),
+            _ => Terminal::pattern(body),
+        };
+        let mut def = TokenDef::new(text, terminal).with_span(name.span);
+        if skip {
+            def = def.skipped();
+        }
+        if self.parser.check(Tok::Name) && self.text(self.parser.peek_token()) == "priority" {
+            self.parser.advance();
+            let priority = self
+                .parser
+                .expect_one_of(&[Tok::Integer], &mut self.diagnostics)?;
+            match self.text(priority).parse() {
+                Ok(priority) => def = def.with_priority(priority),
+                Err(_) => self.diagnostics.push(
+                    Diagnostic::error("priority is out of range").with_label(priority.span, ""),
+                ),
+            }
+        }
+        self.parser
+            .expect_one_of(&[Tok::Semicolon], &mut self.diagnostics)?;
+        Some(def)
+    }
+
+    fn precedence(&mut self) -> Option<PrecedenceLevel> {
+        let directive = self.parser.advance();
+        let assoc = match self.text(directive) {
+            "%left" => Assoc::Left,
+            "%right" => Assoc::Right,
+            "%nonassoc" => Assoc::NonAssoc,
+            text => {
+                self.diagnostics.push(
+                    Diagnostic::error(format!("unknown declaration `{}`", text))
+                        .with_label(directive.span, "")
+                        .with_note("expected `%left`, `%right` or `%nonassoc`"),
+                );
+                return None;
+            }
+        };
+        let mut tokens = Vec::new();
+        loop {
+            let token = self
+                .parser
+                .expect_one_of(&[Tok::Name, Tok::Literal], &mut self.diagnostics)?;
+            let text = self.text(token);
+            if token.value == Tok::Name && !text.starts_with(|c: char| c.is_uppercase()) {
+                self.diagnostics.push(
+                    Diagnostic::error(format!("`{}` is a rule, not a token kind", text))
+                        .with_label(token.span, "")
+                        .with_note("precedence is declared for operator tokens"),
+                );
+            }
+            tokens.push(text);
+            if !self.parser.check_one_of(&[Tok::Name, Tok::Literal]) {
+                break;
+            }
+        }
+        let end = self
+            .parser
+            .expect_one_of(&[Tok::Semicolon], &mut self.diagnostics)?;
+        Some(PrecedenceLevel::new(assoc, tokens).with_span(directive.span.union(&end.span)))
+    }
+
+    fn rule(&mut self) -> Option<Rule> {
+        let name = self
+            .parser
+            .expect_one_of(&[Tok::Name], &mut self.diagnostics)?;
+        self.parser
+            .expect_one_of(&[Tok::Define], &mut self.diagnostics)?;
+        let body = self.choice()?;
+        let error = match self.on_error_keyword() {
+            true => Some(self.on_error()?),
+            false => None,
+        };
+        self.parser
+            .expect_one_of(&[Tok::Bar, Tok::Semicolon], &mut self.diagnostics)?;
+        let mut rule = Rule::new(self.text(name), body.expr)
+            .with_span(name.span)
+            .with_expr_spans(body.spans);
+        rule.error = error;
+        Some(rule)
+    }
+
+    /// Returns whether a custom error message starts here: `on_error` is a
+    /// rule name unless followed by `(`.
+    fn on_error_keyword(&mut self) -> bool {
+        if !self.parser.check(Tok::Name) || self.text(self.parser.peek_token()) != "on_error" {
+            return false;
+        }
+        self.parser.checkpoint();
+        self.parser.advance();
+        let is_keyword = self.parser.check(Tok::LParen);
+        self.parser.rewind();
+        is_keyword
+    }
+
+    fn on_error(&mut self) -> Option<ErrorMessage> {
+        self.parser.advance();
+        self.parser.advance();
+        let message = self
+            .parser
+            .expect_one_of(&[Tok::Literal], &mut self.diagnostics)?;
+        let mut error = ErrorMessage::new(self.literal_text(message));
+        if self.parser.is(Tok::Comma) {
+            let hint = self
+                .parser
+                .expect_one_of(&[Tok::Literal], &mut self.diagnostics)?;
+            error = error.with_hint(self.literal_text(hint));
+        }
+        self.parser
+            .expect_one_of(&[Tok::RParen], &mut self.diagnostics)?;
+        Some(error)
+    }
+
+    /// Returns the text of a literal without its quotes.
+    fn literal_text(&self, token: &WithSpan<Tok>) -> &'a str {
+        let text = self.text(token);
+        &text[1..text.len() - 1]
+    }
+
+    fn choice(&mut self) -> Option<Spanned> {
+        let at = Span::point(self.parser.peek_token().span.start());
+        let mut alternatives = vec![self.seq()?];
+        while self.parser.is(Tok::Bar) {
+            alternatives.push(self.seq()?);
+        }
+        Some(Spanned::node(alternatives, at, Expr::Choice))
+    }
+
+    fn seq(&mut self) -> Option<Spanned> {
+        let at = Span::point(self.parser.peek_token().span.start());
+        let mut exprs = Vec::new();
+        while self.parser.check_one_of(&[
+            Tok::Name,
+            Tok::Literal,
+            Tok::LParen,
+            Tok::LBracket,
+            Tok::LBrace,
+        ]) && !self.on_error_keyword()
+        {
+            exprs.push(self.postfix()?);
+        }
+        Some(Spanned::node(exprs, at, Expr::Seq))
+    }
+
+    fn postfix(&mut self) -> Option<Spanned> {
+        let mut expr = self.atom()?;
+        loop {
+            let make: fn(Expr) -> Expr = match self.parser.peek() {
+                Tok::Question => Expr::optional,
+                Tok::Star => Expr::repeat,
+                Tok::Plus => Expr::repeat1,
+                _ => return Some(expr),
+            };
+            let span = expr.span().union(&self.parser.advance().span);
+            expr = expr.wrap(span, make);
+        }
+    }
+
+    fn atom(&mut self) -> Option<Spanned> {
+        let token = self.parser.expect_one_of(
+            &[
+                Tok::Name,
+                Tok::Literal,
+                Tok::LParen,
+                Tok::LBracket,
+                Tok::LBrace,
+            ],
+            &mut self.diagnostics,
+        )?;
+        // The tokens that can end the group, `|` going on to the next
+        // alternative.
+        let (ends, make): (&[Tok], fn(Expr) -> Expr) = match token.value {
+            Tok::Name | Tok::Literal => {
+                let text = self.text(token);
+                let is_token = token.value == Tok::Literal
+                    || text.starts_with(|c: char| c.is_uppercase()) && !text.contains('.');
+                let expr = if is_token {
+                    Expr::token(text)
+                } else {
+                    Expr::rule(text)
+                };
+                return Some(Spanned {
+                    expr,
+                    spans: vec![token.span],
+                });
+            }
+            Tok::LParen => (&[Tok::Bar, Tok::RParen], |expr| expr),
+            Tok::LBracket => (&[Tok::Bar, Tok::RBracket], Expr::optional),
+            _ => (&[Tok::Bar, Tok::RBrace], Expr::repeat),
+        };
+        let inner = self.choice()?;
+        let end = self.parser.expect_one_of(ends, &mut self.diagnostics)?;
+        let span = token.span.union(&end.span);
+        if token.value == Tok::LParen {
+            // Parentheses only group; an empty pair is the empty sequence.
+            return Some(match inner.expr {
+                Expr::Seq(ref exprs) if exprs.is_empty() => Spanned {
+                    expr: inner.expr,
+                    spans: vec![span],
+                },
+                _ => inner,
+            });
+        }
+        Some(inner.wrap(span, make))
+    }
+}
+
+/// Parses a grammar written in an EBNF notation.
+///
+/// A grammar is a list of rules `name = expression ;`, where `::=` may be
+/// used instead of `=`. Expressions are made of
+///
+/// - names: token kinds if they start with an uppercase letter, such as
+///   `Number`, and rules otherwise, such as `term`,
+/// - literals, such as `"+"` or `'if'`, which are token kinds named by their
+///   quoted text,
+/// - sequences `a b`, alternatives `a | b` and groups `(a)`, where `()` is
+///   the empty sequence,
+/// - optional parts `a?` or `[a]`, repetitions `a*` or `{a}`, and
+///   non-empty repetitions `a+`.
+///
+/// Token kinds can be defined too, for [`Lexer`](super::Lexer), as
+/// `token Name = pattern ;`, where the pattern is a literal, such as
+/// `"while"`, or a regular expression between slashes, such as
+/// `/[0-9]+/`. A definition can end with a `priority`, such as
+/// `priority 1`, and skip rules for whitespace and comments are written
+/// `skip` rather than `token`. See [`TokenDef`].
+///
+/// A rule can end with a custom error message, and a hint, reported where
+/// it cannot go on, such as `on_error("expected a condition", "add one")`.
+/// See [`ErrorMessage`](super::ErrorMessage).
+///
+/// Rules of other grammars are imported with `import "path" ;`, or
+/// `import "path" as name ;` to refer to them as `name.rule`. See
+/// [`Grammar::resolve_imports`].
+///
+/// The precedence of operators is declared as `%left "+" "-" ;`, or with
+/// `%right` or `%nonassoc`, later declarations binding tighter. See
+/// [`Grammar::resolve_precedence`].
+///
+/// Comments are written `(* ... *)`, `// ...` or `# ...`. This is the
+/// notation [`Grammar`]'s `Display` implementation writes, so a grammar
+/// can be written out and read back.
+///
+/// Rules remember the spans of their names and expressions in the source,
+/// so analyses can point into it. On errors, the rule in error is skipped
+/// and parsing resumes after the next `;`.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::grammar::*;
+///
+/// let output = parse_ebnf(r#"
+///     (* Sums of numbers. *)
+///     sum  ::= term { ("+" | "-") term } ;
+///     term  =  Number | "(" sum ")" ;
+/// "#);
+/// assert!(output.diagnostics.is_empty());
+/// assert_eq!(
+///     output.value.to_string(),
+///     r#"sum = term (("+" | "-") term)* ;
+/// term = Number | "(" sum ")" ;
+/// "#
+/// );
+/// ```
+pub fn parse_ebnf(source: &str) -> ParseOutput<Grammar> {
+    let mut diagnostics = Vec::new();
+    let tokens = lex(source, &mut diagnostics);
+    let eof = WithSpan::new(Tok::Eof, Span::point(source.len()));
+    let mut parser = EbnfParser {
+        source,
+        parser: Parser::new(&tokens, &eof),
+        diagnostics,
+    };
+    let grammar = parser.grammar();
+    ParseOutput::new(grammar, parser.diagnostics)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_and_spans() {
+        let source = "list = \"[\" [item {\",\" item}] \"]\" ;\nitem = Number+ | list | () ;\n";
+        let output = parse_ebnf(source);
+        assert!(output.diagnostics.is_empty(), "{:?}", output.diagnostics);
+        let grammar = output.value;
+        let reparsed = parse_ebnf(&grammar.to_string()).value;
+        let exprs = |grammar: &Grammar| -> Vec<Expr> {
+            grammar
+                .rules()
+                .iter()
+                .map(|rule| rule.expr.clone())
+                .collect()
+        };
+        assert_eq!(exprs(&reparsed), exprs(&grammar));
+
+        let item = grammar.get("item").unwrap();
+        assert_eq!(item.span, Span::new(35, 39));
+        let mut visited = Vec::new();
+        item.expr.walk(&mut |expr| visited.push(expr.to_string()));
+        let texts: Vec<_> = item
+            .expr_spans
+            .iter()
+            .map(|span| &source[span.start()..span.end()])
+            .collect();
+        assert_eq!(
+            visited,
+            ["Number+ | list | ()", "Number+", "Number", "list", "()"]
+        );
+        assert_eq!(
+            texts,
+            ["Number+ | list | ()", "Number+", "Number", "list", "()"]
+        );
+    }
+
+    #[test]
+    fn test_token_defs() {
+        let source = r#"
+            list = "[" Number* "]" ;
+            token Number = /[0-9]+/ priority -1 ;
+            skip Comment = /\/\/[^\n]*/ ;
+            token = Quote ;
+            token Quote = '"' ;
+        "#;
+        let output = parse_ebnf(source);
+        assert!(output.diagnostics.is_empty(), "{:?}", output.diagnostics);
+        let grammar = output.value;
+        assert_eq!(
+            grammar.to_string(),
+            r#"list = "[" Number* "]" ;
+token = Quote ;
+token Number = /[0-9]+/ priority -1 ;
+skip Comment = /\/\/[^\n]*/ ;
+token Quote = '"' ;
+"#
+        );
+        let defs = |grammar: &Grammar| -> Vec<TokenDef> {
+            grammar
+                .token_defs()
+                .iter()
+                .map(|def| TokenDef {
+                    span: None,
+                    ..def.clone()
+                })
+                .collect()
+        };
+        assert_eq!(
+            defs(&parse_ebnf(&grammar.to_string()).value),
+            defs(&grammar)
+        );
+        assert_eq!(grammar.token_def("Number").unwrap().priority, -1);
+        assert!(grammar.token_def("Comment").unwrap().skip);
+
+        let output = parse_ebnf("token Plus = \"+\" ; token Plus = Plus ; token minus = \"-\" ;");
+        let messages: Vec<_> = output
+            .diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.message.as_str())
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                "expected a literal or a pattern, found a name",
+                "token `minus` does not start with an uppercase letter",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_precedence() {
+        let source = "e = e \"+\" e | e Caret e | X ;\n%left \"+\" ;\n%right Caret ;\n";
+        let output = parse_ebnf(source);
+        assert!(output.diagnostics.is_empty(), "{:?}", output.diagnostics);
+        assert_eq!(output.value.to_string(), source);
+        let level = &output.value.precedence()[1];
+        assert_eq!(level.assoc, Assoc::Right);
+        assert_eq!(level.tokens, ["Caret"]);
+        assert_eq!(level.span, Span::new(42, 56));
+
+        let output = parse_ebnf("%prec \"+\" ; %left e ; %left ; e = X ;");
+        let messages: Vec<_> = output
+            .diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.message.as_str())
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                "unknown declaration `%prec`",
+                "`e` is a rule, not a token kind",
+                "expected a name or a literal, found `;`",
+            ]
+        );
+        assert_eq!(output.value.precedence().len(), 1);
+        assert_eq!(output.value.get("e").unwrap().expr, Expr::token("X"));
+    }
+
+    #[test]
+    fn test_on_error() {
+        let source = r#"
+            if = "if" cond block on_error("expected an `if` statement") ;
+            cond = "(" on_error ")" on_error("expected a condition", 'write it as "(x)"') ;
+            on_error = X ;
+            block = () ;
+        "#;
+        let output = parse_ebnf(source);
+        assert!(output.diagnostics.is_empty(), "{:?}", output.diagnostics);
+        let grammar = output.value;
+        let cond = grammar.get("cond").unwrap();
+        assert_eq!(cond.expr.to_string(), r#""(" on_error ")""#);
+        assert_eq!(
+            cond.error,
+            Some(ErrorMessage::new("expected a condition").with_hint("write it as \"(x)\""))
+        );
+        assert_eq!(
+            parse_ebnf(&grammar.to_string()).value.to_string(),
+            grammar.to_string()
+        );
+
+        let output = parse_ebnf("a = X on_error(Y) ; b = X on_error(\"b\" \"c\") ;");
+        let messages: Vec<_> = output
+            .diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.message.as_str())
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                "expected a literal, found a name",
+                "expected `)`, found a literal",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_errors_recover() {
+        let output = parse_ebnf("a = B ) ;\nb = 'x\n;\nc = D ;\nc = E ;");
+        let messages: Vec<_> = output
+            .diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.message.as_str())
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                "unterminated literal",
+                "expected `|` or `;`, found `)`",
+                "rule `c` is defined more than once",
+            ]
+        );
+        assert_eq!(output.value.get("a"), None);
+        assert_eq!(output.value.get("b").unwrap().expr, Expr::empty());
+        assert_eq!(output.value.get("c").unwrap().expr, Expr::token("E"));
+    }
+}
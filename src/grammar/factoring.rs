@@ -0,0 +1,322 @@
+use super::recursion::choice;
+use super::{Expr, Grammar, Rule};
+use crate::diagnostics::{Applicability, Diagnostic, Suggestion};
+
+/// Alternatives of a choice that start with the same expressions, found by
+/// [`Grammar::common_prefixes`].
+///
+/// A parser has to look past the prefix, or backtrack, to choose between
+/// them, which factoring it out, as [`Grammar::left_factor`] does, avoids.
+/// Expressions are given by their index in the order [`Expr::walk`] visits
+/// the body of the rule, the whole body being 0.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommonPrefix {
+    /// The rule the choice is in.
+    pub rule: String,
+    /// The choice.
+    pub expr: usize,
+    /// The alternatives starting with the prefix, at least two.
+    pub alternatives: Vec<usize>,
+    /// The longest prefix the alternatives share.
+    pub prefix: Vec<Expr>,
+    /// The choice with its common prefixes factored out.
+    pub factored: Expr,
+}
+
+impl CommonPrefix {
+    /// Converts the finding into a warning pointing at the alternatives, with
+    /// a suggestion to factor the prefix out, if `grammar` was read from a
+    /// source.
+    pub fn to_diagnostic(&self, grammar: &Grammar) -> Diagnostic {
+        let prefix = Expr::seq(self.prefix.iter().cloned());
+        let mut diagnostic = Diagnostic::warning(format!(
+            "{} alternatives of rule `{}` start with `{}`",
+            self.alternatives.len(),
+            self.rule,
+            prefix
+        ));
+        let rule = grammar.get(&self.rule);
+        let span = |index: usize| rule.and_then(|rule| rule.expr_span(index));
+        for (i, &alternative) in self.alternatives.iter().enumerate() {
+            if let Some(span) = span(alternative) {
+                diagnostic = match i {
+                    0 => diagnostic.with_label(span, "this alternative starts the same way"),
+                    _ => diagnostic.with_secondary_label(span, "as this one"),
+                };
+            }
+        }
+        diagnostic = diagnostic.with_note(format!(
+            "a parser has to look past `{}` to choose between them",
+            prefix
+        ));
+        match span(self.expr) {
+            Some(span) => diagnostic.with_suggestion(Suggestion::replace(
+                "factor out the common prefix",
+                span,
+                self.factored.to_string(),
+                Applicability::MaybeIncorrect,
+            )),
+            None => diagnostic.with_note(format!("it can be written `{}`", self.factored)),
+        }
+    }
+}
+
+impl Grammar {
+    /// Finds the alternatives of choices that start with the same
+    /// expressions, rule by rule, in the order of the rules.
+    ///
+    /// Prefixes are compared as written, without looking into the rules
+    /// they refer to. Every shared prefix is reported, however short; the
+    /// longer ones can be picked out by the length of
+    /// [`prefix`](CommonPrefix::prefix).
+    ///
+    /// # Examples
+    /// ```
+    /// use grammarsmith::grammar::*;
+    ///
+    /// let source = r#"stmt = Id "=" expr | Id "(" args ")" | "return" expr ;"#;
+    /// let grammar = parse_ebnf(source).value;
+    /// let found = grammar.common_prefixes();
+    /// assert_eq!(found.len(), 1);
+    /// assert_eq!(found[0].factored.to_string(), r#"Id ("=" expr | "(" args ")") | "return" expr"#);
+    ///
+    /// let diagnostic = found[0].to_diagnostic(&grammar);
+    /// assert_eq!(diagnostic.message, "2 alternatives of rule `stmt` start with `Id`");
+    /// ```
+    pub fn common_prefixes(&self) -> Vec<CommonPrefix> {
+        let mut found = Vec::new();
+        for rule in self.rules() {
+            find_prefixes(&rule.name, &rule.expr, 0, &mut found);
+        }
+        found
+    }
+
+    /// Reports the alternatives that start with the same expressions as
+    /// warnings, as found by [`Grammar::common_prefixes`].
+    pub fn check_common_prefixes(&self) -> Vec<Diagnostic> {
+        self.common_prefixes()
+            .iter()
+            .map(|found| found.to_diagnostic(self))
+            .collect()
+    }
+
+    /// Returns an equivalent grammar with the common prefixes of the
+    /// alternatives of its choices factored out.
+    ///
+    /// Alternatives starting with the same expressions are replaced, where
+    /// the first of them was, by the prefix followed by a choice of what
+    /// comes after it, which is optional if one of them ends with the
+    /// prefix, so `a b | a c | a` becomes `a (b | c)?`. The new grammar
+    /// matches the same token sequences, but into different trees.
+    /// Rewritten rules keep their span but lose their expression spans.
+    ///
+    /// # Examples
+    /// ```
+    /// use grammarsmith::grammar::*;
+    ///
+    /// let grammar = parse_ebnf(r#"
+    ///     call = Id "(" ")" | Id "(" args ")" | Id ;
+    /// "#).value;
+    /// assert_eq!(
+    ///     grammar.left_factor().to_string(),
+    ///     "call = Id (\"(\" (\")\" | args \")\"))? ;\n"
+    /// );
+    /// ```
+    pub fn left_factor(&self) -> Grammar {
+        let mut grammar = Grammar::new();
+        for import in self.imports() {
+            grammar.add_import(import.clone());
+        }
+        for rule in self.rules() {
+            let expr = factor(&rule.expr);
+            if expr == rule.expr {
+                grammar.add(rule.clone());
+                continue;
+            }
+            let mut factored = Rule::new(rule.name.clone(), expr);
+            factored.span = rule.span;
+            factored.error = rule.error.clone();
+            grammar.add(factored);
+        }
+        for level in self.precedence() {
+            grammar.add_precedence(level.clone());
+        }
+        for def in self.token_defs() {
+            grammar.add_token_def(def.clone());
+        }
+        grammar
+    }
+}
+
+/// Pushes the common prefixes of `expr`, the `index`th expression of
+/// `rule`, and returns the index of the expression after it.
+fn find_prefixes(rule: &str, expr: &Expr, index: usize, found: &mut Vec<CommonPrefix>) -> usize {
+    match expr {
+        Expr::Token(_) | Expr::Rule(_) => index + 1,
+        Expr::Seq(exprs) => exprs.iter().fold(index + 1, |child, expr| {
+            find_prefixes(rule, expr, child, found)
+        }),
+        Expr::Choice(alternatives) => {
+            let mut indices = Vec::with_capacity(alternatives.len());
+            let mut child = index + 1;
+            let mut nested = Vec::new();
+            for alternative in alternatives {
+                indices.push(child);
+                child = find_prefixes(rule, alternative, child, &mut nested);
+            }
+            let factored = factor(expr);
+            for group in groups(alternatives) {
+                if group.len() < 2 {
+                    continue;
+                }
+                let members: Vec<&Expr> = group.iter().map(|&i| &alternatives[i]).collect();
+                found.push(CommonPrefix {
+                    rule: rule.to_string(),
+                    expr: index,
+                    alternatives: group.iter().map(|&i| indices[i]).collect(),
+                    prefix: common_prefix(&members),
+                    factored: factored.clone(),
+                });
+            }
+            found.extend(nested);
+            child
+        }
+        Expr::Optional(inner) | Expr::Repeat(inner) | Expr::Repeat1(inner) => {
+            find_prefixes(rule, inner, index + 1, found)
+        }
+    }
+}
+
+/// Returns `expr` with the common prefixes of its choices factored out.
+fn factor(expr: &Expr) -> Expr {
+    match expr {
+        Expr::Token(_) | Expr::Rule(_) => expr.clone(),
+        Expr::Seq(exprs) => Expr::Seq(exprs.iter().map(factor).collect()),
+        Expr::Choice(alternatives) => {
+            let alternatives: Vec<Expr> = alternatives.iter().map(factor).collect();
+            let mut factored = Vec::new();
+            for group in groups(&alternatives) {
+                if let [single] = group[..] {
+                    factored.push(parts(&alternatives[single]).to_vec());
+                    continue;
+                }
+                let members: Vec<&Expr> = group.iter().map(|&i| &alternatives[i]).collect();
+                let mut prefix = common_prefix(&members);
+                let suffixes: Vec<Vec<Expr>> = members
+                    .iter()
+                    .map(|member| parts(member)[prefix.len()..].to_vec())
+                    .filter(|suffix| !suffix.is_empty())
+                    .collect();
+                if !suffixes.is_empty() {
+                    let rest = factor(&choice(suffixes.clone()));
+                    // One of them ends with the prefix.
+                    if suffixes.len() < members.len() {
+                        prefix.push(Expr::optional(rest));
+                    } else {
+                        prefix.push(rest);
+                    }
+                }
+                factored.push(prefix);
+            }
+            choice(factored)
+        }
+        Expr::Optional(inner) => Expr::optional(factor(inner)),
+        Expr::Repeat(inner) => Expr::repeat(factor(inner)),
+        Expr::Repeat1(inner) => Expr::repeat1(factor(inner)),
+    }
+}
+
+/// Returns the expressions an alternative is a sequence of.
+fn parts(alternative: &Expr) -> &[Expr] {
+    match alternative {
+        Expr::Seq(exprs) => exprs,
+        expr => std::slice::from_ref(expr),
+    }
+}
+
+/// Groups alternatives by their first expression, in the order the groups
+/// first appear. Alternatives matching nothing are on their own.
+fn groups(alternatives: &[Expr]) -> Vec<Vec<usize>> {
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    for (i, alternative) in alternatives.iter().enumerate() {
+        let first = parts(alternative).first();
+        let group = groups
+            .iter_mut()
+            .find(|group| first.is_some() && parts(&alternatives[group[0]]).first() == first);
+        match group {
+            Some(group) => group.push(i),
+            None => groups.push(vec![i]),
+        }
+    }
+    groups
+}
+
+/// Returns the longest prefix of the alternatives, one of which is not a
+/// prefix of all the others.
+fn common_prefix(alternatives: &[&Expr]) -> Vec<Expr> {
+    let first = parts(alternatives[0]);
+    let len = alternatives[1..]
+        .iter()
+        .fold(first.len(), |len, alternative| {
+            let other = parts(alternative);
+            first[..len]
+                .iter()
+                .zip(other)
+                .take_while(|(a, b)| a == b)
+                .count()
+        });
+    first[..len].to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::parse_ebnf;
+
+    #[test]
+    fn test_common_prefixes() {
+        let grammar = parse_ebnf(
+            r#"
+            stmt = "if" e "then" stmt "else" stmt | "if" e "then" stmt | X
+                 | ("a" "b" | "a" "c")* ;
+            e = X ;
+            "#,
+        )
+        .value;
+        let found = grammar.common_prefixes();
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].alternatives, [1, 8]);
+        assert_eq!(
+            Expr::seq(found[0].prefix.iter().cloned()).to_string(),
+            r#""if" e "then" stmt"#
+        );
+        assert_eq!(found[1].prefix, [Expr::token("\"a\"")]);
+        assert!(grammar.check_common_prefixes()[0].primary_span().is_some());
+
+        let factored = grammar.left_factor();
+        assert_eq!(
+            factored.to_string(),
+            r#"stmt = "if" e "then" stmt ("else" stmt)? | X | ("a" ("b" | "c"))* ;
+e = X ;
+"#
+        );
+        assert!(factored.common_prefixes().is_empty());
+        assert_eq!(factored.get("e"), grammar.get("e"));
+    }
+
+    #[test]
+    fn test_suggestion() {
+        let source = r#"a = X (Y Z | Y W) ;"#;
+        let grammar = parse_ebnf(source).value;
+        let diagnostics = grammar.check_common_prefixes();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "2 alternatives of rule `a` start with `Y`"
+        );
+        let edit = &diagnostics[0].suggestions[0].edits[0];
+        assert_eq!(edit.new_text, "Y (Z | W)");
+        let span = edit.range;
+        assert_eq!(&source[span.start()..span.end()], "Y Z | Y W");
+    }
+}
@@ -0,0 +1,462 @@
+use crate::diagnostics::Diagnostic;
+use crate::position::Span;
+use crate::tree::ParseTree;
+use std::collections::HashMap;
+
+/// The index of a node of a [`ParseForest`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NodeId(usize);
+
+impl NodeId {
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+/// A node of a [`ParseForest`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ForestNode {
+    /// The token at `index` in the input.
+    Token { kind: usize, index: usize },
+    /// A match of `rule` from the token at `start` up to the one at `end`,
+    /// with each way it matches.
+    Rule {
+        rule: usize,
+        start: usize,
+        end: usize,
+        alternatives: Vec<Packed>,
+    },
+}
+
+/// A way a rule matches: one of its productions, with a node for each of
+/// its symbols.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Packed {
+    pub production: usize,
+    pub children: Vec<NodeId>,
+}
+
+/// A region of the input that a rule matches in more than one way, from
+/// [`ParseForest::ambiguities`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Ambiguity {
+    pub rule: String,
+    pub span: Span,
+    /// For each way, the spans of the parts it splits the region into.
+    pub interpretations: Vec<Vec<Span>>,
+}
+
+impl Ambiguity {
+    /// Returns a warning at the region, with a note per interpretation
+    /// quoting its parts from `source`, the text the spans refer to.
+    pub fn to_diagnostic(&self, source: &str) -> Diagnostic {
+        let mut diagnostic = Diagnostic::warning(format!(
+            "`{}` matches this in {} ways",
+            self.rule,
+            self.interpretations.len()
+        ))
+        .with_label(self.span, "ambiguous");
+        for parts in &self.interpretations {
+            let parts: Vec<String> = parts
+                .iter()
+                .map(|span| match source.get(span.start()..span.end()) {
+                    Some("") | None => "nothing".to_string(),
+                    Some(text) => format!("`{}`", text),
+                })
+                .collect();
+            diagnostic = diagnostic.with_note(format!("it can be read as {}", parts.join(" ")));
+        }
+        diagnostic
+    }
+}
+
+/// A shared packed parse forest: every parse of an input, from an
+/// ambiguity-tolerant parser such as
+/// [`GeneralizedParser`](super::GeneralizedParser).
+///
+/// Each match of a rule over a region of the input is a single node, shared
+/// by the parses using it, which holds each way the rule matches there, so
+/// the forest stays polynomial in the size of the input even when the
+/// number of trees is exponential. Trees can be enumerated with
+/// [`trees`](ParseForest::trees) and counted with
+/// [`count_trees`](ParseForest::count_trees), and the ambiguous regions
+/// reported with [`ambiguities`](ParseForest::ambiguities).
+///
+/// The rules are those of [`Grammar::to_bnf`](super::Grammar::to_bnf).
+/// Trees leave out the auxiliary rules, whose children go to the rule they
+/// come from, so they follow the rules of the grammar.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseForest {
+    rules: Vec<String>,
+    /// The rule of the grammar each rule comes from.
+    origins: Vec<usize>,
+    tokens: Vec<String>,
+    spans: Vec<Span>,
+    end: Span,
+    nodes: Vec<ForestNode>,
+    root: NodeId,
+}
+
+impl ParseForest {
+    /// Returns the node matching the start rule over the whole input.
+    pub fn root(&self) -> NodeId {
+        self.root
+    }
+
+    pub fn node(&self, id: NodeId) -> &ForestNode {
+        &self.nodes[id.0]
+    }
+
+    /// Returns the name of the token kind or rule of a node, auxiliary
+    /// rules being named after the rule they come from.
+    pub fn name(&self, id: NodeId) -> &str {
+        match self.nodes[id.0] {
+            ForestNode::Token { kind, .. } => &self.tokens[kind],
+            ForestNode::Rule { rule, .. } => &self.rules[self.origins[rule]],
+        }
+    }
+
+    /// Returns the span of a node. An empty match is placed at the start of
+    /// the token after it.
+    pub fn span(&self, id: NodeId) -> Span {
+        match self.nodes[id.0] {
+            ForestNode::Token { index, .. } => self.spans[index],
+            ForestNode::Rule { start, end, .. } if start < end => {
+                self.spans[start].union(&self.spans[end - 1])
+            }
+            ForestNode::Rule { start, .. } => match self.spans.get(start) {
+                Some(span) => Span::point(span.start()),
+                None => Span::point(self.end.start()),
+            },
+        }
+    }
+
+    /// Returns the number of trees in the forest, or `u64::MAX` if there
+    /// are more.
+    pub fn count_trees(&self) -> u64 {
+        fn count(forest: &ParseForest, id: NodeId, counts: &mut HashMap<NodeId, u64>) -> u64 {
+            if let Some(&count) = counts.get(&id) {
+                return count;
+            }
+            let total = match &forest.nodes[id.0] {
+                ForestNode::Token { .. } => 1,
+                ForestNode::Rule { alternatives, .. } => {
+                    alternatives.iter().fold(0u64, |total, packed| {
+                        let ways = packed.children.iter().fold(1u64, |ways, &child| {
+                            ways.saturating_mul(count(forest, child, counts))
+                        });
+                        total.saturating_add(ways)
+                    })
+                }
+            };
+            counts.insert(id, total);
+            total
+        }
+        count(self, self.root, &mut HashMap::new())
+    }
+
+    /// Returns whether the input has more than one tree.
+    pub fn is_ambiguous(&self) -> bool {
+        self.count_trees() > 1
+    }
+
+    /// Returns the regions of the input that a rule matches in more than
+    /// one way, in the order they start, enclosing regions first.
+    pub fn ambiguities(&self) -> Vec<Ambiguity> {
+        let mut ambiguities = Vec::new();
+        let mut seen = vec![false; self.nodes.len()];
+        let mut stack = vec![self.root];
+        while let Some(id) = stack.pop() {
+            if std::mem::replace(&mut seen[id.0], true) {
+                continue;
+            }
+            let ForestNode::Rule { alternatives, .. } = &self.nodes[id.0] else {
+                continue;
+            };
+            if alternatives.len() > 1 {
+                ambiguities.push(Ambiguity {
+                    rule: self.name(id).to_string(),
+                    span: self.span(id),
+                    interpretations: alternatives
+                        .iter()
+                        .map(|packed| packed.children.iter().map(|&c| self.span(c)).collect())
+                        .collect(),
+                });
+            }
+            for packed in alternatives {
+                stack.extend(&packed.children);
+            }
+        }
+        ambiguities.sort_by_key(|ambiguity| {
+            let span = ambiguity.span;
+            (span.start(), std::cmp::Reverse(span.end()))
+        });
+        ambiguities
+    }
+
+    /// Returns up to `limit` of the trees of the forest, taking earlier
+    /// productions first.
+    ///
+    /// Tree nodes are named after their rules, and tokens after their
+    /// kinds.
+    pub fn trees(&self, limit: usize) -> Vec<ParseTree<String>> {
+        self.expand(self.root, limit)
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+
+    /// Returns the ways of writing a node as a list of trees: a single
+    /// tree, or the children of an auxiliary rule.
+    fn expand(&self, id: NodeId, limit: usize) -> Vec<Vec<ParseTree<String>>> {
+        let span = self.span(id);
+        let (rule, alternatives) = match &self.nodes[id.0] {
+            ForestNode::Token { kind, .. } => {
+                let kind = self.tokens[*kind].clone();
+                return vec![vec![ParseTree::Token { kind, span }]];
+            }
+            ForestNode::Rule {
+                rule, alternatives, ..
+            } => (*rule, alternatives),
+        };
+        let mut ways = Vec::new();
+        for packed in alternatives {
+            let mut partial = vec![Vec::new()];
+            for &child in &packed.children {
+                let child_ways = self.expand(child, limit);
+                let mut next = Vec::new();
+                'product: for prefix in &partial {
+                    for way in &child_ways {
+                        if next.len() == limit {
+                            break 'product;
+                        }
+                        let mut trees: Vec<ParseTree<String>> = prefix.clone();
+                        trees.extend(way.iter().cloned());
+                        next.push(trees);
+                    }
+                }
+                partial = next;
+            }
+            ways.extend(partial.into_iter().take(limit - ways.len()));
+            if ways.len() == limit {
+                break;
+            }
+        }
+        if self.origins[rule] != rule {
+            return ways;
+        }
+        ways.into_iter()
+            .map(|children| {
+                vec![ParseTree::Node {
+                    kind: self.rules[rule].clone(),
+                    span,
+                    children,
+                }]
+            })
+            .collect()
+    }
+}
+
+/// Builds a [`ParseForest`], sharing the nodes of the same match.
+#[derive(Debug, Default)]
+pub(super) struct ForestBuilder {
+    nodes: Vec<ForestNode>,
+    tokens: HashMap<usize, NodeId>,
+    rules: HashMap<(usize, usize, usize), NodeId>,
+}
+
+impl ForestBuilder {
+    /// Returns the node of the token of kind `kind` at `index`.
+    pub(super) fn token(&mut self, kind: usize, index: usize) -> NodeId {
+        let nodes = &mut self.nodes;
+        *self.tokens.entry(index).or_insert_with(|| {
+            nodes.push(ForestNode::Token { kind, index });
+            NodeId(nodes.len() - 1)
+        })
+    }
+
+    /// Returns the node of the match of `rule` from `start` to `end`.
+    pub(super) fn rule(&mut self, rule: usize, start: usize, end: usize) -> NodeId {
+        let nodes = &mut self.nodes;
+        *self.rules.entry((rule, start, end)).or_insert_with(|| {
+            nodes.push(ForestNode::Rule {
+                rule,
+                start,
+                end,
+                alternatives: Vec::new(),
+            });
+            NodeId(nodes.len() - 1)
+        })
+    }
+
+    /// Returns the node of the match of `rule` from `start` to `end`, if
+    /// there is one.
+    pub(super) fn get(&self, rule: usize, start: usize, end: usize) -> Option<NodeId> {
+        self.rules.get(&(rule, start, end)).copied()
+    }
+
+    /// Adds a way to match to a rule node, returning whether it is new.
+    pub(super) fn pack(&mut self, id: NodeId, packed: Packed) -> bool {
+        let ForestNode::Rule { alternatives, .. } = &mut self.nodes[id.0] else {
+            panic!("only rule nodes have alternatives");
+        };
+        if alternatives.contains(&packed) {
+            return false;
+        }
+        alternatives.push(packed);
+        true
+    }
+
+    /// Removes the ways to match that go round a cycle, those with a node
+    /// that is also above them, so the forest reachable from `root` has
+    /// finitely many trees.
+    pub(super) fn break_cycles(&mut self, root: NodeId) {
+        #[derive(Clone, Copy, PartialEq)]
+        enum Visit {
+            New,
+            Open,
+            Done,
+        }
+        fn visit(nodes: &mut [ForestNode], visits: &mut [Visit], id: NodeId) {
+            visits[id.0] = Visit::Open;
+            let ForestNode::Rule { alternatives, .. } = &mut nodes[id.0] else {
+                visits[id.0] = Visit::Done;
+                return;
+            };
+            alternatives.retain(|packed| {
+                packed
+                    .children
+                    .iter()
+                    .all(|child| visits[child.0] != Visit::Open)
+            });
+            let children: Vec<NodeId> = alternatives
+                .iter()
+                .flat_map(|packed| packed.children.iter().copied())
+                .collect();
+            for child in children {
+                if visits[child.0] == Visit::New {
+                    visit(nodes, visits, child);
+                }
+            }
+            visits[id.0] = Visit::Done;
+        }
+        let mut visits = vec![Visit::New; self.nodes.len()];
+        visit(&mut self.nodes, &mut visits, root);
+    }
+
+    /// Finishes the forest rooted at `root`, naming its rules and tokens,
+    /// where `spans` are the spans of the tokens and `end` that of the end
+    /// of the input.
+    pub(super) fn finish(
+        self,
+        root: NodeId,
+        rules: &[String],
+        origins: &[usize],
+        tokens: &[String],
+        spans: Vec<Span>,
+        end: Span,
+    ) -> ParseForest {
+        let mut nodes = self.nodes;
+        let ends: Vec<usize> = nodes
+            .iter()
+            .map(|node| match *node {
+                ForestNode::Token { index, .. } => index + 1,
+                ForestNode::Rule { end, .. } => end,
+            })
+            .collect();
+        // Earlier productions first, then shorter first children, whatever
+        // order they were found in.
+        for node in &mut nodes {
+            if let ForestNode::Rule { alternatives, .. } = node {
+                alternatives.sort_by_cached_key(|packed| {
+                    let children = packed.children.iter().map(|child| ends[child.0]);
+                    (packed.production, children.collect::<Vec<_>>())
+                });
+            }
+        }
+        ParseForest {
+            rules: rules.to_vec(),
+            origins: origins.to_vec(),
+            tokens: tokens.to_vec(),
+            spans,
+            end,
+            nodes,
+            root,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::parse_ebnf;
+    use crate::position::WithSpan;
+
+    fn forest(source: &str, kinds: &[&str]) -> ParseForest {
+        let parser = parse_ebnf(source).value.generalized_parser().unwrap();
+        let tokens: Vec<_> = kinds
+            .iter()
+            .enumerate()
+            .map(|(i, kind)| {
+                let span = Span::new_unchecked(2 * i, 2 * i + 1);
+                WithSpan::new(parser.token(kind).unwrap(), span)
+            })
+            .collect();
+        let end = Span::point(2 * kinds.len());
+        parser.parse(&tokens, end).value.unwrap()
+    }
+
+    #[test]
+    fn test_trees_leave_out_auxiliary_rules() {
+        let forest = forest(
+            r#"list = "[" (N ("," N)*)? "]" ;"#,
+            &["\"[\"", "N", "\",\"", "N", "\"]\""],
+        );
+        let trees = forest.trees(10);
+        assert_eq!(trees.len(), 1);
+        let ParseTree::Node {
+            kind,
+            span,
+            children,
+        } = &trees[0]
+        else {
+            panic!("the root is a node");
+        };
+        assert_eq!(kind, "list");
+        assert_eq!((span.start(), span.end()), (0, 9));
+        let kinds: Vec<_> = children
+            .iter()
+            .map(|child| match child {
+                ParseTree::Token { kind, .. } => kind.as_str(),
+                ParseTree::Node { kind, .. } => kind.as_str(),
+            })
+            .collect();
+        assert_eq!(kinds, ["\"[\"", "N", "\",\"", "N", "\"]\""]);
+        assert!(forest.ambiguities().is_empty());
+    }
+
+    #[test]
+    fn test_ambiguous_empty_matches() {
+        let forest = forest(r#"s = a N | N b ; a = ; b = ;"#, &["N"]);
+        assert_eq!(forest.count_trees(), 2);
+        let ambiguities = forest.ambiguities();
+        assert_eq!(ambiguities.len(), 1);
+        let diagnostic = ambiguities[0].to_diagnostic("x");
+        assert_eq!(diagnostic.message, "`s` matches this in 2 ways");
+        assert_eq!(
+            diagnostic.notes,
+            [
+                "it can be read as nothing `x`",
+                "it can be read as `x` nothing"
+            ]
+        );
+        let trees = forest.trees(1);
+        let ParseTree::Node { children, .. } = &trees[0] else {
+            panic!("the root is a node");
+        };
+        let ParseTree::Node { span: empty, .. } = children[0] else {
+            panic!("`a` is a node");
+        };
+        assert_eq!((empty.start(), empty.end()), (0, 0));
+    }
+}
@@ -0,0 +1,379 @@
+use super::forest::{ForestBuilder, Packed, ParseForest};
+use super::table::{Bnf, Production, Symbol};
+use super::Grammar;
+use crate::diagnostics::{expected_list, Diagnostic};
+use crate::parser::ParseOutput;
+use crate::position::{Span, WithSpan};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
+
+/// An error building a [`GeneralizedParser`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GeneralizedError {
+    /// A rule can derive itself and nothing else, so an input would have
+    /// infinitely many trees.
+    Cyclic { rule: String },
+}
+
+impl fmt::Display for GeneralizedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GeneralizedError::Cyclic { rule } => {
+                write!(f, "rule `{}` can derive itself and nothing else", rule)
+            }
+        }
+    }
+}
+
+impl std::error::Error for GeneralizedError {}
+
+/// A parser for any grammar, ambiguous or left-recursive, which finds every
+/// parse of an input at once, as a [`ParseForest`].
+///
+/// Where [`LlTable`](super::LlTable) and [`RustGenerator`](super::RustGenerator)
+/// need an LL(1) grammar, this takes the grammar as it is written, such as
+/// `expr = expr "+" expr | Number`, at the cost of speed, and leaves it to
+/// the caller to choose between the trees, or to report the ambiguities.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::grammar::*;
+/// use grammarsmith::position::{Span, WithSpan};
+///
+/// let grammar = parse_ebnf(r#"expr = expr "+" expr | Number ;"#).value;
+/// let parser = GeneralizedParser::new(&grammar).unwrap();
+/// let (number, plus) = (parser.token("Number").unwrap(), parser.token("\"+\"").unwrap());
+///
+/// // 1+2+3
+/// let tokens: Vec<_> = [number, plus, number, plus, number]
+///     .into_iter()
+///     .enumerate()
+///     .map(|(i, kind)| WithSpan::new(kind, Span::new_unchecked(i, i + 1)))
+///     .collect();
+/// let forest = parser.parse(&tokens, Span::point(5)).value.unwrap();
+///
+/// assert_eq!(forest.count_trees(), 2);
+/// let ambiguity = &forest.ambiguities()[0];
+/// assert_eq!(
+///     ambiguity.to_diagnostic("1+2+3").notes,
+///     ["it can be read as `1` `+` `2+3`", "it can be read as `1+2` `+` `3`"]
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct GeneralizedParser {
+    bnf: Bnf,
+    /// The indices of the productions of each rule.
+    by_rule: Vec<Vec<usize>>,
+}
+
+impl GeneralizedParser {
+    pub fn new(grammar: &Grammar) -> Result<Self, GeneralizedError> {
+        let bnf = Bnf::new(grammar);
+        let mut by_rule = vec![Vec::new(); bnf.rules.len()];
+        for (index, production) in bnf.productions.iter().enumerate() {
+            by_rule[production.rule].push(index);
+        }
+        let parser = GeneralizedParser { bnf, by_rule };
+        if let Some(rule) = parser.cycle() {
+            return Err(GeneralizedError::Cyclic {
+                rule: parser.bnf.rules[parser.bnf.origins[rule]].clone(),
+            });
+        }
+        Ok(parser)
+    }
+
+    /// Returns the names of the rules, as in [`Grammar::to_bnf`], the start
+    /// rule first.
+    pub fn rules(&self) -> &[String] {
+        &self.bnf.rules
+    }
+
+    /// Returns the names of the token kinds.
+    pub fn tokens(&self) -> &[String] {
+        &self.bnf.tokens
+    }
+
+    /// Returns the index of the token kind with the given name.
+    pub fn token(&self, name: &str) -> Option<usize> {
+        self.bnf.tokens.iter().position(|token| token == name)
+    }
+
+    /// Parses token kinds, by index, from the start rule, where `end` is the
+    /// span of the end of the input.
+    ///
+    /// # Returns
+    /// The forest of every parse of the whole input, or `None` with an error
+    /// at the furthest token no parse gets past.
+    pub fn parse(&self, tokens: &[WithSpan<usize>], end: Span) -> ParseOutput<Option<ParseForest>> {
+        if self.by_rule.is_empty() {
+            return ParseOutput::new(None, Vec::new());
+        }
+        let mut run = Run {
+            parser: self,
+            tokens,
+            builder: ForestBuilder::default(),
+            ends: HashMap::new(),
+            visited: HashSet::new(),
+            changed: true,
+            furthest: 0,
+            expected: BTreeSet::new(),
+        };
+        // Left recursion sees the matches found so far, so repeat until no
+        // more are found.
+        while run.changed {
+            run.changed = false;
+            run.visited.clear();
+            run.ends_of(0, 0);
+        }
+        let ends = run.ends.get(&(0, 0)).cloned().unwrap_or_default();
+        for &position in &ends {
+            if position < tokens.len() {
+                run.fail(position, None);
+            }
+        }
+        if let Some(root) = run.builder.get(0, 0, tokens.len()) {
+            let spans = tokens.iter().map(|token| token.span).collect();
+            let bnf = &self.bnf;
+            let forest =
+                run.builder
+                    .finish(root, &bnf.rules, &bnf.origins, &bnf.tokens, spans, end);
+            return ParseOutput::new(Some(forest), Vec::new());
+        }
+        let name = |token: Option<usize>| match token {
+            Some(token) => self
+                .bnf
+                .tokens
+                .get(token)
+                .map_or("an unknown token", String::as_str),
+            None => "end of input",
+        };
+        let (found, span) = match tokens.get(run.furthest) {
+            Some(token) => (Some(token.value), token.span),
+            None => (None, end),
+        };
+        let error = Diagnostic::error(format!(
+            "expected {}, found {}",
+            expected_list(run.expected.iter().map(|&token| name(token))),
+            name(found)
+        ))
+        .with_label(span, "");
+        ParseOutput::new(None, vec![error])
+    }
+
+    /// Returns a rule that can derive itself with nothing around it, if
+    /// there is one.
+    fn cycle(&self) -> Option<usize> {
+        let analysis = self.bnf.plain.analyze();
+        let nullable = |symbol: &Symbol| match *symbol {
+            Symbol::Token(_) => false,
+            Symbol::Rule(rule) => analysis.is_nullable(&self.bnf.rules[rule]),
+        };
+        // The rules each rule can derive with only nullable symbols around.
+        let mut unit: Vec<BTreeSet<usize>> = vec![BTreeSet::new(); self.bnf.rules.len()];
+        for production in &self.bnf.productions {
+            for (i, symbol) in production.symbols.iter().enumerate() {
+                let Symbol::Rule(rule) = *symbol else {
+                    continue;
+                };
+                let (before, after) = (&production.symbols[..i], &production.symbols[i + 1..]);
+                if before.iter().chain(after).all(nullable) {
+                    unit[production.rule].insert(rule);
+                }
+            }
+        }
+        (0..self.bnf.rules.len()).find(|&start| {
+            let mut seen = HashSet::new();
+            let mut stack: Vec<usize> = unit[start].iter().copied().collect();
+            while let Some(rule) = stack.pop() {
+                if rule == start {
+                    return true;
+                }
+                if seen.insert(rule) {
+                    stack.extend(unit[rule].iter().copied());
+                }
+            }
+            false
+        })
+    }
+}
+
+/// The state of a parse: the matches found so far, in the forest and by
+/// where they start.
+struct Run<'a> {
+    parser: &'a GeneralizedParser,
+    tokens: &'a [WithSpan<usize>],
+    builder: ForestBuilder,
+    /// Where the matches of each rule from each position end.
+    ends: HashMap<(usize, usize), BTreeSet<usize>>,
+    /// The rules and positions parsed this round.
+    visited: HashSet<(usize, usize)>,
+    /// Whether this round found a match.
+    changed: bool,
+    furthest: usize,
+    /// What could come at the furthest position, `None` for the end of the
+    /// input.
+    expected: BTreeSet<Option<usize>>,
+}
+
+impl Run<'_> {
+    /// Returns where the matches of `rule` from `start` end, as far as they
+    /// are known in this round.
+    fn ends_of(&mut self, rule: usize, start: usize) -> BTreeSet<usize> {
+        if self.visited.insert((rule, start)) {
+            for &production in &self.parser.by_rule[rule] {
+                self.production(production, start);
+            }
+        }
+        self.ends.get(&(rule, start)).cloned().unwrap_or_default()
+    }
+
+    /// Adds the matches of a production from `start` to the forest.
+    fn production(&mut self, index: usize, start: usize) {
+        let production: &Production = &self.parser.bnf.productions[index];
+        // Each way to match the symbols so far: where it ends, and a node
+        // for each symbol.
+        let mut partial = vec![(start, Vec::new())];
+        for &symbol in &production.symbols {
+            let mut next = Vec::new();
+            for (position, children) in partial {
+                match symbol {
+                    Symbol::Token(kind) => {
+                        if self.tokens.get(position).map(|token| token.value) == Some(kind) {
+                            let mut children = children;
+                            children.push(self.builder.token(kind, position));
+                            next.push((position + 1, children));
+                        } else {
+                            self.fail(position, Some(kind));
+                        }
+                    }
+                    Symbol::Rule(rule) => {
+                        for end in self.ends_of(rule, position) {
+                            let mut children = children.clone();
+                            children.push(self.builder.rule(rule, position, end));
+                            next.push((end, children));
+                        }
+                    }
+                }
+            }
+            partial = next;
+        }
+        for (end, children) in partial {
+            let node = self.builder.rule(production.rule, start, end);
+            let packed = Packed {
+                production: index,
+                children,
+            };
+            if self.builder.pack(node, packed) {
+                self.ends
+                    .entry((production.rule, start))
+                    .or_default()
+                    .insert(end);
+                self.changed = true;
+            }
+        }
+    }
+
+    /// Records that `expected` could come at `position` but does not.
+    fn fail(&mut self, position: usize, expected: Option<usize>) {
+        if position > self.furthest {
+            self.furthest = position;
+            self.expected.clear();
+        }
+        if position == self.furthest {
+            self.expected.insert(expected);
+        }
+    }
+}
+
+impl Grammar {
+    /// Returns a [`GeneralizedParser`] for the grammar.
+    pub fn generalized_parser(&self) -> Result<GeneralizedParser, GeneralizedError> {
+        GeneralizedParser::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::{parse_ebnf, ForestNode};
+    use crate::tree::ParseTree;
+
+    fn tokens(parser: &GeneralizedParser, kinds: &[&str]) -> Vec<WithSpan<usize>> {
+        kinds
+            .iter()
+            .enumerate()
+            .map(|(i, kind)| {
+                WithSpan::new(parser.token(kind).unwrap(), Span::new_unchecked(i, i + 1))
+            })
+            .collect()
+    }
+
+    fn shape(tree: &ParseTree<String>) -> String {
+        match tree {
+            ParseTree::Token { kind, .. } => kind.trim_matches('"').to_string(),
+            ParseTree::Node { children, .. } => {
+                let children: Vec<String> = children.iter().map(shape).collect();
+                format!("({})", children.join(" "))
+            }
+        }
+    }
+
+    #[test]
+    fn test_ambiguous_forest() {
+        let grammar = parse_ebnf(
+            r#"
+            stmt = expr ";"* ;
+            expr = expr "+" expr | N ;
+            "#,
+        )
+        .value;
+        let parser = grammar.generalized_parser().unwrap();
+        let input = ["N", "\"+\"", "N", "\"+\"", "N", "\"+\"", "N", "\";\""];
+        let forest = parser
+            .parse(&tokens(&parser, &input), Span::point(8))
+            .value
+            .unwrap();
+        assert_eq!(forest.count_trees(), 5);
+        assert!(forest.is_ambiguous());
+        let shapes: Vec<String> = forest.trees(2).iter().map(shape).collect();
+        assert_eq!(
+            shapes,
+            [
+                "(((N) + ((N) + ((N) + (N)))) ;)",
+                "(((N) + (((N) + (N)) + (N))) ;)"
+            ]
+        );
+        let ambiguities = forest.ambiguities();
+        let spans: Vec<_> = ambiguities
+            .iter()
+            .map(|a| (a.span.start(), a.span.end(), a.interpretations.len()))
+            .collect();
+        assert_eq!(spans, [(0, 7, 3), (0, 5, 2), (2, 7, 2)]);
+        let ForestNode::Rule { alternatives, .. } = forest.node(forest.root()) else {
+            panic!("the root is a rule");
+        };
+        assert_eq!(alternatives.len(), 1);
+        assert_eq!(forest.name(forest.root()), "stmt");
+    }
+
+    #[test]
+    fn test_errors() {
+        let grammar = parse_ebnf(r#"list = list "," N | N | ;"#).value;
+        let parser = grammar.generalized_parser().unwrap();
+        let forest = parser
+            .parse(&tokens(&parser, &["N", "\",\"", "N"]), Span::point(3))
+            .value
+            .unwrap();
+        assert!(!forest.is_ambiguous());
+        let error = parser
+            .parse(&tokens(&parser, &["N", "N"]), Span::point(2))
+            .diagnostics;
+        assert_eq!(error[0].message, "expected end of input or \",\", found N");
+
+        let cyclic = parse_ebnf(r#"a = b | X ; b = a Y? ;"#).value;
+        assert_eq!(
+            cyclic.generalized_parser().unwrap_err().to_string(),
+            "rule `a` can derive itself and nothing else"
+        );
+    }
+}
@@ -0,0 +1,595 @@
+use super::lexer::{parse_regex, Class, Node};
+use super::{Expr, Grammar, Terminal};
+use crate::position::{Span, WithSpan};
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+/// An error generating sentences from a grammar.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GenerateError {
+    /// The grammar has no rules.
+    Empty,
+    /// A rule refers to a rule the grammar does not define.
+    UndefinedRule { rule: String, name: String },
+    /// A token kind has no payload, literal text or definition to give it
+    /// text.
+    MissingPayload { token: String },
+    /// The pattern of a token definition is not a regular expression of the
+    /// subset [`Lexer`](super::Lexer) supports.
+    InvalidPattern { token: String, message: String },
+    /// The rule to generate can only match infinite sentences.
+    NonTerminating { rule: String },
+}
+
+impl fmt::Display for GenerateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GenerateError::Empty => write!(f, "the grammar has no rules"),
+            GenerateError::UndefinedRule { rule, name } => {
+                write!(f, "rule `{}` refers to undefined rule `{}`", rule, name)
+            }
+            GenerateError::MissingPayload { token } => {
+                write!(f, "no text can be generated for token `{}`", token)
+            }
+            GenerateError::InvalidPattern { token, message } => {
+                write!(f, "invalid pattern for token `{}`: {}", token, message)
+            }
+            GenerateError::NonTerminating { rule } => {
+                write!(f, "rule `{}` matches no finite sentence", rule)
+            }
+        }
+    }
+}
+
+impl std::error::Error for GenerateError {}
+
+/// A sentence generated by a [`SentenceGenerator`]: its text, and its
+/// tokens with their kinds and spans in the text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sentence {
+    pub text: String,
+    pub tokens: Vec<WithSpan<String>>,
+}
+
+/// Generates random sentences matching a [`Grammar`], the inverse of
+/// parsing, to seed fuzzing corpora, benchmark parsers, or check that what
+/// a parser accepts round-trips.
+///
+/// Tokens get their text from a payload factory given with
+/// [`with_payload`](SentenceGenerator::with_payload), which is called
+/// with a random number, or else from their literal, or else from their
+/// [`TokenDef`](super::TokenDef), patterns generating random text they
+/// match. Tokens are separated by a space, or the text given with
+/// [`with_separator`](SentenceGenerator::with_separator).
+///
+/// Alternatives are chosen at random, but the deeper rules nest, the less
+/// likely the generator is to take alternatives, optional parts and
+/// repetitions that lead deeper: their weight is multiplied by the
+/// damping factor at each level. Past the maximum depth it always takes the
+/// shortest way out, so generation ends even for recursive grammars.
+///
+/// The same seed always generates the same sentences.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::grammar::*;
+///
+/// let grammar = parse_ebnf(r#"
+///     expr = term (("+" | "-") term)* ;
+///     term = Number | "(" expr ")" ;
+///     token Number = /[1-9][0-9]?/ ;
+///     skip Space = / +/ ;
+/// "#).value;
+///
+/// let mut generator = SentenceGenerator::new(&grammar).with_seed(7);
+/// let lexer = Lexer::new(&grammar).unwrap();
+/// for _ in 0..10 {
+///     let sentence = generator.generate().unwrap();
+///     let tokens = lexer.tokenize(&sentence.text).value;
+///     assert_eq!(tokens.len(), sentence.tokens.len());
+///     for (token, generated) in tokens.iter().zip(&sentence.tokens) {
+///         assert_eq!(lexer.name(token.value), generated.value);
+///         assert_eq!(token.span, generated.span);
+///     }
+/// }
+/// ```
+#[derive(Clone)]
+pub struct SentenceGenerator<'g> {
+    grammar: &'g Grammar,
+    heights: HashMap<&'g str, usize>,
+    payloads: HashMap<String, Rc<dyn Fn(u64) -> String>>,
+    separator: String,
+    max_depth: usize,
+    max_repeat: usize,
+    damping: f64,
+    state: u64,
+}
+
+impl fmt::Debug for SentenceGenerator<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SentenceGenerator")
+            .field("separator", &self.separator)
+            .field("max_depth", &self.max_depth)
+            .field("max_repeat", &self.max_repeat)
+            .field("damping", &self.damping)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'g> SentenceGenerator<'g> {
+    pub fn new(grammar: &'g Grammar) -> Self {
+        SentenceGenerator {
+            grammar,
+            heights: heights(grammar),
+            payloads: HashMap::new(),
+            separator: " ".to_string(),
+            max_depth: 8,
+            max_repeat: 3,
+            damping: 0.7,
+            state: 0,
+        }
+    }
+
+    /// Sets the seed of the random numbers, 0 by default.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.state = seed;
+        self
+    }
+
+    /// Sets how deep rules can nest before the generator takes the shortest
+    /// way out, 8 by default.
+    pub fn with_max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Sets how many times repetitions repeat at most, 3 by default, not
+    /// counting the first match of a non-empty repetition.
+    pub fn with_max_repeat(mut self, count: usize) -> Self {
+        self.max_repeat = count;
+        self
+    }
+
+    /// Sets by how much the weights of ways deeper are multiplied at each
+    /// level, between 0 and 1, 0.7 by default.
+    pub fn with_damping(mut self, damping: f64) -> Self {
+        self.damping = damping;
+        self
+    }
+
+    /// Sets the text between tokens, a space by default.
+    pub fn with_separator(mut self, separator: impl Into<String>) -> Self {
+        self.separator = separator.into();
+        self
+    }
+
+    /// Sets the factory of the text of the token kind `token`, called with
+    /// a random number.
+    pub fn with_payload(
+        mut self,
+        token: impl Into<String>,
+        payload: impl Fn(u64) -> String + 'static,
+    ) -> Self {
+        self.payloads.insert(token.into(), Rc::new(payload));
+        self
+    }
+
+    /// Generates a sentence of the start rule.
+    pub fn generate(&mut self) -> Result<Sentence, GenerateError> {
+        let grammar = self.grammar;
+        let start = grammar.start().ok_or(GenerateError::Empty)?;
+        self.generate_rule(&start.name)
+    }
+
+    /// Generates a sentence of the rule `name`.
+    pub fn generate_rule(&mut self, name: &str) -> Result<Sentence, GenerateError> {
+        let mut state = self.state;
+        let sentence = self.run(name, &mut || next_u64(&mut state));
+        self.state = state;
+        sentence
+    }
+
+    /// Generates a sentence of the start rule with choices taken from
+    /// `u`, for fuzzing with [`arbitrary`]. Once `u` runs out of data, the
+    /// generator takes the shortest way out.
+    #[cfg(feature = "fuzzing")]
+    pub fn generate_arbitrary(
+        &self,
+        u: &mut arbitrary::Unstructured<'_>,
+    ) -> Result<Sentence, GenerateError> {
+        let start = self.grammar.start().ok_or(GenerateError::Empty)?;
+        self.run(&start.name, &mut || u.arbitrary().unwrap_or(0))
+    }
+
+    fn run(&self, name: &str, random: &mut Random<'_>) -> Result<Sentence, GenerateError> {
+        let rule = self
+            .grammar
+            .get(name)
+            .ok_or_else(|| GenerateError::UndefinedRule {
+                rule: name.to_string(),
+                name: name.to_string(),
+            })?;
+        for rule in self.grammar.rules() {
+            let mut undefined = None;
+            rule.expr.walk(&mut |expr| match expr {
+                Expr::Rule(name) if self.grammar.get(name).is_none() => {
+                    undefined.get_or_insert(name);
+                }
+                _ => {}
+            });
+            if let Some(name) = undefined {
+                return Err(GenerateError::UndefinedRule {
+                    rule: rule.name.clone(),
+                    name: name.clone(),
+                });
+            }
+        }
+        if self.height(&rule.expr) == INFINITE {
+            return Err(GenerateError::NonTerminating {
+                rule: rule.name.clone(),
+            });
+        }
+        let mut payloads = HashMap::new();
+        for token in self.grammar.tokens() {
+            payloads.insert(token, self.payload(token)?);
+        }
+        let mut run = Run {
+            generator: self,
+            payloads,
+            random,
+            sentence: Sentence {
+                text: String::new(),
+                tokens: Vec::new(),
+            },
+        };
+        run.expr(&rule.expr, 0);
+        Ok(run.sentence)
+    }
+
+    fn payload(&self, token: &str) -> Result<Payload<'_>, GenerateError> {
+        if let Some(factory) = self.payloads.get(token) {
+            return Ok(Payload::Factory(factory.as_ref()));
+        }
+        if let Some(text) = literal_text(token) {
+            return Ok(Payload::Text(text.to_string()));
+        }
+        match self.grammar.token_def(token).map(|def| &def.terminal) {
+            Some(Terminal::Literal(text)) => Ok(Payload::Text(text.clone())),
+            Some(Terminal::Pattern(regex)) => {
+                parse_regex(regex)
+                    .map(Payload::Pattern)
+                    .map_err(|(offset, message)| GenerateError::InvalidPattern {
+                        token: token.to_string(),
+                        message: format!("{} at offset {}", message, offset),
+                    })
+            }
+            None => Err(GenerateError::MissingPayload {
+                token: token.to_string(),
+            }),
+        }
+    }
+
+    /// Returns how deep rules must nest at least to match `expr`.
+    fn height(&self, expr: &Expr) -> usize {
+        height(expr, &self.heights)
+    }
+}
+
+/// The height of what can only match infinite sentences.
+const INFINITE: usize = usize::MAX;
+
+/// Computes how deep rules must nest at least to match each rule, by
+/// iterating to a fixpoint.
+fn heights(grammar: &Grammar) -> HashMap<&str, usize> {
+    let mut heights: HashMap<&str, usize> = grammar
+        .rules()
+        .iter()
+        .map(|rule| (rule.name.as_str(), INFINITE))
+        .collect();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for rule in grammar.rules() {
+            let height = height(&rule.expr, &heights);
+            if height < heights[rule.name.as_str()] {
+                heights.insert(&rule.name, height);
+                changed = true;
+            }
+        }
+    }
+    heights
+}
+
+fn height(expr: &Expr, heights: &HashMap<&str, usize>) -> usize {
+    match expr {
+        Expr::Token(_) => 0,
+        Expr::Rule(name) => heights
+            .get(name.as_str())
+            .map_or(INFINITE, |&height| height.saturating_add(1)),
+        Expr::Seq(exprs) => exprs
+            .iter()
+            .map(|expr| height(expr, heights))
+            .max()
+            .unwrap_or(0),
+        Expr::Choice(alternatives) => alternatives
+            .iter()
+            .map(|expr| height(expr, heights))
+            .min()
+            .unwrap_or(INFINITE),
+        Expr::Optional(_) | Expr::Repeat(_) => 0,
+        Expr::Repeat1(inner) => height(inner, heights),
+    }
+}
+
+/// Returns the text of a literal token kind, such as `+` for `"+"`.
+fn literal_text(kind: &str) -> Option<&str> {
+    ['"', '\''].into_iter().find_map(|quote| {
+        kind.strip_prefix(quote)?
+            .strip_suffix(quote)
+            .filter(|text| !text.is_empty())
+    })
+}
+
+/// Returns the next number of a SplitMix64 sequence.
+fn next_u64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+enum Payload<'a> {
+    Factory(&'a dyn Fn(u64) -> String),
+    Text(String),
+    Pattern(Node),
+}
+
+/// The state of the generation of a sentence.
+struct Run<'s, 'g, 'r> {
+    generator: &'s SentenceGenerator<'g>,
+    payloads: HashMap<&'g str, Payload<'s>>,
+    random: &'r mut Random<'r>,
+    sentence: Sentence,
+}
+
+type Random<'r> = dyn FnMut() -> u64 + 'r;
+
+// Random choices are made so that a random number of 0 takes the shortest
+// way, as fuzzers run out of data.
+
+/// Returns a random number below `n`, which must not be 0.
+fn below(random: &mut Random<'_>, n: usize) -> usize {
+    (random() % n as u64) as usize
+}
+
+/// Returns a random number between 0 and 1.
+fn uniform(random: &mut Random<'_>) -> f64 {
+    (random() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+impl Run<'_, '_, '_> {
+    /// Returns whether to go one level deeper, at rule depth `depth`.
+    fn deeper(&mut self, depth: usize) -> bool {
+        depth < self.generator.max_depth
+            && uniform(self.random) >= 1.0 - 0.5 * self.generator.damping.powi(depth as i32)
+    }
+
+    fn expr(&mut self, expr: &Expr, depth: usize) {
+        let generator = self.generator;
+        match expr {
+            Expr::Token(kind) => self.token(kind),
+            Expr::Rule(name) => {
+                let rule = generator.grammar.get(name).expect("rules are checked");
+                self.expr(&rule.expr, depth + 1);
+            }
+            Expr::Seq(exprs) => {
+                for expr in exprs {
+                    self.expr(expr, depth);
+                }
+            }
+            Expr::Choice(alternatives) => {
+                let heights: Vec<usize> = alternatives
+                    .iter()
+                    .map(|alternative| generator.height(alternative))
+                    .collect();
+                let lowest = heights.iter().copied().min().unwrap_or(INFINITE);
+                if lowest == INFINITE {
+                    return;
+                }
+                let weights: Vec<f64> = heights
+                    .iter()
+                    .map(|&height| match height {
+                        INFINITE => 0.0,
+                        _ if height == lowest => 1.0,
+                        _ if depth >= generator.max_depth => 0.0,
+                        _ => generator.damping.powi(depth as i32),
+                    })
+                    .collect();
+                // The lowest alternatives come first, to be taken for 0.
+                let mut order: Vec<usize> = (0..alternatives.len()).collect();
+                order.sort_by_key(|&i| heights[i] != lowest);
+                let mut chosen = uniform(self.random) * weights.iter().sum::<f64>();
+                let mut index = order[0];
+                for i in order {
+                    if weights[i] > 0.0 && chosen < weights[i] {
+                        index = i;
+                        break;
+                    }
+                    chosen -= weights[i];
+                }
+                self.expr(&alternatives[index], depth);
+            }
+            Expr::Optional(inner) => {
+                if generator.height(inner) != INFINITE && self.deeper(depth) {
+                    self.expr(inner, depth);
+                }
+            }
+            Expr::Repeat(inner) | Expr::Repeat1(inner) => {
+                if matches!(expr, Expr::Repeat1(_)) {
+                    self.expr(inner, depth);
+                } else if generator.height(inner) == INFINITE {
+                    return;
+                }
+                let mut count = 0;
+                while count < generator.max_repeat && self.deeper(depth) {
+                    self.expr(inner, depth);
+                    count += 1;
+                }
+            }
+        }
+    }
+
+    fn token(&mut self, kind: &str) {
+        let text = match &self.payloads[kind] {
+            Payload::Factory(factory) => factory((self.random)()),
+            Payload::Text(text) => text.clone(),
+            Payload::Pattern(node) => {
+                let mut text = String::new();
+                pattern(node, self.generator.max_repeat, self.random, &mut text);
+                text
+            }
+        };
+        if !self.sentence.tokens.is_empty() {
+            self.sentence.text.push_str(&self.generator.separator);
+        }
+        let start = self.sentence.text.len();
+        self.sentence.text.push_str(&text);
+        self.sentence.tokens.push(WithSpan::new(
+            kind.to_string(),
+            Span::new_unchecked(start, self.sentence.text.len()),
+        ));
+    }
+}
+
+/// Writes random text matching `node`.
+fn pattern(node: &Node, max_repeat: usize, random: &mut Random<'_>, out: &mut String) {
+    match node {
+        Node::Class(class) => {
+            if let Some(c) = char_in(class, random) {
+                out.push(c);
+            }
+        }
+        Node::Seq(nodes) => {
+            for node in nodes {
+                pattern(node, max_repeat, random, out);
+            }
+        }
+        Node::Choice(alternatives) => {
+            let index = below(random, alternatives.len());
+            pattern(&alternatives[index], max_repeat, random, out);
+        }
+        Node::Optional(inner) => {
+            if below(random, 2) == 1 {
+                pattern(inner, max_repeat, random, out);
+            }
+        }
+        Node::Repeat(inner) | Node::Repeat1(inner) => {
+            let least = usize::from(matches!(node, Node::Repeat1(_)));
+            let count = least + below(random, max_repeat + 1);
+            for _ in 0..count {
+                pattern(inner, max_repeat, random, out);
+            }
+        }
+    }
+}
+
+/// Returns a random character of `class`, preferring printable ASCII for
+/// negated classes, or `None` if it has no characters.
+fn char_in(class: &Class, random: &mut Random<'_>) -> Option<char> {
+    if class.negated {
+        let printable: Vec<char> = (' '..='~').filter(|&c| class.matches(c)).collect();
+        if printable.is_empty() {
+            return ('\u{a1}'..=char::MAX).find(|&c| class.matches(c));
+        }
+        return Some(printable[below(random, printable.len())]);
+    }
+    if class.ranges.is_empty() {
+        return None;
+    }
+    let (low, high) = class.ranges[below(random, class.ranges.len())];
+    let offset = below(random, high as usize - low as usize + 1);
+    Some(char::from_u32(low as u32 + offset as u32).unwrap_or(low))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::{parse_ebnf, Lexer};
+
+    const SOURCE: &str = r#"
+        list = "[" (item ("," item)*)? "]" ;
+        item = Number | Id | list ;
+        token Number = /-?\d+/ ;
+        token Id = /[a-z_]\w*/ ;
+        skip Space = /\s+/ ;
+    "#;
+
+    #[test]
+    fn test_generated_sentences_lex_back() {
+        let grammar = parse_ebnf(SOURCE).value;
+        let lexer = Lexer::new(&grammar).unwrap();
+        let mut generator = SentenceGenerator::new(&grammar).with_seed(42);
+        let mut lengths = Vec::new();
+        for _ in 0..50 {
+            let sentence = generator.generate().unwrap();
+            let output = lexer.tokenize(&sentence.text);
+            assert!(output.diagnostics.is_empty(), "{}", sentence.text);
+            let kinds: Vec<&str> = output.value.iter().map(|t| lexer.name(t.value)).collect();
+            let expected: Vec<&str> = sentence.tokens.iter().map(|t| t.value.as_str()).collect();
+            assert_eq!(kinds, expected, "{}", sentence.text);
+            lengths.push(sentence.tokens.len());
+        }
+        assert!(lengths.iter().any(|&len| len > 2));
+
+        let again: Vec<Sentence> = {
+            let mut generator = SentenceGenerator::new(&grammar).with_seed(42);
+            (0..3).map(|_| generator.generate().unwrap()).collect()
+        };
+        let mut generator = SentenceGenerator::new(&grammar).with_seed(42);
+        assert_eq!(generator.generate().unwrap(), again[0]);
+
+        let mut shallow = SentenceGenerator::new(&grammar)
+            .with_max_depth(0)
+            .with_separator("");
+        assert_eq!(shallow.generate().unwrap().text, "[]");
+        #[cfg(feature = "fuzzing")]
+        {
+            let mut u = arbitrary::Unstructured::new(&[]);
+            let generator = SentenceGenerator::new(&grammar).with_separator("");
+            assert_eq!(generator.generate_arbitrary(&mut u).unwrap().text, "[]");
+        }
+    }
+
+    #[test]
+    fn test_payloads_and_errors() {
+        let grammar = parse_ebnf("sum = Number \"+\" Number ;").value;
+        let mut generator = SentenceGenerator::new(&grammar)
+            .with_payload("Number", |random| (random % 10).to_string());
+        let sentence = generator.generate().unwrap();
+        assert_eq!(sentence.tokens[1].value, "\"+\"");
+        assert_eq!(&sentence.text[1..4], " + ");
+
+        let error = |source: &str| {
+            SentenceGenerator::new(&parse_ebnf(source).value)
+                .generate()
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(error(""), "the grammar has no rules");
+        assert_eq!(
+            error("a = Number ;"),
+            "no text can be generated for token `Number`"
+        );
+        assert_eq!(
+            error("a = X a ; token X = \"x\" ;"),
+            "rule `a` matches no finite sentence"
+        );
+        assert_eq!(error("a = b ;"), "rule `a` refers to undefined rule `b`");
+        assert_eq!(
+            error("a = X ; token X = /(x/ ;"),
+            "invalid pattern for token `X`: unclosed group at offset 0"
+        );
+    }
+}
@@ -0,0 +1,294 @@
+use super::{Expr, Grammar, PrecedenceLevel, Rule};
+use crate::position::Span;
+use std::fmt;
+
+/// An import of the rules of another grammar description, written
+/// `import "path" as namespace ;` in the EBNF notation.
+///
+/// With a namespace, the imported rules are named `namespace.rule`, and so
+/// are their references to other rules, including undefined ones, which
+/// the importing grammar is expected to define. Without one, they keep
+/// their names.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Import {
+    /// The path of the grammar, as given to the loader of
+    /// [`Grammar::resolve_imports`].
+    pub path: String,
+    pub namespace: Option<String>,
+    /// Where the import is, if the grammar was read from a source.
+    pub span: Option<Span>,
+}
+
+impl Import {
+    pub fn new(path: impl Into<String>) -> Self {
+        Import {
+            path: path.into(),
+            namespace: None,
+            span: None,
+        }
+    }
+
+    pub fn with_namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespace = Some(namespace.into());
+        self
+    }
+
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = Some(span);
+        self
+    }
+}
+
+impl fmt::Display for Import {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "import \"{}\"", self.path)?;
+        if let Some(namespace) = &self.namespace {
+            write!(f, " as {}", namespace)?;
+        }
+        write!(f, " ;")
+    }
+}
+
+/// An error resolving the imports of a grammar.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImportError {
+    /// The loader found no grammar at the path.
+    NotFound { path: String },
+    /// The grammar at the path imports itself, directly or through others.
+    Cycle { path: String },
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::NotFound { path } => write!(f, "cannot find grammar `{}`", path),
+            ImportError::Cycle { path } => write!(f, "grammar `{}` imports itself", path),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+impl Grammar {
+    /// Adds the rules, token definitions and precedence levels of `base`,
+    /// with its rules named `namespace.rule` if a namespace is given.
+    ///
+    /// What the grammar already defines overrides what `base` does: an
+    /// imported rule is only added if the grammar has no rule of the same
+    /// name, so an extension can replace a rule of the base, such as its
+    /// primary expressions, or fill in one the base leaves undefined. The
+    /// same goes for token definitions, and for the precedence of tokens,
+    /// the imported levels binding tighter than those of the grammar.
+    ///
+    /// # Examples
+    /// ```
+    /// use grammarsmith::grammar::*;
+    ///
+    /// let base = parse_ebnf(r#"expr = atom ("+" atom)* ; atom = Number ;"#).value;
+    /// let mut grammar = parse_ebnf(r#"
+    ///     stmt = "print" e.expr ;
+    ///     e.atom = Number | Id ;
+    /// "#).value;
+    /// grammar.import(&base, Some("e"));
+    ///
+    /// assert_eq!(grammar.to_string(), r#"stmt = "print" e.expr ;
+    /// e.atom = Number | Id ;
+    /// e.expr = e.atom ("+" e.atom)* ;
+    /// "#);
+    /// ```
+    pub fn import(&mut self, base: &Grammar, namespace: Option<&str>) {
+        let name = |name: &str| match namespace {
+            Some(namespace) => format!("{}.{}", namespace, name),
+            None => name.to_string(),
+        };
+        for rule in base.rules() {
+            if self.get(&name(&rule.name)).is_none() {
+                let expr = match namespace {
+                    Some(namespace) => qualify(&rule.expr, namespace),
+                    None => rule.expr.clone(),
+                };
+                let mut imported = Rule::new(name(&rule.name), expr);
+                imported.error = rule.error.clone();
+                self.add(imported);
+            }
+        }
+        for def in base.token_defs() {
+            if self.token_def(&def.name).is_none() {
+                self.add_token_def(def.clone());
+            }
+        }
+        for level in base.precedence() {
+            let tokens: Vec<&String> = level
+                .tokens
+                .iter()
+                .filter(|token| {
+                    !self
+                        .precedence()
+                        .iter()
+                        .any(|declared| declared.tokens.contains(token))
+                })
+                .collect();
+            if !tokens.is_empty() {
+                self.add_precedence(PrecedenceLevel::new(level.assoc, tokens));
+            }
+        }
+    }
+
+    /// Returns the grammar with its imports, and theirs, resolved with
+    /// [`Grammar::import`], where `load` returns the grammar at a path.
+    ///
+    /// The rules of the grammar come first, so its start rule is that of
+    /// the result, followed by the imported rules in the order of the
+    /// imports.
+    ///
+    /// # Examples
+    /// ```
+    /// use grammarsmith::grammar::*;
+    ///
+    /// let grammar = parse_ebnf(r#"
+    ///     import "expr.ebnf" as expr ;
+    ///     program = (expr.expr ";")* ;
+    /// "#).value;
+    ///
+    /// let resolved = grammar
+    ///     .resolve_imports(|path| match path {
+    ///         "expr.ebnf" => Some(parse_ebnf(r#"expr = Number ("+" Number)* ;"#).value),
+    ///         _ => None,
+    ///     })
+    ///     .unwrap();
+    /// assert_eq!(resolved.to_string(), r#"program = (expr.expr ";")* ;
+    /// expr.expr = Number ("+" Number)* ;
+    /// "#);
+    /// ```
+    pub fn resolve_imports(
+        &self,
+        mut load: impl FnMut(&str) -> Option<Grammar>,
+    ) -> Result<Grammar, ImportError> {
+        self.resolve_imports_from(&mut load, &mut Vec::new())
+    }
+
+    fn resolve_imports_from(
+        &self,
+        load: &mut dyn FnMut(&str) -> Option<Grammar>,
+        importing: &mut Vec<String>,
+    ) -> Result<Grammar, ImportError> {
+        let mut grammar = Grammar::new();
+        for rule in self.rules() {
+            grammar.add(rule.clone());
+        }
+        for def in self.token_defs() {
+            grammar.add_token_def(def.clone());
+        }
+        for level in self.precedence() {
+            grammar.add_precedence(level.clone());
+        }
+        for import in self.imports() {
+            if importing.contains(&import.path) {
+                return Err(ImportError::Cycle {
+                    path: import.path.clone(),
+                });
+            }
+            let base = load(&import.path).ok_or_else(|| ImportError::NotFound {
+                path: import.path.clone(),
+            })?;
+            importing.push(import.path.clone());
+            let base = base.resolve_imports_from(load, importing)?;
+            importing.pop();
+            grammar.import(&base, import.namespace.as_deref());
+        }
+        Ok(grammar)
+    }
+}
+
+/// Returns `expr` with the rules it refers to named `namespace.rule`.
+fn qualify(expr: &Expr, namespace: &str) -> Expr {
+    let qualify_all = |exprs: &[Expr]| -> Vec<Expr> {
+        exprs.iter().map(|expr| qualify(expr, namespace)).collect()
+    };
+    match expr {
+        Expr::Token(_) => expr.clone(),
+        Expr::Rule(name) => Expr::rule(format!("{}.{}", namespace, name)),
+        Expr::Seq(exprs) => Expr::seq(qualify_all(exprs)),
+        Expr::Choice(alternatives) => Expr::choice(qualify_all(alternatives)),
+        Expr::Optional(expr) => Expr::optional(qualify(expr, namespace)),
+        Expr::Repeat(expr) => Expr::repeat(qualify(expr, namespace)),
+        Expr::Repeat1(expr) => Expr::repeat1(qualify(expr, namespace)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::{parse_ebnf, Assoc};
+
+    #[test]
+    fn test_resolve_nested_imports() {
+        let sources = [
+            (
+                "expr",
+                r#"
+                import "lexical" ;
+                expr = expr "+" expr | atom ;
+                atom = Number | "(" expr ")" ;
+                %left "+" ;
+                "#,
+            ),
+            (
+                "lexical",
+                r#"token Number = /[0-9]+/ ; skip Space = / +/ ; %left "*" ;"#,
+            ),
+        ];
+        let load = |path: &str| {
+            let (_, source) = sources.iter().find(|(name, _)| *name == path)?;
+            Some(parse_ebnf(source).value)
+        };
+        let grammar = parse_ebnf(
+            r#"
+            import "expr" as e ;
+            stmt = "let" Id "=" e.expr ;
+            e.atom = Number | Id ;
+            token Number = /[0-9_]+/ ;
+            %left "+" ;
+            "#,
+        )
+        .value;
+        let resolved = grammar.resolve_imports(load).unwrap();
+        assert_eq!(
+            resolved.to_string(),
+            r#"stmt = "let" Id "=" e.expr ;
+e.atom = Number | Id ;
+e.expr = e.expr "+" e.expr | e.atom ;
+%left "+" ;
+%left "*" ;
+token Number = /[0-9_]+/ ;
+skip Space = / +/ ;
+"#
+        );
+        assert_eq!(resolved.precedence()[1].assoc, Assoc::Left);
+        assert!(resolved.resolve_precedence().is_ok());
+    }
+
+    #[test]
+    fn test_errors() {
+        let cyclic = |path: &str| {
+            let source = match path {
+                "a" => r#"import "b" ; a = X ;"#,
+                "b" => r#"import "a" as a ; b = Y ;"#,
+                _ => return None,
+            };
+            Some(parse_ebnf(source).value)
+        };
+        let grammar = Grammar::new().with_import("a", None);
+        assert_eq!(
+            grammar.resolve_imports(cyclic).unwrap_err().to_string(),
+            "grammar `a` imports itself"
+        );
+        let grammar = Grammar::new().with_import("c", Some("c"));
+        assert_eq!(
+            grammar.resolve_imports(cyclic),
+            Err(ImportError::NotFound {
+                path: "c".to_string()
+            })
+        );
+    }
+}
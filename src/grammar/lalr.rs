@@ -0,0 +1,721 @@
+use super::table::{Bnf, Production, Symbol};
+use super::{Assoc, Grammar, Lookahead};
+use crate::diagnostics::{expected_list, Diagnostic};
+use crate::parser::ParseOutput;
+use crate::position::{Span, WithSpan};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+
+/// What an [`LalrTable`] does in a state on a token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum LrAction {
+    /// Consume the token and go to the state.
+    Shift(usize),
+    /// Replace the symbols of the production with its rule.
+    Reduce(usize),
+    /// The input is complete.
+    Accept,
+}
+
+/// The kind of an [`LrConflict`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum LrConflictKind {
+    /// The parser can both consume the token and reduce a production.
+    ShiftReduce,
+    /// The parser can reduce more than one production.
+    ReduceReduce,
+}
+
+impl fmt::Display for LrConflictKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LrConflictKind::ShiftReduce => write!(f, "shift/reduce"),
+            LrConflictKind::ReduceReduce => write!(f, "reduce/reduce"),
+        }
+    }
+}
+
+/// A state and token where an LALR(1) parser has more than one thing to do,
+/// which precedence declarations do not settle.
+///
+/// As yacc does, the table shifts on shift/reduce conflicts, and reduces
+/// the production written first on reduce/reduce conflicts.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LrConflict {
+    pub kind: LrConflictKind,
+    pub state: usize,
+    pub token: Lookahead,
+    /// The rule of the grammar the first production to reduce comes from.
+    pub rule: String,
+    /// The productions that can be reduced, as `rule = symbols`, the one
+    /// the table reduces first.
+    pub reductions: Vec<String>,
+}
+
+impl LrConflict {
+    /// Converts the conflict into an error pointing at the rule, if
+    /// `grammar` was read from a source.
+    pub fn to_diagnostic(&self, grammar: &Grammar) -> Diagnostic {
+        let token = match &self.token {
+            Lookahead::Token(_) => format!("`{}`", self.token),
+            Lookahead::End => self.token.to_string(),
+        };
+        let mut diagnostic = Diagnostic::error(format!(
+            "{} conflict in rule `{}` on {}",
+            self.kind, self.rule, token
+        ));
+        if let Some(span) = grammar.get(&self.rule).and_then(|rule| rule.span) {
+            diagnostic = diagnostic.with_label(span, "");
+        }
+        for reduction in &self.reductions {
+            diagnostic = diagnostic.with_note(format!("the parser can reduce `{}`", reduction));
+        }
+        match self.kind {
+            LrConflictKind::ShiftReduce => diagnostic
+                .with_note(format!("or shift {}, which it does", token))
+                .with_note(format!(
+                    "declaring the precedence of {} and of the production would settle it",
+                    token
+                )),
+            LrConflictKind::ReduceReduce => {
+                diagnostic.with_note("the parser reduces the production written first")
+            }
+        }
+    }
+}
+
+/// An LALR(1) parse table, as yacc and bison build, over the rules of
+/// [`Grammar::to_bnf`].
+///
+/// It takes grammars an [`LlTable`](super::LlTable) cannot, such as
+/// left-recursive ones, and settles the shift/reduce conflicts of operator
+/// rules with the precedence declarations of the grammar: the precedence of
+/// a production is that of its last token with one, and a conflict between
+/// reducing it and shifting a token goes to the one that binds tighter, or
+/// by the associativity of the level if they are at the same one. The
+/// conflicts left are reported in [`conflicts`](LalrTable::conflicts).
+///
+/// # Examples
+/// ```
+/// use grammarsmith::grammar::*;
+/// use grammarsmith::position::{Span, WithSpan};
+///
+/// let grammar = parse_ebnf(r#"
+///     expr = expr "+" expr | expr "*" expr | Number ;
+///     %left "+" ;
+///     %left "*" ;
+/// "#).value;
+/// let table = grammar.lalr_table();
+/// assert!(table.conflicts().is_empty());
+///
+/// // 1+2*3
+/// let kinds = ["Number", "\"+\"", "Number", "\"*\"", "Number"];
+/// let tokens = kinds
+///     .iter()
+///     .enumerate()
+///     .map(|(i, kind)| WithSpan::new(table.token(kind).unwrap(), Span::new_unchecked(i, i + 1)));
+/// let text = table.parse_with(
+///     tokens,
+///     Span::point(5),
+///     |&kind| kind,
+///     |token| ["1", "+", "2", "*", "3"][token.span.start()].to_string(),
+///     |_, children, _| format!("({})", children.concat()),
+/// );
+/// assert_eq!(text.value.unwrap(), "((1)+((2)*(3)))");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LalrTable {
+    rules: Vec<String>,
+    tokens: Vec<String>,
+    productions: Vec<Production>,
+    /// For each state, then each token and the end of the input, what to do.
+    action: Vec<Option<LrAction>>,
+    /// For each state, then each rule, the state to go to after reducing it.
+    goto: Vec<Option<usize>>,
+    conflicts: Vec<LrConflict>,
+}
+
+/// An item of a state: a production, or the start rule for
+/// `productions.len()`, with the number of its symbols matched.
+type Item = (usize, usize);
+
+impl LalrTable {
+    pub fn new(grammar: &Grammar) -> Self {
+        Builder::new(grammar).build()
+    }
+
+    /// Returns the names of the rules, the start rule first.
+    pub fn rules(&self) -> &[String] {
+        &self.rules
+    }
+
+    /// Returns the names of the token kinds.
+    pub fn tokens(&self) -> &[String] {
+        &self.tokens
+    }
+
+    /// Returns the productions, grouped by rule.
+    pub fn productions(&self) -> &[Production] {
+        &self.productions
+    }
+
+    /// Returns the index of the token kind with the given name.
+    pub fn token(&self, name: &str) -> Option<usize> {
+        self.tokens.iter().position(|token| token == name)
+    }
+
+    /// Returns the number of states.
+    pub fn states(&self) -> usize {
+        self.action.len() / (self.tokens.len() + 1)
+    }
+
+    /// Returns what to do in `state` when the next token is `lookahead`,
+    /// or the end of the input for `None`.
+    pub fn action(&self, state: usize, lookahead: Option<usize>) -> Option<LrAction> {
+        let column = lookahead.unwrap_or(self.tokens.len());
+        if column > self.tokens.len() {
+            return None;
+        }
+        *self.action.get(state * (self.tokens.len() + 1) + column)?
+    }
+
+    /// Returns the state to go to from `state` after reducing `rule`.
+    pub fn goto(&self, state: usize, rule: usize) -> Option<usize> {
+        *self.goto.get(state * self.rules.len() + rule)?
+    }
+
+    /// Returns the conflicts the precedence declarations do not settle.
+    pub fn conflicts(&self) -> &[LrConflict] {
+        &self.conflicts
+    }
+
+    /// Parses token kinds, by index, from the start rule, where `end` is the
+    /// span of the end of the input.
+    ///
+    /// # Returns
+    /// The indices of the productions in the order they are reduced, which
+    /// is the rightmost derivation in reverse. Parsing stops at the first
+    /// token that cannot come next, with an error, and the reductions up to
+    /// there.
+    pub fn parse(&self, tokens: &[WithSpan<usize>], end: Span) -> ParseOutput<Vec<usize>> {
+        let mut reductions = Vec::new();
+        let output = self.parse_with(
+            tokens.iter().cloned(),
+            end,
+            |&kind| kind,
+            |_| (),
+            |production, _, _| reductions.push(production),
+        );
+        ParseOutput::new(reductions, output.diagnostics)
+    }
+
+    /// Parses a stream of tokens from the start rule, where `end` is the
+    /// span of the end of the input, building a value for each token and
+    /// each reduced production, like the actions of yacc.
+    ///
+    /// `kind` gives the token kind of a token, by index, `shift` the value
+    /// of a token, and `reduce` that of a production from the values of its
+    /// symbols and its span. An empty production is placed at the start of
+    /// the next token.
+    ///
+    /// # Returns
+    /// The value of the start rule, or `None` with an error at the first
+    /// token that cannot come next.
+    pub fn parse_with<T, V>(
+        &self,
+        tokens: impl IntoIterator<Item = WithSpan<T>>,
+        end: Span,
+        mut kind: impl FnMut(&T) -> usize,
+        mut shift: impl FnMut(WithSpan<T>) -> V,
+        mut reduce: impl FnMut(usize, Vec<V>, Span) -> V,
+    ) -> ParseOutput<Option<V>> {
+        let mut tokens = tokens.into_iter().peekable();
+        let mut states = vec![0];
+        let mut values: Vec<(V, Span)> = Vec::new();
+        loop {
+            let state = *states.last().unwrap();
+            let next = tokens.peek().map(|token| (kind(&token.value), token.span));
+            let lookahead = next.map(|(kind, _)| kind);
+            match self.action(state, lookahead) {
+                Some(LrAction::Shift(target)) => {
+                    let token = tokens.next().unwrap();
+                    let span = token.span;
+                    values.push((shift(token), span));
+                    states.push(target);
+                }
+                Some(LrAction::Reduce(index)) => {
+                    let production = &self.productions[index];
+                    let count = production.symbols.len();
+                    states.truncate(states.len() - count);
+                    let children = values.split_off(values.len() - count);
+                    let span = match (children.first(), children.last()) {
+                        (Some((_, first)), Some((_, last))) => first.union(last),
+                        _ => Span::point(next.map_or(end, |(_, span)| span).start()),
+                    };
+                    let children = children.into_iter().map(|(value, _)| value).collect();
+                    values.push((reduce(index, children, span), span));
+                    let from = *states.last().unwrap();
+                    states.push(self.goto(from, production.rule).unwrap());
+                }
+                Some(LrAction::Accept) => {
+                    let value = values.pop().map(|(value, _)| value);
+                    return ParseOutput::new(value, Vec::new());
+                }
+                None => {
+                    let span = next.map_or(end, |(_, span)| span);
+                    return ParseOutput::new(None, vec![self.unexpected(state, lookahead, span)]);
+                }
+            }
+        }
+    }
+
+    /// Returns the error for `lookahead`, at `span`, which cannot come next
+    /// in `state`.
+    pub(super) fn unexpected(
+        &self,
+        state: usize,
+        lookahead: Option<usize>,
+        span: Span,
+    ) -> Diagnostic {
+        let name = |token: Option<usize>| match token {
+            Some(token) => self
+                .tokens
+                .get(token)
+                .map_or("an unknown token", String::as_str),
+            None => "end of input",
+        };
+        let expected = (0..self.tokens.len())
+            .map(Some)
+            .chain([None])
+            .filter(|&column| self.action(state, column).is_some());
+        Diagnostic::error(format!(
+            "expected {}, found {}",
+            expected_list(expected.map(name)),
+            name(lookahead)
+        ))
+        .with_label(span, "")
+    }
+}
+
+impl Grammar {
+    /// Returns the [`LalrTable`] of the grammar.
+    pub fn lalr_table(&self) -> LalrTable {
+        LalrTable::new(self)
+    }
+
+    /// Reports the LALR(1) conflicts of the grammar that its precedence
+    /// declarations do not settle as errors.
+    pub fn check_lalr(&self) -> Vec<Diagnostic> {
+        self.lalr_table()
+            .conflicts()
+            .iter()
+            .map(|conflict| conflict.to_diagnostic(self))
+            .collect()
+    }
+}
+
+/// Builds an [`LalrTable`]: the LR(0) states, then the lookaheads of their
+/// items, propagated until they settle.
+struct Builder<'a> {
+    grammar: &'a Grammar,
+    bnf: Bnf,
+    /// The indices of the productions of each rule.
+    by_rule: Vec<Vec<usize>>,
+    nullable: Vec<bool>,
+    first: Vec<BTreeSet<usize>>,
+    /// The items of each state that are not added by closure.
+    kernels: Vec<Vec<Item>>,
+    /// The state each state goes to on each symbol.
+    transitions: Vec<BTreeMap<Symbol, usize>>,
+}
+
+impl<'a> Builder<'a> {
+    fn new(grammar: &'a Grammar) -> Self {
+        let bnf = Bnf::new(grammar);
+        let mut by_rule = vec![Vec::new(); bnf.rules.len()];
+        for (index, production) in bnf.productions.iter().enumerate() {
+            by_rule[production.rule].push(index);
+        }
+        let mut builder = Builder {
+            grammar,
+            nullable: vec![false; bnf.rules.len()],
+            first: vec![BTreeSet::new(); bnf.rules.len()],
+            bnf,
+            by_rule,
+            kernels: Vec::new(),
+            transitions: Vec::new(),
+        };
+        builder.first_sets();
+        builder
+    }
+
+    fn first_sets(&mut self) {
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for production in &self.bnf.productions {
+                let (first, nullable) = self.first_of(&production.symbols);
+                let rule = production.rule;
+                if nullable && !self.nullable[rule] {
+                    self.nullable[rule] = true;
+                    changed = true;
+                }
+                let before = self.first[rule].len();
+                self.first[rule].extend(first);
+                changed |= self.first[rule].len() != before;
+            }
+        }
+    }
+
+    /// Returns the tokens `symbols` can start with, and whether they can
+    /// match nothing.
+    fn first_of(&self, symbols: &[Symbol]) -> (BTreeSet<usize>, bool) {
+        let mut first = BTreeSet::new();
+        for symbol in symbols {
+            match *symbol {
+                Symbol::Token(token) => {
+                    first.insert(token);
+                    return (first, false);
+                }
+                Symbol::Rule(rule) => {
+                    first.extend(&self.first[rule]);
+                    if !self.nullable[rule] {
+                        return (first, false);
+                    }
+                }
+            }
+        }
+        (first, true)
+    }
+
+    /// Returns the symbols of the production of an item.
+    fn symbols(&self, production: usize) -> &[Symbol] {
+        match self.bnf.productions.get(production) {
+            Some(production) => &production.symbols,
+            None => std::slice::from_ref(&Symbol::Rule(0)),
+        }
+    }
+
+    /// Returns the items of a state with their lookaheads, from those of its
+    /// kernel, where the end of the input is `tokens.len()`.
+    fn closure(&self, kernel: BTreeMap<Item, BTreeSet<usize>>) -> BTreeMap<Item, BTreeSet<usize>> {
+        let mut items = kernel;
+        let mut pending: Vec<Item> = items.keys().copied().collect();
+        while let Some((production, dot)) = pending.pop() {
+            let symbols = self.symbols(production);
+            let Some(&Symbol::Rule(rule)) = symbols.get(dot) else {
+                continue;
+            };
+            let (mut lookahead, nullable) = self.first_of(&symbols[dot + 1..]);
+            if nullable {
+                lookahead.extend(&items[&(production, dot)]);
+            }
+            for &next in self.by_rule.get(rule).into_iter().flatten() {
+                let is_new = !items.contains_key(&(next, 0));
+                let entry = items.entry((next, 0)).or_default();
+                let before = entry.len();
+                entry.extend(&lookahead);
+                if is_new || entry.len() != before {
+                    pending.push((next, 0));
+                }
+            }
+        }
+        items
+    }
+
+    /// Builds the LR(0) states, reachable from the start item.
+    fn states(&mut self) {
+        let start = vec![(self.bnf.productions.len(), 0)];
+        let mut index: HashMap<Vec<Item>, usize> = HashMap::from([(start.clone(), 0)]);
+        self.kernels.push(start);
+        let mut state = 0;
+        while state < self.kernels.len() {
+            let kernel = self.kernels[state]
+                .iter()
+                .map(|&item| (item, BTreeSet::new()));
+            let items = self.closure(kernel.collect());
+            let mut targets: BTreeMap<Symbol, Vec<Item>> = BTreeMap::new();
+            for &(production, dot) in items.keys() {
+                if let Some(&symbol) = self.symbols(production).get(dot) {
+                    targets
+                        .entry(symbol)
+                        .or_default()
+                        .push((production, dot + 1));
+                }
+            }
+            let mut transitions = BTreeMap::new();
+            for (symbol, mut kernel) in targets {
+                kernel.sort_unstable();
+                let next = self.kernels.len();
+                let target = *index.entry(kernel.clone()).or_insert(next);
+                if target == next {
+                    self.kernels.push(kernel);
+                }
+                transitions.insert(symbol, target);
+            }
+            self.transitions.push(transitions);
+            state += 1;
+        }
+    }
+
+    /// Returns the lookaheads of the kernel items of each state.
+    fn lookaheads(&self) -> Vec<BTreeMap<Item, BTreeSet<usize>>> {
+        let end = self.bnf.tokens.len();
+        let mut lookaheads: Vec<BTreeMap<Item, BTreeSet<usize>>> = self
+            .kernels
+            .iter()
+            .map(|kernel| kernel.iter().map(|&item| (item, BTreeSet::new())).collect())
+            .collect();
+        lookaheads[0].values_mut().for_each(|set| {
+            set.insert(end);
+        });
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for state in 0..self.kernels.len() {
+                for ((production, dot), lookahead) in self.closure(lookaheads[state].clone()) {
+                    let Some(symbol) = self.symbols(production).get(dot) else {
+                        continue;
+                    };
+                    let target = self.transitions[state][symbol];
+                    let entry = lookaheads[target].get_mut(&(production, dot + 1)).unwrap();
+                    let before = entry.len();
+                    entry.extend(lookahead);
+                    changed |= entry.len() != before;
+                }
+            }
+        }
+        lookaheads
+    }
+
+    /// Returns the precedence level of a token, if it has one.
+    fn precedence(&self, token: usize) -> Option<(usize, Assoc)> {
+        let name = &self.bnf.tokens[token];
+        let levels = self.grammar.precedence().iter().enumerate();
+        levels
+            .filter(|(_, level)| level.tokens.contains(name))
+            .map(|(index, level)| (index, level.assoc))
+            .next()
+    }
+
+    /// Returns the precedence of a production, that of its last token with
+    /// one.
+    fn production_precedence(&self, production: usize) -> Option<(usize, Assoc)> {
+        self.symbols(production)
+            .iter()
+            .rev()
+            .find_map(|symbol| match *symbol {
+                Symbol::Token(token) => self.precedence(token),
+                Symbol::Rule(_) => None,
+            })
+    }
+
+    fn production_text(&self, production: usize) -> String {
+        let production = &self.bnf.productions[production];
+        let mut text = format!("{} =", self.bnf.rules[production.rule]);
+        for symbol in &production.symbols {
+            let name = match *symbol {
+                Symbol::Token(token) => &self.bnf.tokens[token],
+                Symbol::Rule(rule) => &self.bnf.rules[rule],
+            };
+            text.push(' ');
+            text.push_str(name);
+        }
+        text
+    }
+
+    fn build(mut self) -> LalrTable {
+        self.states();
+        let lookaheads = self.lookaheads();
+        let width = self.bnf.tokens.len() + 1;
+        let mut action = vec![None; self.kernels.len() * width];
+        let mut goto = vec![None; self.kernels.len() * self.bnf.rules.len()];
+        let mut conflicts = Vec::new();
+        for (state, kernel) in lookaheads.into_iter().enumerate() {
+            // The productions to reduce on each token.
+            let mut reductions: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+            for ((production, dot), lookahead) in self.closure(kernel) {
+                if dot < self.symbols(production).len() {
+                    continue;
+                }
+                for token in lookahead {
+                    if production == self.bnf.productions.len() {
+                        action[state * width + token] = Some(LrAction::Accept);
+                    } else {
+                        reductions.entry(token).or_default().push(production);
+                    }
+                }
+            }
+            for (&symbol, &target) in &self.transitions[state] {
+                match symbol {
+                    Symbol::Token(token) => {
+                        action[state * width + token] = Some(LrAction::Shift(target))
+                    }
+                    // The start item refers to the start rule even if the
+                    // grammar has none.
+                    Symbol::Rule(rule) => {
+                        if let Some(cell) = goto.get_mut(state * self.bnf.rules.len() + rule) {
+                            *cell = Some(target);
+                        }
+                    }
+                }
+            }
+            for (token, mut productions) in reductions {
+                productions.sort_unstable();
+                let lookahead = match self.bnf.tokens.get(token) {
+                    Some(name) => Lookahead::Token(name.clone()),
+                    None => Lookahead::End,
+                };
+                let conflict = |kind| LrConflict {
+                    kind,
+                    state,
+                    token: lookahead.clone(),
+                    rule: self.bnf.rules
+                        [self.bnf.origins[self.bnf.productions[productions[0]].rule]]
+                        .clone(),
+                    reductions: productions
+                        .iter()
+                        .map(|&p| self.production_text(p))
+                        .collect(),
+                };
+                if productions.len() > 1 {
+                    conflicts.push(conflict(LrConflictKind::ReduceReduce));
+                }
+                let cell = &mut action[state * width + token];
+                let reduce = Some(LrAction::Reduce(productions[0]));
+                if cell.is_none() {
+                    *cell = reduce;
+                    continue;
+                }
+                let settled = self
+                    .precedence(token)
+                    .zip(self.production_precedence(productions[0]));
+                match settled {
+                    Some(((shift, _), (level, _))) if shift > level => {}
+                    Some(((shift, _), (level, _))) if shift < level => *cell = reduce,
+                    Some(((_, Assoc::Left), _)) => *cell = reduce,
+                    Some(((_, Assoc::Right), _)) => {}
+                    Some(((_, Assoc::NonAssoc), _)) => *cell = None,
+                    None => conflicts.push(conflict(LrConflictKind::ShiftReduce)),
+                }
+            }
+        }
+        LalrTable {
+            rules: self.bnf.rules,
+            tokens: self.bnf.tokens,
+            productions: self.bnf.productions,
+            action,
+            goto,
+            conflicts,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::parse_ebnf;
+
+    fn tokens(table: &LalrTable, kinds: &[&str]) -> Vec<WithSpan<usize>> {
+        kinds
+            .iter()
+            .enumerate()
+            .map(|(i, kind)| {
+                WithSpan::new(table.token(kind).unwrap(), Span::new_unchecked(i, i + 1))
+            })
+            .collect()
+    }
+
+    /// Returns the tree the table parses, written with parentheses.
+    fn shape(table: &LalrTable, kinds: &[&str]) -> Result<String, String> {
+        let output = table.parse_with(
+            tokens(table, kinds),
+            Span::point(kinds.len()),
+            |&kind| kind,
+            |token| table.tokens()[token.value].trim_matches('"').to_string(),
+            |_, children, _| format!("({})", children.join(" ")),
+        );
+        match output.value {
+            Some(value) => Ok(value),
+            None => Err(output.diagnostics[0].message.clone()),
+        }
+    }
+
+    #[test]
+    fn test_precedence_settles_conflicts() {
+        let grammar = parse_ebnf(
+            r#"
+            expr = expr "+" expr | expr "^" expr | expr "==" expr | "-" expr | N ;
+            %nonassoc "==" ;
+            %left "+" ;
+            %right "^" ;
+            %left "-" ;
+            "#,
+        )
+        .value;
+        let table = grammar.lalr_table();
+        assert_eq!(table.conflicts(), []);
+        let n = "N";
+        let (plus, pow, eq, minus) = ("\"+\"", "\"^\"", "\"==\"", "\"-\"");
+        assert_eq!(
+            shape(&table, &[n, plus, n, plus, n]).unwrap(),
+            "(((N) + (N)) + (N))"
+        );
+        assert_eq!(
+            shape(&table, &[n, pow, n, pow, n]).unwrap(),
+            "((N) ^ ((N) ^ (N)))"
+        );
+        assert_eq!(
+            shape(&table, &[minus, n, plus, n]).unwrap(),
+            "((- (N)) + (N))"
+        );
+        assert_eq!(
+            shape(&table, &[n, eq, n, eq, n]).unwrap_err(),
+            "expected \"+\", \"^\", or end of input, found \"==\""
+        );
+        assert_eq!(
+            shape(&table, &[n, plus]).unwrap_err(),
+            "expected \"-\" or N, found end of input"
+        );
+    }
+
+    #[test]
+    fn test_conflicts() {
+        let source = r#"
+            stmt = "if" E stmt | "if" E stmt "else" stmt | X ;
+            "#;
+        let grammar = parse_ebnf(source).value;
+        let table = grammar.lalr_table();
+        assert_eq!(table.conflicts().len(), 1);
+        let diagnostic = table.conflicts()[0].to_diagnostic(&grammar);
+        assert_eq!(
+            diagnostic.message,
+            "shift/reduce conflict in rule `stmt` on `\"else\"`"
+        );
+        assert_eq!(
+            diagnostic.notes[0],
+            "the parser can reduce `stmt = \"if\" E stmt`"
+        );
+        // The conflict is settled by shifting, so `else` goes with the
+        // nearest `if`.
+        let kinds = ["\"if\"", "E", "\"if\"", "E", "X", "\"else\"", "X"];
+        assert_eq!(shape(&table, &kinds).unwrap(), "(if E (if E (X) else (X)))");
+
+        // LALR(1), though not SLR(1).
+        let grammar = parse_ebnf(r#"s = l "=" r | r ; l = "*" r | Id ; r = l ;"#).value;
+        assert!(grammar.check_lalr().is_empty());
+
+        let grammar = parse_ebnf(r#"s = a | b ; a = X ; b = X ;"#).value;
+        let conflicts = grammar.lalr_table().conflicts().to_vec();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].kind, LrConflictKind::ReduceReduce);
+        assert_eq!(conflicts[0].reductions, ["a = X", "b = X"]);
+        assert_eq!(grammar.check_lalr().len(), 1);
+    }
+}
@@ -0,0 +1,673 @@
+use super::{Grammar, Terminal, TokenDef};
+use crate::diagnostics::Diagnostic;
+use crate::parser::ParseOutput;
+use crate::position::{Span, WithSpan};
+use std::fmt;
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+/// An error building a [`Lexer`] from the token definitions of a grammar.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LexerError {
+    /// The pattern of a token definition is not a regular expression of the
+    /// supported subset. `offset` is the byte offset of the error in the
+    /// pattern.
+    InvalidPattern {
+        token: String,
+        offset: usize,
+        message: String,
+    },
+    /// A token definition matches the empty text, so lexing would never
+    /// advance.
+    MatchesEmpty { token: String },
+    /// A rule uses a token kind that has no definition, and is not a literal.
+    UndefinedToken { token: String },
+}
+
+impl fmt::Display for LexerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LexerError::InvalidPattern {
+                token,
+                offset,
+                message,
+            } => write!(
+                f,
+                "invalid pattern for token `{}` at offset {}: {}",
+                token, offset, message
+            ),
+            LexerError::MatchesEmpty { token } => {
+                write!(f, "token `{}` matches the empty text", token)
+            }
+            LexerError::UndefinedToken { token } => {
+                write!(f, "no definition is given for token `{}`", token)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LexerError {}
+
+/// The kind of a token from a [`Lexer`], named by [`Lexer::name`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TokenId(usize);
+
+impl TokenId {
+    /// Returns the index of the definition of the kind, in the order of
+    /// [`Grammar::token_defs`], followed by the literals used by the rules
+    /// without a definition of their own.
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+/// A lexer interpreting the [`TokenDef`]s of a [`Grammar`], so a simple
+/// language can define its tokens and its syntax in one place.
+///
+/// At each position the lexer takes the longest match of any definition.
+/// Ties go to the definition with the highest priority, then to literals
+/// over patterns, so keywords win over identifiers, then to the earliest
+/// definition. Matches of skip definitions are dropped, and text no
+/// definition matches is reported and skipped.
+///
+/// Literal token kinds used by the rules, such as `"+"`, need no
+/// definition: they are matched by a definition of the same literal text
+/// if there is one, and else by an implicit definition named by the
+/// literal. Other token kinds must be defined.
+///
+/// Patterns are regular expressions of a subset common to most engines:
+/// characters, escapes such as `\n`, `\d`, `\w`, `\s` or `\.`, the
+/// wildcard `.`, which matches anything but a newline, classes such as
+/// `[a-z_]` or `[^"\\]`, groups, alternatives `|`, and the quantifiers
+/// `?`, `*` and `+`.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::grammar::*;
+///
+/// let grammar = parse_ebnf(r#"
+///     sum = Number ("+" Number)* ;
+///     token Number = /[0-9]+/ ;
+///     skip Space = /[ \t\n]+/ ;
+/// "#).value;
+///
+/// let lexer = Lexer::new(&grammar).unwrap();
+/// let output = lexer.tokenize("1 + 23");
+/// assert!(output.diagnostics.is_empty());
+/// let names: Vec<&str> = output.value.iter().map(|token| lexer.name(token.value)).collect();
+/// assert_eq!(names, ["Number", "\"+\"", "Number"]);
+/// assert_eq!(output.value[2].span.start(), 4);
+/// assert_eq!(lexer.kind("Number"), Some(output.value[0].value));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Lexer {
+    defs: Vec<Compiled>,
+    /// The definitions of literal token kinds of the rules matched by a
+    /// definition with another name.
+    aliases: Vec<(String, usize)>,
+    program: Vec<Inst>,
+    starts: Vec<usize>,
+}
+
+#[derive(Debug, Clone)]
+struct Compiled {
+    name: String,
+    priority: i32,
+    literal: bool,
+    skip: bool,
+}
+
+impl Lexer {
+    pub fn new(grammar: &Grammar) -> Result<Self, LexerError> {
+        let mut defs: Vec<TokenDef> = grammar.token_defs().to_vec();
+        let mut aliases = Vec::new();
+        for token in grammar.tokens() {
+            if defs.iter().any(|def| def.name == token) {
+                continue;
+            }
+            let Some(text) = literal_text(token) else {
+                return Err(LexerError::UndefinedToken {
+                    token: token.to_string(),
+                });
+            };
+            let same = defs.iter().position(
+                |def| matches!(&def.terminal, Terminal::Literal(literal) if literal == text),
+            );
+            match same {
+                Some(index) => aliases.push((token.to_string(), index)),
+                None => defs.push(TokenDef::new(token, Terminal::literal(text))),
+            }
+        }
+
+        let mut lexer = Lexer {
+            defs: Vec::new(),
+            aliases,
+            program: Vec::new(),
+            starts: Vec::new(),
+        };
+        for (index, def) in defs.into_iter().enumerate() {
+            let node = match &def.terminal {
+                Terminal::Literal(text) => {
+                    Node::Seq(text.chars().map(Class::char).map(Node::Class).collect())
+                }
+                Terminal::Pattern(regex) => {
+                    parse_regex(regex).map_err(|(offset, message)| LexerError::InvalidPattern {
+                        token: def.name.clone(),
+                        offset,
+                        message,
+                    })?
+                }
+            };
+            let start = lexer.program.len();
+            compile(&node, &mut lexer.program);
+            lexer.program.push(Inst::Match(index));
+            if lexer
+                .closure(vec![start])
+                .iter()
+                .any(|&pc| matches!(lexer.program[pc], Inst::Match(_)))
+            {
+                return Err(LexerError::MatchesEmpty { token: def.name });
+            }
+            lexer.starts.push(start);
+            lexer.defs.push(Compiled {
+                literal: matches!(def.terminal, Terminal::Literal(_)),
+                name: def.name,
+                priority: def.priority,
+                skip: def.skip,
+            });
+        }
+        Ok(lexer)
+    }
+
+    /// Returns the kind of the token kind `name`, either defined or a
+    /// literal used by the rules.
+    pub fn kind(&self, name: &str) -> Option<TokenId> {
+        match self.defs.iter().position(|def| def.name == name) {
+            Some(index) => Some(TokenId(index)),
+            None => self
+                .aliases
+                .iter()
+                .find(|(alias, _)| alias == name)
+                .map(|&(_, index)| TokenId(index)),
+        }
+    }
+
+    /// Returns the name of the definition of `kind`.
+    ///
+    /// # Panics
+    /// If `kind` is not from this lexer.
+    pub fn name(&self, kind: TokenId) -> &str {
+        &self.defs[kind.0].name
+    }
+
+    /// Splits `source` into tokens.
+    pub fn tokenize(&self, source: &str) -> ParseOutput<Vec<WithSpan<TokenId>>> {
+        let mut tokens = Vec::new();
+        let mut diagnostics = Vec::new();
+        let mut unexpected: Option<usize> = None;
+        let mut pos = 0;
+        while let Some(c) = source[pos..].chars().next() {
+            let Some((len, index)) = self.longest_match(&source[pos..]) else {
+                unexpected.get_or_insert(pos);
+                pos += c.len_utf8();
+                continue;
+            };
+            if let Some(start) = unexpected.take() {
+                diagnostics.push(unexpected_text(source, start, pos));
+            }
+            if !self.defs[index].skip {
+                tokens.push(WithSpan::new(
+                    TokenId(index),
+                    Span::new_unchecked(pos, pos + len),
+                ));
+            }
+            pos += len;
+        }
+        if let Some(start) = unexpected {
+            diagnostics.push(unexpected_text(source, start, pos));
+        }
+        ParseOutput::new(tokens, diagnostics)
+    }
+
+    /// Returns the length and definition of the best match at the start of
+    /// `text`.
+    fn longest_match(&self, text: &str) -> Option<(usize, usize)> {
+        let mut current = self.closure(self.starts.clone());
+        let mut best = None;
+        for (i, c) in text.char_indices() {
+            let next: Vec<usize> = current
+                .iter()
+                .filter(|&&pc| matches!(&self.program[pc], Inst::Char(class) if class.matches(c)))
+                .map(|pc| pc + 1)
+                .collect();
+            if next.is_empty() {
+                break;
+            }
+            current = self.closure(next);
+            let matched = current
+                .iter()
+                .filter_map(|&pc| match self.program[pc] {
+                    Inst::Match(index) => Some(index),
+                    _ => None,
+                })
+                .max_by_key(|&index| {
+                    let def = &self.defs[index];
+                    (def.priority, def.literal, std::cmp::Reverse(index))
+                });
+            if let Some(index) = matched {
+                best = Some((i + c.len_utf8(), index));
+            }
+        }
+        best
+    }
+
+    /// Returns the length of the longest match of the definition of `kind`
+    /// at the start of `text`.
+    pub(super) fn match_kind(&self, kind: TokenId, text: &str) -> Option<usize> {
+        let mut current = self.closure(vec![self.starts[kind.0]]);
+        let mut best = None;
+        for (i, c) in text.char_indices() {
+            let next: Vec<usize> = current
+                .iter()
+                .filter(|&&pc| matches!(&self.program[pc], Inst::Char(class) if class.matches(c)))
+                .map(|pc| pc + 1)
+                .collect();
+            if next.is_empty() {
+                break;
+            }
+            current = self.closure(next);
+            if current
+                .iter()
+                .any(|&pc| matches!(self.program[pc], Inst::Match(_)))
+            {
+                best = Some(i + c.len_utf8());
+            }
+        }
+        best
+    }
+
+    /// Returns the instructions reachable from `pcs` without consuming a
+    /// character.
+    fn closure(&self, mut pcs: Vec<usize>) -> Vec<usize> {
+        let mut seen = vec![false; self.program.len()];
+        let mut closure = Vec::new();
+        while let Some(pc) = pcs.pop() {
+            if std::mem::replace(&mut seen[pc], true) {
+                continue;
+            }
+            match self.program[pc] {
+                Inst::Split(first, second) => pcs.extend([second, first]),
+                Inst::Jump(target) => pcs.push(target),
+                Inst::Char(_) | Inst::Match(_) => closure.push(pc),
+            }
+        }
+        closure
+    }
+}
+
+fn unexpected_text(source: &str, start: usize, end: usize) -> Diagnostic {
+    let text = &source[start..end];
+    let message = if text.chars().count() == 1 {
+        format!("unexpected character `{}`", text)
+    } else {
+        format!("unexpected characters `{}`", text)
+    };
+    Diagnostic::error(message).with_label(Span::new_unchecked(start, end), "")
+}
+
+/// Returns the text of a literal token kind, such as `+` for `"+"`.
+fn literal_text(kind: &str) -> Option<&str> {
+    ['"', '\''].into_iter().find_map(|quote| {
+        kind.strip_prefix(quote)?
+            .strip_suffix(quote)
+            .filter(|text| !text.is_empty())
+    })
+}
+
+/// A set of characters.
+#[derive(Debug, Clone)]
+pub(super) struct Class {
+    pub(super) ranges: Vec<(char, char)>,
+    pub(super) negated: bool,
+}
+
+impl Class {
+    fn char(c: char) -> Class {
+        Class {
+            ranges: vec![(c, c)],
+            negated: false,
+        }
+    }
+
+    pub(super) fn matches(&self, c: char) -> bool {
+        self.ranges.iter().any(|&(low, high)| low <= c && c <= high) != self.negated
+    }
+}
+
+/// A parsed regular expression.
+pub(super) enum Node {
+    Class(Class),
+    Seq(Vec<Node>),
+    Choice(Vec<Node>),
+    Optional(Box<Node>),
+    Repeat(Box<Node>),
+    Repeat1(Box<Node>),
+}
+
+/// An instruction of the program a lexer runs, in the style of Thompson's
+/// construction: each thread of a match is at an instruction, and all run
+/// in step over the text.
+#[derive(Debug, Clone)]
+enum Inst {
+    /// Consumes a character of the class.
+    Char(Class),
+    /// Continues at both instructions.
+    Split(usize, usize),
+    Jump(usize),
+    /// Matches the definition of that index.
+    Match(usize),
+}
+
+fn compile(node: &Node, program: &mut Vec<Inst>) {
+    match node {
+        Node::Class(class) => program.push(Inst::Char(class.clone())),
+        Node::Seq(nodes) => {
+            for node in nodes {
+                compile(node, program);
+            }
+        }
+        Node::Choice(alternatives) => {
+            let mut jumps = Vec::new();
+            for (i, alternative) in alternatives.iter().enumerate() {
+                if i + 1 == alternatives.len() {
+                    compile(alternative, program);
+                    break;
+                }
+                let split = program.len();
+                program.push(Inst::Split(split + 1, 0));
+                compile(alternative, program);
+                jumps.push(program.len());
+                program.push(Inst::Jump(0));
+                program[split] = Inst::Split(split + 1, program.len());
+            }
+            let end = program.len();
+            for jump in jumps {
+                program[jump] = Inst::Jump(end);
+            }
+        }
+        Node::Optional(inner) => {
+            let split = program.len();
+            program.push(Inst::Split(split + 1, 0));
+            compile(inner, program);
+            program[split] = Inst::Split(split + 1, program.len());
+        }
+        Node::Repeat(inner) => {
+            let split = program.len();
+            program.push(Inst::Split(split + 1, 0));
+            compile(inner, program);
+            program.push(Inst::Jump(split));
+            program[split] = Inst::Split(split + 1, program.len());
+        }
+        Node::Repeat1(inner) => {
+            let start = program.len();
+            compile(inner, program);
+            program.push(Inst::Split(start, program.len() + 1));
+        }
+    }
+}
+
+/// An error in a regular expression: its byte offset and a message.
+pub(super) type RegexError = (usize, String);
+
+pub(super) fn parse_regex(pattern: &str) -> Result<Node, RegexError> {
+    let mut parser = RegexParser {
+        chars: pattern.char_indices().peekable(),
+    };
+    let node = parser.choice()?;
+    match parser.chars.next() {
+        Some((offset, _)) => Err((offset, "unmatched `)`".to_string())),
+        None => Ok(node),
+    }
+}
+
+struct RegexParser<'p> {
+    chars: Peekable<CharIndices<'p>>,
+}
+
+impl RegexParser<'_> {
+    fn choice(&mut self) -> Result<Node, RegexError> {
+        let mut alternatives = vec![self.seq()?];
+        while self.chars.next_if(|&(_, c)| c == '|').is_some() {
+            alternatives.push(self.seq()?);
+        }
+        Ok(match alternatives.len() {
+            1 => alternatives.pop().unwrap(),
+            _ => Node::Choice(alternatives),
+        })
+    }
+
+    fn seq(&mut self) -> Result<Node, RegexError> {
+        let mut nodes = Vec::new();
+        while let Some(&(_, c)) = self.chars.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let mut node = self.atom()?;
+            while let Some((_, c)) = self.chars.next_if(|&(_, c)| matches!(c, '?' | '*' | '+')) {
+                node = match c {
+                    '?' => Node::Optional(Box::new(node)),
+                    '*' => Node::Repeat(Box::new(node)),
+                    _ => Node::Repeat1(Box::new(node)),
+                };
+            }
+            nodes.push(node);
+        }
+        Ok(match nodes.len() {
+            1 => nodes.pop().unwrap(),
+            _ => Node::Seq(nodes),
+        })
+    }
+
+    fn atom(&mut self) -> Result<Node, RegexError> {
+        let (offset, c) = self.chars.next().expect("seq checks for a character");
+        let class = match c {
+            '(' => {
+                let inner = self.choice()?;
+                if self.chars.next_if(|&(_, c)| c == ')').is_none() {
+                    return Err((offset, "unclosed group".to_string()));
+                }
+                return Ok(inner);
+            }
+            '[' => self.class(offset)?,
+            '.' => Class {
+                ranges: vec![('\n', '\n')],
+                negated: true,
+            },
+            '\\' => self.escape(offset)?,
+            '?' | '*' | '+' => return Err((offset, format!("nothing to repeat before `{}`", c))),
+            '^' | '$' => return Err((offset, "anchors are not supported".to_string())),
+            c => Class::char(c),
+        };
+        Ok(Node::Class(class))
+    }
+
+    /// Parses a class after its `[`, at `offset`.
+    fn class(&mut self, offset: usize) -> Result<Class, RegexError> {
+        let negated = self.chars.next_if(|&(_, c)| c == '^').is_some();
+        let mut ranges = Vec::new();
+        loop {
+            let (at, c) = self
+                .chars
+                .next()
+                .ok_or_else(|| (offset, "unclosed character class".to_string()))?;
+            let low = match c {
+                ']' => break,
+                '\\' => {
+                    let escape = self.escape(at)?;
+                    match escape.ranges.as_slice() {
+                        &[(c, c2)] if c == c2 && !escape.negated => c,
+                        _ if escape.negated => {
+                            return Err((
+                                at,
+                                "negated escapes are not supported in classes".to_string(),
+                            ))
+                        }
+                        _ => {
+                            ranges.extend(escape.ranges);
+                            continue;
+                        }
+                    }
+                }
+                c => c,
+            };
+            let is_range = {
+                let mut ahead = self.chars.clone();
+                matches!(ahead.next(), Some((_, '-')))
+                    && !matches!(ahead.next(), Some((_, ']')) | None)
+            };
+            if !is_range {
+                ranges.push((low, low));
+                continue;
+            }
+            self.chars.next();
+            let (at, high) = self.chars.next().unwrap();
+            let high = match high {
+                '\\' => match self.escape(at)?.ranges.as_slice() {
+                    &[(c, c2)] if c == c2 => c,
+                    _ => return Err((at, "a class cannot end a range".to_string())),
+                },
+                c => c,
+            };
+            if high < low {
+                return Err((at, format!("invalid range `{}-{}`", low, high)));
+            }
+            ranges.push((low, high));
+        }
+        Ok(Class { ranges, negated })
+    }
+
+    /// Parses an escape after its `\`, at `offset`.
+    fn escape(&mut self, offset: usize) -> Result<Class, RegexError> {
+        let (_, c) = self
+            .chars
+            .next()
+            .ok_or_else(|| (offset, "trailing backslash".to_string()))?;
+        let (ranges, negated) = match c {
+            'd' | 'D' => (vec![('0', '9')], c == 'D'),
+            'w' | 'W' => (
+                vec![('0', '9'), ('A', 'Z'), ('_', '_'), ('a', 'z')],
+                c == 'W',
+            ),
+            's' | 'S' => (vec![('\t', '\r'), (' ', ' ')], c == 'S'),
+            'n' => (vec![('\n', '\n')], false),
+            'r' => (vec![('\r', '\r')], false),
+            't' => (vec![('\t', '\t')], false),
+            c if c.is_alphanumeric() => {
+                return Err((offset, format!("unknown escape `\\{}`", c)));
+            }
+            c => (vec![(c, c)], false),
+        };
+        Ok(Class { ranges, negated })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::parse_ebnf;
+
+    fn names(lexer: &Lexer, source: &str) -> Vec<String> {
+        let output = lexer.tokenize(source);
+        assert!(output.diagnostics.is_empty(), "{:?}", output.diagnostics);
+        output
+            .value
+            .iter()
+            .map(|token| {
+                let span = token.span;
+                format!(
+                    "{}:{}",
+                    lexer.name(token.value),
+                    &source[span.start()..span.end()]
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_longest_match_and_priorities() {
+        let grammar = parse_ebnf(
+            r#"
+            stmt = If Id "=" Number ";" | Id "==" Id ;
+            token If = "if" ;
+            token Id = /[a-zA-Z_]\w*/ ;
+            token Number = /-?\d+(\.\d+)?/ ;
+            token Minus = "-" priority 1 ;
+            skip Space = /\s+/ ;
+            skip Comment = /\/\/[^\n]*/ ;
+            "#,
+        )
+        .value;
+        let lexer = Lexer::new(&grammar).unwrap();
+        assert_eq!(
+            names(&lexer, "if iffy = 1.5; // if\nx == -2 - 3"),
+            [
+                "If:if",
+                "Id:iffy",
+                "\"=\":=",
+                "Number:1.5",
+                "\";\":;",
+                "Id:x",
+                "\"==\":==",
+                "Number:-2",
+                "Minus:-",
+                "Number:3",
+            ]
+        );
+        assert_eq!(lexer.kind("\"=\""), Some(TokenId(6)));
+        assert_eq!(lexer.kind("Space"), Some(TokenId(4)));
+        assert_eq!(lexer.kind("Missing"), None);
+
+        let output = lexer.tokenize("a @@ b $");
+        let messages: Vec<&str> = output
+            .diagnostics
+            .iter()
+            .map(|d| d.message.as_str())
+            .collect();
+        assert_eq!(
+            messages,
+            ["unexpected characters `@@`", "unexpected character `$`"]
+        );
+        assert_eq!(output.diagnostics[0].primary_span(), Span::new(2, 4));
+        assert_eq!(output.value.len(), 2);
+    }
+
+    #[test]
+    fn test_literal_definitions_and_errors() {
+        let grammar = parse_ebnf(r#"a = "+" Plus ; token Plus = "+" ;"#).value;
+        let lexer = Lexer::new(&grammar).unwrap();
+        assert_eq!(lexer.kind("\"+\""), lexer.kind("Plus"));
+
+        let error = |source: &str| {
+            Lexer::new(&parse_ebnf(source).value)
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(error("a = Id ;"), "no definition is given for token `Id`");
+        assert_eq!(
+            error("a = Id ; token Id = /a*/ ;"),
+            "token `Id` matches the empty text"
+        );
+        assert_eq!(
+            error(r"a = Id ; token Id = /(a|b/ ;"),
+            "invalid pattern for token `Id` at offset 0: unclosed group"
+        );
+        assert_eq!(
+            error(r"a = Id ; token Id = /[z-a]/ ;"),
+            "invalid pattern for token `Id` at offset 3: invalid range `z-a`"
+        );
+        assert_eq!(
+            error(r"a = Id ; token Id = /a\q/ ;"),
+            "invalid pattern for token `Id` at offset 1: unknown escape `\\q`"
+        );
+    }
+}
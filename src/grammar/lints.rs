@@ -0,0 +1,372 @@
+use super::{Analysis, Expr, Grammar, Lookahead};
+use crate::diagnostics::{suggest, Diagnostic, DiagnosticTag, Suggestion};
+use std::collections::{BTreeSet, HashSet};
+
+/// A likely mistake in a [`Grammar`], found by [`Analysis::lints`].
+///
+/// Expressions are given by their index in the order [`Expr::walk`]
+/// visits the body of the rule, the whole body being 0.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Lint {
+    /// A rule refers to a rule the grammar does not define.
+    UndefinedRule {
+        rule: String,
+        expr: usize,
+        name: String,
+    },
+    /// A rule is not used by the start rule, directly or not.
+    UnreachableRule { rule: String },
+    /// An alternative of a choice is never taken by a parser looking one
+    /// token ahead, such as one from
+    /// [`RustGenerator`](super::RustGenerator), as the earlier alternatives
+    /// are taken for every token it can start with. `by` is the earlier
+    /// alternative taken instead.
+    ShadowedAlternative {
+        rule: String,
+        expr: usize,
+        by: usize,
+    },
+    /// The body of a repetition can match nothing.
+    EmptyRepetition { rule: String, expr: usize },
+}
+
+impl Lint {
+    /// Returns the name of the rule the lint is in.
+    pub fn rule(&self) -> &str {
+        match self {
+            Lint::UndefinedRule { rule, .. }
+            | Lint::UnreachableRule { rule }
+            | Lint::ShadowedAlternative { rule, .. }
+            | Lint::EmptyRepetition { rule, .. } => rule,
+        }
+    }
+
+    /// Describes the lint, with labels on the spans of the rule if the
+    /// grammar was read from a source. Undefined rules are errors, and the
+    /// other lints warnings.
+    pub fn to_diagnostic(&self, grammar: &Grammar) -> Diagnostic {
+        let rule = grammar.get(self.rule());
+        let span = |index: usize| rule.and_then(|rule| rule.expr_span(index));
+        match self {
+            Lint::UndefinedRule { expr, name, .. } => {
+                let mut diagnostic = Diagnostic::error(format!("undefined rule `{}`", name));
+                if let Some(span) = span(*expr) {
+                    diagnostic = diagnostic.with_label(span, "not defined in this grammar");
+                    let names = grammar.rules().iter().map(|rule| rule.name.as_str());
+                    if let Some(candidate) = suggest(names, name, 0.34) {
+                        diagnostic =
+                            diagnostic.with_suggestion(Suggestion::did_you_mean(span, candidate));
+                    }
+                }
+                diagnostic
+            }
+            Lint::UnreachableRule { rule: name } => {
+                let mut diagnostic = Diagnostic::warning(format!("rule `{}` is never used", name))
+                    .with_tag(DiagnosticTag::Unnecessary);
+                if let Some(span) = rule.and_then(|rule| rule.span) {
+                    let start = grammar.start().map_or("", |start| start.name.as_str());
+                    diagnostic = diagnostic.with_label(
+                        span,
+                        format!("not reachable from the start rule `{}`", start),
+                    );
+                }
+                diagnostic
+            }
+            Lint::ShadowedAlternative {
+                rule: name,
+                expr,
+                by,
+            } => {
+                let mut diagnostic =
+                    Diagnostic::warning(format!("alternative of rule `{}` is never taken", name))
+                        .with_tag(DiagnosticTag::Unnecessary);
+                if let Some(span) = span(*expr) {
+                    diagnostic = diagnostic.with_label(span, "this alternative is never taken");
+                }
+                if let Some(span) = span(*by) {
+                    diagnostic = diagnostic
+                        .with_secondary_label(span, "as this one is taken for the same tokens");
+                }
+                diagnostic.with_note(
+                    "a parser looking one token ahead takes the first alternative that can \
+                     start with it",
+                )
+            }
+            Lint::EmptyRepetition { rule: name, expr } => {
+                let mut diagnostic =
+                    Diagnostic::warning(format!("empty repetition in rule `{}`", name));
+                if let Some(span) = span(*expr) {
+                    diagnostic = diagnostic
+                        .with_label(span, "the body of this repetition can match nothing");
+                }
+                diagnostic.with_note(
+                    "the repetition can match the same input in many ways, and a parser may \
+                     repeat it forever",
+                )
+            }
+        }
+    }
+}
+
+impl Analysis {
+    /// Looks for likely mistakes in `grammar`: references to undefined
+    /// rules, rules the start rule never uses, alternatives that are never
+    /// taken and repetitions of what can match nothing.
+    ///
+    /// The lints come rule by rule, in the order of the rules.
+    ///
+    /// # Examples
+    /// ```
+    /// use grammarsmith::grammar::*;
+    ///
+    /// let source = "stmt = Id \"=\" expr ; expr = Number (\"+\" exrp)? ; block = stmt* ;";
+    /// let grammar = parse_ebnf(source).value;
+    ///
+    /// let lints = grammar.analyze().lints(&grammar);
+    /// assert_eq!(lints.len(), 2);
+    /// assert_eq!(lints[1], Lint::UnreachableRule { rule: "block".to_string() });
+    /// let diagnostic = lints[0].to_diagnostic(&grammar);
+    /// assert_eq!(diagnostic.message, "undefined rule `exrp`");
+    /// let span = diagnostic.primary_span().unwrap();
+    /// assert_eq!(&source[span.start()..span.end()], "exrp");
+    /// ```
+    pub fn lints(&self, grammar: &Grammar) -> Vec<Lint> {
+        let used = match grammar.start() {
+            Some(start) => {
+                let mut used = HashSet::from([start.name.as_str()]);
+                let mut stack = vec![start];
+                while let Some(rule) = stack.pop() {
+                    rule.expr.walk(&mut |expr| {
+                        if let Expr::Rule(name) = expr {
+                            if let Some(rule) = grammar.get(name) {
+                                if used.insert(rule.name.as_str()) {
+                                    stack.push(rule);
+                                }
+                            }
+                        }
+                    });
+                }
+                used
+            }
+            None => HashSet::new(),
+        };
+        let mut linter = Linter {
+            analysis: self,
+            grammar,
+            rule: "",
+            lints: Vec::new(),
+        };
+        for rule in grammar.rules() {
+            if !used.contains(rule.name.as_str()) {
+                linter.lints.push(Lint::UnreachableRule {
+                    rule: rule.name.clone(),
+                });
+            }
+            linter.rule = &rule.name;
+            linter.visit(&rule.expr, 0);
+        }
+        linter.lints
+    }
+}
+
+impl Grammar {
+    /// Reports likely mistakes in the grammar, as found by
+    /// [`Analysis::lints`].
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        self.analyze()
+            .lints(self)
+            .iter()
+            .map(|lint| lint.to_diagnostic(self))
+            .collect()
+    }
+}
+
+struct Linter<'a> {
+    analysis: &'a Analysis,
+    grammar: &'a Grammar,
+    rule: &'a str,
+    lints: Vec<Lint>,
+}
+
+impl Linter<'_> {
+    /// Looks for lints in `expr`, the `index`th expression of the rule, and
+    /// returns the index of the expression after it.
+    fn visit(&mut self, expr: &Expr, index: usize) -> usize {
+        match expr {
+            Expr::Token(_) => index + 1,
+            Expr::Rule(name) => {
+                if self.grammar.get(name).is_none() {
+                    self.lints.push(Lint::UndefinedRule {
+                        rule: self.rule.to_string(),
+                        expr: index,
+                        name: name.clone(),
+                    });
+                }
+                index + 1
+            }
+            Expr::Seq(exprs) => exprs
+                .iter()
+                .fold(index + 1, |child, expr| self.visit(expr, child)),
+            Expr::Choice(alternatives) => {
+                // The alternatives seen so far, whether they can match
+                // nothing, and their FIRST sets.
+                let mut seen: Vec<(usize, bool, BTreeSet<Lookahead>)> = Vec::new();
+                let mut child = index + 1;
+                for alternative in alternatives {
+                    let nullable = self.analysis.is_nullable_expr(alternative);
+                    let first = self.analysis.first_of(alternative);
+                    if let Some(by) = shadowing(&seen, nullable, &first) {
+                        self.lints.push(Lint::ShadowedAlternative {
+                            rule: self.rule.to_string(),
+                            expr: child,
+                            by,
+                        });
+                    }
+                    let next = self.visit(alternative, child);
+                    seen.push((child, nullable, first));
+                    child = next;
+                }
+                child
+            }
+            Expr::Optional(inner) => self.visit(inner, index + 1),
+            Expr::Repeat(inner) | Expr::Repeat1(inner) => {
+                if self.analysis.is_nullable_expr(inner) {
+                    self.lints.push(Lint::EmptyRepetition {
+                        rule: self.rule.to_string(),
+                        expr: index,
+                    });
+                }
+                self.visit(inner, index + 1)
+            }
+        }
+    }
+}
+
+/// Returns the earlier alternative taken instead of one that can match
+/// nothing if `nullable`, and can start with `first`, if it is never taken.
+fn shadowing(
+    seen: &[(usize, bool, BTreeSet<Lookahead>)],
+    nullable: bool,
+    first: &BTreeSet<Lookahead>,
+) -> Option<usize> {
+    // Only the first alternative that can match nothing is taken when no
+    // alternative can start with the current token.
+    let fallback = seen.iter().find(|(_, nullable, _)| *nullable);
+    if nullable && fallback.is_none() {
+        return None;
+    }
+    if !nullable && first.is_empty() {
+        // The alternative matches nothing at all, such as an undefined
+        // rule, which is reported on its own.
+        return None;
+    }
+    let covered = first
+        .iter()
+        .all(|token| seen.iter().any(|(_, _, earlier)| earlier.contains(token)));
+    if !covered {
+        return None;
+    }
+    match first.iter().next() {
+        Some(token) => seen
+            .iter()
+            .find(|(_, _, earlier)| earlier.contains(token))
+            .map(|(index, _, _)| *index),
+        None => fallback.map(|(index, _, _)| *index),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::parse_ebnf;
+
+    fn lints(source: &str) -> Vec<Lint> {
+        let grammar = parse_ebnf(source).value;
+        grammar.analyze().lints(&grammar)
+    }
+
+    #[test]
+    fn test_lints() {
+        assert_eq!(lints("a = X b ; b = Y | Z ;"), []);
+        assert_eq!(
+            lints("a = X bb ; b = Y ; c = a ;"),
+            [
+                Lint::UndefinedRule {
+                    rule: "a".to_string(),
+                    expr: 2,
+                    name: "bb".to_string()
+                },
+                Lint::UnreachableRule {
+                    rule: "b".to_string()
+                },
+                Lint::UnreachableRule {
+                    rule: "c".to_string()
+                },
+            ]
+        );
+        // `X Z` is never taken, as `X Y` is for `X`, and neither is the
+        // second empty alternative.
+        assert_eq!(
+            lints("a = X Y | Z | X Z | () | () ;"),
+            [
+                Lint::ShadowedAlternative {
+                    rule: "a".to_string(),
+                    expr: 5,
+                    by: 1
+                },
+                Lint::ShadowedAlternative {
+                    rule: "a".to_string(),
+                    expr: 9,
+                    by: 8
+                },
+            ]
+        );
+        assert_eq!(
+            lints("a = (X?)* Y+ ;"),
+            [Lint::EmptyRepetition {
+                rule: "a".to_string(),
+                expr: 1
+            }]
+        );
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let source = "a = X expt | Y ; expr = Z ; b = (X | X)* ;";
+        let grammar = parse_ebnf(source).value;
+        let diagnostics = grammar.validate();
+        let text = |span: Option<crate::position::Span>| {
+            let span = span.unwrap();
+            &source[span.start()..span.end()]
+        };
+        let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            [
+                "undefined rule `expt`",
+                "rule `expr` is never used",
+                "rule `b` is never used",
+                "alternative of rule `b` is never taken",
+            ]
+        );
+        assert!(diagnostics[0].is_error());
+        assert_eq!(text(diagnostics[0].primary_span()), "expt");
+        assert_eq!(
+            diagnostics[0].suggestions[0].message,
+            "did you mean `expr`?"
+        );
+        assert!(!diagnostics[1].is_error());
+        assert_eq!(text(diagnostics[1].primary_span()), "expr");
+        assert_eq!(text(diagnostics[3].primary_span()), "X");
+        assert_eq!(
+            text(
+                diagnostics[3]
+                    .secondary_labels()
+                    .next()
+                    .map(|label| label.span)
+            ),
+            "X"
+        );
+        assert!(diagnostics[3].tags.contains(&DiagnosticTag::Unnecessary));
+    }
+}
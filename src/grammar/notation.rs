@@ -0,0 +1,350 @@
+use super::{Expr, Grammar};
+use std::collections::HashSet;
+use std::fmt::Write;
+
+impl Grammar {
+    /// Writes the grammar in the EBNF notation of the W3C XML
+    /// specification, as read by specification tools and railroad diagram
+    /// generators.
+    ///
+    /// Rules are written `name ::= expression`, and token kinds as they are
+    /// named, literals keeping their quotes.
+    ///
+    /// # Examples
+    /// ```
+    /// use grammarsmith::grammar::*;
+    ///
+    /// let grammar = parse_ebnf(r#"list = "[" [Number {"," Number}] "]" ;"#).value;
+    /// assert_eq!(
+    ///     grammar.to_w3c_ebnf(),
+    ///     "list ::= \"[\" (Number (\",\" Number)*)? \"]\"\n"
+    /// );
+    /// ```
+    pub fn to_w3c_ebnf(&self) -> String {
+        let mut out = String::new();
+        for rule in self.rules() {
+            let _ = write!(out, "{} ::=", rule.name);
+            write_body(&mut out, &rule.expr, &|out, name| out.push_str(name));
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Writes the grammar as an ANTLR 4 combined grammar named `name`.
+    ///
+    /// Rules become parser rules, and literals single-quoted ANTLR literals.
+    /// Token kinds are declared in a `tokens` section, to be given lexer
+    /// rules or a token vocabulary. ANTLR requires rule names to start with
+    /// a lowercase letter and token kinds with an uppercase one.
+    ///
+    /// # Examples
+    /// ```
+    /// use grammarsmith::grammar::*;
+    ///
+    /// let grammar = parse_ebnf(r#"sum = Number ("+" Number)* ;"#).value;
+    /// assert_eq!(
+    ///     grammar.to_antlr("Calc"),
+    ///     "grammar Calc;\n\ntokens { Number }\n\nsum\n    : Number ('+' Number)*\n    ;\n"
+    /// );
+    /// ```
+    pub fn to_antlr(&self, name: &str) -> String {
+        let mut out = format!("grammar {};\n", name);
+        let named: Vec<&str> = self
+            .tokens()
+            .into_iter()
+            .filter(|token| literal_text(token).is_none())
+            .collect();
+        if !named.is_empty() {
+            let _ = write!(out, "\ntokens {{ {} }}\n", named.join(", "));
+        }
+        let token = |out: &mut String, name: &str| match literal_text(name) {
+            Some(text) => {
+                out.push('\'');
+                for c in text.chars() {
+                    match c {
+                        '\'' => out.push_str("\\'"),
+                        '\\' => out.push_str("\\\\"),
+                        c => out.push(c),
+                    }
+                }
+                out.push('\'');
+            }
+            None => out.push_str(name),
+        };
+        for rule in self.rules() {
+            let _ = write!(out, "\n{}\n    :", rule.name);
+            write_body(&mut out, &rule.expr, &token);
+            out.push_str("\n    ;\n");
+        }
+        out
+    }
+
+    /// Writes the grammar in plain BNF, without repetitions, optional parts
+    /// or groups.
+    ///
+    /// Rules are written `<name> ::= ...`, with token kinds as they are
+    /// named, literals keeping their quotes, and `""` for the empty
+    /// sequence. Repetitions, optional parts and nested choices become
+    /// auxiliary rules named after the rule they come from, such as
+    /// `<list-1>`, with repetitions using right recursion.
+    ///
+    /// # Examples
+    /// ```
+    /// use grammarsmith::grammar::*;
+    ///
+    /// let grammar = parse_ebnf(r#"args = Id ("," Id)* ;"#).value;
+    /// assert_eq!(
+    ///     grammar.to_bnf(),
+    ///     "<args> ::= Id <args-1>\n<args-1> ::= \",\" Id <args-1> | \"\"\n"
+    /// );
+    /// ```
+    pub fn to_bnf(&self) -> String {
+        let mut out = String::new();
+        for (name, alternatives) in self.bnf_rules() {
+            let _ = write!(out, "<{}> ::=", name);
+            for (i, alternative) in alternatives.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(" |");
+                }
+                if alternative.is_empty() {
+                    out.push_str(" \"\"");
+                }
+                for symbol in alternative {
+                    match symbol {
+                        Expr::Rule(name) => write!(out, " <{}>", name),
+                        symbol => write!(out, " {}", symbol),
+                    }
+                    .unwrap();
+                }
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Returns the rules as in [`Grammar::to_bnf`]: each a name and its
+    /// alternatives, sequences of token kinds and rule references, with
+    /// auxiliary rules following the rule they come from.
+    pub(super) fn bnf_rules(&self) -> Vec<(String, Vec<Vec<Expr>>)> {
+        let mut writer = BnfWriter {
+            names: self.rules().iter().map(|rule| rule.name.clone()).collect(),
+            pending: Vec::new(),
+        };
+        let mut rules = Vec::new();
+        for rule in self.rules() {
+            let alternatives = writer.alternatives(&rule.name, &rule.expr);
+            rules.push((rule.name.clone(), alternatives));
+            rules.append(&mut writer.pending);
+        }
+        rules
+    }
+}
+
+/// Returns the text of a literal token kind, such as `+` for `"+"`.
+fn literal_text(kind: &str) -> Option<&str> {
+    ['"', '\''].into_iter().find_map(|quote| {
+        kind.strip_prefix(quote)?
+            .strip_suffix(quote)
+            .filter(|text| !text.is_empty())
+    })
+}
+
+/// Writes the body of a rule after its `::=` or `:`, leaving an empty body
+/// or alternative empty.
+fn write_body(out: &mut String, expr: &Expr, token: &dyn Fn(&mut String, &str)) {
+    match expr {
+        Expr::Choice(alternatives) if alternatives.len() > 1 => {
+            for (i, alternative) in alternatives.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(" |");
+                }
+                if !is_empty(alternative) {
+                    out.push(' ');
+                    write_ebnf(out, alternative, 1, token);
+                }
+            }
+        }
+        expr if is_empty(expr) => {}
+        expr => {
+            out.push(' ');
+            write_ebnf(out, expr, 0, token);
+        }
+    }
+}
+
+/// Writes `expr` with the usual EBNF operators, in parentheses if it binds
+/// less tightly than `precedence`.
+fn write_ebnf(out: &mut String, expr: &Expr, precedence: u8, token: &dyn Fn(&mut String, &str)) {
+    if expr.precedence() < precedence {
+        out.push('(');
+        write_ebnf(out, expr, 0, token);
+        out.push(')');
+        return;
+    }
+    match expr {
+        Expr::Token(name) => token(out, name),
+        Expr::Rule(name) => out.push_str(name),
+        Expr::Seq(exprs) | Expr::Choice(exprs) if exprs.is_empty() => out.push_str("()"),
+        Expr::Seq(exprs) => {
+            for (i, expr) in exprs.iter().enumerate() {
+                if i > 0 {
+                    out.push(' ');
+                }
+                write_ebnf(out, expr, 2, token);
+            }
+        }
+        Expr::Choice(alternatives) => {
+            for (i, alternative) in alternatives.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(" | ");
+                }
+                write_ebnf(out, alternative, 1, token);
+            }
+        }
+        Expr::Optional(inner) | Expr::Repeat(inner) | Expr::Repeat1(inner) => {
+            write_ebnf(out, inner, 3, token);
+            out.push(match expr {
+                Expr::Optional(_) => '?',
+                Expr::Repeat(_) => '*',
+                _ => '+',
+            });
+        }
+    }
+}
+
+fn is_empty(expr: &Expr) -> bool {
+    matches!(expr, Expr::Seq(exprs) | Expr::Choice(exprs) if exprs.is_empty())
+}
+
+/// Splits rules into BNF alternatives, collecting the auxiliary rules of
+/// the current rule.
+struct BnfWriter {
+    names: HashSet<String>,
+    pending: Vec<(String, Vec<Vec<Expr>>)>,
+}
+
+impl BnfWriter {
+    /// Returns the alternatives of `expr` as sequences of symbols.
+    fn alternatives(&mut self, rule: &str, expr: &Expr) -> Vec<Vec<Expr>> {
+        match expr {
+            Expr::Choice(alternatives) if !alternatives.is_empty() => alternatives
+                .iter()
+                .map(|alternative| self.sequence(rule, alternative))
+                .collect(),
+            expr => vec![self.sequence(rule, expr)],
+        }
+    }
+
+    /// Returns `expr` as a sequence of symbols.
+    fn sequence(&mut self, rule: &str, expr: &Expr) -> Vec<Expr> {
+        match expr {
+            Expr::Seq(exprs) => exprs
+                .iter()
+                .flat_map(|expr| self.sequence(rule, expr))
+                .collect(),
+            Expr::Choice(alternatives) if alternatives.is_empty() => Vec::new(),
+            expr => vec![self.symbol(rule, expr)],
+        }
+    }
+
+    /// Returns `expr` as a single symbol, adding an auxiliary rule if
+    /// needed.
+    fn symbol(&mut self, rule: &str, expr: &Expr) -> Expr {
+        match expr {
+            Expr::Token(_) | Expr::Rule(_) => return expr.clone(),
+            Expr::Choice(alternatives) if alternatives.len() == 1 => {
+                return self.symbol(rule, &alternatives[0])
+            }
+            Expr::Seq(exprs) if exprs.len() == 1 => return self.symbol(rule, &exprs[0]),
+            _ => {}
+        }
+        let name = self.fresh_name(rule);
+        let symbol = Expr::rule(&name);
+        let alternatives = match expr {
+            Expr::Optional(inner) => {
+                let mut alternatives = self.alternatives(rule, inner);
+                alternatives.push(Vec::new());
+                alternatives
+            }
+            Expr::Repeat(inner) | Expr::Repeat1(inner) => {
+                let once = self.sequence(rule, inner);
+                let mut again = once.clone();
+                again.push(symbol.clone());
+                let last = if matches!(expr, Expr::Repeat(_)) {
+                    Vec::new()
+                } else {
+                    once
+                };
+                vec![again, last]
+            }
+            expr => self.alternatives(rule, expr),
+        };
+        self.pending.push((name, alternatives));
+        symbol
+    }
+
+    fn fresh_name(&mut self, rule: &str) -> String {
+        let mut count = 1;
+        loop {
+            let name = format!("{}-{}", rule, count);
+            if self.names.insert(name.clone()) {
+                return name;
+            }
+            count += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::parse_ebnf;
+
+    const SOURCE: &str = r#"
+        stmt = If expr block ["else" block] | Id ("=" | "+=") expr | () ;
+        block = "{" stmt+ "}" ;
+        expr = Id ;
+    "#;
+
+    #[test]
+    fn test_ebnf_notations() {
+        let grammar = parse_ebnf(SOURCE).value;
+        assert_eq!(
+            grammar.to_w3c_ebnf(),
+            "stmt ::= If expr block (\"else\" block)? | Id (\"=\" | \"+=\") expr |\n\
+             block ::= \"{\" stmt+ \"}\"\n\
+             expr ::= Id\n"
+        );
+        assert_eq!(
+            grammar.to_antlr("Lang"),
+            "grammar Lang;\n\
+             \n\
+             tokens { If, Id }\n\
+             \n\
+             stmt\n    : If expr block ('else' block)? | Id ('=' | '+=') expr |\n    ;\n\
+             \n\
+             block\n    : '{' stmt+ '}'\n    ;\n\
+             \n\
+             expr\n    : Id\n    ;\n"
+        );
+        let grammar = Grammar::new().with_rule("quote", Expr::token("\"'\\\""));
+        assert_eq!(
+            grammar.to_antlr("Q"),
+            "grammar Q;\n\nquote\n    : '\\'\\\\'\n    ;\n"
+        );
+    }
+
+    #[test]
+    fn test_bnf() {
+        let grammar = parse_ebnf(SOURCE).value;
+        assert_eq!(
+            grammar.to_bnf(),
+            "<stmt> ::= If <expr> <block> <stmt-1> | Id <stmt-2> <expr> | \"\"\n\
+             <stmt-1> ::= \"else\" <block> | \"\"\n\
+             <stmt-2> ::= \"=\" | \"+=\"\n\
+             <block> ::= \"{\" <block-1> \"}\"\n\
+             <block-1> ::= <stmt> <block-1> | <stmt>\n\
+             <expr> ::= Id\n"
+        );
+    }
+}
@@ -0,0 +1,361 @@
+use super::recursion::{choice, split_alternatives};
+use super::{Expr, Grammar, Rule};
+use crate::position::Span;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+
+/// How operators of the same precedence level group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Assoc {
+    /// `a - b - c` is `(a - b) - c`.
+    Left,
+    /// `a ^ b ^ c` is `a ^ (b ^ c)`.
+    Right,
+    /// `a == b == c` is an error.
+    NonAssoc,
+}
+
+impl fmt::Display for Assoc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Assoc::Left => write!(f, "%left"),
+            Assoc::Right => write!(f, "%right"),
+            Assoc::NonAssoc => write!(f, "%nonassoc"),
+        }
+    }
+}
+
+/// Operators of the same precedence and associativity, declared in a
+/// grammar with [`Grammar::add_precedence`], or `%left "+" "-" ;` in the
+/// EBNF notation.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PrecedenceLevel {
+    pub assoc: Assoc,
+    /// The token kinds of the operators.
+    pub tokens: Vec<String>,
+    /// Where the level is declared, if the grammar was read from a source.
+    pub span: Option<Span>,
+}
+
+impl PrecedenceLevel {
+    pub fn new(assoc: Assoc, tokens: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        PrecedenceLevel {
+            assoc,
+            tokens: tokens.into_iter().map(Into::into).collect(),
+            span: None,
+        }
+    }
+
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = Some(span);
+        self
+    }
+}
+
+impl fmt::Display for PrecedenceLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.assoc)?;
+        for token in &self.tokens {
+            write!(f, " {}", token)?;
+        }
+        write!(f, " ;")
+    }
+}
+
+/// An error resolving the precedence declarations of a grammar.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PrecedenceError {
+    /// A token kind is declared at more than one level.
+    Redeclared { token: String },
+    /// A rule has an alternative `rule op rule` whose operator has no
+    /// declared precedence.
+    Undeclared { rule: String, token: String },
+}
+
+impl fmt::Display for PrecedenceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PrecedenceError::Redeclared { token } => {
+                write!(
+                    f,
+                    "the precedence of `{}` is declared more than once",
+                    token
+                )
+            }
+            PrecedenceError::Undeclared { rule, token } => write!(
+                f,
+                "rule `{}` uses `{}` as a binary operator, but its precedence is not declared",
+                rule, token
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PrecedenceError {}
+
+/// The operators of a rule at one precedence level.
+#[derive(Default)]
+struct Operators {
+    binary: Vec<String>,
+    prefix: Vec<String>,
+    postfix: Vec<String>,
+}
+
+impl Grammar {
+    /// Rewrites the operator alternatives of the rules into a rule per
+    /// precedence level, following the precedence declarations.
+    ///
+    /// An alternative of a rule `expr` is an operator if it is `expr op
+    /// expr`, binary, `op expr`, prefix, or `expr op`, postfix, where `op`
+    /// is a token kind with a declared precedence. Levels declared later
+    /// bind tighter, and a prefix or postfix operator binds as tightly as
+    /// its level. The other alternatives are the primaries, which bind
+    /// tightest.
+    ///
+    /// `expr` then matches the loosest level, and each level gets a rule
+    /// named after `expr`, such as `expr_1`, down to `expr_primary`.
+    /// Left-associative operators become repetitions, so the result has no
+    /// left recursion and can be given to
+    /// [`RustGenerator`](super::RustGenerator).
+    ///
+    /// # Examples
+    /// ```
+    /// use grammarsmith::grammar::*;
+    ///
+    /// let grammar = parse_ebnf(r#"
+    ///     expr = expr ("+" | "-") expr | expr "*" expr | expr "^" expr
+    ///          | "-" expr | Number | "(" expr ")" ;
+    ///     %left "+" "-" ;
+    ///     %left "*" ;
+    ///     %right "^" ;
+    /// "#).value;
+    ///
+    /// assert_eq!(grammar.resolve_precedence().unwrap().to_string(), r#"expr = "-" expr | expr_1 (("+" | "-") expr_1)* ;
+    /// expr_1 = expr_2 ("*" expr_2)* ;
+    /// expr_2 = expr_primary ("^" expr_2)? ;
+    /// expr_primary = Number | "(" expr ")" ;
+    /// "#);
+    /// ```
+    pub fn resolve_precedence(&self) -> Result<Grammar, PrecedenceError> {
+        let mut levels: HashMap<&str, usize> = HashMap::new();
+        for (index, level) in self.precedence().iter().enumerate() {
+            for token in &level.tokens {
+                if levels.insert(token, index).is_some() {
+                    return Err(PrecedenceError::Redeclared {
+                        token: token.clone(),
+                    });
+                }
+            }
+        }
+        let mut names: HashSet<String> =
+            self.rules().iter().map(|rule| rule.name.clone()).collect();
+        let mut grammar = Grammar::new();
+        for import in self.imports() {
+            grammar.add_import(import.clone());
+        }
+        for def in self.token_defs() {
+            grammar.add_token_def(def.clone());
+        }
+        for rule in self.rules() {
+            let mut operators: BTreeMap<usize, Operators> = BTreeMap::new();
+            let mut primaries = Vec::new();
+            for alternative in split_alternatives(&rule.expr) {
+                let is_rule = |expr: &Expr| matches!(expr, Expr::Rule(name) if *name == rule.name);
+                let level = |token: &str| levels.get(token).copied();
+                let declared = |expr: &Expr| {
+                    tokens(expr).is_some_and(|ops| ops.iter().all(|op| level(op).is_some()))
+                };
+                match alternative.as_slice() {
+                    [left, ops, right]
+                        if is_rule(left) && is_rule(right) && tokens(ops).is_some() =>
+                    {
+                        for op in tokens(ops).unwrap() {
+                            let level = level(op).ok_or_else(|| PrecedenceError::Undeclared {
+                                rule: rule.name.clone(),
+                                token: op.clone(),
+                            })?;
+                            operators.entry(level).or_default().binary.push(op.clone());
+                        }
+                    }
+                    [ops, operand] if is_rule(operand) && declared(ops) => {
+                        for op in tokens(ops).unwrap() {
+                            let level = level(op).unwrap();
+                            operators.entry(level).or_default().prefix.push(op.clone());
+                        }
+                    }
+                    [operand, ops] if is_rule(operand) && declared(ops) => {
+                        for op in tokens(ops).unwrap() {
+                            let level = level(op).unwrap();
+                            operators.entry(level).or_default().postfix.push(op.clone());
+                        }
+                    }
+                    _ => primaries.push(alternative),
+                }
+            }
+            if operators.is_empty() {
+                grammar.add(rule.clone());
+                continue;
+            }
+
+            let mut level_names = vec![rule.name.clone()];
+            let mut suffix = 0;
+            while level_names.len() < operators.len() {
+                suffix += 1;
+                let name = format!("{}_{}", rule.name, suffix);
+                if names.insert(name.clone()) {
+                    level_names.push(name);
+                }
+            }
+            level_names.push(fresh_name(&mut names, &format!("{}_primary", rule.name)));
+            for (i, (level, operators)) in operators.iter().enumerate() {
+                let this = Expr::rule(&level_names[i]);
+                let next = Expr::rule(&level_names[i + 1]);
+                let operand = match one_of(&operators.postfix) {
+                    Some(postfix) => Expr::seq([next, Expr::repeat(postfix)]),
+                    None => next,
+                };
+                let mut body = match one_of(&operators.binary) {
+                    None => operand,
+                    Some(binary) => match self.precedence()[*level].assoc {
+                        Assoc::Left => {
+                            Expr::seq([operand.clone(), Expr::repeat(Expr::seq([binary, operand]))])
+                        }
+                        Assoc::Right => {
+                            Expr::seq([operand, Expr::optional(Expr::seq([binary, this.clone()]))])
+                        }
+                        Assoc::NonAssoc => Expr::seq([
+                            operand.clone(),
+                            Expr::optional(Expr::seq([binary, operand])),
+                        ]),
+                    },
+                };
+                if let Some(prefix) = one_of(&operators.prefix) {
+                    body = Expr::choice([Expr::seq([prefix, this]), body]);
+                }
+                let mut resolved = with_span_of(rule, Rule::new(&level_names[i], body));
+                if i == 0 {
+                    resolved.error = rule.error.clone();
+                }
+                grammar.add(resolved);
+            }
+            let primary = Rule::new(&level_names[operators.len()], choice(primaries));
+            grammar.add(with_span_of(rule, primary));
+        }
+        Ok(grammar)
+    }
+}
+
+/// Returns the token kinds of `expr` if it is a token or a choice of
+/// tokens.
+fn tokens(expr: &Expr) -> Option<Vec<&String>> {
+    match expr {
+        Expr::Token(token) => Some(vec![token]),
+        Expr::Choice(alternatives) => alternatives
+            .iter()
+            .map(|alternative| match alternative {
+                Expr::Token(token) => Some(token),
+                _ => None,
+            })
+            .collect(),
+        _ => None,
+    }
+}
+
+/// Returns the choice of the token kinds `tokens`, if there are any.
+fn one_of(tokens: &[String]) -> Option<Expr> {
+    match tokens {
+        [] => None,
+        [token] => Some(Expr::token(token)),
+        tokens => Some(Expr::choice(tokens.iter().map(Expr::token))),
+    }
+}
+
+fn with_span_of(original: &Rule, rule: Rule) -> Rule {
+    match original.span {
+        Some(span) => rule.with_span(span),
+        None => rule,
+    }
+}
+
+fn fresh_name(names: &mut HashSet<String>, base: &str) -> String {
+    let mut name = base.to_string();
+    let mut count = 1;
+    while !names.insert(name.clone()) {
+        count += 1;
+        name = format!("{}{}", base, count);
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::parse_ebnf;
+
+    #[test]
+    fn test_resolve_precedence() {
+        let grammar = parse_ebnf(
+            r#"
+            stmt = expr ";" ;
+            expr = expr "==" expr | expr ("+" | "-") expr | expr "*" expr
+                 | "-" expr | "!" expr | expr "?" | Id | "(" expr ")" ;
+            expr_1 = Id ;
+            %nonassoc "==" ;
+            %left "+" "-" ;
+            %left "*" "/" ;
+            %left "?" "!" ;
+            "#,
+        )
+        .value;
+        let resolved = grammar.resolve_precedence().unwrap();
+        assert_eq!(
+            resolved.to_string(),
+            r#"stmt = expr ";" ;
+expr = expr_2 ("==" expr_2)? ;
+expr_2 = "-" expr_2 | expr_3 (("+" | "-") expr_3)* ;
+expr_3 = expr_4 ("*" expr_4)* ;
+expr_4 = "!" expr_4 | expr_primary "?"* ;
+expr_primary = Id | "(" expr ")" ;
+expr_1 = Id ;
+"#
+        );
+        assert!(resolved.check_left_recursion().is_empty());
+        assert!(resolved.check_ll1().is_empty());
+    }
+
+    #[test]
+    fn test_keeps_imports() {
+        let grammar = parse_ebnf(
+            r#"
+            import "lexical" as lex ;
+            expr = expr "+" expr | lex.Number ;
+            %left "+" ;
+            "#,
+        )
+        .value;
+        let resolved = grammar.resolve_precedence().unwrap();
+        assert_eq!(grammar.imports().len(), 1);
+        assert_eq!(resolved.imports(), grammar.imports());
+    }
+
+    #[test]
+    fn test_errors() {
+        let resolve = |source: &str| {
+            parse_ebnf(source)
+                .value
+                .resolve_precedence()
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            resolve(r#"e = e "+" e | X ; %left "+" ; %right "+" ;"#),
+            "the precedence of `\"+\"` is declared more than once"
+        );
+        assert_eq!(
+            resolve(r#"e = e "+" e | e "-" e | X ; %left "+" ;"#),
+            "rule `e` uses `\"-\"` as a binary operator, but its precedence is not declared"
+        );
+        let grammar = Grammar::new().with_rule("e", Expr::token("X"));
+        assert_eq!(grammar.resolve_precedence().unwrap(), grammar);
+    }
+}
@@ -0,0 +1,410 @@
+use super::{Expr, Grammar};
+use std::fmt::Write;
+
+/// The radius of the arcs joining lines.
+const ARC: i64 = 10;
+/// The space between the items of a sequence.
+const H_GAP: i64 = 10;
+/// The space between the alternatives of a choice.
+const V_GAP: i64 = 10;
+/// The height of the boxes of tokens and rules.
+const BOX_HEIGHT: i64 = 24;
+/// The width of a character in the monospace font of the boxes.
+const CHAR_WIDTH: i64 = 9;
+/// The space between the text of a box and its sides.
+const BOX_PADDING: i64 = 10;
+/// The space around a diagram, and the length of its entry and exit lines.
+const MARGIN: i64 = 20;
+
+const STYLE: &str = "path { stroke: #333; stroke-width: 2; fill: none; } \
+rect { stroke: #333; stroke-width: 2; fill: #ffd; } \
+rect.rule { fill: #def; } \
+text { font: 14px monospace; text-anchor: middle; }";
+
+/// Renders a [`Grammar`] as railroad diagrams in SVG.
+///
+/// Each rule gets a diagram, where tokens are drawn in rounded boxes and
+/// references to rules in square boxes linking to the diagram of the rule.
+/// Links go to `#name` by default, the id of the diagram of rule `name`, so
+/// they work between the diagrams of a page such as the one written by
+/// [`export_html`](RailroadExporter::export_html).
+///
+/// Diagrams are styled with an embedded style sheet and the classes
+/// `token` and `rule`, so documentation can restyle them.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::grammar::*;
+///
+/// let grammar = parse_ebnf(r#"
+///     list = "[" [item {"," item}] "]" ;
+///     item = Number | list ;
+/// "#).value;
+/// let exporter = RailroadExporter::new(&grammar).with_link_template("rules/{}.svg");
+/// let svg = exporter.rule_svg("list").unwrap();
+/// assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+/// assert!(svg.contains("<a href=\"rules/item.svg\">"));
+/// assert!(svg.contains("<text x=\"54\" y=\"37\">[</text>"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct RailroadExporter<'g> {
+    grammar: &'g Grammar,
+    link_template: String,
+}
+
+impl<'g> RailroadExporter<'g> {
+    pub fn new(grammar: &'g Grammar) -> Self {
+        RailroadExporter {
+            grammar,
+            link_template: "#{}".to_string(),
+        }
+    }
+
+    /// Sets where references to rules link, with `{}` standing for the
+    /// name of the rule, such as `{}.svg` for a file per rule.
+    pub fn with_link_template(mut self, template: impl Into<String>) -> Self {
+        self.link_template = template.into();
+        self
+    }
+
+    /// Renders the diagram of the rule `name`, if the grammar has it.
+    pub fn rule_svg(&self, name: &str) -> Option<String> {
+        let rule = self.grammar.get(name)?;
+        let diagram = self.node(&rule.expr);
+        let (width, up, down) = diagram.size();
+        let total_width = width + 4 * MARGIN;
+        let y = MARGIN + up;
+        let mut out = String::new();
+        let _ = write!(
+            out,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" id=\"{}\" class=\"railroad\" \
+             width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">",
+            escape(name),
+            total_width,
+            up + down + 2 * MARGIN,
+            total_width,
+            up + down + 2 * MARGIN
+        );
+        let _ = write!(out, "<style>{}</style>", STYLE);
+        // The entry and exit are marked with vertical bars.
+        let _ = write!(
+            out,
+            "<path d=\"M{} {}v20m0 -10h{}\"/>",
+            MARGIN,
+            y - 10,
+            MARGIN
+        );
+        self.draw(&mut out, &diagram, 2 * MARGIN, y, width);
+        let _ = write!(
+            out,
+            "<path d=\"M{} {}h{}m0 -10v20\"/>",
+            2 * MARGIN + width,
+            y,
+            MARGIN
+        );
+        out.push_str("</svg>");
+        Some(out)
+    }
+
+    /// Renders the diagrams of all rules, with their names, in grammar
+    /// order.
+    pub fn export(&self) -> Vec<(String, String)> {
+        self.grammar
+            .rules()
+            .iter()
+            .filter_map(|rule| Some((rule.name.clone(), self.rule_svg(&rule.name)?)))
+            .collect()
+    }
+
+    /// Writes an HTML page with the diagrams of all rules under headings,
+    /// for the default links.
+    pub fn export_html(&self, title: &str) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "<!DOCTYPE html>");
+        let _ = writeln!(out, "<html>");
+        let _ = writeln!(out, "<head>");
+        let _ = writeln!(out, "<meta charset=\"utf-8\">");
+        let _ = writeln!(out, "<title>{}</title>", escape(title));
+        let _ = writeln!(out, "</head>");
+        let _ = writeln!(out, "<body>");
+        let _ = writeln!(out, "<h1>{}</h1>", escape(title));
+        for (name, svg) in self.export() {
+            let _ = writeln!(out, "<h2>{}</h2>", escape(&name));
+            let _ = writeln!(out, "{}", svg);
+        }
+        let _ = writeln!(out, "</body>");
+        let _ = writeln!(out, "</html>");
+        out
+    }
+
+    fn node(&self, expr: &Expr) -> Node {
+        match expr {
+            Expr::Token(kind) => Node::Token(unquote(kind).to_string()),
+            Expr::Rule(name) => Node::Rule(name.clone()),
+            Expr::Seq(exprs) if exprs.is_empty() => Node::Skip,
+            Expr::Seq(exprs) => Node::Seq(exprs.iter().map(|expr| self.node(expr)).collect()),
+            Expr::Choice(alternatives) if alternatives.is_empty() => Node::Skip,
+            Expr::Choice(alternatives) => Node::Choice(
+                alternatives
+                    .iter()
+                    .map(|alternative| self.node(alternative))
+                    .collect(),
+            ),
+            Expr::Optional(expr) => Node::Choice(vec![Node::Skip, self.node(expr)]),
+            Expr::Repeat(expr) => {
+                Node::Choice(vec![Node::Skip, Node::Loop(Box::new(self.node(expr)))])
+            }
+            Expr::Repeat1(expr) => Node::Loop(Box::new(self.node(expr))),
+        }
+    }
+
+    /// Draws `node` with its line entering at (`x`, `y`), stretched to
+    /// `width`.
+    fn draw(&self, out: &mut String, node: &Node, x: i64, y: i64, width: i64) {
+        let (own_width, _, _) = node.size();
+        match node {
+            Node::Skip => {}
+            Node::Token(text) | Node::Rule(text) => {
+                let is_rule = matches!(node, Node::Rule(_));
+                let linked = is_rule && self.grammar.get(text).is_some();
+                if linked {
+                    let _ = write!(
+                        out,
+                        "<a href=\"{}\">",
+                        escape(&self.link_template.replace("{}", text))
+                    );
+                }
+                let _ = write!(
+                    out,
+                    "<rect class=\"{}\" x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" rx=\"{}\"/>",
+                    if is_rule { "rule" } else { "token" },
+                    x,
+                    y - BOX_HEIGHT / 2,
+                    own_width,
+                    BOX_HEIGHT,
+                    if is_rule { 0 } else { BOX_HEIGHT / 2 }
+                );
+                let _ = write!(
+                    out,
+                    "<text x=\"{}\" y=\"{}\">{}</text>",
+                    x + own_width / 2,
+                    y + 5,
+                    escape(text)
+                );
+                if linked {
+                    out.push_str("</a>");
+                }
+            }
+            Node::Seq(items) => {
+                let mut cursor = x;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        line(out, cursor, y, H_GAP);
+                        cursor += H_GAP;
+                    }
+                    let (item_width, _, _) = item.size();
+                    self.draw(out, item, cursor, y, item_width);
+                    cursor += item_width;
+                }
+            }
+            Node::Choice(alternatives) => {
+                let inner = width - 4 * ARC;
+                line(out, x, y, 2 * ARC);
+                self.stretch(out, &alternatives[0], x + 2 * ARC, y, inner);
+                line(out, x + width - 2 * ARC, y, 2 * ARC);
+                for (alternative, baseline) in alternatives.iter().zip(node.baselines()).skip(1) {
+                    let drop = baseline - 2 * ARC;
+                    let _ = write!(
+                        out,
+                        "<path d=\"M{} {}a{r} {r} 0 0 1 {r} {r}v{}a{r} {r} 0 0 0 {r} {r}\"/>",
+                        x,
+                        y,
+                        drop,
+                        r = ARC
+                    );
+                    self.stretch(out, alternative, x + 2 * ARC, y + baseline, inner);
+                    let _ = write!(
+                        out,
+                        "<path d=\"M{} {}a{r} {r} 0 0 0 {r} -{r}v-{}a{r} {r} 0 0 1 {r} -{r}\"/>",
+                        x + width - 2 * ARC,
+                        y + baseline,
+                        drop,
+                        r = ARC
+                    );
+                }
+            }
+            Node::Loop(item) => {
+                let (_, _, item_down) = item.size();
+                let back = (item_down + V_GAP).max(2 * ARC);
+                line(out, x, y, ARC);
+                self.stretch(out, item, x + ARC, y, width - 2 * ARC);
+                line(out, x + width - ARC, y, ARC);
+                let _ = write!(
+                    out,
+                    "<path d=\"M{} {}a{r} {r} 0 0 1 {r} {r}v{}a{r} {r} 0 0 1 -{r} {r}h-{}\
+                     a{r} {r} 0 0 1 -{r} -{r}v-{}a{r} {r} 0 0 1 {r} -{r}\"/>",
+                    x + width - ARC,
+                    y,
+                    back - 2 * ARC,
+                    width - 2 * ARC,
+                    back - 2 * ARC,
+                    r = ARC
+                );
+            }
+        }
+    }
+
+    /// Draws `node` at its own width, followed by a line up to `width`.
+    fn stretch(&self, out: &mut String, node: &Node, x: i64, y: i64, width: i64) {
+        let (own_width, _, _) = node.size();
+        if matches!(node, Node::Choice(_) | Node::Loop(_)) {
+            self.draw(out, node, x, y, width);
+        } else {
+            self.draw(out, node, x, y, own_width);
+            line(out, x + own_width, y, width - own_width);
+        }
+    }
+}
+
+/// The layout of an expression.
+enum Node {
+    Skip,
+    Token(String),
+    Rule(String),
+    Seq(Vec<Node>),
+    Choice(Vec<Node>),
+    /// One or more times.
+    Loop(Box<Node>),
+}
+
+impl Node {
+    /// Returns the width, and the heights above and below the line through
+    /// the node.
+    fn size(&self) -> (i64, i64, i64) {
+        match self {
+            Node::Skip => (0, 0, 0),
+            Node::Token(text) | Node::Rule(text) => (
+                text.chars().count() as i64 * CHAR_WIDTH + 2 * BOX_PADDING,
+                BOX_HEIGHT / 2,
+                BOX_HEIGHT / 2,
+            ),
+            Node::Seq(items) => {
+                let mut size = (H_GAP * (items.len() as i64 - 1).max(0), 0, 0);
+                for item in items {
+                    let (width, up, down) = item.size();
+                    size = (size.0 + width, size.1.max(up), size.2.max(down));
+                }
+                size
+            }
+            Node::Choice(alternatives) => {
+                let width = alternatives
+                    .iter()
+                    .map(|alternative| alternative.size().0)
+                    .max()
+                    .unwrap_or(0);
+                let (_, up, first_down) = alternatives[0].size();
+                let down = match (alternatives.last(), self.baselines().last()) {
+                    (Some(last), Some(&baseline)) if alternatives.len() > 1 => {
+                        baseline + last.size().2
+                    }
+                    _ => first_down,
+                };
+                (width + 4 * ARC, up, down)
+            }
+            Node::Loop(item) => {
+                let (width, up, down) = item.size();
+                (width + 2 * ARC, up, (down + V_GAP).max(2 * ARC))
+            }
+        }
+    }
+
+    /// Returns the offsets of the lines of the alternatives of a choice
+    /// below its own line.
+    fn baselines(&self) -> Vec<i64> {
+        let Node::Choice(alternatives) = self else {
+            return vec![0];
+        };
+        let mut baselines = vec![0];
+        let mut bottom = alternatives[0].size().2;
+        for alternative in &alternatives[1..] {
+            let (_, up, down) = alternative.size();
+            let baseline = (bottom + V_GAP + up).max(2 * ARC);
+            baselines.push(baseline);
+            bottom = baseline + down;
+        }
+        baselines
+    }
+}
+
+fn line(out: &mut String, x: i64, y: i64, length: i64) {
+    if length > 0 {
+        let _ = write!(out, "<path d=\"M{} {}h{}\"/>", x, y, length);
+    }
+}
+
+/// Removes the quotes around literal token kinds, such as `"+"`.
+fn unquote(kind: &str) -> &str {
+    for quote in ['"', '\''] {
+        if let Some(inner) = kind
+            .strip_prefix(quote)
+            .and_then(|rest| rest.strip_suffix(quote))
+        {
+            if !inner.is_empty() {
+                return inner;
+            }
+        }
+    }
+    kind
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::parse_ebnf;
+
+    #[test]
+    fn test_layout() {
+        let grammar = parse_ebnf("a = X | \"<=\" Y ; b = X+ ;").value;
+        let exporter = RailroadExporter::new(&grammar);
+        let choice = exporter.node(&grammar.get("a").unwrap().expr);
+        // `X` is 29 wide, `<= Y` 38 + 10 + 29.
+        assert_eq!(choice.size(), (77 + 4 * ARC, 12, 46));
+        assert_eq!(choice.baselines(), [0, 34]);
+
+        let svg = exporter.rule_svg("a").unwrap();
+        assert!(svg.contains("<text x=\"79\" y=\"71\">&lt;=</text>"));
+        let svg = exporter.rule_svg("b").unwrap();
+        assert!(svg.contains(
+            "<path d=\"M79 32a10 10 0 0 1 10 10v2a10 10 0 0 1 -10 10h-29\
+             a10 10 0 0 1 -10 -10v-2a10 10 0 0 1 10 -10\"/>"
+        ));
+        assert_eq!(exporter.rule_svg("c"), None);
+    }
+
+    #[test]
+    fn test_links_and_html() {
+        let grammar = parse_ebnf("a = b c ; b = X ;").value;
+        let html = RailroadExporter::new(&grammar).export_html("A & B");
+        assert!(html.contains("<title>A &amp; B</title>"));
+        assert!(html.contains("<h2>b</h2>\n<svg xmlns=\"http://www.w3.org/2000/svg\" id=\"b\""));
+        assert!(html.contains("<a href=\"#b\"><rect class=\"rule\""));
+        // `c` is undefined, so it is not linked.
+        assert!(!html.contains("href=\"#c\""));
+        assert!(html.contains(">c</text>"));
+    }
+}
@@ -0,0 +1,509 @@
+use super::{Analysis, Expr, Grammar, Rule};
+use crate::diagnostics::Diagnostic;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+
+/// A cycle of rules, each of which can start with the next, so that a
+/// recursive-descent parser for the first would call itself without
+/// consuming any token.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LeftRecursion {
+    /// The rules of the cycle, starting with the first one in grammar
+    /// order. The last rule can start with the first.
+    pub cycle: Vec<String>,
+}
+
+impl LeftRecursion {
+    /// Returns whether the rule can start with itself directly.
+    pub fn is_direct(&self) -> bool {
+        self.cycle.len() == 1
+    }
+
+    /// Converts the cycle into an error pointing at the rules of the cycle,
+    /// if `grammar` was read from a source.
+    pub fn to_diagnostic(&self, grammar: &Grammar) -> Diagnostic {
+        let mut diagnostic =
+            Diagnostic::error(format!("rule `{}` is left-recursive", self.cycle[0]));
+        for (i, name) in self.cycle.iter().enumerate() {
+            let next = &self.cycle[(i + 1) % self.cycle.len()];
+            let Some(span) = grammar.get(name).and_then(|rule| rule.span) else {
+                continue;
+            };
+            let message = if next == name {
+                format!("`{}` can start with itself", name)
+            } else {
+                format!("`{}` can start with `{}`", name, next)
+            };
+            diagnostic = if i == 0 {
+                diagnostic.with_label(span, message)
+            } else {
+                diagnostic.with_secondary_label(span, message)
+            };
+        }
+        diagnostic.with_note(format!(
+            "a recursive-descent parser would loop forever on {}",
+            self
+        ))
+    }
+}
+
+impl fmt::Display for LeftRecursion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for name in &self.cycle {
+            write!(f, "{} -> ", name)?;
+        }
+        write!(f, "{}", self.cycle[0])
+    }
+}
+
+/// An error eliminating left recursion from a grammar.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LeftRecursionError {
+    /// Every alternative of a left-recursive rule starts with the rule
+    /// itself, so it matches nothing.
+    NoBase { rule: String },
+    /// Left recursion remains that the transform cannot remove, such as
+    /// behind a nullable expression or inside a repetition.
+    Remaining(Vec<LeftRecursion>),
+}
+
+impl fmt::Display for LeftRecursionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LeftRecursionError::NoBase { rule } => write!(
+                f,
+                "every alternative of rule `{}` starts with `{}`",
+                rule, rule
+            ),
+            LeftRecursionError::Remaining(cycles) => {
+                write!(f, "cannot eliminate left recursion")?;
+                for (i, cycle) in cycles.iter().enumerate() {
+                    write!(f, "{} {}", if i == 0 { ":" } else { "," }, cycle)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for LeftRecursionError {}
+
+impl Analysis {
+    /// Finds the left recursion of `grammar`, the grammar this analysis was
+    /// computed for, as one shortest cycle per group of rules that can
+    /// start with each other.
+    ///
+    /// # Examples
+    /// ```
+    /// use grammarsmith::grammar::*;
+    ///
+    /// let grammar = parse_ebnf("
+    ///     expr = call | Number ;
+    ///     call = expr LParen RParen ;
+    /// ").value;
+    /// let cycles = grammar.analyze().left_recursion(&grammar);
+    /// assert_eq!(cycles.len(), 1);
+    /// assert_eq!(cycles[0].to_string(), "expr -> call -> expr");
+    /// ```
+    pub fn left_recursion(&self, grammar: &Grammar) -> Vec<LeftRecursion> {
+        let edges = self.left_corner_edges(grammar);
+        let mut cycles = Vec::new();
+        let mut reported: HashSet<&str> = HashSet::new();
+        for rule in grammar.rules() {
+            let name = rule.name.as_str();
+            if reported.contains(name) {
+                continue;
+            }
+            let Some(cycle) = shortest_cycle(&edges, name) else {
+                continue;
+            };
+            let reachable = reachable(&edges, name);
+            for other in &reachable {
+                if reachable_from(&edges, other, name) {
+                    reported.insert(other);
+                }
+            }
+            cycles.push(LeftRecursion {
+                cycle: cycle.into_iter().map(str::to_string).collect(),
+            });
+        }
+        cycles
+    }
+
+    /// Returns the rules each rule can start with.
+    fn left_corner_edges<'g>(&self, grammar: &'g Grammar) -> HashMap<&'g str, Vec<&'g str>> {
+        grammar
+            .rules()
+            .iter()
+            .map(|rule| {
+                let mut corners = Vec::new();
+                self.left_corners(&rule.expr, &mut corners);
+                (rule.name.as_str(), corners)
+            })
+            .collect()
+    }
+
+    /// Pushes the rules `expr` can start with.
+    fn left_corners<'g>(&self, expr: &'g Expr, corners: &mut Vec<&'g str>) {
+        match expr {
+            Expr::Token(_) => {}
+            Expr::Rule(name) => {
+                if !corners.contains(&name.as_str()) {
+                    corners.push(name);
+                }
+            }
+            Expr::Seq(exprs) => {
+                for expr in exprs {
+                    self.left_corners(expr, corners);
+                    if !self.is_nullable_expr(expr) {
+                        break;
+                    }
+                }
+            }
+            Expr::Choice(alternatives) => {
+                for alternative in alternatives {
+                    self.left_corners(alternative, corners);
+                }
+            }
+            Expr::Optional(expr) | Expr::Repeat(expr) | Expr::Repeat1(expr) => {
+                self.left_corners(expr, corners)
+            }
+        }
+    }
+}
+
+/// Returns the rules reachable from `from` in one step or more.
+fn reachable<'g>(edges: &HashMap<&'g str, Vec<&'g str>>, from: &'g str) -> HashSet<&'g str> {
+    let mut seen = HashSet::new();
+    let mut stack = vec![from];
+    while let Some(name) = stack.pop() {
+        for &next in edges.get(name).into_iter().flatten() {
+            if seen.insert(next) {
+                stack.push(next);
+            }
+        }
+    }
+    seen
+}
+
+fn reachable_from(edges: &HashMap<&str, Vec<&str>>, from: &str, to: &str) -> bool {
+    reachable(edges, from).contains(to)
+}
+
+/// Returns the shortest path from `start` back to itself, without
+/// repeating `start` at the end.
+fn shortest_cycle<'g>(
+    edges: &HashMap<&'g str, Vec<&'g str>>,
+    start: &'g str,
+) -> Option<Vec<&'g str>> {
+    let mut parents: HashMap<&str, &str> = HashMap::new();
+    let mut queue = VecDeque::from([start]);
+    while let Some(name) = queue.pop_front() {
+        for &next in edges.get(name).into_iter().flatten() {
+            if next == start {
+                let mut cycle = vec![name];
+                while let Some(&parent) = parents.get(cycle[cycle.len() - 1]) {
+                    cycle.push(parent);
+                }
+                cycle.reverse();
+                return Some(cycle);
+            }
+            if !parents.contains_key(next) {
+                parents.insert(next, name);
+                queue.push_back(next);
+            }
+        }
+    }
+    None
+}
+
+impl Grammar {
+    /// Reports the left recursion of the grammar as errors.
+    pub fn check_left_recursion(&self) -> Vec<Diagnostic> {
+        self.analyze()
+            .left_recursion(self)
+            .iter()
+            .map(|cycle| cycle.to_diagnostic(self))
+            .collect()
+    }
+
+    /// Returns an equivalent grammar without left recursion.
+    ///
+    /// The rules of each left-recursive group are rewritten in grammar
+    /// order: references to earlier rules of the group at the start of an
+    /// alternative are replaced by the alternatives of those rules, then a
+    /// rule `a = a x | a y | b | c` becomes `a = (b | c) (x | y)*`. Other
+    /// rules are left as they are.
+    ///
+    /// The new grammar matches the same token sequences, but into different
+    /// trees: the repetitions are left-associative where the recursion
+    /// was. Rewritten rules keep their span and error message but lose
+    /// their expression spans, and the imports, precedence levels and token definitions are
+    /// kept as they are.
+    ///
+    /// # Examples
+    /// ```
+    /// use grammarsmith::grammar::*;
+    ///
+    /// let grammar = parse_ebnf("
+    ///     sum  = sum Plus term | sum Minus term | term ;
+    ///     term = Number ;
+    /// ").value;
+    /// let grammar = grammar.eliminate_left_recursion().unwrap();
+    /// assert_eq!(
+    ///     grammar.to_string(),
+    ///     "sum = term (Plus term | Minus term)* ;\nterm = Number ;\n"
+    /// );
+    /// ```
+    pub fn eliminate_left_recursion(&self) -> Result<Grammar, LeftRecursionError> {
+        let analysis = self.analyze();
+        let cycles = analysis.left_recursion(self);
+        if cycles.is_empty() {
+            return Ok(self.clone());
+        }
+        let edges = analysis.left_corner_edges(self);
+        let recursive: HashSet<&str> = self
+            .rules()
+            .iter()
+            .map(|rule| rule.name.as_str())
+            .filter(|name| reachable_from(&edges, name, name))
+            .collect();
+
+        let mut rewritten: HashMap<String, Vec<Vec<Expr>>> = HashMap::new();
+        let mut done: Vec<&str> = Vec::new();
+        for rule in self.rules() {
+            let name = rule.name.as_str();
+            if !recursive.contains(name) {
+                continue;
+            }
+            let mut alternatives = split_alternatives(&rule.expr);
+            for earlier in &done {
+                if !reachable_from(&edges, earlier, name) || !reachable_from(&edges, name, earlier)
+                {
+                    continue;
+                }
+                let substitutes = &rewritten[*earlier];
+                alternatives = alternatives
+                    .into_iter()
+                    .flat_map(|alternative| match alternative.first() {
+                        Some(Expr::Rule(first)) if first == earlier => substitutes
+                            .iter()
+                            .flat_map(|substitute| {
+                                let mut exprs = substitute.clone();
+                                exprs.extend(alternative[1..].iter().cloned());
+                                split_alternatives(&Expr::Seq(exprs))
+                            })
+                            .collect(),
+                        _ => vec![alternative],
+                    })
+                    .collect();
+            }
+            rewritten.insert(name.to_string(), eliminate_direct(name, alternatives)?);
+            done.push(name);
+        }
+
+        let mut grammar = Grammar::new();
+        for import in self.imports() {
+            grammar.add_import(import.clone());
+        }
+        for rule in self.rules() {
+            match rewritten.get(&rule.name) {
+                Some(alternatives) => {
+                    let mut new = Rule::new(rule.name.clone(), choice(alternatives.clone()));
+                    new.span = rule.span;
+                    new.error = rule.error.clone();
+                    grammar.add(new);
+                }
+                None => grammar.add(rule.clone()),
+            }
+        }
+        for level in self.precedence() {
+            grammar.add_precedence(level.clone());
+        }
+        for def in self.token_defs() {
+            grammar.add_token_def(def.clone());
+        }
+        let remaining = grammar.analyze().left_recursion(&grammar);
+        if remaining.is_empty() {
+            Ok(grammar)
+        } else {
+            Err(LeftRecursionError::Remaining(remaining))
+        }
+    }
+}
+
+/// Rewrites `name = name x | name y | b | c` as `name = (b | c) (x | y)*`,
+/// returned as its single alternative.
+fn eliminate_direct(
+    name: &str,
+    alternatives: Vec<Vec<Expr>>,
+) -> Result<Vec<Vec<Expr>>, LeftRecursionError> {
+    let mut bases = Vec::new();
+    let mut tails = Vec::new();
+    for mut alternative in alternatives {
+        match alternative.first() {
+            Some(Expr::Rule(first)) if first == name => {
+                alternative.remove(0);
+                // `name = name` adds nothing to the language.
+                if !alternative.is_empty() {
+                    tails.push(alternative);
+                }
+            }
+            _ => bases.push(alternative),
+        }
+    }
+    if tails.is_empty() {
+        return Ok(bases);
+    }
+    if bases.is_empty() {
+        return Err(LeftRecursionError::NoBase {
+            rule: name.to_string(),
+        });
+    }
+    let mut exprs = match choice(bases) {
+        Expr::Seq(exprs) => exprs,
+        base => vec![base],
+    };
+    exprs.push(Expr::repeat(choice(tails)));
+    Ok(vec![exprs])
+}
+
+/// Splits `expr` into alternatives, each a sequence not starting with a
+/// choice or a sequence.
+pub(super) fn split_alternatives(expr: &Expr) -> Vec<Vec<Expr>> {
+    match expr {
+        Expr::Choice(alternatives) => alternatives.iter().flat_map(split_alternatives).collect(),
+        Expr::Seq(exprs) => match exprs.split_first() {
+            None => vec![Vec::new()],
+            Some((first, rest)) => split_alternatives(first)
+                .into_iter()
+                .flat_map(|mut alternative| {
+                    if alternative.is_empty() {
+                        split_alternatives(&Expr::Seq(rest.to_vec()))
+                    } else {
+                        alternative.extend(rest.iter().cloned());
+                        vec![alternative]
+                    }
+                })
+                .collect(),
+        },
+        expr => vec![vec![expr.clone()]],
+    }
+}
+
+/// Joins alternatives back into an expression, without wrapping single
+/// alternatives and expressions.
+pub(super) fn choice(alternatives: Vec<Vec<Expr>>) -> Expr {
+    let mut alternatives: Vec<Expr> = alternatives
+        .into_iter()
+        .map(|mut exprs| {
+            if exprs.len() == 1 {
+                exprs.pop().unwrap()
+            } else {
+                Expr::Seq(exprs)
+            }
+        })
+        .collect();
+    if alternatives.len() == 1 {
+        alternatives.pop().unwrap()
+    } else {
+        Expr::Choice(alternatives)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::parse_ebnf;
+
+    #[test]
+    fn test_detection() {
+        let source = "
+            a = b X | Y ;
+            b = c? a ;
+            c = Z ;
+            d = d d | W ;
+            e = Y e ;
+        ";
+        let grammar = parse_ebnf(source).value;
+        let cycles = grammar.analyze().left_recursion(&grammar);
+        let cycles: Vec<_> = cycles.iter().map(ToString::to_string).collect();
+        assert_eq!(cycles, ["a -> b -> a", "d -> d"]);
+
+        let diagnostics = grammar.check_left_recursion();
+        assert_eq!(diagnostics[0].message, "rule `a` is left-recursive");
+        assert_eq!(diagnostics[0].labels.len(), 2);
+        assert_eq!(diagnostics[0].labels[1].message, "`b` can start with `a`");
+        assert_eq!(
+            diagnostics[1].labels[0].message,
+            "`d` can start with itself"
+        );
+    }
+
+    #[test]
+    fn test_elimination() {
+        let grammar = parse_ebnf(
+            "
+            expr = call | Id ;
+            call = expr LParen RParen | expr Dot Id ;
+            list = Item | () ;
+            ",
+        )
+        .value;
+        let eliminated = grammar.eliminate_left_recursion().unwrap();
+        assert_eq!(
+            eliminated.to_string(),
+            "expr = call | Id ;\n\
+             call = (Id LParen RParen | Id Dot Id) (LParen RParen | Dot Id)* ;\n\
+             list = Item | () ;\n"
+        );
+        assert!(eliminated.check_left_recursion().is_empty());
+        assert_eq!(
+            eliminated.get("call").unwrap().span,
+            grammar.get("call").unwrap().span
+        );
+
+        let error = parse_ebnf("a = a X ;").value.eliminate_left_recursion();
+        assert_eq!(
+            error,
+            Err(LeftRecursionError::NoBase {
+                rule: "a".to_string()
+            })
+        );
+        let error = parse_ebnf("a = b? a X | Y ; b = Z ;")
+            .value
+            .eliminate_left_recursion()
+            .unwrap_err();
+        assert_eq!(error.to_string(), "cannot eliminate left recursion: a -> a");
+    }
+
+    #[test]
+    fn test_elimination_keeps_declarations() {
+        let grammar = parse_ebnf(
+            r#"
+            import "lexical" ;
+            sum = sum "+" Number | Number ;
+            %left "+" ;
+            token Number = /\d+/ ;
+            skip Space = /\s+/ ;
+            "#,
+        )
+        .value;
+        let eliminated = grammar.eliminate_left_recursion().unwrap();
+        assert_eq!(eliminated.imports(), grammar.imports());
+        assert_eq!(eliminated.precedence(), grammar.precedence());
+        assert_eq!(eliminated.token_defs(), grammar.token_defs());
+
+        let lexer = crate::grammar::Lexer::new(&eliminated).unwrap();
+        let tokens = lexer.tokenize("1 + 2").value;
+        assert_eq!(tokens.len(), 3);
+    }
+
+    #[test]
+    fn test_elimination_keeps_error_messages() {
+        let grammar = parse_ebnf(r#"sum = sum "+" N | N on_error("expected a sum") ;"#).value;
+        let eliminated = grammar.eliminate_left_recursion().unwrap();
+        let error = eliminated.get("sum").unwrap().error.as_ref().unwrap();
+        assert_eq!(error.message, "expected a sum");
+    }
+}
@@ -0,0 +1,411 @@
+use super::table::{Bnf, Symbol};
+use super::{Grammar, Lexer, LexerError, TokenId};
+use crate::diagnostics::{expected_list, Diagnostic};
+use crate::parser::ParseOutput;
+use crate::position::Span;
+use crate::tree::ParseTree;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// An Earley item: a production, how many of its symbols are matched, and
+/// the byte offset its match starts at.
+type Item = (usize, usize, usize);
+
+/// A parser whose rules match characters, with no tokenizer in between.
+///
+/// The token kinds of the rules are matched in the text where the rules
+/// expect them, and nowhere else: a literal such as `"<"` matches its text,
+/// and another kind the pattern of its [`TokenDef`](super::TokenDef), taking
+/// the longest match. So a token kind can be matched only in some contexts,
+/// patterns can overlap without a lexer having to choose between them, and
+/// whitespace only counts where the rules ask for it. Skip definitions are
+/// not dropped between tokens, but can be used by the rules like any token
+/// kind.
+///
+/// Like [`EarleyParser`](super::EarleyParser), it takes any grammar, with
+/// left recursion and ambiguities, and parses into the first of its trees,
+/// where tokens are named by their kinds and span the text they match.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::grammar::*;
+/// use grammarsmith::tree::ParseTree;
+///
+/// let grammar = parse_ebnf(r#"
+///     doc  = (Text | tag)* ;
+///     tag  = "<" Name ">" doc "</" Name ">" ;
+///     token Name = /[a-z]+/ ;
+///     token Text = /[^<]+/ ;
+/// "#).value;
+/// let parser = ScannerlessParser::new(&grammar).unwrap();
+///
+/// let tree = parser.parse("see <b>bold</b>!").value.unwrap();
+/// let ParseTree::Node { children, .. } = &tree else { unreachable!() };
+/// let kinds: Vec<&str> = children
+///     .iter()
+///     .map(|child| match child {
+///         ParseTree::Token { kind, .. } | ParseTree::Node { kind, .. } => kind.as_str(),
+///     })
+///     .collect();
+/// assert_eq!(kinds, ["Text", "tag", "Text"]);
+///
+/// let error = &parser.parse("<b>bold</b").diagnostics[0];
+/// assert_eq!(error.message, "expected \">\", found end of input");
+/// ```
+#[derive(Debug, Clone)]
+pub struct ScannerlessParser {
+    bnf: Bnf,
+    /// The indices of the productions of each rule.
+    by_rule: Vec<Vec<usize>>,
+    nullable: Vec<bool>,
+    lexer: Lexer,
+    /// The definition matching each token kind.
+    kinds: Vec<TokenId>,
+}
+
+impl ScannerlessParser {
+    /// Creates a parser for the grammar, whose token kinds must be literals
+    /// or defined, as for a [`Lexer`].
+    pub fn new(grammar: &Grammar) -> Result<Self, LexerError> {
+        let lexer = Lexer::new(grammar)?;
+        let bnf = Bnf::new(grammar);
+        let kinds = bnf
+            .tokens
+            .iter()
+            .map(|token| lexer.kind(token).unwrap())
+            .collect();
+        let mut by_rule = vec![Vec::new(); bnf.rules.len()];
+        for (index, production) in bnf.productions.iter().enumerate() {
+            by_rule[production.rule].push(index);
+        }
+        let analysis = bnf.plain.analyze();
+        let nullable = bnf
+            .rules
+            .iter()
+            .map(|rule| analysis.is_nullable(rule))
+            .collect();
+        Ok(ScannerlessParser {
+            bnf,
+            by_rule,
+            nullable,
+            lexer,
+            kinds,
+        })
+    }
+
+    /// Parses `source` from the start rule.
+    ///
+    /// # Returns
+    /// The first tree of the whole source, or `None` with an error at the
+    /// furthest offset a parse gets to.
+    pub fn parse(&self, source: &str) -> ParseOutput<Option<ParseTree<String>>> {
+        let mut run = Run {
+            parser: self,
+            source,
+            matches: HashMap::new(),
+            chart: vec![HashSet::new(); source.len() + 1],
+            trees: HashMap::new(),
+            building: HashSet::new(),
+        };
+        if let Some(error) = run.recognize() {
+            return ParseOutput::new(None, vec![error]);
+        }
+        let tree = run
+            .node(0, 0, source.len())
+            .and_then(|mut trees| trees.pop());
+        ParseOutput::new(tree, Vec::new())
+    }
+}
+
+impl Grammar {
+    /// Returns a [`ScannerlessParser`] for the grammar.
+    pub fn scannerless_parser(&self) -> Result<ScannerlessParser, LexerError> {
+        ScannerlessParser::new(self)
+    }
+}
+
+/// The state of a parse.
+struct Run<'a> {
+    parser: &'a ScannerlessParser,
+    source: &'a str,
+    /// The length of the match of each token kind at each offset.
+    matches: HashMap<(usize, usize), Option<usize>>,
+    /// The items of each offset.
+    chart: Vec<HashSet<Item>>,
+    /// The trees of the matches of rules, as the trees of the rule or, for
+    /// auxiliary rules, of their children.
+    trees: HashMap<(usize, usize, usize), Vec<ParseTree<String>>>,
+    /// The matches whose tree is being built.
+    building: HashSet<(usize, usize, usize)>,
+}
+
+impl Run<'_> {
+    /// Returns the length of the match of token kind `kind` at `offset`.
+    fn match_at(&mut self, kind: usize, offset: usize) -> Option<usize> {
+        let (parser, source) = (self.parser, self.source);
+        *self.matches.entry((kind, offset)).or_insert_with(|| {
+            let text = source.get(offset..)?;
+            parser.lexer.match_kind(parser.kinds[kind], text)
+        })
+    }
+
+    /// Fills in the chart, returning an error at the furthest offset with
+    /// items if the start rule does not match the whole source.
+    fn recognize(&mut self) -> Option<Diagnostic> {
+        let parser = self.parser;
+        let productions = &parser.bnf.productions;
+        let mut sets: Vec<Vec<Item>> = vec![Vec::new(); self.source.len() + 1];
+        fn add(sets: &mut [Vec<Item>], chart: &mut [HashSet<Item>], offset: usize, item: Item) {
+            if chart[offset].insert(item) {
+                sets[offset].push(item);
+            }
+        }
+        for &production in parser.by_rule.first().into_iter().flatten() {
+            add(&mut sets, &mut self.chart, 0, (production, 0, 0));
+        }
+        let mut furthest = 0;
+        for offset in 0..=self.source.len() {
+            if !sets[offset].is_empty() {
+                furthest = offset;
+            }
+            let mut next = 0;
+            while let Some(&(production, dot, origin)) = sets[offset].get(next) {
+                next += 1;
+                let rule = productions[production].rule;
+                match productions[production].symbols.get(dot) {
+                    None => {
+                        for index in 0..sets[origin].len() {
+                            let (waiting, at, from) = sets[origin][index];
+                            if productions[waiting].symbols.get(at) == Some(&Symbol::Rule(rule)) {
+                                add(&mut sets, &mut self.chart, offset, (waiting, at + 1, from));
+                            }
+                        }
+                    }
+                    Some(&Symbol::Rule(called)) => {
+                        for &predicted in &parser.by_rule[called] {
+                            add(&mut sets, &mut self.chart, offset, (predicted, 0, offset));
+                        }
+                        // A rule that can match nothing is completed as soon
+                        // as it is predicted.
+                        if parser.nullable[called] {
+                            add(
+                                &mut sets,
+                                &mut self.chart,
+                                offset,
+                                (production, dot + 1, origin),
+                            );
+                        }
+                    }
+                    Some(&Symbol::Token(kind)) => {
+                        if let Some(len) = self.match_at(kind, offset) {
+                            let item = (production, dot + 1, origin);
+                            add(&mut sets, &mut self.chart, offset + len, item);
+                        }
+                    }
+                }
+            }
+        }
+        if self.completes(0, 0, self.source.len()) {
+            return None;
+        }
+        let mut expected: BTreeSet<Option<usize>> = BTreeSet::new();
+        for &(production, dot, origin) in &sets[furthest] {
+            let production = &productions[production];
+            match production.symbols.get(dot) {
+                Some(&Symbol::Token(kind)) => {
+                    expected.insert(Some(kind));
+                }
+                None if production.rule == 0 && origin == 0 => {
+                    expected.insert(None);
+                }
+                _ => {}
+            }
+        }
+        let name = |token: Option<usize>| match token {
+            Some(token) => parser.bnf.tokens[token].as_str(),
+            None => "end of input",
+        };
+        let (found, span) = match self.source[furthest..].chars().next() {
+            Some(c) => (
+                format!("`{}`", c),
+                Span::new_unchecked(furthest, furthest + c.len_utf8()),
+            ),
+            None => ("end of input".to_string(), Span::point(furthest)),
+        };
+        Some(
+            Diagnostic::error(format!(
+                "expected {}, found {}",
+                expected_list(expected.iter().map(|&token| name(token))),
+                found
+            ))
+            .with_label(span, ""),
+        )
+    }
+
+    /// Returns whether `rule` matches from `start` to `end`.
+    fn completes(&self, rule: usize, start: usize, end: usize) -> bool {
+        self.parser.by_rule[rule].iter().any(|&production| {
+            let len = self.parser.bnf.productions[production].symbols.len();
+            self.chart[end].contains(&(production, len, start))
+        })
+    }
+
+    /// Returns the trees of a match of `rule` from `start` to `end`, the
+    /// chart has: one tree, or the children of an auxiliary rule.
+    fn node(&mut self, rule: usize, start: usize, end: usize) -> Option<Vec<ParseTree<String>>> {
+        let key = (rule, start, end);
+        if let Some(trees) = self.trees.get(&key) {
+            return Some(trees.clone());
+        }
+        // A match that is part of itself goes round a cycle of rules.
+        if !self.building.insert(key) {
+            return None;
+        }
+        let bnf = &self.parser.bnf;
+        let mut children = None;
+        for &production in &self.parser.by_rule[rule] {
+            let len = bnf.productions[production].symbols.len();
+            if self.chart[end].contains(&(production, len, start)) {
+                children = self.split(production, len, start, end);
+                if children.is_some() {
+                    break;
+                }
+            }
+        }
+        self.building.remove(&key);
+        let children = children?;
+        let trees = if bnf.origins[rule] == rule {
+            vec![ParseTree::Node {
+                kind: bnf.rules[rule].clone(),
+                span: Span::new_unchecked(start, end),
+                children,
+            }]
+        } else {
+            children
+        };
+        self.trees.insert(key, trees.clone());
+        Some(trees)
+    }
+
+    /// Returns the trees of the first `count` symbols of a production
+    /// matching from `start` to `end`.
+    fn split(
+        &mut self,
+        production: usize,
+        count: usize,
+        start: usize,
+        end: usize,
+    ) -> Option<Vec<ParseTree<String>>> {
+        if count == 0 {
+            return (start == end).then(Vec::new);
+        }
+        let before = (production, count - 1, start);
+        let symbol = self.parser.bnf.productions[production].symbols[count - 1];
+        for middle in (start..=end).rev() {
+            if !self.chart[middle].contains(&before) {
+                continue;
+            }
+            let trees = match symbol {
+                Symbol::Token(kind) => {
+                    if self.matches.get(&(kind, middle)) != Some(&Some(end - middle)) {
+                        continue;
+                    }
+                    vec![ParseTree::Token {
+                        kind: self.parser.bnf.tokens[kind].clone(),
+                        span: Span::new_unchecked(middle, end),
+                    }]
+                }
+                Symbol::Rule(rule) => {
+                    if !self.completes(rule, middle, end) {
+                        continue;
+                    }
+                    match self.node(rule, middle, end) {
+                        Some(trees) => trees,
+                        None => continue,
+                    }
+                }
+            };
+            if let Some(mut children) = self.split(production, count - 1, start, middle) {
+                children.extend(trees);
+                return Some(children);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::parse_ebnf;
+
+    /// Returns the tree written with parentheses around nodes, and tokens
+    /// as their text.
+    fn shape(tree: &ParseTree<String>, source: &str) -> String {
+        match tree {
+            ParseTree::Token { span, .. } => source[span.start()..span.end()].to_string(),
+            ParseTree::Node { children, .. } => {
+                let children: Vec<String> = children.iter().map(|c| shape(c, source)).collect();
+                format!("({})", children.join(" "))
+            }
+        }
+    }
+
+    #[test]
+    fn test_context_sensitive_tokens() {
+        // `Key` and `Value` overlap, and spaces only count around `=`.
+        let grammar = parse_ebnf(
+            r#"
+            config = (entry Newline)* ;
+            entry  = Key Space? "=" Space? Value | "[" Key "]" ;
+            token Key   = /[a-z]+/ ;
+            token Value = /[^\n]+/ ;
+            token Space = / +/ ;
+            token Newline = /\n/ ;
+            "#,
+        )
+        .value;
+        let parser = grammar.scannerless_parser().unwrap();
+        let source = "[main]\nname = a b\n";
+        let output = parser.parse(source);
+        assert!(output.diagnostics.is_empty());
+        assert_eq!(
+            shape(&output.value.unwrap(), source),
+            "(([ main ]) \n (name   =   a b) \n)"
+        );
+
+        let output = parser.parse("name=x\n [a]\n");
+        assert!(output.value.is_none());
+        assert_eq!(
+            output.diagnostics[0].message,
+            "expected end of input, Key, or \"[\", found ` `"
+        );
+        let span = output.diagnostics[0].primary_span().unwrap();
+        assert_eq!((span.start(), span.end()), (7, 8));
+    }
+
+    #[test]
+    fn test_left_recursion_and_errors() {
+        let grammar = parse_ebnf(
+            r#"
+            sum = sum "+" Digit | Digit ;
+            token Digit = /[0-9]/ ;
+            "#,
+        )
+        .value;
+        let parser = grammar.scannerless_parser().unwrap();
+        let source = "1+2+3";
+        assert_eq!(
+            shape(&parser.parse(source).value.unwrap(), source),
+            "(((1) + 2) + 3)"
+        );
+        assert!(parser.parse("").value.is_none());
+
+        let grammar = parse_ebnf("a = Word ;").value;
+        assert_eq!(
+            grammar.scannerless_parser().unwrap_err(),
+            LexerError::UndefinedToken {
+                token: "Word".to_string()
+            }
+        );
+    }
+}
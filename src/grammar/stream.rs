@@ -0,0 +1,279 @@
+use super::{LalrTable, LrAction};
+use crate::parser::ParseOutput;
+use crate::position::{Span, WithSpan};
+use crate::stream::StreamLexer;
+use std::fmt;
+
+/// Gives the token kind of a token, or `None` for trivia.
+type Kind<T> = dyn Fn(&T) -> Option<usize>;
+
+/// A token or a completed node from a [`StreamParser`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamEvent<T> {
+    /// A token, in input order, trivia included.
+    Token(WithSpan<T>),
+    /// A node completed by reducing a production of the table, after its
+    /// children.
+    Node {
+        rule: usize,
+        production: usize,
+        span: Span,
+    },
+}
+
+/// A push parser fed input in chunks, which lexes it with a
+/// [`StreamLexer`] and parses the tokens with an [`LalrTable`] as they
+/// come.
+///
+/// Tokens are emitted as soon as the lexer completes them, and nodes as
+/// soon as the token after them shows they are complete, which is when an
+/// LR parser reduces them; the nodes completed by the end of the input are
+/// emitted by [`finish`](StreamParser::finish).
+///
+/// Parsing stops for good at the first token that cannot come next: the
+/// error is reported, and from then on the parser only emits tokens, with
+/// no more nodes or errors, as it does not recover. The rest of the input
+/// is not checked, so a stream with several syntax errors reports only the
+/// first.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::grammar::*;
+/// use grammarsmith::position::{Span, WithSpan};
+/// use grammarsmith::stream::StreamLexer;
+///
+/// let grammar = parse_ebnf(r#"list = list "," Item | Item ;"#).value;
+/// let table = grammar.lalr_table();
+/// let (comma, item) = (table.token("\",\"").unwrap(), table.token("Item").unwrap());
+///
+/// // Lexes commas and runs of anything else.
+/// let lexer = StreamLexer::new(|source: &str, start| {
+///     let len = match source[start..].find(',') {
+///         Some(0) => 1,
+///         Some(len) => len,
+///         None => source.len() - start,
+///     };
+///     let kind = source[start..].starts_with(',');
+///     WithSpan::new(kind, Span::new(start, start + len).unwrap())
+/// });
+/// let mut parser =
+///     StreamParser::new(&table, lexer, move |&is_comma| Some(if is_comma { comma } else { item }));
+///
+/// let output = parser.feed("ab,c");
+/// // `ab`, the `list` the comma completes, and the comma; `c` may go on.
+/// assert_eq!(output.value.len(), 3);
+/// let output = parser.finish();
+/// assert!(output.diagnostics.is_empty());
+/// assert_eq!(
+///     output.value.last(),
+///     Some(&StreamEvent::Node { rule: 0, production: 0, span: Span::new(0, 4).unwrap() })
+/// );
+/// ```
+pub struct StreamParser<'t, T> {
+    table: &'t LalrTable,
+    lexer: StreamLexer<T>,
+    kind: Box<Kind<T>>,
+    states: Vec<usize>,
+    /// The spans of the symbols on the stack.
+    spans: Vec<Span>,
+    failed: bool,
+}
+
+impl<'t, T> StreamParser<'t, T> {
+    /// Creates a parser for the start rule of `table`, where `kind` gives
+    /// the token kind of a token, by index, or `None` for trivia, which the
+    /// parser skips.
+    pub fn new(
+        table: &'t LalrTable,
+        lexer: StreamLexer<T>,
+        kind: impl Fn(&T) -> Option<usize> + 'static,
+    ) -> Self {
+        StreamParser {
+            table,
+            lexer,
+            kind: Box::new(kind),
+            states: vec![0],
+            spans: Vec::new(),
+            failed: false,
+        }
+    }
+
+    /// Adds `chunk` to the input and returns the tokens and nodes that are
+    /// complete, with the errors found.
+    pub fn feed(&mut self, chunk: &str) -> ParseOutput<Vec<StreamEvent<T>>> {
+        let mut output = ParseOutput::new(Vec::new(), Vec::new());
+        for token in self.lexer.feed(chunk) {
+            self.push(token, &mut output);
+        }
+        output
+    }
+
+    /// Ends the input and returns the remaining tokens and nodes, the last
+    /// node being that of the start rule if the input is complete.
+    pub fn finish(mut self) -> ParseOutput<Vec<StreamEvent<T>>> {
+        let end = Span::point(self.lexer.len());
+        let mut output = ParseOutput::new(Vec::new(), Vec::new());
+        for token in self.lexer.flush() {
+            self.push(token, &mut output);
+        }
+        if !self.failed {
+            self.advance(None, end, &mut output);
+        }
+        output
+    }
+
+    fn push(&mut self, token: WithSpan<T>, output: &mut ParseOutput<Vec<StreamEvent<T>>>) {
+        if let Some(kind) = (self.kind)(&token.value).filter(|_| !self.failed) {
+            self.advance(Some(kind), token.span, output);
+        }
+        output.value.push(StreamEvent::Token(token));
+    }
+
+    /// Reduces the nodes that `lookahead`, at `span`, completes, and shifts
+    /// it, or accepts at the end of the input.
+    fn advance(
+        &mut self,
+        lookahead: Option<usize>,
+        span: Span,
+        output: &mut ParseOutput<Vec<StreamEvent<T>>>,
+    ) {
+        loop {
+            let state = *self.states.last().unwrap();
+            match self.table.action(state, lookahead) {
+                Some(LrAction::Shift(target)) => {
+                    self.states.push(target);
+                    self.spans.push(span);
+                    return;
+                }
+                Some(LrAction::Reduce(index)) => {
+                    let production = &self.table.productions()[index];
+                    let count = production.symbols.len();
+                    self.states.truncate(self.states.len() - count);
+                    let children = self.spans.split_off(self.spans.len() - count);
+                    let node = match (children.first(), children.last()) {
+                        (Some(first), Some(last)) => first.union(last),
+                        _ => Span::point(span.start()),
+                    };
+                    output.value.push(StreamEvent::Node {
+                        rule: production.rule,
+                        production: index,
+                        span: node,
+                    });
+                    self.spans.push(node);
+                    let from = *self.states.last().unwrap();
+                    self.states
+                        .push(self.table.goto(from, production.rule).unwrap());
+                }
+                Some(LrAction::Accept) => return,
+                None => {
+                    let error = self.table.unexpected(state, lookahead, span);
+                    output.diagnostics.push(error);
+                    self.failed = true;
+                    return;
+                }
+            }
+        }
+    }
+}
+
+impl<T> fmt::Debug for StreamParser<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StreamParser")
+            .field("lexer", &self.lexer)
+            .field("states", &self.states)
+            .field("failed", &self.failed)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::parse_ebnf;
+
+    /// Lexes runs of digits as `n`, runs of spaces as ` `, and single
+    /// other characters as themselves.
+    fn lex(source: &str, start: usize) -> WithSpan<char> {
+        let rest = &source[start..];
+        let first = rest.chars().next().unwrap();
+        let kind = match first {
+            '0'..='9' => 'n',
+            ch => ch,
+        };
+        let len = match kind {
+            'n' | ' ' => rest.find(|ch: char| ch != first && (kind != 'n' || !ch.is_ascii_digit())),
+            _ => Some(first.len_utf8()),
+        };
+        let len = len.unwrap_or(rest.len());
+        WithSpan::new(kind, Span::new(start, start + len).unwrap())
+    }
+
+    fn parser(table: &LalrTable) -> StreamParser<'_, char> {
+        let (plus, n) = (table.token("\"+\"").unwrap(), table.token("N").unwrap());
+        StreamParser::new(table, StreamLexer::new(lex), move |&kind| match kind {
+            '+' => Some(plus),
+            'n' => Some(n),
+            ' ' => None,
+            _ => Some(usize::MAX),
+        })
+    }
+
+    fn nodes(events: &[StreamEvent<char>]) -> Vec<Span> {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                StreamEvent::Node { span, .. } => Some(*span),
+                StreamEvent::Token(_) => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_chunks() {
+        let table = parse_ebnf(r#"sum = sum "+" N | N ;"#).value.lalr_table();
+        let mut parser = parser(&table);
+        let mut events = Vec::new();
+        let mut sizes = Vec::new();
+        for chunk in ["1", "2 +", " 3", "4"] {
+            let output = parser.feed(chunk);
+            assert!(output.diagnostics.is_empty());
+            sizes.push(output.value.len());
+            events.extend(output.value);
+        }
+        // `12` waits for a character after it, and the first `sum` for `+`.
+        assert_eq!(sizes, [0, 2, 3, 0]);
+        let output = parser.finish();
+        assert!(output.diagnostics.is_empty());
+        events.extend(output.value);
+
+        assert_eq!(events, parser_output(&table, "12 + 34").value);
+        let spans = [Span::new(0, 2).unwrap(), Span::new(0, 7).unwrap()];
+        assert_eq!(nodes(&events), spans);
+    }
+
+    fn parser_output(table: &LalrTable, source: &str) -> ParseOutput<Vec<StreamEvent<char>>> {
+        let mut parser = parser(table);
+        let mut output = parser.feed(source);
+        let rest = parser.finish();
+        output.value.extend(rest.value);
+        output.diagnostics.extend(rest.diagnostics);
+        output
+    }
+
+    #[test]
+    fn test_errors() {
+        let table = parse_ebnf(r#"sum = sum "+" N | N ;"#).value.lalr_table();
+        let output = parser_output(&table, "1 + + 2");
+        assert_eq!(output.diagnostics.len(), 1);
+        assert_eq!(output.diagnostics[0].message, "expected N, found \"+\"");
+        assert_eq!(output.diagnostics[0].primary_span(), Span::new(4, 5));
+        assert_eq!(output.value.len(), 8);
+
+        let output = parser_output(&table, "1 +");
+        assert_eq!(
+            output.diagnostics[0].message,
+            "expected N, found end of input"
+        );
+        assert_eq!(output.diagnostics[0].primary_span(), Some(Span::point(3)));
+    }
+}
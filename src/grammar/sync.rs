@@ -0,0 +1,281 @@
+use super::{Analysis, Grammar, Lookahead};
+use crate::parser::KindMeta;
+use std::collections::{btree_map, btree_set, BTreeMap, BTreeSet};
+
+/// A set of tokens of a grammar description, such as the tokens a rule
+/// skips to after an error.
+///
+/// Tokens are named as in the grammar, and turned into the kinds of a
+/// token type with [`kinds`](TokenSet::kinds) or
+/// [`kinds_with`](TokenSet::kinds_with), to be given to
+/// [`Parser::drop_until`](crate::parser::Parser::drop_until).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TokenSet {
+    tokens: BTreeSet<Lookahead>,
+}
+
+impl TokenSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a token to the set, returning whether it was new.
+    pub fn insert(&mut self, token: Lookahead) -> bool {
+        self.tokens.insert(token)
+    }
+
+    pub fn contains(&self, token: &Lookahead) -> bool {
+        self.tokens.contains(token)
+    }
+
+    /// Returns whether the set holds the token kind `kind`.
+    pub fn contains_token(&self, kind: &str) -> bool {
+        self.tokens.contains(&Lookahead::token(kind))
+    }
+
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    /// Returns the tokens in order, with [`Lookahead::End`] last.
+    pub fn iter(&self) -> btree_set::Iter<'_, Lookahead> {
+        self.tokens.iter()
+    }
+
+    /// Returns the kinds of `K` whose [`name`](KindMeta::name) is a token
+    /// kind of the set, in declaration order.
+    ///
+    /// The end of the input needs no kind, as the parser stops there anyway.
+    pub fn kinds<K: KindMeta + Clone>(&self) -> Vec<K> {
+        K::ALL
+            .iter()
+            .filter(|kind| self.contains_token(kind.name()))
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the kinds `kind` gives for the token kinds of the set, in
+    /// order, leaving out the tokens it gives none for.
+    pub fn kinds_with<K>(&self, mut kind: impl FnMut(&str) -> Option<K>) -> Vec<K> {
+        self.tokens
+            .iter()
+            .filter_map(|token| match token {
+                Lookahead::Token(name) => kind(name),
+                Lookahead::End => None,
+            })
+            .collect()
+    }
+}
+
+impl FromIterator<Lookahead> for TokenSet {
+    fn from_iter<I: IntoIterator<Item = Lookahead>>(iter: I) -> Self {
+        TokenSet {
+            tokens: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl Extend<Lookahead> for TokenSet {
+    fn extend<I: IntoIterator<Item = Lookahead>>(&mut self, iter: I) {
+        self.tokens.extend(iter);
+    }
+}
+
+impl<'s> IntoIterator for &'s TokenSet {
+    type Item = &'s Lookahead;
+    type IntoIter = btree_set::Iter<'s, Lookahead>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// The synchronization sets of the rules of a [`Grammar`], for panic-mode
+/// error recovery.
+///
+/// A rule in error skips to a token of its sync set and gives up, so the
+/// rule using it can carry on. The sync set of a rule starts as its FOLLOW
+/// set, the tokens that can come right after it, and can be extended with
+/// tokens that always make good recovery points, such as statement
+/// terminators, with [`with_token`](SyncSets::with_token).
+///
+/// # Examples
+/// ```
+/// use grammarsmith::grammar::*;
+///
+/// let grammar = parse_ebnf(r#"
+///     block = "{" stmt* "}" ;
+///     stmt  = Id "=" Number ";" ;
+/// "#).value;
+///
+/// let sync = grammar.sync_sets();
+/// let stmt = sync.get("stmt").unwrap();
+/// assert!(stmt.contains_token("Id"));
+/// assert!(stmt.contains_token("\"}\""));
+///
+/// let sync = sync.with_token("\";\"");
+/// assert!(sync.get("block").unwrap().contains_token("\";\""));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncSets {
+    sets: BTreeMap<String, TokenSet>,
+}
+
+impl SyncSets {
+    pub fn new(grammar: &Grammar) -> Self {
+        grammar.analyze().sync_sets(grammar)
+    }
+
+    /// Adds the token kind `kind` to every sync set.
+    pub fn with_token(mut self, kind: impl Into<String>) -> Self {
+        let token = Lookahead::token(kind);
+        for set in self.sets.values_mut() {
+            set.insert(token.clone());
+        }
+        self
+    }
+
+    /// Returns the sync set of the rule `name`, if it is defined.
+    pub fn get(&self, name: &str) -> Option<&TokenSet> {
+        self.sets.get(name)
+    }
+
+    /// Returns the rules and their sync sets, by rule name.
+    pub fn iter(&self) -> btree_map::Iter<'_, String, TokenSet> {
+        self.sets.iter()
+    }
+}
+
+impl Analysis {
+    /// Derives the sync sets of the rules of `grammar` from their FOLLOW
+    /// sets.
+    pub fn sync_sets(&self, grammar: &Grammar) -> SyncSets {
+        let sets = grammar
+            .rules()
+            .iter()
+            .map(|rule| {
+                let set = self.follow(&rule.name).iter().cloned().collect();
+                (rule.name.clone(), set)
+            })
+            .collect();
+        SyncSets { sets }
+    }
+}
+
+impl Grammar {
+    /// Derives the sync sets of the rules from their FOLLOW sets.
+    pub fn sync_sets(&self) -> SyncSets {
+        SyncSets::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::parse_ebnf;
+    use crate::parser::{EndOfFile, Parser, Token};
+    use crate::position::{Span, WithSpan};
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum Kind {
+        Id,
+        Number,
+        Semicolon,
+        Eof,
+    }
+
+    impl Token for Kind {
+        type Kind = Kind;
+
+        fn to_kind(&self) -> Kind {
+            *self
+        }
+    }
+
+    impl EndOfFile for Kind {
+        fn eof() -> Kind {
+            Kind::Eof
+        }
+
+        fn eof_kind() -> Kind {
+            Kind::Eof
+        }
+    }
+
+    impl KindMeta for Kind {
+        const ALL: &'static [Kind] = &[Kind::Id, Kind::Number, Kind::Semicolon, Kind::Eof];
+
+        fn display_name(&self) -> &'static str {
+            match self {
+                Kind::Id => "an identifier",
+                Kind::Number => "a number",
+                Kind::Semicolon => "`;`",
+                Kind::Eof => "end of input",
+            }
+        }
+
+        fn name(&self) -> &'static str {
+            match self {
+                Kind::Id => "Id",
+                Kind::Number => "Number",
+                Kind::Semicolon => "Semicolon",
+                Kind::Eof => "Eof",
+            }
+        }
+    }
+
+    #[test]
+    fn test_sync_sets() {
+        let grammar =
+            parse_ebnf("program = stmt* ; stmt = Id expr Semicolon ; expr = Number+ ;").value;
+        let sync = grammar.sync_sets();
+        let sets: Vec<(&str, Vec<String>)> = sync
+            .iter()
+            .map(|(name, set)| (name.as_str(), set.iter().map(|t| t.to_string()).collect()))
+            .collect();
+        assert_eq!(
+            sets,
+            [
+                ("expr", vec!["Semicolon".to_string()]),
+                ("program", vec!["end of input".to_string()]),
+                ("stmt", vec!["Id".to_string(), "end of input".to_string()]),
+            ]
+        );
+        assert_eq!(sync.get("missing"), None);
+
+        let sync = sync.with_token("Semicolon");
+        assert_eq!(
+            sync.get("stmt").unwrap().kinds::<Kind>(),
+            [Kind::Id, Kind::Semicolon]
+        );
+        assert_eq!(
+            sync.get("program")
+                .unwrap()
+                .kinds_with(|name| (name == "Semicolon").then_some(Kind::Semicolon)),
+            [Kind::Semicolon]
+        );
+    }
+
+    #[test]
+    fn test_recovery_with_sync_set() {
+        let grammar =
+            parse_ebnf("program = stmt* ; stmt = Id expr Semicolon ; expr = Number+ ;").value;
+        let kinds = grammar.sync_sets().get("expr").unwrap().kinds::<Kind>();
+        let tokens: Vec<WithSpan<Kind>> = [Kind::Id, Kind::Id, Kind::Semicolon, Kind::Id]
+            .into_iter()
+            .enumerate()
+            .map(|(i, kind)| WithSpan::new(kind, Span::new(i, i + 1).unwrap()))
+            .collect();
+        let eof = WithSpan::new(Kind::Eof, Span::point(4));
+        let mut parser = Parser::new(&tokens, &eof);
+        parser.advance();
+        // `expr` fails on the second `Id`, and skips to the `;` ending the
+        // statement rather than to the next statement.
+        assert_eq!(parser.drop_until(&kinds), Span::new(1, 2));
+        assert_eq!(parser.peek(), Kind::Semicolon);
+    }
+}
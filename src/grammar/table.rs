@@ -0,0 +1,623 @@
+use super::{ErrorMessage, Expr, Grammar, Lookahead, Rule};
+use crate::diagnostics::{expected_list, Diagnostic};
+use crate::parser::ParseOutput;
+use crate::position::{Span, WithSpan};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// The magic number starting an encoded [`LlTable`].
+const MAGIC: &[u8; 4] = b"GSLL";
+/// The version of the encoding [`LlTable::to_bytes`] writes.
+const VERSION: u8 = 2;
+
+/// A symbol of a [`Production`]: a token kind or a rule, by index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Symbol {
+    Token(usize),
+    Rule(usize),
+}
+
+/// An alternative of a rule, as a sequence of symbols.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Production {
+    pub rule: usize,
+    pub symbols: Vec<Symbol>,
+}
+
+/// An error decoding an [`LlTable`] with [`LlTable::from_bytes`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableError {
+    /// The bytes do not start with the magic number of a table.
+    NotATable,
+    /// The table was encoded with an unknown version of the format.
+    UnsupportedVersion(u8),
+    /// The bytes end in the middle of the table.
+    Truncated,
+    /// A name or message is not valid UTF-8.
+    InvalidText,
+    /// A rule, token or production index is out of range.
+    InvalidIndex,
+}
+
+impl fmt::Display for TableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TableError::NotATable => write!(f, "not an encoded parse table"),
+            TableError::UnsupportedVersion(version) => {
+                write!(f, "unsupported parse table version {}", version)
+            }
+            TableError::Truncated => write!(f, "the parse table is truncated"),
+            TableError::InvalidText => write!(f, "a text in the parse table is not valid UTF-8"),
+            TableError::InvalidIndex => write!(f, "an index in the parse table is out of range"),
+        }
+    }
+}
+
+impl std::error::Error for TableError {}
+
+/// The LL(1) prediction table of a [`Grammar`], for parsing with a stack
+/// rather than generated code.
+///
+/// The rules are those of [`Grammar::to_bnf`]: the rules of the grammar,
+/// in order, with auxiliary rules for repetitions, optional parts and
+/// nested choices. For each rule and lookahead, the table holds the
+/// production to predict; where the grammar has LL(1) conflicts, see
+/// [`Grammar::check_ll1`], the earlier alternative wins, as in the parsers
+/// of [`RustGenerator`](super::RustGenerator), and so are custom
+/// [`ErrorMessage`]s.
+///
+/// Building a table analyzes the whole grammar, so it can be done once, in
+/// a build script, and the table embedded in the program: with
+/// [`LlTable::to_bytes`] and [`LlTable::from_bytes`], a compact binary
+/// encoding, or with the `serde` feature.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::grammar::*;
+/// use grammarsmith::position::{Span, WithSpan};
+///
+/// let grammar = parse_ebnf(r#"args = Id ("," Id)* ;"#).value;
+/// let bytes = grammar.ll1_table().to_bytes();
+///
+/// // At runtime, in place of `include_bytes!`:
+/// let table = LlTable::from_bytes(&bytes).unwrap();
+/// let id = table.token("Id").unwrap();
+/// let comma = table.token("\",\"").unwrap();
+/// let tokens = [id, comma, id]
+///     .into_iter()
+///     .enumerate()
+///     .map(|(i, kind)| WithSpan::new(kind, Span::new_unchecked(i, i + 1)))
+///     .collect::<Vec<_>>();
+///
+/// let derivation = table.parse(&tokens, Span::point(3)).value;
+/// let rules: Vec<_> = derivation
+///     .iter()
+///     .map(|&p| table.rules()[table.productions()[p].rule].as_str())
+///     .collect();
+/// assert_eq!(rules, ["args", "args-1", "args-1"]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LlTable {
+    rules: Vec<String>,
+    tokens: Vec<String>,
+    productions: Vec<Production>,
+    /// The custom error message of each rule, which auxiliary rules share
+    /// with the rule they come from.
+    errors: Vec<Option<ErrorMessage>>,
+    /// For each rule, then each token and the end of the input, the index
+    /// of the production to predict plus one, or 0 for none.
+    predict: Vec<usize>,
+}
+
+/// A grammar in BNF, as in [`Grammar::to_bnf`], with its rules, token
+/// kinds and productions by index, for the table-driven and generalized
+/// parsers.
+#[derive(Debug, Clone)]
+pub(super) struct Bnf {
+    /// The rules, then the undefined rules they refer to, which have no
+    /// productions.
+    pub(super) rules: Vec<String>,
+    pub(super) tokens: Vec<String>,
+    /// The productions, grouped by rule.
+    pub(super) productions: Vec<Production>,
+    /// The rule of the grammar each rule comes from, itself for all but
+    /// auxiliary rules.
+    pub(super) origins: Vec<usize>,
+    pub(super) errors: Vec<Option<ErrorMessage>>,
+    /// The grammar of the BNF rules, to analyze.
+    pub(super) plain: Grammar,
+}
+
+impl Bnf {
+    pub(super) fn new(grammar: &Grammar) -> Self {
+        let bnf = grammar.bnf_rules();
+        let mut plain = Grammar::new();
+        for (name, alternatives) in &bnf {
+            let alternatives = alternatives
+                .iter()
+                .map(|alternative| Expr::seq(alternative.iter().cloned()));
+            plain.add(Rule::new(name, Expr::choice(alternatives)));
+        }
+        let mut rules: Vec<String> = bnf.iter().map(|(name, _)| name.clone()).collect();
+        for (_, alternatives) in &bnf {
+            for symbol in alternatives.iter().flatten() {
+                if let Expr::Rule(name) = symbol {
+                    if !rules.contains(name) {
+                        rules.push(name.clone());
+                    }
+                }
+            }
+        }
+        let mut origins: Vec<usize> = (0..rules.len()).collect();
+        let mut errors = vec![None; rules.len()];
+        let mut origin = 0;
+        for (i, (name, _)) in bnf.iter().enumerate() {
+            if grammar.get(name).is_some() {
+                origin = i;
+            }
+            origins[i] = origin;
+            errors[i] = grammar
+                .get(&rules[origin])
+                .and_then(|rule| rule.error.clone());
+        }
+        let tokens: Vec<String> = plain.tokens().into_iter().map(String::from).collect();
+        let index = |names: &[String], name: &str| names.iter().position(|n| n == name).unwrap();
+        let mut productions = Vec::new();
+        for (name, alternatives) in &bnf {
+            for alternative in alternatives {
+                let symbols = alternative
+                    .iter()
+                    .map(|symbol| match symbol {
+                        Expr::Rule(name) => Symbol::Rule(index(&rules, name)),
+                        Expr::Token(kind) => Symbol::Token(index(&tokens, kind)),
+                        _ => unreachable!("BNF alternatives hold only tokens and rules"),
+                    })
+                    .collect();
+                productions.push(Production {
+                    rule: index(&rules, name),
+                    symbols,
+                });
+            }
+        }
+        Bnf {
+            rules,
+            tokens,
+            productions,
+            origins,
+            errors,
+            plain,
+        }
+    }
+
+    /// Returns the production as an expression of the plain grammar.
+    pub(super) fn expr(&self, production: &Production) -> Expr {
+        Expr::seq(production.symbols.iter().map(|symbol| match *symbol {
+            Symbol::Token(token) => Expr::token(&self.tokens[token]),
+            Symbol::Rule(rule) => Expr::rule(&self.rules[rule]),
+        }))
+    }
+}
+
+impl LlTable {
+    pub fn new(grammar: &Grammar) -> Self {
+        let bnf = Bnf::new(grammar);
+        let analysis = bnf.plain.analyze();
+        let width = bnf.tokens.len() + 1;
+        let mut predict = vec![0; bnf.rules.len() * width];
+        for (index, production) in bnf.productions.iter().enumerate() {
+            let follow = analysis.follow(&bnf.rules[production.rule]);
+            for lookahead in analysis.lookahead(&bnf.expr(production), follow) {
+                let column = match lookahead {
+                    Lookahead::Token(kind) => bnf.tokens.iter().position(|t| *t == kind).unwrap(),
+                    Lookahead::End => bnf.tokens.len(),
+                };
+                let cell = &mut predict[production.rule * width + column];
+                if *cell == 0 {
+                    *cell = index + 1;
+                }
+            }
+        }
+        LlTable {
+            rules: bnf.rules,
+            tokens: bnf.tokens,
+            productions: bnf.productions,
+            errors: bnf.errors,
+            predict,
+        }
+    }
+
+    /// Returns the names of the rules, the start rule first.
+    pub fn rules(&self) -> &[String] {
+        &self.rules
+    }
+
+    /// Returns the names of the token kinds.
+    pub fn tokens(&self) -> &[String] {
+        &self.tokens
+    }
+
+    /// Returns the productions, grouped by rule.
+    pub fn productions(&self) -> &[Production] {
+        &self.productions
+    }
+
+    /// Returns the index of the rule with the given name.
+    pub fn rule(&self, name: &str) -> Option<usize> {
+        self.rules.iter().position(|rule| rule == name)
+    }
+
+    /// Returns the index of the token kind with the given name.
+    pub fn token(&self, name: &str) -> Option<usize> {
+        self.tokens.iter().position(|token| token == name)
+    }
+
+    /// Returns the index of the production to predict for `rule` when the
+    /// next token is `lookahead`, or the end of the input for `None`.
+    pub fn predict(&self, rule: usize, lookahead: Option<usize>) -> Option<usize> {
+        let column = lookahead.unwrap_or(self.tokens.len());
+        let cell = *self.predict.get(rule * (self.tokens.len() + 1) + column)?;
+        cell.checked_sub(1)
+    }
+
+    /// Parses token kinds, by index, from the start rule, where `end` is the
+    /// span of the end of the input.
+    ///
+    /// # Returns
+    /// The indices of the productions of the leftmost derivation. Parsing
+    /// stops at the first token that cannot come next, with an error, and
+    /// the derivation up to there.
+    pub fn parse(&self, tokens: &[WithSpan<usize>], end: Span) -> ParseOutput<Vec<usize>> {
+        // Each symbol with the rule of the production it comes from.
+        let mut stack = Vec::new();
+        if !self.rules.is_empty() {
+            stack.push((Symbol::Rule(0), None));
+        }
+        let mut derivation = Vec::new();
+        let mut position = 0;
+        while let Some((symbol, owner)) = stack.pop() {
+            let lookahead = tokens.get(position).map(|token| token.value);
+            match symbol {
+                Symbol::Token(kind) if lookahead == Some(kind) => position += 1,
+                Symbol::Token(kind) => {
+                    let error = self.error(owner, &[Some(kind)], tokens, position, end);
+                    return ParseOutput::new(derivation, vec![error]);
+                }
+                Symbol::Rule(rule) => match self.predict(rule, lookahead) {
+                    Some(production) => {
+                        derivation.push(production);
+                        let symbols = self.productions[production].symbols.iter().rev();
+                        stack.extend(symbols.map(|&symbol| (symbol, Some(rule))));
+                    }
+                    None => {
+                        let expected: Vec<_> = (0..self.tokens.len())
+                            .map(Some)
+                            .chain([None])
+                            .filter(|&column| self.predict(rule, column).is_some())
+                            .collect();
+                        let error = self.error(owner, &expected, tokens, position, end);
+                        return ParseOutput::new(derivation, vec![error]);
+                    }
+                },
+            }
+        }
+        if position < tokens.len() {
+            let error = self.error(None, &[None], tokens, position, end);
+            return ParseOutput::new(derivation, vec![error]);
+        }
+        ParseOutput::new(derivation, Vec::new())
+    }
+
+    /// Returns the error for a token that cannot come next in `rule`,
+    /// which is its custom error message if it has one.
+    fn error(
+        &self,
+        rule: Option<usize>,
+        expected: &[Option<usize>],
+        tokens: &[WithSpan<usize>],
+        position: usize,
+        end: Span,
+    ) -> Diagnostic {
+        let name = |token: Option<usize>| match token {
+            Some(token) => self
+                .tokens
+                .get(token)
+                .map_or("an unknown token", String::as_str),
+            None => "end of input",
+        };
+        let (found, span) = match tokens.get(position) {
+            Some(token) => (Some(token.value), token.span),
+            None => (None, end),
+        };
+        let custom = rule.and_then(|rule| self.errors[rule].as_ref());
+        let Some(custom) = custom else {
+            return Diagnostic::error(format!(
+                "expected {}, found {}",
+                expected_list(expected.iter().map(|&token| name(token))),
+                name(found)
+            ))
+            .with_label(span, "");
+        };
+        let diagnostic = Diagnostic::error(&custom.message).with_label(span, "");
+        match &custom.hint {
+            Some(hint) => diagnostic.with_note(hint),
+            None => diagnostic,
+        }
+    }
+
+    /// Encodes the table in a compact binary format, for
+    /// [`LlTable::from_bytes`].
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = MAGIC.to_vec();
+        out.push(VERSION);
+        for names in [&self.rules, &self.tokens] {
+            write_varint(&mut out, names.len());
+            for name in names {
+                write_str(&mut out, name);
+            }
+        }
+        write_varint(&mut out, self.productions.len());
+        for production in &self.productions {
+            write_varint(&mut out, production.rule);
+            write_varint(&mut out, production.symbols.len());
+            for symbol in &production.symbols {
+                match *symbol {
+                    Symbol::Token(token) => write_varint(&mut out, token << 1),
+                    Symbol::Rule(rule) => write_varint(&mut out, rule << 1 | 1),
+                }
+            }
+        }
+        for error in &self.errors {
+            match error {
+                None => write_varint(&mut out, 0),
+                Some(error) => {
+                    write_varint(&mut out, if error.hint.is_some() { 2 } else { 1 });
+                    write_str(&mut out, &error.message);
+                    if let Some(hint) = &error.hint {
+                        write_str(&mut out, hint);
+                    }
+                }
+            }
+        }
+        for &cell in &self.predict {
+            write_varint(&mut out, cell);
+        }
+        out
+    }
+
+    /// Decodes a table encoded with [`LlTable::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<LlTable, TableError> {
+        let rest = bytes.strip_prefix(MAGIC).ok_or(TableError::NotATable)?;
+        let (&version, rest) = rest.split_first().ok_or(TableError::Truncated)?;
+        if version != VERSION {
+            return Err(TableError::UnsupportedVersion(version));
+        }
+        let mut reader = Reader { bytes: rest };
+        let rules = reader.names()?;
+        let tokens = reader.names()?;
+        let count = reader.varint()?;
+        let mut productions = Vec::new();
+        for _ in 0..count {
+            let rule = reader.index(rules.len())?;
+            let len = reader.varint()?;
+            let symbols = (0..len)
+                .map(|_| {
+                    let symbol = reader.varint()?;
+                    match symbol & 1 {
+                        0 if symbol >> 1 < tokens.len() => Ok(Symbol::Token(symbol >> 1)),
+                        1 if symbol >> 1 < rules.len() => Ok(Symbol::Rule(symbol >> 1)),
+                        _ => Err(TableError::InvalidIndex),
+                    }
+                })
+                .collect::<Result<_, _>>()?;
+            productions.push(Production { rule, symbols });
+        }
+        let errors = (0..rules.len())
+            .map(|_| {
+                let error = match reader.varint()? {
+                    0 => return Ok(None),
+                    1 => ErrorMessage::new(reader.string()?),
+                    2 => ErrorMessage::new(reader.string()?).with_hint(reader.string()?),
+                    _ => return Err(TableError::InvalidIndex),
+                };
+                Ok(Some(error))
+            })
+            .collect::<Result<_, _>>()?;
+        let predict = (0..rules.len() * (tokens.len() + 1))
+            .map(|_| reader.index(productions.len() + 1))
+            .collect::<Result<_, _>>()?;
+        Ok(LlTable {
+            rules,
+            tokens,
+            productions,
+            errors,
+            predict,
+        })
+    }
+}
+
+impl Grammar {
+    /// Builds the LL(1) prediction table of the grammar. See [`LlTable`].
+    pub fn ll1_table(&self) -> LlTable {
+        LlTable::new(self)
+    }
+}
+
+/// Writes `value` in LEB128: seven bits per byte, low bits first, with the
+/// high bit set on all but the last byte.
+fn write_varint(out: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn write_str(out: &mut Vec<u8>, text: &str) {
+    write_varint(out, text.len());
+    out.extend_from_slice(text.as_bytes());
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl Reader<'_> {
+    fn varint(&mut self) -> Result<usize, TableError> {
+        let mut value = 0usize;
+        let mut shift = 0;
+        loop {
+            let (&byte, rest) = self.bytes.split_first().ok_or(TableError::Truncated)?;
+            self.bytes = rest;
+            let bits = ((byte & 0x7f) as usize)
+                .checked_shl(shift)
+                .filter(|bits| bits >> shift == (byte & 0x7f) as usize)
+                .ok_or(TableError::InvalidIndex)?;
+            value |= bits;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+            shift += 7;
+        }
+    }
+
+    /// Reads an index below `len`.
+    fn index(&mut self, len: usize) -> Result<usize, TableError> {
+        let index = self.varint()?;
+        if index < len {
+            Ok(index)
+        } else {
+            Err(TableError::InvalidIndex)
+        }
+    }
+
+    fn string(&mut self) -> Result<String, TableError> {
+        let len = self.varint()?;
+        if len > self.bytes.len() {
+            return Err(TableError::Truncated);
+        }
+        let (text, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        let text = std::str::from_utf8(text).map_err(|_| TableError::InvalidText)?;
+        Ok(text.to_string())
+    }
+
+    fn names(&mut self) -> Result<Vec<String>, TableError> {
+        let count = self.varint()?;
+        (0..count).map(|_| self.string()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::parse_ebnf;
+
+    fn tokens(table: &LlTable, kinds: &[&str]) -> Vec<WithSpan<usize>> {
+        kinds
+            .iter()
+            .enumerate()
+            .map(|(i, kind)| {
+                WithSpan::new(table.token(kind).unwrap(), Span::new_unchecked(i, i + 1))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        let grammar = parse_ebnf(
+            r#"
+            list = "[" items? "]" ;
+            items = item ("," item)* ;
+            item = Number | list ;
+            "#,
+        )
+        .value;
+        let table = grammar.ll1_table();
+        assert_eq!(
+            table.rules(),
+            ["list", "list-1", "items", "items-1", "item"]
+        );
+
+        let output = table.parse(
+            &tokens(
+                &table,
+                &["\"[\"", "Number", "\",\"", "\"[\"", "\"]\"", "\"]\""],
+            ),
+            Span::point(6),
+        );
+        assert!(output.diagnostics.is_empty());
+        let productions: Vec<_> = output
+            .value
+            .iter()
+            .map(|&p| table.rules()[table.productions()[p].rule].as_str())
+            .collect();
+        assert_eq!(
+            productions,
+            ["list", "list-1", "items", "item", "items-1", "item", "list", "list-1", "items-1"]
+        );
+
+        let error = table
+            .parse(
+                &tokens(&table, &["\"[\"", "Number", "Number"]),
+                Span::point(3),
+            )
+            .diagnostics;
+        assert_eq!(error[0].message, "expected \"]\" or \",\", found Number");
+        let error = table
+            .parse(
+                &tokens(&table, &["\"[\"", "\"]\"", "\"]\""]),
+                Span::point(3),
+            )
+            .diagnostics;
+        assert_eq!(error[0].message, "expected end of input, found \"]\"");
+        let error = table.parse(&[], Span::point(0)).diagnostics;
+        assert_eq!(error[0].message, "expected \"[\", found end of input");
+    }
+
+    #[test]
+    fn test_bytes_round_trip() {
+        let grammar = parse_ebnf(
+            r#"
+            expr = Id ("+" Id)* | undefined on_error("expected an expression", "try `x`") ;
+            other = ("-" expr)+ on_error("expected a negation") ;
+            "#,
+        )
+        .value;
+        let table = grammar.ll1_table();
+        let bytes = table.to_bytes();
+        assert_eq!(LlTable::from_bytes(&bytes), Ok(table.clone()));
+
+        // The auxiliary rule `expr-1` has the message of `expr`.
+        let error = &table
+            .parse(&tokens(&table, &["Id", "\"+\"", "\"-\""]), Span::point(3))
+            .diagnostics[0];
+        assert_eq!(error.message, "expected an expression");
+        assert_eq!(error.notes, ["try `x`"]);
+        assert_eq!(error.primary_span(), Span::new(2, 3));
+
+        assert_eq!(LlTable::from_bytes(b"GSL"), Err(TableError::NotATable));
+        assert_eq!(
+            LlTable::from_bytes(b"GSLL\x09"),
+            Err(TableError::UnsupportedVersion(9))
+        );
+        assert_eq!(
+            LlTable::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(TableError::Truncated)
+        );
+        let mut corrupt = bytes.clone();
+        *corrupt.last_mut().unwrap() = 0x7f;
+        assert_eq!(LlTable::from_bytes(&corrupt), Err(TableError::InvalidIndex));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let table = parse_ebnf(r#"args = Id ("," Id)* ;"#).value.ll1_table();
+        let json = serde_json::to_string(&table).unwrap();
+        assert_eq!(serde_json::from_str::<LlTable>(&json).unwrap(), table);
+    }
+}
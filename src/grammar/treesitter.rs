@@ -0,0 +1,306 @@
+use super::{Expr, Grammar};
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+
+/// How a token kind is matched by tree-sitter.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Terminal {
+    /// Exact text, such as a keyword or punctuation. Literals are inlined
+    /// into the rules using them, becoming anonymous nodes.
+    Literal(String),
+    /// A JavaScript regular expression, without the delimiting slashes.
+    /// Patterns become named rules, and so named nodes.
+    Pattern(String),
+}
+
+impl Terminal {
+    pub fn literal(text: impl Into<String>) -> Self {
+        Terminal::Literal(text.into())
+    }
+
+    pub fn pattern(regex: impl Into<String>) -> Self {
+        Terminal::Pattern(regex.into())
+    }
+}
+
+/// An error exporting a grammar to tree-sitter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExportError {
+    /// The grammar has no rules.
+    Empty,
+    /// A rule refers to a rule the grammar does not define.
+    UndefinedRule { rule: String, name: String },
+    /// A token kind has no terminal.
+    MissingTerminal { token: String },
+}
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportError::Empty => write!(f, "the grammar has no rules"),
+            ExportError::UndefinedRule { rule, name } => {
+                write!(f, "rule `{}` refers to undefined rule `{}`", rule, name)
+            }
+            ExportError::MissingTerminal { token } => {
+                write!(f, "no terminal is defined for token `{}`", token)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ExportError {}
+
+/// Exports a [`Grammar`] as a tree-sitter `grammar.js`.
+///
+/// Each token kind needs a [`Terminal`] telling tree-sitter how to match
+/// it, either from its definition in the grammar or given with
+/// [`with_terminal`](TreeSitterExporter::with_terminal). Rules keep their names and token
+/// kinds with a pattern become rules named in snake case, so `Number` is
+/// exported as `number`.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::grammar::*;
+///
+/// let grammar = Grammar::new()
+///     .with_rule("sum", Expr::seq([
+///         Expr::token("Number"),
+///         Expr::repeat(Expr::seq([Expr::token("Plus"), Expr::token("Number")])),
+///     ]));
+///
+/// let js = TreeSitterExporter::new("calc", &grammar)
+///     .with_terminal("Plus", Terminal::literal("+"))
+///     .with_terminal("Number", Terminal::pattern(r"\d+"))
+///     .export()
+///     .unwrap();
+///
+/// assert_eq!(js, r"module.exports = grammar({
+///   name: 'calc',
+///
+///   rules: {
+///     sum: $ => seq($.number, repeat(seq('+', $.number))),
+///
+///     number: $ => /\d+/,
+///   }
+/// });
+/// ");
+/// ```
+#[derive(Debug, Clone)]
+pub struct TreeSitterExporter<'g> {
+    name: String,
+    grammar: &'g Grammar,
+    terminals: HashMap<String, Terminal>,
+}
+
+impl<'g> TreeSitterExporter<'g> {
+    /// Creates an exporter for the language `name`, which must be a valid
+    /// tree-sitter grammar name.
+    pub fn new(name: impl Into<String>, grammar: &'g Grammar) -> Self {
+        TreeSitterExporter {
+            name: name.into(),
+            grammar,
+            terminals: HashMap::new(),
+        }
+    }
+
+    /// Sets how the token kind `token` is matched, overriding its
+    /// [`TokenDef`](super::TokenDef) in the grammar, if any.
+    pub fn with_terminal(mut self, token: impl Into<String>, terminal: Terminal) -> Self {
+        self.terminals.insert(token.into(), terminal);
+        self
+    }
+
+    fn terminal(&self, token: &str) -> Option<&Terminal> {
+        self.terminals.get(token).or_else(|| {
+            let def = self.grammar.token_def(token)?;
+            Some(&def.terminal)
+        })
+    }
+
+    /// Returns the `grammar.js` source.
+    pub fn export(&self) -> Result<String, ExportError> {
+        if self.grammar.rules().is_empty() {
+            return Err(ExportError::Empty);
+        }
+        let mut entries = Vec::new();
+        for rule in self.grammar.rules() {
+            let mut body = String::new();
+            self.write_expr(&mut body, &rule.name, &rule.expr)?;
+            entries.push((rule.name.clone(), body));
+        }
+        for token in self.grammar.tokens() {
+            if let Some(Terminal::Pattern(regex)) = self.terminal(token) {
+                entries.push((snake_case(token), format!("/{}/", escape_regex(regex))));
+            }
+        }
+
+        let mut out = String::new();
+        out.push_str("module.exports = grammar({\n");
+        let _ = writeln!(out, "  name: {},\n", js_string(&self.name));
+        out.push_str("  rules: {\n");
+        for (i, (name, body)) in entries.iter().enumerate() {
+            if i > 0 {
+                out.push('\n');
+            }
+            let _ = writeln!(out, "    {}: $ => {},", name, body);
+        }
+        out.push_str("  }\n});\n");
+        Ok(out)
+    }
+
+    fn write_expr(&self, out: &mut String, rule: &str, expr: &Expr) -> Result<(), ExportError> {
+        match expr {
+            Expr::Token(token) => match self.terminal(token) {
+                Some(Terminal::Literal(text)) => out.push_str(&js_string(text)),
+                Some(Terminal::Pattern(_)) => {
+                    out.push_str("$.");
+                    out.push_str(&snake_case(token));
+                }
+                None => {
+                    return Err(ExportError::MissingTerminal {
+                        token: token.clone(),
+                    })
+                }
+            },
+            Expr::Rule(name) => {
+                if self.grammar.get(name).is_none() {
+                    return Err(ExportError::UndefinedRule {
+                        rule: rule.to_string(),
+                        name: name.clone(),
+                    });
+                }
+                out.push_str("$.");
+                out.push_str(name);
+            }
+            Expr::Seq(exprs) if exprs.is_empty() => out.push_str("blank()"),
+            Expr::Seq(exprs) if exprs.len() == 1 => self.write_expr(out, rule, &exprs[0])?,
+            Expr::Seq(exprs) => self.write_call(out, rule, "seq", exprs)?,
+            Expr::Choice(alternatives) if alternatives.is_empty() => out.push_str("blank()"),
+            Expr::Choice(alternatives) if alternatives.len() == 1 => {
+                self.write_expr(out, rule, &alternatives[0])?
+            }
+            Expr::Choice(alternatives) => self.write_call(out, rule, "choice", alternatives)?,
+            Expr::Optional(expr) => self.write_call(out, rule, "optional", [&**expr])?,
+            Expr::Repeat(expr) => self.write_call(out, rule, "repeat", [&**expr])?,
+            Expr::Repeat1(expr) => self.write_call(out, rule, "repeat1", [&**expr])?,
+        }
+        Ok(())
+    }
+
+    fn write_call<'e>(
+        &self,
+        out: &mut String,
+        rule: &str,
+        function: &str,
+        args: impl IntoIterator<Item = &'e Expr>,
+    ) -> Result<(), ExportError> {
+        out.push_str(function);
+        out.push('(');
+        for (i, arg) in args.into_iter().enumerate() {
+            if i > 0 {
+                out.push_str(", ");
+            }
+            self.write_expr(out, rule, arg)?;
+        }
+        out.push(')');
+        Ok(())
+    }
+}
+
+fn snake_case(name: &str) -> String {
+    let mut out = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 {
+                out.push('_');
+            }
+            out.extend(c.to_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+fn js_string(text: &str) -> String {
+    let mut out = String::from("'");
+    for c in text.chars() {
+        match c {
+            '\'' => out.push_str("\\'"),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c => out.push(c),
+        }
+    }
+    out.push('\'');
+    out
+}
+
+/// Escapes the slashes of a regular expression not already escaped, so it
+/// can be written as a JavaScript regex literal.
+fn escape_regex(regex: &str) -> String {
+    let mut out = String::new();
+    let mut escaped = false;
+    for c in regex.chars() {
+        if c == '/' && !escaped {
+            out.push('\\');
+        }
+        escaped = c == '\\' && !escaped;
+        out.push(c);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_errors() {
+        let grammar = Grammar::new().with_rule("a", Expr::seq([Expr::token("X"), Expr::rule("b")]));
+        let exporter = TreeSitterExporter::new("t", &grammar);
+        assert_eq!(
+            exporter.export(),
+            Err(ExportError::MissingTerminal {
+                token: "X".to_string()
+            })
+        );
+        let exporter = exporter.with_terminal("X", Terminal::literal("x"));
+        assert_eq!(
+            exporter.export().unwrap_err().to_string(),
+            "rule `a` refers to undefined rule `b`"
+        );
+        assert_eq!(
+            TreeSitterExporter::new("t", &Grammar::new()).export(),
+            Err(ExportError::Empty)
+        );
+        let grammar = grammar
+            .with_rule("b", Expr::empty())
+            .with_token_def("X", Terminal::literal("y"));
+        assert!(TreeSitterExporter::new("t", &grammar)
+            .export()
+            .unwrap()
+            .contains("a: $ => seq('y', $.b),"));
+    }
+
+    #[test]
+    fn test_escaping() {
+        let grammar = Grammar::new().with_rule(
+            "doc",
+            Expr::choice([
+                Expr::optional(Expr::token("Quote")),
+                Expr::token("LineComment"),
+                Expr::empty(),
+            ]),
+        );
+        let js = TreeSitterExporter::new("t", &grammar)
+            .with_terminal("Quote", Terminal::literal("'\\"))
+            .with_terminal("LineComment", Terminal::pattern(r"//.*|a\/b"))
+            .export()
+            .unwrap();
+        assert!(js.contains(r"doc: $ => choice(optional('\'\\'), $.line_comment, blank()),"));
+        assert!(js.contains(r"line_comment: $ => /\/\/.*|a\/b/,"));
+    }
+}
@@ -0,0 +1,36 @@
+//! Syntax highlighting.
+//!
+//! This module classifies spanned tokens into [`HighlightKind`]s with
+//! [`highlight`], and renders highlighted source text for terminals with an
+//! [`AnsiHighlighter`] or for web pages with an [`HtmlHighlighter`].
+//!
+//! # Examples
+//! ```
+//! use grammarsmith::highlight::*;
+//! use grammarsmith::position::{Span, WithSpan};
+//!
+//! let source = "let x = 1;";
+//! let tokens = [
+//!     WithSpan::new("let", Span::new(0, 3).unwrap()),
+//!     WithSpan::new("x", Span::new(4, 5).unwrap()),
+//!     WithSpan::new("1", Span::new(8, 9).unwrap()),
+//! ];
+//! let classify = |token: &&str| match *token {
+//!     "let" => HighlightKind::Keyword,
+//!     "1" => HighlightKind::Number,
+//!     _ => HighlightKind::Variable,
+//! };
+//!
+//! let highlights = highlight(source, &tokens, classify);
+//! assert_eq!(highlights[0].value, HighlightKind::Keyword);
+//! assert_eq!(highlights[1].value, HighlightKind::Plain);
+//! assert_eq!(highlights[1].span, Span::new(3, 4).unwrap());
+//! ```
+
+pub mod ansi;
+pub mod classify;
+pub mod html;
+
+pub use ansi::*;
+pub use classify::*;
+pub use html::*;
@@ -0,0 +1,147 @@
+use super::{highlight, HighlightKind};
+use crate::position::WithSpan;
+use std::io;
+
+/// The ANSI escape sequences used for each [`HighlightKind`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Theme {
+    styles: [&'static str; HighlightKind::ALL.len()],
+    /// Ends each styled piece of text.
+    pub reset: &'static str,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            styles: [""; HighlightKind::ALL.len()],
+            reset: "\x1b[0m",
+        }
+        .with_style(HighlightKind::Keyword, "\x1b[35m")
+        .with_style(HighlightKind::Type, "\x1b[33m")
+        .with_style(HighlightKind::Function, "\x1b[34m")
+        .with_style(HighlightKind::Constant, "\x1b[36m")
+        .with_style(HighlightKind::Number, "\x1b[36m")
+        .with_style(HighlightKind::String, "\x1b[32m")
+        .with_style(HighlightKind::Comment, "\x1b[2m")
+        .with_style(HighlightKind::Error, "\x1b[4;31m")
+    }
+}
+
+impl Theme {
+    /// Returns the escape sequence for `kind`, empty if it is not styled.
+    pub fn style(&self, kind: HighlightKind) -> &'static str {
+        self.styles[kind as usize]
+    }
+
+    /// Sets the escape sequence for `kind`; an empty one leaves it unstyled.
+    pub fn with_style(mut self, kind: HighlightKind, style: &'static str) -> Self {
+        self.styles[kind as usize] = style;
+        self
+    }
+}
+
+/// Renders source text highlighted with ANSI escape sequences.
+///
+/// Styles are reset at the end of every line, so each line of the output can
+/// be printed on its own, for example after a REPL prompt.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::highlight::*;
+/// use grammarsmith::position::{Span, WithSpan};
+///
+/// let tokens = [WithSpan::new(HighlightKind::Keyword, Span::new(0, 2).unwrap())];
+/// let highlighter = AnsiHighlighter::new();
+///
+/// assert_eq!(
+///     highlighter.render("if x", &tokens, |kind| *kind),
+///     "\x1b[35mif\x1b[0m x"
+/// );
+/// ```
+#[derive(Debug, Copy, Clone, Default)]
+pub struct AnsiHighlighter {
+    theme: Theme,
+}
+
+impl AnsiHighlighter {
+    /// Creates a highlighter using the default [`Theme`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    /// Renders `source` with its `tokens` classified by `classify`.
+    ///
+    /// See [`highlight`] for how the tokens are laid out.
+    pub fn render<T>(
+        &self,
+        source: &str,
+        tokens: &[WithSpan<T>],
+        classify: impl Fn(&T) -> HighlightKind,
+    ) -> String {
+        let mut out = String::with_capacity(source.len());
+        for piece in highlight(source, tokens, classify) {
+            let style = self.theme.style(piece.value);
+            let text = &source[piece.span.start()..piece.span.end()];
+            if style.is_empty() {
+                out.push_str(text);
+                continue;
+            }
+            for (i, line) in text.split('\n').enumerate() {
+                if i > 0 {
+                    out.push('\n');
+                }
+                if !line.is_empty() {
+                    out.push_str(style);
+                    out.push_str(line);
+                    out.push_str(self.theme.reset);
+                }
+            }
+        }
+        out
+    }
+
+    /// Renders `source` with its `tokens` classified by `classify` into
+    /// `writer`.
+    pub fn emit<T>(
+        &self,
+        writer: &mut impl io::Write,
+        source: &str,
+        tokens: &[WithSpan<T>],
+        classify: impl Fn(&T) -> HighlightKind,
+    ) -> io::Result<()> {
+        writer.write_all(self.render(source, tokens, classify).as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::position::Span;
+
+    #[test]
+    fn test_multiline_token() {
+        let tokens = [WithSpan::new((), Span::new(2, 10).unwrap())];
+        let theme = Theme::default().with_style(HighlightKind::Comment, "<c>");
+        let rendered =
+            AnsiHighlighter::new()
+                .with_theme(theme)
+                .render("x /*a\n\nb*/", &tokens, |_| HighlightKind::Comment);
+        assert_eq!(rendered, "x <c>/*a\x1b[0m\n\n<c>b*/\x1b[0m");
+    }
+
+    #[test]
+    fn test_unstyled_kind() {
+        let tokens = [WithSpan::new((), Span::new(0, 1).unwrap())];
+        let theme = Theme::default().with_style(HighlightKind::Operator, "");
+        assert_eq!(theme.style(HighlightKind::Keyword), "\x1b[35m");
+        let rendered = AnsiHighlighter::new()
+            .with_theme(theme)
+            .render("+", &tokens, |_| HighlightKind::Operator);
+        assert_eq!(rendered, "+");
+    }
+}
@@ -0,0 +1,179 @@
+use crate::parser::Token;
+use crate::position::{Span, WithSpan};
+
+/// The highlighting class of a piece of source text.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum HighlightKind {
+    /// Text without highlighting, such as whitespace.
+    #[default]
+    Plain,
+    Keyword,
+    Type,
+    Function,
+    Variable,
+    Constant,
+    Number,
+    String,
+    Comment,
+    Operator,
+    Punctuation,
+    /// Tokens the lexer could not make sense of.
+    Error,
+}
+
+impl HighlightKind {
+    /// Every kind, in declaration order.
+    pub const ALL: &'static [HighlightKind] = &[
+        HighlightKind::Plain,
+        HighlightKind::Keyword,
+        HighlightKind::Type,
+        HighlightKind::Function,
+        HighlightKind::Variable,
+        HighlightKind::Constant,
+        HighlightKind::Number,
+        HighlightKind::String,
+        HighlightKind::Comment,
+        HighlightKind::Operator,
+        HighlightKind::Punctuation,
+        HighlightKind::Error,
+    ];
+
+    /// Returns the name of the kind in lowercase, such as `keyword`.
+    pub fn name(self) -> &'static str {
+        match self {
+            HighlightKind::Plain => "plain",
+            HighlightKind::Keyword => "keyword",
+            HighlightKind::Type => "type",
+            HighlightKind::Function => "function",
+            HighlightKind::Variable => "variable",
+            HighlightKind::Constant => "constant",
+            HighlightKind::Number => "number",
+            HighlightKind::String => "string",
+            HighlightKind::Comment => "comment",
+            HighlightKind::Operator => "operator",
+            HighlightKind::Punctuation => "punctuation",
+            HighlightKind::Error => "error",
+        }
+    }
+}
+
+/// Classifies tokens by their kind, for tokens implementing [`Token`].
+///
+/// # Examples
+/// ```
+/// use grammarsmith::highlight::{by_kind, HighlightKind};
+/// use grammarsmith::parser::Token;
+///
+/// #[derive(Debug, Clone, Copy, PartialEq)]
+/// enum Kind {
+///     Fn,
+///     Name,
+/// }
+///
+/// impl Token for Kind {
+///     type Kind = Kind;
+///
+///     fn to_kind(&self) -> Kind {
+///         *self
+///     }
+/// }
+///
+/// let classify = by_kind(|kind: Kind| match kind {
+///     Kind::Fn => HighlightKind::Keyword,
+///     Kind::Name => HighlightKind::Function,
+/// });
+/// assert_eq!(classify(&Kind::Fn), HighlightKind::Keyword);
+/// ```
+pub fn by_kind<T: Token>(
+    classify: impl Fn(T::Kind) -> HighlightKind,
+) -> impl Fn(&T) -> HighlightKind {
+    move |token| classify(token.to_kind())
+}
+
+/// Splits `source` into highlighted pieces, classifying each token with
+/// `classify`.
+///
+/// The pieces cover the whole source in order: text between tokens, such
+/// as whitespace, is [`HighlightKind::Plain`]. Tokens are expected in source
+/// order; the part of a token overlapping an earlier one is ignored, as are
+/// empty tokens. Adjacent pieces of the same kind are merged.
+///
+/// # Panics
+/// Panics if a token span is out of bounds of `source` or does not lie on
+/// character boundaries.
+pub fn highlight<T>(
+    source: &str,
+    tokens: &[WithSpan<T>],
+    classify: impl Fn(&T) -> HighlightKind,
+) -> Vec<WithSpan<HighlightKind>> {
+    let mut highlights: Vec<WithSpan<HighlightKind>> = Vec::new();
+    let mut push = |start: usize, end: usize, kind: HighlightKind| {
+        if start >= end {
+            return;
+        }
+        match highlights.last_mut() {
+            Some(last) if last.value == kind && last.span.end() == start => {
+                last.span = Span::new(last.span.start(), end).unwrap();
+            }
+            _ => highlights.push(WithSpan::new(kind, Span::new(start, end).unwrap())),
+        }
+    };
+
+    let mut position = 0;
+    for token in tokens {
+        let end = token.span.end();
+        if end <= position {
+            continue;
+        }
+        let start = token.span.start().max(position);
+        assert!(
+            source.is_char_boundary(start) && end <= source.len() && source.is_char_boundary(end),
+            "token span is not within the source"
+        );
+        push(position, start, HighlightKind::Plain);
+        push(start, end, classify(&token.value));
+        position = end;
+    }
+    push(position, source.len(), HighlightKind::Plain);
+    highlights
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_covers_source() {
+        let tokens = [
+            WithSpan::new(1, Span::new(1, 3).unwrap()),
+            WithSpan::new(1, Span::new(3, 4).unwrap()),
+            WithSpan::new(2, Span::new(5, 5).unwrap()),
+        ];
+        let highlights = highlight(" abc ", &tokens, |_| HighlightKind::Number);
+        assert_eq!(
+            highlights,
+            [
+                WithSpan::new(HighlightKind::Plain, Span::new(0, 1).unwrap()),
+                WithSpan::new(HighlightKind::Number, Span::new(1, 4).unwrap()),
+                WithSpan::new(HighlightKind::Plain, Span::new(4, 5).unwrap()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_overlapping_tokens() {
+        let tokens = [
+            WithSpan::new(HighlightKind::String, Span::new(0, 4).unwrap()),
+            WithSpan::new(HighlightKind::Error, Span::new(2, 6).unwrap()),
+            WithSpan::new(HighlightKind::Comment, Span::new(1, 3).unwrap()),
+        ];
+        let highlights = highlight("abcdef", &tokens, |kind| *kind);
+        assert_eq!(
+            highlights,
+            [
+                WithSpan::new(HighlightKind::String, Span::new(0, 4).unwrap()),
+                WithSpan::new(HighlightKind::Error, Span::new(4, 6).unwrap()),
+            ]
+        );
+    }
+}
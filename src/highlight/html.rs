@@ -0,0 +1,177 @@
+use super::{highlight, HighlightKind};
+use crate::diagnostics::escape_html;
+use crate::position::WithSpan;
+
+/// A default stylesheet for the classes of [`HtmlHighlighter`] with the
+/// [`Prefixed`] scheme.
+pub const HIGHLIGHT_CSS: &str = "\
+pre.gs-highlight { font-family: monospace; }
+.gs-keyword { color: #8e44ad; }
+.gs-type { color: #b8860b; }
+.gs-function { color: #1e5aa8; }
+.gs-constant, .gs-number { color: #008b8b; }
+.gs-string { color: #2e8b57; }
+.gs-comment { color: #808080; font-style: italic; }
+.gs-error { color: #d0312d; text-decoration: underline wavy; }
+";
+
+/// Decides the CSS class of each [`HighlightKind`].
+///
+/// Closures from a kind to an optional class name implement this trait.
+pub trait ClassScheme {
+    /// Returns the class of `kind`, or None to leave its text unwrapped.
+    fn class_name(&self, kind: HighlightKind) -> Option<String>;
+}
+
+impl<F: Fn(HighlightKind) -> Option<String>> ClassScheme for F {
+    fn class_name(&self, kind: HighlightKind) -> Option<String> {
+        self(kind)
+    }
+}
+
+/// Names classes after the kind with a prefix, such as `gs-keyword`, and
+/// leaves plain text unwrapped.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Prefixed(pub &'static str);
+
+impl Default for Prefixed {
+    fn default() -> Self {
+        Prefixed("gs-")
+    }
+}
+
+impl ClassScheme for Prefixed {
+    fn class_name(&self, kind: HighlightKind) -> Option<String> {
+        match kind {
+            HighlightKind::Plain => None,
+            kind => Some(format!("{}{}", self.0, kind.name())),
+        }
+    }
+}
+
+/// Renders highlighted source text as HTML, for documentation sites and web
+/// playgrounds.
+///
+/// Each highlighted piece is wrapped in a `<span>` whose class is given by
+/// the [`ClassScheme`], by default [`Prefixed`]; see [`HIGHLIGHT_CSS`]. All
+/// text is escaped, and whitespace is kept as is, so the output belongs in a
+/// `<pre>` element.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::highlight::*;
+/// use grammarsmith::position::{Span, WithSpan};
+///
+/// let tokens = [
+///     WithSpan::new(HighlightKind::Keyword, Span::new(0, 2).unwrap()),
+///     WithSpan::new(HighlightKind::Operator, Span::new(5, 6).unwrap()),
+/// ];
+///
+/// let html = HtmlHighlighter::new().render("if a < b", &tokens, |kind| *kind);
+/// assert_eq!(
+///     html,
+///     "<pre class=\"gs-highlight\"><span class=\"gs-keyword\">if</span> a \
+///      <span class=\"gs-operator\">&lt;</span> b</pre>"
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HtmlHighlighter<S = Prefixed> {
+    scheme: S,
+}
+
+impl HtmlHighlighter {
+    /// Creates a highlighter using the [`Prefixed`] scheme.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<S: ClassScheme> HtmlHighlighter<S> {
+    /// Sets the scheme deciding the class of each kind.
+    ///
+    /// # Examples
+    /// ```
+    /// use grammarsmith::highlight::*;
+    /// use grammarsmith::position::{Span, WithSpan};
+    ///
+    /// let tokens = [WithSpan::new(HighlightKind::String, Span::new(0, 3).unwrap())];
+    /// let highlighter = HtmlHighlighter::new().with_scheme(|kind: HighlightKind| match kind {
+    ///     HighlightKind::String => Some("hljs-string".to_string()),
+    ///     _ => None,
+    /// });
+    ///
+    /// assert_eq!(
+    ///     highlighter.render_inline("\"a\"", &tokens, |kind| *kind),
+    ///     "<span class=\"hljs-string\">&quot;a&quot;</span>"
+    /// );
+    /// ```
+    pub fn with_scheme<T: ClassScheme>(self, scheme: T) -> HtmlHighlighter<T> {
+        HtmlHighlighter { scheme }
+    }
+
+    /// Renders `source` with its `tokens` classified by `classify`, inside a
+    /// `<pre class="gs-highlight">` element.
+    pub fn render<T>(
+        &self,
+        source: &str,
+        tokens: &[WithSpan<T>],
+        classify: impl Fn(&T) -> HighlightKind,
+    ) -> String {
+        format!(
+            "<pre class=\"gs-highlight\">{}</pre>",
+            self.render_inline(source, tokens, classify)
+        )
+    }
+
+    /// Renders `source` with its `tokens` classified by `classify`, without
+    /// a surrounding element.
+    pub fn render_inline<T>(
+        &self,
+        source: &str,
+        tokens: &[WithSpan<T>],
+        classify: impl Fn(&T) -> HighlightKind,
+    ) -> String {
+        let mut out = String::with_capacity(source.len());
+        for piece in highlight(source, tokens, classify) {
+            let text = escape_html(&source[piece.span.start()..piece.span.end()]);
+            match self.scheme.class_name(piece.value) {
+                Some(class) => {
+                    out.push_str("<span class=\"");
+                    out.push_str(&escape_html(&class));
+                    out.push_str("\">");
+                    out.push_str(&text);
+                    out.push_str("</span>");
+                }
+                None => out.push_str(&text),
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::position::Span;
+
+    #[test]
+    fn test_escapes_and_keeps_whitespace() {
+        let tokens = [WithSpan::new((), Span::new(3, 8).unwrap())];
+        let html = HtmlHighlighter::new()
+            .render_inline("\t<\n'a&b'\n", &tokens, |_| HighlightKind::String);
+        assert_eq!(
+            html,
+            "\t&lt;\n<span class=\"gs-string\">&#39;a&amp;b&#39;</span>\n"
+        );
+    }
+
+    #[test]
+    fn test_prefixed_scheme() {
+        let scheme = Prefixed("tok-");
+        assert_eq!(scheme.class_name(HighlightKind::Plain), None);
+        assert_eq!(
+            scheme.class_name(HighlightKind::Punctuation).as_deref(),
+            Some("tok-punctuation")
+        );
+    }
+}
@@ -0,0 +1,351 @@
+//! String interning.
+//!
+//! An [`Interner`] stores each distinct string once and hands out
+//! [`Symbol`]s, small copyable ids that compare and hash as integers. Tokens
+//! and AST nodes can hold symbols instead of `String`s, which saves the
+//! allocation and copy of every identifier occurrence.
+//!
+//! # Examples
+//! ```
+//! use grammarsmith::intern::*;
+//! use grammarsmith::scanner::Scanner;
+//!
+//! const LET: Symbol = Symbol::from_u32(0);
+//!
+//! let mut interner = Interner::prefilled(&["let"]);
+//! let mut scanner = Scanner::new("let x");
+//! scanner.consume_while(|c| c.is_alphabetic());
+//! assert_eq!(scanner.intern(&mut interner), LET);
+//!
+//! let x = interner.intern("x");
+//! assert_eq!(interner.intern("x"), x);
+//! assert_eq!(interner.resolve(x), "x");
+//! ```
+
+use crate::parser::{EndOfFile, Parser, Token};
+use crate::position::GetSpan;
+use crate::scanner::Scanner;
+use crate::source::SourceText;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{BuildHasherDefault, Hasher};
+use std::sync::{Arc, RwLock};
+
+/// An interned string, resolved by the [`Interner`] that produced it.
+///
+/// Symbols are numbered in the order their strings were first interned,
+/// starting from zero, so the symbols of
+/// [prefilled](Interner::prefilled) strings can be declared as constants.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
+pub struct Symbol(u32);
+
+impl Symbol {
+    pub const fn from_u32(index: u32) -> Self {
+        Symbol(index)
+    }
+
+    pub const fn as_u32(self) -> u32 {
+        self.0
+    }
+
+    fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Symbol({})", self.0)
+    }
+}
+
+/// The FxHash function used by rustc: much faster than the default hasher
+/// on short keys such as identifiers, and not resistant to collision
+/// attacks, which do not matter for source code.
+#[derive(Default)]
+struct FxHasher {
+    hash: u64,
+}
+
+const SEED: u64 = 0x51_7c_c1_b7_27_22_0a_95;
+
+impl FxHasher {
+    fn add(&mut self, word: u64) {
+        self.hash = (self.hash.rotate_left(5) ^ word).wrapping_mul(SEED);
+    }
+}
+
+impl Hasher for FxHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        let mut chunks = bytes.chunks_exact(8);
+        for chunk in &mut chunks {
+            self.add(u64::from_le_bytes(chunk.try_into().unwrap()));
+        }
+        for &byte in chunks.remainder() {
+            self.add(u64::from(byte));
+        }
+    }
+
+    fn write_u8(&mut self, i: u8) {
+        self.add(u64::from(i));
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.add(i as u64);
+    }
+
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+}
+
+type FxBuildHasher = BuildHasherDefault<FxHasher>;
+
+/// Interns strings into [`Symbol`]s.
+///
+/// Each string is stored once, shared between the lookup table and the
+/// list of strings resolving symbols.
+#[derive(Clone, Default)]
+pub struct Interner {
+    symbols: HashMap<Arc<str>, Symbol, FxBuildHasher>,
+    strings: Vec<Arc<str>>,
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an interner with `strings` already interned, as symbols `0`,
+    /// `1`, and so on, for keywords and other strings known in advance.
+    ///
+    /// # Panics
+    /// Panics if a string is repeated, as its symbols would not be numbered
+    /// as expected.
+    pub fn prefilled(strings: &[&str]) -> Self {
+        let mut interner = Interner::new();
+        for (i, string) in strings.iter().enumerate() {
+            assert_eq!(
+                interner.intern(string).index(),
+                i,
+                "duplicate prefilled string `{}`",
+                string
+            );
+        }
+        interner
+    }
+
+    /// Returns the symbol of `string`, interning it if needed.
+    ///
+    /// # Panics
+    /// Panics if more than `u32::MAX` strings are interned.
+    pub fn intern(&mut self, string: &str) -> Symbol {
+        if let Some(&symbol) = self.symbols.get(string) {
+            return symbol;
+        }
+        let index = u32::try_from(self.strings.len()).expect("too many interned strings");
+        let symbol = Symbol(index);
+        let string: Arc<str> = Arc::from(string);
+        self.strings.push(string.clone());
+        self.symbols.insert(string, symbol);
+        symbol
+    }
+
+    /// Returns the symbol of the text covered by `span` in `source`.
+    pub fn intern_span(&mut self, source: &str, span: impl GetSpan) -> Symbol {
+        let span = span.get_span();
+        self.intern(&source[span.start()..span.end()])
+    }
+
+    /// Returns the symbol of `string` if it has been interned.
+    pub fn get(&self, string: &str) -> Option<Symbol> {
+        self.symbols.get(string).copied()
+    }
+
+    /// Returns the string of a symbol.
+    ///
+    /// # Panics
+    /// Panics if the symbol was produced by another interner holding more
+    /// strings.
+    pub fn resolve(&self, symbol: Symbol) -> &str {
+        &self.strings[symbol.index()]
+    }
+
+    /// Returns the string of a symbol, if this interner produced it.
+    pub fn try_resolve(&self, symbol: Symbol) -> Option<&str> {
+        self.strings.get(symbol.index()).map(|string| &**string)
+    }
+
+    /// Returns the number of interned strings.
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+
+    /// Iterates over the symbols and their strings, in symbol order.
+    pub fn iter(&self) -> impl Iterator<Item = (Symbol, &str)> {
+        self.strings
+            .iter()
+            .enumerate()
+            .map(|(i, string)| (Symbol(i as u32), &**string))
+    }
+}
+
+impl fmt::Debug for Interner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+/// An [`Interner`] that can be shared between threads, such as by parsers
+/// running in parallel over the files of a project.
+///
+/// Lookups of strings already interned only take a read lock.
+#[derive(Debug, Default)]
+pub struct SyncInterner {
+    inner: RwLock<Interner>,
+}
+
+impl SyncInterner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an interner with `strings` already interned, like
+    /// [`Interner::prefilled`].
+    pub fn prefilled(strings: &[&str]) -> Self {
+        SyncInterner {
+            inner: RwLock::new(Interner::prefilled(strings)),
+        }
+    }
+
+    /// Returns the symbol of `string`, interning it if needed.
+    pub fn intern(&self, string: &str) -> Symbol {
+        if let Some(symbol) = self.get(string) {
+            return symbol;
+        }
+        self.inner
+            .write()
+            .unwrap_or_else(|err| err.into_inner())
+            .intern(string)
+    }
+
+    /// Returns the symbol of `string` if it has been interned.
+    pub fn get(&self, string: &str) -> Option<Symbol> {
+        self.read().get(string)
+    }
+
+    /// Returns the string of a symbol.
+    ///
+    /// The string is shared rather than borrowed, as the interner may grow
+    /// meanwhile.
+    ///
+    /// # Panics
+    /// Panics if the symbol was produced by another interner holding more
+    /// strings.
+    pub fn resolve(&self, symbol: Symbol) -> Arc<str> {
+        self.read().strings[symbol.index()].clone()
+    }
+
+    /// Returns the number of interned strings.
+    pub fn len(&self) -> usize {
+        self.read().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.read().is_empty()
+    }
+
+    /// Returns the inner interner, for resolving symbols without locking
+    /// once parsing is done.
+    pub fn into_inner(self) -> Interner {
+        self.inner
+            .into_inner()
+            .unwrap_or_else(|err| err.into_inner())
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, Interner> {
+        self.inner.read().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl From<Interner> for SyncInterner {
+    fn from(interner: Interner) -> Self {
+        SyncInterner {
+            inner: RwLock::new(interner),
+        }
+    }
+}
+
+impl<S: SourceText + ?Sized> Scanner<'_, S> {
+    /// Interns the text of the current token.
+    pub fn intern(&self, interner: &mut Interner) -> Symbol {
+        interner.intern(&self.slice_cow())
+    }
+}
+
+impl<T> Parser<'_, T>
+where
+    T: Token + EndOfFile,
+{
+    /// Interns the text of the previously consumed token, taken from
+    /// `source`, the text its span refers to.
+    pub fn intern_previous(&self, source: &str, interner: &mut Interner) -> Symbol {
+        interner.intern_span(source, self.previous())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_intern_and_resolve() {
+        let mut interner = Interner::prefilled(&["fn", "let"]);
+        let names = ["x", "y", "let", "x", "a_much_longer_identifier_name"];
+        let symbols: Vec<_> = names.iter().map(|name| interner.intern(name)).collect();
+        assert_eq!(symbols[2], Symbol::from_u32(1));
+        assert_eq!(symbols[0], symbols[3]);
+        assert_eq!(
+            symbols.iter().collect::<HashSet<_>>().len(),
+            4,
+            "distinct strings get distinct symbols"
+        );
+        for (name, symbol) in names.iter().zip(&symbols) {
+            assert_eq!(interner.resolve(*symbol), *name);
+        }
+        assert_eq!(interner.len(), 5);
+        assert_eq!(interner.get("z"), None);
+        assert_eq!(interner.try_resolve(Symbol::from_u32(5)), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "duplicate prefilled string `if`")]
+    fn test_duplicate_prefilled() {
+        Interner::prefilled(&["if", "if"]);
+    }
+
+    #[test]
+    fn test_sync_interner() {
+        let interner = SyncInterner::prefilled(&["fn"]);
+        let symbols: Vec<_> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..4)
+                .map(|_| scope.spawn(|| ["a", "b", "fn"].map(|name| interner.intern(name))))
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        assert!(symbols.windows(2).all(|pair| pair[0] == pair[1]));
+        assert_eq!(symbols[0][2], Symbol::from_u32(0));
+        assert_eq!(&*interner.resolve(symbols[0][0]), "a");
+        assert_eq!(interner.into_inner().len(), 3);
+    }
+}
@@ -0,0 +1,130 @@
+//! Keyword tables.
+//!
+//! Lexers usually scan keywords as identifiers and then look the text up in
+//! a table. [`Keywords`] is such a table, built at compile time by the
+//! `keywords!` macro (with the `derive` feature), which buckets keywords by
+//! length so a lookup compares against only a few candidates.
+//!
+//! # Examples
+//! ```
+//! # #[cfg(feature = "derive")]
+//! # {
+//! use grammarsmith::keywords::{keywords, Keywords};
+//!
+//! #[derive(Debug, PartialEq)]
+//! enum Token {
+//!     If,
+//!     Else,
+//!     While,
+//!     Identifier(String),
+//! }
+//!
+//! const KEYWORDS: Keywords<Token> = keywords! {
+//!     "if" => Token::If,
+//!     "else" => Token::Else,
+//!     "while" => Token::While,
+//! };
+//!
+//! fn identifier(text: &str) -> Token {
+//!     KEYWORDS
+//!         .get(text)
+//!         .unwrap_or_else(|| Token::Identifier(text.to_string()))
+//! }
+//!
+//! assert_eq!(identifier("while"), Token::While);
+//! assert_eq!(identifier("whale"), Token::Identifier("whale".to_string()));
+//! assert_eq!(KEYWORDS.words(), ["if", "else", "while"]);
+//! # }
+//! ```
+
+use std::fmt;
+
+/// Generates a [`Keywords`] table from `"text" => value` pairs.
+///
+/// The values are expressions evaluated on every lookup, typically unit
+/// variants of a token enum. Duplicate keywords are a compile error.
+#[cfg(feature = "derive")]
+pub use grammarsmith_macros::keywords;
+
+/// A table of keywords mapping their text to a value, usually a token.
+pub struct Keywords<T: 'static> {
+    words: &'static [&'static str],
+    lookup: fn(&str) -> Option<T>,
+}
+
+impl<T> Keywords<T> {
+    /// Creates a table of `words` looked up with `lookup`, which must return
+    /// `Some` for exactly the given words.
+    ///
+    /// Tables are usually generated by the `keywords!` macro.
+    pub const fn new(words: &'static [&'static str], lookup: fn(&str) -> Option<T>) -> Self {
+        Keywords { words, lookup }
+    }
+
+    /// Returns the value of the keyword `text`, or None if it is not a
+    /// keyword.
+    pub fn get(&self, text: &str) -> Option<T> {
+        (self.lookup)(text)
+    }
+
+    /// Returns whether `text` is a keyword.
+    pub fn contains(&self, text: &str) -> bool {
+        self.get(text).is_some()
+    }
+
+    /// Returns the keywords in declaration order.
+    pub fn words(&self) -> &'static [&'static str] {
+        self.words
+    }
+
+    /// Returns the keywords and their values in declaration order, for
+    /// example to offer keywords as completions.
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, T)> + '_ {
+        self.words
+            .iter()
+            .filter_map(|word| Some((*word, self.get(word)?)))
+    }
+
+    /// Returns the number of keywords.
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    /// Returns true if there are no keywords.
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+}
+
+impl<T> Clone for Keywords<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Keywords<T> {}
+
+impl<T> fmt::Debug for Keywords<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Keywords").field(&self.words).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEYWORDS: Keywords<u8> = Keywords::new(&["let", "in"], |text| match text {
+        "let" => Some(0),
+        "in" => Some(1),
+        _ => None,
+    });
+
+    #[test]
+    fn test_lookup() {
+        assert_eq!(KEYWORDS.get("in"), Some(1));
+        assert!(!KEYWORDS.contains("inn"));
+        assert_eq!(KEYWORDS.iter().collect::<Vec<_>>(), [("let", 0), ("in", 1)]);
+        assert_eq!(KEYWORDS.len(), 2);
+    }
+}
@@ -8,13 +8,71 @@
 //!
 //! # Crate Features
 //!
-//! - `serde`: Enable Serde serialization and deserialization for `BytePos` and `Span`.
+//! - `serde`: Enable Serde serialization and deserialization for positions, `WithSpan`,
+//!   `Diagnostic`, `ParseOutput`, `ParseTree` and `grammar::LlTable`, and the JSON and SARIF
+//!   diagnostic formats in `diagnostics::json` and `diagnostics::sarif`.
+//! - `annotate-snippets`: Enable rendering diagnostics with `annotate-snippets`.
+//! - `ariadne`: Enable rendering diagnostics with `ariadne`.
+//! - `arena`: Enable `arena::Arena`, a `bumpalo` bump allocator for AST nodes.
+//! - `chumsky`: Enable running `chumsky` 0.9 parsers on grammarsmith tokens and spans.
+//! - `codespan`: Enable rendering diagnostics with `codespan_reporting`.
+//! - `derive`: Enable `#[derive(Token)]`, generating the kind enum and the `Token`, `KindMeta` and
+//!   `EndOfFile` impls of a token enum, and the `keywords!` macro building keyword tables.
+//! - `fuzzing`: Enable `arbitrary` implementations and `proptest` strategies for the position types.
+//! - `graphemes`: Enable counting columns in extended grapheme clusters.
+//! - `lsp`: Enable conversions between positions and `lsp_types` positions and ranges.
+//! - `nom`: Enable running `nom` parsers from a `Scanner` with `Scanner::nom`.
+//! - `proc-macro2`: Enable mapping spans inside string literals to `proc_macro2::Span`s.
+//! - `rowan`: Enable building `rowan` green trees from parser events with `rowan::RowanSink`.
+//! - `rkyv`: Enable zero-copy `rkyv` archiving for `BytePos`, `Span` and `WithSpan`, so cached
+//!   token streams can be memory-mapped instead of deserialized.
+//! - `unicode-width`: Align the underlines of `diagnostics::TerminalRenderer` by display width, so
+//!   East Asian wide characters and emoji take two columns.
+//! - `wasm`: Enable JavaScript-friendly serializable views of tokens, spans and diagnostics, with
+//!   UTF-16 offsets and precomputed lines and columns, for web playgrounds.
+//! - `u64-positions`: Store `BytePos` as a `u64` instead of a `u32`, for inputs larger than 4GB.
 //!
 
+#[cfg(feature = "annotate-snippets")]
+pub mod annotate_snippets;
+#[cfg(feature = "arena")]
+pub mod arena;
+#[cfg(feature = "ariadne")]
+pub mod ariadne;
+#[cfg(feature = "chumsky")]
+pub mod chumsky;
+#[cfg(feature = "codespan")]
+pub mod codespan;
+pub mod diagnostics;
+pub mod document;
+pub mod event;
+#[cfg(feature = "fuzzing")]
+pub mod fuzzing;
+pub mod grammar;
+pub mod highlight;
+pub mod intern;
+pub mod keywords;
+#[cfg(feature = "lsp")]
+pub mod lsp;
+#[cfg(feature = "nom")]
+pub mod nom;
 pub mod parser;
 pub mod position;
+#[cfg(feature = "proc-macro2")]
+pub mod proc_macro;
+pub mod render;
+pub mod rewrite;
+#[cfg(feature = "rowan")]
+pub mod rowan;
 pub mod scanner;
+pub mod source;
+pub mod stream;
+pub mod testing;
+pub mod tree;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
 pub use parser::*;
 pub use position::*;
 pub use scanner::*;
+pub use source::*;
@@ -0,0 +1,367 @@
+//! Conversions between grammarsmith positions and diagnostics and
+//! `lsp_types`.
+//!
+//! LSP positions are zero-based lines and columns, where columns are counted
+//! in the position encoding negotiated with the client (UTF-16 unless the
+//! client says otherwise). [`LspDocument`] does the conversion for one source
+//! text.
+//!
+//! # Examples
+//! ```
+//! use grammarsmith::lsp::LspDocument;
+//! use grammarsmith::position::*;
+//! use lsp_types::{Position, PositionEncodingKind};
+//!
+//! let source = "let 🦀 = 1;\nfoo";
+//! let offsets = LineOffsets::new(source);
+//! let encoding = ColumnMode::try_from(&PositionEncodingKind::UTF16).unwrap();
+//! let doc = LspDocument::new(source, &offsets, encoding);
+//!
+//! let range = doc.range(Span::new(9, 10).unwrap());
+//! assert_eq!(range.start, Position::new(0, 7));
+//! assert_eq!(doc.span(range), Span::new(9, 10));
+//! ```
+//!
+//! Semantic tokens for highlighting are built with a [`SemanticTokensBuilder`],
+//! and document outlines and folding ranges with [`document_symbols`],
+//! [`folding_ranges`] and [`bracket_folding_ranges`].
+
+pub mod semantic;
+pub mod symbols;
+
+pub use semantic::*;
+pub use symbols::*;
+
+use crate::diagnostics::{Diagnostic, DiagnosticTag, Severity};
+use crate::position::*;
+use crate::source::{FileId, SourceFile, SourceMap};
+use lsp_types::{
+    DiagnosticRelatedInformation, DiagnosticSeverity, Location, NumberOrString, Position,
+    PositionEncodingKind, Range, Uri,
+};
+use std::fmt;
+
+/// Error returned for position encodings this crate does not know about.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnsupportedEncoding(pub String);
+
+impl fmt::Display for UnsupportedEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unsupported position encoding `{}`", self.0)
+    }
+}
+
+impl std::error::Error for UnsupportedEncoding {}
+
+impl TryFrom<&PositionEncodingKind> for ColumnMode {
+    type Error = UnsupportedEncoding;
+
+    fn try_from(kind: &PositionEncodingKind) -> Result<Self, Self::Error> {
+        match kind.as_str() {
+            "utf-8" => Ok(ColumnMode::Byte),
+            "utf-16" => Ok(ColumnMode::Utf16),
+            "utf-32" => Ok(ColumnMode::Char),
+            "grapheme" => Ok(ColumnMode::Grapheme),
+            other => Err(UnsupportedEncoding(other.to_string())),
+        }
+    }
+}
+
+/// Grapheme columns have no encoding in LSP, so they map to a `grapheme`
+/// kind that clients do not know about; servers should negotiate one of the
+/// other modes.
+impl From<ColumnMode> for PositionEncodingKind {
+    fn from(mode: ColumnMode) -> Self {
+        match mode {
+            ColumnMode::Byte => PositionEncodingKind::UTF8,
+            ColumnMode::Utf16 => PositionEncodingKind::UTF16,
+            ColumnMode::Char => PositionEncodingKind::UTF32,
+            ColumnMode::Grapheme => PositionEncodingKind::new("grapheme"),
+        }
+    }
+}
+
+/// Converts between byte positions in a source text and LSP positions.
+#[derive(Debug, Clone, Copy)]
+pub struct LspDocument<'a> {
+    source: &'a str,
+    offsets: &'a LineOffsets,
+    encoding: ColumnMode,
+}
+
+impl<'a> LspDocument<'a> {
+    /// Creates a converter for `source`, whose line offsets are `offsets`,
+    /// using the negotiated position encoding.
+    pub fn new(source: &'a str, offsets: &'a LineOffsets, encoding: ColumnMode) -> Self {
+        LspDocument {
+            source,
+            offsets,
+            encoding,
+        }
+    }
+
+    /// Returns the source text.
+    pub fn source(&self) -> &'a str {
+        self.source
+    }
+
+    /// Returns the line offsets of the source text.
+    pub fn offsets(&self) -> &'a LineOffsets {
+        self.offsets
+    }
+
+    /// Returns the position encoding columns are counted in.
+    pub fn encoding(&self) -> ColumnMode {
+        self.encoding
+    }
+
+    /// Converts a byte position into an LSP position.
+    pub fn position(&self, pos: BytePos) -> Position {
+        let (line, column) = self.offsets.line_column(pos, self.source, self.encoding);
+        Position::new((line - 1) as u32, (column - 1) as u32)
+    }
+
+    /// Converts a span into an LSP range.
+    pub fn range(&self, span: Span) -> Range {
+        Range::new(self.position(span.start), self.position(span.end))
+    }
+
+    /// Converts an LSP position into a byte position.
+    ///
+    /// Positions past the end of a line or of the document are clamped, as
+    /// the LSP specification requires.
+    pub fn byte_pos(&self, position: Position) -> BytePos {
+        self.offsets.offset(
+            position.line as usize + 1,
+            position.character as usize + 1,
+            self.source,
+            self.encoding,
+        )
+    }
+
+    /// Converts an LSP range into a span, returning `None` if the range ends
+    /// before it starts.
+    pub fn span(&self, range: Range) -> Option<Span> {
+        let start = self.byte_pos(range.start);
+        let end = self.byte_pos(range.end);
+        (start <= end).then_some(Span { start, end })
+    }
+}
+
+impl From<Severity> for DiagnosticSeverity {
+    fn from(severity: Severity) -> Self {
+        match severity {
+            Severity::Error => DiagnosticSeverity::ERROR,
+            Severity::Warning => DiagnosticSeverity::WARNING,
+            Severity::Note => DiagnosticSeverity::INFORMATION,
+            Severity::Help => DiagnosticSeverity::HINT,
+        }
+    }
+}
+
+impl From<DiagnosticTag> for lsp_types::DiagnosticTag {
+    fn from(tag: DiagnosticTag) -> Self {
+        match tag {
+            DiagnosticTag::Unnecessary => lsp_types::DiagnosticTag::UNNECESSARY,
+            DiagnosticTag::Deprecated => lsp_types::DiagnosticTag::DEPRECATED,
+        }
+    }
+}
+
+impl Diagnostic {
+    /// Converts the diagnostic into an LSP diagnostic for the document `uri`,
+    /// whose positions are converted by `doc`.
+    ///
+    /// The range is the primary label's span. Notes are appended to the
+    /// message, and the other labels become related information. Labels
+    /// pointing into other files are left out; use
+    /// [`Diagnostic::to_lsp_in`] to include them.
+    pub fn to_lsp(&self, uri: &Uri, doc: &LspDocument<'_>) -> lsp_types::Diagnostic {
+        self.convert_to_lsp(uri, doc, |_| None)
+    }
+
+    /// Converts the diagnostic reported for the file `file` of `map` into an
+    /// LSP diagnostic, including labels that point into other files.
+    ///
+    /// `uri` returns the URI of a file, or `None` to leave out labels in that
+    /// file. Returns `None` if `file` is not in `map` or has no URI.
+    pub fn to_lsp_in(
+        &self,
+        file: FileId,
+        map: &SourceMap,
+        encoding: ColumnMode,
+        uri: impl Fn(FileId, &SourceFile) -> Option<Uri>,
+    ) -> Option<lsp_types::Diagnostic> {
+        let document = |id: FileId| {
+            let source = map.get(id)?;
+            let doc = LspDocument::new(source.source(), source.line_offsets(), encoding);
+            Some((uri(id, source)?, doc))
+        };
+        let (main_uri, main_doc) = document(file)?;
+        Some(self.convert_to_lsp(&main_uri, &main_doc, document))
+    }
+
+    fn convert_to_lsp<'a>(
+        &self,
+        uri: &Uri,
+        doc: &LspDocument<'a>,
+        other: impl Fn(FileId) -> Option<(Uri, LspDocument<'a>)>,
+    ) -> lsp_types::Diagnostic {
+        let main = self
+            .primary_label()
+            .filter(|label| label.file.is_none())
+            .or_else(|| self.labels.iter().find(|label| label.file.is_none()));
+
+        let mut message = self.message.clone();
+        for note in &self.notes {
+            message.push_str("\nnote: ");
+            message.push_str(note);
+        }
+
+        let related: Vec<_> = self
+            .labels
+            .iter()
+            .filter(|label| {
+                !label.message.is_empty() || !main.is_some_and(|main| std::ptr::eq(main, *label))
+            })
+            .filter_map(|label| {
+                let location = match label.file {
+                    None => Location::new(uri.clone(), doc.range(label.span)),
+                    Some(id) => {
+                        let (uri, doc) = other(id)?;
+                        let range = doc.range(label.span);
+                        Location::new(uri, range)
+                    }
+                };
+                Some(DiagnosticRelatedInformation {
+                    location,
+                    message: label.message.clone(),
+                })
+            })
+            .collect();
+
+        lsp_types::Diagnostic {
+            range: main.map_or_else(Range::default, |label| doc.range(label.span)),
+            severity: Some(self.severity.into()),
+            code: self
+                .code
+                .as_ref()
+                .map(|code| NumberOrString::String(code.to_string())),
+            message,
+            related_information: (!related.is_empty()).then_some(related),
+            tags: (!self.tags.is_empty())
+                .then(|| self.tags.iter().map(|&tag| tag.into()).collect()),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::FileSpan;
+
+    #[test]
+    fn test_encodings() {
+        let source = "é🦀x\n";
+        let offsets = LineOffsets::new(source);
+        let x = BytePos(6);
+        for (kind, character) in [
+            (PositionEncodingKind::UTF8, 6),
+            (PositionEncodingKind::UTF16, 3),
+            (PositionEncodingKind::UTF32, 2),
+        ] {
+            let doc = LspDocument::new(source, &offsets, ColumnMode::try_from(&kind).unwrap());
+            assert_eq!(doc.position(x), Position::new(0, character));
+            assert_eq!(doc.byte_pos(Position::new(0, character)), x);
+            assert_eq!(PositionEncodingKind::from(doc.encoding()), kind);
+        }
+    }
+
+    #[test]
+    fn test_unknown_encoding() {
+        let kind = PositionEncodingKind::new("utf-7");
+        assert_eq!(
+            ColumnMode::try_from(&kind),
+            Err(UnsupportedEncoding("utf-7".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_end_of_document() {
+        let source = "ab\n";
+        let offsets = LineOffsets::new(source);
+        let doc = LspDocument::new(source, &offsets, ColumnMode::Utf16);
+        assert_eq!(doc.position(BytePos(3)), Position::new(1, 0));
+        assert_eq!(doc.byte_pos(Position::new(5, 0)), BytePos(3));
+        assert_eq!(doc.byte_pos(Position::new(0, 10)), BytePos(2));
+        let reversed = Range::new(Position::new(0, 2), Position::new(0, 1));
+        assert_eq!(doc.span(reversed), None);
+    }
+
+    #[test]
+    fn test_diagnostic_to_lsp() {
+        let source = "let 🦀 = x;\n";
+        let offsets = LineOffsets::new(source);
+        let doc = LspDocument::new(source, &offsets, ColumnMode::Utf16);
+        let uri: Uri = "file:///main.lang".parse().unwrap();
+        let diagnostic = Diagnostic::builder(Severity::Warning, "unused binding")
+            .code("W1")
+            .primary(Span::new(4, 8).unwrap(), "")
+            .secondary(Span::new(11, 12).unwrap(), "initialized here")
+            .note("prefix it with `_`")
+            .tag(DiagnosticTag::Unnecessary)
+            .build();
+
+        let converted = diagnostic.to_lsp(&uri, &doc);
+        assert_eq!(
+            converted.range,
+            Range::new(Position::new(0, 4), Position::new(0, 6))
+        );
+        assert_eq!(converted.severity, Some(DiagnosticSeverity::WARNING));
+        assert_eq!(converted.code, Some(NumberOrString::String("W1".into())));
+        assert_eq!(
+            converted.message,
+            "unused binding\nnote: prefix it with `_`"
+        );
+        assert_eq!(
+            converted.related_information,
+            Some(vec![DiagnosticRelatedInformation {
+                location: Location::new(uri, Range::new(Position::new(0, 9), Position::new(0, 10))),
+                message: "initialized here".into(),
+            }])
+        );
+        assert_eq!(
+            converted.tags,
+            Some(vec![lsp_types::DiagnosticTag::UNNECESSARY])
+        );
+    }
+
+    #[test]
+    fn test_diagnostic_to_lsp_in() {
+        let mut map = SourceMap::new();
+        let main = map.add("main.lang", "use lib;\nfn f() {}");
+        let lib = map.add("lib.lang", "\nfn f() {}");
+        let diagnostic = Diagnostic::builder(Severity::Error, "`f` is defined twice")
+            .primary(Span::new(9, 18).unwrap(), "redefined here")
+            .secondary_in(
+                FileSpan::new(lib, Span::new(1, 10).unwrap()),
+                "first defined here",
+            )
+            .build();
+
+        let uri = |_: FileId, file: &SourceFile| format!("file:///{}", file.name()).parse().ok();
+        let converted = diagnostic
+            .to_lsp_in(main, &map, ColumnMode::Utf16, uri)
+            .unwrap();
+        assert_eq!(converted.range.start, Position::new(1, 0));
+        let related = converted.related_information.unwrap();
+        assert_eq!(related.len(), 2);
+        assert_eq!(related[0].message, "redefined here");
+        assert_eq!(related[1].location.uri.as_str(), "file:///lib.lang");
+        assert_eq!(related[1].location.range.start, Position::new(1, 0));
+        assert!(diagnostic
+            .to_lsp_in(FileId(5), &map, ColumnMode::Utf16, uri)
+            .is_none());
+    }
+}
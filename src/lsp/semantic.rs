@@ -0,0 +1,286 @@
+use super::LspDocument;
+use crate::highlight::HighlightKind;
+use crate::position::lineoffset::trim_line_terminator;
+use crate::position::{Span, WithSpan};
+use lsp_types::{
+    SemanticToken, SemanticTokenModifier, SemanticTokenType, SemanticTokens, SemanticTokensDelta,
+    SemanticTokensEdit, SemanticTokensLegend,
+};
+
+/// Returns the legend for semantic tokens pushed with
+/// [`SemanticTokensBuilder::push_highlight`].
+///
+/// Constants are reported as read-only variables. Plain text, punctuation
+/// and errors have no semantic token type and are left to the client's
+/// syntax highlighting.
+pub fn highlight_legend() -> SemanticTokensLegend {
+    SemanticTokensLegend {
+        token_types: vec![
+            SemanticTokenType::KEYWORD,
+            SemanticTokenType::TYPE,
+            SemanticTokenType::FUNCTION,
+            SemanticTokenType::VARIABLE,
+            SemanticTokenType::NUMBER,
+            SemanticTokenType::STRING,
+            SemanticTokenType::COMMENT,
+            SemanticTokenType::OPERATOR,
+        ],
+        token_modifiers: vec![SemanticTokenModifier::READONLY],
+    }
+}
+
+/// Returns the token type index and modifiers of `kind` in
+/// [`highlight_legend`].
+fn highlight_token_type(kind: HighlightKind) -> Option<(u32, u32)> {
+    match kind {
+        HighlightKind::Keyword => Some((0, 0)),
+        HighlightKind::Type => Some((1, 0)),
+        HighlightKind::Function => Some((2, 0)),
+        HighlightKind::Variable => Some((3, 0)),
+        HighlightKind::Constant => Some((3, 1)),
+        HighlightKind::Number => Some((4, 0)),
+        HighlightKind::String => Some((5, 0)),
+        HighlightKind::Comment => Some((6, 0)),
+        HighlightKind::Operator => Some((7, 0)),
+        HighlightKind::Plain | HighlightKind::Punctuation | HighlightKind::Error => None,
+    }
+}
+
+/// Builds the LSP semantic tokens of a document.
+///
+/// Tokens can be pushed in any order; they are sorted when built, split at
+/// line breaks, since most clients do not support tokens spanning several
+/// lines, and encoded relative to each other with columns in the document's
+/// position encoding.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::lsp::{LspDocument, SemanticTokensBuilder};
+/// use grammarsmith::position::*;
+/// use lsp_types::SemanticToken;
+///
+/// let source = "let 🦀 = 1;\nx";
+/// let offsets = LineOffsets::new(source);
+/// let doc = LspDocument::new(source, &offsets, ColumnMode::Utf16);
+///
+/// let mut builder = SemanticTokensBuilder::new(doc);
+/// builder.push(Span::new(14, 15).unwrap(), 3, 0);
+/// builder.push(Span::new(0, 3).unwrap(), 0, 0);
+/// builder.push(Span::new(11, 12).unwrap(), 4, 0);
+///
+/// let data = builder.build().data;
+/// assert_eq!(
+///     data[1],
+///     SemanticToken {
+///         delta_line: 0,
+///         delta_start: 9,
+///         length: 1,
+///         token_type: 4,
+///         token_modifiers_bitset: 0,
+///     }
+/// );
+/// assert_eq!((data[2].delta_line, data[2].delta_start), (1, 0));
+/// ```
+#[derive(Debug, Clone)]
+pub struct SemanticTokensBuilder<'a> {
+    doc: LspDocument<'a>,
+    tokens: Vec<(Span, u32, u32)>,
+    result_id: Option<String>,
+}
+
+impl<'a> SemanticTokensBuilder<'a> {
+    pub fn new(doc: LspDocument<'a>) -> Self {
+        SemanticTokensBuilder {
+            doc,
+            tokens: Vec::new(),
+            result_id: None,
+        }
+    }
+
+    /// Sets the result id, which clients send back to request a delta.
+    pub fn with_result_id(mut self, result_id: impl Into<String>) -> Self {
+        self.result_id = Some(result_id.into());
+        self
+    }
+
+    /// Adds a token with the index of its type in the legend and the bitset
+    /// of its modifiers. Empty tokens are ignored.
+    pub fn push(&mut self, span: Span, token_type: u32, modifiers: u32) {
+        if !span.is_empty() {
+            self.tokens.push((span, token_type, modifiers));
+        }
+    }
+
+    /// Adds a highlighted piece using [`highlight_legend`]. Kinds without a
+    /// semantic token type are ignored.
+    pub fn push_highlight(&mut self, span: Span, kind: HighlightKind) {
+        if let Some((token_type, modifiers)) = highlight_token_type(kind) {
+            self.push(span, token_type, modifiers);
+        }
+    }
+
+    /// Adds highlighted pieces using [`highlight_legend`], such as those
+    /// returned by [`highlight`](crate::highlight::highlight).
+    pub fn extend_highlights<'h>(
+        &mut self,
+        highlights: impl IntoIterator<Item = &'h WithSpan<HighlightKind>>,
+    ) {
+        for piece in highlights {
+            self.push_highlight(piece.span, piece.value);
+        }
+    }
+
+    /// Returns the encoded tokens.
+    pub fn build(mut self) -> SemanticTokens {
+        self.tokens
+            .sort_by_key(|(span, _, _)| (span.start, span.end));
+        let source = self.doc.source();
+        let offsets = self.doc.offsets();
+        let encoding = self.doc.encoding();
+
+        let mut data = Vec::with_capacity(self.tokens.len());
+        let (mut line, mut character) = (0, 0);
+        for (span, token_type, modifiers) in self.tokens {
+            for (_, part) in offsets.lines_of(span) {
+                let text = trim_line_terminator(&source[part.start()..part.end()]);
+                if text.is_empty() {
+                    continue;
+                }
+                let start = self.doc.position(part.start);
+                let delta_line = start.line - line;
+                let delta_start = if delta_line == 0 {
+                    start.character - character
+                } else {
+                    start.character
+                };
+                data.push(SemanticToken {
+                    delta_line,
+                    delta_start,
+                    length: encoding.str_width(text) as u32,
+                    token_type,
+                    token_modifiers_bitset: modifiers,
+                });
+                (line, character) = (start.line, start.character);
+            }
+        }
+        SemanticTokens {
+            result_id: self.result_id,
+            data,
+        }
+    }
+}
+
+/// Computes the edits turning `previous` semantic tokens into `current`
+/// ones, for answering `textDocument/semanticTokens/full/delta`.
+///
+/// The edit replaces the tokens between the longest common prefix and suffix
+/// of the two results, so an edit in one place of a document sends only the
+/// tokens around it. The delta carries the result id of `current`.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::lsp::semantic_tokens_delta;
+/// use lsp_types::{SemanticToken, SemanticTokens};
+///
+/// let token = |length| SemanticToken { length, ..Default::default() };
+/// let previous = SemanticTokens { result_id: None, data: vec![token(1), token(2), token(3)] };
+/// let current = SemanticTokens { result_id: None, data: vec![token(1), token(5), token(3)] };
+///
+/// let delta = semantic_tokens_delta(&previous, &current);
+/// assert_eq!(delta.edits.len(), 1);
+/// assert_eq!(delta.edits[0].start, 5);
+/// assert_eq!(delta.edits[0].delete_count, 5);
+/// assert_eq!(delta.edits[0].data, Some(vec![token(5)]));
+/// ```
+pub fn semantic_tokens_delta(
+    previous: &SemanticTokens,
+    current: &SemanticTokens,
+) -> SemanticTokensDelta {
+    let (old, new) = (&previous.data, &current.data);
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let max_suffix = old.len().min(new.len()) - prefix;
+    let suffix = old
+        .iter()
+        .rev()
+        .zip(new.iter().rev())
+        .take(max_suffix)
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let deleted = old.len() - prefix - suffix;
+    let inserted = &new[prefix..new.len() - suffix];
+    let edits = if deleted == 0 && inserted.is_empty() {
+        Vec::new()
+    } else {
+        // Offsets and counts are in integers of the flattened data, five per token.
+        vec![SemanticTokensEdit {
+            start: (prefix * 5) as u32,
+            delete_count: (deleted * 5) as u32,
+            data: (!inserted.is_empty()).then(|| inserted.to_vec()),
+        }]
+    };
+    SemanticTokensDelta {
+        result_id: current.result_id.clone(),
+        edits,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::position::{ColumnMode, LineOffsets};
+
+    #[test]
+    fn test_multiline_token_is_split() {
+        let source = "a /* x\r\n\nyz */";
+        let offsets = LineOffsets::new(source);
+        let doc = LspDocument::new(source, &offsets, ColumnMode::Utf16);
+        let mut builder = SemanticTokensBuilder::new(doc).with_result_id("1");
+        builder.push_highlight(Span::new(2, source.len()).unwrap(), HighlightKind::Comment);
+        builder.push_highlight(Span::new(0, 1).unwrap(), HighlightKind::Constant);
+        builder.push_highlight(Span::new(1, 2).unwrap(), HighlightKind::Plain);
+        let tokens = builder.build();
+        assert_eq!(tokens.result_id.as_deref(), Some("1"));
+        let encoded: Vec<_> = tokens
+            .data
+            .iter()
+            .map(|t| {
+                (
+                    t.delta_line,
+                    t.delta_start,
+                    t.length,
+                    t.token_type,
+                    t.token_modifiers_bitset,
+                )
+            })
+            .collect();
+        assert_eq!(encoded, [(0, 0, 1, 3, 1), (0, 2, 4, 6, 0), (2, 0, 5, 6, 0)]);
+    }
+
+    #[test]
+    fn test_delta_insert_and_unchanged() {
+        let token = |length| SemanticToken {
+            length,
+            ..Default::default()
+        };
+        let previous = SemanticTokens {
+            result_id: Some("1".to_string()),
+            data: vec![token(1), token(1)],
+        };
+        let current = SemanticTokens {
+            result_id: Some("2".to_string()),
+            data: vec![token(1), token(1), token(1)],
+        };
+        let delta = semantic_tokens_delta(&previous, &current);
+        assert_eq!(delta.result_id.as_deref(), Some("2"));
+        assert_eq!(
+            delta.edits,
+            [SemanticTokensEdit {
+                start: 10,
+                delete_count: 0,
+                data: Some(vec![token(1)]),
+            }]
+        );
+        assert!(semantic_tokens_delta(&current, &current).edits.is_empty());
+    }
+}
@@ -0,0 +1,355 @@
+use super::LspDocument;
+use crate::parser::Bracket;
+use crate::position::{GetSpan, Span, WithSpan};
+use lsp_types::{DocumentSymbol, FoldingRange, FoldingRangeKind, SymbolKind};
+
+/// What a tree node contributes to the document outline.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub detail: Option<String>,
+    /// The part of the node to select when the symbol is picked, such as
+    /// its name; defaults to the whole node.
+    pub selection: Option<Span>,
+}
+
+impl Symbol {
+    pub fn new(name: impl Into<String>, kind: SymbolKind) -> Self {
+        Symbol {
+            name: name.into(),
+            kind,
+            detail: None,
+            selection: None,
+        }
+    }
+
+    pub fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    pub fn with_selection(mut self, span: impl GetSpan) -> Self {
+        self.selection = Some(span.get_span());
+        self
+    }
+}
+
+/// Builds the document outline of a tree.
+///
+/// `children` returns the children of a node, and `classify` the symbol of
+/// a node, if it is one. Symbols nested in nodes that are not symbols become
+/// children of the closest enclosing symbol.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::lsp::{document_symbols, LspDocument, Symbol};
+/// use grammarsmith::position::*;
+/// use lsp_types::SymbolKind;
+///
+/// struct Node {
+///     name: Option<WithSpan<&'static str>>,
+///     span: Span,
+///     children: Vec<Node>,
+/// }
+///
+/// impl GetSpan for Node {
+///     fn get_span(&self) -> Span {
+///         self.span
+///     }
+/// }
+///
+/// let source = "mod a { fn b() {} }";
+/// let tree = Node {
+///     name: Some(WithSpan::new("a", Span::new(4, 5).unwrap())),
+///     span: Span::new(0, 19).unwrap(),
+///     children: vec![Node {
+///         name: Some(WithSpan::new("b", Span::new(11, 12).unwrap())),
+///         span: Span::new(8, 17).unwrap(),
+///         children: vec![],
+///     }],
+/// };
+///
+/// let offsets = LineOffsets::new(source);
+/// let doc = LspDocument::new(source, &offsets, ColumnMode::Utf16);
+/// let symbols = document_symbols(
+///     &doc,
+///     [&tree],
+///     |node| &node.children,
+///     |node| {
+///         let name = node.name.as_ref()?;
+///         let kind = if node.children.is_empty() { SymbolKind::FUNCTION } else { SymbolKind::MODULE };
+///         Some(Symbol::new(name.value, kind).with_selection(name))
+///     },
+/// );
+///
+/// assert_eq!(symbols[0].name, "a");
+/// assert_eq!(symbols[0].children.as_ref().unwrap()[0].name, "b");
+/// assert_eq!(symbols[0].selection_range, doc.range(Span::new(4, 5).unwrap()));
+/// ```
+pub fn document_symbols<'n, N, C>(
+    doc: &LspDocument<'_>,
+    roots: impl IntoIterator<Item = &'n N>,
+    children: impl Fn(&'n N) -> C,
+    classify: impl Fn(&N) -> Option<Symbol>,
+) -> Vec<DocumentSymbol>
+where
+    N: GetSpan + 'n,
+    C: IntoIterator<Item = &'n N>,
+{
+    let mut symbols = Vec::new();
+    for root in roots {
+        collect_symbols(doc, root, &children, &classify, &mut symbols);
+    }
+    symbols
+}
+
+fn collect_symbols<'n, N, C>(
+    doc: &LspDocument<'_>,
+    node: &'n N,
+    children: &impl Fn(&'n N) -> C,
+    classify: &impl Fn(&N) -> Option<Symbol>,
+    out: &mut Vec<DocumentSymbol>,
+) where
+    N: GetSpan + 'n,
+    C: IntoIterator<Item = &'n N>,
+{
+    let Some(symbol) = classify(node) else {
+        for child in children(node) {
+            collect_symbols(doc, child, children, classify, out);
+        }
+        return;
+    };
+
+    let mut nested = Vec::new();
+    for child in children(node) {
+        collect_symbols(doc, child, children, classify, &mut nested);
+    }
+    let span = node.get_span();
+    let selection = symbol
+        .selection
+        .filter(|selection| span.start <= selection.start && selection.end <= span.end)
+        .unwrap_or(span);
+    #[allow(deprecated)]
+    out.push(DocumentSymbol {
+        name: symbol.name,
+        detail: symbol.detail,
+        kind: symbol.kind,
+        tags: None,
+        deprecated: None,
+        range: doc.range(span),
+        selection_range: doc.range(selection),
+        children: (!nested.is_empty()).then_some(nested),
+    });
+}
+
+/// Computes folding ranges for the nodes of a tree spanning several lines.
+///
+/// `children` returns the children of a node, and `fold` the kind of
+/// folding range of a node, or None if it should not fold; use
+/// [`FoldingRangeKind::Region`] for ordinary nodes. A folding range covers
+/// the node's lines up to and including its last one. Ranges are returned
+/// in document order.
+pub fn folding_ranges<'n, N, C>(
+    doc: &LspDocument<'_>,
+    roots: impl IntoIterator<Item = &'n N>,
+    children: impl Fn(&'n N) -> C,
+    fold: impl Fn(&N) -> Option<FoldingRangeKind>,
+) -> Vec<FoldingRange>
+where
+    N: GetSpan + 'n,
+    C: IntoIterator<Item = &'n N>,
+{
+    fn collect<'n, N, C>(
+        doc: &LspDocument<'_>,
+        node: &'n N,
+        children: &impl Fn(&'n N) -> C,
+        fold: &impl Fn(&N) -> Option<FoldingRangeKind>,
+        out: &mut Vec<FoldingRange>,
+    ) where
+        N: GetSpan + 'n,
+        C: IntoIterator<Item = &'n N>,
+    {
+        if let Some(kind) = fold(node) {
+            let range = doc.range(node.get_span());
+            out.push(folding_range(range.start.line, range.end.line, kind));
+        }
+        for child in children(node) {
+            collect(doc, child, children, fold, out);
+        }
+    }
+
+    let mut ranges = Vec::new();
+    for root in roots {
+        collect(doc, root, &children, &fold, &mut ranges);
+    }
+    ranges.retain(|range| range.end_line > range.start_line);
+    ranges
+}
+
+/// Computes folding ranges from the brackets of a token stream, for
+/// servers without a syntax tree or for regions the tree does not have.
+///
+/// `bracket` tells which tokens open and close regions. Brackets are paired
+/// by nesting, and unpaired ones are ignored. A folding range starts at the
+/// line of the opening bracket and ends on the line before the closing one,
+/// which stays visible. Ranges are returned in document order.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::lsp::{bracket_folding_ranges, LspDocument};
+/// use grammarsmith::parser::Bracket;
+/// use grammarsmith::position::*;
+///
+/// let source = "f {\n  g {\n    h\n  }\n}";
+/// let tokens: Vec<_> = source
+///     .char_indices()
+///     .filter(|(_, c)| !c.is_whitespace())
+///     .map(|(i, c)| WithSpan::new(c, Span::new(i, i + 1).unwrap()))
+///     .collect();
+/// let offsets = LineOffsets::new(source);
+/// let doc = LspDocument::new(source, &offsets, ColumnMode::Utf16);
+///
+/// let ranges = bracket_folding_ranges(&doc, &tokens, |c| match c {
+///     '{' => Some(Bracket::Open),
+///     '}' => Some(Bracket::Close),
+///     _ => None,
+/// });
+/// assert_eq!(ranges.len(), 2);
+/// assert_eq!((ranges[0].start_line, ranges[0].end_line), (0, 3));
+/// assert_eq!((ranges[1].start_line, ranges[1].end_line), (1, 2));
+/// ```
+pub fn bracket_folding_ranges<T>(
+    doc: &LspDocument<'_>,
+    tokens: &[WithSpan<T>],
+    bracket: impl Fn(&T) -> Option<Bracket>,
+) -> Vec<FoldingRange> {
+    let mut open = Vec::new();
+    let mut ranges = Vec::new();
+    for token in tokens {
+        match bracket(&token.value) {
+            Some(Bracket::Open) => open.push(doc.position(token.span.start).line),
+            Some(Bracket::Close) => {
+                let Some(start_line) = open.pop() else {
+                    continue;
+                };
+                let close_line = doc.position(token.span.start).line;
+                if close_line > start_line + 1 {
+                    ranges.push(folding_range(
+                        start_line,
+                        close_line - 1,
+                        FoldingRangeKind::Region,
+                    ));
+                }
+            }
+            None => {}
+        }
+    }
+    ranges.sort_by_key(|range| (range.start_line, std::cmp::Reverse(range.end_line)));
+    ranges
+}
+
+fn folding_range(start_line: u32, end_line: u32, kind: FoldingRangeKind) -> FoldingRange {
+    FoldingRange {
+        start_line,
+        start_character: None,
+        end_line,
+        end_character: None,
+        kind: Some(kind),
+        collapsed_text: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::position::{ColumnMode, LineOffsets};
+
+    struct Node {
+        span: Span,
+        symbol: bool,
+        children: Vec<Node>,
+    }
+
+    impl GetSpan for Node {
+        fn get_span(&self) -> Span {
+            self.span
+        }
+    }
+
+    fn node(start: usize, end: usize, symbol: bool, children: Vec<Node>) -> Node {
+        Node {
+            span: Span::new(start, end).unwrap(),
+            symbol,
+            children,
+        }
+    }
+
+    #[test]
+    fn test_symbols_skip_non_symbols() {
+        let source = "a\nb\nc\nd";
+        let offsets = LineOffsets::new(source);
+        let doc = LspDocument::new(source, &offsets, ColumnMode::Utf16);
+        let tree = node(
+            0,
+            7,
+            false,
+            vec![node(
+                0,
+                5,
+                true,
+                vec![node(2, 5, false, vec![node(4, 5, true, vec![])])],
+            )],
+        );
+        let symbols = document_symbols(
+            &doc,
+            [&tree],
+            |node| &node.children,
+            |node| {
+                node.symbol.then(|| {
+                    Symbol::new(format!("{}", node.span.start()), SymbolKind::FIELD)
+                        .with_selection(Span::new(6, 7).unwrap())
+                })
+            },
+        );
+        assert_eq!(symbols.len(), 1);
+        assert_eq!(symbols[0].name, "0");
+        // A selection outside the node falls back to the whole node.
+        assert_eq!(symbols[0].selection_range, symbols[0].range);
+        let children = symbols[0].children.as_ref().unwrap();
+        assert_eq!(children[0].name, "4");
+        assert_eq!(children[0].children, None);
+    }
+
+    #[test]
+    fn test_folding_ranges() {
+        let source = "a\nb\nc\nd";
+        let offsets = LineOffsets::new(source);
+        let doc = LspDocument::new(source, &offsets, ColumnMode::Utf16);
+        let tree = node(
+            0,
+            7,
+            true,
+            vec![node(2, 3, true, vec![]), node(2, 5, false, vec![])],
+        );
+        let ranges = folding_ranges(
+            &doc,
+            [&tree],
+            |node| &node.children,
+            |node| node.symbol.then_some(FoldingRangeKind::Region),
+        );
+        assert_eq!(ranges, [folding_range(0, 3, FoldingRangeKind::Region)]);
+
+        let tokens = [
+            WithSpan::new(')', Span::new(0, 1).unwrap()),
+            WithSpan::new('(', Span::new(0, 1).unwrap()),
+            WithSpan::new(')', Span::new(2, 3).unwrap()),
+        ];
+        let bracket = |c: &char| match c {
+            '(' => Some(Bracket::Open),
+            ')' => Some(Bracket::Close),
+            _ => None,
+        };
+        assert!(bracket_folding_ranges(&doc, &tokens, bracket).is_empty());
+    }
+}
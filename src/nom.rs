@@ -0,0 +1,104 @@
+//! Running `nom` parsers from a [`Scanner`].
+//!
+//! A handwritten lexer sometimes has to scan an embedded mini-format, such as
+//! a date or a version number, for which a `nom` parser already exists.
+//! [`Scanner::nom`] runs such a parser on the rest of the input and advances
+//! the scanner past what it consumed, so the token's span covers the parsed
+//! text.
+//!
+//! # Examples
+//! ```
+//! use grammarsmith::Scanner;
+//! use nom::bytes::complete::tag;
+//! use nom::character::complete::digit1;
+//! use nom::Parser;
+//!
+//! let mut scanner = Scanner::new("v1.20 rest");
+//! scanner.next_match('v');
+//! let version = scanner
+//!     .nom((digit1::<_, nom::error::Error<&str>>, tag("."), digit1))
+//!     .unwrap();
+//! assert_eq!(version, ("1", ".", "20"));
+//! assert_eq!(scanner.slice(), "v1.20");
+//! ```
+
+use crate::position::{BytePos, Span};
+use crate::scanner::Scanner;
+use nom::Parser;
+
+impl<'a> Scanner<'a, str> {
+    /// Returns the source text after the current position.
+    pub fn rest(&self) -> &'a str {
+        &self.source()[self.current().to_usize()..]
+    }
+
+    /// Runs `parser` on the rest of the input, advancing the scanner past
+    /// the text it consumed.
+    ///
+    /// The input is complete, so parsers never report `Incomplete`. On
+    /// failure the scanner does not move; use [`Scanner::position_of`] to
+    /// locate the input carried by the error.
+    pub fn nom<P>(&mut self, mut parser: P) -> Result<P::Output, nom::Err<P::Error>>
+    where
+        P: Parser<&'a str>,
+    {
+        let (rest, output) = parser.parse_complete(self.rest())?;
+        let end = self.position_of(rest);
+        while self.current() < end {
+            self.next();
+        }
+        Ok(output)
+    }
+
+    /// Returns the position in the source at which `rest`, a suffix of the
+    /// source such as the input of a `nom` error, begins.
+    ///
+    /// # Panics
+    /// Panics if `rest` is longer than the source.
+    pub fn position_of(&self, rest: &str) -> BytePos {
+        BytePos::from_usize(self.source().len() - rest.len())
+    }
+
+    /// Returns the span from `rest`, a suffix of the source, to the end of
+    /// its first character, or an empty span at the end of the source.
+    pub fn span_of(&self, rest: &str) -> Span {
+        let start = self.position_of(rest);
+        let len = rest.chars().next().map_or(0, char::len_utf8);
+        Span::new(start.to_usize(), start.to_usize() + len).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nom::character::complete::{alpha1, digit1};
+
+    #[test]
+    fn test_nom_advances() {
+        let mut scanner = Scanner::new("12🦀ab");
+        scanner.next();
+        scanner.shift();
+        let digits = scanner.nom(digit1::<_, nom::error::Error<&str>>).unwrap();
+        assert_eq!(digits, "2");
+        assert_eq!(scanner.peek(), Some(&'🦀'));
+        scanner.next();
+        let letters = scanner.nom(alpha1::<_, nom::error::Error<&str>>).unwrap();
+        assert_eq!(letters, "ab");
+        assert_eq!(scanner.slice(), "2🦀ab");
+        assert_eq!(scanner.rest(), "");
+    }
+
+    #[test]
+    fn test_nom_error() {
+        let mut scanner = Scanner::new("x🦀");
+        scanner.next();
+        let err = scanner
+            .nom(digit1::<_, nom::error::Error<&str>>)
+            .unwrap_err();
+        let nom::Err::Error(err) = err else {
+            panic!("unexpected error {:?}", err);
+        };
+        assert_eq!(scanner.current(), BytePos::from_usize(1));
+        assert_eq!(scanner.span_of(err.input), Span::new(1, 5).unwrap());
+    }
+}
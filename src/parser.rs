@@ -1,5 +1,48 @@
+use crate::diagnostics::{
+    expected_list, Applicability, Diagnostic, DiagnosticSink, Severity, Suggestion,
+};
 use crate::position::*;
 
+pub mod coverage;
+pub mod lazy;
+pub mod output;
+pub mod repl;
+
+pub use coverage::*;
+pub use lazy::*;
+pub use output::*;
+pub use repl::*;
+
+/// Derives [`Token`], [`KindMeta`] and, optionally, [`EndOfFile`] for a
+/// token enum.
+///
+/// Variants are described by their name in error messages unless given a
+/// display name with `#[token(name = "...")]`.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::parser::{EndOfFile, KindMeta, Token};
+///
+/// #[derive(Debug, Clone, PartialEq, Token)]
+/// enum CalcToken {
+///     #[token(name = "a number")]
+///     Number(u64),
+///     #[token(name = "`+`")]
+///     Plus,
+///     #[token(eof)]
+///     Eof,
+/// }
+///
+/// assert_eq!(CalcToken::Number(1).to_kind(), CalcTokenKind::Number);
+/// assert_eq!(CalcTokenKind::ALL, &[CalcTokenKind::Number, CalcTokenKind::Plus, CalcTokenKind::Eof]);
+/// assert_eq!(CalcToken::eof(), CalcToken::Eof);
+/// assert_eq!(CalcTokenKind::Plus.display_name(), "`+`");
+/// assert_eq!(CalcTokenKind::Plus.name(), "Plus");
+/// assert_eq!(CalcTokenKind::Eof.display_name(), "Eof");
+/// ```
+#[cfg(feature = "derive")]
+pub use grammarsmith_macros::Token;
+
 /// A trait for tokens that can be parsed.
 ///
 /// This trait defines the basic requirements for a token type that can be used
@@ -29,6 +72,55 @@ pub trait EndOfFile: Token {
     fn eof_kind() -> Self::Kind;
 }
 
+/// Metadata about token kinds, used to describe them to users.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::parser::KindMeta;
+///
+/// #[derive(Debug, Clone, Copy, PartialEq)]
+/// enum Kind {
+///     Comma,
+///     Identifier,
+/// }
+///
+/// impl KindMeta for Kind {
+///     const ALL: &'static [Kind] = &[Kind::Comma, Kind::Identifier];
+///
+///     fn display_name(&self) -> &'static str {
+///         match self {
+///             Kind::Comma => "`,`",
+///             Kind::Identifier => "an identifier",
+///         }
+///     }
+/// }
+///
+/// assert_eq!(Kind::Comma.display_name(), "`,`");
+/// ```
+pub trait KindMeta: Sized + 'static {
+    /// Every kind, in declaration order.
+    const ALL: &'static [Self];
+
+    /// A name for the kind as it should appear in error messages, such as
+    /// "`+`" for an operator or "an identifier" for a class of tokens.
+    fn display_name(&self) -> &'static str;
+
+    /// An identifier for the kind, such as `Plus`, for use in grammar
+    /// descriptions and debugging output. Defaults to the display name.
+    fn name(&self) -> &'static str {
+        self.display_name()
+    }
+}
+
+/// A position in the token stream of a [`Parser`], from [`Parser::mark`].
+///
+/// Markers compare by position, so a loop can tell whether an iteration
+/// consumed any token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Marker {
+    index: usize,
+}
+
 /// A parser for a token stream.
 ///
 /// The Parser provides methods for traversing and analyzing a sequence of tokens.
@@ -46,6 +138,8 @@ where
     current: usize,
     tokens: &'a [WithSpan<T>],
     eof_token: &'a WithSpan<T>,
+    rules: Vec<&'static str>,
+    coverage: Option<&'a Coverage>,
 }
 
 impl<'a, T> Parser<'a, T>
@@ -61,8 +155,47 @@ where
         Parser {
             check_points: Vec::new(),
             current: 0,
-            tokens: tokens,
-            eof_token: eof_token,
+            tokens,
+            eof_token,
+            rules: Vec::new(),
+            coverage: None,
+        }
+    }
+
+    /// Records the rules entered and the branches taken into `coverage`.
+    pub fn with_coverage(mut self, coverage: &'a Coverage) -> Self {
+        self.coverage = Some(coverage);
+        self
+    }
+
+    /// Parses the rule `name` with `f`.
+    ///
+    /// Rules only name the checks made while parsing them, for a
+    /// [`Coverage`] collector; they do not change how the parser works.
+    pub fn rule<R>(&mut self, name: &'static str, f: impl FnOnce(&mut Self) -> R) -> R {
+        if let Some(coverage) = self.coverage {
+            coverage.enter_rule(name);
+        }
+        self.rules.push(name);
+        let result = f(self);
+        self.rules.pop();
+        result
+    }
+
+    /// Returns the innermost rule being parsed, if any.
+    pub fn current_rule(&self) -> Option<&'static str> {
+        self.rules.last().copied()
+    }
+
+    #[track_caller]
+    fn record_branch(&self, alternative: usize, taken: bool) {
+        if let Some(coverage) = self.coverage {
+            let branch = Branch {
+                rule: self.current_rule(),
+                location: std::panic::Location::caller(),
+                alternative,
+            };
+            coverage.record(branch, taken);
         }
     }
 
@@ -85,32 +218,51 @@ where
     ///
     /// If there are no checkpoints, this method does nothing.
     pub fn rewind(&mut self) {
-        match self.check_points.pop() {
-            Some(checkpoint) => self.current = checkpoint,
-            None => {}
+        if let Some(checkpoint) = self.check_points.pop() {
+            self.current = checkpoint;
+        }
+    }
+
+    /// Marks the current position in the token stream, to compute the span
+    /// of what is parsed from there with [`span_since`](Parser::span_since).
+    pub fn mark(&self) -> Marker {
+        Marker {
+            index: self.current,
+        }
+    }
+
+    /// Returns the span of the tokens consumed since `marker`, or an empty
+    /// span at the current token if none were.
+    pub fn span_since(&self, marker: Marker) -> Span {
+        match self.tokens.get(marker.index) {
+            Some(first) if self.current > marker.index => first.span.union(&self.previous().span),
+            _ => {
+                let start = self.peek_token().span.start;
+                Span { start, end: start }
+            }
         }
     }
 
     /// Returns the kind of the current token without advancing the parser.
     pub fn peek(&self) -> T::Kind {
-        return self.peek_token().value.to_kind();
+        self.peek_token().value.to_kind()
     }
 
     /// Returns a reference to the current token with its span information.
     pub fn peek_token(&self) -> &'a WithSpan<T> {
-        self.tokens.get(self.current).unwrap_or(&self.eof_token)
+        self.tokens.get(self.current).unwrap_or(self.eof_token)
     }
 
     /// Returns a reference to the previously consumed token.
     ///
     /// If no tokens have been consumed yet, returns the EOF token.
     pub fn previous(&self) -> &'a WithSpan<T> {
-        return self.tokens.get(self.current - 1).unwrap_or(&self.eof_token);
+        self.tokens.get(self.current - 1).unwrap_or(self.eof_token)
     }
 
     /// Returns true if the parser has reached the end of the token stream.
     pub fn is_at_end(&self) -> bool {
-        return self.peek() == T::eof_kind();
+        self.peek() == T::eof_kind()
     }
 
     /// Checks if the current token matches the specified kind without advancing.
@@ -120,20 +272,27 @@ where
     ///
     /// # Returns
     /// `true` if the current token matches the specified kind, `false` otherwise
+    #[track_caller]
     pub fn check(&self, token: T::Kind) -> bool {
-        if self.is_at_end() {
-            false
-        } else {
-            token == self.peek()
-        }
+        let taken = !self.is_at_end() && token == self.peek();
+        self.record_branch(0, taken);
+        taken
     }
 
     /// Checks if the current token matches any of the specified kinds.
     ///
     /// # Arguments
     /// * `tokens` - A slice of token kinds to check against
+    #[track_caller]
     pub fn check_one_of(&mut self, tokens: &[T::Kind]) -> bool {
-        tokens.contains(&self.peek())
+        let found = self.peek();
+        let taken = tokens.iter().position(|token| *token == found);
+        if self.coverage.is_some() {
+            for alternative in 0..tokens.len() {
+                self.record_branch(alternative, taken == Some(alternative));
+            }
+        }
+        taken.is_some()
     }
 
     /// Advances the parser to the next token and returns the previous token.
@@ -144,7 +303,7 @@ where
         if !self.is_at_end() {
             self.current += 1;
         }
-        return self.previous();
+        self.previous()
     }
 
     /// Checks if the current token matches any of the specified kinds and advances if true.
@@ -154,36 +313,198 @@ where
     ///
     /// # Returns
     /// `true` if a match was found and consumed, `false` otherwise
+    #[track_caller]
     pub fn is_one_of<I: IntoIterator<Item = T::Kind>>(&mut self, tokens: I) -> bool {
-        for token in tokens {
-            if self.check(token) {
-                self.advance();
-                return true;
+        let mut taken = None;
+        for (alternative, token) in tokens.into_iter().enumerate() {
+            if taken.is_none() && !self.is_at_end() && token == self.peek() {
+                taken = Some(alternative);
+            } else if self.coverage.is_none() && taken.is_some() {
+                break;
             }
+            self.record_branch(alternative, taken == Some(alternative));
+        }
+        if taken.is_some() {
+            self.advance();
         }
-        return false;
+        taken.is_some()
     }
 
     /// Checks if the current token matches the specified kind and advances if true.
     ///
     /// This is a convenience method that combines `check()` and `advance()`.
+    #[track_caller]
     pub fn is(&mut self, token: T::Kind) -> bool {
         if self.check(token) {
             self.advance();
             return true;
         }
-        return false;
+        false
     }
 
     /// Similar to `is()` but with a more semantic name for optional tokens.
     ///
     /// This method is particularly useful when parsing optional syntax elements.
+    #[track_caller]
     pub fn optional(&mut self, token: T::Kind) -> bool {
         if self.check(token) {
             self.advance();
             return true;
         }
-        return false;
+        false
+    }
+
+    /// Consumes a token of the specified kind, or reports an error at the
+    /// current token to `sink`.
+    ///
+    /// # Returns
+    /// The consumed token, or None if the current token did not match
+    #[track_caller]
+    pub fn expect<D: DiagnosticSink + ?Sized>(
+        &mut self,
+        token: T::Kind,
+        message: impl Into<String>,
+        sink: &mut D,
+    ) -> Option<&'a WithSpan<T>> {
+        if self.is(token) {
+            Some(self.previous())
+        } else {
+            self.error(message, sink);
+            None
+        }
+    }
+
+    /// Consumes a token of one of the specified kinds, or reports an error
+    /// at the current token to `sink`.
+    ///
+    /// The error names the expected kinds and the kind found, using their
+    /// [`KindMeta::display_name`]s, as in "expected `,` or `)`, found an
+    /// identifier". See [`expected_list`] for how the kinds are joined. The
+    /// message is built with [`DiagnosticSink::emit_lazy`], only if the sink
+    /// keeps the error, which is why the kinds are a constant slice, such as
+    /// `&[Kind::Comma, Kind::RParen]`, borrowed until then.
+    ///
+    /// # Returns
+    /// The consumed token, or None if the current token did not match
+    pub fn expect_one_of<D: DiagnosticSink + ?Sized>(
+        &mut self,
+        tokens: &'static [T::Kind],
+        sink: &mut D,
+    ) -> Option<&'a WithSpan<T>>
+    where
+        T::Kind: KindMeta,
+    {
+        let found = self.peek();
+        if !self.is_at_end() && tokens.contains(&found) {
+            return Some(self.advance());
+        }
+        let span = self.peek_token().span;
+        sink.emit_lazy(Box::new(move || {
+            let message = format!(
+                "expected {}, found {}",
+                expected_list(tokens.iter().map(KindMeta::display_name)),
+                found.display_name()
+            );
+            Diagnostic::builder(Severity::Error, message)
+                .primary(span, "")
+                .build()
+        }));
+        None
+    }
+
+    /// Consumes a token of the specified kind, or reports an error and
+    /// carries on as if it had been there.
+    ///
+    /// The error comes with a machine-applicable [`Suggestion`] to insert
+    /// `text`, the source text of the missing token, right after the
+    /// previous token, such as "insert `;` before `}`". The messages are
+    /// built with [`DiagnosticSink::emit_lazy`], only if the sink keeps the
+    /// error.
+    ///
+    /// # Returns
+    /// The consumed token, or None if it was missing
+    #[track_caller]
+    pub fn expect_or_insert<D: DiagnosticSink + ?Sized>(
+        &mut self,
+        token: T::Kind,
+        text: &str,
+        sink: &mut D,
+    ) -> Option<&'a WithSpan<T>>
+    where
+        T::Kind: KindMeta + Clone,
+    {
+        if self.check(token.clone()) {
+            return Some(self.advance());
+        }
+        let found = self.peek();
+        let span = self.peek_token().span;
+        let at = match self.current.checked_sub(1).and_then(|i| self.tokens.get(i)) {
+            Some(previous) => previous.span.end,
+            None => span.start,
+        };
+        let text = text.to_string();
+        sink.emit_lazy(Box::new(move || {
+            Diagnostic::error(format!(
+                "expected {}, found {}",
+                token.display_name(),
+                found.display_name()
+            ))
+            .with_label(span, "")
+            .with_suggestion(Suggestion::new(
+                format!("insert `{}` before {}", text, found.display_name()),
+                vec![TextEdit::insert(at, text)],
+                Applicability::MachineApplicable,
+            ))
+        }));
+        None
+    }
+
+    /// Discards tokens until one matching the specified kinds is found, and
+    /// reports the discarded tokens, if any, to `sink`.
+    ///
+    /// The error comes with a [`Suggestion`] to remove them, such as "remove
+    /// stray `,`". It is only
+    /// [`MaybeIncorrect`](Applicability::MaybeIncorrect), as the user may
+    /// rather have meant to add what the tokens are missing.
+    ///
+    /// # Returns
+    /// The span covering all skipped tokens, or None if no tokens were skipped
+    pub fn recover_until<D: DiagnosticSink + ?Sized>(
+        &mut self,
+        tokens: &[T::Kind],
+        message: impl Into<String>,
+        sink: &mut D,
+    ) -> Option<Span>
+    where
+        T::Kind: KindMeta,
+    {
+        let first = self.peek();
+        let start = self.current;
+        let span = self.drop_until(tokens)?;
+        let hint = match self.current - start {
+            1 => format!("remove stray {}", first.display_name()),
+            count => format!("remove these {} tokens", count),
+        };
+        sink.emit(
+            Diagnostic::error(message)
+                .with_label(span, "")
+                .with_suggestion(Suggestion::new(
+                    hint,
+                    vec![TextEdit::delete(span)],
+                    Applicability::MaybeIncorrect,
+                )),
+        );
+        Some(span)
+    }
+
+    /// Reports an error at the current token to `sink`.
+    pub fn error<D: DiagnosticSink + ?Sized>(&self, message: impl Into<String>, sink: &mut D) {
+        let span = self.peek_token().span;
+        sink.emit(
+            Diagnostic::builder(Severity::Error, message)
+                .primary(span, "")
+                .build(),
+        );
     }
 
     /// Discards tokens until one matching the specified kinds is found.
@@ -207,3 +528,215 @@ where
         dropped_span
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::{apply_suggestions, SpeculativeSink};
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum Kind {
+        Number,
+        Semicolon,
+        Eof,
+    }
+
+    impl Token for Kind {
+        type Kind = Kind;
+
+        fn to_kind(&self) -> Kind {
+            *self
+        }
+    }
+
+    thread_local! {
+        /// The number of display names looked up, to tell when messages are
+        /// built.
+        static NAMES: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+    }
+
+    impl KindMeta for Kind {
+        const ALL: &'static [Kind] = &[Kind::Number, Kind::Semicolon, Kind::Eof];
+
+        fn display_name(&self) -> &'static str {
+            NAMES.with(|names| names.set(names.get() + 1));
+            match self {
+                Kind::Number => "a number",
+                Kind::Semicolon => "`;`",
+                Kind::Eof => "end of input",
+            }
+        }
+    }
+
+    impl EndOfFile for Kind {
+        fn eof() -> Self {
+            Kind::Eof
+        }
+
+        fn eof_kind() -> Kind {
+            Kind::Eof
+        }
+    }
+
+    #[test]
+    fn test_expect() {
+        let tokens = [
+            WithSpan::new(Kind::Number, Span::new(0, 1).unwrap()),
+            WithSpan::new(Kind::Number, Span::new(2, 3).unwrap()),
+        ];
+        let eof = WithSpan::new(Kind::Eof, Span::point(3));
+        let mut parser = Parser::new(&tokens, &eof);
+        let mut sink = Vec::new();
+
+        assert_eq!(
+            parser
+                .expect(Kind::Number, "expected a number", &mut sink)
+                .map(|token| token.span),
+            Span::new(0, 1)
+        );
+        assert!(parser
+            .expect(Kind::Semicolon, "expected `;`", &mut sink)
+            .is_none());
+        assert_eq!(sink.len(), 1);
+        assert_eq!(sink[0].message, "expected `;`");
+        assert_eq!(sink[0].primary_span(), Span::new(2, 3));
+    }
+
+    #[test]
+    fn test_expect_one_of() {
+        let tokens = [WithSpan::new(Kind::Number, Span::new(0, 1).unwrap())];
+        let eof = WithSpan::new(Kind::Eof, Span::point(1));
+        let mut parser = Parser::new(&tokens, &eof);
+        let mut sink = Vec::new();
+
+        assert!(parser
+            .expect_one_of(&[Kind::Semicolon, Kind::Number], &mut sink)
+            .is_some());
+        assert!(parser
+            .expect_one_of(&[Kind::Semicolon, Kind::Number], &mut sink)
+            .is_none());
+        assert_eq!(
+            sink[0].message,
+            "expected `;` or a number, found end of input"
+        );
+        assert_eq!(sink[0].primary_span(), Some(Span::point(1)));
+    }
+
+    #[test]
+    fn test_expect_or_insert() {
+        let tokens = [
+            WithSpan::new(Kind::Number, Span::new(0, 1).unwrap()),
+            WithSpan::new(Kind::Number, Span::new(2, 3).unwrap()),
+        ];
+        let eof = WithSpan::new(Kind::Eof, Span::point(3));
+        let mut parser = Parser::new(&tokens, &eof);
+        let mut sink = Vec::new();
+
+        parser.advance();
+        assert!(parser
+            .expect_or_insert(Kind::Semicolon, ";", &mut sink)
+            .is_none());
+        assert_eq!(parser.peek_token().span, Span::new(2, 3).unwrap());
+        assert_eq!(sink[0].message, "expected `;`, found a number");
+        assert_eq!(sink[0].suggestions[0].message, "insert `;` before a number");
+        assert_eq!(
+            apply_suggestions("1 2", &sink),
+            "1; 2",
+            "the insertion goes right after the previous token"
+        );
+    }
+
+    #[test]
+    fn test_rewound_errors_are_not_formatted() {
+        let tokens = [WithSpan::new(Kind::Number, Span::new(0, 1).unwrap())];
+        let eof = WithSpan::new(Kind::Eof, Span::point(1));
+        let mut parser = Parser::new(&tokens, &eof);
+        let mut sink = SpeculativeSink::new(Vec::new());
+
+        NAMES.with(|names| names.set(0));
+        sink.checkpoint();
+        assert!(parser
+            .expect_one_of(&[Kind::Semicolon], &mut sink)
+            .is_none());
+        assert!(parser
+            .expect_or_insert(Kind::Semicolon, ";", &mut sink)
+            .is_none());
+        assert_eq!(sink.pending(), 2);
+        sink.rewind();
+        assert_eq!(NAMES.with(|names| names.get()), 0);
+
+        sink.checkpoint();
+        parser.expect_one_of(&[Kind::Semicolon], &mut sink);
+        sink.unwind();
+        let diagnostics = sink.into_inner();
+        assert_eq!(diagnostics[0].message, "expected `;`, found a number");
+    }
+
+    #[test]
+    fn test_recover_until() {
+        let tokens = [
+            WithSpan::new(Kind::Number, Span::new(0, 1).unwrap()),
+            WithSpan::new(Kind::Number, Span::new(2, 3).unwrap()),
+            WithSpan::new(Kind::Semicolon, Span::new(3, 4).unwrap()),
+        ];
+        let eof = WithSpan::new(Kind::Eof, Span::point(4));
+        let mut parser = Parser::new(&tokens, &eof);
+        let mut sink = Vec::new();
+
+        assert!(parser
+            .recover_until(&[Kind::Number], "unexpected tokens", &mut sink)
+            .is_none());
+        assert!(sink.is_empty());
+        assert_eq!(
+            parser.recover_until(&[Kind::Semicolon], "unexpected tokens", &mut sink),
+            Span::new(0, 3)
+        );
+        assert_eq!(sink[0].suggestions[0].message, "remove these 2 tokens");
+        assert_eq!(
+            sink[0].suggestions[0].edits,
+            [TextEdit::delete(Span::new(0, 3).unwrap())]
+        );
+        assert!(parser.check(Kind::Semicolon));
+    }
+
+    #[test]
+    fn test_span_since() {
+        let tokens = [
+            WithSpan::new(Kind::Number, Span::new(0, 1).unwrap()),
+            WithSpan::new(Kind::Number, Span::new(2, 3).unwrap()),
+        ];
+        let eof = WithSpan::new(Kind::Eof, Span::point(3));
+        let mut parser = Parser::new(&tokens, &eof);
+
+        let marker = parser.mark();
+        assert_eq!(parser.span_since(marker), Span::point(0));
+        parser.advance();
+        parser.advance();
+        assert_eq!(parser.span_since(marker), Span::new(0, 3).unwrap());
+        assert!(parser.mark() > marker);
+        assert_eq!(parser.span_since(parser.mark()), Span::point(3));
+    }
+
+    #[test]
+    fn test_coverage() {
+        let tokens = [WithSpan::new(Kind::Semicolon, Span::new(0, 1).unwrap())];
+        let eof = WithSpan::new(Kind::Eof, Span::point(1));
+        let coverage = Coverage::new().with_rules(["statement", "expression"]);
+        let mut parser = Parser::new(&tokens, &eof).with_coverage(&coverage);
+
+        let taken = parser.rule("statement", |parser| {
+            assert_eq!(parser.current_rule(), Some("statement"));
+            parser.is_one_of([Kind::Number, Kind::Semicolon])
+        });
+        assert!(taken);
+        assert_eq!(parser.current_rule(), None);
+        assert!(parser.is_at_end());
+
+        let report = coverage.report();
+        assert_eq!(report.untested_rules, ["expression"]);
+        assert_eq!(report.branches, 2);
+        assert_eq!(report.untested_branches.len(), 1);
+        assert_eq!(report.untested_branches[0].alternative, 0);
+        assert_eq!(report.untested_branches[0].location.file(), file!());
+    }
+}
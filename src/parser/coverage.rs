@@ -0,0 +1,257 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::panic::Location;
+
+/// A branch of a grammar: an alternative of a [`Parser::check`] or
+/// [`Parser::is_one_of`] call.
+///
+/// Branches are identified by their call site, so the same check made from
+/// two places in a rule is two branches. A single-kind check has a single
+/// alternative.
+///
+/// [`Parser::check`]: super::Parser::check
+/// [`Parser::is_one_of`]: super::Parser::is_one_of
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Branch {
+    /// The innermost rule the check was made in, if any.
+    pub rule: Option<&'static str>,
+    pub location: &'static Location<'static>,
+    /// The index of the alternative among the kinds checked.
+    pub alternative: usize,
+}
+
+impl fmt::Display for Branch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(rule) = self.rule {
+            write!(f, "{}: ", rule)?;
+        }
+        write!(f, "{}", self.location)?;
+        if self.alternative > 0 {
+            write!(f, " alternative {}", self.alternative + 1)?;
+        }
+        Ok(())
+    }
+}
+
+/// How often a branch was tried and taken.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct BranchHits {
+    pub tried: usize,
+    pub taken: usize,
+}
+
+/// Collects which rules and branches of a grammar parsers take, across any
+/// number of parses, to find the parts of a grammar a test corpus never
+/// exercises.
+///
+/// Collection is opt-in: a [`Parser`](super::Parser) only records into a
+/// collector given with [`with_coverage`](super::Parser::with_coverage).
+/// Rules are registered up front with [`with_rules`](Coverage::with_rules),
+/// so the rules never entered are known; branches are only known once
+/// tried, so a branch whose check is never reached does not appear.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::parser::*;
+/// use grammarsmith::position::{Span, WithSpan};
+///
+/// #[derive(Debug, Clone, Copy, PartialEq)]
+/// enum Kind {
+///     Number,
+///     Minus,
+///     Eof,
+/// }
+///
+/// impl Token for Kind {
+///     type Kind = Kind;
+///
+///     fn to_kind(&self) -> Kind {
+///         *self
+///     }
+/// }
+///
+/// impl EndOfFile for Kind {
+///     fn eof() -> Kind {
+///         Kind::Eof
+///     }
+///
+///     fn eof_kind() -> Kind {
+///         Kind::Eof
+///     }
+/// }
+///
+/// let coverage = Coverage::new().with_rules(["unary", "statement"]);
+/// let tokens = [WithSpan::new(Kind::Number, Span::new(0, 1).unwrap())];
+/// let eof = WithSpan::new(Kind::Eof, Span::point(1));
+/// let mut parser = Parser::new(&tokens, &eof).with_coverage(&coverage);
+/// parser.rule("unary", |parser| {
+///     if !parser.is(Kind::Minus) {
+///         parser.advance();
+///     }
+/// });
+///
+/// let report = coverage.report();
+/// assert_eq!(report.untested_rules, ["statement"]);
+/// assert_eq!(report.untested_branches.len(), 1);
+/// assert_eq!(report.untested_branches[0].rule, Some("unary"));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Coverage {
+    rules: RefCell<BTreeMap<&'static str, usize>>,
+    branches: RefCell<BTreeMap<Branch, BranchHits>>,
+}
+
+impl Coverage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the rules of the grammar, so rules never entered are
+    /// reported.
+    pub fn with_rules(self, rules: impl IntoIterator<Item = &'static str>) -> Self {
+        {
+            let mut registered = self.rules.borrow_mut();
+            for rule in rules {
+                registered.entry(rule).or_insert(0);
+            }
+        }
+        self
+    }
+
+    pub(crate) fn enter_rule(&self, rule: &'static str) {
+        *self.rules.borrow_mut().entry(rule).or_insert(0) += 1;
+    }
+
+    pub(crate) fn record(&self, branch: Branch, taken: bool) {
+        let mut branches = self.branches.borrow_mut();
+        let hits = branches.entry(branch).or_default();
+        hits.tried += 1;
+        hits.taken += usize::from(taken);
+    }
+
+    /// Returns how often each rule was entered, by name.
+    pub fn rules(&self) -> BTreeMap<&'static str, usize> {
+        self.rules.borrow().clone()
+    }
+
+    /// Returns the hits of every branch tried so far.
+    pub fn branches(&self) -> BTreeMap<Branch, BranchHits> {
+        self.branches.borrow().clone()
+    }
+
+    /// Returns the rules never entered and the branches never taken.
+    pub fn report(&self) -> CoverageReport {
+        let rules = self.rules.borrow();
+        let branches = self.branches.borrow();
+        CoverageReport {
+            rules: rules.len(),
+            untested_rules: rules
+                .iter()
+                .filter(|(_, &count)| count == 0)
+                .map(|(&rule, _)| rule)
+                .collect(),
+            branches: branches.len(),
+            untested_branches: branches
+                .iter()
+                .filter(|(_, hits)| hits.taken == 0)
+                .map(|(&branch, _)| branch)
+                .collect(),
+        }
+    }
+}
+
+/// The parts of a grammar a [`Coverage`] collector saw untested.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoverageReport {
+    /// The number of rules registered or entered.
+    pub rules: usize,
+    /// The rules never entered, by name.
+    pub untested_rules: Vec<&'static str>,
+    /// The number of branches tried.
+    pub branches: usize,
+    /// The branches tried but never taken, in rule and source order.
+    pub untested_branches: Vec<Branch>,
+}
+
+impl CoverageReport {
+    /// Returns whether every rule was entered and every branch taken.
+    pub fn is_complete(&self) -> bool {
+        self.untested_rules.is_empty() && self.untested_branches.is_empty()
+    }
+}
+
+impl fmt::Display for CoverageReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "rules: {}/{} entered",
+            self.rules - self.untested_rules.len(),
+            self.rules
+        )?;
+        for rule in &self.untested_rules {
+            writeln!(f, "  never entered: {}", rule)?;
+        }
+        writeln!(
+            f,
+            "branches: {}/{} taken",
+            self.branches - self.untested_branches.len(),
+            self.branches
+        )?;
+        for branch in &self.untested_branches {
+            writeln!(f, "  never taken: {}", branch)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report() {
+        let here = Location::caller();
+        let coverage = Coverage::new().with_rules(["a", "b"]);
+        coverage.enter_rule("a");
+        let branch = |alternative| Branch {
+            rule: Some("a"),
+            location: here,
+            alternative,
+        };
+        coverage.record(branch(0), true);
+        coverage.record(branch(1), false);
+        coverage.record(branch(1), false);
+
+        let report = coverage.report();
+        assert_eq!(report.untested_rules, ["b"]);
+        assert_eq!(report.untested_branches, [branch(1)]);
+        assert_eq!(
+            coverage.branches()[&branch(1)],
+            BranchHits { tried: 2, taken: 0 }
+        );
+        assert!(!report.is_complete());
+    }
+
+    #[test]
+    fn test_display() {
+        let location = Location::caller();
+        let report = CoverageReport {
+            rules: 2,
+            untested_rules: vec!["b"],
+            branches: 1,
+            untested_branches: vec![Branch {
+                rule: Some("a"),
+                location,
+                alternative: 1,
+            }],
+        };
+        assert_eq!(
+            report.to_string(),
+            format!(
+                "rules: 1/2 entered\n  never entered: b\nbranches: 0/1 taken\n  never taken: a: {} alternative 2\n",
+                location
+            )
+        );
+    }
+}
@@ -0,0 +1,231 @@
+use super::{EndOfFile, Marker, Parser, Token};
+use crate::position::{SortedSpans, Span, WithSpan};
+
+/// A region of the token stream, such as a function body, that a first
+/// pass skipped over to be parsed when it is needed.
+///
+/// Skipping the bodies of a large file lets a first pass find its outline
+/// quickly, and the bodies that are looked at, such as those on screen, are
+/// parsed later with [`parse`](LazyNode::parse), by a parser restricted to
+/// the tokens of the region. Only the span is kept, so a node can be parsed
+/// any number of times, and nodes skipped while parsing another are in the
+/// same coordinates.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::parser::*;
+/// use grammarsmith::position::{Span, WithSpan};
+///
+/// #[derive(Debug, Clone, Copy, PartialEq)]
+/// enum Kind {
+///     Name,
+///     LBrace,
+///     RBrace,
+///     Eof,
+/// }
+///
+/// impl Token for Kind {
+///     type Kind = Kind;
+///
+///     fn to_kind(&self) -> Kind {
+///         *self
+///     }
+/// }
+///
+/// impl EndOfFile for Kind {
+///     fn eof() -> Kind {
+///         Kind::Eof
+///     }
+///
+///     fn eof_kind() -> Kind {
+///         Kind::Eof
+///     }
+/// }
+///
+/// // `f { g { } } h { }`
+/// let kinds = [(Kind::Name, 0, 1), (Kind::LBrace, 2, 3), (Kind::Name, 4, 5), (Kind::LBrace, 6, 7),
+///     (Kind::RBrace, 8, 9), (Kind::RBrace, 10, 11), (Kind::Name, 12, 13), (Kind::LBrace, 14, 15),
+///     (Kind::RBrace, 16, 17)];
+/// let tokens: Vec<_> = kinds
+///     .iter()
+///     .map(|&(kind, start, end)| WithSpan::new(kind, Span::new(start, end).unwrap()))
+///     .collect();
+/// let eof = WithSpan::new(Kind::Eof, Span::point(17));
+///
+/// // The first pass only reads the names, skipping the bodies.
+/// let mut parser = Parser::new(&tokens, &eof);
+/// let mut bodies = Vec::new();
+/// while parser.is(Kind::Name) {
+///     bodies.extend(parser.skip_balanced(Kind::LBrace, Kind::RBrace));
+/// }
+/// assert!(parser.is_at_end());
+/// assert_eq!(bodies[0].span(), Span::new(2, 11).unwrap());
+///
+/// // The body of `f` is parsed on demand, skipping the body of `g` in turn.
+/// let inner = bodies[0].parse(&tokens, |parser| {
+///     parser.advance();
+///     parser.is(Kind::Name);
+///     parser.skip_balanced(Kind::LBrace, Kind::RBrace)
+/// });
+/// assert_eq!(inner.unwrap().span(), Span::new(6, 9).unwrap());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LazyNode {
+    span: Span,
+}
+
+impl LazyNode {
+    pub fn new(span: Span) -> Self {
+        LazyNode { span }
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// Returns the tokens of the node, out of all the tokens of the source.
+    pub fn tokens<'t, T>(&self, tokens: &'t [WithSpan<T>]) -> &'t [WithSpan<T>] {
+        tokens.tokens_in_range(self.span)
+    }
+
+    /// Parses the node with `f`, given a parser over its tokens, out of all
+    /// the tokens of the source, which ends at the end of the node.
+    pub fn parse<T, R>(&self, tokens: &[WithSpan<T>], f: impl FnOnce(&mut Parser<'_, T>) -> R) -> R
+    where
+        T: Token + EndOfFile,
+    {
+        let eof = WithSpan::new(T::eof(), Span::point(self.span.end()));
+        let mut parser = Parser::new(self.tokens(tokens), &eof);
+        f(&mut parser)
+    }
+}
+
+impl<'a, T> Parser<'a, T>
+where
+    T: Token + EndOfFile,
+{
+    /// Returns the tokens consumed since `marker` as a [`LazyNode`], to be
+    /// parsed later, such as after skipping them with
+    /// [`drop_until`](Parser::drop_until).
+    pub fn lazy_since(&self, marker: Marker) -> LazyNode {
+        LazyNode::new(self.span_since(marker))
+    }
+
+    /// Skips a region between `open` and the `close` that balances it, and
+    /// returns it as a [`LazyNode`], delimiters included, or returns `None`
+    /// without advancing if the current token is not `open`.
+    ///
+    /// An unbalanced region goes on to the end of the tokens, so that
+    /// parsing it reports the missing `close`.
+    pub fn skip_balanced(&mut self, open: T::Kind, close: T::Kind) -> Option<LazyNode> {
+        if self.is_at_end() || self.peek() != open {
+            return None;
+        }
+        let marker = self.mark();
+        let mut depth = 0usize;
+        while !self.is_at_end() {
+            let kind = self.advance().value.to_kind();
+            if kind == open {
+                depth += 1;
+            } else if kind == close {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+        }
+        Some(self.lazy_since(marker))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum Kind {
+        Number,
+        Open,
+        Close,
+        Eof,
+    }
+
+    impl Token for Kind {
+        type Kind = Kind;
+
+        fn to_kind(&self) -> Kind {
+            *self
+        }
+    }
+
+    impl EndOfFile for Kind {
+        fn eof() -> Self {
+            Kind::Eof
+        }
+
+        fn eof_kind() -> Kind {
+            Kind::Eof
+        }
+    }
+
+    /// Lexes digits and parentheses, one byte each, skipping spaces.
+    fn lex(source: &str) -> Vec<WithSpan<Kind>> {
+        let kinds = source.char_indices().filter_map(|(start, ch)| {
+            let kind = match ch {
+                '(' => Kind::Open,
+                ')' => Kind::Close,
+                ' ' => return None,
+                _ => Kind::Number,
+            };
+            Some(WithSpan::new(kind, Span::new(start, start + 1).unwrap()))
+        });
+        kinds.collect()
+    }
+
+    fn span(start: usize, end: usize) -> Span {
+        Span::new(start, end).unwrap()
+    }
+
+    #[test]
+    fn test_parse_on_demand() {
+        let tokens = lex("1 (2 (3) 4) 5");
+        let eof = WithSpan::new(Kind::Eof, Span::point(13));
+        let mut parser = Parser::new(&tokens, &eof);
+        assert!(parser.skip_balanced(Kind::Open, Kind::Close).is_none());
+        assert!(parser.is(Kind::Number));
+        let node = parser.skip_balanced(Kind::Open, Kind::Close).unwrap();
+        assert_eq!(node.span(), span(2, 11));
+        assert_eq!(node.tokens(&tokens).len(), 7);
+        assert_eq!(parser.peek_token().span, span(12, 13));
+
+        let (numbers, inner) = node.parse(&tokens, |parser| {
+            let mut numbers = 0;
+            let mut inner = None;
+            parser.advance();
+            while !parser.is_at_end() {
+                if parser.is(Kind::Number) {
+                    numbers += 1;
+                } else {
+                    inner = inner.or(parser.skip_balanced(Kind::Open, Kind::Close));
+                    parser.optional(Kind::Close);
+                }
+            }
+            assert_eq!(parser.peek_token().span, Span::point(11));
+            (numbers, inner)
+        });
+        assert_eq!(numbers, 2);
+        assert_eq!(inner, Some(LazyNode::new(span(5, 8))));
+    }
+
+    #[test]
+    fn test_unbalanced() {
+        let tokens = lex("(1 (2)");
+        let eof = WithSpan::new(Kind::Eof, Span::point(6));
+        let mut parser = Parser::new(&tokens, &eof);
+        let marker = parser.mark();
+        let node = parser.skip_balanced(Kind::Open, Kind::Close).unwrap();
+        assert!(parser.is_at_end());
+        assert_eq!(node, parser.lazy_since(marker));
+        assert_eq!(node.span(), span(0, 6));
+    }
+}
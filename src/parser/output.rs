@@ -0,0 +1,103 @@
+use crate::diagnostics::Diagnostic;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// The result of a parse that recovers from errors: a value, such as a
+/// syntax tree, along with the diagnostics reported while parsing it.
+///
+/// # Serialization
+/// With the `serde` feature, an output is an object with the `value` and
+/// the list of `diagnostics`:
+///
+/// ```json
+/// { "value": ..., "diagnostics": [ ... ] }
+/// ```
+///
+/// # Examples
+/// ```
+/// use grammarsmith::diagnostics::Diagnostic;
+/// use grammarsmith::parser::ParseOutput;
+///
+/// let output = ParseOutput::new(vec!["statement"], vec![Diagnostic::warning("unused")]);
+/// assert!(!output.has_errors());
+/// assert_eq!(output.map(|statements| statements.len()).value, 1);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ParseOutput<T> {
+    pub value: T,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl<T> ParseOutput<T> {
+    pub fn new(value: T, diagnostics: Vec<Diagnostic>) -> Self {
+        ParseOutput { value, diagnostics }
+    }
+
+    /// Returns true if any diagnostic is an error.
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics.iter().any(Diagnostic::is_error)
+    }
+
+    /// Transforms the value, keeping the diagnostics.
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> ParseOutput<U> {
+        ParseOutput {
+            value: f(self.value),
+            diagnostics: self.diagnostics,
+        }
+    }
+
+    /// Returns the value if there are no errors, or the diagnostics
+    /// otherwise.
+    pub fn into_result(self) -> Result<T, Vec<Diagnostic>> {
+        if self.has_errors() {
+            Err(self.diagnostics)
+        } else {
+            Ok(self.value)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_into_result() {
+        let ok = ParseOutput::new(1, vec![Diagnostic::warning("w")]);
+        assert_eq!(ok.into_result(), Ok(1));
+        let err = ParseOutput::new(1, vec![Diagnostic::error("e")]);
+        assert_eq!(err.into_result().unwrap_err()[0].message, "e");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_roundtrip() {
+        use crate::position::{Span, WithSpan};
+
+        let output = ParseOutput::new(
+            vec![WithSpan::new("x".to_string(), Span::new(0, 1).unwrap())],
+            vec![Diagnostic::error("bad").with_label(Span::new(0, 1).unwrap(), "here")],
+        );
+        let value = serde_json::to_value(&output).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "value": [{ "value": "x", "span": { "start": 0, "end": 1 } }],
+                "diagnostics": [{
+                    "severity": "error",
+                    "message": "bad",
+                    "labels": [{
+                        "style": "primary",
+                        "span": { "start": 0, "end": 1 },
+                        "message": "here",
+                    }],
+                }],
+            })
+        );
+        assert_eq!(
+            serde_json::from_value::<ParseOutput<Vec<WithSpan<String>>>>(value).unwrap(),
+            output
+        );
+    }
+}
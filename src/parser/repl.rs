@@ -0,0 +1,241 @@
+use super::{EndOfFile, Parser, Token};
+use crate::diagnostics::Diagnostic;
+use crate::position::WithSpan;
+
+/// Whether a token opens or closes a delimited region, such as a pair of
+/// parentheses or a block.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Bracket {
+    Open,
+    Close,
+}
+
+/// The outcome of parsing a line of REPL input.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReplInput<R> {
+    /// The input parsed without errors, possibly with warnings.
+    Complete(R, Vec<Diagnostic>),
+    /// The input is a valid beginning, but ends too early; the REPL should
+    /// read another line and parse again.
+    NeedsMoreInput,
+    /// The input has errors no additional input could fix.
+    Invalid(Vec<Diagnostic>),
+}
+
+/// Parses REPL input, telling incomplete input from erroneous input.
+///
+/// `parse` is called on a parser over `tokens` and reports errors into the
+/// given sink, and `bracket` tells which tokens open and close delimited
+/// regions. The input needs more lines if
+///
+/// - it has errors, all of them at the end of file, such as after a
+///   dangling operator or inside an unclosed delimiter, or
+/// - it has no errors but leaves delimiters unclosed, for parsers that do
+///   not report them.
+///
+/// Any error before the end of file makes the input invalid, and so do
+/// errors at the end of file after a closing delimiter without an opening
+/// one, as no more lines could balance it. Such a stray closing delimiter
+/// only keeps the input from needing more lines: if the parser reports no
+/// errors, the input is complete. A REPL would usually still evaluate, or
+/// reject, input that needs more lines once the user enters an empty line.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::diagnostics::Diagnostic;
+/// use grammarsmith::parser::*;
+/// use grammarsmith::position::*;
+///
+/// #[derive(Debug, Clone, Copy, PartialEq)]
+/// enum Tok {
+///     Num,
+///     Plus,
+///     LParen,
+///     RParen,
+///     Eof,
+/// }
+///
+/// impl Token for Tok {
+///     type Kind = Tok;
+///
+///     fn to_kind(&self) -> Tok {
+///         *self
+///     }
+/// }
+///
+/// impl EndOfFile for Tok {
+///     fn eof() -> Tok {
+///         Tok::Eof
+///     }
+///
+///     fn eof_kind() -> Tok {
+///         Tok::Eof
+///     }
+/// }
+///
+/// // expr = Num (Plus expr)? | LParen expr RParen
+/// fn expr(parser: &mut Parser<'_, Tok>, sink: &mut Vec<Diagnostic>) {
+///     if parser.is(Tok::LParen) {
+///         expr(parser, sink);
+///         parser.expect(Tok::RParen, "expected `)`", sink);
+///     } else {
+///         parser.expect(Tok::Num, "expected a number", sink);
+///     }
+///     if parser.is(Tok::Plus) {
+///         expr(parser, sink);
+///     }
+/// }
+///
+/// fn status(tokens: &[Tok]) -> ReplInput<()> {
+///     let tokens: Vec<_> = tokens
+///         .iter()
+///         .enumerate()
+///         .map(|(i, tok)| WithSpan::new(*tok, Span::new(i, i + 1).unwrap()))
+///         .collect();
+///     let eof = WithSpan::new(Tok::Eof, Span::point(tokens.len()));
+///     let bracket = |tok: &Tok| match tok {
+///         Tok::LParen => Some(Bracket::Open),
+///         Tok::RParen => Some(Bracket::Close),
+///         _ => None,
+///     };
+///     parse_repl_input(&tokens, &eof, bracket, expr)
+/// }
+///
+/// assert_eq!(status(&[Tok::Num, Tok::Plus, Tok::Num]), ReplInput::Complete((), vec![]));
+/// assert_eq!(status(&[Tok::Num, Tok::Plus]), ReplInput::NeedsMoreInput);
+/// assert_eq!(status(&[Tok::LParen, Tok::Num]), ReplInput::NeedsMoreInput);
+/// assert!(matches!(status(&[Tok::Plus, Tok::Num]), ReplInput::Invalid(_)));
+/// ```
+pub fn parse_repl_input<'a, T, R>(
+    tokens: &'a [WithSpan<T>],
+    eof: &'a WithSpan<T>,
+    bracket: impl Fn(&T) -> Option<Bracket>,
+    parse: impl FnOnce(&mut Parser<'a, T>, &mut Vec<Diagnostic>) -> R,
+) -> ReplInput<R>
+where
+    T: Token + EndOfFile,
+{
+    let mut depth = 0usize;
+    let mut stray = false;
+    for token in tokens {
+        match bracket(&token.value) {
+            Some(Bracket::Open) => depth += 1,
+            Some(Bracket::Close) => match depth.checked_sub(1) {
+                Some(d) => depth = d,
+                None => stray = true,
+            },
+            None => {}
+        }
+    }
+
+    let mut parser = Parser::new(tokens, eof);
+    let mut diagnostics = Vec::new();
+    let value = parse(&mut parser, &mut diagnostics);
+
+    let mut errors = diagnostics.iter().filter(|d| d.is_error()).peekable();
+    if errors.peek().is_none() {
+        return if depth > 0 && !stray {
+            ReplInput::NeedsMoreInput
+        } else {
+            ReplInput::Complete(value, diagnostics)
+        };
+    }
+    let at_eof = errors.all(|error| {
+        error
+            .primary_span()
+            .is_some_and(|span| span.start >= eof.span.start)
+    });
+    if at_eof && !stray {
+        ReplInput::NeedsMoreInput
+    } else {
+        ReplInput::Invalid(diagnostics)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::Severity;
+    use crate::position::Span;
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum Tok {
+        Open,
+        Close,
+        Eof,
+    }
+
+    impl Token for Tok {
+        type Kind = Tok;
+
+        fn to_kind(&self) -> Tok {
+            *self
+        }
+    }
+
+    impl EndOfFile for Tok {
+        fn eof() -> Tok {
+            Tok::Eof
+        }
+
+        fn eof_kind() -> Tok {
+            Tok::Eof
+        }
+    }
+
+    fn status(tokens: &[Tok], errors: &[usize]) -> ReplInput<()> {
+        let tokens: Vec<_> = tokens
+            .iter()
+            .enumerate()
+            .map(|(i, tok)| WithSpan::new(*tok, Span::new(i, i + 1).unwrap()))
+            .collect();
+        let eof = WithSpan::new(Tok::Eof, Span::point(tokens.len()));
+        let bracket = |tok: &Tok| match tok {
+            Tok::Open => Some(Bracket::Open),
+            Tok::Close => Some(Bracket::Close),
+            Tok::Eof => None,
+        };
+        parse_repl_input(&tokens, &eof, bracket, |_, sink| {
+            for &at in errors {
+                sink.push(Diagnostic::error("bad").with_label(Span::point(at), ""));
+            }
+        })
+    }
+
+    #[test]
+    fn test_unclosed_without_errors() {
+        assert_eq!(status(&[Tok::Open], &[]), ReplInput::NeedsMoreInput);
+        assert_eq!(
+            status(&[Tok::Open, Tok::Close], &[]),
+            ReplInput::Complete((), vec![])
+        );
+        assert_eq!(
+            status(&[Tok::Close, Tok::Open], &[]),
+            ReplInput::Complete((), vec![])
+        );
+    }
+
+    #[test]
+    fn test_error_positions() {
+        assert_eq!(status(&[Tok::Open], &[1]), ReplInput::NeedsMoreInput);
+        assert!(matches!(
+            status(&[Tok::Open], &[0, 1]),
+            ReplInput::Invalid(_)
+        ));
+        assert!(matches!(
+            status(&[Tok::Close, Tok::Open], &[2]),
+            ReplInput::Invalid(_)
+        ));
+
+        let warning = Diagnostic::new(Severity::Warning, "unused");
+        let tokens = [WithSpan::new(Tok::Close, Span::new(0, 1).unwrap())];
+        let eof = WithSpan::new(Tok::Eof, Span::point(1));
+        let outcome = parse_repl_input(
+            &tokens,
+            &eof,
+            |_| None,
+            |_, sink| sink.push(warning.clone()),
+        );
+        assert_eq!(outcome, ReplInput::Complete((), vec![warning]));
+    }
+}
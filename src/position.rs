@@ -7,10 +7,24 @@
 //! use grammarsmith::position::*;
 //! ```
 
+pub mod anchor;
 pub mod bytepos;
+pub mod cursor;
+pub mod edit;
 pub mod lineoffset;
+pub mod remap;
+pub mod sorted;
 pub mod span;
+pub mod spanindex;
+pub mod virtualsource;
 
+pub use anchor::*;
 pub use bytepos::*;
+pub use cursor::*;
+pub use edit::*;
 pub use lineoffset::*;
+pub use remap::*;
+pub use sorted::*;
 pub use span::*;
+pub use spanindex::*;
+pub use virtualsource::*;
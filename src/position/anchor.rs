@@ -0,0 +1,231 @@
+use super::{BytePos, TextEdit};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Which side of an edit an [`Anchor`] sticks to.
+///
+/// The bias only matters when an edit touches the anchor's position: text
+/// inserted exactly at the anchor, or a replaced range containing it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Bias {
+    /// Stay before text inserted at the anchor; collapse to the start of a
+    /// replaced range.
+    #[default]
+    Left,
+    /// Move after text inserted at the anchor; collapse to the end of the
+    /// replacement text.
+    Right,
+}
+
+/// A position that survives edits to the underlying text.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::position::*;
+/// let edits = [TextEdit::insert(BytePos(2), "xyz")];
+/// let left = Anchor::new(BytePos(2), Bias::Left);
+/// let right = Anchor::new(BytePos(2), Bias::Right);
+/// assert_eq!(left.map_through_edits(&edits).pos, BytePos(2));
+/// assert_eq!(right.map_through_edits(&edits).pos, BytePos(5));
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Anchor {
+    pub pos: BytePos,
+    pub bias: Bias,
+}
+
+impl Anchor {
+    pub const fn new(pos: BytePos, bias: Bias) -> Self {
+        Anchor { pos, bias }
+    }
+
+    /// Returns the anchor moved through `edits`.
+    ///
+    /// The edits must not overlap and must be sorted by the start of their
+    /// range, all expressed in the coordinates of the text before editing.
+    pub fn map_through_edits(&self, edits: &[TextEdit]) -> Self {
+        let pos = self.pos.to_usize();
+        let mut delta: isize = 0;
+
+        for (index, edit) in edits.iter().enumerate() {
+            let start = edit.range.start();
+            let end = edit.range.end();
+
+            if end < pos || (end == pos && start < pos) {
+                delta += edit.delta();
+            } else if start > pos {
+                break;
+            } else {
+                let start = (start as isize + delta) as usize;
+                let pos = match self.bias {
+                    Bias::Left => start,
+                    // Text inserted at the anchor by the edits after this
+                    // one goes after it too.
+                    Bias::Right => {
+                        let inserted = edits[index + 1..]
+                            .iter()
+                            .take_while(|next| next.range.start() == pos)
+                            .map(|next| next.new_text.len());
+                        start + edit.new_text.len() + inserted.sum::<usize>()
+                    }
+                };
+                return Anchor::new(BytePos::from_usize(pos), self.bias);
+            }
+        }
+
+        Anchor::new(
+            BytePos::from_usize((pos as isize + delta) as usize),
+            self.bias,
+        )
+    }
+}
+
+/// Identifies an anchor inside an [`AnchorSet`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AnchorId(usize);
+
+/// A collection of anchors that can be moved through edits in one batch.
+///
+/// Tools that hold on to positions across edits (breakpoints, diagnostics,
+/// rename targets) register them once and call [`AnchorSet::apply_edits`]
+/// whenever the text changes.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::position::*;
+/// let mut anchors = AnchorSet::new();
+/// let id = anchors.insert(Anchor::new(BytePos(10), Bias::Left));
+/// anchors.apply_edits(&[TextEdit::delete(Span::new(0, 4).unwrap())]);
+/// assert_eq!(anchors.get(id).unwrap().pos, BytePos(6));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct AnchorSet {
+    anchors: Vec<Option<Anchor>>,
+}
+
+impl AnchorSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an anchor to the set.
+    pub fn insert(&mut self, anchor: Anchor) -> AnchorId {
+        self.anchors.push(Some(anchor));
+        AnchorId(self.anchors.len() - 1)
+    }
+
+    /// Removes an anchor from the set, returning it if it was present.
+    pub fn remove(&mut self, id: AnchorId) -> Option<Anchor> {
+        self.anchors.get_mut(id.0).and_then(Option::take)
+    }
+
+    /// Returns the current state of an anchor.
+    pub fn get(&self, id: AnchorId) -> Option<Anchor> {
+        self.anchors.get(id.0).copied().flatten()
+    }
+
+    /// Returns the number of anchors in the set.
+    pub fn len(&self) -> usize {
+        self.anchors.iter().flatten().count()
+    }
+
+    /// Returns true if the set contains no anchors.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Iterates over all anchors in the set.
+    pub fn iter(&self) -> impl Iterator<Item = (AnchorId, Anchor)> + '_ {
+        self.anchors
+            .iter()
+            .enumerate()
+            .filter_map(|(i, anchor)| anchor.map(|anchor| (AnchorId(i), anchor)))
+    }
+
+    /// Moves every anchor through `edits`.
+    ///
+    /// The edits must not overlap and are expressed in the coordinates of the
+    /// text before editing; they do not need to be sorted.
+    pub fn apply_edits(&mut self, edits: &[TextEdit]) {
+        let mut sorted = edits.to_vec();
+        sorted.sort_by_key(|edit| (edit.range.start, edit.range.end));
+
+        for anchor in self.anchors.iter_mut().flatten() {
+            *anchor = anchor.map_through_edits(&sorted);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::position::{apply_edits, Span};
+
+    fn map(pos: usize, bias: Bias, edits: &[TextEdit]) -> usize {
+        Anchor::new(BytePos::from_usize(pos), bias)
+            .map_through_edits(edits)
+            .pos
+            .to_usize()
+    }
+
+    #[test]
+    fn test_edits_before_and_after() {
+        let edits = [
+            TextEdit::new(Span::new(0, 2).unwrap(), "abcd"),
+            TextEdit::delete(Span::new(10, 12).unwrap()),
+        ];
+        assert_eq!(map(5, Bias::Left, &edits), 7);
+        assert_eq!(map(5, Bias::Right, &edits), 7);
+        assert_eq!(map(15, Bias::Left, &edits), 15);
+    }
+
+    #[test]
+    fn test_insert_at_anchor() {
+        let edits = [TextEdit::insert(BytePos(4), "xx")];
+        assert_eq!(map(4, Bias::Left, &edits), 4);
+        assert_eq!(map(4, Bias::Right, &edits), 6);
+    }
+
+    #[test]
+    fn test_several_inserts_at_anchor() {
+        let edits = [
+            TextEdit::insert(BytePos(4), "x"),
+            TextEdit::insert(BytePos(4), "y"),
+        ];
+        assert_eq!(apply_edits("abcdefg", &edits).unwrap(), "abcdxyefg");
+        assert_eq!(map(4, Bias::Left, &edits), 4);
+        assert_eq!(map(4, Bias::Right, &edits), 6);
+        assert_eq!(map(5, Bias::Left, &edits), 7);
+    }
+
+    #[test]
+    fn test_anchor_inside_replaced_range() {
+        let edits = [TextEdit::new(Span::new(2, 8).unwrap(), "abc")];
+        assert_eq!(map(5, Bias::Left, &edits), 2);
+        assert_eq!(map(5, Bias::Right, &edits), 5);
+        assert_eq!(map(2, Bias::Left, &edits), 2);
+        assert_eq!(map(2, Bias::Right, &edits), 5);
+        // The end of a replaced range always follows the replacement.
+        assert_eq!(map(8, Bias::Left, &edits), 5);
+    }
+
+    #[test]
+    fn test_anchor_set() {
+        let mut anchors = AnchorSet::new();
+        let a = anchors.insert(Anchor::new(BytePos(1), Bias::Left));
+        let b = anchors.insert(Anchor::new(BytePos(9), Bias::Right));
+        let c = anchors.insert(Anchor::new(BytePos(20), Bias::Left));
+        assert_eq!(anchors.remove(c).unwrap().pos, BytePos(20));
+        assert_eq!(anchors.len(), 2);
+
+        anchors.apply_edits(&[
+            TextEdit::insert(BytePos(9), "!"),
+            TextEdit::insert(BytePos(0), "hello "),
+        ]);
+        assert_eq!(anchors.get(a).unwrap().pos, BytePos(7));
+        assert_eq!(anchors.get(b).unwrap().pos, BytePos(16));
+        assert_eq!(anchors.get(c), None);
+    }
+}
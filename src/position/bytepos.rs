@@ -1,10 +1,28 @@
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
+use std::fmt;
 use std::ops::{Add, AddAssign, Sub, SubAssign};
 
+/// The integer type backing [`BytePos`].
+///
+/// This is `u32` by default, which keeps spans and tokens small. Enable the
+/// `u64-positions` feature for inputs larger than 4GB.
+#[cfg(not(feature = "u64-positions"))]
+pub type RawPos = u32;
+
+/// The integer type backing [`BytePos`].
+///
+/// This is `u64` because the `u64-positions` feature is enabled.
+#[cfg(feature = "u64-positions")]
+pub type RawPos = u64;
+
 /// A position in a file.
 ///
+/// Positions are stored as a [`RawPos`], but most of the API accepts and
+/// returns `usize` for convenience. Converting a `usize` that does not fit in
+/// a `RawPos` panics; use [`BytePos::try_from_usize`] to handle it instead.
+///
 /// # Examples
 /// ```
 /// use grammarsmith::position::BytePos;
@@ -13,26 +31,126 @@ use std::ops::{Add, AddAssign, Sub, SubAssign};
 /// ```
 #[derive(Debug, Copy, Clone, PartialEq, PartialOrd, Eq, Ord, Hash, Default)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-pub struct BytePos(pub usize);
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    rkyv(
+        derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash),
+        compare(PartialEq)
+    )
+)]
+pub struct BytePos(pub RawPos);
 
 impl BytePos {
+    /// The largest representable position.
+    pub const MAX: BytePos = BytePos(RawPos::MAX);
+
+    /// Converts a `usize` offset into a position.
+    ///
+    /// # Panics
+    /// Panics if `value` does not fit in a [`RawPos`].
+    pub const fn from_usize(value: usize) -> Self {
+        match Self::try_from_usize(value) {
+            Some(pos) => pos,
+            None => panic!("byte position does not fit in the position type"),
+        }
+    }
+
+    /// Converts a `usize` offset into a position, returning `None` if it does
+    /// not fit in a [`RawPos`].
+    pub const fn try_from_usize(value: usize) -> Option<Self> {
+        if value as u128 > RawPos::MAX as u128 {
+            None
+        } else {
+            Some(BytePos(value as RawPos))
+        }
+    }
+
+    /// Returns the position as a `usize` offset, e.g. for slicing the source.
+    pub const fn to_usize(self) -> usize {
+        self.0 as usize
+    }
+
     pub fn shift(self, ch: char) -> Self {
-        BytePos(self.0 + ch.len_utf8())
+        self + ch.len_utf8()
+    }
+
+    /// Checked addition. Returns `None` if the result would overflow.
+    ///
+    /// # Examples
+    /// ```
+    /// use grammarsmith::position::BytePos;
+    /// assert_eq!(BytePos(1).checked_add(2), Some(BytePos(3)));
+    /// assert_eq!(BytePos::MAX.checked_add(1), None);
+    /// ```
+    pub fn checked_add(self, rhs: usize) -> Option<Self> {
+        let rhs = RawPos::try_from(rhs).ok()?;
+        self.0.checked_add(rhs).map(BytePos)
+    }
+
+    /// Checked subtraction. Returns `None` if the result would underflow.
+    ///
+    /// # Examples
+    /// ```
+    /// use grammarsmith::position::BytePos;
+    /// assert_eq!(BytePos(3).checked_sub(2), Some(BytePos(1)));
+    /// assert_eq!(BytePos(0).checked_sub(1), None);
+    /// ```
+    pub fn checked_sub(self, rhs: usize) -> Option<Self> {
+        let rhs = RawPos::try_from(rhs).ok()?;
+        self.0.checked_sub(rhs).map(BytePos)
+    }
+
+    /// The number of bytes between two positions, regardless of their order.
+    ///
+    /// # Examples
+    /// ```
+    /// use grammarsmith::position::BytePos;
+    /// assert_eq!(BytePos(3).distance(BytePos(10)), 7);
+    /// assert_eq!(BytePos(10).distance(BytePos(3)), 7);
+    /// ```
+    pub fn distance(self, other: BytePos) -> usize {
+        self.0.abs_diff(other.0) as usize
     }
 }
 
 impl From<usize> for BytePos {
+    /// # Panics
+    /// Panics if `value` does not fit in a [`RawPos`].
     fn from(value: usize) -> Self {
-        BytePos(value)
+        BytePos::from_usize(value)
     }
 }
 
 impl From<BytePos> for usize {
     fn from(value: BytePos) -> Self {
-        value.0
+        value.to_usize()
     }
 }
 
+/// Error returned when a source text is too large to be addressed by
+/// [`BytePos`].
+///
+/// Enable the `u64-positions` feature to support larger inputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PositionOverflow {
+    /// The length of the offending source text in bytes.
+    pub len: usize,
+}
+
+impl fmt::Display for PositionOverflow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "source of {} bytes exceeds the maximum addressable position {}",
+            self.len,
+            RawPos::MAX
+        )
+    }
+}
+
+impl std::error::Error for PositionOverflow {}
+
 impl Add<BytePos> for BytePos {
     type Output = Self;
 
@@ -45,13 +163,13 @@ impl Add<usize> for BytePos {
     type Output = Self;
 
     fn add(self, rhs: usize) -> Self::Output {
-        BytePos(self.0 + rhs)
+        self + BytePos::from_usize(rhs)
     }
 }
 
 impl AddAssign<usize> for BytePos {
     fn add_assign(&mut self, rhs: usize) {
-        self.0 += rhs;
+        *self = *self + rhs;
     }
 }
 
@@ -73,13 +191,13 @@ impl Sub<usize> for BytePos {
     type Output = Self;
 
     fn sub(self, rhs: usize) -> Self::Output {
-        BytePos(self.0 - rhs)
+        self - BytePos::from_usize(rhs)
     }
 }
 
 impl SubAssign<usize> for BytePos {
     fn sub_assign(&mut self, rhs: usize) {
-        self.0 -= rhs;
+        *self = *self - rhs;
     }
 }
 
@@ -117,6 +235,38 @@ mod tests {
         assert_eq!(pos, BytePos(2));
     }
 
+    #[test]
+    fn test_checked_operations() {
+        assert_eq!(BytePos(5).checked_add(3), Some(BytePos(8)));
+        assert_eq!(BytePos(RawPos::MAX - 1).checked_add(2), None);
+        assert_eq!(BytePos(5).checked_sub(5), Some(BytePos(0)));
+        assert_eq!(BytePos(5).checked_sub(6), None);
+    }
+
+    #[test]
+    fn test_usize_conversions() {
+        assert_eq!(BytePos::from_usize(42), BytePos(42));
+        assert_eq!(BytePos(42).to_usize(), 42);
+        assert_eq!(
+            BytePos::try_from_usize(RawPos::MAX as usize),
+            Some(BytePos::MAX)
+        );
+    }
+
+    #[cfg(all(not(feature = "u64-positions"), target_pointer_width = "64"))]
+    #[test]
+    fn test_usize_overflow() {
+        assert_eq!(BytePos::try_from_usize(u32::MAX as usize + 1), None);
+        assert_eq!(BytePos(0).checked_add(u32::MAX as usize + 1), None);
+    }
+
+    #[test]
+    fn test_distance() {
+        assert_eq!(BytePos(5).distance(BytePos(5)), 0);
+        assert_eq!(BytePos(2).distance(BytePos(9)), 7);
+        assert_eq!(BytePos(9).distance(BytePos(2)), 7);
+    }
+
     #[test]
     fn test_shift_with_different_chars() {
         let pos = BytePos(0);
@@ -0,0 +1,206 @@
+use super::{BytePos, GetSpan, Span};
+
+/// How far a token like a string or comment extends, as seen by a cursor.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Enclosure {
+    /// The token ends with a closing delimiter, like a terminated string or a
+    /// block comment; a cursor at its end is outside of it.
+    Delimited,
+    /// The token runs to its end, like a line comment or an unterminated
+    /// string; a cursor at its end is still inside of it.
+    Open,
+}
+
+/// The tokens around a cursor, the first step of completion and signature
+/// help.
+///
+/// The tokens must be sorted and must not overlap, see
+/// [`SortedSpans`](super::SortedSpans). Empty tokens, such as an end of file
+/// token, are ignored.
+///
+/// A cursor inside a token, or right after it, is in that token: at `foo|`
+/// the token being typed is `foo`. A cursor between two adjacent tokens is
+/// therefore in the left one, so at `foo|(` it is in `foo`, and the `(` is
+/// the next token.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::position::*;
+///
+/// // `let x = fo| + 1`
+/// let tokens = vec![
+///     WithSpan::new("let", Span::new(0, 3).unwrap()),
+///     WithSpan::new("x", Span::new(4, 5).unwrap()),
+///     WithSpan::new("=", Span::new(6, 7).unwrap()),
+///     WithSpan::new("foo", Span::new(8, 11).unwrap()),
+///     WithSpan::new("+", Span::new(12, 13).unwrap()),
+/// ];
+///
+/// let cursor = CursorContext::new(&tokens, BytePos(10));
+/// assert_eq!(cursor.token().unwrap().value, "foo");
+/// assert_eq!(cursor.previous().unwrap().value, "=");
+/// assert_eq!(cursor.next().unwrap().value, "+");
+/// assert_eq!(cursor.prefix(), Span::new(8, 10).unwrap());
+///
+/// // In the whitespace after `=`, no token is being typed.
+/// let cursor = CursorContext::new(&tokens, BytePos(8));
+/// assert!(cursor.token().is_none());
+/// assert_eq!(cursor.previous().unwrap().value, "=");
+/// assert_eq!(cursor.next().unwrap().value, "foo");
+/// ```
+#[derive(Debug)]
+pub struct CursorContext<'a, T> {
+    tokens: &'a [T],
+    offset: BytePos,
+    /// The index of the first token starting at or after the cursor.
+    split: usize,
+    /// The index of the token the cursor is in.
+    token: Option<usize>,
+}
+
+impl<T> Clone for CursorContext<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for CursorContext<'_, T> {}
+
+impl<'a, T: GetSpan> CursorContext<'a, T> {
+    /// Finds the tokens around the cursor at `offset`.
+    pub fn new(tokens: &'a [T], offset: BytePos) -> Self {
+        let split = tokens.partition_point(|token| token.get_span().start < offset);
+        let token = split.checked_sub(1).filter(|&i| {
+            let span = tokens[i].get_span();
+            !span.is_empty() && span.end >= offset
+        });
+        CursorContext {
+            tokens,
+            offset,
+            split,
+            token,
+        }
+    }
+
+    /// Returns the offset of the cursor.
+    pub fn offset(&self) -> BytePos {
+        self.offset
+    }
+
+    /// Returns the token the cursor is in or right after, usually the one
+    /// being typed.
+    pub fn token(&self) -> Option<&'a T> {
+        self.token.map(|i| &self.tokens[i])
+    }
+
+    /// Returns the token before the cursor, not counting the token being
+    /// typed.
+    pub fn previous(&self) -> Option<&'a T> {
+        let end = self.token.unwrap_or(self.split);
+        self.tokens[..end]
+            .iter()
+            .rev()
+            .find(|token| !token.get_span().is_empty())
+    }
+
+    /// Returns the first token after the cursor.
+    pub fn next(&self) -> Option<&'a T> {
+        self.tokens[self.split..]
+            .iter()
+            .find(|token| !token.get_span().is_empty())
+    }
+
+    /// Returns the part of the token being typed before the cursor, which a
+    /// completion replaces, or an empty span at the cursor.
+    pub fn prefix(&self) -> Span {
+        let start = self
+            .token()
+            .map_or(self.offset, |token| token.get_span().start);
+        Span {
+            start,
+            end: self.offset,
+        }
+    }
+
+    /// Returns whether the cursor is inside a token like a string or comment,
+    /// where code completion makes no sense.
+    ///
+    /// `enclosure` returns how far such tokens extend, and None for other
+    /// tokens.
+    ///
+    /// # Examples
+    /// ```
+    /// use grammarsmith::position::*;
+    ///
+    /// // `"a" // b`
+    /// let tokens = vec![
+    ///     WithSpan::new("string", Span::new(0, 3).unwrap()),
+    ///     WithSpan::new("comment", Span::new(4, 8).unwrap()),
+    /// ];
+    /// let enclosure = |token: &WithSpan<&str>| match token.value {
+    ///     "string" => Some(Enclosure::Delimited),
+    ///     "comment" => Some(Enclosure::Open),
+    ///     _ => None,
+    /// };
+    ///
+    /// assert!(CursorContext::new(&tokens, BytePos(2)).is_inside(enclosure));
+    /// assert!(!CursorContext::new(&tokens, BytePos(3)).is_inside(enclosure));
+    /// assert!(CursorContext::new(&tokens, BytePos(8)).is_inside(enclosure));
+    /// ```
+    pub fn is_inside(&self, enclosure: impl Fn(&T) -> Option<Enclosure>) -> bool {
+        let Some(token) = self.token() else {
+            return false;
+        };
+        match enclosure(token) {
+            Some(Enclosure::Delimited) => self.offset < token.get_span().end,
+            Some(Enclosure::Open) => true,
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spans(ranges: &[(usize, usize)]) -> Vec<Span> {
+        ranges
+            .iter()
+            .map(|&(start, end)| Span::new(start, end).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_between_adjacent_tokens() {
+        let tokens = spans(&[(0, 3), (3, 4), (4, 4), (5, 6), (6, 6)]);
+        let cursor = CursorContext::new(&tokens, BytePos(3));
+        assert_eq!(cursor.token(), Some(&tokens[0]));
+        assert_eq!(cursor.previous(), None);
+        assert_eq!(cursor.next(), Some(&tokens[1]));
+
+        let cursor = CursorContext::new(&tokens, BytePos(4));
+        assert_eq!(cursor.token(), Some(&tokens[1]));
+        assert_eq!(cursor.previous(), Some(&tokens[0]));
+        assert_eq!(cursor.next(), Some(&tokens[3]));
+
+        let cursor = CursorContext::new(&tokens, BytePos(6));
+        assert_eq!(cursor.token(), Some(&tokens[3]));
+        assert_eq!(cursor.next(), None);
+    }
+
+    #[test]
+    fn test_start_and_empty() {
+        let tokens = spans(&[(1, 2)]);
+        let cursor = CursorContext::new(&tokens, BytePos(0));
+        assert_eq!(cursor.token(), None);
+        assert_eq!(cursor.prefix(), Span::point(0));
+        assert_eq!(cursor.next(), Some(&tokens[0]));
+
+        let cursor = CursorContext::new(&[] as &[Span], BytePos(0));
+        assert_eq!(
+            (cursor.token(), cursor.previous(), cursor.next()),
+            (None, None, None)
+        );
+        assert!(!cursor.is_inside(|_| Some(Enclosure::Open)));
+    }
+}
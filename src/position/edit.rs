@@ -0,0 +1,283 @@
+use super::{BytePos, Span};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A single edit to a source text: replace the text in `range` with `new_text`.
+///
+/// Insertions are edits with an empty range and deletions are edits with an
+/// empty `new_text`. The range is expressed in the coordinates of the text
+/// *before* the edit is applied.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::position::*;
+/// let insert = TextEdit::insert(BytePos(3), "abc");
+/// assert_eq!(insert.range, Span::point(3));
+/// let delete = TextEdit::delete(Span::new(0, 2).unwrap());
+/// assert_eq!(delete.new_text, "");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TextEdit {
+    pub range: Span,
+    pub new_text: String,
+}
+
+impl TextEdit {
+    /// Creates an edit replacing `range` with `new_text`.
+    pub fn new(range: Span, new_text: impl Into<String>) -> Self {
+        TextEdit {
+            range,
+            new_text: new_text.into(),
+        }
+    }
+
+    /// Creates an edit inserting `new_text` at `pos`.
+    pub fn insert(pos: BytePos, new_text: impl Into<String>) -> Self {
+        TextEdit::new(Span::point(pos.to_usize()), new_text)
+    }
+
+    /// Creates an edit removing the text in `range`.
+    pub fn delete(range: Span) -> Self {
+        TextEdit::new(range, String::new())
+    }
+
+    /// Returns true if the edit does not remove any text.
+    pub fn is_insert(&self) -> bool {
+        self.range.is_empty()
+    }
+
+    /// The change in text length caused by applying this edit.
+    pub fn delta(&self) -> isize {
+        self.new_text.len() as isize - self.range.len() as isize
+    }
+}
+
+/// Error returned when a set of edits cannot be applied to a source text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditError {
+    /// Two edits touch the same text.
+    Overlapping(Span, Span),
+    /// An edit's range extends past the end of the source.
+    OutOfBounds(Span),
+    /// An edit's range does not start or end on a UTF-8 character boundary.
+    NotCharBoundary(Span),
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EditError::Overlapping(a, b) => write!(
+                f,
+                "edits at {}..{} and {}..{} overlap",
+                a.start(),
+                a.end(),
+                b.start(),
+                b.end()
+            ),
+            EditError::OutOfBounds(span) => write!(
+                f,
+                "edit at {}..{} is out of bounds",
+                span.start(),
+                span.end()
+            ),
+            EditError::NotCharBoundary(span) => write!(
+                f,
+                "edit at {}..{} is not on a character boundary",
+                span.start(),
+                span.end()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for EditError {}
+
+/// Sorts edits by position and checks that they do not overlap.
+///
+/// Several insertions at the same position are allowed and keep their
+/// relative order.
+pub fn sort_edits(edits: &[TextEdit]) -> Result<Vec<&TextEdit>, EditError> {
+    let mut sorted: Vec<&TextEdit> = edits.iter().collect();
+    sorted.sort_by_key(|edit| (edit.range.start, edit.range.end));
+
+    for pair in sorted.windows(2) {
+        if pair[1].range.start < pair[0].range.end {
+            return Err(EditError::Overlapping(pair[0].range, pair[1].range));
+        }
+    }
+
+    Ok(sorted)
+}
+
+/// Applies `edits` to `source` and returns the edited text.
+///
+/// All edit ranges are expressed in the coordinates of `source`; the order of
+/// `edits` does not matter except between insertions at the same position.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::position::*;
+/// let edits = [
+///     TextEdit::new(Span::new(4, 7).unwrap(), "y"),
+///     TextEdit::insert(BytePos(0), "// "),
+/// ];
+/// assert_eq!(apply_edits("let foo = 1;", &edits).unwrap(), "// let y = 1;");
+/// ```
+pub fn apply_edits(source: &str, edits: &[TextEdit]) -> Result<String, EditError> {
+    let sorted = sort_edits(edits)?;
+
+    for edit in &sorted {
+        let range = edit.range;
+        if range.end() > source.len() {
+            return Err(EditError::OutOfBounds(range));
+        }
+        if !source.is_char_boundary(range.start()) || !source.is_char_boundary(range.end()) {
+            return Err(EditError::NotCharBoundary(range));
+        }
+    }
+
+    let delta: isize = sorted.iter().map(|edit| edit.delta()).sum();
+    let mut result = String::with_capacity((source.len() as isize + delta).max(0) as usize);
+    let mut last = 0;
+    for edit in sorted {
+        result.push_str(&source[last..edit.range.start()]);
+        result.push_str(&edit.new_text);
+        last = edit.range.end();
+    }
+    result.push_str(&source[last..]);
+
+    Ok(result)
+}
+
+impl Span {
+    /// Maps the span through `edits`, returning where the same text lives in
+    /// the edited source.
+    ///
+    /// Edits before the span shift it, and edits inside it grow or shrink it.
+    /// Insertions at the span's boundaries end up outside the span. Returns
+    /// `None` if an edit crosses one of the span's boundaries or if the edits
+    /// overlap each other.
+    ///
+    /// # Examples
+    /// ```
+    /// use grammarsmith::position::*;
+    /// let span = Span::new(4, 7).unwrap();
+    /// let edits = [TextEdit::insert(BytePos(0), "pub ")];
+    /// assert_eq!(span.map_through_edits(&edits), Span::new(8, 11));
+    /// let edits = [TextEdit::delete(Span::new(2, 5).unwrap())];
+    /// assert_eq!(span.map_through_edits(&edits), None);
+    /// ```
+    pub fn map_through_edits(&self, edits: &[TextEdit]) -> Option<Span> {
+        let mut start = self.start() as isize;
+        let mut end = self.end() as isize;
+
+        for edit in sort_edits(edits).ok()? {
+            if edit.range.end <= self.start {
+                start += edit.delta();
+                end += edit.delta();
+            } else if edit.range.start >= self.end {
+                break;
+            } else if self.start <= edit.range.start && edit.range.end <= self.end {
+                end += edit.delta();
+            } else {
+                return None;
+            }
+        }
+
+        Span::new(start as usize, end as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(start: usize, end: usize) -> Span {
+        Span::new(start, end).unwrap()
+    }
+
+    #[test]
+    fn test_apply_edits() {
+        let source = "hello world";
+        let edits = [
+            TextEdit::new(span(6, 11), "there"),
+            TextEdit::delete(span(0, 1)),
+            TextEdit::insert(BytePos(1), "J"),
+        ];
+        assert_eq!(apply_edits(source, &edits).unwrap(), "Jello there");
+        assert_eq!(apply_edits(source, &[]).unwrap(), source);
+    }
+
+    #[test]
+    fn test_apply_edits_same_position_inserts() {
+        let edits = [
+            TextEdit::insert(BytePos(1), "x"),
+            TextEdit::insert(BytePos(1), "y"),
+        ];
+        assert_eq!(apply_edits("ab", &edits).unwrap(), "axyb");
+    }
+
+    #[test]
+    fn test_apply_edits_errors() {
+        let overlapping = [TextEdit::delete(span(0, 3)), TextEdit::delete(span(2, 4))];
+        assert_eq!(
+            apply_edits("hello", &overlapping),
+            Err(EditError::Overlapping(span(0, 3), span(2, 4)))
+        );
+        let inside = [
+            TextEdit::delete(span(0, 3)),
+            TextEdit::insert(BytePos(1), "x"),
+        ];
+        assert!(matches!(
+            apply_edits("hello", &inside),
+            Err(EditError::Overlapping(..))
+        ));
+        assert_eq!(
+            apply_edits("hello", &[TextEdit::delete(span(3, 6))]),
+            Err(EditError::OutOfBounds(span(3, 6)))
+        );
+        assert_eq!(
+            apply_edits("é", &[TextEdit::delete(span(0, 1))]),
+            Err(EditError::NotCharBoundary(span(0, 1)))
+        );
+    }
+
+    #[test]
+    fn test_map_through_edits() {
+        let s = span(5, 10);
+        // Before, inside and after the span.
+        let edits = [
+            TextEdit::new(span(0, 2), "a"),
+            TextEdit::new(span(6, 8), "xyz"),
+            TextEdit::delete(span(12, 15)),
+        ];
+        assert_eq!(s.map_through_edits(&edits), Some(span(4, 10)));
+        // Insertions at the boundaries stay outside.
+        let edits = [
+            TextEdit::insert(BytePos(5), "ab"),
+            TextEdit::insert(BytePos(10), "cd"),
+        ];
+        assert_eq!(s.map_through_edits(&edits), Some(span(7, 12)));
+        // Replacing the whole span.
+        let edits = [TextEdit::new(span(5, 10), "x")];
+        assert_eq!(s.map_through_edits(&edits), Some(span(5, 6)));
+        // Crossing a boundary.
+        let edits = [TextEdit::delete(span(8, 12))];
+        assert_eq!(s.map_through_edits(&edits), None);
+    }
+
+    #[test]
+    fn test_map_through_edits_matches_apply() {
+        let source = "fn main() { body(); }";
+        let body = span(12, 19);
+        let edits = [
+            TextEdit::new(span(3, 7), "start"),
+            TextEdit::insert(BytePos(16), "_fn"),
+        ];
+        let edited = apply_edits(source, &edits).unwrap();
+        let mapped = body.map_through_edits(&edits).unwrap();
+        assert_eq!(&edited[mapped.start()..mapped.end()], "body_fn();");
+    }
+}
@@ -1,4 +1,60 @@
-use super::BytePos;
+use super::{BytePos, Span, TextEdit};
+use crate::source::SourceText;
+#[cfg(feature = "graphemes")]
+use unicode_segmentation::UnicodeSegmentation;
+
+/// The unit in which columns are counted.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum ColumnMode {
+    /// Count UTF-8 bytes.
+    Byte,
+    /// Count Unicode scalar values (`char`s).
+    #[default]
+    Char,
+    /// Count UTF-16 code units, as used by LSP and JavaScript.
+    Utf16,
+    /// Count extended grapheme clusters, matching what a human sees in an
+    /// editor when the text contains emoji or combining marks.
+    ///
+    /// Clusters are only known with the `graphemes` feature; without it,
+    /// characters are counted as with [`Char`](ColumnMode::Char).
+    Grapheme,
+}
+
+impl ColumnMode {
+    /// The width of a character in this mode.
+    ///
+    /// A character on its own is one grapheme cluster, so it counts as one
+    /// in [`Grapheme`](ColumnMode::Grapheme) mode; use
+    /// [`str_width`](ColumnMode::str_width) for text, where characters may
+    /// combine.
+    pub fn width(self, ch: char) -> usize {
+        match self {
+            ColumnMode::Byte => ch.len_utf8(),
+            ColumnMode::Char | ColumnMode::Grapheme => 1,
+            ColumnMode::Utf16 => ch.len_utf16(),
+        }
+    }
+
+    /// The width of a string in this mode.
+    pub fn str_width(self, text: &str) -> usize {
+        self.segments(text).map(|(_, width)| width).sum()
+    }
+
+    /// Splits `text` into the units counted by this mode, yielding the byte
+    /// offset and width of each.
+    fn segments(self, text: &str) -> Box<dyn Iterator<Item = (usize, usize)> + '_> {
+        match self {
+            ColumnMode::Byte => Box::new(text.char_indices().map(|(i, ch)| (i, ch.len_utf8()))),
+            ColumnMode::Char => Box::new(text.char_indices().map(|(i, _)| (i, 1))),
+            ColumnMode::Utf16 => Box::new(text.char_indices().map(|(i, ch)| (i, ch.len_utf16()))),
+            #[cfg(feature = "graphemes")]
+            ColumnMode::Grapheme => Box::new(text.grapheme_indices(true).map(|(i, _)| (i, 1))),
+            #[cfg(not(feature = "graphemes"))]
+            ColumnMode::Grapheme => ColumnMode::Char.segments(text),
+        }
+    }
+}
 
 /// Helper struct to convert BytePos into line numbers.
 ///
@@ -12,19 +68,33 @@ use super::BytePos;
 /// assert_eq!(offsets.line(BytePos(3)), 1);
 /// assert_eq!(offsets.line(BytePos(7)), 2);
 /// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct LineOffsets {
     offsets: Vec<usize>,
     len: usize,
 }
 
 impl LineOffsets {
-    pub fn new(data: &str) -> Self {
+    pub fn new<S: SourceText + ?Sized>(data: &S) -> Self {
         let mut offsets = vec![0];
         let len = data.len();
 
-        for (i, val) in data.bytes().enumerate() {
-            if val == b'\n' {
-                offsets.push(i + 1);
+        match data.as_str() {
+            Some(text) => {
+                for (i, val) in text.bytes().enumerate() {
+                    if val == b'\n' {
+                        offsets.push(i + 1);
+                    }
+                }
+            }
+            None => {
+                let mut i = 0;
+                for ch in data.chars_from(0) {
+                    i += ch.len_utf8();
+                    if ch == '\n' {
+                        offsets.push(i);
+                    }
+                }
             }
         }
 
@@ -33,7 +103,7 @@ impl LineOffsets {
 
     /// Find the line number for a given BytePos
     pub fn line(&self, pos: BytePos) -> usize {
-        let offset = pos.0;
+        let offset = pos.to_usize();
 
         assert!(offset <= self.len);
 
@@ -42,6 +112,188 @@ impl LineOffsets {
             Err(line) => line,
         }
     }
+
+    /// Find the column (1-based) of a BytePos, counted in the given mode.
+    ///
+    /// `source` must be the text the offsets were computed from.
+    ///
+    /// # Panics
+    /// Panics if `pos` is out of bounds or not on a character boundary.
+    ///
+    /// # Examples
+    /// ```
+    /// use grammarsmith::position::{BytePos, ColumnMode, LineOffsets};
+    /// let source = "a\n€🦀x";
+    /// let offsets = LineOffsets::new(source);
+    /// assert_eq!(offsets.column(BytePos(9), source, ColumnMode::Byte), 8);
+    /// assert_eq!(offsets.column(BytePos(9), source, ColumnMode::Char), 3);
+    /// assert_eq!(offsets.column(BytePos(9), source, ColumnMode::Utf16), 4);
+    /// ```
+    pub fn column<S: SourceText + ?Sized>(
+        &self,
+        pos: BytePos,
+        source: &S,
+        mode: ColumnMode,
+    ) -> usize {
+        let line_start = self.offsets[self.line(pos) - 1];
+        mode.str_width(&source.slice(line_start..pos.to_usize())) + 1
+    }
+
+    /// Find the line and column (both 1-based) of a BytePos.
+    pub fn line_column<S: SourceText + ?Sized>(
+        &self,
+        pos: BytePos,
+        source: &S,
+        mode: ColumnMode,
+    ) -> (usize, usize) {
+        (self.line(pos), self.column(pos, source, mode))
+    }
+
+    /// Find the BytePos of a line and column (both 1-based).
+    ///
+    /// Out of range positions are clamped: a line past the end maps to the end
+    /// of the text, and a column past the end of a line maps to the end of
+    /// that line, before its terminator. A column inside a character (e.g.
+    /// between the halves of a UTF-16 surrogate pair) maps to the start of
+    /// that character.
+    ///
+    /// # Examples
+    /// ```
+    /// use grammarsmith::position::{BytePos, ColumnMode, LineOffsets};
+    /// let source = "a\n€🦀x";
+    /// let offsets = LineOffsets::new(source);
+    /// assert_eq!(offsets.offset(2, 4, source, ColumnMode::Utf16), BytePos(9));
+    /// assert_eq!(offsets.offset(1, 10, source, ColumnMode::Char), BytePos(1));
+    /// ```
+    pub fn offset<S: SourceText + ?Sized>(
+        &self,
+        line: usize,
+        column: usize,
+        source: &S,
+        mode: ColumnMode,
+    ) -> BytePos {
+        let Some(line_span) = self.line_span(line.max(1)) else {
+            return BytePos::from_usize(self.len);
+        };
+        let line_text = source.slice(line_span.start()..line_span.end());
+        let text = trim_line_terminator(&line_text);
+
+        let target = column.saturating_sub(1);
+        let mut width = 0;
+        for (i, segment_width) in mode.segments(text) {
+            width += segment_width;
+            if width > target {
+                return BytePos::from_usize(line_span.start() + i);
+            }
+        }
+        BytePos::from_usize(line_span.start() + text.len())
+    }
+
+    /// Returns the number of lines in the text.
+    pub fn line_count(&self) -> usize {
+        self.offsets.len()
+    }
+
+    /// Returns the span of a line (1-based), including its line terminator.
+    ///
+    /// Returns `None` if the line does not exist.
+    ///
+    /// # Examples
+    /// ```
+    /// use grammarsmith::position::{LineOffsets, Span};
+    /// let offsets = LineOffsets::new("abc\ndef");
+    /// assert_eq!(offsets.line_span(1), Span::new(0, 4));
+    /// assert_eq!(offsets.line_span(2), Span::new(4, 7));
+    /// assert_eq!(offsets.line_span(3), None);
+    /// ```
+    pub fn line_span(&self, line: usize) -> Option<Span> {
+        let start = *self.offsets.get(line.checked_sub(1)?)?;
+        let end = self.offsets.get(line).copied().unwrap_or(self.len);
+        Some(Span::new_unchecked(start, end))
+    }
+
+    /// Iterates over the lines covered by `span`, yielding each line number
+    /// (1-based) together with the portion of the span on that line.
+    ///
+    /// The portions are contiguous and together cover exactly `span`, so a
+    /// portion includes the line terminator when the span extends past it. A
+    /// span ending right after a line terminator does not yield an empty
+    /// portion for the following line, while an empty span yields a single
+    /// empty portion.
+    ///
+    /// # Examples
+    /// ```
+    /// use grammarsmith::position::{LineOffsets, Span};
+    /// let offsets = LineOffsets::new("abc\ndef\nghi");
+    /// let lines: Vec<_> = offsets.lines_of(Span::new(2, 9).unwrap()).collect();
+    /// assert_eq!(
+    ///     lines,
+    ///     vec![
+    ///         (1, Span::new(2, 4).unwrap()),
+    ///         (2, Span::new(4, 8).unwrap()),
+    ///         (3, Span::new(8, 9).unwrap()),
+    ///     ]
+    /// );
+    /// ```
+    pub fn lines_of(&self, span: Span) -> impl Iterator<Item = (usize, Span)> + '_ {
+        let first = self.line(span.start);
+        let last = if span.is_empty() {
+            first
+        } else {
+            self.line(span.end - 1)
+        };
+
+        (first..=last).map(move |line| {
+            let line_span = self.line_span(line).unwrap();
+            let start = line_span.start.max(span.start);
+            let end = line_span.end.min(span.end);
+            (line, Span { start, end })
+        })
+    }
+
+    /// Updates the offsets for `edit` applied to the text, without
+    /// scanning the text outside of the edit.
+    ///
+    /// # Panics
+    /// Panics if the range of `edit` is out of bounds.
+    ///
+    /// # Examples
+    /// ```
+    /// use grammarsmith::position::{LineOffsets, Span, TextEdit};
+    /// let mut offsets = LineOffsets::new("abc\ndef");
+    /// offsets.apply_edit(&TextEdit::new(Span::new(1, 5).unwrap(), "\n\nx"));
+    /// assert_eq!(offsets, LineOffsets::new("a\n\nxef"));
+    /// ```
+    pub fn apply_edit(&mut self, edit: &TextEdit) {
+        let range = edit.range;
+        assert!(range.end() <= self.len, "edit is out of bounds");
+        // Lines starting inside the removed text, after its first byte.
+        let first = self
+            .offsets
+            .partition_point(|&offset| offset <= range.start());
+        let last = self
+            .offsets
+            .partition_point(|&offset| offset <= range.end());
+        let inserted = edit
+            .new_text
+            .char_indices()
+            .filter(|&(_, ch)| ch == '\n')
+            .map(|(i, _)| range.start() + i + 1);
+        self.offsets.splice(first..last, inserted);
+        let delta = edit.delta();
+        let shifted = first + edit.new_text.matches('\n').count();
+        for offset in &mut self.offsets[shifted..] {
+            *offset = offset.wrapping_add_signed(delta);
+        }
+        self.len = self.len.wrapping_add_signed(delta);
+    }
+}
+
+/// Returns `text` without a trailing `\n` or `\r\n`.
+pub(crate) fn trim_line_terminator(text: &str) -> &str {
+    text.strip_suffix('\n')
+        .map(|text| text.strip_suffix('\r').unwrap_or(text))
+        .unwrap_or(text)
 }
 
 #[cfg(test)]
@@ -100,6 +352,118 @@ mod tests {
         assert_eq!(offsets.line(BytePos(13)), 3); // start of line3
     }
 
+    #[test]
+    fn test_lines_of_single_line() {
+        let offsets = LineOffsets::new("hello\nworld");
+        let lines: Vec<_> = offsets.lines_of(Span::new(7, 9).unwrap()).collect();
+        assert_eq!(lines, vec![(2, Span::new(7, 9).unwrap())]);
+    }
+
+    #[test]
+    fn test_lines_of_empty_span() {
+        let offsets = LineOffsets::new("hello\nworld");
+        let lines: Vec<_> = offsets.lines_of(Span::point(6)).collect();
+        assert_eq!(lines, vec![(2, Span::point(6))]);
+        let lines: Vec<_> = offsets.lines_of(Span::point(11)).collect();
+        assert_eq!(lines, vec![(2, Span::point(11))]);
+    }
+
+    #[test]
+    fn test_lines_of_ending_at_line_start() {
+        let offsets = LineOffsets::new("hello\nworld");
+        let lines: Vec<_> = offsets.lines_of(Span::new(2, 6).unwrap()).collect();
+        assert_eq!(lines, vec![(1, Span::new(2, 6).unwrap())]);
+    }
+
+    #[test]
+    fn test_lines_of_empty_lines() {
+        let offsets = LineOffsets::new("a\n\n\nb");
+        let lines: Vec<_> = offsets.lines_of(Span::new(0, 5).unwrap()).collect();
+        assert_eq!(
+            lines,
+            vec![
+                (1, Span::new(0, 2).unwrap()),
+                (2, Span::new(2, 3).unwrap()),
+                (3, Span::new(3, 4).unwrap()),
+                (4, Span::new(4, 5).unwrap()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_column_modes() {
+        let source = "ab\né🦀c";
+        let offsets = LineOffsets::new(source);
+        assert_eq!(
+            offsets.line_column(BytePos(0), source, ColumnMode::Char),
+            (1, 1)
+        );
+        assert_eq!(
+            offsets.line_column(BytePos(2), source, ColumnMode::Char),
+            (1, 3)
+        );
+        assert_eq!(
+            offsets.line_column(BytePos(3), source, ColumnMode::Char),
+            (2, 1)
+        );
+        assert_eq!(offsets.column(BytePos(9), source, ColumnMode::Byte), 7);
+        assert_eq!(offsets.column(BytePos(9), source, ColumnMode::Char), 3);
+        assert_eq!(offsets.column(BytePos(9), source, ColumnMode::Utf16), 4);
+    }
+
+    #[cfg(not(feature = "graphemes"))]
+    #[test]
+    fn test_grapheme_columns_without_feature() {
+        let source = "e\u{301}x";
+        let offsets = LineOffsets::new(source);
+        let x = BytePos(3);
+        assert_eq!(offsets.column(x, source, ColumnMode::Grapheme), 3);
+        assert_eq!(offsets.offset(1, 3, source, ColumnMode::Grapheme), x);
+    }
+
+    #[cfg(feature = "graphemes")]
+    #[test]
+    fn test_grapheme_columns() {
+        // `e` + combining acute accent, then a family emoji made of 5 chars.
+        let source = "e\u{301}👨\u{200d}👩\u{200d}👧x";
+        let offsets = LineOffsets::new(source);
+        let x = BytePos::from_usize(source.len() - 1);
+        assert_eq!(offsets.column(x, source, ColumnMode::Char), 8);
+        assert_eq!(offsets.column(x, source, ColumnMode::Grapheme), 3);
+        assert_eq!(offsets.offset(1, 3, source, ColumnMode::Grapheme), x);
+        assert_eq!(
+            offsets.offset(1, 2, source, ColumnMode::Grapheme),
+            BytePos(3)
+        );
+    }
+
+    #[test]
+    fn test_offset_round_trip() {
+        let source = "ab\r\né🦀c\n";
+        let offsets = LineOffsets::new(source);
+        for mode in [ColumnMode::Byte, ColumnMode::Char, ColumnMode::Utf16] {
+            for (i, _) in source.char_indices() {
+                let pos = BytePos::from_usize(i);
+                if source[i..].starts_with('\n') {
+                    continue;
+                }
+                let (line, column) = offsets.line_column(pos, source, mode);
+                assert_eq!(offsets.offset(line, column, source, mode), pos);
+            }
+        }
+    }
+
+    #[test]
+    fn test_offset_clamping() {
+        let source = "ab\r\n🦀";
+        let offsets = LineOffsets::new(source);
+        assert_eq!(offsets.offset(1, 99, source, ColumnMode::Char), BytePos(2));
+        assert_eq!(offsets.offset(9, 1, source, ColumnMode::Char), BytePos(8));
+        // Between the two halves of the surrogate pair.
+        assert_eq!(offsets.offset(2, 2, source, ColumnMode::Utf16), BytePos(4));
+        assert_eq!(offsets.offset(2, 3, source, ColumnMode::Utf16), BytePos(8));
+    }
+
     #[test]
     #[should_panic]
     fn test_position_beyond_length() {
@@ -0,0 +1,152 @@
+use super::{BytePos, LineOffsets};
+
+#[derive(Debug, Clone)]
+struct LineDirective {
+    at: BytePos,
+    target: Option<(String, usize)>,
+}
+
+/// A location reported in terms of an original file rather than the text
+/// that was actually parsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RemappedLocation<'a> {
+    pub file: &'a str,
+    /// 1-based line number in `file`.
+    pub line: usize,
+}
+
+/// Remaps positions in generated or preprocessed text back to the files they
+/// came from, in the style of C's `#line` directive.
+///
+/// Preprocessors register directives of the form "from byte X onward, report
+/// file F line N", and location lookups go through [`PositionRemapper::remap`].
+/// Lines after `X` are counted from `N`. Only lines are remapped: columns
+/// are those of the text that was parsed.
+///
+/// A [`SourceFile`](crate::source::SourceFile) given a remapper with
+/// [`with_remapper`](crate::source::SourceFile::with_remapper) reports its
+/// locations through it, so diagnostics rendered for it point into the
+/// original files.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::position::*;
+/// let source = "#line 10 \"main.c\"\nint x;\nint y;\n";
+/// let offsets = LineOffsets::new(source);
+/// let mut remapper = PositionRemapper::new();
+/// remapper.add(BytePos(18), "main.c", 10);
+///
+/// let loc = remapper.remap(BytePos(25), &offsets).unwrap();
+/// assert_eq!(loc.file, "main.c");
+/// assert_eq!(loc.line, 11);
+/// assert_eq!(remapper.remap(BytePos(0), &offsets), None);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PositionRemapper {
+    directives: Vec<LineDirective>,
+}
+
+impl PositionRemapper {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Report positions from `at` onward as being in `file`, with the line
+    /// containing `at` numbered `line` (1-based).
+    ///
+    /// A later directive at the same position replaces an earlier one.
+    pub fn add(&mut self, at: BytePos, file: impl Into<String>, line: usize) {
+        self.insert(LineDirective {
+            at,
+            target: Some((file.into(), line)),
+        });
+    }
+
+    /// Stop remapping positions from `at` onward, so they are reported at
+    /// their physical location again.
+    pub fn reset(&mut self, at: BytePos) {
+        self.insert(LineDirective { at, target: None });
+    }
+
+    fn insert(&mut self, directive: LineDirective) {
+        let index = self.directives.partition_point(|d| d.at < directive.at);
+        match self.directives.get_mut(index) {
+            Some(existing) if existing.at == directive.at => *existing = directive,
+            _ => self.directives.insert(index, directive),
+        }
+    }
+
+    /// Returns true if no directives have been registered.
+    pub fn is_empty(&self) -> bool {
+        self.directives.is_empty()
+    }
+
+    /// Looks up the original location of `pos`.
+    ///
+    /// Returns `None` if no directive applies, in which case the physical
+    /// location given by `offsets` should be reported.
+    pub fn remap<'a>(
+        &'a self,
+        pos: BytePos,
+        offsets: &LineOffsets,
+    ) -> Option<RemappedLocation<'a>> {
+        let index = self
+            .directives
+            .partition_point(|d| d.at <= pos)
+            .checked_sub(1)?;
+        let directive = &self.directives[index];
+        let (file, line) = directive.target.as_ref()?;
+
+        Some(RemappedLocation {
+            file,
+            line: line + offsets.line(pos) - offsets.line(directive.at),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_multiple_directives() {
+        let source = "a\nb\nc\nd\ne\n";
+        let offsets = LineOffsets::new(source);
+        let mut remapper = PositionRemapper::new();
+        remapper.add(BytePos(6), "second.txt", 1);
+        remapper.add(BytePos(2), "first.txt", 40);
+        remapper.reset(BytePos(8));
+
+        assert_eq!(remapper.remap(BytePos(0), &offsets), None);
+        assert_eq!(
+            remapper.remap(BytePos(4), &offsets),
+            Some(RemappedLocation {
+                file: "first.txt",
+                line: 41
+            })
+        );
+        assert_eq!(
+            remapper.remap(BytePos(6), &offsets),
+            Some(RemappedLocation {
+                file: "second.txt",
+                line: 1
+            })
+        );
+        assert_eq!(remapper.remap(BytePos(8), &offsets), None);
+    }
+
+    #[test]
+    fn test_replace_directive() {
+        let offsets = LineOffsets::new("abc");
+        let mut remapper = PositionRemapper::new();
+        remapper.add(BytePos(0), "old", 1);
+        remapper.add(BytePos(0), "new", 5);
+        assert_eq!(
+            remapper.remap(BytePos(1), &offsets),
+            Some(RemappedLocation {
+                file: "new",
+                line: 5
+            })
+        );
+    }
+}
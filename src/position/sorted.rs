@@ -0,0 +1,212 @@
+use super::{BytePos, GetSpan, Span};
+use std::fmt;
+
+/// The result of looking up the item at an offset in a sorted slice.
+///
+/// An offset on the boundary between two adjacent items touches both of them,
+/// so the caller decides which one it is interested in.
+#[derive(Debug, PartialEq, Eq)]
+pub enum TokenAtOffset<'a, T> {
+    /// No item touches the offset.
+    None,
+    /// The offset is inside or on the edge of exactly one item.
+    Single(&'a T),
+    /// The offset is between two adjacent items.
+    Between(&'a T, &'a T),
+}
+
+impl<T> Clone for TokenAtOffset<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for TokenAtOffset<'_, T> {}
+
+impl<'a, T> TokenAtOffset<'a, T> {
+    /// Prefer the item ending at the offset.
+    pub fn left_biased(self) -> Option<&'a T> {
+        match self {
+            TokenAtOffset::None => None,
+            TokenAtOffset::Single(item) => Some(item),
+            TokenAtOffset::Between(left, _) => Some(left),
+        }
+    }
+
+    /// Prefer the item starting at the offset.
+    pub fn right_biased(self) -> Option<&'a T> {
+        match self {
+            TokenAtOffset::None => None,
+            TokenAtOffset::Single(item) => Some(item),
+            TokenAtOffset::Between(_, right) => Some(right),
+        }
+    }
+}
+
+/// Error describing why a slice is not sorted by span.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpanOrderError {
+    /// The item at `index` starts before the previous item.
+    Unordered { index: usize },
+    /// The item at `index` starts before the previous item ends.
+    Overlapping { index: usize },
+}
+
+impl fmt::Display for SpanOrderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpanOrderError::Unordered { index } => {
+                write!(f, "item {} starts before the previous item", index)
+            }
+            SpanOrderError::Overlapping { index } => {
+                write!(f, "item {} overlaps the previous item", index)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SpanOrderError {}
+
+/// Queries over slices of spanned items, such as token lists, that are
+/// sorted by position and do not overlap.
+///
+/// All queries use binary search. Their results are unspecified if the slice
+/// is not sorted; use [`SortedSpans::validate_spans`] to check.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::position::*;
+/// let tokens = vec![
+///     WithSpan::new("foo", Span::new(0, 3).unwrap()),
+///     WithSpan::new("(", Span::new(3, 4).unwrap()),
+///     WithSpan::new(")", Span::new(4, 5).unwrap()),
+/// ];
+/// let at = tokens.token_at_offset(BytePos(3));
+/// assert_eq!(at.left_biased().unwrap().value, "foo");
+/// assert_eq!(at.right_biased().unwrap().value, "(");
+/// assert_eq!(tokens.tokens_in_range(Span::new(2, 4).unwrap()).len(), 2);
+/// ```
+pub trait SortedSpans<T: GetSpan> {
+    /// Finds the items touching `pos`.
+    fn token_at_offset(&self, pos: BytePos) -> TokenAtOffset<'_, T>;
+
+    /// Returns the items overlapping `range`.
+    ///
+    /// Items that only touch the edges of `range` are not included. An empty
+    /// range strictly inside an item returns that item.
+    fn tokens_in_range(&self, range: Span) -> &[T];
+
+    /// Checks that the items are sorted and do not overlap.
+    fn validate_spans(&self) -> Result<(), SpanOrderError>;
+}
+
+impl<T: GetSpan> SortedSpans<T> for [T] {
+    fn token_at_offset(&self, pos: BytePos) -> TokenAtOffset<'_, T> {
+        let first = self.partition_point(|item| item.get_span().end < pos);
+        let last = self.partition_point(|item| item.get_span().start <= pos);
+
+        match self.get(first..last) {
+            Some([item]) => TokenAtOffset::Single(item),
+            Some([left, .., right]) => TokenAtOffset::Between(left, right),
+            _ => TokenAtOffset::None,
+        }
+    }
+
+    fn tokens_in_range(&self, range: Span) -> &[T] {
+        let first = self.partition_point(|item| item.get_span().end <= range.start);
+        let last = self.partition_point(|item| item.get_span().start < range.end);
+        &self[first..last.max(first)]
+    }
+
+    fn validate_spans(&self) -> Result<(), SpanOrderError> {
+        for (i, pair) in self.windows(2).enumerate() {
+            let (prev, next) = (pair[0].get_span(), pair[1].get_span());
+            if next.start < prev.start {
+                return Err(SpanOrderError::Unordered { index: i + 1 });
+            }
+            if next.start < prev.end {
+                return Err(SpanOrderError::Overlapping { index: i + 1 });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spans(ranges: &[(usize, usize)]) -> Vec<Span> {
+        ranges
+            .iter()
+            .map(|&(start, end)| Span::new(start, end).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_token_at_offset() {
+        // `ab cd` with a gap at 2..3.
+        let tokens = spans(&[(0, 2), (3, 5), (5, 6)]);
+        assert_eq!(
+            tokens.token_at_offset(BytePos(0)),
+            TokenAtOffset::Single(&tokens[0])
+        );
+        assert_eq!(
+            tokens.token_at_offset(BytePos(1)),
+            TokenAtOffset::Single(&tokens[0])
+        );
+        assert_eq!(
+            tokens.token_at_offset(BytePos(2)),
+            TokenAtOffset::Single(&tokens[0])
+        );
+        assert_eq!(
+            tokens.token_at_offset(BytePos(3)),
+            TokenAtOffset::Single(&tokens[1])
+        );
+        assert_eq!(
+            tokens.token_at_offset(BytePos(5)),
+            TokenAtOffset::Between(&tokens[1], &tokens[2])
+        );
+        assert_eq!(tokens.token_at_offset(BytePos(7)), TokenAtOffset::None);
+        assert_eq!(spans(&[]).token_at_offset(BytePos(0)), TokenAtOffset::None);
+    }
+
+    #[test]
+    fn test_token_at_offset_gap() {
+        let tokens = spans(&[(0, 1), (4, 5)]);
+        assert_eq!(tokens.token_at_offset(BytePos(2)), TokenAtOffset::None);
+    }
+
+    #[test]
+    fn test_tokens_in_range() {
+        let tokens = spans(&[(0, 2), (3, 5), (5, 6), (8, 9)]);
+        assert_eq!(
+            tokens.tokens_in_range(Span::new(1, 4).unwrap()),
+            &tokens[0..2]
+        );
+        assert!(tokens.tokens_in_range(Span::new(2, 3).unwrap()).is_empty());
+        assert_eq!(
+            tokens.tokens_in_range(Span::new(5, 9).unwrap()),
+            &tokens[2..4]
+        );
+        assert_eq!(tokens.tokens_in_range(Span::point(4)), &tokens[1..2]);
+        assert!(tokens.tokens_in_range(Span::point(5)).is_empty());
+        assert_eq!(
+            tokens.tokens_in_range(Span::new(0, 100).unwrap()),
+            &tokens[..]
+        );
+    }
+
+    #[test]
+    fn test_validate_spans() {
+        assert_eq!(spans(&[(0, 2), (2, 3), (5, 5)]).validate_spans(), Ok(()));
+        assert_eq!(
+            spans(&[(0, 2), (1, 3)]).validate_spans(),
+            Err(SpanOrderError::Overlapping { index: 1 })
+        );
+        assert_eq!(
+            spans(&[(0, 2), (4, 5), (2, 3)]).validate_spans(),
+            Err(SpanOrderError::Unordered { index: 2 })
+        );
+    }
+}
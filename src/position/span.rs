@@ -1,6 +1,9 @@
 use super::BytePos;
+use crate::source::SourceText;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
+use std::hash::{Hash, Hasher};
+use std::ops::{Deref, DerefMut};
 
 /// A trait for getting the span of a value.
 ///
@@ -26,6 +29,14 @@ pub trait SetSpan {
 /// `start: 5, end: 10` covers the bytes/characters at positions 5, 6, 7, 8, and 9.
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Hash)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    rkyv(
+        derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash),
+        compare(PartialEq)
+    )
+)]
 pub struct Span {
     pub start: BytePos,
     pub end: BytePos,
@@ -47,8 +58,8 @@ impl Span {
             None
         } else {
             Some(Span {
-                start: BytePos(start),
-                end: BytePos(end),
+                start: BytePos::from_usize(start),
+                end: BytePos::from_usize(end),
             })
         }
     }
@@ -65,16 +76,16 @@ impl Span {
     /// ```
     pub fn point(pos: usize) -> Self {
         Span {
-            start: BytePos(pos),
-            end: BytePos(pos),
+            start: BytePos::from_usize(pos),
+            end: BytePos::from_usize(pos),
         }
     }
 
     /// This function does not check if the start is less than the end.
     pub fn new_unchecked(start: usize, end: usize) -> Self {
         Span {
-            start: BytePos(start),
-            end: BytePos(end),
+            start: BytePos::from_usize(start),
+            end: BytePos::from_usize(end),
         }
     }
 
@@ -100,7 +111,7 @@ impl Span {
     pub fn maybe_union(&self, other: &Option<Self>) -> Self {
         match other {
             Some(other) => self.union(other),
-            None => self.clone(),
+            None => *self,
         }
     }
 
@@ -108,11 +119,11 @@ impl Span {
     ///
     /// Example: [15, 10) ∪ 9 = [9, 20)
     pub fn extend(&self, pos: &BytePos) -> Self {
-        let mut span = self.clone();
-        if span.start.0 > pos.0 {
+        let mut span = *self;
+        if span.start > *pos {
             span.start = *pos;
         }
-        if span.end.0 < pos.0 {
+        if span.end < *pos {
             span.end = *pos;
         }
         span
@@ -120,27 +131,138 @@ impl Span {
 
     /// Get the start position of the span.
     pub fn start(&self) -> usize {
-        self.start.0
+        self.start.to_usize()
     }
 
     /// Get the end position of the span.
     pub fn end(&self) -> usize {
-        self.end.0
+        self.end.to_usize()
     }
 
     /// Get the length of the span.
     pub fn len(&self) -> usize {
-        self.end.0 - self.start.0
+        self.end.distance(self.start)
+    }
+
+    /// Check if the span is empty, i.e. covers no positions.
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
     }
 
     /// Check if the span contains a given position.
     pub fn contains(&self, offset: usize) -> bool {
-        offset >= self.start.0 && offset < self.end.0
+        offset >= self.start() && offset < self.end()
     }
 
     /// Check if the span intersects with another span.
     pub fn intersects(&self, other: &Self) -> bool {
-        self.start.0 <= other.end.0 && self.end.0 >= other.start.0
+        self.start <= other.end && self.end >= other.start
+    }
+
+    /// Express the span relative to `base`, i.e. in the local coordinates of
+    /// a region of the source starting at `base`.
+    ///
+    /// This is the inverse of [`Span::offset_by`].
+    ///
+    /// # Panics
+    /// Panics if the span starts before `base`.
+    ///
+    /// # Examples
+    /// ```
+    /// use grammarsmith::position::*;
+    /// let span = Span::new(12, 15).unwrap();
+    /// assert_eq!(span.relative_to(BytePos(10)), Span::new(2, 5).unwrap());
+    /// ```
+    pub fn relative_to(&self, base: BytePos) -> Self {
+        assert!(base <= self.start, "span starts before the base offset");
+        Span {
+            start: self.start - base,
+            end: self.end - base,
+        }
+    }
+
+    /// Map a span in the local coordinates of a region starting at `base`
+    /// back into the coordinates of the enclosing source.
+    ///
+    /// Sub-parsers working on an extracted region (string interpolation,
+    /// embedded languages, macro arguments) produce local spans; this moves
+    /// them back into the enclosing file.
+    ///
+    /// # Examples
+    /// ```
+    /// use grammarsmith::position::*;
+    /// let local = Span::new(2, 5).unwrap();
+    /// assert_eq!(local.offset_by(BytePos(10)), Span::new(12, 15).unwrap());
+    /// ```
+    pub fn offset_by(&self, base: BytePos) -> Self {
+        Span {
+            start: self.start + base,
+            end: self.end + base,
+        }
+    }
+
+    /// Shrink the span to exclude leading and trailing whitespace in `source`.
+    ///
+    /// Useful for tidy diagnostic underlines when a node's span includes
+    /// surrounding trivia. A span that covers only whitespace collapses to a
+    /// zero-width span at its start, as with [`trim_end`](Span::trim_end).
+    ///
+    /// # Panics
+    /// Panics if the span is out of bounds of `source` or not on character
+    /// boundaries.
+    ///
+    /// # Examples
+    /// ```
+    /// use grammarsmith::position::*;
+    /// let source = "let x =  42 ;";
+    /// let span = Span::new(7, 12).unwrap();
+    /// assert_eq!(span.trim(source), Span::new(9, 11).unwrap());
+    /// ```
+    pub fn trim<S: SourceText + ?Sized>(&self, source: &S) -> Self {
+        self.trim_end(source).trim_start(source)
+    }
+
+    /// Shrink the span to exclude leading whitespace in `source`.
+    ///
+    /// A span that covers only whitespace collapses to a zero-width span at
+    /// its end.
+    ///
+    /// # Panics
+    /// Panics as [`trim`](Span::trim) does.
+    pub fn trim_start<S: SourceText + ?Sized>(&self, source: &S) -> Self {
+        let text = source.slice(self.start()..self.end());
+        let trimmed = text.len() - text.trim_start().len();
+        Span {
+            start: self.start + trimmed,
+            end: self.end,
+        }
+    }
+
+    /// Shrink the span to exclude trailing whitespace in `source`.
+    ///
+    /// A span that covers only whitespace collapses to a zero-width span at
+    /// its start.
+    ///
+    /// # Panics
+    /// Panics as [`trim`](Span::trim) does.
+    pub fn trim_end<S: SourceText + ?Sized>(&self, source: &S) -> Self {
+        let text = source.slice(self.start()..self.end());
+        Span {
+            start: self.start,
+            end: self.start + text.trim_end().len(),
+        }
+    }
+}
+
+impl GetSpan for Span {
+    fn get_span(&self) -> Span {
+        *self
+    }
+}
+
+impl<T: GetSpan + ?Sized> GetSpan for &T {
+    fn get_span(&self) -> Span {
+        (**self).get_span()
     }
 }
 
@@ -176,7 +298,20 @@ impl<T> From<&WithSpan<T>> for Span {
 /// let span = Span::new(0, 3).unwrap();
 /// let spanned_token = WithSpan::new(token, span);
 /// ```
+///
+/// # Serialization
+/// With the `serde` feature, a value with a span is an object with the
+/// `value` and its `span`, whose `start` and `end` are byte offsets:
+///
+/// ```json
+/// { "value": ..., "span": { "start": 0, "end": 3 } }
+/// ```
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
 pub struct WithSpan<T> {
     pub value: T,
     pub span: Span,
@@ -213,17 +348,233 @@ impl<T> WithSpan<T> {
         Self {
             value,
             span: Span {
-                start: BytePos(start),
-                end: BytePos(end),
+                start: BytePos::from_usize(start),
+                end: BytePos::from_usize(end),
             },
         }
     }
 
-    //TODO Move to AsRef trait impl?
-    pub const fn as_ref(&self) -> WithSpan<&T> {
+    /// Compare the values of two spanned items, ignoring their spans.
+    ///
+    /// # Examples
+    /// ```
+    /// use grammarsmith::position::*;
+    /// let a = WithSpan::new("x", Span::new(0, 1).unwrap());
+    /// let b = WithSpan::new("x", Span::new(7, 8).unwrap());
+    /// assert!(a != b);
+    /// assert!(a.eq_ignoring_span(&b));
+    /// ```
+    pub fn eq_ignoring_span(&self, other: &Self) -> bool
+    where
+        T: PartialEq,
+    {
+        self.value == other.value
+    }
+
+    /// Wrap the item so that comparisons and hashing ignore its span.
+    pub fn erase_span(self) -> SpanErased<T> {
+        SpanErased(self)
+    }
+
+    /// Borrow the value while keeping its span.
+    ///
+    /// Use [`AsRef`] or [`Deref`] to get a plain reference to the value.
+    pub const fn as_spanned_ref(&self) -> WithSpan<&T> {
         WithSpan {
             span: self.span,
             value: &self.value,
         }
     }
 }
+
+/// A spanned item that compares, orders and hashes by value only.
+///
+/// Useful for snapshot tests and deduplication where positions differ but the
+/// values must be identical. Only the outer span is ignored; spans nested
+/// inside the value are still compared by the value's own implementations.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::position::*;
+/// use std::collections::HashSet;
+///
+/// let mut seen = HashSet::new();
+/// assert!(seen.insert(WithSpan::new("x", Span::new(0, 1).unwrap()).erase_span()));
+/// assert!(!seen.insert(WithSpan::new("x", Span::new(4, 5).unwrap()).erase_span()));
+/// ```
+#[derive(Debug, Clone)]
+pub struct SpanErased<T>(pub WithSpan<T>);
+
+impl<T> SpanErased<T> {
+    /// Unwrap the spanned item.
+    pub fn into_inner(self) -> WithSpan<T> {
+        self.0
+    }
+}
+
+impl<T: PartialEq> PartialEq for SpanErased<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.value == other.0.value
+    }
+}
+
+impl<T: Eq> Eq for SpanErased<T> {}
+
+impl<T: PartialOrd> PartialOrd for SpanErased<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        self.0.value.partial_cmp(&other.0.value)
+    }
+}
+
+impl<T: Ord> Ord for SpanErased<T> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.value.cmp(&other.0.value)
+    }
+}
+
+impl<T: Hash> Hash for SpanErased<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.value.hash(state);
+    }
+}
+
+impl<T> GetSpan for SpanErased<T> {
+    fn get_span(&self) -> Span {
+        self.0.span
+    }
+}
+
+impl<T> Deref for SpanErased<T> {
+    type Target = WithSpan<T>;
+
+    fn deref(&self) -> &WithSpan<T> {
+        &self.0
+    }
+}
+
+impl<T> From<WithSpan<T>> for SpanErased<T> {
+    fn from(with_span: WithSpan<T>) -> Self {
+        SpanErased(with_span)
+    }
+}
+
+/// Gives direct access to the wrapped value, so methods can be called on it
+/// without going through `.value`.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::position::*;
+/// let ident = WithSpan::new("foo".to_string(), Span::new(0, 3).unwrap());
+/// assert_eq!(ident.len(), 3);
+/// ```
+impl<T> Deref for WithSpan<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T> DerefMut for WithSpan<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+impl<T> AsRef<T> for WithSpan<T> {
+    fn as_ref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T> AsMut<T> for WithSpan<T> {
+    fn as_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relative_round_trip() {
+        let span = Span::new(7, 11).unwrap();
+        let base = BytePos(4);
+        assert_eq!(span.relative_to(base), Span::new(3, 7).unwrap());
+        assert_eq!(span.relative_to(base).offset_by(base), span);
+        assert_eq!(span.relative_to(BytePos(7)), Span::new(0, 4).unwrap());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_relative_to_after_start() {
+        Span::new(3, 5).unwrap().relative_to(BytePos(4));
+    }
+
+    #[test]
+    fn test_with_span_deref() {
+        let mut ident = WithSpan::new(String::from("foo"), Span::new(0, 3).unwrap());
+        assert_eq!(ident.len(), 3);
+        ident.push('!');
+        assert_eq!(ident.as_ref(), "foo!");
+        assert_eq!(ident.as_spanned_ref().value, "foo!");
+        assert_eq!(ident.as_spanned_ref().span, Span::new(0, 3).unwrap());
+    }
+
+    #[cfg(feature = "rkyv")]
+    #[test]
+    fn test_rkyv_round_trip() {
+        let tokens = vec![
+            WithSpan::new(1u32, Span::new(0, 1).unwrap()),
+            WithSpan::new(2u32, Span::new(2, 5).unwrap()),
+        ];
+        let bytes = rkyv::to_bytes::<rkyv::rancor::Error>(&tokens).unwrap();
+        let archived =
+            rkyv::access::<rkyv::Archived<Vec<WithSpan<u32>>>, rkyv::rancor::Error>(&bytes)
+                .unwrap();
+        assert_eq!(archived[1].span, Span::new(2, 5).unwrap());
+        assert_eq!(archived[1].value, 2);
+
+        let deserialized =
+            rkyv::deserialize::<Vec<WithSpan<u32>>, rkyv::rancor::Error>(archived).unwrap();
+        assert_eq!(deserialized, tokens);
+    }
+
+    #[test]
+    fn test_span_erased() {
+        let a = WithSpan::new(vec![1, 2], Span::new(0, 2).unwrap());
+        let b = WithSpan::new(vec![1, 2], Span::new(5, 9).unwrap());
+        let c = WithSpan::new(vec![1, 3], Span::new(0, 2).unwrap());
+        assert!(a.eq_ignoring_span(&b));
+        assert!(!a.eq_ignoring_span(&c));
+        assert_eq!(a.clone().erase_span(), b.clone().erase_span());
+        assert!(a.clone().erase_span() < c.clone().erase_span());
+        assert_eq!(b.erase_span().get_span(), Span::new(5, 9).unwrap());
+    }
+
+    #[test]
+    fn test_trim() {
+        let source = "  foo bar \n";
+        let span = Span::new(0, source.len()).unwrap();
+        assert_eq!(span.trim(source), Span::new(2, 9).unwrap());
+        assert_eq!(span.trim_start(source), Span::new(2, 11).unwrap());
+        assert_eq!(span.trim_end(source), Span::new(0, 9).unwrap());
+    }
+
+    #[test]
+    fn test_trim_only_whitespace() {
+        let source = "a   b";
+        let span = Span::new(1, 4).unwrap();
+        assert_eq!(span.trim(source), Span::point(1));
+        assert_eq!(span.trim_start(source), Span::point(4));
+        assert_eq!(span.trim_end(source), Span::point(1));
+    }
+
+    #[test]
+    fn test_trim_unicode_whitespace() {
+        let source = "\u{3000}é\u{a0}";
+        let span = Span::new(0, source.len()).unwrap();
+        assert_eq!(span.trim(source), Span::new(3, 5).unwrap());
+    }
+}
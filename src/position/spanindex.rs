@@ -0,0 +1,260 @@
+use super::{BytePos, GetSpan, Span};
+
+/// An index over spanned items, possibly nested like AST nodes, for fast
+/// offset-to-item queries.
+///
+/// The items are stored sorted by span in an implicit interval tree, so
+/// queries take `O(log n + k)` time for `k` results instead of walking every
+/// item.
+///
+/// Containment follows [`Span::contains`]: an item contains `pos` if
+/// `start <= pos < end`.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::position::*;
+/// // fn f() { g(x) }
+/// let index: SpanIndex<_> = vec![
+///     WithSpan::new("fn", Span::new(0, 15).unwrap()),
+///     WithSpan::new("body", Span::new(7, 15).unwrap()),
+///     WithSpan::new("call", Span::new(9, 13).unwrap()),
+///     WithSpan::new("arg", Span::new(11, 12).unwrap()),
+/// ]
+/// .into_iter()
+/// .collect();
+///
+/// assert_eq!(index.smallest_containing(BytePos(11)).unwrap().value, "arg");
+/// assert_eq!(index.all_containing(BytePos(10)).count(), 3);
+/// ```
+#[derive(Debug, Clone)]
+pub struct SpanIndex<T> {
+    items: Vec<T>,
+    /// The largest end of the subtree rooted at each index.
+    max_end: Vec<BytePos>,
+}
+
+impl<T: GetSpan> SpanIndex<T> {
+    /// Builds an index from the given items.
+    pub fn new(items: impl IntoIterator<Item = T>) -> Self {
+        let mut items: Vec<T> = items.into_iter().collect();
+        items.sort_by_key(|item| {
+            let span = item.get_span();
+            (span.start, std::cmp::Reverse(span.end))
+        });
+
+        let mut max_end = vec![BytePos::default(); items.len()];
+        build(&items, &mut max_end, 0, items.len());
+        SpanIndex { items, max_end }
+    }
+
+    /// Returns the number of items in the index.
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Returns true if the index contains no items.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Iterates over the items, sorted by start and then by descending end,
+    /// so parents come before their children.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.items.iter()
+    }
+
+    /// Returns every item whose span contains `pos`, outermost first.
+    pub fn all_containing(&self, pos: BytePos) -> impl Iterator<Item = &T> {
+        self.query(
+            move |span| span.start <= pos,
+            move |end| end > pos,
+            move |span| span.contains(pos.to_usize()),
+        )
+        .into_iter()
+    }
+
+    /// Returns the innermost item whose span contains `pos`.
+    ///
+    /// If several items share the smallest span, the one added last wins.
+    pub fn smallest_containing(&self, pos: BytePos) -> Option<&T> {
+        self.query(
+            move |span| span.start <= pos,
+            move |end| end > pos,
+            move |span| span.contains(pos.to_usize()),
+        )
+        .into_iter()
+        .rev()
+        .min_by_key(|item| item.get_span().len())
+    }
+
+    /// Returns every item whose span overlaps `range`, sorted by start.
+    ///
+    /// Items that only touch the edges of `range` are not included. An empty
+    /// range returns the items containing it.
+    pub fn overlapping(&self, range: Span) -> impl Iterator<Item = &T> {
+        let starts_early = move |span: Span| match range.is_empty() {
+            true => span.start <= range.start,
+            false => span.start < range.end,
+        };
+        self.query(
+            starts_early,
+            move |end| end > range.start,
+            move |span| starts_early(span) && span.end > range.start,
+        )
+        .into_iter()
+    }
+
+    /// Returns the innermost item whose span covers all of `range`.
+    ///
+    /// If several items share the smallest span, the one added last wins.
+    pub fn smallest_covering(&self, range: Span) -> Option<&T> {
+        self.query(
+            move |span| span.start <= range.start,
+            move |end| end >= range.end,
+            move |span| span.start <= range.start && range.end <= span.end,
+        )
+        .into_iter()
+        .rev()
+        .min_by_key(|item| item.get_span().len())
+    }
+
+    /// Collects items matching `matches`. `starts_early` must hold for every
+    /// match and be monotone over the sorted items, and `ends_late` must hold
+    /// for the end of every match and be monotone over ends; subtrees whose
+    /// largest end fails `ends_late` are skipped.
+    fn query(
+        &self,
+        starts_early: impl Fn(Span) -> bool,
+        ends_late: impl Fn(BytePos) -> bool,
+        matches: impl Fn(Span) -> bool,
+    ) -> Vec<&T> {
+        let mut found = Vec::new();
+        let mut stack = vec![(0, self.items.len())];
+        while let Some((lo, hi)) = stack.pop() {
+            if lo >= hi {
+                continue;
+            }
+            let mid = lo + (hi - lo) / 2;
+            if !ends_late(self.max_end[mid]) {
+                continue;
+            }
+            let span = self.items[mid].get_span();
+            if starts_early(span) {
+                stack.push((mid + 1, hi));
+                if matches(span) {
+                    found.push(mid);
+                }
+            }
+            stack.push((lo, mid));
+        }
+        found.sort_unstable();
+        found.into_iter().map(|i| &self.items[i]).collect()
+    }
+}
+
+impl<T: GetSpan> FromIterator<T> for SpanIndex<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        SpanIndex::new(iter)
+    }
+}
+
+fn build<T: GetSpan>(items: &[T], max_end: &mut [BytePos], lo: usize, hi: usize) -> BytePos {
+    if lo >= hi {
+        return BytePos::default();
+    }
+    let mid = lo + (hi - lo) / 2;
+    let left = build(items, max_end, lo, mid);
+    let right = build(items, max_end, mid + 1, hi);
+    max_end[mid] = items[mid].get_span().end.max(left).max(right);
+    max_end[mid]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::position::WithSpan;
+    use proptest::prelude::*;
+
+    fn span(start: usize, end: usize) -> Span {
+        Span::new(start, end).unwrap()
+    }
+
+    #[test]
+    fn test_nested() {
+        let index = SpanIndex::new(vec![span(0, 10), span(2, 8), span(3, 5), span(6, 7)]);
+        let containing: Vec<_> = index.all_containing(BytePos(4)).collect();
+        assert_eq!(containing, vec![&span(0, 10), &span(2, 8), &span(3, 5)]);
+        assert_eq!(index.smallest_containing(BytePos(6)), Some(&span(6, 7)));
+        assert_eq!(index.smallest_containing(BytePos(9)), Some(&span(0, 10)));
+        assert_eq!(index.smallest_containing(BytePos(10)), None);
+    }
+
+    #[test]
+    fn test_identical_spans_prefer_last() {
+        let index = SpanIndex::new(vec![
+            WithSpan::new("outer", span(0, 3)),
+            WithSpan::new("inner", span(0, 3)),
+        ]);
+        assert_eq!(
+            index.smallest_containing(BytePos(1)).unwrap().value,
+            "inner"
+        );
+        assert_eq!(index.smallest_covering(span(0, 3)).unwrap().value, "inner");
+    }
+
+    #[test]
+    fn test_overlapping_and_covering() {
+        let index = SpanIndex::new(vec![span(0, 10), span(2, 8), span(3, 5), span(6, 7)]);
+        let overlapping: Vec<_> = index.overlapping(span(5, 7)).collect();
+        assert_eq!(overlapping, vec![&span(0, 10), &span(2, 8), &span(6, 7)]);
+        assert_eq!(index.smallest_covering(span(3, 7)), Some(&span(2, 8)));
+        assert_eq!(index.smallest_covering(span(3, 3)), Some(&span(3, 5)));
+        assert_eq!(index.smallest_covering(span(5, 12)), None);
+    }
+
+    #[test]
+    fn test_empty_range_at_end() {
+        // The result must not depend on the shape of the tree.
+        let index = SpanIndex::new(vec![span(0, 3)]);
+        assert_eq!(index.smallest_covering(Span::point(3)), Some(&span(0, 3)));
+        let index = SpanIndex::new(vec![span(0, 10), span(0, 3)]);
+        assert_eq!(index.smallest_covering(Span::point(3)), Some(&span(0, 3)));
+        assert_eq!(index.overlapping(Span::point(3)).count(), 1);
+
+        let end = BytePos::MAX;
+        let index = SpanIndex::new(vec![Span { start: end, end }]);
+        assert_eq!(index.all_containing(end).count(), 0);
+        assert_eq!(index.overlapping(Span { start: end, end }).count(), 0);
+        assert!(index.smallest_covering(Span { start: end, end }).is_some());
+    }
+
+    proptest! {
+        #[test]
+        fn test_matches_linear_scan(
+            ranges in proptest::collection::vec((0usize..50, 0usize..20), 0..40),
+            pos in 0usize..70,
+            len in 0usize..10,
+        ) {
+            let spans: Vec<Span> = ranges.iter().map(|&(s, l)| span(s, s + l)).collect();
+            let index = SpanIndex::new(spans.clone());
+            let pos = BytePos::from_usize(pos);
+            let range = Span { start: pos, end: pos + len };
+
+            let expected = spans.iter().filter(|s| s.contains(pos.to_usize())).count();
+            prop_assert_eq!(index.all_containing(pos).count(), expected);
+
+            let expected = spans
+                .iter()
+                .filter(|s| s.start < range.end.max(range.start + 1) && s.end > range.start)
+                .count();
+            prop_assert_eq!(index.overlapping(range).count(), expected);
+
+            let expected = spans
+                .iter()
+                .filter(|s| s.start <= range.start && range.end <= s.end)
+                .map(Span::len)
+                .min();
+            prop_assert_eq!(index.smallest_covering(range).map(Span::len), expected);
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use super::{BytePos, Span};
+
+/// Identifies a chunk inside a [`VirtualSource`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ChunkId(pub usize);
+
+#[derive(Debug, Clone)]
+struct Chunk {
+    name: String,
+    start: BytePos,
+}
+
+/// A source text assembled from several input chunks, such as the lines of a
+/// REPL session or included files.
+///
+/// All chunks share one logical position space, so the concatenated text can
+/// be scanned and parsed as a whole while any position can still be mapped back
+/// to the chunk it came from.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::position::*;
+/// let mut source = VirtualSource::new();
+/// let first = source.push("<repl:1>", "let x = 1;\n");
+/// let second = source.push("<repl:2>", "x + y\n");
+/// assert_eq!(source.text(), "let x = 1;\nx + y\n");
+/// assert_eq!(source.locate(BytePos(15)), Some((second, BytePos(4))));
+/// assert_eq!(source.chunk_name(first), "<repl:1>");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct VirtualSource {
+    text: String,
+    chunks: Vec<Chunk>,
+}
+
+impl VirtualSource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a chunk of text and returns its id.
+    ///
+    /// # Panics
+    /// Panics if the combined text becomes too large to be addressed by
+    /// [`BytePos`].
+    pub fn push(&mut self, name: impl Into<String>, text: &str) -> ChunkId {
+        let start = BytePos::from_usize(self.text.len());
+        assert!(
+            BytePos::try_from_usize(self.text.len() + text.len()).is_some(),
+            "virtual source exceeds the maximum addressable position"
+        );
+
+        self.text.push_str(text);
+        self.chunks.push(Chunk {
+            name: name.into(),
+            start,
+        });
+        ChunkId(self.chunks.len() - 1)
+    }
+
+    /// Returns the concatenated text of all chunks.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns the number of chunks.
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Returns the name a chunk was pushed with.
+    pub fn chunk_name(&self, id: ChunkId) -> &str {
+        &self.chunks[id.0].name
+    }
+
+    /// Returns the span a chunk occupies in the concatenated text.
+    pub fn chunk_span(&self, id: ChunkId) -> Span {
+        let start = self.chunks[id.0].start;
+        let end = self
+            .chunks
+            .get(id.0 + 1)
+            .map(|chunk| chunk.start)
+            .unwrap_or(BytePos::from_usize(self.text.len()));
+        Span { start, end }
+    }
+
+    /// Returns the text of a single chunk.
+    pub fn chunk_text(&self, id: ChunkId) -> &str {
+        let span = self.chunk_span(id);
+        &self.text[span.start()..span.end()]
+    }
+
+    /// Maps a position in the concatenated text to the chunk containing it
+    /// and the offset within that chunk.
+    ///
+    /// A position on the boundary between two chunks belongs to the later
+    /// one, except for the end of the text which belongs to the last chunk.
+    /// Returns `None` if there are no chunks or `pos` is past the end.
+    pub fn locate(&self, pos: BytePos) -> Option<(ChunkId, BytePos)> {
+        if pos.to_usize() > self.text.len() {
+            return None;
+        }
+
+        let index = self
+            .chunks
+            .partition_point(|chunk| chunk.start <= pos)
+            .checked_sub(1)?;
+        Some((ChunkId(index), pos - self.chunks[index].start))
+    }
+
+    /// Maps a span in the concatenated text to the chunk containing its start
+    /// and the span in that chunk's local coordinates.
+    ///
+    /// Returns `None` if the span does not fit inside a single chunk.
+    pub fn locate_span(&self, span: Span) -> Option<(ChunkId, Span)> {
+        let (id, _) = self.locate(span.start)?;
+        let chunk = self.chunk_span(id);
+        if span.end > chunk.end {
+            return None;
+        }
+        Some((id, span.relative_to(chunk.start)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty() {
+        let source = VirtualSource::new();
+        assert_eq!(source.locate(BytePos(0)), None);
+        assert_eq!(source.chunk_count(), 0);
+    }
+
+    #[test]
+    fn test_locate_boundaries() {
+        let mut source = VirtualSource::new();
+        let a = source.push("a", "abc");
+        let b = source.push("b", "de");
+        assert_eq!(source.locate(BytePos(0)), Some((a, BytePos(0))));
+        assert_eq!(source.locate(BytePos(2)), Some((a, BytePos(2))));
+        assert_eq!(source.locate(BytePos(3)), Some((b, BytePos(0))));
+        assert_eq!(source.locate(BytePos(5)), Some((b, BytePos(2))));
+        assert_eq!(source.locate(BytePos(6)), None);
+        assert_eq!(source.chunk_text(a), "abc");
+        assert_eq!(source.chunk_text(b), "de");
+    }
+
+    #[test]
+    fn test_locate_span() {
+        let mut source = VirtualSource::new();
+        source.push("a", "abc");
+        let b = source.push("b", "defg");
+        assert_eq!(
+            source.locate_span(Span::new(4, 6).unwrap()),
+            Some((b, Span::new(1, 3).unwrap()))
+        );
+        assert_eq!(source.locate_span(Span::new(2, 5).unwrap()), None);
+    }
+}
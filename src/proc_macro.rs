@@ -0,0 +1,212 @@
+//! Interop with `proc_macro2` for procedural macros that parse embedded DSLs.
+//!
+//! A proc-macro typically receives the DSL as a string literal. [`LiteralSource`]
+//! decodes the literal and maps grammarsmith [`Span`]s in the decoded text back
+//! to `proc_macro2::Span`s inside the literal token, accounting for the quotes
+//! and any escape sequences, so compile errors point at the right characters.
+//!
+//! # Examples
+//! ```
+//! use grammarsmith::proc_macro::LiteralSource;
+//! use grammarsmith::position::Span;
+//!
+//! let literal: proc_macro2::Literal = "\"1 +\\t x\"".parse().unwrap();
+//! let source = LiteralSource::new(&literal).unwrap();
+//! assert_eq!(source.text(), "1 +\t x");
+//! // `x` is at offset 5 in the decoded text but offset 7 in the literal token.
+//! assert_eq!(source.token_range(Span::new(5, 6).unwrap()), 7..8);
+//! ```
+
+use crate::position::*;
+use proc_macro2::{Delimiter, Group, Ident, Literal, Punct, Spacing, TokenStream, TokenTree};
+use std::ops::Range;
+
+/// The decoded contents of a string literal token together with a mapping
+/// from decoded byte offsets back into the literal's source text.
+#[derive(Debug, Clone)]
+pub struct LiteralSource {
+    literal: Literal,
+    text: String,
+    /// `offsets[i]` is the offset in the literal token of decoded byte `i`.
+    offsets: Vec<usize>,
+}
+
+impl LiteralSource {
+    /// Decodes a string literal (`"..."` or `r#"..."#`).
+    ///
+    /// Returns `None` if the literal is not a string literal.
+    pub fn new(literal: &Literal) -> Option<Self> {
+        let token = literal.to_string();
+        let (text, offsets) = decode(&token)?;
+        Some(LiteralSource {
+            literal: literal.clone(),
+            text,
+            offsets,
+        })
+    }
+
+    /// Returns the decoded text of the literal.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns the literal token this source was decoded from.
+    pub fn literal(&self) -> &Literal {
+        &self.literal
+    }
+
+    /// Maps a span in the decoded text to a byte range in the literal token.
+    ///
+    /// # Panics
+    /// Panics if the span is out of bounds for the decoded text.
+    pub fn token_range(&self, span: Span) -> Range<usize> {
+        self.offsets[span.start()]..self.offsets[span.end()]
+    }
+
+    /// Maps a span in the decoded text to a `proc_macro2::Span`.
+    ///
+    /// Sub-spans of literals are only supported by the compiler on some
+    /// toolchains; otherwise this falls back to the span of the whole literal.
+    pub fn proc_macro_span(&self, span: Span) -> proc_macro2::Span {
+        self.literal
+            .subspan(self.token_range(span))
+            .unwrap_or_else(|| self.literal.span())
+    }
+
+    /// Builds a `compile_error!("message")` invocation pointing at `span`.
+    pub fn compile_error(&self, span: Span, message: &str) -> TokenStream {
+        let span = self.proc_macro_span(span);
+        let mut message = Literal::string(message);
+        message.set_span(span);
+        let mut bang = Punct::new('!', Spacing::Alone);
+        bang.set_span(span);
+        let mut args = Group::new(Delimiter::Parenthesis, TokenTree::from(message).into());
+        args.set_span(span);
+
+        [
+            TokenTree::from(Ident::new("compile_error", span)),
+            TokenTree::from(bang),
+            TokenTree::from(args),
+        ]
+        .into_iter()
+        .collect()
+    }
+}
+
+/// Decodes a string literal token, returning the text and the offset table.
+fn decode(token: &str) -> Option<(String, Vec<usize>)> {
+    let mut text = String::new();
+    let mut offsets = Vec::new();
+
+    if let Some(raw) = token.strip_prefix('r') {
+        let hashes = raw.len() - raw.trim_start_matches('#').len();
+        let open = 1 + hashes + 1;
+        let close = token.len().checked_sub(1 + hashes)?;
+        if close < open || !token[open - 1..].starts_with('"') {
+            return None;
+        }
+        text.push_str(&token[open..close]);
+        offsets.extend(open..=close);
+        return Some((text, offsets));
+    }
+
+    let inner = token.strip_prefix('"')?.strip_suffix('"')?;
+    let mut chars = inner.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let start = i + 1;
+        let decoded = if c == '\\' {
+            let (_, escape) = chars.next()?;
+            match escape {
+                'n' => '\n',
+                'r' => '\r',
+                't' => '\t',
+                '\\' => '\\',
+                '0' => '\0',
+                '\'' => '\'',
+                '"' => '"',
+                'x' => {
+                    let hi = chars.next()?.1.to_digit(16)?;
+                    let lo = chars.next()?.1.to_digit(16)?;
+                    char::from_u32(hi * 16 + lo)?
+                }
+                'u' => {
+                    chars.next().filter(|(_, c)| *c == '{')?;
+                    let mut value = 0;
+                    loop {
+                        match chars.next()?.1 {
+                            '}' => break,
+                            '_' => {}
+                            c => value = value * 16 + c.to_digit(16)?,
+                        }
+                    }
+                    char::from_u32(value)?
+                }
+                '\n' => {
+                    while chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+                    continue;
+                }
+                _ => return None,
+            }
+        } else {
+            c
+        };
+
+        offsets.extend(std::iter::repeat_n(start, decoded.len_utf8()));
+        text.push(decoded);
+    }
+    offsets.push(token.len() - 1);
+
+    Some((text, offsets))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(token: &str) -> LiteralSource {
+        LiteralSource::new(&token.parse().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_plain_literal() {
+        let source = source("\"abc\"");
+        assert_eq!(source.text(), "abc");
+        assert_eq!(source.token_range(Span::new(0, 3).unwrap()), 1..4);
+        assert_eq!(source.token_range(Span::point(3)), 4..4);
+    }
+
+    #[test]
+    fn test_escapes() {
+        let source = source(
+            r#""a\"\u{e9}\x41\
+            b""#,
+        );
+        assert_eq!(source.text(), "a\"éAb");
+        // `é` is two bytes decoded but `\u{e9}` in the token.
+        assert_eq!(source.token_range(Span::new(2, 4).unwrap()), 4..10);
+        // The line continuation belongs to the preceding character.
+        assert_eq!(source.token_range(Span::new(4, 5).unwrap()), 10..28);
+        let b = source.text().find('b').unwrap();
+        assert_eq!(source.token_range(Span::new(b, b + 1).unwrap()), 28..29);
+    }
+
+    #[test]
+    fn test_raw_literal() {
+        let source = source("r#\"a\"b\"#");
+        assert_eq!(source.text(), "a\"b");
+        assert_eq!(source.token_range(Span::new(0, 3).unwrap()), 3..6);
+    }
+
+    #[test]
+    fn test_not_a_string() {
+        assert!(LiteralSource::new(&Literal::u8_suffixed(1)).is_none());
+        assert!(LiteralSource::new(&"b\"abc\"".parse().unwrap()).is_none());
+    }
+
+    #[test]
+    fn test_compile_error() {
+        let source = source("\"x\"");
+        let tokens = source.compile_error(Span::new(0, 1).unwrap(), "bad");
+        assert_eq!(tokens.to_string(), "compile_error ! (\"bad\")");
+    }
+}
@@ -0,0 +1,123 @@
+//! Plain-text rendering of source snippets.
+//!
+//! These helpers produce decent error output without colors or a full
+//! diagnostics framework.
+//!
+//! # Examples
+//! ```
+//! use grammarsmith::position::*;
+//! use grammarsmith::render::render_span;
+//!
+//! let source = "let x = 1;\nlet y = x +;\n";
+//! let offsets = LineOffsets::new(source);
+//! let rendered = render_span(source, Span::new(19, 22).unwrap(), &offsets);
+//! assert_eq!(
+//!     rendered,
+//!     "  |\n\
+//!      2 | let y = x +;\n\
+//!      \x20 |         ^^^\n"
+//! );
+//! ```
+
+use crate::position::lineoffset::trim_line_terminator;
+use crate::position::*;
+use crate::source::SourceText;
+use std::fmt::Write;
+
+/// Renders the source lines covered by `span` with a line-number gutter and
+/// `^` underlines beneath the covered text.
+///
+/// Empty spans, and spans covering only a line terminator, are rendered as a
+/// single caret. Tabs before the underline are preserved so the carets line
+/// up with the source as displayed by a terminal.
+pub fn render_span<S: SourceText + ?Sized>(
+    source: &S,
+    span: Span,
+    offsets: &LineOffsets,
+) -> String {
+    let last_line = offsets.lines_of(span).last().map_or(1, |(line, _)| line);
+    let gutter = last_line.to_string().len();
+
+    let mut out = String::new();
+    writeln!(out, "{:gutter$} |", "").unwrap();
+    for (line, portion) in offsets.lines_of(span) {
+        let line_span = offsets.line_span(line).unwrap();
+        let line_text = source.slice(line_span.start()..line_span.end());
+        let text = trim_line_terminator(&line_text);
+        let prefix = &line_text[..portion.start() - line_span.start()];
+        let covered = trim_line_terminator(
+            &line_text[portion.start() - line_span.start()..portion.end() - line_span.start()],
+        );
+
+        writeln!(out, "{:>gutter$} | {}", line, text).unwrap();
+        writeln!(
+            out,
+            "{:gutter$} | {}{}",
+            "",
+            padding(prefix),
+            "^".repeat(covered.chars().count().max(1))
+        )
+        .unwrap();
+    }
+    out
+}
+
+/// Whitespace that occupies the same columns as `prefix`, keeping its tabs.
+pub(crate) fn padding(prefix: &str) -> String {
+    prefix
+        .chars()
+        .map(|ch| if ch == '\t' { '\t' } else { ' ' })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(source: &str, start: usize, end: usize) -> String {
+        render_span(
+            source,
+            Span::new(start, end).unwrap(),
+            &LineOffsets::new(source),
+        )
+    }
+
+    #[test]
+    fn test_single_line() {
+        assert_eq!(render("a + b", 2, 3), "  |\n1 | a + b\n  |   ^\n");
+    }
+
+    #[test]
+    fn test_empty_span() {
+        assert_eq!(render("foo(", 4, 4), "  |\n1 | foo(\n  |     ^\n");
+    }
+
+    #[test]
+    fn test_multi_line() {
+        let source = "if x {\n  y\n}\n";
+        assert_eq!(
+            render(source, 5, 12),
+            "  |\n\
+             1 | if x {\n  |      ^\n\
+             2 |   y\n  | ^^^\n\
+             3 | }\n  | ^\n"
+        );
+    }
+
+    #[test]
+    fn test_unicode_and_tabs() {
+        assert_eq!(
+            render("\té = 🦀;", 6, 10),
+            "  |\n1 | \té = 🦀;\n  | \t    ^\n"
+        );
+    }
+
+    #[test]
+    fn test_gutter_width() {
+        let source = "\n".repeat(9) + "x\ny";
+        assert_eq!(
+            render(&source, 9, 12),
+            "   |\n10 | x\n   | ^\n11 | y\n   | ^\n"
+        );
+    }
+}
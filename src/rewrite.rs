@@ -0,0 +1,280 @@
+//! Rewriting source text token by token, for formatters and codemods.
+//!
+//! A [`TokenStreamEditor`] records insertions, deletions and replacements
+//! of the tokens of a lexed source, and of the trivia between them, and
+//! [`finish`](TokenStreamEditor::finish) applies them into a [`Rewrite`]:
+//! the new text, the [`TextEdit`]s that make it, and where each token ended
+//! up. Text the editor is not told to change is kept as it is, comments
+//! included.
+//!
+//! # Examples
+//! ```
+//! use grammarsmith::position::{Span, WithSpan};
+//! use grammarsmith::rewrite::TokenStreamEditor;
+//!
+//! // `let  x=1` lexed into words, spaces and punctuation.
+//! let source = "let  x=1";
+//! let tokens = [
+//!     WithSpan::new("word", Span::new(0, 3).unwrap()),
+//!     WithSpan::new("space", Span::new(3, 5).unwrap()),
+//!     WithSpan::new("word", Span::new(5, 6).unwrap()),
+//!     WithSpan::new("=", Span::new(6, 7).unwrap()),
+//!     WithSpan::new("word", Span::new(7, 8).unwrap()),
+//! ];
+//! let mut editor = TokenStreamEditor::new(source, &tokens, |&kind| kind == "space");
+//! for index in [0, 2, 3] {
+//!     editor.replace_trivia_after(index, " ");
+//! }
+//! editor.insert_after(4, ";");
+//!
+//! let rewrite = editor.finish().unwrap();
+//! assert_eq!(rewrite.text, "let x = 1;");
+//! // The old spaces are gone, and the tokens after them have moved.
+//! assert_eq!(rewrite.spans[1], None);
+//! assert_eq!(rewrite.spans[4], Span::new(8, 9));
+//! ```
+
+use crate::position::{apply_edits, sort_edits, EditError, Span, TextEdit, WithSpan};
+
+/// Where an edit goes among the edits at the same position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Side {
+    /// After the token ending there.
+    After,
+    /// In the trivia between tokens.
+    Trivia,
+    /// Before the token starting there.
+    Before,
+}
+
+/// Records edits to the tokens of a source and applies them together.
+///
+/// Tokens are given by their index in the token list, which holds the
+/// trivia too, sorted and not overlapping. Text between tokens that no
+/// token covers counts as trivia. Edits are checked when applied, so that
+/// editing a token twice, such as by replacing a deleted one, is an
+/// [`EditError::Overlapping`].
+///
+/// Insertions at the same position go in the order of the tokens they are
+/// attached to: after the token before, then replaced trivia, then before
+/// the token after, and in the order they were made otherwise.
+///
+/// # Panics
+/// The methods taking an index panic if it is out of bounds.
+#[derive(Debug, Clone)]
+pub struct TokenStreamEditor<'s, T> {
+    source: &'s str,
+    tokens: &'s [WithSpan<T>],
+    trivia: Vec<bool>,
+    deleted: Vec<bool>,
+    edits: Vec<(Side, TextEdit)>,
+}
+
+impl<'s, T> TokenStreamEditor<'s, T> {
+    /// Creates an editor for `tokens` of `source`, where `is_trivia` tells
+    /// which tokens are trivia.
+    pub fn new(source: &'s str, tokens: &'s [WithSpan<T>], is_trivia: impl Fn(&T) -> bool) -> Self {
+        TokenStreamEditor {
+            source,
+            tokens,
+            trivia: tokens.iter().map(|token| is_trivia(&token.value)).collect(),
+            deleted: vec![false; tokens.len()],
+            edits: Vec::new(),
+        }
+    }
+
+    pub fn source(&self) -> &'s str {
+        self.source
+    }
+
+    pub fn tokens(&self) -> &'s [WithSpan<T>] {
+        self.tokens
+    }
+
+    /// Returns the edits recorded so far, in the order they were made.
+    pub fn edits(&self) -> impl Iterator<Item = &TextEdit> {
+        self.edits.iter().map(|(_, edit)| edit)
+    }
+
+    /// Replaces the text of the token at `index` with `text`.
+    pub fn replace(&mut self, index: usize, text: impl Into<String>) {
+        let span = self.tokens[index].span;
+        self.edit(Side::Before, TextEdit::new(span, text));
+    }
+
+    /// Deletes the token at `index`, keeping the trivia around it.
+    pub fn delete(&mut self, index: usize) {
+        self.deleted[index] = true;
+        let span = self.tokens[index].span;
+        self.edit(Side::Before, TextEdit::delete(span));
+    }
+
+    /// Deletes the token at `index` with the trivia after it, up to the next
+    /// token that is not trivia, or with the trivia before it if it is the
+    /// last such token, so that the tokens around it are spaced as before.
+    pub fn delete_with_trivia(&mut self, index: usize) {
+        let span = self.tokens[index].span;
+        let span = match self.next_token(index) {
+            Some(next) => Span::new_unchecked(span.start(), self.tokens[next].span.start()),
+            None => match self.prev_token(index) {
+                Some(prev) => Span::new_unchecked(self.tokens[prev].span.end(), span.end()),
+                None => span,
+            },
+        };
+        self.delete_covered(span);
+        self.edit(Side::Before, TextEdit::delete(span));
+    }
+
+    /// Inserts `text` before the token at `index`, after its leading
+    /// trivia.
+    pub fn insert_before(&mut self, index: usize, text: impl Into<String>) {
+        let start = self.tokens[index].span.start();
+        self.edit(Side::Before, TextEdit::new(Span::point(start), text));
+    }
+
+    /// Inserts `text` after the token at `index`, before its trailing
+    /// trivia.
+    pub fn insert_after(&mut self, index: usize, text: impl Into<String>) {
+        let end = self.tokens[index].span.end();
+        self.edit(Side::After, TextEdit::new(Span::point(end), text));
+    }
+
+    /// Replaces the trivia after the token at `index`, up to the next token
+    /// that is not trivia or the end of the source, with `text`, such as to
+    /// normalize the spacing between two tokens.
+    pub fn replace_trivia_after(&mut self, index: usize, text: impl Into<String>) {
+        let start = self.tokens[index].span.end();
+        let end = match self.next_token(index) {
+            Some(next) => self.tokens[next].span.start(),
+            None => self.source.len(),
+        };
+        let span = Span::new_unchecked(start, end);
+        self.delete_covered(span);
+        self.edit(Side::Trivia, TextEdit::new(span, text));
+    }
+
+    /// Applies the edits to the source.
+    pub fn finish(mut self) -> Result<Rewrite, EditError> {
+        self.edits.sort_by_key(|(side, _)| *side);
+        let edits: Vec<TextEdit> = self.edits.into_iter().map(|(_, edit)| edit).collect();
+        let text = apply_edits(self.source, &edits)?;
+        let edits: Vec<TextEdit> = sort_edits(&edits)?.into_iter().cloned().collect();
+        let spans = self
+            .tokens
+            .iter()
+            .zip(self.deleted)
+            .map(|(token, deleted)| match deleted {
+                true => None,
+                false => token.span.map_through_edits(&edits),
+            })
+            .collect();
+        Ok(Rewrite { text, edits, spans })
+    }
+
+    fn edit(&mut self, side: Side, edit: TextEdit) {
+        self.edits.push((side, edit));
+    }
+
+    /// Marks the tokens inside `span` as deleted.
+    fn delete_covered(&mut self, span: Span) {
+        for (token, deleted) in self.tokens.iter().zip(&mut self.deleted) {
+            if span.start() <= token.span.start() && token.span.end() <= span.end() {
+                *deleted = true;
+            }
+        }
+    }
+
+    fn next_token(&self, index: usize) -> Option<usize> {
+        (index + 1..self.tokens.len()).find(|&next| !self.trivia[next])
+    }
+
+    fn prev_token(&self, index: usize) -> Option<usize> {
+        (0..index).rev().find(|&prev| !self.trivia[prev])
+    }
+}
+
+/// The result of a [`TokenStreamEditor`]: the new text, and how spans of
+/// the old text map into it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rewrite {
+    pub text: String,
+    /// The edits that turn the old text into the new one, sorted.
+    pub edits: Vec<TextEdit>,
+    /// The span in the new text of each token, by index, or `None` if it
+    /// was deleted. A replaced token spans its replacement.
+    pub spans: Vec<Option<Span>>,
+}
+
+impl Rewrite {
+    /// Maps a span of the old text into the new one, as
+    /// [`Span::map_through_edits`] does.
+    pub fn map_span(&self, span: Span) -> Option<Span> {
+        span.map_through_edits(&self.edits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Splits `source` into words and single spaces or punctuation.
+    fn lex(source: &str) -> Vec<WithSpan<char>> {
+        let mut tokens: Vec<WithSpan<char>> = Vec::new();
+        for (start, ch) in source.char_indices() {
+            let kind = match ch {
+                'a'..='z' => 'w',
+                ch => ch,
+            };
+            let end = start + ch.len_utf8();
+            match tokens.last_mut() {
+                Some(last) if kind == 'w' && last.value == 'w' && last.span.end() == start => {
+                    last.span = Span::new(last.span.start(), end).unwrap();
+                }
+                _ => tokens.push(WithSpan::new(kind, Span::new(start, end).unwrap())),
+            }
+        }
+        tokens
+    }
+
+    #[test]
+    fn test_edits() {
+        let source = "f(a, b) c";
+        let tokens = lex(source);
+        let mut editor = TokenStreamEditor::new(source, &tokens, |&kind| kind == ' ');
+        // Drops `a, `, renames `b` and wraps `c`.
+        editor.delete_with_trivia(2);
+        editor.delete_with_trivia(3);
+        editor.replace(5, "bb");
+        editor.insert_before(8, "[");
+        editor.insert_after(8, "]");
+        editor.insert_after(6, ";");
+        editor.replace_trivia_after(6, "\n");
+
+        let rewrite = editor.finish().unwrap();
+        assert_eq!(rewrite.text, "f(bb);\n[c]");
+        let spans = [Some(0..1), Some(1..2), None, None, None, Some(2..4)];
+        let expected: Vec<_> = spans
+            .into_iter()
+            .map(|span| span.map(|span| Span::new(span.start, span.end).unwrap()))
+            .collect();
+        assert_eq!(rewrite.spans[..6], expected);
+        assert_eq!(rewrite.spans[8], Span::new(8, 9));
+        assert_eq!(rewrite.map_span(Span::new(0, 7).unwrap()), Span::new(0, 5));
+    }
+
+    #[test]
+    fn test_overlapping() {
+        let source = "a b";
+        let tokens = lex(source);
+        let mut editor = TokenStreamEditor::new(source, &tokens, |&kind| kind == ' ');
+        editor.delete_with_trivia(2);
+        editor.replace(2, "c");
+        assert_eq!(
+            editor.finish(),
+            Err(EditError::Overlapping(
+                Span::new(1, 3).unwrap(),
+                Span::new(2, 3).unwrap()
+            ))
+        );
+    }
+}
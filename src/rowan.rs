@@ -0,0 +1,154 @@
+//! Building [`rowan`] green trees from parser events.
+//!
+//! [`RowanSink`] is a [`TreeSink`] feeding a [`rowan::GreenNodeBuilder`], so
+//! projects built on rowan can keep their syntax trees while using
+//! grammarsmith's parser and recovery helpers. Kinds are converted with the
+//! user's [`rowan::Language`].
+//!
+//! # Examples
+//! ```
+//! use grammarsmith::event::Events;
+//! use grammarsmith::position::Span;
+//! use grammarsmith::rowan::RowanSink;
+//!
+//! #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+//! #[repr(u16)]
+//! enum SyntaxKind {
+//!     Number,
+//!     Root,
+//! }
+//!
+//! #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+//! enum Lang {}
+//!
+//! impl rowan::Language for Lang {
+//!     type Kind = SyntaxKind;
+//!
+//!     fn kind_from_raw(raw: rowan::SyntaxKind) -> SyntaxKind {
+//!         [SyntaxKind::Number, SyntaxKind::Root][raw.0 as usize]
+//!     }
+//!
+//!     fn kind_to_raw(kind: SyntaxKind) -> rowan::SyntaxKind {
+//!         rowan::SyntaxKind(kind as u16)
+//!     }
+//! }
+//!
+//! let mut events = Events::new();
+//! events.start_node(SyntaxKind::Root);
+//! events.token(SyntaxKind::Number, Span::new(0, 2).unwrap());
+//! events.finish_node();
+//!
+//! let mut sink = RowanSink::<Lang>::new();
+//! events.replay("42", &mut sink);
+//! let root = sink.finish_syntax();
+//! assert_eq!(root.kind(), SyntaxKind::Root);
+//! assert_eq!(root.text(), "42");
+//! ```
+
+use crate::event::TreeSink;
+use rowan::{GreenNode, GreenNodeBuilder, Language, SyntaxNode};
+use std::fmt;
+use std::marker::PhantomData;
+
+/// A [`TreeSink`] building a rowan green tree for the language `L`.
+pub struct RowanSink<L: Language> {
+    builder: GreenNodeBuilder<'static>,
+    language: PhantomData<L>,
+}
+
+impl<L: Language> Default for RowanSink<L> {
+    fn default() -> Self {
+        RowanSink {
+            builder: GreenNodeBuilder::new(),
+            language: PhantomData,
+        }
+    }
+}
+
+impl<L: Language> RowanSink<L> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the green tree.
+    ///
+    /// # Panics
+    /// Panics if the events did not describe exactly one root node.
+    pub fn finish(self) -> GreenNode {
+        self.builder.finish()
+    }
+
+    /// Returns the root of the tree as a syntax node.
+    ///
+    /// # Panics
+    /// Panics if the events did not describe exactly one root node.
+    pub fn finish_syntax(self) -> SyntaxNode<L> {
+        SyntaxNode::new_root(self.finish())
+    }
+}
+
+impl<L: Language> TreeSink<L::Kind> for RowanSink<L> {
+    fn start_node(&mut self, kind: L::Kind) {
+        self.builder.start_node(L::kind_to_raw(kind));
+    }
+
+    fn token(&mut self, kind: L::Kind, text: &str) {
+        self.builder.token(L::kind_to_raw(kind), text);
+    }
+
+    fn finish_node(&mut self) {
+        self.builder.finish_node();
+    }
+}
+
+impl<L: Language> fmt::Debug for RowanSink<L> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RowanSink").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::Events;
+    use crate::position::Span;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    enum Lang {}
+
+    impl Language for Lang {
+        type Kind = u16;
+
+        fn kind_from_raw(raw: rowan::SyntaxKind) -> u16 {
+            raw.0
+        }
+
+        fn kind_to_raw(kind: u16) -> rowan::SyntaxKind {
+            rowan::SyntaxKind(kind)
+        }
+    }
+
+    #[test]
+    fn test_nested_nodes() {
+        let source = "(a b)";
+        let mut events = Events::new();
+        events.start_node(0);
+        events.token(1, Span::new(0, 1).unwrap());
+        events.start_node(2);
+        events.token(3, Span::new(1, 2).unwrap());
+        events.token(4, Span::new(2, 3).unwrap());
+        events.token(3, Span::new(3, 4).unwrap());
+        events.finish_node();
+        events.token(1, Span::new(4, 5).unwrap());
+        events.finish_node();
+
+        let mut sink = RowanSink::<Lang>::new();
+        events.replay(source, &mut sink);
+        let root = sink.finish_syntax();
+        assert_eq!(root.text(), source);
+        let inner = root.first_child().unwrap();
+        assert_eq!(inner.kind(), 2);
+        assert_eq!(inner.text(), "a b");
+        assert_eq!(u32::from(inner.text_range().start()), 1);
+    }
+}
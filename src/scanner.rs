@@ -1,6 +1,8 @@
-use std::{iter::Peekable, str::Chars};
+use std::{borrow::Cow, iter::Peekable};
 
+use crate::diagnostics::{Diagnostic, DiagnosticSink, Severity};
 use crate::position::*;
+use crate::source::SourceText;
 
 /// A lexical scanner that processes input text character by character.
 ///
@@ -10,29 +12,51 @@ use crate::position::*;
 ///
 /// This allows the scanner to accumulate characters for tokens while keeping track
 /// of their position in the source text.
-pub struct Scanner<'a> {
+///
+/// The source can be any [`SourceText`]; it defaults to `str`.
+pub struct Scanner<'a, S: SourceText + ?Sized = str> {
     start: BytePos,
     current: BytePos,
-    source: &'a str,
-    it: Peekable<Chars<'a>>,
+    source: &'a S,
+    it: Peekable<S::Chars<'a>>,
 }
 
-impl<'a> Scanner<'a> {
+impl<'a, S: SourceText + ?Sized> Scanner<'a, S> {
     /// Creates a new Scanner from the given input string.
     ///
     /// # Arguments
     /// * `buf` - The source text to scan
-    pub fn new(buf: &str) -> Scanner {
-        Scanner {
+    ///
+    /// # Panics
+    /// Panics if `buf` is too large to be addressed by [`BytePos`]. Use
+    /// [`Scanner::try_new`] to handle this case.
+    pub fn new(buf: &'a S) -> Scanner<'a, S> {
+        match Scanner::try_new(buf) {
+            Ok(scanner) => scanner,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// Creates a new Scanner from the given input string, failing if the input
+    /// is too large to be addressed by [`BytePos`].
+    ///
+    /// # Arguments
+    /// * `buf` - The source text to scan
+    pub fn try_new(buf: &'a S) -> Result<Scanner<'a, S>, PositionOverflow> {
+        if BytePos::try_from_usize(buf.len()).is_none() {
+            return Err(PositionOverflow { len: buf.len() });
+        }
+
+        Ok(Scanner {
             current: BytePos::default(),
             start: BytePos::default(),
             source: buf,
-            it: buf.chars().peekable(),
-        }
+            it: buf.chars_from(0).peekable(),
+        })
     }
 
     /// Returns a reference to the complete source text.
-    pub fn source(&self) -> &str {
+    pub fn source(&self) -> &'a S {
         self.source
     }
 
@@ -54,11 +78,13 @@ impl<'a> Scanner<'a> {
         self.start = self.current;
     }
 
-    /// Returns a slice of the source text from the start to the current position.
+    /// Returns the source text from the start to the current position, for
+    /// any source, borrowed when the source is contiguous.
     ///
-    /// This is typically used to extract the text of the current token being scanned.
-    pub fn slice(&self) -> &str {
-        &self.source[self.start.0..self.current.0]
+    /// Sources that are strings can use [`slice`](Scanner::slice) instead.
+    pub fn slice_cow(&self) -> Cow<'a, str> {
+        self.source
+            .slice(self.start.to_usize()..self.current.to_usize())
     }
 
     /// Advances the scanner to the next character and returns it.
@@ -68,6 +94,7 @@ impl<'a> Scanner<'a> {
     /// # Returns
     /// * `Some(char)` - The next character in the input
     /// * `None` - If the end of input has been reached
+    #[allow(clippy::should_implement_trait)]
     pub fn next(&mut self) -> Option<char> {
         let next = self.it.next();
         if let Some(c) = next {
@@ -154,7 +181,7 @@ impl<'a> Scanner<'a> {
     where
         P: Fn(char) -> bool,
     {
-        let mut it: Peekable<Chars<'a>> = self.it.clone();
+        let mut it = self.it.clone();
 
         match it.next() {
             Some(_) => {
@@ -164,9 +191,9 @@ impl<'a> Scanner<'a> {
                         return true;
                     }
                 }
-                return false;
+                false
             }
-            None => return false,
+            None => false,
         }
     }
 
@@ -215,18 +242,50 @@ impl<'a> Scanner<'a> {
     /// # Arguments
     /// * `token_type` - The token to wrap with position information
     pub fn with_span<T>(&self, token_type: T) -> WithSpan<T> {
-        WithSpan::new_unchecked(token_type, self.start.0, self.current.0)
+        WithSpan::new(
+            token_type,
+            Span {
+                start: self.start,
+                end: self.current,
+            },
+        )
+    }
+
+    /// Reports an error covering the current token to `sink`.
+    ///
+    /// # Arguments
+    /// * `message` - The error message
+    /// * `sink` - Where the error is reported
+    pub fn error<D: DiagnosticSink + ?Sized>(&self, message: impl Into<String>, sink: &mut D) {
+        let span = Span {
+            start: self.start,
+            end: self.current,
+        };
+        sink.emit(
+            Diagnostic::builder(Severity::Error, message)
+                .primary(span, "")
+                .build(),
+        );
     }
 
     /// Returns a copy of the iterator over the characters in the source text.
     ///
     /// # Returns
     /// A copy of the iterator over the characters in the source text
-    pub fn iterator(&self) -> impl Iterator<Item = char> + use<'a> {
+    pub fn iterator(&self) -> impl Iterator<Item = char> + use<'a, S> {
         self.it.clone()
     }
 }
 
+impl<'a, S: AsRef<str> + SourceText + ?Sized> Scanner<'a, S> {
+    /// Returns a slice of the source text from the start to the current position.
+    ///
+    /// This is typically used to extract the text of the current token being scanned.
+    pub fn slice(&self) -> &'a str {
+        &self.source.as_ref()[self.start.to_usize()..self.current.to_usize()]
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -291,6 +350,20 @@ mod tests {
         assert_eq!(scanner.slice(), "12");
     }
 
+    #[test]
+    fn test_error() {
+        let mut scanner = Scanner::new("1 $");
+        let mut sink = Vec::new();
+        scanner.next();
+        scanner.next();
+        scanner.shift();
+        scanner.next();
+        scanner.error("unexpected character", &mut sink);
+        assert_eq!(sink.len(), 1);
+        assert_eq!(sink[0].primary_span(), Span::new(2, 3));
+        assert!(sink[0].is_error());
+    }
+
     #[test]
     fn test_if_next() {
         let mut scanner = Scanner::new("123abc");
@@ -0,0 +1,18 @@
+//! Source texts and files.
+//!
+//! This module provides the [`SourceText`] abstraction over text
+//! representations, and [`SourceFile`]s collected in a [`SourceMap`], or
+//! loaded on demand by a [`SourceCache`], for rendering diagnostics.
+//!
+//! # Examples
+//! ```
+//! use grammarsmith::source::*;
+//! ```
+
+pub mod cache;
+pub mod map;
+pub mod text;
+
+pub use cache::*;
+pub use map::*;
+pub use text::*;
@@ -0,0 +1,211 @@
+use crate::source::{FileId, SourceFile, SourceMap};
+use std::cell::OnceCell;
+use std::io;
+
+/// Looks up source files by id, for renderers showing excerpts of several
+/// files.
+pub trait SourceLookup {
+    /// Returns a file by id, or `None` if it is unknown or cannot be read.
+    fn file(&self, id: FileId) -> Option<&SourceFile>;
+}
+
+impl SourceLookup for SourceMap {
+    fn file(&self, id: FileId) -> Option<&SourceFile> {
+        self.get(id)
+    }
+}
+
+/// Reads the contents of files by name for a [`SourceCache`].
+pub trait SourceLoader {
+    fn load(&self, name: &str) -> io::Result<String>;
+}
+
+/// Loads files from the file system, using their names as paths.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FsLoader;
+
+impl SourceLoader for FsLoader {
+    fn load(&self, name: &str) -> io::Result<String> {
+        std::fs::read_to_string(name)
+    }
+}
+
+/// Loads files from the files of a [`SourceMap`] with the same name.
+impl SourceLoader for SourceMap {
+    fn load(&self, name: &str) -> io::Result<String> {
+        self.iter()
+            .find(|(_, file)| file.name() == name)
+            .map(|(_, file)| file.source().to_string())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, name.to_string()))
+    }
+}
+
+impl<L: SourceLoader + ?Sized> SourceLoader for &L {
+    fn load(&self, name: &str) -> io::Result<String> {
+        (**self).load(name)
+    }
+}
+
+/// A set of files whose contents are loaded the first time they are needed.
+///
+/// Files are registered by name up front, which gives them an id to use in
+/// diagnostics without reading them. Rendering a diagnostic that points into
+/// ten imported files then reads only those files, once each.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::diagnostics::*;
+/// use grammarsmith::position::Span;
+/// use grammarsmith::source::*;
+///
+/// let mut files = SourceMap::new();
+/// files.add("lib.lang", "fn f() {}");
+///
+/// let mut cache = SourceCache::new(files);
+/// let main = cache.insert(SourceFile::new("main.lang", "use lib;"));
+/// let lib = cache.add("lib.lang");
+/// assert!(!cache.is_loaded(lib));
+///
+/// let diagnostic = Diagnostic::error("`f` is unused")
+///     .with_label_in(FileSpan::new(lib, Span::new(3, 4).unwrap()), "");
+/// let rendered = TerminalRenderer::new().render_in(&diagnostic, main, &cache);
+/// assert!(rendered.contains("lib.lang:1:4"));
+/// assert!(cache.is_loaded(lib));
+/// ```
+#[derive(Debug)]
+pub struct SourceCache<L = FsLoader> {
+    loader: L,
+    files: Vec<(String, OnceCell<Option<SourceFile>>)>,
+}
+
+impl Default for SourceCache<FsLoader> {
+    fn default() -> Self {
+        SourceCache::new(FsLoader)
+    }
+}
+
+impl<L: SourceLoader> SourceCache<L> {
+    pub fn new(loader: L) -> Self {
+        SourceCache {
+            loader,
+            files: Vec::new(),
+        }
+    }
+
+    /// Registers the file `name` without loading it, and returns its id. A
+    /// name that is already registered keeps its id.
+    pub fn add(&mut self, name: impl Into<String>) -> FileId {
+        let name = name.into();
+        if let Some(id) = self.id(&name) {
+            return id;
+        }
+        self.files.push((name, OnceCell::new()));
+        FileId((self.files.len() - 1) as u32)
+    }
+
+    /// Adds a file whose contents are already known, and returns its id.
+    pub fn insert(&mut self, file: SourceFile) -> FileId {
+        let id = self.add(file.name());
+        let cell = OnceCell::new();
+        let _ = cell.set(Some(file));
+        self.files[id.0 as usize].1 = cell;
+        id
+    }
+
+    /// Returns the id of a registered file.
+    pub fn id(&self, name: &str) -> Option<FileId> {
+        self.files
+            .iter()
+            .position(|(file, _)| file == name)
+            .map(|i| FileId(i as u32))
+    }
+
+    /// Returns a file by id, loading it if needed. Returns `None` if the id
+    /// is unknown or the file could not be loaded; a failed load is not
+    /// retried.
+    pub fn get(&self, id: FileId) -> Option<&SourceFile> {
+        let (name, cell) = self.files.get(id.0 as usize)?;
+        cell.get_or_init(|| {
+            self.loader
+                .load(name)
+                .ok()
+                .map(|source| SourceFile::new(name.as_str(), source))
+        })
+        .as_ref()
+    }
+
+    /// Returns true if the file has been loaded, or failed to load.
+    pub fn is_loaded(&self, id: FileId) -> bool {
+        self.files
+            .get(id.0 as usize)
+            .is_some_and(|(_, cell)| cell.get().is_some())
+    }
+
+    /// Returns the number of registered files.
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Returns true if no files are registered.
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+}
+
+impl<L: SourceLoader> SourceLookup for SourceCache<L> {
+    fn file(&self, id: FileId) -> Option<&SourceFile> {
+        self.get(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    struct Counting<'a>(&'a Cell<usize>);
+
+    impl SourceLoader for Counting<'_> {
+        fn load(&self, name: &str) -> io::Result<String> {
+            self.0.set(self.0.get() + 1);
+            match name {
+                "missing" => Err(io::ErrorKind::NotFound.into()),
+                name => Ok(format!("contents of {}", name)),
+            }
+        }
+    }
+
+    #[test]
+    fn test_loads_once() {
+        let loads = Cell::new(0);
+        let mut cache = SourceCache::new(Counting(&loads));
+        let a = cache.add("a");
+        assert_eq!(cache.add("a"), a);
+        let missing = cache.add("missing");
+        assert_eq!(loads.get(), 0);
+
+        assert_eq!(cache.get(a).unwrap().source(), "contents of a");
+        assert_eq!(cache.get(a).unwrap().name(), "a");
+        assert!(cache.get(missing).is_none());
+        assert!(cache.get(missing).is_none());
+        assert!(cache.get(FileId(7)).is_none());
+        assert_eq!(loads.get(), 2);
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn test_fs_loader() {
+        let cache = {
+            let mut cache = SourceCache::default();
+            cache.add(concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml"));
+            cache.add("/this/file/does/not/exist");
+            cache
+        };
+        assert!(cache
+            .get(FileId(0))
+            .unwrap()
+            .source()
+            .contains("grammarsmith"));
+        assert!(cache.get(FileId(1)).is_none());
+    }
+}
@@ -0,0 +1,212 @@
+use crate::position::{BytePos, ColumnMode, GetSpan, LineOffsets, PositionRemapper, Span};
+
+/// Identifies a file inside a [`SourceMap`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
+pub struct FileId(pub u32);
+
+/// A span inside a specific file of a [`SourceMap`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FileSpan {
+    pub file: FileId,
+    pub span: Span,
+}
+
+impl FileSpan {
+    pub fn new(file: FileId, span: Span) -> Self {
+        FileSpan { file, span }
+    }
+}
+
+impl GetSpan for FileSpan {
+    fn get_span(&self) -> Span {
+        self.span
+    }
+}
+
+/// A named source text together with its line offsets.
+///
+/// A file that was generated or preprocessed can be given a
+/// [`PositionRemapper`] with [`with_remapper`](SourceFile::with_remapper),
+/// so that its locations, and the diagnostics rendered for it, are reported
+/// in the files it came from.
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    name: String,
+    source: String,
+    line_offsets: LineOffsets,
+    remapper: Option<PositionRemapper>,
+}
+
+impl SourceFile {
+    pub fn new(name: impl Into<String>, source: impl Into<String>) -> Self {
+        let source = source.into();
+        SourceFile {
+            name: name.into(),
+            line_offsets: LineOffsets::new(source.as_str()),
+            source,
+            remapper: None,
+        }
+    }
+
+    /// Reports the locations of the file through `remapper`.
+    pub fn with_remapper(mut self, remapper: PositionRemapper) -> Self {
+        self.remapper = Some(remapper);
+        self
+    }
+
+    /// Returns the remapper of the file, if any.
+    pub fn remapper(&self) -> Option<&PositionRemapper> {
+        self.remapper.as_ref()
+    }
+
+    /// Returns the name of the file, usually its path.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the contents of the file.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Returns the line offsets of the file.
+    pub fn line_offsets(&self) -> &LineOffsets {
+        &self.line_offsets
+    }
+
+    /// Returns the line and column (both 1-based) of a position, with the
+    /// line remapped as for [`location`](SourceFile::location).
+    pub fn line_column(&self, pos: BytePos, mode: ColumnMode) -> (usize, usize) {
+        let (_, line, column) = self.location(pos, mode);
+        (line, column)
+    }
+
+    /// Returns the name of the file a position is reported in, and its line
+    /// and column (both 1-based).
+    ///
+    /// Without a remapper, or where no directive applies, this is the name
+    /// of this file and the physical line. Otherwise the file and line come
+    /// from the remapper. Columns are never remapped, since directives only
+    /// map lines: they are counted in the line of this file.
+    pub fn location(&self, pos: BytePos, mode: ColumnMode) -> (&str, usize, usize) {
+        let (line, column) = self
+            .line_offsets
+            .line_column(pos, self.source.as_str(), mode);
+        let remapped = self
+            .remapper
+            .as_ref()
+            .and_then(|remapper| remapper.remap(pos, &self.line_offsets));
+        match remapped {
+            Some(location) => (location.file, location.line, column),
+            None => (&self.name, line, column),
+        }
+    }
+
+    /// Returns the text covered by a span.
+    pub fn slice(&self, span: Span) -> &str {
+        &self.source[span.start()..span.end()]
+    }
+}
+
+/// A collection of source files, addressed by [`FileId`].
+///
+/// # Examples
+/// ```
+/// use grammarsmith::source::*;
+/// let mut map = SourceMap::new();
+/// let main = map.add("main.lang", "let x = 1;");
+/// assert_eq!(map.get(main).unwrap().name(), "main.lang");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a file and returns its id.
+    pub fn add(&mut self, name: impl Into<String>, source: impl Into<String>) -> FileId {
+        self.add_file(SourceFile::new(name, source))
+    }
+
+    /// Adds an existing [`SourceFile`] and returns its id.
+    pub fn add_file(&mut self, file: SourceFile) -> FileId {
+        self.files.push(file);
+        FileId((self.files.len() - 1) as u32)
+    }
+
+    /// Returns a file by id.
+    pub fn get(&self, id: FileId) -> Option<&SourceFile> {
+        self.files.get(id.0 as usize)
+    }
+
+    /// Returns the text covered by a [`FileSpan`], if its file exists.
+    pub fn slice(&self, span: FileSpan) -> Option<&str> {
+        self.get(span.file).map(|file| file.slice(span.span))
+    }
+
+    /// Returns the number of files.
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Returns true if the map contains no files.
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Iterates over all files with their ids.
+    pub fn iter(&self) -> impl Iterator<Item = (FileId, &SourceFile)> {
+        self.files
+            .iter()
+            .enumerate()
+            .map(|(i, file)| (FileId(i as u32), file))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_source_map() {
+        let mut map = SourceMap::new();
+        let a = map.add("a", "first\nline");
+        let b = map.add("b", "second");
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get(b).unwrap().source(), "second");
+        assert_eq!(map.get(FileId(2)).map(SourceFile::name), None);
+
+        let file = map.get(a).unwrap();
+        assert_eq!(file.line_column(BytePos(8), ColumnMode::Char), (2, 3));
+        assert_eq!(file.slice(Span::new(6, 10).unwrap()), "line");
+
+        let span = FileSpan::new(b, Span::new(0, 3).unwrap());
+        assert_eq!(map.slice(span), Some("sec"));
+        assert_eq!(map.slice(FileSpan::new(FileId(2), span.span)), None);
+    }
+
+    #[test]
+    fn test_remapper() {
+        let mut remapper = PositionRemapper::new();
+        remapper.add(BytePos(4), "original", 7);
+        let file = SourceFile::new("generated", "abc\n  de\n").with_remapper(remapper);
+        assert_eq!(
+            file.location(BytePos(1), ColumnMode::Char),
+            ("generated", 1, 2)
+        );
+        assert_eq!(
+            file.location(BytePos(6), ColumnMode::Char),
+            ("original", 7, 3)
+        );
+        assert_eq!(file.line_column(BytePos(6), ColumnMode::Char), (7, 3));
+    }
+}
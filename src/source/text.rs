@@ -0,0 +1,182 @@
+use std::borrow::Cow;
+use std::ops::Range;
+use std::rc::Rc;
+use std::str::Chars;
+use std::sync::Arc;
+
+/// A UTF-8 text that can be scanned, sliced and measured.
+///
+/// This lets the [`Scanner`](crate::scanner::Scanner),
+/// [`LineOffsets`](crate::position::LineOffsets) and snippet rendering work
+/// over plain `&str`s and owned or shared strings such as `Arc<str>`, which
+/// are the implementations this crate provides. Texts stored in pieces, such
+/// as ropes, can implement it too, at the cost of copying the slices that
+/// cross a piece boundary.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::*;
+/// use std::sync::Arc;
+///
+/// let source: Arc<str> = Arc::from("1 + 2");
+/// let mut scanner = Scanner::new(&source);
+/// scanner.next();
+/// assert_eq!(scanner.slice(), "1");
+/// ```
+pub trait SourceText {
+    /// Iterator over the characters of the text.
+    type Chars<'a>: Iterator<Item = char> + Clone
+    where
+        Self: 'a;
+
+    /// The length of the text in bytes.
+    fn len(&self) -> usize;
+
+    /// Returns the whole text if it is stored contiguously, so that it can
+    /// be scanned as a `str`. Defaults to `None`.
+    fn as_str(&self) -> Option<&str> {
+        None
+    }
+
+    /// Returns true if the text is empty.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the text in the given byte range.
+    ///
+    /// Implementations borrow when the text is stored contiguously.
+    ///
+    /// # Panics
+    /// Panics if the range is out of bounds or not on character boundaries.
+    fn slice(&self, range: Range<usize>) -> Cow<'_, str>;
+
+    /// Iterates over the characters of the text starting at byte `offset`.
+    ///
+    /// # Panics
+    /// Panics if `offset` is out of bounds or not on a character boundary.
+    fn chars_from(&self, offset: usize) -> Self::Chars<'_>;
+}
+
+impl SourceText for str {
+    type Chars<'a> = Chars<'a>;
+
+    fn len(&self) -> usize {
+        str::len(self)
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        Some(self)
+    }
+
+    fn slice(&self, range: Range<usize>) -> Cow<'_, str> {
+        Cow::Borrowed(&self[range])
+    }
+
+    fn chars_from(&self, offset: usize) -> Chars<'_> {
+        self[offset..].chars()
+    }
+}
+
+macro_rules! deref_source_text {
+    ($($ty:ty),*) => {
+        $(
+            impl SourceText for $ty {
+                type Chars<'a> = Chars<'a>;
+
+                fn len(&self) -> usize {
+                    str::len(self)
+                }
+
+                fn as_str(&self) -> Option<&str> {
+                    Some(self)
+                }
+
+                fn slice(&self, range: Range<usize>) -> Cow<'_, str> {
+                    Cow::Borrowed(&self[range])
+                }
+
+                fn chars_from(&self, offset: usize) -> Chars<'_> {
+                    self[offset..].chars()
+                }
+            }
+        )*
+    };
+}
+
+deref_source_text!(String, Box<str>, Rc<str>, Arc<str>);
+
+impl<T: SourceText + ?Sized> SourceText for &T {
+    type Chars<'a>
+        = T::Chars<'a>
+    where
+        Self: 'a;
+
+    fn len(&self) -> usize {
+        (**self).len()
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        (**self).as_str()
+    }
+
+    fn slice(&self, range: Range<usize>) -> Cow<'_, str> {
+        (**self).slice(range)
+    }
+
+    fn chars_from(&self, offset: usize) -> Self::Chars<'_> {
+        (**self).chars_from(offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A text stored in separate chunks, like a rope.
+    struct Chunks(Vec<&'static str>);
+
+    impl SourceText for Chunks {
+        type Chars<'a> = std::vec::IntoIter<char>;
+
+        fn len(&self) -> usize {
+            self.0.iter().map(|chunk| chunk.len()).sum()
+        }
+
+        fn slice(&self, range: Range<usize>) -> Cow<'_, str> {
+            Cow::Owned(self.0.concat()[range].to_string())
+        }
+
+        fn chars_from(&self, offset: usize) -> Self::Chars<'_> {
+            self.0.concat()[offset..]
+                .chars()
+                .collect::<Vec<_>>()
+                .into_iter()
+        }
+    }
+
+    #[test]
+    fn test_str_impls_agree() {
+        let text = "a€🦀";
+        let owned = String::from(text);
+        let shared: Arc<str> = Arc::from(text);
+        assert_eq!(SourceText::len(text), 8);
+        assert_eq!(SourceText::len(&owned), 8);
+        assert_eq!(shared.slice(1..4), "€");
+        assert_eq!(owned.chars_from(1).collect::<String>(), "€🦀");
+    }
+
+    #[test]
+    fn test_chunked_source() {
+        let text = Chunks(vec!["ab\nc", "d\nef"]);
+        let offsets = crate::position::LineOffsets::new(&text);
+        assert_eq!(offsets.line(crate::position::BytePos(6)), 3);
+
+        let mut scanner = crate::scanner::Scanner::new(&text);
+        scanner.consume_while(|c| c != '\n');
+        scanner.next();
+        scanner.shift();
+        scanner.consume_while(|c| c != '\n');
+        assert_eq!(scanner.slice_cow(), "cd");
+    }
+}
@@ -0,0 +1,232 @@
+//! Lexing input as it arrives in chunks, such as from a socket or a pipe.
+//!
+//! A [`StreamLexer`] is fed chunks of text with [`feed`](StreamLexer::feed)
+//! and returns the tokens that more input cannot change, with spans over the
+//! whole input; [`finish`](StreamLexer::finish) returns the rest. Tokens
+//! can be parsed as they come with
+//! [`StreamParser`](crate::grammar::StreamParser).
+//!
+//! # Examples
+//! ```
+//! use grammarsmith::position::{Span, WithSpan};
+//! use grammarsmith::stream::StreamLexer;
+//!
+//! /// Lexes runs of digits and single other characters.
+//! fn lex(source: &str, start: usize) -> WithSpan<char> {
+//!     let rest = &source[start..];
+//!     let len = match rest.find(|ch: char| !ch.is_ascii_digit()) {
+//!         Some(0) => rest.chars().next().unwrap().len_utf8(),
+//!         Some(len) => len,
+//!         None => rest.len(),
+//!     };
+//!     let kind = if rest.starts_with(|ch: char| ch.is_ascii_digit()) { 'n' } else { 'o' };
+//!     WithSpan::new(kind, Span::new(start, start + len).unwrap())
+//! }
+//!
+//! let mut lexer = StreamLexer::new(lex);
+//! // The number may go on in the next chunk.
+//! assert_eq!(lexer.feed("1+2"), [
+//!     WithSpan::new('n', Span::new(0, 1).unwrap()),
+//!     WithSpan::new('o', Span::new(1, 2).unwrap()),
+//! ]);
+//! assert!(lexer.feed("3").is_empty());
+//! assert_eq!(lexer.finish(), [WithSpan::new('n', Span::new(2, 4).unwrap())]);
+//! ```
+
+use crate::position::{Span, WithSpan};
+use std::fmt;
+
+/// Lexes the token starting at an offset of a source.
+type Lex<T> = dyn Fn(&str, usize) -> WithSpan<T>;
+
+/// A lexer fed input in chunks, which holds back the tokens that the next
+/// chunk could still extend.
+///
+/// The lexer returns the token starting at an offset of the text it is
+/// given, which must not be empty, as for a
+/// [`DocumentState`](crate::document::DocumentState). It is only given the
+/// text not yet lexed into returned tokens, so it should not depend on the
+/// text before the offset.
+///
+/// A token is returned once the input goes on for the lookahead past it,
+/// one byte by default, so that a token ending at the end of a chunk is
+/// held back. Lexers that look further ahead to end a token, such as to
+/// tell `1.` from `1.5`, need a longer
+/// [lookahead](StreamLexer::with_lookahead).
+///
+/// The lexer cannot resume a token part way, so the token held back is
+/// lexed again from its start on every feed. Each feed only lexes the new
+/// tokens and that one, but a token that arrives over `n` chunks, such as a
+/// long string literal fed a byte at a time, costs `O(n²)` in all. Feeding
+/// larger chunks, such as whole reads from a socket, keeps this small.
+pub struct StreamLexer<T> {
+    lex: Box<Lex<T>>,
+    lookahead: usize,
+    /// The input not yet lexed into returned tokens.
+    buffer: String,
+    /// The offset of the buffer in the whole input.
+    offset: usize,
+}
+
+impl<T> StreamLexer<T> {
+    pub fn new(lex: impl Fn(&str, usize) -> WithSpan<T> + 'static) -> Self {
+        StreamLexer {
+            lex: Box::new(lex),
+            lookahead: 1,
+            buffer: String::new(),
+            offset: 0,
+        }
+    }
+
+    /// Sets how many bytes of input must follow a token for it to be
+    /// returned.
+    pub fn with_lookahead(mut self, lookahead: usize) -> Self {
+        self.lookahead = lookahead;
+        self
+    }
+
+    /// Returns the length of the input fed so far.
+    pub fn len(&self) -> usize {
+        self.offset + self.buffer.len()
+    }
+
+    /// Returns true if no input has been fed.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Adds `chunk` to the input and returns the tokens that are complete.
+    ///
+    /// # Panics
+    /// Panics if the lexer returns an empty token or one not starting at
+    /// the offset it was given.
+    pub fn feed(&mut self, chunk: &str) -> Vec<WithSpan<T>> {
+        self.buffer.push_str(chunk);
+        self.lex(false)
+    }
+
+    /// Ends the input and returns the remaining tokens.
+    ///
+    /// # Panics
+    /// Panics as [`feed`](StreamLexer::feed) does.
+    pub fn finish(mut self) -> Vec<WithSpan<T>> {
+        self.flush()
+    }
+
+    /// Returns the remaining tokens, as for the end of the input.
+    pub(crate) fn flush(&mut self) -> Vec<WithSpan<T>> {
+        self.lex(true)
+    }
+
+    fn lex(&mut self, last: bool) -> Vec<WithSpan<T>> {
+        let mut tokens = Vec::new();
+        let mut pos = 0;
+        while pos < self.buffer.len() {
+            let token = (self.lex)(&self.buffer, pos);
+            assert!(
+                token.span.start() == pos && token.span.end() > pos,
+                "lexer returned an empty token or one not starting at {}",
+                self.offset + pos
+            );
+            if !last && token.span.end() + self.lookahead > self.buffer.len() {
+                break;
+            }
+            pos = token.span.end();
+            let span = Span::new_unchecked(self.offset + token.span.start(), self.offset + pos);
+            tokens.push(WithSpan::new(token.value, span));
+        }
+        self.buffer.drain(..pos);
+        self.offset += pos;
+        tokens
+    }
+}
+
+impl<T> fmt::Debug for StreamLexer<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StreamLexer")
+            .field("lookahead", &self.lookahead)
+            .field("buffer", &self.buffer)
+            .field("offset", &self.offset)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lexes `//` comments to the end of the line, and single characters.
+    fn lex(source: &str, start: usize) -> WithSpan<char> {
+        let rest = &source[start..];
+        let (kind, len) = match rest.starts_with("//") {
+            true => ('c', rest.find('\n').unwrap_or(rest.len())),
+            false => ('x', rest.chars().next().unwrap().len_utf8()),
+        };
+        WithSpan::new(kind, Span::new(start, start + len).unwrap())
+    }
+
+    fn kinds(tokens: &[WithSpan<char>]) -> String {
+        tokens.iter().map(|token| token.value).collect()
+    }
+
+    #[test]
+    fn test_partial_tokens() {
+        let mut lexer = StreamLexer::new(lex);
+        // The slash may start a comment.
+        assert_eq!(kinds(&lexer.feed("a/")), "x");
+        assert_eq!(kinds(&lexer.feed("/ b")), "");
+        assert_eq!(kinds(&lexer.feed("é\nc")), "cx");
+        assert_eq!(lexer.len(), 9);
+        let rest = lexer.finish();
+        assert_eq!(kinds(&rest), "x");
+        assert_eq!(rest[0].span, Span::new(8, 9).unwrap());
+    }
+
+    #[test]
+    fn test_long_token_byte_by_byte() {
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        // Lexes `"` strings, which go on to the closing quote, and single
+        // characters.
+        let calls = Rc::new(Cell::new(0));
+        let counter = calls.clone();
+        let mut lexer = StreamLexer::new(move |source: &str, start| {
+            counter.set(counter.get() + 1);
+            let rest = &source[start..];
+            let len = match rest.strip_prefix('"') {
+                Some(string) => string.find('"').map_or(rest.len(), |end| end + 2),
+                None => rest.chars().next().unwrap().len_utf8(),
+            };
+            WithSpan::new(
+                rest.starts_with('"'),
+                Span::new(start, start + len).unwrap(),
+            )
+        });
+
+        let input = format!("\"{}\"x", "a".repeat(1000));
+        let mut tokens = Vec::new();
+        for index in 0..input.len() {
+            tokens.extend(lexer.feed(&input[index..index + 1]));
+        }
+        tokens.extend(lexer.finish());
+        assert_eq!(
+            tokens,
+            [
+                WithSpan::new(true, Span::new(0, 1002).unwrap()),
+                WithSpan::new(false, Span::new(1002, 1003).unwrap()),
+            ]
+        );
+        // The string is lexed again on each feed, but nothing before it.
+        assert_eq!(calls.get(), input.len() + 2);
+    }
+
+    #[test]
+    fn test_lookahead() {
+        let mut lexer = StreamLexer::new(lex).with_lookahead(2);
+        assert_eq!(kinds(&lexer.feed("ab/")), "x");
+        assert_eq!(kinds(&lexer.feed("")), "");
+        assert_eq!(kinds(&lexer.feed("c")), "x");
+        assert_eq!(kinds(&lexer.finish()), "xx");
+    }
+}
@@ -0,0 +1,322 @@
+//! A harness for directory-driven parser tests.
+//!
+//! A [`Corpus`] is a directory of input files, each next to a `.expected`
+//! file holding the tree dump and rendered diagnostics the parser should
+//! produce for it. Running the corpus parses every input and compares the
+//! output with the expected file; in bless mode, enabled by setting the
+//! environment variable [`BLESS_VAR`], the expected files are written
+//! instead, so they can be reviewed in version control.
+//!
+//! # Examples
+//! ```no_run
+//! use grammarsmith::testing::Corpus;
+//! use grammarsmith::tree::dump_trees;
+//! # fn parse(source: &str) -> (Vec<grammarsmith::tree::ParseTree<u8>>, Vec<grammarsmith::diagnostics::Diagnostic>) { unimplemented!() }
+//!
+//! Corpus::new("tests/corpus")
+//!     .with_extension("lang")
+//!     .assert(|file| {
+//!         let (trees, diagnostics) = parse(file.source());
+//!         (dump_trees(&trees, file.source()), diagnostics)
+//!     });
+//! ```
+
+use crate::diagnostics::{snapshot, Diagnostic};
+use crate::source::SourceFile;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The environment variable enabling bless mode when set to anything but
+/// an empty string or `0`.
+pub const BLESS_VAR: &str = "GRAMMARSMITH_BLESS";
+
+/// The extension of expected output files.
+pub const EXPECTED_EXTENSION: &str = "expected";
+
+/// A directory of parser inputs with their expected outputs.
+///
+/// The expected output of `dir/a.lang` is in `dir/a.expected`.
+/// Subdirectories are included, and inputs are run in path order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Corpus {
+    dir: PathBuf,
+    extension: Option<String>,
+    bless: bool,
+}
+
+impl Corpus {
+    /// Creates a corpus of the files in `dir`, blessing if [`BLESS_VAR`] is
+    /// set.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        let bless =
+            std::env::var_os(BLESS_VAR).is_some_and(|value| !value.is_empty() && value != "0");
+        Corpus {
+            dir: dir.into(),
+            extension: None,
+            bless,
+        }
+    }
+
+    /// Only uses the files with the given extension as inputs. By default,
+    /// all files but expected outputs are inputs.
+    pub fn with_extension(mut self, extension: impl Into<String>) -> Self {
+        self.extension = Some(extension.into());
+        self
+    }
+
+    /// Sets whether expected outputs are written instead of compared.
+    pub fn with_bless(mut self, bless: bool) -> Self {
+        self.bless = bless;
+        self
+    }
+
+    /// Returns the inputs of the corpus, in path order.
+    pub fn inputs(&self) -> io::Result<Vec<PathBuf>> {
+        let mut inputs = Vec::new();
+        self.collect_inputs(&self.dir, &mut inputs)?;
+        inputs.sort();
+        Ok(inputs)
+    }
+
+    fn collect_inputs(&self, dir: &Path, inputs: &mut Vec<PathBuf>) -> io::Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                self.collect_inputs(&path, inputs)?;
+                continue;
+            }
+            let extension = path.extension().and_then(|extension| extension.to_str());
+            let is_input = match &self.extension {
+                Some(wanted) => extension == Some(wanted.as_str()),
+                None => extension != Some(EXPECTED_EXTENSION),
+            };
+            if is_input {
+                inputs.push(path);
+            }
+        }
+        Ok(())
+    }
+
+    /// Parses every input with `parse`, which returns the tree dump and the
+    /// diagnostics, and compares the output with the expected file, or
+    /// writes it in bless mode.
+    ///
+    /// Source files are named by their path relative to the corpus
+    /// directory, with forward slashes, and line endings of expected files
+    /// are normalized, so outputs do not depend on the platform.
+    pub fn run<F>(&self, mut parse: F) -> io::Result<CorpusReport>
+    where
+        F: FnMut(&SourceFile) -> (String, Vec<Diagnostic>),
+    {
+        let mut report = CorpusReport::default();
+        for path in self.inputs()? {
+            let name = path
+                .strip_prefix(&self.dir)
+                .unwrap_or(&path)
+                .to_string_lossy()
+                .replace('\\', "/");
+            let file = SourceFile::new(name, fs::read_to_string(&path)?);
+            let (tree, diagnostics) = parse(&file);
+            let actual = render(&tree, &diagnostics, &file);
+
+            let expected_path = path.with_extension(EXPECTED_EXTENSION);
+            if self.bless {
+                fs::write(&expected_path, &actual)?;
+                report.blessed.push(path);
+                continue;
+            }
+            let expected = match fs::read_to_string(&expected_path) {
+                Ok(expected) => Some(expected.replace("\r\n", "\n")),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+                Err(err) => return Err(err),
+            };
+            if expected.as_deref() == Some(actual.as_str()) {
+                report.passed.push(path);
+            } else {
+                report.failures.push(CorpusFailure {
+                    path,
+                    expected,
+                    actual,
+                });
+            }
+        }
+        Ok(report)
+    }
+
+    /// Runs the corpus like [`run`](Corpus::run).
+    ///
+    /// # Panics
+    /// Panics with the report if an output does not match, or if the corpus
+    /// cannot be read.
+    pub fn assert<F>(&self, parse: F)
+    where
+        F: FnMut(&SourceFile) -> (String, Vec<Diagnostic>),
+    {
+        match self.run(parse) {
+            Ok(report) if report.is_ok() => {}
+            Ok(report) => panic!("{}", report),
+            Err(err) => panic!("cannot run corpus {}: {}", self.dir.display(), err),
+        }
+    }
+}
+
+/// Renders a tree dump and diagnostics as an expected output: the dump,
+/// followed by the diagnostics, if any.
+fn render(tree: &str, diagnostics: &[Diagnostic], file: &SourceFile) -> String {
+    let mut out = tree.to_string();
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push('\n');
+    }
+    if !diagnostics.is_empty() {
+        out.push_str("\n--- diagnostics ---\n");
+        out.push_str(&snapshot(diagnostics, file));
+    }
+    out
+}
+
+/// An input whose output does not match the expected one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CorpusFailure {
+    pub path: PathBuf,
+    /// The expected output, or `None` if there is no expected file.
+    pub expected: Option<String>,
+    pub actual: String,
+}
+
+impl fmt::Display for CorpusFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(expected) = &self.expected else {
+            writeln!(
+                f,
+                "{}: no expected output, actual output:",
+                self.path.display()
+            )?;
+            return write!(f, "{}", self.actual);
+        };
+        writeln!(f, "{}: output differs from expected", self.path.display())?;
+        let expected: Vec<_> = expected.lines().collect();
+        let actual: Vec<_> = self.actual.lines().collect();
+        for i in 0..expected.len().max(actual.len()) {
+            match (expected.get(i), actual.get(i)) {
+                (Some(e), Some(a)) if e == a => writeln!(f, " {}", e)?,
+                (e, a) => {
+                    if let Some(e) = e {
+                        writeln!(f, "-{}", e)?;
+                    }
+                    if let Some(a) = a {
+                        writeln!(f, "+{}", a)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The result of running a [`Corpus`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CorpusReport {
+    pub passed: Vec<PathBuf>,
+    /// The inputs whose expected outputs were written in bless mode.
+    pub blessed: Vec<PathBuf>,
+    pub failures: Vec<CorpusFailure>,
+}
+
+impl CorpusReport {
+    /// Returns whether every output matched or was blessed.
+    pub fn is_ok(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+impl fmt::Display for CorpusReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for failure in &self.failures {
+            writeln!(f, "{}", failure)?;
+        }
+        write!(
+            f,
+            "{} passed, {} blessed, {} failed",
+            self.passed.len(),
+            self.blessed.len(),
+            self.failures.len()
+        )?;
+        if !self.failures.is_empty() {
+            write!(f, "; set {}=1 to update the expected outputs", BLESS_VAR)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::position::Span;
+
+    fn corpus_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "grammarsmith-corpus-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        dir
+    }
+
+    fn parse(file: &SourceFile) -> (String, Vec<Diagnostic>) {
+        let diagnostics = file
+            .source()
+            .find('!')
+            .map(|i| Diagnostic::error("bang").with_label(Span::new(i, i + 1).unwrap(), ""))
+            .into_iter()
+            .collect();
+        (format!("(Text {:?})", file.source()), diagnostics)
+    }
+
+    #[test]
+    fn test_bless_then_compare() {
+        let dir = corpus_dir("bless");
+        fs::write(dir.join("a.lang"), "a").unwrap();
+        fs::write(dir.join("nested/b.lang"), "b!").unwrap();
+        let corpus = Corpus::new(&dir).with_extension("lang");
+
+        let report = corpus.clone().with_bless(true).run(parse).unwrap();
+        assert_eq!(report.blessed.len(), 2);
+        assert_eq!(
+            fs::read_to_string(dir.join("nested/b.expected")).unwrap(),
+            "(Text \"b!\")\n\n--- diagnostics ---\nerror: bang\n \
+             --> nested/b.lang:1:2\n  |\n1 | b!\n  |  ^\n"
+        );
+
+        let report = corpus.with_bless(false).run(parse).unwrap();
+        assert!(report.is_ok());
+        assert_eq!(report.passed.len(), 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_failures() {
+        let dir = corpus_dir("failures");
+        fs::write(dir.join("a.lang"), "a").unwrap();
+        fs::write(dir.join("a.expected"), "(Text \"b\")\r\n").unwrap();
+        fs::write(dir.join("nested/c.lang"), "c").unwrap();
+
+        let report = Corpus::new(&dir).with_bless(false).run(parse).unwrap();
+        assert_eq!(report.failures.len(), 2);
+        assert_eq!(
+            report.failures[0].to_string(),
+            format!(
+                "{}: output differs from expected\n-(Text \"b\")\n+(Text \"a\")\n",
+                dir.join("a.lang").display()
+            )
+        );
+        assert_eq!(report.failures[1].expected, None);
+        assert!(report.to_string().ends_with(
+            "0 passed, 0 blessed, 2 failed; set GRAMMARSMITH_BLESS=1 to update the expected outputs"
+        ));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
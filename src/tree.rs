@@ -0,0 +1,262 @@
+//! Generic syntax trees, and a canonical text format for them.
+//!
+//! [`ParseTree`] is a concrete syntax tree built from parser [`Event`]s,
+//! with a kind and span per node and no other data. Any tree, including a
+//! user AST, can implement [`TreeNode`] to be dumped with [`dump_tree`],
+//! which is meant for snapshot tests of parsers, or shown as an outline
+//! with [`PrettyTree`] while developing a grammar. [`SyntaxTree`] is a
+//! lossless tree instead, keeping trivia attached to its tokens, with
+//! parent and sibling navigation and a [`SyntaxCursor`]. Two versions of
+//! one are compared with [`diff_trees`].
+//!
+//! # Examples
+//! ```
+//! use grammarsmith::event::Events;
+//! use grammarsmith::position::Span;
+//! use grammarsmith::tree::*;
+//!
+//! #[derive(Debug, Clone)]
+//! enum Kind {
+//!     Sum,
+//!     Number,
+//!     Plus,
+//! }
+//!
+//! let source = "1+2";
+//! let mut events = Events::new();
+//! events.start_node(Kind::Sum);
+//! events.token(Kind::Number, Span::new(0, 1).unwrap());
+//! events.token(Kind::Plus, Span::new(1, 2).unwrap());
+//! events.token(Kind::Number, Span::new(2, 3).unwrap());
+//! events.finish_node();
+//!
+//! let trees = ParseTree::from_events(events.as_slice());
+//! assert_eq!(
+//!     dump_tree(&trees[0], source),
+//!     "(Sum 0..3\n  (Number 0..1 \"1\")\n  (Plus 1..2 \"+\")\n  (Number 2..3 \"2\"))\n"
+//! );
+//! ```
+
+pub mod diff;
+pub mod dump;
+pub mod print;
+pub mod syntax;
+
+pub use diff::*;
+pub use dump::*;
+pub use print::*;
+pub use syntax::*;
+
+use crate::event::Event;
+use crate::position::{GetSpan, Span};
+use std::borrow::Cow;
+use std::fmt;
+
+/// A node of a tree that can be inspected generically, such as by
+/// [`dump_tree`].
+///
+/// Children are returned as trait objects, so the nodes of an AST with a
+/// type per node can implement it.
+pub trait TreeNode: GetSpan {
+    /// Returns the kind or rule name of the node.
+    fn name(&self) -> Cow<'_, str>;
+
+    /// Returns the children of the node, in source order.
+    fn children(&self) -> Vec<&dyn TreeNode> {
+        Vec::new()
+    }
+
+    /// Returns the text of the node if it is a token. Nodes that do not
+    /// store their text can take it from `source`, the text their spans
+    /// refer to.
+    fn token_text<'a>(&'a self, source: &'a str) -> Option<&'a str> {
+        let _ = source;
+        None
+    }
+}
+
+/// A concrete syntax tree with a kind per node.
+///
+/// As a [`TreeNode`], a node is named by the `Debug` form of its kind.
+///
+/// # Serialization
+/// With the `serde` feature, a tree is an object tagged with its `type`,
+/// `node` or `token`, with byte offset spans:
+///
+/// ```json
+/// {
+///   "type": "node",
+///   "kind": "Sum",
+///   "span": { "start": 0, "end": 1 },
+///   "children": [{ "type": "token", "kind": "Number", "span": { "start": 0, "end": 1 } }]
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "type", rename_all = "lowercase")
+)]
+pub enum ParseTree<K> {
+    /// An inner node, spanning its children.
+    Node {
+        kind: K,
+        span: Span,
+        children: Vec<ParseTree<K>>,
+    },
+    /// A token.
+    Token { kind: K, span: Span },
+}
+
+impl<K> ParseTree<K> {
+    pub fn kind(&self) -> &K {
+        match self {
+            ParseTree::Node { kind, .. } | ParseTree::Token { kind, .. } => kind,
+        }
+    }
+
+    /// Returns the children of a node, or nothing for a token.
+    pub fn children(&self) -> &[ParseTree<K>] {
+        match self {
+            ParseTree::Node { children, .. } => children,
+            ParseTree::Token { .. } => &[],
+        }
+    }
+
+    pub fn is_token(&self) -> bool {
+        matches!(self, ParseTree::Token { .. })
+    }
+}
+
+impl<K: Clone> ParseTree<K> {
+    /// Builds the trees described by `events`, usually a single root.
+    ///
+    /// A node spans its children; a node without tokens is empty, and
+    /// placed after the preceding token. Nodes left open at the end of the
+    /// events are finished there.
+    ///
+    /// # Panics
+    /// Panics if a [`FinishNode`](Event::FinishNode) has no node to finish.
+    pub fn from_events(events: &[Event<K>]) -> Vec<ParseTree<K>> {
+        let mut stack: Vec<(K, Vec<ParseTree<K>>, usize)> = Vec::new();
+        let mut roots = Vec::new();
+        let mut pos = 0;
+        let finish = |(kind, children, start): (K, Vec<ParseTree<K>>, usize), pos: usize| {
+            let span = match (children.first(), children.last()) {
+                (Some(first), Some(last)) => first.get_span().union(&last.get_span()),
+                _ => Span::new_unchecked(start, pos),
+            };
+            ParseTree::Node {
+                kind,
+                span,
+                children,
+            }
+        };
+
+        for event in events {
+            let tree = match event {
+                Event::StartNode(kind) => {
+                    stack.push((kind.clone(), Vec::new(), pos));
+                    continue;
+                }
+                Event::Token { kind, span } => {
+                    pos = span.end();
+                    ParseTree::Token {
+                        kind: kind.clone(),
+                        span: *span,
+                    }
+                }
+                Event::FinishNode => finish(stack.pop().expect("no node to finish"), pos),
+            };
+            match stack.last_mut() {
+                Some((_, children, _)) => children.push(tree),
+                None => roots.push(tree),
+            }
+        }
+        while let Some(node) = stack.pop() {
+            let tree = finish(node, pos);
+            match stack.last_mut() {
+                Some((_, children, _)) => children.push(tree),
+                None => roots.push(tree),
+            }
+        }
+        roots
+    }
+}
+
+impl<K> GetSpan for ParseTree<K> {
+    fn get_span(&self) -> Span {
+        match self {
+            ParseTree::Node { span, .. } | ParseTree::Token { span, .. } => *span,
+        }
+    }
+}
+
+impl<K: fmt::Debug> TreeNode for ParseTree<K> {
+    fn name(&self) -> Cow<'_, str> {
+        Cow::Owned(format!("{:?}", self.kind()))
+    }
+
+    fn children(&self) -> Vec<&dyn TreeNode> {
+        ParseTree::children(self)
+            .iter()
+            .map(|child| child as &dyn TreeNode)
+            .collect()
+    }
+
+    fn token_text<'a>(&'a self, source: &'a str) -> Option<&'a str> {
+        let span = self.get_span();
+        self.is_token()
+            .then(|| source.get(span.start()..span.end()))
+            .flatten()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::Events;
+
+    #[test]
+    fn test_empty_node_spans() {
+        let mut events = Events::new();
+        events.start_node('a');
+        events.token('x', Span::new(1, 3).unwrap());
+        events.start_node('b');
+        events.finish_node();
+        let trees = ParseTree::from_events(events.as_slice());
+        assert_eq!(trees.len(), 1);
+        assert_eq!(trees[0].get_span(), Span::new(1, 3).unwrap());
+        assert_eq!(trees[0].children()[1].get_span(), Span::point(3));
+    }
+
+    #[test]
+    #[should_panic(expected = "no node to finish")]
+    fn test_unbalanced() {
+        ParseTree::from_events(&[Event::<u8>::FinishNode]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_schema() {
+        let tree = ParseTree::Node {
+            kind: "Sum".to_string(),
+            span: Span::new(0, 1).unwrap(),
+            children: vec![ParseTree::Token {
+                kind: "Number".to_string(),
+                span: Span::new(0, 1).unwrap(),
+            }],
+        };
+        let json = serde_json::json!({
+            "type": "node",
+            "kind": "Sum",
+            "span": { "start": 0, "end": 1 },
+            "children": [{ "type": "token", "kind": "Number", "span": { "start": 0, "end": 1 } }]
+        });
+        assert_eq!(serde_json::to_value(&tree).unwrap(), json);
+        assert_eq!(
+            serde_json::from_value::<ParseTree<String>>(json).unwrap(),
+            tree
+        );
+    }
+}
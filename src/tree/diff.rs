@@ -0,0 +1,402 @@
+use super::{SyntaxElement, SyntaxTree};
+use crate::position::Span;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+/// A difference between two versions of a [`SyntaxTree`], found by
+/// [`diff_trees`].
+///
+/// Changes are reported for the topmost element they apply to: the
+/// descendants of an inserted, removed, or moved node are not reported
+/// separately.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TreeChange<K> {
+    /// A node or token of the new tree matching nothing in the old one.
+    Inserted { kind: K, new: Span },
+    /// A node or token of the old tree matching nothing in the new one.
+    Removed { kind: K, old: Span },
+    /// A node or token found in both trees under a different parent, or
+    /// out of order among its siblings.
+    Moved { kind: K, old: Span, new: Span },
+    /// A token found at the same place in both trees, with a new text.
+    Updated { kind: K, old: Span, new: Span },
+}
+
+/// Compares two versions of a tree, such as before and after an edit, and
+/// returns the removed elements in the order of the old tree followed by
+/// the other changes in the order of the new tree.
+///
+/// Nodes are first matched to identical nodes, with the same kinds and
+/// token texts, anywhere in the old tree, then the remaining children of
+/// matched nodes are matched in order by kind, between the same matched
+/// siblings. Elements whose parents are
+/// matched to each other, in the same order, are unchanged; others are
+/// moved, and unmatched ones inserted or removed. Trivia are not compared.
+///
+/// Trees built from event streams compare the same way, once built with
+/// [`SyntaxTree::new`].
+///
+/// # Examples
+/// ```
+/// use grammarsmith::event::Event;
+/// use grammarsmith::position::Span;
+/// use grammarsmith::tree::*;
+///
+/// fn list(source: &str) -> SyntaxTree<&'static str> {
+///     let mut events = vec![Event::StartNode("list")];
+///     for (i, _) in source.char_indices() {
+///         events.push(Event::StartNode("item"));
+///         events.push(Event::Token { kind: "id", span: Span::new(i, i + 1).unwrap() });
+///         events.push(Event::FinishNode);
+///     }
+///     events.push(Event::FinishNode);
+///     SyntaxTree::new(source, &events, |_| false)
+/// }
+///
+/// let changes = diff_trees(&list("abc"), &list("xab"));
+/// assert_eq!(
+///     changes,
+///     [
+///         TreeChange::Removed { kind: "item", old: Span::new(2, 3).unwrap() },
+///         TreeChange::Inserted { kind: "item", new: Span::new(0, 1).unwrap() },
+///     ]
+/// );
+/// ```
+pub fn diff_trees<K: Clone + Eq + Hash>(
+    old: &SyntaxTree<K>,
+    new: &SyntaxTree<K>,
+) -> Vec<TreeChange<K>> {
+    let old = Flat::new(old);
+    let new = Flat::new(new);
+    let mut matching = Matching {
+        old_to_new: vec![None; old.elements.len()],
+        new_to_old: vec![None; new.elements.len()],
+    };
+    matching.match_identical(&old, &new);
+    matching.match_children(&old, &new, &old.roots, &new.roots);
+    for n in 0..new.elements.len() {
+        if let Some(o) = matching.new_to_old[n] {
+            matching.match_children(&old, &new, &old.children[o], &new.children[n]);
+        }
+    }
+
+    let mut changes = Vec::new();
+    for (o, element) in old.elements.iter().enumerate() {
+        let parent_matched = old.parents[o].is_none_or(|p| matching.old_to_new[p].is_some());
+        if matching.old_to_new[o].is_none() && parent_matched {
+            changes.push(TreeChange::Removed {
+                kind: element.kind().clone(),
+                old: element.span(),
+            });
+        }
+    }
+    let moved = matching.moved(&old, &new);
+    for (n, element) in new.elements.iter().enumerate() {
+        let kind = element.kind().clone();
+        match matching.new_to_old[n] {
+            None if new.parents[n].is_none_or(|p| matching.new_to_old[p].is_some()) => changes
+                .push(TreeChange::Inserted {
+                    kind,
+                    new: element.span(),
+                }),
+            None => {}
+            Some(o) => {
+                let old_element = old.elements[o];
+                let (old, new) = (old_element.span(), element.span());
+                if moved[n] {
+                    changes.push(TreeChange::Moved { kind, old, new });
+                } else if text(&old_element) != text(element) {
+                    changes.push(TreeChange::Updated { kind, old, new });
+                }
+            }
+        }
+    }
+    changes
+}
+
+/// The elements of a tree in preorder, with their relations by index.
+struct Flat<'a, K> {
+    elements: Vec<SyntaxElement<'a, K>>,
+    parents: Vec<Option<usize>>,
+    children: Vec<Vec<usize>>,
+    /// The index after the last descendant of each element.
+    ends: Vec<usize>,
+    hashes: Vec<u64>,
+    roots: Vec<usize>,
+}
+
+impl<'a, K: Hash> Flat<'a, K> {
+    fn new(tree: &'a SyntaxTree<K>) -> Self {
+        let mut flat = Flat {
+            elements: Vec::new(),
+            parents: Vec::new(),
+            children: Vec::new(),
+            ends: Vec::new(),
+            hashes: Vec::new(),
+            roots: Vec::new(),
+        };
+        for root in tree.roots() {
+            let id = flat.visit(root, None);
+            flat.roots.push(id);
+        }
+        flat
+    }
+
+    fn visit(&mut self, element: SyntaxElement<'a, K>, parent: Option<usize>) -> usize {
+        let id = self.elements.len();
+        self.elements.push(element);
+        self.parents.push(parent);
+        self.children.push(Vec::new());
+        self.ends.push(id + 1);
+        self.hashes.push(0);
+
+        let mut hasher = DefaultHasher::new();
+        element.kind().hash(&mut hasher);
+        match element {
+            SyntaxElement::Node(node) => {
+                for child in node.children() {
+                    let child = self.visit(child, Some(id));
+                    self.children[id].push(child);
+                    self.hashes[child].hash(&mut hasher);
+                }
+            }
+            SyntaxElement::Token(token) => token.text().hash(&mut hasher),
+        }
+        self.ends[id] = self.elements.len();
+        self.hashes[id] = hasher.finish();
+        id
+    }
+}
+
+struct Matching {
+    old_to_new: Vec<Option<usize>>,
+    new_to_old: Vec<Option<usize>>,
+}
+
+impl Matching {
+    fn link(&mut self, o: usize, n: usize) {
+        self.old_to_new[o] = Some(n);
+        self.new_to_old[n] = Some(o);
+    }
+
+    /// Matches the nodes of `new` to identical unmatched nodes of `old`,
+    /// with all their descendants, preferring the first after the last
+    /// match to keep the order.
+    fn match_identical<K: Eq>(&mut self, old: &Flat<'_, K>, new: &Flat<'_, K>) {
+        let mut by_hash: HashMap<u64, Vec<usize>> = HashMap::new();
+        for (o, element) in old.elements.iter().enumerate() {
+            if let SyntaxElement::Node(_) = element {
+                by_hash.entry(old.hashes[o]).or_default().push(o);
+            }
+        }
+        let mut last = 0;
+        let mut n = 0;
+        while n < new.elements.len() {
+            let candidates = match new.elements[n] {
+                SyntaxElement::Node(_) => by_hash.get(&new.hashes[n]),
+                SyntaxElement::Token(_) => None,
+            };
+            let mut candidates = candidates.into_iter().flatten().copied().filter(|&o| {
+                self.old_to_new[o..old.ends[o]].iter().all(Option::is_none)
+                    && identical(old, o, new, n)
+            });
+            let first = candidates.next();
+            let found = first
+                .into_iter()
+                .chain(candidates)
+                .find(|&o| o >= last)
+                .or(first);
+            match found {
+                Some(o) => {
+                    for offset in 0..old.ends[o] - o {
+                        self.link(o + offset, n + offset);
+                    }
+                    last = old.ends[o];
+                    n = new.ends[n];
+                }
+                None => n += 1,
+            }
+        }
+    }
+
+    /// Matches the unmatched elements among `olds` and `news`, siblings in
+    /// each tree, in order by kind, between the same matched siblings.
+    fn match_children<K: Eq>(
+        &mut self,
+        old: &Flat<'_, K>,
+        new: &Flat<'_, K>,
+        olds: &[usize],
+        news: &[usize],
+    ) {
+        let positions: HashMap<usize, usize> =
+            olds.iter().enumerate().map(|(i, &o)| (o, i)).collect();
+        let mut next = 0;
+        for &n in news {
+            if let Some(o) = self.new_to_old[n] {
+                if let Some(&i) = positions.get(&o) {
+                    next = next.max(i + 1);
+                }
+                continue;
+            }
+            let found = olds[next..]
+                .iter()
+                .take_while(|&&o| self.old_to_new[o].is_none())
+                .position(|&o| {
+                    old.elements[o].kind() == new.elements[n].kind()
+                        && is_token(&old.elements[o]) == is_token(&new.elements[n])
+                });
+            if let Some(i) = found {
+                self.link(olds[next + i], n);
+                next += i + 1;
+            }
+        }
+    }
+
+    /// Returns, for each element of `new`, whether it is matched to an
+    /// element under a parent its own parent is not matched to, or out of
+    /// order with its siblings, which are kept in order as many as
+    /// possible.
+    fn moved<K>(&self, old: &Flat<'_, K>, new: &Flat<'_, K>) -> Vec<bool> {
+        let mut moved = vec![false; new.elements.len()];
+        let parents = std::iter::once((None, &new.roots)).chain(
+            new.children
+                .iter()
+                .enumerate()
+                .map(|(n, children)| (Some(n), children)),
+        );
+        for (parent, children) in parents {
+            let old_parent = parent.map(|n| self.new_to_old[n]);
+            let mut kept = Vec::new();
+            for &n in children {
+                let Some(o) = self.new_to_old[n] else {
+                    continue;
+                };
+                match old_parent {
+                    Some(old_parent) if old.parents[o] != old_parent || old_parent.is_none() => {
+                        moved[n] = true;
+                    }
+                    None if old.parents[o].is_some() => moved[n] = true,
+                    _ => kept.push((o, n)),
+                }
+            }
+            let in_order = longest_increasing(&kept);
+            for (i, &(_, n)) in kept.iter().enumerate() {
+                moved[n] = !in_order[i];
+            }
+        }
+        moved
+    }
+}
+
+/// Returns which pairs are part of a longest run of pairs increasing in
+/// their first index.
+fn longest_increasing(pairs: &[(usize, usize)]) -> Vec<bool> {
+    // The index of the pair ending the best run of each length, and the
+    // previous pair of each pair in its run.
+    let mut tails: Vec<usize> = Vec::new();
+    let mut previous = vec![None; pairs.len()];
+    for (i, &(o, _)) in pairs.iter().enumerate() {
+        let len = tails.partition_point(|&tail| pairs[tail].0 < o);
+        previous[i] = len.checked_sub(1).map(|len| tails[len]);
+        match tails.get_mut(len) {
+            Some(tail) => *tail = i,
+            None => tails.push(i),
+        }
+    }
+    let mut in_run = vec![false; pairs.len()];
+    let mut current = tails.last().copied();
+    while let Some(i) = current {
+        in_run[i] = true;
+        current = previous[i];
+    }
+    in_run
+}
+
+/// Returns whether the subtrees at `o` and `n` have the same kinds and
+/// token texts.
+fn identical<K: Eq>(old: &Flat<'_, K>, o: usize, new: &Flat<'_, K>, n: usize) -> bool {
+    old.ends[o] - o == new.ends[n] - n
+        && (0..old.ends[o] - o).all(|offset| {
+            let (a, b) = (&old.elements[o + offset], &new.elements[n + offset]);
+            a.kind() == b.kind()
+                && text(a) == text(b)
+                && old.children[o + offset].len() == new.children[n + offset].len()
+        })
+}
+
+fn is_token<K>(element: &SyntaxElement<'_, K>) -> bool {
+    matches!(element, SyntaxElement::Token(_))
+}
+
+fn text<'a, K>(element: &SyntaxElement<'a, K>) -> Option<&'a str> {
+    match element {
+        SyntaxElement::Node(_) => None,
+        SyntaxElement::Token(token) => Some(token.text()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::Events;
+
+    /// Builds a block of statements from lines of `name = value`.
+    fn block(source: &str) -> SyntaxTree<&'static str> {
+        let mut events = Events::new();
+        events.start_node("block");
+        let mut offset = 0;
+        for line in source.split_inclusive('\n') {
+            events.start_node("stmt");
+            for (i, word) in line.split(' ').enumerate() {
+                let word = word.trim_end();
+                let kind = ["name", "=", "value"][i];
+                events.token(kind, Span::new(offset, offset + word.len()).unwrap());
+                offset += word.len();
+                events.token("space", Span::new(offset, offset + 1).unwrap());
+                offset += 1;
+            }
+            events.finish_node();
+        }
+        events.finish_node();
+        SyntaxTree::new(source, events.as_slice(), |kind| *kind == "space")
+    }
+
+    fn span(start: usize, end: usize) -> Span {
+        Span::new(start, end).unwrap()
+    }
+
+    #[test]
+    fn test_unchanged_and_updated() {
+        let old = block("a = 1\nb = 2\n");
+        assert!(diff_trees(&old, &old).is_empty());
+        assert_eq!(
+            diff_trees(&old, &block("a = 1\nb = 42\n")),
+            [TreeChange::Updated {
+                kind: "value",
+                old: span(10, 11),
+                new: span(10, 12),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_moved() {
+        let old = block("a = 1\nb = 2\nc = 3\n");
+        let new = block("c = 3\na = 1\nb = 2\nd = 4\n");
+        assert_eq!(
+            diff_trees(&old, &new),
+            [
+                TreeChange::Moved {
+                    kind: "stmt",
+                    old: span(12, 17),
+                    new: span(0, 5),
+                },
+                TreeChange::Inserted {
+                    kind: "stmt",
+                    new: span(18, 23),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,166 @@
+use super::TreeNode;
+use std::fmt::Write;
+
+/// Writes a tree in a canonical S-expression format, for snapshot tests.
+///
+/// Each node takes a line, indented by two spaces per level, with its name,
+/// its span as `start..end`, and for tokens their quoted text. Closing
+/// parentheses follow the last child, so a change to a node only changes
+/// its own line.
+///
+/// The text of tokens is escaped independently of `Debug`, so snapshots do
+/// not change between Rust versions: backslashes, quotes, and control
+/// characters are escaped, and everything else is written as is.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::position::{GetSpan, Span};
+/// use grammarsmith::tree::*;
+/// use std::borrow::Cow;
+///
+/// enum Expr {
+///     Name(Span),
+///     Call(Span, Box<Expr>, Vec<Expr>),
+/// }
+///
+/// impl GetSpan for Expr {
+///     fn get_span(&self) -> Span {
+///         match self {
+///             Expr::Name(span) | Expr::Call(span, ..) => *span,
+///         }
+///     }
+/// }
+///
+/// impl TreeNode for Expr {
+///     fn name(&self) -> Cow<'_, str> {
+///         match self {
+///             Expr::Name(_) => "Name".into(),
+///             Expr::Call(..) => "Call".into(),
+///         }
+///     }
+///
+///     fn children(&self) -> Vec<&dyn TreeNode> {
+///         match self {
+///             Expr::Name(_) => Vec::new(),
+///             Expr::Call(_, callee, args) => std::iter::once(&**callee)
+///                 .chain(args)
+///                 .map(|expr| expr as &dyn TreeNode)
+///                 .collect(),
+///         }
+///     }
+///
+///     fn token_text<'a>(&'a self, source: &'a str) -> Option<&'a str> {
+///         match self {
+///             Expr::Name(span) => Some(&source[span.start()..span.end()]),
+///             Expr::Call(..) => None,
+///         }
+///     }
+/// }
+///
+/// let source = "f(x)";
+/// let call = Expr::Call(
+///     Span::new(0, 4).unwrap(),
+///     Box::new(Expr::Name(Span::new(0, 1).unwrap())),
+///     vec![Expr::Name(Span::new(2, 3).unwrap())],
+/// );
+/// assert_eq!(
+///     dump_tree(&call, source),
+///     "(Call 0..4\n  (Name 0..1 \"f\")\n  (Name 2..3 \"x\"))\n"
+/// );
+/// ```
+pub fn dump_tree(node: &dyn TreeNode, source: &str) -> String {
+    let mut out = String::new();
+    dump_node(&mut out, node, source, 0);
+    out.push('\n');
+    out
+}
+
+/// Writes several trees, such as the roots of a parse, with
+/// [`dump_tree`], one after the other.
+pub fn dump_trees<'n, N>(roots: impl IntoIterator<Item = &'n N>, source: &str) -> String
+where
+    N: TreeNode + 'n,
+{
+    roots
+        .into_iter()
+        .map(|root| dump_tree(root, source))
+        .collect()
+}
+
+fn dump_node(out: &mut String, node: &dyn TreeNode, source: &str, depth: usize) {
+    let span = node.get_span();
+    let _ = write!(
+        out,
+        "{:indent$}({} {}..{}",
+        "",
+        node.name(),
+        span.start(),
+        span.end(),
+        indent = depth * 2
+    );
+    if let Some(text) = node.token_text(source) {
+        out.push(' ');
+        write_quoted(out, text);
+    }
+    for child in node.children() {
+        out.push('\n');
+        dump_node(out, child, source, depth + 1);
+    }
+    out.push(')');
+}
+
+pub(super) fn write_quoted(out: &mut String, text: &str) {
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{{{:x}}}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::Event;
+    use crate::position::Span;
+    use crate::tree::ParseTree;
+
+    #[test]
+    fn test_nested_and_empty() {
+        let events = [
+            Event::StartNode("Block"),
+            Event::Token {
+                kind: "LBrace",
+                span: Span::new(0, 1).unwrap(),
+            },
+            Event::StartNode("Stmts"),
+            Event::FinishNode,
+            Event::Token {
+                kind: "RBrace",
+                span: Span::new(2, 3).unwrap(),
+            },
+            Event::FinishNode,
+        ];
+        let trees = ParseTree::from_events(&events);
+        assert_eq!(
+            dump_trees(&trees, "{ }"),
+            "(\"Block\" 0..3\n  (\"LBrace\" 0..1 \"{\")\n  (\"Stmts\" 1..1)\n  (\"RBrace\" 2..3 \"}\"))\n"
+        );
+    }
+
+    #[test]
+    fn test_escaping() {
+        let mut out = String::new();
+        write_quoted(&mut out, "a\"\\\n\u{7}é");
+        assert_eq!(out, r#""a\"\\\n\u{7}é""#);
+    }
+}
@@ -0,0 +1,176 @@
+use super::dump::write_quoted;
+use super::TreeNode;
+use crate::position::ColumnMode;
+use crate::source::SourceFile;
+use std::fmt::{self, Write};
+
+/// Displays a tree as an indented outline, for inspecting trees during
+/// grammar development, such as with `dbg!`.
+///
+/// Each node takes a line with its name, its span, its start and end as
+/// `line:column`, and for tokens their quoted text. Unlike [`dump_tree`],
+/// the format is meant for humans and may change; use `dump_tree` for
+/// snapshots.
+///
+/// [`dump_tree`]: super::dump_tree
+///
+/// # Examples
+/// ```
+/// use grammarsmith::event::Event;
+/// use grammarsmith::position::Span;
+/// use grammarsmith::source::SourceFile;
+/// use grammarsmith::tree::*;
+///
+/// #[derive(Debug, Clone)]
+/// enum Kind {
+///     Call,
+///     Name,
+/// }
+///
+/// let file = SourceFile::new("main", "f\n(x)");
+/// let trees = ParseTree::from_events(&[
+///     Event::StartNode(Kind::Call),
+///     Event::Token { kind: Kind::Name, span: Span::new(0, 1).unwrap() },
+///     Event::StartNode(Kind::Call),
+///     Event::Token { kind: Kind::Name, span: Span::new(3, 4).unwrap() },
+///     Event::FinishNode,
+///     Event::FinishNode,
+/// ]);
+///
+/// assert_eq!(
+///     PrettyTree::new(&trees[0], &file).to_string(),
+///     "Call 0..4 1:1-2:3\n\
+///      ├─ Name 0..1 1:1-1:2 \"f\"\n\
+///      └─ Call 3..4 2:2-2:3\n   \
+///         └─ Name 3..4 2:2-2:3 \"x\"\n"
+/// );
+/// ```
+#[derive(Clone, Copy)]
+pub struct PrettyTree<'a> {
+    node: &'a dyn TreeNode,
+    file: &'a SourceFile,
+    column_mode: ColumnMode,
+}
+
+impl<'a> PrettyTree<'a> {
+    /// Creates a printer for `node`, whose spans refer to `file`.
+    pub fn new(node: &'a dyn TreeNode, file: &'a SourceFile) -> Self {
+        PrettyTree {
+            node,
+            file,
+            column_mode: ColumnMode::default(),
+        }
+    }
+
+    /// Sets the unit columns are counted in, characters by default.
+    pub fn with_column_mode(mut self, column_mode: ColumnMode) -> Self {
+        self.column_mode = column_mode;
+        self
+    }
+
+    fn write_node(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        node: &dyn TreeNode,
+        prefix: &mut String,
+    ) -> fmt::Result {
+        let span = node.get_span();
+        let (start_line, start_column) = self.file.line_column(span.start, self.column_mode);
+        let (end_line, end_column) = self.file.line_column(span.end, self.column_mode);
+        write!(
+            f,
+            "{} {}..{} {}:{}-{}:{}",
+            node.name(),
+            span.start(),
+            span.end(),
+            start_line,
+            start_column,
+            end_line,
+            end_column
+        )?;
+        if let Some(text) = node.token_text(self.file.source()) {
+            let mut quoted = String::new();
+            write_quoted(&mut quoted, text);
+            write!(f, " {}", quoted)?;
+        }
+        f.write_char('\n')?;
+
+        let children = node.children();
+        for (i, child) in children.iter().enumerate() {
+            let last = i + 1 == children.len();
+            write!(f, "{}{}", prefix, if last { "└─ " } else { "├─ " })?;
+            let len = prefix.len();
+            prefix.push_str(if last { "   " } else { "│  " });
+            self.write_node(f, *child, prefix)?;
+            prefix.truncate(len);
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for PrettyTree<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_node(f, self.node, &mut String::new())
+    }
+}
+
+/// Shows the same outline as `Display`, so the tree reads well in `dbg!`
+/// and assertion messages.
+impl fmt::Debug for PrettyTree<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+/// Returns the outline of `node` shown by [`PrettyTree`].
+pub fn print_tree(node: &dyn TreeNode, file: &SourceFile) -> String {
+    PrettyTree::new(node, file).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::Event;
+    use crate::position::Span;
+    use crate::tree::ParseTree;
+
+    #[test]
+    fn test_column_mode() {
+        let file = SourceFile::new("main", "é😀");
+        let tree = ParseTree::Token {
+            kind: 't',
+            span: Span::new(2, 6).unwrap(),
+        };
+        assert_eq!(print_tree(&tree, &file), "'t' 2..6 1:2-1:3 \"😀\"\n");
+        assert_eq!(
+            PrettyTree::new(&tree, &file)
+                .with_column_mode(ColumnMode::Utf16)
+                .to_string(),
+            "'t' 2..6 1:2-1:4 \"😀\"\n"
+        );
+    }
+
+    #[test]
+    fn test_guides() {
+        let file = SourceFile::new("main", "ab");
+        let token = |span| Event::Token {
+            kind: 't',
+            span: Span::new(span, span + 1).unwrap(),
+        };
+        let trees = ParseTree::from_events(&[
+            Event::StartNode('a'),
+            Event::StartNode('b'),
+            token(0),
+            Event::FinishNode,
+            token(1),
+            Event::FinishNode,
+        ]);
+        assert_eq!(
+            format!("{:?}", PrettyTree::new(&trees[0], &file)),
+            "'a' 0..2 1:1-1:3\n\
+             ├─ 'b' 0..1 1:1-1:2\n\
+             │  └─ 't' 0..1 1:1-1:2 \"a\"\n\
+             └─ 't' 1..2 1:2-1:3 \"b\"\n"
+        );
+    }
+}
@@ -0,0 +1,1208 @@
+use crate::event::Event;
+use crate::position::{GetSpan, Span};
+use std::cmp::Ordering;
+use std::fmt;
+
+/// A lossless syntax tree built from parser [`Event`]s, owning its source.
+///
+/// Unlike [`ParseTree`](super::ParseTree), trivia such as whitespace and
+/// comments are not children of nodes but attached to the tokens around
+/// them: a token's trailing trivia are those after it on the same line, and
+/// its leading trivia are the rest of those before it. Trivia after the last
+/// line with a token are kept by the tree, so the tree keeps every token of
+/// the source.
+///
+/// The tree is navigated through [`SyntaxNode`] and [`SyntaxToken`] handles,
+/// which know their parent and siblings, or with a [`SyntaxCursor`].
+///
+/// # Examples
+/// ```
+/// use grammarsmith::event::Events;
+/// use grammarsmith::position::Span;
+/// use grammarsmith::tree::*;
+///
+/// #[derive(Debug, Clone, PartialEq)]
+/// enum Kind {
+///     Sum,
+///     Number,
+///     Plus,
+///     Space,
+/// }
+///
+/// let source = "1 + 2";
+/// let mut events = Events::new();
+/// events.start_node(Kind::Sum);
+/// events.token(Kind::Number, Span::new(0, 1).unwrap());
+/// events.token(Kind::Space, Span::new(1, 2).unwrap());
+/// events.token(Kind::Plus, Span::new(2, 3).unwrap());
+/// events.token(Kind::Space, Span::new(3, 4).unwrap());
+/// events.token(Kind::Number, Span::new(4, 5).unwrap());
+/// events.finish_node();
+///
+/// let tree = SyntaxTree::new(source, events.as_slice(), |kind| *kind == Kind::Space);
+/// let sum = tree.root().unwrap();
+/// assert_eq!(sum.child_tokens().count(), 3);
+///
+/// let plus = sum.token_at_offset(2).right_biased().unwrap();
+/// assert_eq!(plus.text(), "+");
+/// assert_eq!(plus.full_text(), "+ ");
+/// assert_eq!(plus.prev_token().unwrap().full_text(), "1 ");
+/// assert_eq!(plus.parent().unwrap().kind(), &Kind::Sum);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxTree<K> {
+    source: String,
+    nodes: Vec<NodeData<K>>,
+    tokens: Vec<TokenData<K>>,
+    trivia: Vec<Trivia<K>>,
+    roots: Vec<Element>,
+    end_trivia: std::ops::Range<usize>,
+}
+
+/// A piece of trivia, such as whitespace or a comment, attached to a token.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Trivia<K> {
+    pub kind: K,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Element {
+    Node(usize),
+    Token(usize),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct NodeData<K> {
+    kind: K,
+    span: Span,
+    parent: Option<usize>,
+    index: usize,
+    children: Vec<Element>,
+    /// The tokens of the node, by their index in the tree.
+    tokens: std::ops::Range<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TokenData<K> {
+    kind: K,
+    span: Span,
+    parent: Option<usize>,
+    index: usize,
+    leading: std::ops::Range<usize>,
+    trailing: std::ops::Range<usize>,
+}
+
+impl<K: Clone> SyntaxTree<K> {
+    /// Builds the tree described by `events` over `source`, with the tokens
+    /// of the kinds for which `is_trivia` holds as trivia.
+    ///
+    /// Nodes are spanned as by [`ParseTree::from_events`], without their
+    /// trivia, and nodes left open at the end of the events are finished
+    /// there.
+    ///
+    /// The tree is built even if the tokens and trivia miss or repeat bytes
+    /// of the source; [`verify`](SyntaxTree::verify) checks that it keeps
+    /// every byte.
+    ///
+    /// # Panics
+    /// Panics if a [`FinishNode`](Event::FinishNode) has no node to finish.
+    ///
+    /// [`ParseTree::from_events`]: super::ParseTree::from_events
+    pub fn new(
+        source: impl Into<String>,
+        events: &[Event<K>],
+        is_trivia: impl Fn(&K) -> bool,
+    ) -> Self {
+        let mut tree = SyntaxTree {
+            source: source.into(),
+            nodes: Vec::new(),
+            tokens: Vec::new(),
+            trivia: Vec::new(),
+            roots: Vec::new(),
+            end_trivia: 0..0,
+        };
+        let mut stack: Vec<usize> = Vec::new();
+        let mut pos = 0;
+        // Trivia not yet attached, from this index on.
+        let mut pending = 0;
+
+        for event in events {
+            match event {
+                Event::StartNode(kind) => {
+                    let id = tree.nodes.len();
+                    tree.nodes.push(NodeData {
+                        kind: kind.clone(),
+                        span: Span::point(pos),
+                        parent: stack.last().copied(),
+                        index: 0,
+                        children: Vec::new(),
+                        tokens: tree.tokens.len()..tree.tokens.len(),
+                    });
+                    tree.push(stack.last().copied(), Element::Node(id));
+                    stack.push(id);
+                }
+                Event::Token { kind, span } if is_trivia(kind) => {
+                    tree.trivia.push(Trivia {
+                        kind: kind.clone(),
+                        span: *span,
+                    });
+                }
+                Event::Token { kind, span } => {
+                    let leading = tree.attach_trailing(pending);
+                    pending = tree.trivia.len();
+                    pos = span.end();
+                    let id = tree.tokens.len();
+                    tree.tokens.push(TokenData {
+                        kind: kind.clone(),
+                        span: *span,
+                        parent: stack.last().copied(),
+                        index: 0,
+                        leading: leading..pending,
+                        trailing: pending..pending,
+                    });
+                    tree.push(stack.last().copied(), Element::Token(id));
+                }
+                Event::FinishNode => {
+                    let id = stack.pop().expect("no node to finish");
+                    tree.finish(id, pos);
+                }
+            }
+        }
+        while let Some(id) = stack.pop() {
+            tree.finish(id, pos);
+        }
+        let end = tree.attach_trailing(pending);
+        tree.end_trivia = end..tree.trivia.len();
+        tree
+    }
+
+    /// Makes the trivia from `pending` on that are on the line of the last
+    /// token its trailing trivia, and returns the index of the first of the
+    /// others.
+    fn attach_trailing(&mut self, pending: usize) -> usize {
+        let Some(last) = self.tokens.last_mut() else {
+            return pending;
+        };
+        let source = &self.source;
+        let end = pending
+            + self.trivia[pending..]
+                .iter()
+                .take_while(|trivia| {
+                    source
+                        .get(trivia.span.start()..trivia.span.end())
+                        .is_some_and(|text| !text.contains('\n'))
+                })
+                .count();
+        last.trailing = pending..end;
+        end
+    }
+
+    fn push(&mut self, parent: Option<usize>, element: Element) {
+        let siblings = match parent {
+            Some(parent) => &mut self.nodes[parent].children,
+            None => &mut self.roots,
+        };
+        let index = siblings.len();
+        siblings.push(element);
+        match element {
+            Element::Node(id) => self.nodes[id].index = index,
+            Element::Token(id) => self.tokens[id].index = index,
+        }
+    }
+
+    fn finish(&mut self, id: usize, pos: usize) {
+        let span = |element: Option<&Element>| match element {
+            Some(Element::Node(id)) => Some(self.nodes[*id].span),
+            Some(Element::Token(id)) => Some(self.tokens[*id].span),
+            None => None,
+        };
+        let node = &self.nodes[id];
+        let span = match (span(node.children.first()), span(node.children.last())) {
+            (Some(first), Some(last)) => first.union(&last),
+            _ => Span::new_unchecked(node.span.start(), pos),
+        };
+        let tokens = self.tokens.len();
+        let node = &mut self.nodes[id];
+        node.span = span;
+        node.tokens.end = tokens;
+    }
+}
+
+impl<K> SyntaxTree<K> {
+    /// Returns the source the tree was built over.
+    pub fn text(&self) -> &str {
+        &self.source
+    }
+
+    /// Checks that the tokens and trivia of the tree, in order, concatenate
+    /// back to exactly its source, so that the tree is lossless.
+    ///
+    /// # Examples
+    /// ```
+    /// use grammarsmith::event::Event;
+    /// use grammarsmith::position::Span;
+    /// use grammarsmith::tree::*;
+    ///
+    /// let events = [
+    ///     Event::Token { kind: "a", span: Span::new(0, 1).unwrap() },
+    ///     Event::Token { kind: "ws", span: Span::new(1, 2).unwrap() },
+    ///     Event::Token { kind: "b", span: Span::new(2, 3).unwrap() },
+    /// ];
+    /// let tree = SyntaxTree::new("a b", &events, |kind| *kind == "ws");
+    /// assert_eq!(tree.verify(), Ok(()));
+    /// ```
+    pub fn verify(&self) -> Result<(), LosslessError> {
+        let trivia = |range: &std::ops::Range<usize>| {
+            self.trivia[range.clone()].iter().map(|trivia| trivia.span)
+        };
+        let pieces = self
+            .tokens
+            .iter()
+            .flat_map(|token| {
+                trivia(&token.leading)
+                    .chain(std::iter::once(token.span))
+                    .chain(trivia(&token.trailing))
+            })
+            .chain(trivia(&self.end_trivia));
+        let mut pos = 0;
+        for span in pieces {
+            if self.source.get(span.start()..span.end()).is_none() {
+                return Err(LosslessError::OutOfBounds(span));
+            }
+            match span.start().cmp(&pos) {
+                Ordering::Less => return Err(LosslessError::Overlap(span)),
+                Ordering::Greater => {
+                    return Err(LosslessError::Gap(Span::new_unchecked(pos, span.start())))
+                }
+                Ordering::Equal => pos = span.end(),
+            }
+        }
+        match pos < self.source.len() {
+            true => Err(LosslessError::Gap(Span::new_unchecked(
+                pos,
+                self.source.len(),
+            ))),
+            false => Ok(()),
+        }
+    }
+
+    /// Returns the first root node, usually the only one.
+    pub fn root(&self) -> Option<SyntaxNode<'_, K>> {
+        self.roots().find_map(SyntaxElement::into_node)
+    }
+
+    /// Returns the roots of the tree, which are siblings of each other.
+    pub fn roots(&self) -> impl Iterator<Item = SyntaxElement<'_, K>> + '_ {
+        self.roots.iter().map(|&element| self.element(element))
+    }
+
+    /// Returns the tokens of the tree, in source order.
+    pub fn tokens(&self) -> impl Iterator<Item = SyntaxToken<'_, K>> + '_ {
+        (0..self.tokens.len()).map(|id| SyntaxToken { tree: self, id })
+    }
+
+    /// Returns the tokens at `offset`, which is between two tokens if one
+    /// ends where the next starts.
+    pub fn token_at_offset(&self, offset: usize) -> TokenAtOffset<SyntaxToken<'_, K>> {
+        self.token_in(0..self.tokens.len(), offset)
+    }
+
+    /// Returns the deepest node containing `offset`, as by
+    /// [`SyntaxNode::node_at_offset`], among all the roots.
+    pub fn node_at_offset(&self, offset: usize) -> Option<SyntaxNode<'_, K>> {
+        self.roots()
+            .filter_map(SyntaxElement::into_node)
+            .find(|root| contains_offset(root.span(), offset))
+            .map(|root| root.node_at_offset(offset).unwrap_or(root))
+    }
+
+    /// Returns the smallest node covering `span`, as by
+    /// [`SyntaxNode::covering_node`], among all the roots.
+    pub fn covering_node(&self, span: Span) -> Option<SyntaxNode<'_, K>> {
+        self.roots()
+            .filter_map(SyntaxElement::into_node)
+            .find(|root| covers(root.span(), span))
+            .map(|root| root.covering_node(span).unwrap_or(root))
+    }
+
+    /// Returns the nodes containing `offset`, from the deepest to its root,
+    /// such as for expanding a selection.
+    pub fn ancestors_at_offset(&self, offset: usize) -> impl Iterator<Item = SyntaxNode<'_, K>> {
+        let node = self.node_at_offset(offset);
+        node.into_iter()
+            .chain(node.into_iter().flat_map(|node| node.ancestors()))
+    }
+
+    /// Returns the trivia after the line of the last token, or all of them
+    /// if there are no tokens.
+    pub fn end_trivia(&self) -> &[Trivia<K>] {
+        &self.trivia[self.end_trivia.clone()]
+    }
+
+    fn element(&self, element: Element) -> SyntaxElement<'_, K> {
+        match element {
+            Element::Node(id) => SyntaxElement::Node(SyntaxNode { tree: self, id }),
+            Element::Token(id) => SyntaxElement::Token(SyntaxToken { tree: self, id }),
+        }
+    }
+
+    fn siblings(&self, parent: Option<usize>) -> &[Element] {
+        match parent {
+            Some(parent) => &self.nodes[parent].children,
+            None => &self.roots,
+        }
+    }
+
+    fn token_in(
+        &self,
+        tokens: std::ops::Range<usize>,
+        offset: usize,
+    ) -> TokenAtOffset<SyntaxToken<'_, K>> {
+        let candidates = &self.tokens[tokens.clone()];
+        // The tokens ending at or after `offset` and starting at or before it.
+        let first = candidates.partition_point(|token| token.span.end() < offset);
+        let last = candidates.partition_point(|token| token.span.start() <= offset);
+        let token = |i: usize| SyntaxToken {
+            tree: self,
+            id: tokens.start + i,
+        };
+        match last.saturating_sub(first) {
+            0 => TokenAtOffset::None,
+            1 => TokenAtOffset::Single(token(first)),
+            _ => TokenAtOffset::Between(token(first), token(last - 1)),
+        }
+    }
+
+    fn slice(&self, span: Span) -> &str {
+        &self.source[span.start()..span.end()]
+    }
+}
+
+/// A node of a [`SyntaxTree`].
+pub struct SyntaxNode<'a, K> {
+    tree: &'a SyntaxTree<K>,
+    id: usize,
+}
+
+/// A token of a [`SyntaxTree`], with the trivia attached to it.
+pub struct SyntaxToken<'a, K> {
+    tree: &'a SyntaxTree<K>,
+    id: usize,
+}
+
+/// A child of a [`SyntaxNode`]: a node or a token.
+pub enum SyntaxElement<'a, K> {
+    Node(SyntaxNode<'a, K>),
+    Token(SyntaxToken<'a, K>),
+}
+
+impl<'a, K> SyntaxNode<'a, K> {
+    fn data(&self) -> &'a NodeData<K> {
+        &self.tree.nodes[self.id]
+    }
+
+    pub fn kind(&self) -> &'a K {
+        &self.data().kind
+    }
+
+    /// Returns the span of the node, without the trivia around it.
+    pub fn span(&self) -> Span {
+        self.data().span
+    }
+
+    /// Returns the span of the node with the leading trivia of its first
+    /// token and the trailing trivia of its last.
+    pub fn full_span(&self) -> Span {
+        match (self.first_token(), self.last_token()) {
+            (Some(first), Some(last)) => first.full_span().union(&last.full_span()),
+            _ => self.span(),
+        }
+    }
+
+    /// Returns the text of the node, reconstructed from its tokens and the
+    /// trivia between them.
+    pub fn text(&self) -> String {
+        self.reconstruct(false)
+    }
+
+    /// Returns the text of the node with the trivia around it, so that the
+    /// full texts of the roots and the [end trivia](SyntaxTree::end_trivia)
+    /// of a lossless tree concatenate to its source.
+    pub fn full_text(&self) -> String {
+        self.reconstruct(true)
+    }
+
+    fn reconstruct(&self, full: bool) -> String {
+        let tokens = self.data().tokens.clone();
+        let mut text = String::new();
+        for token in self.tokens() {
+            let leading = match full || token.id != tokens.start {
+                true => token.leading_trivia(),
+                false => &[],
+            };
+            let trailing = match full || token.id + 1 != tokens.end {
+                true => token.trailing_trivia(),
+                false => &[],
+            };
+            for trivia in leading {
+                text.push_str(self.tree.slice(trivia.span));
+            }
+            text.push_str(token.text());
+            for trivia in trailing {
+                text.push_str(self.tree.slice(trivia.span));
+            }
+        }
+        text
+    }
+
+    pub fn parent(&self) -> Option<SyntaxNode<'a, K>> {
+        let tree = self.tree;
+        self.data().parent.map(|id| SyntaxNode { tree, id })
+    }
+
+    /// Returns the parent of the node, its parent, and so on up to a root.
+    pub fn ancestors(&self) -> impl Iterator<Item = SyntaxNode<'a, K>> {
+        std::iter::successors(self.parent(), SyntaxNode::parent)
+    }
+
+    /// Returns the nodes and tokens of the node, in source order.
+    pub fn children(&self) -> impl Iterator<Item = SyntaxElement<'a, K>> {
+        let tree = self.tree;
+        self.data()
+            .children
+            .iter()
+            .map(move |&element| tree.element(element))
+    }
+
+    /// Returns the nodes among the children of the node.
+    pub fn child_nodes(&self) -> impl Iterator<Item = SyntaxNode<'a, K>> {
+        self.children().filter_map(SyntaxElement::into_node)
+    }
+
+    /// Returns the tokens among the children of the node.
+    pub fn child_tokens(&self) -> impl Iterator<Item = SyntaxToken<'a, K>> {
+        self.children().filter_map(SyntaxElement::into_token)
+    }
+
+    pub fn first_child(&self) -> Option<SyntaxElement<'a, K>> {
+        self.data()
+            .children
+            .first()
+            .map(|&element| self.tree.element(element))
+    }
+
+    pub fn last_child(&self) -> Option<SyntaxElement<'a, K>> {
+        self.data()
+            .children
+            .last()
+            .map(|&element| self.tree.element(element))
+    }
+
+    pub fn next_sibling(&self) -> Option<SyntaxElement<'a, K>> {
+        let data = self.data();
+        self.tree.siblings(data.parent)[data.index + 1..]
+            .first()
+            .map(|&element| self.tree.element(element))
+    }
+
+    pub fn prev_sibling(&self) -> Option<SyntaxElement<'a, K>> {
+        let data = self.data();
+        self.tree.siblings(data.parent)[..data.index]
+            .last()
+            .map(|&element| self.tree.element(element))
+    }
+
+    /// Returns the tokens of the node and its descendants, in source order.
+    pub fn tokens(&self) -> impl DoubleEndedIterator<Item = SyntaxToken<'a, K>> {
+        let tree = self.tree;
+        self.data()
+            .tokens
+            .clone()
+            .map(move |id| SyntaxToken { tree, id })
+    }
+
+    pub fn first_token(&self) -> Option<SyntaxToken<'a, K>> {
+        self.tokens().next()
+    }
+
+    pub fn last_token(&self) -> Option<SyntaxToken<'a, K>> {
+        self.tokens().next_back()
+    }
+
+    /// Returns the tokens of the node at `offset`, which is between two
+    /// tokens if one ends where the next starts.
+    pub fn token_at_offset(&self, offset: usize) -> TokenAtOffset<SyntaxToken<'a, K>> {
+        self.tree.token_in(self.data().tokens.clone(), offset)
+    }
+
+    /// Returns the deepest descendant of the node containing `offset`.
+    ///
+    /// Spans include their start but not their end, so between two nodes
+    /// the one on the right contains the offset. An empty node contains the
+    /// offset it is at, and is preferred over a node starting there.
+    pub fn node_at_offset(&self, offset: usize) -> Option<SyntaxNode<'a, K>> {
+        self.descend(|span| contains_offset(span, offset))
+    }
+
+    /// Returns the smallest descendant of the node whose span contains
+    /// `span`, itself included.
+    ///
+    /// As with [`node_at_offset`](SyntaxNode::node_at_offset), an empty
+    /// span between two nodes is covered by the one on the right, and the
+    /// node is returned only if it covers `span` itself.
+    pub fn covering_node(&self, span: Span) -> Option<SyntaxNode<'a, K>> {
+        if !covers(self.span(), span) {
+            return None;
+        }
+        Some(self.descend(|node| covers(node, span)).unwrap_or(*self))
+    }
+
+    /// Returns the deepest descendant for which `matches` holds of its span
+    /// and the spans of its ancestors below the node. Of siblings, the
+    /// first matching one is taken.
+    fn descend(&self, matches: impl Fn(Span) -> bool) -> Option<SyntaxNode<'a, K>> {
+        let mut found = None;
+        let mut node = *self;
+        while let Some(child) = node.child_nodes().find(|child| matches(child.span())) {
+            found = Some(child);
+            node = child;
+        }
+        found
+    }
+
+    /// Returns a cursor starting at the node, which it does not leave.
+    pub fn cursor(&self) -> SyntaxCursor<'a, K> {
+        SyntaxCursor::new(*self)
+    }
+}
+
+impl<'a, K> SyntaxToken<'a, K> {
+    fn data(&self) -> &'a TokenData<K> {
+        &self.tree.tokens[self.id]
+    }
+
+    pub fn kind(&self) -> &'a K {
+        &self.data().kind
+    }
+
+    /// Returns the span of the token, without its trivia.
+    pub fn span(&self) -> Span {
+        self.data().span
+    }
+
+    /// Returns the span of the token with its leading and trailing trivia.
+    pub fn full_span(&self) -> Span {
+        let data = self.data();
+        let trivia = &self.tree.trivia;
+        let start = trivia[data.leading.clone()]
+            .first()
+            .map_or(data.span, |first| first.span);
+        let end = trivia[data.trailing.clone()]
+            .last()
+            .map_or(data.span, |last| last.span);
+        start.union(&end)
+    }
+
+    pub fn text(&self) -> &'a str {
+        self.tree.slice(self.span())
+    }
+
+    /// Returns the source text of the token with its trivia.
+    pub fn full_text(&self) -> &'a str {
+        self.tree.slice(self.full_span())
+    }
+
+    /// Returns the trivia before the token, after the line of the previous
+    /// one.
+    pub fn leading_trivia(&self) -> &'a [Trivia<K>] {
+        &self.tree.trivia[self.data().leading.clone()]
+    }
+
+    /// Returns the trivia after the token, on the same line.
+    pub fn trailing_trivia(&self) -> &'a [Trivia<K>] {
+        &self.tree.trivia[self.data().trailing.clone()]
+    }
+
+    pub fn parent(&self) -> Option<SyntaxNode<'a, K>> {
+        let tree = self.tree;
+        self.data().parent.map(|id| SyntaxNode { tree, id })
+    }
+
+    /// Returns the parent of the token, its parent, and so on up to a root.
+    pub fn ancestors(&self) -> impl Iterator<Item = SyntaxNode<'a, K>> {
+        std::iter::successors(self.parent(), SyntaxNode::parent)
+    }
+
+    pub fn next_sibling(&self) -> Option<SyntaxElement<'a, K>> {
+        let data = self.data();
+        self.tree.siblings(data.parent)[data.index + 1..]
+            .first()
+            .map(|&element| self.tree.element(element))
+    }
+
+    pub fn prev_sibling(&self) -> Option<SyntaxElement<'a, K>> {
+        let data = self.data();
+        self.tree.siblings(data.parent)[..data.index]
+            .last()
+            .map(|&element| self.tree.element(element))
+    }
+
+    /// Returns the next token in source order, wherever it is in the tree.
+    pub fn next_token(&self) -> Option<SyntaxToken<'a, K>> {
+        let tree = self.tree;
+        (self.id + 1 < tree.tokens.len()).then(|| SyntaxToken {
+            tree,
+            id: self.id + 1,
+        })
+    }
+
+    /// Returns the previous token in source order, wherever it is in the
+    /// tree.
+    pub fn prev_token(&self) -> Option<SyntaxToken<'a, K>> {
+        let tree = self.tree;
+        self.id.checked_sub(1).map(|id| SyntaxToken { tree, id })
+    }
+}
+
+impl<'a, K> SyntaxElement<'a, K> {
+    pub fn kind(&self) -> &'a K {
+        match self {
+            SyntaxElement::Node(node) => node.kind(),
+            SyntaxElement::Token(token) => token.kind(),
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            SyntaxElement::Node(node) => node.span(),
+            SyntaxElement::Token(token) => token.span(),
+        }
+    }
+
+    pub fn parent(&self) -> Option<SyntaxNode<'a, K>> {
+        match self {
+            SyntaxElement::Node(node) => node.parent(),
+            SyntaxElement::Token(token) => token.parent(),
+        }
+    }
+
+    pub fn next_sibling(&self) -> Option<SyntaxElement<'a, K>> {
+        match self {
+            SyntaxElement::Node(node) => node.next_sibling(),
+            SyntaxElement::Token(token) => token.next_sibling(),
+        }
+    }
+
+    pub fn prev_sibling(&self) -> Option<SyntaxElement<'a, K>> {
+        match self {
+            SyntaxElement::Node(node) => node.prev_sibling(),
+            SyntaxElement::Token(token) => token.prev_sibling(),
+        }
+    }
+
+    pub fn into_node(self) -> Option<SyntaxNode<'a, K>> {
+        match self {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        }
+    }
+
+    pub fn into_token(self) -> Option<SyntaxToken<'a, K>> {
+        match self {
+            SyntaxElement::Node(_) => None,
+            SyntaxElement::Token(token) => Some(token),
+        }
+    }
+}
+
+/// A way in which a [`SyntaxTree`] does not keep its source, found by
+/// [`SyntaxTree::verify`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LosslessError {
+    /// Text of the source not covered by any token or trivia.
+    Gap(Span),
+    /// A token or trivia starting before the end of the previous one.
+    Overlap(Span),
+    /// A token or trivia out of bounds of the source, or splitting a
+    /// character.
+    OutOfBounds(Span),
+}
+
+impl fmt::Display for LosslessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LosslessError::Gap(span) => write!(
+                f,
+                "source text at {}..{} is not covered by a token or trivia",
+                span.start(),
+                span.end()
+            ),
+            LosslessError::Overlap(span) => write!(
+                f,
+                "token or trivia at {}..{} overlaps the previous one",
+                span.start(),
+                span.end()
+            ),
+            LosslessError::OutOfBounds(span) => write!(
+                f,
+                "token or trivia at {}..{} is out of bounds of the source",
+                span.start(),
+                span.end()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LosslessError {}
+
+/// The tokens at an offset of a [`SyntaxTree`], found with
+/// [`SyntaxTree::token_at_offset`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenAtOffset<T> {
+    /// No token touches the offset, which is in trivia or out of the tree.
+    None,
+    /// A single token contains the offset or starts or ends at it.
+    Single(T),
+    /// The offset is where one token ends and the next starts.
+    Between(T, T),
+}
+
+impl<T> TokenAtOffset<T> {
+    /// Returns the token at the offset, the one on the left between two,
+    /// such as for completing the word before a cursor.
+    pub fn left_biased(self) -> Option<T> {
+        match self {
+            TokenAtOffset::None => None,
+            TokenAtOffset::Single(token) | TokenAtOffset::Between(token, _) => Some(token),
+        }
+    }
+
+    /// Returns the token at the offset, the one on the right between two.
+    pub fn right_biased(self) -> Option<T> {
+        match self {
+            TokenAtOffset::None => None,
+            TokenAtOffset::Single(token) | TokenAtOffset::Between(_, token) => Some(token),
+        }
+    }
+
+    pub fn is_none(&self) -> bool {
+        matches!(self, TokenAtOffset::None)
+    }
+}
+
+/// Returns whether a node spanning `span` contains `offset`.
+fn contains_offset(span: Span, offset: usize) -> bool {
+    span.start() <= offset && (offset < span.end() || span.is_empty() && offset == span.end())
+}
+
+/// Returns whether a node spanning `outer` covers `inner`: an empty span
+/// is covered as an offset is contained.
+fn covers(outer: Span, inner: Span) -> bool {
+    match inner.is_empty() {
+        true => contains_offset(outer, inner.start()),
+        false => outer.start() <= inner.start() && inner.end() <= outer.end(),
+    }
+}
+
+/// A cursor walking a [`SyntaxTree`] from a node, without leaving it.
+///
+/// Moves return whether they succeeded, leaving the cursor in place if
+/// not, which makes walks without recursion or allocation simple.
+///
+/// # Examples
+/// ```
+/// use grammarsmith::event::Event;
+/// use grammarsmith::position::Span;
+/// use grammarsmith::tree::*;
+///
+/// let events = [
+///     Event::StartNode("call"),
+///     Event::Token { kind: "name", span: Span::new(0, 1).unwrap() },
+///     Event::Token { kind: "space", span: Span::new(1, 2).unwrap() },
+///     Event::StartNode("args"),
+///     Event::Token { kind: "name", span: Span::new(2, 3).unwrap() },
+///     Event::FinishNode,
+///     Event::FinishNode,
+/// ];
+/// let tree = SyntaxTree::new("f x", &events, |kind| *kind == "space");
+///
+/// // Visits every element in preorder.
+/// let mut cursor = tree.root().unwrap().cursor();
+/// let mut kinds = vec![*cursor.kind()];
+/// 'walk: loop {
+///     if !cursor.goto_first_child() {
+///         while !cursor.goto_next_sibling() {
+///             if !cursor.goto_parent() {
+///                 break 'walk;
+///             }
+///         }
+///     }
+///     kinds.push(*cursor.kind());
+/// }
+/// assert_eq!(kinds, ["call", "name", "args", "name"]);
+/// ```
+pub struct SyntaxCursor<'a, K> {
+    root: SyntaxNode<'a, K>,
+    current: SyntaxElement<'a, K>,
+}
+
+impl<'a, K> SyntaxCursor<'a, K> {
+    /// Creates a cursor at `root`.
+    pub fn new(root: SyntaxNode<'a, K>) -> Self {
+        SyntaxCursor {
+            root,
+            current: SyntaxElement::Node(root),
+        }
+    }
+
+    /// Returns the element the cursor is at.
+    pub fn element(&self) -> SyntaxElement<'a, K> {
+        self.current
+    }
+
+    pub fn kind(&self) -> &'a K {
+        self.current.kind()
+    }
+
+    pub fn span(&self) -> Span {
+        self.current.span()
+    }
+
+    /// Returns the number of moves to the parent that reach the node the
+    /// cursor started at.
+    pub fn depth(&self) -> usize {
+        std::iter::successors(self.current.parent(), SyntaxNode::parent)
+            .take_while(|node| node.id != self.root.id)
+            .count()
+            + usize::from(!self.is_at_root())
+    }
+
+    fn is_at_root(&self) -> bool {
+        matches!(self.current, SyntaxElement::Node(node) if node.id == self.root.id)
+    }
+
+    fn goto(&mut self, element: Option<SyntaxElement<'a, K>>) -> bool {
+        match element {
+            Some(element) => {
+                self.current = element;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Moves to the first child of the current node.
+    pub fn goto_first_child(&mut self) -> bool {
+        let child = self.current.into_node().and_then(|node| node.first_child());
+        self.goto(child)
+    }
+
+    /// Moves to the last child of the current node.
+    pub fn goto_last_child(&mut self) -> bool {
+        let child = self.current.into_node().and_then(|node| node.last_child());
+        self.goto(child)
+    }
+
+    /// Moves to the child of the current node that contains `offset`, or
+    /// starts at it if it is empty.
+    pub fn goto_child_at_offset(&mut self, offset: usize) -> bool {
+        let child = self.current.into_node().and_then(|node| {
+            node.children().find(|child| {
+                let span = child.span();
+                span.start() <= offset && (offset < span.end() || span.is_empty())
+            })
+        });
+        self.goto(child)
+    }
+
+    pub fn goto_next_sibling(&mut self) -> bool {
+        let sibling = match self.is_at_root() {
+            true => None,
+            false => self.current.next_sibling(),
+        };
+        self.goto(sibling)
+    }
+
+    pub fn goto_prev_sibling(&mut self) -> bool {
+        let sibling = match self.is_at_root() {
+            true => None,
+            false => self.current.prev_sibling(),
+        };
+        self.goto(sibling)
+    }
+
+    pub fn goto_parent(&mut self) -> bool {
+        let parent = match self.is_at_root() {
+            true => None,
+            false => self.current.parent().map(SyntaxElement::Node),
+        };
+        self.goto(parent)
+    }
+}
+
+impl<K> Clone for SyntaxNode<'_, K> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K> Copy for SyntaxNode<'_, K> {}
+
+impl<K> Clone for SyntaxToken<'_, K> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K> Copy for SyntaxToken<'_, K> {}
+
+impl<K> Clone for SyntaxElement<'_, K> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K> Copy for SyntaxElement<'_, K> {}
+
+impl<K> Clone for SyntaxCursor<'_, K> {
+    fn clone(&self) -> Self {
+        SyntaxCursor {
+            root: self.root,
+            current: self.current,
+        }
+    }
+}
+
+/// Handles are equal if they refer to the same node of the same tree.
+impl<K> PartialEq for SyntaxNode<'_, K> {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self.tree, other.tree) && self.id == other.id
+    }
+}
+
+impl<K> Eq for SyntaxNode<'_, K> {}
+
+/// Handles are equal if they refer to the same token of the same tree.
+impl<K> PartialEq for SyntaxToken<'_, K> {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self.tree, other.tree) && self.id == other.id
+    }
+}
+
+impl<K> Eq for SyntaxToken<'_, K> {}
+
+impl<K> PartialEq for SyntaxElement<'_, K> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (SyntaxElement::Node(a), SyntaxElement::Node(b)) => a == b,
+            (SyntaxElement::Token(a), SyntaxElement::Token(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl<K> Eq for SyntaxElement<'_, K> {}
+
+impl<K: fmt::Debug> fmt::Debug for SyntaxNode<'_, K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?}@{}..{}",
+            self.kind(),
+            self.span().start(),
+            self.span().end()
+        )
+    }
+}
+
+impl<K: fmt::Debug> fmt::Debug for SyntaxToken<'_, K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?}@{}..{} {:?}",
+            self.kind(),
+            self.span().start(),
+            self.span().end(),
+            self.text()
+        )
+    }
+}
+
+impl<K: fmt::Debug> fmt::Debug for SyntaxElement<'_, K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SyntaxElement::Node(node) => node.fmt(f),
+            SyntaxElement::Token(token) => token.fmt(f),
+        }
+    }
+}
+
+impl<K: fmt::Debug> fmt::Debug for SyntaxCursor<'_, K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SyntaxCursor")
+            .field("root", &self.root)
+            .field("current", &self.current)
+            .finish()
+    }
+}
+
+impl<K> GetSpan for SyntaxNode<'_, K> {
+    fn get_span(&self) -> Span {
+        self.span()
+    }
+}
+
+impl<K> GetSpan for SyntaxToken<'_, K> {
+    fn get_span(&self) -> Span {
+        self.span()
+    }
+}
+
+impl<K> GetSpan for SyntaxElement<'_, K> {
+    fn get_span(&self) -> Span {
+        self.span()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::Events;
+
+    fn span(start: usize, end: usize) -> Span {
+        Span::new(start, end).unwrap()
+    }
+
+    #[test]
+    fn test_trivia_attachment() {
+        let source = "a # c\n  b  \n# d\n";
+        let mut events = Events::new();
+        events.start_node("list");
+        events.token("name", span(0, 1));
+        events.token("ws", span(1, 2));
+        events.token("comment", span(2, 5));
+        events.token("ws", span(5, 8));
+        events.token("name", span(8, 9));
+        events.token("ws", span(9, 12));
+        events.finish_node();
+        events.token("comment", span(12, 15));
+        events.token("ws", span(15, 16));
+        let tree = SyntaxTree::new(source, events.as_slice(), |kind| {
+            matches!(*kind, "ws" | "comment")
+        });
+
+        let list = tree.root().unwrap();
+        assert_eq!(list.span(), span(0, 9));
+        assert_eq!(list.text(), "a # c\n  b");
+        let [a, b] = [list.first_token().unwrap(), list.last_token().unwrap()];
+        assert_eq!(a.full_text(), "a # c");
+        assert!(a.leading_trivia().is_empty());
+        assert_eq!(b.leading_trivia().len(), 1);
+        assert_eq!(b.full_text(), "\n  b");
+        assert!(b.trailing_trivia().is_empty());
+        assert_eq!(tree.end_trivia().len(), 3);
+        assert_eq!(tree.verify(), Ok(()));
+        let full: String = tree
+            .roots()
+            .map(|root| root.into_node().unwrap().full_text())
+            .collect();
+        assert_eq!(full, "a # c\n  b");
+        assert!(tree.token_at_offset(3).is_none());
+        assert_eq!(tree.token_at_offset(8), TokenAtOffset::Single(b));
+    }
+
+    #[test]
+    fn test_gap() {
+        let mut events = Events::new();
+        events.token('a', span(0, 1));
+        events.token('b', span(2, 3));
+        let tree = SyntaxTree::new("a b", events.as_slice(), |_| false);
+        assert_eq!(
+            tree.verify().unwrap_err().to_string(),
+            "source text at 1..2 is not covered by a token or trivia"
+        );
+    }
+
+    #[test]
+    fn test_navigation() {
+        let events = [
+            Event::StartNode('r'),
+            Event::Token {
+                kind: 'x',
+                span: span(0, 1),
+            },
+            Event::StartNode('n'),
+            Event::FinishNode,
+            Event::StartNode('m'),
+            Event::Token {
+                kind: 'y',
+                span: span(1, 2),
+            },
+        ];
+        let tree = SyntaxTree::new("xy", &events, |_| false);
+        let root = tree.root().unwrap();
+        let kinds: Vec<char> = root.children().map(|child| *child.kind()).collect();
+        assert_eq!(kinds, ['x', 'n', 'm']);
+        let n = root.child_nodes().next().unwrap();
+        assert_eq!(n.span(), Span::point(1));
+        assert_eq!(*n.prev_sibling().unwrap().kind(), 'x');
+        assert_eq!(*n.next_sibling().unwrap().kind(), 'm');
+        let y = tree.token_at_offset(1).right_biased().unwrap();
+        assert_eq!(y.ancestors().count(), 2);
+        assert_eq!(y.prev_token().unwrap().next_token(), Some(y));
+
+        let mut cursor = n.next_sibling().unwrap().into_node().unwrap().cursor();
+        assert!(!cursor.goto_next_sibling());
+        assert!(cursor.goto_child_at_offset(1));
+        assert_eq!(cursor.depth(), 1);
+        assert!(cursor.goto_parent());
+        assert!(!cursor.goto_parent());
+        assert_eq!(*cursor.kind(), 'm');
+    }
+
+    #[test]
+    fn test_queries() {
+        let source = "f(a, b)";
+        let mut events = Events::new();
+        events.start_node("call");
+        events.start_node("name");
+        events.token("id", span(0, 1));
+        events.finish_node();
+        events.start_node("args");
+        events.token("(", span(1, 2));
+        events.start_node("arg");
+        events.token("id", span(2, 3));
+        events.finish_node();
+        events.token(",", span(3, 4));
+        events.token("ws", span(4, 5));
+        events.start_node("arg");
+        events.token("id", span(5, 6));
+        events.finish_node();
+        events.token(")", span(6, 7));
+        events.finish_node();
+        events.finish_node();
+        let tree = SyntaxTree::new(source, events.as_slice(), |kind| *kind == "ws");
+
+        let TokenAtOffset::Between(f, paren) = tree.token_at_offset(1) else {
+            panic!("expected two tokens");
+        };
+        assert_eq!((f.text(), paren.text()), ("f", "("));
+        assert_eq!(tree.token_at_offset(4).left_biased().unwrap().text(), ",");
+        assert_eq!(tree.token_at_offset(5).left_biased().unwrap().text(), "b");
+
+        let kind = |node: Option<SyntaxNode<'_, &'static str>>| node.map(|node| *node.kind());
+        assert_eq!(kind(tree.node_at_offset(0)), Some("name"));
+        assert_eq!(kind(tree.node_at_offset(1)), Some("args"));
+        assert_eq!(kind(tree.node_at_offset(7)), None);
+        assert_eq!(kind(tree.covering_node(span(2, 6))), Some("args"));
+        assert_eq!(kind(tree.covering_node(span(2, 3))), Some("arg"));
+        assert_eq!(kind(tree.covering_node(Span::point(3))), Some("args"));
+        assert_eq!(kind(tree.covering_node(span(0, 7))), Some("call"));
+        let kinds: Vec<_> = tree
+            .ancestors_at_offset(2)
+            .map(|node| *node.kind())
+            .collect();
+        assert_eq!(kinds, ["arg", "args", "call"]);
+    }
+}
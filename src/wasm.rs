@@ -0,0 +1,192 @@
+//! JavaScript-friendly views of tokens, spans and diagnostics.
+//!
+//! Web playgrounds running a grammarsmith-based parser compiled to
+//! `wasm32-unknown-unknown` hand its results to JavaScript, typically with
+//! `serde-wasm-bindgen`. JavaScript strings are indexed in UTF-16 code units,
+//! so the types in this module give every position as a UTF-16 offset and a
+//! one-based line and UTF-16 column, ready for slicing the source string or
+//! placing markers in an editor. Fields are named in camel case.
+//!
+//! A [`JsDocument`] converts the values of one source file.
+//!
+//! # Examples
+//! ```
+//! use grammarsmith::diagnostics::Diagnostic;
+//! use grammarsmith::position::Span;
+//! use grammarsmith::source::SourceFile;
+//! use grammarsmith::wasm::JsDocument;
+//!
+//! let file = SourceFile::new("main", "🦀 = ;");
+//! let doc = JsDocument::new(&file);
+//! let diagnostic = Diagnostic::error("expected a value").with_label(Span::new(7, 8).unwrap(), "");
+//!
+//! let js = doc.diagnostic(&diagnostic);
+//! let value = serde_json::to_value(&js).unwrap();
+//! assert_eq!(value["span"]["start"], 5);
+//! assert_eq!(value["span"]["startColumn"], 6);
+//! assert_eq!(value["severity"], "error");
+//! ```
+
+use crate::diagnostics::{Diagnostic, LabelStyle, Severity};
+use crate::position::{ColumnMode, GetSpan, Span, WithSpan};
+use crate::source::{FileId, SourceFile};
+use serde::{Deserialize, Serialize};
+
+/// A span in UTF-16 offsets, with one-based lines and UTF-16 columns.
+///
+/// `end` and `endColumn` are exclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JsSpan {
+    pub start: u32,
+    pub end: u32,
+    pub start_line: u32,
+    pub start_column: u32,
+    pub end_line: u32,
+    pub end_column: u32,
+}
+
+/// A token with its span.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct JsToken<T> {
+    pub value: T,
+    pub span: JsSpan,
+}
+
+/// A label of a [`JsDiagnostic`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct JsLabel {
+    pub style: LabelStyle,
+    pub message: String,
+    pub span: JsSpan,
+}
+
+/// A diagnostic reported for one file.
+///
+/// `span` is the span of the primary label, if there is one in the file.
+/// Labels pointing into other files are left out.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct JsDiagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub code: Option<String>,
+    pub span: Option<JsSpan>,
+    pub labels: Vec<JsLabel>,
+    pub notes: Vec<String>,
+}
+
+/// Converts spans, tokens and diagnostics of a source file into their
+/// JavaScript-friendly forms.
+#[derive(Debug, Clone)]
+pub struct JsDocument<'a> {
+    file: &'a SourceFile,
+    /// The UTF-16 offset of the start of each line.
+    line_starts: Vec<u32>,
+}
+
+impl<'a> JsDocument<'a> {
+    pub fn new(file: &'a SourceFile) -> Self {
+        let offsets = file.line_offsets();
+        let mut line_starts = Vec::with_capacity(offsets.line_count());
+        let mut utf16 = 0;
+        for line in 1..=offsets.line_count() {
+            line_starts.push(utf16);
+            let span = offsets.line_span(line).unwrap();
+            utf16 += ColumnMode::Utf16.str_width(file.slice(span)) as u32;
+        }
+        JsDocument { file, line_starts }
+    }
+
+    /// Converts a span.
+    pub fn span(&self, span: impl GetSpan) -> JsSpan {
+        let span = span.get_span();
+        let (start_line, start_column) = self.file.line_column(span.start, ColumnMode::Utf16);
+        let (end_line, end_column) = self.file.line_column(span.end, ColumnMode::Utf16);
+        JsSpan {
+            start: self.line_starts[start_line - 1] + start_column as u32 - 1,
+            end: self.line_starts[end_line - 1] + end_column as u32 - 1,
+            start_line: start_line as u32,
+            start_column: start_column as u32,
+            end_line: end_line as u32,
+            end_column: end_column as u32,
+        }
+    }
+
+    /// Converts a token, borrowing its value.
+    pub fn token<'t, T>(&self, token: &'t WithSpan<T>) -> JsToken<&'t T> {
+        JsToken {
+            value: &token.value,
+            span: self.span(token.span),
+        }
+    }
+
+    /// Converts a list of tokens, borrowing their values.
+    pub fn tokens<'t, T>(&self, tokens: &'t [WithSpan<T>]) -> Vec<JsToken<&'t T>> {
+        tokens.iter().map(|token| self.token(token)).collect()
+    }
+
+    /// Converts a diagnostic whose spans refer to this file.
+    pub fn diagnostic(&self, diagnostic: &Diagnostic) -> JsDiagnostic {
+        let local = |span: Span, file: Option<FileId>| file.is_none().then(|| self.span(span));
+        JsDiagnostic {
+            severity: diagnostic.severity,
+            message: diagnostic.message.clone(),
+            code: diagnostic.code.as_ref().map(|code| code.to_string()),
+            span: diagnostic
+                .primary_label()
+                .and_then(|label| local(label.span, label.file)),
+            labels: diagnostic
+                .labels
+                .iter()
+                .filter_map(|label| {
+                    Some(JsLabel {
+                        style: label.style,
+                        message: label.message.clone(),
+                        span: local(label.span, label.file)?,
+                    })
+                })
+                .collect(),
+            notes: diagnostic.notes.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_utf16_offsets_across_lines() {
+        let file = SourceFile::new("a", "é🦀\r\nab");
+        let doc = JsDocument::new(&file);
+        // `b` is at byte 9, after 1 + 2 + 2 UTF-16 units on the first line.
+        assert_eq!(
+            doc.span(Span::new(9, 10).unwrap()),
+            JsSpan {
+                start: 6,
+                end: 7,
+                start_line: 2,
+                start_column: 2,
+                end_line: 2,
+                end_column: 3,
+            }
+        );
+    }
+
+    #[test]
+    fn test_tokens_and_labels() {
+        let file = SourceFile::new("a", "x y");
+        let doc = JsDocument::new(&file);
+        let tokens = [WithSpan::new('y', Span::new(2, 3).unwrap())];
+        let json = serde_json::to_value(doc.tokens(&tokens)).unwrap();
+        assert_eq!(json[0]["value"], "y");
+        assert_eq!(json[0]["span"]["endColumn"], 4);
+
+        let diagnostic = Diagnostic::warning("unused")
+            .with_label(Span::new(0, 1).unwrap(), "here")
+            .with_note("remove it");
+        let js = doc.diagnostic(&diagnostic);
+        assert_eq!(js.labels.len(), 1);
+        assert_eq!(js.notes, ["remove it"]);
+    }
+}
@@ -1,3 +1,5 @@
+#![allow(dead_code, clippy::manual_checked_ops, clippy::upper_case_acronyms)]
+
 use grammarsmith::*;
 
 #[test]
@@ -0,0 +1,163 @@
+use grammarsmith::diagnostics::Diagnostic;
+use grammarsmith::grammar::{parse_ebnf, RustGenerator};
+use grammarsmith::parser::{EndOfFile, KindMeta, Parser, Token};
+use grammarsmith::position::{Span, WithSpan};
+use grammarsmith::testing::BLESS_VAR;
+
+const GRAMMAR: &str = r#"
+    list = "[" [item {"," item}] "]" ;
+    item = Number | list on_error("expected an item", "items are numbers or lists") ;
+"#;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    LBracket,
+    RBracket,
+    Comma,
+    Number,
+    Eof,
+}
+
+impl Token for Kind {
+    type Kind = Kind;
+
+    fn to_kind(&self) -> Kind {
+        *self
+    }
+}
+
+impl EndOfFile for Kind {
+    fn eof() -> Kind {
+        Kind::Eof
+    }
+
+    fn eof_kind() -> Kind {
+        Kind::Eof
+    }
+}
+
+impl KindMeta for Kind {
+    const ALL: &'static [Kind] = &[
+        Kind::LBracket,
+        Kind::RBracket,
+        Kind::Comma,
+        Kind::Number,
+        Kind::Eof,
+    ];
+
+    fn display_name(&self) -> &'static str {
+        match self {
+            Kind::LBracket => "`[`",
+            Kind::RBracket => "`]`",
+            Kind::Comma => "`,`",
+            Kind::Number => "a number",
+            Kind::Eof => "end of input",
+        }
+    }
+}
+
+mod list {
+    use super::Kind;
+
+    include!("generated/list.rs");
+}
+
+/// Writes trees as S-expressions, with `[..]` for the sizes of lists.
+struct Outline<'s> {
+    source: &'s str,
+}
+
+impl<'a> list::Actions<'a> for Outline<'_> {
+    type Node = String;
+
+    fn token(&mut self, token: &'a WithSpan<Kind>) -> String {
+        self.source[token.span.start()..token.span.end()].to_string()
+    }
+
+    fn node(&mut self, rule: &'static str, _span: Span, children: Vec<String>) -> String {
+        format!("({} {})", rule, children.join(" "))
+    }
+
+    fn on_item(&mut self, _span: Span, mut children: Vec<String>) -> String {
+        children.pop().unwrap_or_default()
+    }
+
+    fn on_list(&mut self, span: Span, children: Vec<String>) -> String {
+        format!(
+            "[{}]{}",
+            span.end() - span.start(),
+            self.node("list", span, children)
+        )
+    }
+}
+
+fn scan(source: &str) -> Vec<WithSpan<Kind>> {
+    source
+        .char_indices()
+        .filter_map(|(i, c)| {
+            let kind = match c {
+                '[' => Kind::LBracket,
+                ']' => Kind::RBracket,
+                ',' => Kind::Comma,
+                '0'..='9' => Kind::Number,
+                _ => return None,
+            };
+            Some(WithSpan::new(kind, Span::new(i, i + 1).unwrap()))
+        })
+        .collect()
+}
+
+fn parse(source: &str) -> (Option<String>, Vec<Diagnostic>) {
+    let tokens = scan(source);
+    let eof = WithSpan::new(Kind::Eof, Span::point(source.len()));
+    let mut parser = Parser::new(&tokens, &eof);
+    let mut diagnostics = Vec::new();
+    let tree = list::parse_list(&mut parser, &mut Outline { source }, &mut diagnostics);
+    (tree, diagnostics)
+}
+
+#[test]
+fn test_generated_code_is_up_to_date() {
+    let grammar = parse_ebnf(GRAMMAR).value;
+    let code = RustGenerator::new(&grammar)
+        .with_token_type("Kind")
+        .with_kind("\"[\"", "Kind::LBracket")
+        .with_kind("\"]\"", "Kind::RBracket")
+        .with_kind("\",\"", "Kind::Comma")
+        .generate()
+        .unwrap();
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/generated/list.rs");
+    if std::env::var_os(BLESS_VAR).is_some() {
+        std::fs::write(path, &code).unwrap();
+    }
+    assert_eq!(
+        code,
+        include_str!("generated/list.rs"),
+        "run with {}=1 to update",
+        BLESS_VAR
+    );
+}
+
+#[test]
+fn test_generated_parser() {
+    assert_eq!(
+        parse("[1,[2,3],[]]"),
+        (
+            Some("[12](list [ 1 , [5](list [ 2 , 3 ]) , [2](list [ ]) ])".to_string()),
+            Vec::new()
+        )
+    );
+
+    let (tree, diagnostics) = parse("[1 2]");
+    assert_eq!(tree, None);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].message, "expected `]`, found a number");
+    assert_eq!(diagnostics[0].primary_span(), Span::new(3, 4));
+
+    // The inner list is missing its `]`; the outer one carries on.
+    let (tree, diagnostics) = parse("[[1,]");
+    assert_eq!(tree, None);
+    assert_eq!(diagnostics[0].message, "expected an item");
+    assert_eq!(diagnostics[0].notes, ["items are numbers or lists"]);
+    assert_eq!(diagnostics[0].primary_span(), Span::new(4, 5));
+}
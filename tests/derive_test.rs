@@ -0,0 +1,88 @@
+#![cfg(feature = "derive")]
+
+use grammarsmith::*;
+
+#[derive(Debug, PartialEq, Eq, Clone, Token)]
+enum CalculatorToken {
+    #[token(name = "a number")]
+    Number(u64),
+    Identifier {
+        name: String,
+    },
+    #[token(name = "`+`")]
+    Plus,
+    #[token(eof)]
+    Eof,
+}
+
+#[derive(Debug, Clone, Token)]
+#[token(kind = Punct)]
+pub enum PunctToken {
+    Comma,
+    Semicolon,
+}
+
+#[test]
+fn test_to_kind() {
+    assert_eq!(
+        CalculatorToken::Number(1).to_kind(),
+        CalculatorTokenKind::Number
+    );
+    let identifier = CalculatorToken::Identifier {
+        name: "x".to_string(),
+    };
+    assert_eq!(identifier.to_kind(), CalculatorTokenKind::Identifier);
+    assert_eq!(PunctToken::Semicolon.to_kind(), Punct::Semicolon);
+}
+
+#[test]
+fn test_kind_meta() {
+    assert_eq!(
+        CalculatorTokenKind::ALL,
+        &[
+            CalculatorTokenKind::Number,
+            CalculatorTokenKind::Identifier,
+            CalculatorTokenKind::Plus,
+            CalculatorTokenKind::Eof
+        ]
+    );
+    assert_eq!(CalculatorTokenKind::Plus.display_name(), "`+`");
+    assert_eq!(CalculatorTokenKind::Plus.name(), "Plus");
+    assert_eq!(CalculatorTokenKind::Identifier.display_name(), "Identifier");
+    assert_eq!(Punct::ALL.len(), 2);
+}
+
+#[test]
+fn test_parser() {
+    let tokens = [
+        WithSpan::new(CalculatorToken::Number(1), Span::new(0, 1).unwrap()),
+        WithSpan::new(CalculatorToken::Plus, Span::new(2, 3).unwrap()),
+    ];
+    let eof = WithSpan::empty(CalculatorToken::eof());
+    let mut parser = Parser::new(&tokens, &eof);
+    let mut sink = Vec::new();
+    parser.expect_one_of(&[CalculatorTokenKind::Number], &mut sink);
+    parser.expect_one_of(&[CalculatorTokenKind::Number], &mut sink);
+    assert_eq!(sink[0].message, "expected a number, found `+`");
+    parser.advance();
+    assert_eq!(
+        parser.peek_token().value.to_kind(),
+        CalculatorToken::eof_kind()
+    );
+}
+
+const KEYWORDS: keywords::Keywords<PunctToken> = keywords::keywords! {
+    "comma" => PunctToken::Comma,
+    "semi" => PunctToken::Semicolon,
+    "semicolon" => PunctToken::Semicolon,
+};
+
+#[test]
+fn test_keywords() {
+    assert_eq!(KEYWORDS.get("semi").unwrap().to_kind(), Punct::Semicolon);
+    assert_eq!(KEYWORDS.get("comma").unwrap().to_kind(), Punct::Comma);
+    assert!(KEYWORDS.get("semic").is_none());
+    assert!(KEYWORDS.get("").is_none());
+    assert_eq!(KEYWORDS.words(), ["comma", "semi", "semicolon"]);
+    assert_eq!(KEYWORDS.iter().count(), 3);
+}
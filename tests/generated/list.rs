@@ -0,0 +1,120 @@
+// Generated by grammarsmith from a grammar description. Do not edit.
+
+/// Builds the values of the rules of the grammar.
+///
+/// Rules are built by `node` unless their `on_*` hook is overridden.
+pub trait Actions<'a> {
+    type Node;
+
+    /// Builds the value of a consumed token.
+    fn token(&mut self, token: &'a grammarsmith::position::WithSpan<Kind>) -> Self::Node;
+
+    /// Builds the value of a rule from the values of its tokens and subrules.
+    fn node(
+        &mut self,
+        rule: &'static str,
+        span: grammarsmith::position::Span,
+        children: Vec<Self::Node>,
+    ) -> Self::Node;
+
+    /// Builds the value of the rule `list`.
+    fn on_list(
+        &mut self,
+        span: grammarsmith::position::Span,
+        children: Vec<Self::Node>,
+    ) -> Self::Node {
+        self.node("list", span, children)
+    }
+
+    /// Builds the value of the rule `item`.
+    fn on_item(
+        &mut self,
+        span: grammarsmith::position::Span,
+        children: Vec<Self::Node>,
+    ) -> Self::Node {
+        self.node("item", span, children)
+    }
+}
+
+/// The tokens `parse_list` skips to after an error.
+pub const SYNC_LIST: &[Kind] = &[Kind::Comma, Kind::RBracket];
+
+/// Parses the rule `list`.
+pub fn parse_list<'a, A, D>(
+    p: &mut grammarsmith::parser::Parser<'a, Kind>,
+    actions: &mut A,
+    sink: &mut D,
+) -> Option<A::Node>
+where
+    A: Actions<'a> + ?Sized,
+    D: grammarsmith::diagnostics::DiagnosticSink + ?Sized,
+{
+    let marker = p.mark();
+    let mut children = Vec::new();
+    let parsed = p.rule("list", |p| {
+        children.push(actions.token(p.expect_one_of(&[Kind::LBracket], sink)?));
+        if p.check_one_of(&[Kind::LBracket, Kind::Number]) {
+            if let Some(node) = parse_item(p, actions, sink) {
+                children.push(node);
+            }
+            while p.check_one_of(&[Kind::Comma]) {
+                let before = p.mark();
+                children.push(actions.token(p.expect_one_of(&[Kind::Comma], sink)?));
+                if let Some(node) = parse_item(p, actions, sink) {
+                    children.push(node);
+                }
+                if p.mark() == before {
+                    break;
+                }
+            }
+        }
+        children.push(actions.token(p.expect_one_of(&[Kind::RBracket], sink)?));
+        Some(())
+    });
+    if parsed.is_none() {
+        p.drop_until(SYNC_LIST);
+        return None;
+    }
+    Some(actions.on_list(p.span_since(marker), children))
+}
+
+/// The tokens `parse_item` skips to after an error.
+pub const SYNC_ITEM: &[Kind] = &[Kind::Comma, Kind::RBracket];
+
+/// Parses the rule `item`.
+pub fn parse_item<'a, A, D>(
+    p: &mut grammarsmith::parser::Parser<'a, Kind>,
+    actions: &mut A,
+    sink: &mut D,
+) -> Option<A::Node>
+where
+    A: Actions<'a> + ?Sized,
+    D: grammarsmith::diagnostics::DiagnosticSink + ?Sized,
+{
+    let marker = p.mark();
+    let mut children = Vec::new();
+    let mut errors: Vec<grammarsmith::diagnostics::Diagnostic> = Vec::new();
+    let parsed = p.rule("item", |p| {
+        if p.check_one_of(&[Kind::Number]) {
+            children.push(actions.token(p.expect_one_of(&[Kind::Number], &mut errors)?));
+        } else if p.check_one_of(&[Kind::LBracket]) {
+            if let Some(node) = parse_list(p, actions, sink) {
+                children.push(node);
+            }
+        } else {
+            let _ = p.expect_one_of(&[Kind::LBracket, Kind::Number], &mut errors);
+            return None;
+        }
+        Some(())
+    });
+    for mut error in errors {
+        error.message = "expected an item".to_string();
+        error.notes.push("items are numbers or lists".to_string());
+        sink.emit(error);
+    }
+    if parsed.is_none() {
+        p.drop_until(SYNC_ITEM);
+        return None;
+    }
+    Some(actions.on_item(p.span_since(marker), children))
+}